@@ -0,0 +1,100 @@
+use image::RgbaImage;
+
+const MAX_PAGE_SIZE: u32 = 2048;
+const PADDING: u32 = 2; // keeps filtering from bleeding between regions
+
+pub struct AtlasPage {
+    pub image: RgbaImage,
+    pub regions: Vec<(String, String, [u32; 4])>, // id, path, rect
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32,
+}
+
+// a page while it is being filled
+struct PagePlan {
+    shelves: Vec<Shelf>,
+    regions: Vec<(usize, [u32; 4])>, // texture index, rect
+    used: [u32; 2],
+}
+
+impl PagePlan {
+    fn insert(&mut self, index: usize, image: &RgbaImage) -> bool {
+        let (width, height) = (image.width() + PADDING, image.height() + PADDING);
+        let Some([x, y]) = place(&mut self.shelves, width, height) else {
+            return false;
+        };
+
+        self.regions
+            .push((index, [x, y, image.width(), image.height()]));
+        self.used[0] = self.used[0].max(x + width);
+        self.used[1] = self.used[1].max(y + height);
+        true
+    }
+}
+
+// shelf packing, tallest first; textures larger than a page get a page of their own
+pub fn pack(textures: &[(String, String, RgbaImage)]) -> Vec<AtlasPage> {
+    let mut order: Vec<usize> = (0..textures.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(textures[i].2.height()));
+
+    let mut pages: Vec<PagePlan> = Vec::new();
+
+    for i in order {
+        let image = &textures[i].2;
+        if pages.iter_mut().any(|page| page.insert(i, image)) {
+            continue;
+        }
+
+        let mut page = PagePlan {
+            shelves: Vec::new(),
+            regions: Vec::new(),
+            used: [0, 0],
+        };
+        page.insert(i, image);
+        pages.push(page);
+    }
+
+    pages
+        .into_iter()
+        .map(|page| {
+            let mut image = RgbaImage::new(page.used[0].max(1), page.used[1].max(1));
+            let regions = page
+                .regions
+                .into_iter()
+                .map(|(i, rect)| {
+                    let (id, path, texture) = &textures[i];
+                    image::imageops::replace(&mut image, texture, rect[0] as i64, rect[1] as i64);
+                    (id.clone(), path.clone(), rect)
+                })
+                .collect();
+
+            AtlasPage { image, regions }
+        })
+        .collect()
+}
+
+fn place(shelves: &mut Vec<Shelf>, width: u32, height: u32) -> Option<[u32; 2]> {
+    for shelf in shelves.iter_mut() {
+        if height <= shelf.height && shelf.x + width <= MAX_PAGE_SIZE {
+            let position = [shelf.x, shelf.y];
+            shelf.x += width;
+            return Some(position);
+        }
+    }
+
+    let y = shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+    if y + height > MAX_PAGE_SIZE && !shelves.is_empty() {
+        return None;
+    }
+
+    shelves.push(Shelf {
+        y,
+        height,
+        x: width,
+    });
+    Some([0, y])
+}
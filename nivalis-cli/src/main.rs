@@ -0,0 +1,477 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use image::RgbaImage;
+use image::imageops::FilterType;
+
+mod atlas;
+mod pak;
+
+use crate::pak::{AtlasRegion, PakEntry, PakKind, PakManifest, PakTexture};
+
+const MANIFEST_FILE: &str = "manifest.toml";
+const META_EXTENSION: &str = "meta";
+const ATLAS_EXTENSION: &str = "atlas";
+const TEXTURE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp", "tga", "qoi"];
+
+const USAGE: &str = "usage:
+    nivalis-cli pack [asset dir] [-o output.nvpak]
+    nivalis-cli manifest [asset dir]
+    nivalis-cli list <archive.nvpak>
+    nivalis-cli atlas <sprite dir> [-o output.png]
+    nivalis-cli qoi <image>...";
+
+// the parts of a `.meta` sidecar the pipeline cares about
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct ImportSettings {
+    atlas: bool,
+    mipmaps: Option<bool>,
+    indexed: bool, // palette indices, averaging them into mips makes them meaningless
+    color_key: Option<[u8; 3]>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("pack") => pack_command(&args[1..]),
+        Some("manifest") => manifest_command(&args[1..]),
+        Some("list") => list_command(&args[1..]),
+        Some("atlas") => atlas_command(&args[1..]),
+        Some("qoi") => qoi_command(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn pack_command(args: &[String]) -> anyhow::Result<()> {
+    let mut asset_dir = PathBuf::from("assets");
+    let mut output = PathBuf::from("assets.nvpak");
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match args.next() {
+                Some(path) => output = PathBuf::from(path),
+                None => bail!("{} needs a path", arg),
+            },
+            _ => asset_dir = PathBuf::from(arg),
+        }
+    }
+
+    pack(&asset_dir, &output)
+}
+
+// registers new files without packing, for runtimes that discover assets from
+// the manifest instead of scanning the directory
+fn manifest_command(args: &[String]) -> anyhow::Result<()> {
+    let asset_dir = args.first().map_or(PathBuf::from("assets"), PathBuf::from);
+    let ids = register_files(&asset_dir, &collect_files(&asset_dir)?)?;
+    for path in ids.values() {
+        if !asset_dir.join(path).exists() {
+            println!("{} is in the manifest but missing", path);
+        }
+    }
+    println!(
+        "{} lists {} assets",
+        asset_dir.join(MANIFEST_FILE).display(),
+        ids.len()
+    );
+    Ok(())
+}
+
+fn list_command(args: &[String]) -> anyhow::Result<()> {
+    let Some(path) = args.first() else {
+        bail!("{}", USAGE);
+    };
+
+    let manifest = pak::read_manifest(Path::new(path))?;
+    for entry in manifest.entries.iter() {
+        match &entry.texture {
+            Some(texture) => println!(
+                "{} {:<40} {}x{} {} mips{}",
+                entry.id,
+                entry.path,
+                texture.width,
+                texture.height,
+                texture.mips,
+                texture
+                    .atlas
+                    .as_ref()
+                    .map(|atlas| format!(" in {}", atlas.page))
+                    .unwrap_or_default()
+            ),
+            None => println!("{} {:<40} {} bytes", entry.id, entry.path, entry.length),
+        }
+    }
+
+    Ok(())
+}
+
+// packs every texture under a directory into one sheet, with a `.atlas` region
+// table beside it that the runtime picks up when the sheet is loaded or reloaded
+fn atlas_command(args: &[String]) -> anyhow::Result<()> {
+    let mut sprite_dir = None;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => bail!("{} needs a path", arg),
+            },
+            _ => sprite_dir = Some(PathBuf::from(arg)),
+        }
+    }
+    let Some(sprite_dir) = sprite_dir else {
+        bail!("{}", USAGE);
+    };
+    let output = match output {
+        Some(output) => output,
+        None => {
+            let name = sprite_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .context("the sprite dir needs a name, or pass -o")?;
+            PathBuf::from(format!("assets/textures/{}.png", name))
+        }
+    };
+
+    pack_sheet(&sprite_dir, &output)
+}
+
+// writes a .qoi beside each image, with a copy of its sidecars. they decode
+// several times faster than png, which adds up when iterating on many textures
+fn qoi_command(args: &[String]) -> anyhow::Result<()> {
+    if args.is_empty() {
+        bail!("{}", USAGE);
+    }
+
+    for path in args.iter().map(Path::new) {
+        let output = path.with_extension("qoi");
+        if output == path {
+            println!("{} is already qoi", path.display());
+            continue;
+        }
+        let image = image::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?
+            .to_rgba8();
+        image
+            .save(&output)
+            .with_context(|| format!("failed to write {}", output.display()))?;
+
+        for extension in [META_EXTENSION, ATLAS_EXTENSION] {
+            let sidecar = PathBuf::from(format!("{}.{}", path.display(), extension));
+            if sidecar.exists() {
+                let copy = PathBuf::from(format!("{}.{}", output.display(), extension));
+                fs::copy(&sidecar, &copy)
+                    .with_context(|| format!("failed to copy {}", sidecar.display()))?;
+            }
+        }
+
+        let before = fs::metadata(path).map_or(0, |metadata| metadata.len());
+        let after = fs::metadata(&output).map_or(0, |metadata| metadata.len());
+        println!(
+            "{} -> {} ({} -> {} bytes)",
+            path.display(),
+            output.display(),
+            before,
+            after
+        );
+    }
+
+    Ok(())
+}
+
+fn pack_sheet(sprite_dir: &Path, output: &Path) -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    struct RegionTable<'a> {
+        size: [u32; 2],
+        regions: &'a BTreeMap<String, [u32; 4]>,
+    }
+
+    // region names are the paths inside the directory without their extension
+    let mut sprites = Vec::new();
+    for file in collect_files(sprite_dir)?
+        .into_iter()
+        .filter(|file| is_texture(file))
+    {
+        let source = sprite_dir.join(&file);
+        if fs::canonicalize(&source).ok() == fs::canonicalize(output).ok() {
+            continue; // a sheet written into its own sprite dir
+        }
+        let image = image::open(&source)
+            .with_context(|| format!("failed to decode {}", source.display()))?
+            .to_rgba8();
+        let name = match file.rsplit_once('.') {
+            Some((name, _)) => name.to_string(),
+            None => file.clone(),
+        };
+        sprites.push((name, file, image));
+    }
+    if sprites.is_empty() {
+        bail!("no textures in {}", sprite_dir.display());
+    }
+
+    let mut pages = atlas::pack(&sprites);
+    if pages.len() > 1 {
+        bail!(
+            "{} sprites don't fit one sheet, split the directory",
+            sprites.len()
+        );
+    }
+    let page = pages.remove(0);
+
+    let regions: BTreeMap<String, [u32; 4]> = page
+        .regions
+        .into_iter()
+        .map(|(name, _, rect)| (name, rect))
+        .collect();
+    let table = toml::to_string(&RegionTable {
+        size: [page.image.width(), page.image.height()],
+        regions: &regions,
+    })?;
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    page.image
+        .save(output)
+        .with_context(|| format!("failed to write {}", output.display()))?;
+    let sidecar = PathBuf::from(format!("{}.{}", output.display(), ATLAS_EXTENSION));
+    fs::write(&sidecar, table).with_context(|| format!("failed to write {}", sidecar.display()))?;
+
+    println!(
+        "packed {} sprites into {} ({}x{})",
+        regions.len(),
+        output.display(),
+        page.image.width(),
+        page.image.height()
+    );
+    Ok(())
+}
+
+fn pack(asset_dir: &Path, output: &Path) -> anyhow::Result<()> {
+    let files = collect_files(asset_dir)?;
+    let ids = register_files(asset_dir, &files)?;
+
+    let mut writer = pak::PakWriter::create(output)?;
+    let mut entries = Vec::new();
+    let mut atlased = Vec::new();
+
+    for (id, path) in ids.iter() {
+        let source = asset_dir.join(path);
+        if !source.exists() {
+            println!("skipping {}, the file is missing", path);
+            continue;
+        }
+
+        if !is_texture(path) {
+            let data = fs::read(&source).with_context(|| format!("failed to read {}", path))?;
+            entries.push(writer.raw(id, path, &data)?);
+            continue;
+        }
+
+        let settings = load_settings(asset_dir, path);
+        let mut image = image::open(&source)
+            .with_context(|| format!("failed to decode {}", path))?
+            .to_rgba8();
+        // keyed before the mips so the key color doesn't bleed into them
+        if let Some(key) = settings.color_key {
+            for pixel in image.pixels_mut() {
+                if pixel.0[..3] == key {
+                    pixel.0 = [0; 4];
+                }
+            }
+        }
+
+        if settings.atlas {
+            atlased.push((id.clone(), path.clone(), image));
+            continue;
+        }
+
+        let mips = if settings.mipmaps.unwrap_or(!settings.indexed) {
+            mip_chain(image)
+        } else {
+            vec![image]
+        };
+        entries.push(writer.texture(id, path, &mips)?);
+    }
+
+    // sidecars ride along so runtime import settings still apply
+    for file in files.iter().filter(|file| is_sidecar(file)) {
+        let data = fs::read(asset_dir.join(file))?;
+        entries.push(writer.raw("", file, &data)?);
+    }
+
+    let pages = atlas::pack(&atlased);
+    for (index, page) in pages.into_iter().enumerate() {
+        let page_path = format!("atlas/page_{}", index);
+        let page_id = uuid::Uuid::new_v4().to_string();
+
+        for (id, path, rect) in page.regions.iter() {
+            entries.push(PakEntry {
+                id: id.clone(),
+                path: path.clone(),
+                kind: PakKind::Texture,
+                offset: 0,
+                length: 0,
+                texture: Some(PakTexture {
+                    width: rect[2],
+                    height: rect[3],
+                    mips: 1,
+                    atlas: Some(AtlasRegion {
+                        page: page_path.clone(),
+                        rect: *rect,
+                    }),
+                }),
+            });
+        }
+
+        println!(
+            "packed {} textures into {} ({}x{})",
+            page.regions.len(),
+            page_path,
+            page.image.width(),
+            page.image.height()
+        );
+        entries.push(writer.texture(&page_id, &page_path, &mip_chain(page.image))?);
+    }
+
+    let count = entries.len();
+    writer.finish(PakManifest {
+        version: pak::VERSION,
+        entries,
+    })?;
+
+    println!("wrote {} entries to {}", count, output.display());
+    Ok(())
+}
+
+// full chain down to 1x1, each level half the size of the last
+fn mip_chain(image: RgbaImage) -> Vec<RgbaImage> {
+    let mut mips = vec![image];
+
+    loop {
+        let last = mips.last().unwrap();
+        if last.width() == 1 && last.height() == 1 {
+            break;
+        }
+
+        let width = (last.width() / 2).max(1);
+        let height = (last.height() / 2).max(1);
+        let next = image::imageops::resize(last, width, height, FilterType::Triangle);
+        mips.push(next);
+    }
+
+    mips
+}
+
+fn load_ids(asset_dir: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    #[derive(serde::Deserialize)]
+    struct Manifest {
+        assets: BTreeMap<String, String>,
+    }
+
+    let path = asset_dir.join(MANIFEST_FILE);
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(BTreeMap::new());
+    };
+
+    let manifest: Manifest =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(manifest.assets)
+}
+
+// new files get an id, the runtime reads the same manifest
+fn register_files(asset_dir: &Path, files: &[String]) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut ids = load_ids(asset_dir)?;
+
+    let before = ids.len();
+    for file in files.iter().filter(|file| !is_sidecar(file)) {
+        if !ids.values().any(|path| path == file) {
+            ids.insert(uuid::Uuid::new_v4().to_string(), file.clone());
+        }
+    }
+    if ids.len() != before {
+        save_ids(asset_dir, &ids)?;
+        println!("registered {} new assets", ids.len() - before);
+    }
+    Ok(ids)
+}
+
+fn save_ids(asset_dir: &Path, ids: &BTreeMap<String, String>) -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    struct Manifest<'a> {
+        assets: &'a BTreeMap<String, String>,
+    }
+
+    let path = asset_dir.join(MANIFEST_FILE);
+    let contents = toml::to_string_pretty(&Manifest { assets: ids })?;
+    fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn load_settings(asset_dir: &Path, path: &str) -> ImportSettings {
+    let sidecar = asset_dir.join(format!("{}.{}", path, META_EXTENSION));
+    fs::read_to_string(sidecar)
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn is_sidecar(path: &str) -> bool {
+    path.ends_with(&format!(".{}", META_EXTENSION))
+        || path.ends_with(&format!(".{}", ATLAS_EXTENSION))
+}
+
+fn is_texture(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| TEXTURE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+// paths relative to the asset dir with forward slashes, without the manifest
+fn collect_files(asset_dir: &Path) -> anyhow::Result<Vec<String>> {
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) -> anyhow::Result<()> {
+        for entry in
+            fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(root, &path, files)?;
+                continue;
+            }
+
+            let relative = path.strip_prefix(root)?;
+            let parts: Vec<_> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect();
+            let file = parts.join("/");
+
+            if file != MANIFEST_FILE {
+                files.push(file);
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(asset_dir, asset_dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
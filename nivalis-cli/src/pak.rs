@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+// layout: header, entry data, toml manifest. the runtime reader in
+// src/assets/pak.rs has to agree with everything in this file
+pub const MAGIC: &[u8; 4] = b"NVPK";
+pub const VERSION: u32 = 1;
+const HEADER_SIZE: u64 = 4 + 4 + 8 + 8; // magic, version, manifest offset, manifest length
+
+#[derive(Serialize, Deserialize)]
+pub struct PakManifest {
+    pub version: u32,
+    pub entries: Vec<PakEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PakKind {
+    Texture, // rgba8 mip chain, largest level first
+    Raw,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PakEntry {
+    pub id: String,
+    pub path: String,
+    pub kind: PakKind,
+    pub offset: u64,
+    pub length: u64,
+    pub texture: Option<PakTexture>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PakTexture {
+    pub width: u32,
+    pub height: u32,
+    pub mips: u32,
+    pub atlas: Option<AtlasRegion>, // data lives in the atlas page instead
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AtlasRegion {
+    pub page: String,
+    pub rect: [u32; 4], // x, y, width, height in pixels
+}
+
+pub struct PakWriter {
+    file: BufWriter<File>,
+    offset: u64,
+}
+
+impl PakWriter {
+    pub fn create(path: &Path) -> anyhow::Result<PakWriter> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+
+        // the manifest location is patched in by finish
+        file.write_all(&[0; HEADER_SIZE as usize])?;
+
+        Ok(PakWriter {
+            file,
+            offset: HEADER_SIZE,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> anyhow::Result<(u64, u64)> {
+        let offset = self.offset;
+        self.file.write_all(data)?;
+        self.offset += data.len() as u64;
+        Ok((offset, data.len() as u64))
+    }
+
+    pub fn raw(&mut self, id: &str, path: &str, data: &[u8]) -> anyhow::Result<PakEntry> {
+        let (offset, length) = self.write(data)?;
+
+        Ok(PakEntry {
+            id: id.to_string(),
+            path: path.to_string(),
+            kind: PakKind::Raw,
+            offset,
+            length,
+            texture: None,
+        })
+    }
+
+    pub fn texture(
+        &mut self,
+        id: &str,
+        path: &str,
+        mips: &[RgbaImage],
+    ) -> anyhow::Result<PakEntry> {
+        let Some(base) = mips.first() else {
+            bail!("{} has no mip levels", path);
+        };
+
+        let offset = self.offset;
+        for mip in mips {
+            self.write(mip.as_raw())?;
+        }
+
+        Ok(PakEntry {
+            id: id.to_string(),
+            path: path.to_string(),
+            kind: PakKind::Texture,
+            offset,
+            length: self.offset - offset,
+            texture: Some(PakTexture {
+                width: base.width(),
+                height: base.height(),
+                mips: mips.len() as u32,
+                atlas: None,
+            }),
+        })
+    }
+
+    pub fn finish(mut self, manifest: PakManifest) -> anyhow::Result<()> {
+        let contents = toml::to_string(&manifest)?;
+        let (offset, length) = self.write(contents.as_bytes())?;
+
+        let mut file = self.file.into_inner()?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&offset.to_le_bytes())?;
+        file.write_all(&length.to_le_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+pub fn read_manifest(path: &Path) -> anyhow::Result<PakManifest> {
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    let mut header = [0u8; HEADER_SIZE as usize];
+    file.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC {
+        bail!("{} is not an nvpak archive", path.display());
+    }
+
+    let version = u32::from_le_bytes(header[4..8].try_into()?);
+    if version != VERSION {
+        bail!(
+            "{} is version {}, expected {}",
+            path.display(),
+            version,
+            VERSION
+        );
+    }
+
+    let offset = u64::from_le_bytes(header[8..16].try_into()?);
+    let length = u64::from_le_bytes(header[16..24].try_into()?);
+
+    let mut contents = vec![0; length as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut contents)?;
+
+    Ok(toml::from_str(std::str::from_utf8(&contents)?)?)
+}
@@ -0,0 +1,68 @@
+struct DebugParams {
+    resolution: vec2<f32>,
+    _pad: vec2<f32>,
+}
+
+// a segment in physical pixels, y down
+struct DebugLine {
+    start: vec2<f32>,
+    end: vec2<f32>,
+    color: vec4<f32>,
+    width: f32,
+    _pad0: f32,
+    _pad1: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: DebugParams;
+@group(0) @binding(1) var<storage, read> lines: array<DebugLine>;
+
+// two triangles per line, grown sideways by half the width and lengthwise so
+// segments of a path meet without gaps
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    let line = lines[instance];
+
+    // y up from here, so the quad winds counter clockwise whichever way it points
+    let start = vec2<f32>(line.start.x, -line.start.y);
+    let end = vec2<f32>(line.end.x, -line.end.y);
+    let delta = end - start;
+    let span = length(delta);
+    let direction = select(vec2<f32>(1.0, 0.0), delta / span, span > 0.0001);
+    let normal = vec2<f32>(-direction.y, direction.x);
+    let half_width = max(line.width, 1.0) * 0.5;
+
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[vertex];
+    let along = mix(-half_width, span + half_width, corner.x);
+    let point = start + direction * along + normal * corner.y * half_width;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        point.x / params.resolution.x * 2.0 - 1.0,
+        point.y / params.resolution.y * 2.0 + 1.0,
+        0.0,
+        1.0,
+    );
+    out.color = line.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,38 @@
+struct DistortionParams {
+    resolution: vec2<f32>,
+    scroll: vec2<f32>, // offset map repeats per second
+    time: f32,
+    strength: f32, // pixels at full offset
+    scale: f32, // screen pixels per offset map repeat
+    _pad: f32,
+}
+
+@group(0) @binding(0) var scene: texture_2d<f32>;
+@group(0) @binding(1) var mask: texture_2d<f32>;
+@group(0) @binding(2) var screen_sampler: sampler;
+@group(0) @binding(3) var<uniform> params: DistortionParams;
+
+// the offset map, bound like any pool texture
+@group(1) @binding(0) var offset_map: texture_2d<f32>;
+@group(1) @binding(1) var offset_sampler: sampler;
+
+// fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex << 1u) & 2u), f32(vertex & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / params.resolution;
+    let weight = textureSampleLevel(mask, screen_sampler, uv, 0.0).a;
+
+    // red and green hold the x and y offset around 0.5, repeated by hand so
+    // clamped pool samplers still tile
+    let map_uv = fract(position.xy / params.scale + params.scroll * params.time);
+    let offset = textureSampleLevel(offset_map, offset_sampler, map_uv, 0.0).rg * 2.0 - 1.0;
+
+    let shifted = uv + offset * params.strength * weight / params.resolution;
+    return textureSampleLevel(scene, screen_sampler, shifted, 0.0);
+}
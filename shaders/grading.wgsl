@@ -0,0 +1,114 @@
+struct GradingParams {
+    ambient: vec3<f32>,
+    exposure: f32, // linear multiplier
+    grading: f32, // 0..1 mix towards the lut
+    lut_size: f32, // cells per axis, the lut is lut_size slices of lut_size squared side by side
+    scene_linear: u32, // whether the scene texture stores linear values
+    output_linear: u32, // same for the target
+    tonemapping: u32, // 0 none, 1 reinhard, 2 aces
+    output_scale: f32, // what white is on the target, above 1 on hdr output
+    peak: f32, // brightest the tonemapper goes, relative to white
+    chromatic: f32, // pixels red and blue are pulled apart at the edges
+    flash: vec4<f32>, // srgb color and how much of it covers the scene
+    fade: vec4<f32>,
+}
+
+@group(0) @binding(0) var scene: texture_2d<f32>;
+@group(0) @binding(1) var lut: texture_2d<f32>;
+@group(0) @binding(2) var lut_sampler: sampler;
+@group(0) @binding(3) var<uniform> params: GradingParams;
+
+// fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex << 1u) & 2u), f32(vertex & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+fn to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    let low = srgb / 12.92;
+    let high = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, srgb <= vec3<f32>(0.04045));
+}
+
+// narkowicz's fit of the aces filmic curve
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let curve = (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+    return clamp(curve, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// rolls values off towards peak instead of clipping them
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    let peak = params.peak;
+    switch params.tonemapping {
+        case 1u: {
+            return color / (1.0 + color / peak);
+        }
+        case 2u: {
+            return aces(color / peak) * peak;
+        }
+        default: {
+            return color;
+        }
+    }
+}
+
+// the lut is indexed with srgb encoded color, blue picks the slice
+fn lookup(color: vec3<f32>) -> vec3<f32> {
+    let size = params.lut_size;
+    let cell = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)) * (size - 1.0);
+    let slice = floor(cell.b);
+    let next = min(slice + 1.0, size - 1.0);
+    let dimensions = vec2<f32>(size * size, size);
+
+    let low = textureSampleLevel(lut, lut_sampler, (vec2<f32>(slice * size + cell.r, cell.g) + 0.5) / dimensions, 0.0);
+    let high = textureSampleLevel(lut, lut_sampler, (vec2<f32>(next * size + cell.r, cell.g) + 0.5) / dimensions, 0.0);
+    return mix(low.rgb, high.rgb, cell.b - slice);
+}
+
+// red and blue from further out and further in, growing towards the edges
+fn scene_texel(position: vec2<f32>) -> vec4<f32> {
+    var texel = textureLoad(scene, vec2<i32>(position), 0);
+    if (params.chromatic > 0.0) {
+        let size = vec2<f32>(textureDimensions(scene));
+        let offset = (position / size * 2.0 - 1.0) * params.chromatic;
+        let last = size - 1.0;
+        texel.r = textureLoad(scene, vec2<i32>(clamp(position + offset, vec2<f32>(0.0), last)), 0).r;
+        texel.b = textureLoad(scene, vec2<i32>(clamp(position - offset, vec2<f32>(0.0), last)), 0).b;
+    }
+    return texel;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = scene_texel(position.xy);
+
+    // transparent windows are premultiplied
+    var color = texel.rgb / max(texel.a, 0.0001);
+    if (params.scene_linear == 0u) {
+        color = to_linear(color);
+    }
+
+    color = tonemap(color * params.ambient * params.exposure);
+
+    var encoded = to_srgb(max(color, vec3<f32>(0.0)));
+    encoded = mix(encoded, lookup(encoded), params.grading);
+
+    // over the graded scene so a white flash stays white, and opaque over
+    // transparent windows
+    encoded = mix(encoded, params.fade.rgb, params.fade.a);
+    encoded = mix(encoded, params.flash.rgb, params.flash.a);
+    let alpha = mix(mix(texel.a, 1.0, params.fade.a), 1.0, params.flash.a);
+
+    var graded = encoded;
+    if (params.output_linear == 1u) {
+        graded = to_linear(encoded) * params.output_scale;
+    }
+    return vec4<f32>(graded * alpha, alpha);
+}
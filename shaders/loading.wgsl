@@ -0,0 +1,38 @@
+struct LoadingParams {
+    resolution: vec2<f32>,
+    progress: f32,
+    _pad: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: LoadingParams;
+
+// fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex << 1u) & 2u), f32(vertex & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let background = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    let border = vec4<f32>(0.6, 0.6, 0.6, 1.0);
+    let fill = vec4<f32>(1.0, 1.0, 1.0, 1.0);
+
+    let center = params.resolution * 0.5;
+    let half_size = vec2<f32>(params.resolution.x * 0.2, 8.0);
+    let distance = abs(position.xy - center);
+
+    if (distance.x > half_size.x + 2.0 || distance.y > half_size.y + 2.0) {
+        return background;
+    }
+    if (distance.x > half_size.x || distance.y > half_size.y) {
+        return border;
+    }
+
+    let fill_edge = center.x - half_size.x + 2.0 * half_size.x * params.progress;
+    if (position.x <= fill_edge) {
+        return fill;
+    }
+    return background;
+}
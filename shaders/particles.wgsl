@@ -0,0 +1,105 @@
+struct Particle {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+    life: f32,
+    max_life: f32,
+    seed: f32,
+    _pad: f32,
+}
+
+struct Params {
+    emitter: vec2<f32>,
+    gravity: vec2<f32>,
+    color: vec4<f32>,
+    dt: f32,
+    time: f32,
+    lifetime: f32,
+    speed: f32,
+    spread: f32,
+    size: f32,
+    spawn: u32,
+    capacity: u32,
+    aspect: f32,
+    sway: f32,
+    ground: f32,
+    stretch: f32,
+    extent: vec2<f32>,
+    wind: vec2<f32>,
+    direction: f32,
+    softness: f32,
+    // over life curves, 16 samples each
+    size_curve: array<vec4<f32>, 4>,
+    alpha_curve: array<vec4<f32>, 4>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) local: vec2<f32>, // -1..1 across the quad
+    @location(2) softness: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> particles: array<Particle>;
+
+fn curve_sample(curve: u32, i: u32) -> f32 {
+    if (curve == 0u) {
+        return params.size_curve[i / 4u][i % 4u];
+    }
+    return params.alpha_curve[i / 4u][i % 4u];
+}
+
+// age 0 when spawned, 1 when gone
+fn over_life(curve: u32, age: f32) -> f32 {
+    let x = clamp(age, 0.0, 1.0) * 15.0;
+    let i = min(u32(x), 14u);
+    return mix(curve_sample(curve, i), curve_sample(curve, i + 1u), x - f32(i));
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+
+    let p = particles[instance];
+    let age = 1.0 - clamp(p.life / p.max_life, 0.0, 1.0);
+    let size = params.size * over_life(0u, age);
+    let corner = corners[vertex];
+    var offset = corner * size;
+
+    // smeared along where it's heading, measured without the aspect squash
+    let velocity = (p.velocity + params.wind) * vec2<f32>(params.aspect, 1.0);
+    let speed = length(velocity);
+    if (params.stretch > 0.0 && speed > 0.0) {
+        let along = velocity / speed;
+        // a rotation of the quad, mirroring it would flip its winding
+        let across = vec2<f32>(along.y, -along.x);
+        offset = across * corner.x * size + along * corner.y * (size + 0.5 * params.stretch * speed);
+    }
+    offset *= vec2<f32>(1.0 / params.aspect, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(p.position + offset, 0.0, 1.0);
+    out.color = vec4<f32>(params.color.rgb, params.color.a * over_life(1u, age));
+    out.local = corner;
+    out.softness = params.softness;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (in.softness <= 0.0) {
+        return in.color;
+    }
+    let fade = 1.0 - smoothstep(1.0 - in.softness, 1.0, length(in.local));
+    return vec4<f32>(in.color.rgb, in.color.a * fade);
+}
@@ -0,0 +1,111 @@
+struct Particle {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+    life: f32,
+    max_life: f32,
+    seed: f32,
+    _pad: f32,
+}
+
+struct Params {
+    emitter: vec2<f32>,
+    gravity: vec2<f32>,
+    color: vec4<f32>,
+    dt: f32,
+    time: f32,
+    lifetime: f32,
+    speed: f32,
+    spread: f32,
+    size: f32,
+    spawn: u32,
+    capacity: u32,
+    aspect: f32,
+    sway: f32,
+    ground: f32,
+    stretch: f32,
+    extent: vec2<f32>,
+    wind: vec2<f32>,
+    direction: f32,
+    softness: f32,
+    // over life curves, 16 samples each
+    size_curve: array<vec4<f32>, 4>,
+    alpha_curve: array<vec4<f32>, 4>,
+}
+
+// same layout as wgpu's DrawIndirectArgs
+struct SrcArgs {
+    vertex_count: u32,
+    instance_count: u32,
+    first_vertex: u32,
+    first_instance: u32,
+}
+
+struct DstArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> src: array<Particle>;
+@group(0) @binding(2) var<storage, read_write> dst: array<Particle>;
+@group(0) @binding(3) var<storage, read> src_args: SrcArgs;
+@group(0) @binding(4) var<storage, read_write> dst_args: DstArgs;
+
+// pcg hash
+fn hash(input: u32) -> u32 {
+    let state = input * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(seed: u32) -> f32 {
+    return f32(hash(seed)) / 4294967295.0;
+}
+
+@compute @workgroup_size(256)
+fn cs_update(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= params.capacity) {
+        return;
+    }
+
+    let alive = src_args.instance_count;
+    var p: Particle;
+
+    if (i < alive) {
+        // simulate survivors from the previous frame
+        p = src[i];
+        p.life -= params.dt;
+        if (p.life <= 0.0) {
+            return;
+        }
+
+        p.velocity += params.gravity * params.dt;
+        let sway = params.sway * sin(params.time * 2.0 + p.seed * 6.2831853);
+        let drift = params.wind + vec2<f32>(sway / params.aspect, 0.0);
+        p.position += (p.velocity + drift) * params.dt;
+        if (p.position.y < params.ground) {
+            return;
+        }
+    } else if (i < alive + params.spawn) {
+        // spawn into the free slots after the survivors
+        let seed = hash(i ^ bitcast<u32>(params.time));
+        let angle = params.direction + (random(seed) - 0.5) * params.spread;
+        let speed = params.speed * (0.5 + 0.5 * random(seed + 1u));
+        let spot = vec2<f32>(random(seed + 3u), random(seed + 4u)) * 2.0 - 1.0;
+
+        p.position = params.emitter + spot * params.extent;
+        p.velocity = vec2<f32>(cos(angle) / params.aspect, sin(angle)) * speed;
+        p.max_life = params.lifetime * (0.5 + 0.5 * random(seed + 2u));
+        p.life = p.max_life;
+        p.seed = random(seed + 5u);
+    } else {
+        return;
+    }
+
+    // compact alive particles into the destination buffer
+    let index = atomicAdd(&dst_args.instance_count, 1u);
+    dst[index] = p;
+}
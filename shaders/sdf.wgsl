@@ -0,0 +1,78 @@
+struct SdfParams {
+    resolution: vec2<f32>,
+    _pad: vec2<f32>,
+}
+
+// a rounded box in physical pixels, y down. circles are boxes rounded all the
+// way, rings hollow them out to a band of their thickness
+struct SdfShape {
+    center: vec2<f32>,
+    half_size: vec2<f32>,
+    fill: vec4<f32>,
+    border_color: vec4<f32>,
+    radius: f32, // of the corners
+    border: f32, // width, inside the edge
+    ring: f32, // thickness of the band, 0 fills the shape
+    _pad: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>, // pixels from the center
+    @location(1) @interpolate(flat) index: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: SdfParams;
+@group(0) @binding(1) var<storage, read> shapes: array<SdfShape>;
+
+const AA = 1.0; // pixels of falloff past the edge
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(-1.0, -1.0),
+);
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    let shape = shapes[instance];
+    // grown by the falloff so edges aren't cut off
+    let local = CORNERS[vertex] * (shape.half_size + AA);
+    let pixel = shape.center + local;
+    let ndc = pixel / params.resolution * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.local = local;
+    out.index = instance;
+    return out;
+}
+
+fn rounded_box(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let q = abs(p) - half_size + radius;
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let shape = shapes[in.index];
+    let radius = min(shape.radius, min(shape.half_size.x, shape.half_size.y));
+    var distance = rounded_box(in.local, shape.half_size, radius);
+    if (shape.ring > 0.0) {
+        distance = abs(distance + shape.ring * 0.5) - shape.ring * 0.5;
+    }
+
+    let coverage = clamp(0.5 - distance / AA, 0.0, 1.0);
+    var color = shape.fill;
+    if (shape.border > 0.0) {
+        let inner = clamp(0.5 - (distance + shape.border) / AA, 0.0, 1.0);
+        color = mix(shape.border_color, shape.fill, inner);
+    }
+    return vec4<f32>(color.rgb, color.a * coverage);
+}
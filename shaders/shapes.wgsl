@@ -0,0 +1,31 @@
+struct ShapeParams {
+    resolution: vec2<f32>,
+    _pad: vec2<f32>,
+}
+
+struct VertexInput {
+    @location(0) position: vec2<f32>, // physical pixels, y down
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: ShapeParams;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let ndc = in.position / params.resolution * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,43 @@
+struct SkyParams {
+    forward: vec4<f32>,
+    right: vec4<f32>, // scaled to the edge of the view
+    up: vec4<f32>,
+    intensity: f32, // exposure in linear terms
+    output_linear: u32, // srgb and float targets encode on their own
+    _pad: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: SkyParams;
+@group(0) @binding(1) var sky: texture_cube<f32>;
+@group(0) @binding(2) var sky_sampler: sampler;
+
+// fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex << 1u) & 2u), f32(vertex & 2u));
+    var out: VertexOutput;
+    out.ndc = uv * 2.0 - 1.0;
+    out.position = vec4<f32>(out.ndc, 0.0, 1.0);
+    return out;
+}
+
+fn to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = params.forward.xyz + in.ndc.x * params.right.xyz + in.ndc.y * params.up.xyz;
+    var color = textureSample(sky, sky_sampler, direction).rgb * params.intensity;
+    if params.output_linear == 0u {
+        color = to_srgb(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)));
+    }
+    return vec4<f32>(color, 1.0);
+}
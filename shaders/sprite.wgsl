@@ -0,0 +1,288 @@
+struct Camera {
+    resolution: vec2<f32>,
+    position: vec2<f32>, // world pixel at the top left
+    zoom: f32,
+    snap: u32, // 1 snaps sprites to whole pixels
+}
+
+struct Sprite {
+    position: vec2<f32>, // pivot in pixels, y down
+    size: vec2<f32>,
+    color: vec4<f32>,
+    pivot: vec2<f32>, // 0..1 from the top left
+    rotation: f32,
+    outline: f32, // screen pixels the silhouette grows by, 0 for the sprite itself
+    region: vec4<f32>, // the part of the texture drawn, uv offset and size
+    id: u32, // the sprite's id plus one, 0 is nothing in the pick buffer
+    flash: f32, // 0..1 towards flash_color
+    dissolve: f32, // 0..1 of the sprite eaten away
+    palette_row: u32, // the variant palette pipelines read
+    flash_color: vec4<f32>,
+    dissolve_color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>, // 0..1 over the sprite, region maps it into the texture
+    @location(1) color: vec4<f32>,
+    @location(2) reach: vec2<f32>, // outline width in uv
+    @location(3) @interpolate(flat) region: vec4<f32>,
+    @location(4) @interpolate(flat) id: u32,
+    @location(5) @interpolate(flat) material: vec2<f32>, // flash and dissolve
+    @location(6) @interpolate(flat) flash_color: vec3<f32>,
+    @location(7) @interpolate(flat) dissolve_color: vec3<f32>,
+    @location(8) @interpolate(flat) palette_row: u32,
+}
+
+@group(0) @binding(0) var t: texture_2d<f32>;
+@group(0) @binding(1) var s: sampler;
+
+@group(1) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(1) var<storage, read> sprites: array<Sprite>;
+
+// only bound for the palette pipelines, the sampler is unused
+@group(2) @binding(0) var palette: texture_2d<f32>;
+
+// two counter clockwise triangles once y is flipped into clip space
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(-0.5, 0.5),
+    vec2<f32>(0.5, 0.5),
+    vec2<f32>(0.5, -0.5),
+    vec2<f32>(-0.5, 0.5),
+    vec2<f32>(0.5, -0.5),
+    vec2<f32>(-0.5, -0.5),
+);
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    let sprite = sprites[instance];
+    let corner = CORNERS[vertex];
+
+    // outlines push every corner out, the same on screen whatever the zoom
+    let grow = corner * 2.0 * sprite.outline / camera.zoom;
+
+    // relative to the pivot so rotation and scale happen around it
+    let local = (corner + 0.5 - sprite.pivot) * sprite.size + grow;
+    let cos_r = cos(sprite.rotation);
+    let sin_r = sin(sprite.rotation);
+    let rotated = vec2<f32>(local.x * cos_r - local.y * sin_r, local.x * sin_r + local.y * cos_r);
+
+    // snapping the unrotated top left corner keeps the edges on the pixel grid
+    var origin = (sprite.position - camera.position) * camera.zoom;
+    if (camera.snap == 1u) {
+        let pivot_offset = sprite.pivot * sprite.size * camera.zoom;
+        origin = round(origin - pivot_offset) + pivot_offset;
+    }
+    let pixel = origin + rotated * camera.zoom;
+
+    let ndc = pixel / camera.resolution * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.uv = corner + 0.5;
+    out.color = sprite.color;
+    out.reach = vec2<f32>(0.0);
+    out.region = sprite.region;
+    out.id = sprite.id;
+    out.material = vec2<f32>(sprite.flash, sprite.dissolve);
+    out.flash_color = sprite.flash_color.rgb;
+    out.dissolve_color = sprite.dissolve_color.rgb;
+    out.palette_row = sprite.palette_row;
+    if (sprite.outline > 0.0) {
+        out.uv += grow / sprite.size;
+        out.reach = abs(sprite.outline / camera.zoom / sprite.size);
+    }
+    return out;
+}
+
+fn texture_uv(in: VertexOutput) -> vec2<f32> {
+    return in.region.xy + in.uv * in.region.zw;
+}
+
+const DISSOLVE_SCALE = 12.0; // noise cells across the sprite
+const DISSOLVE_EDGE = 0.08; // of the noise range that glows before it goes
+
+// value noise over the sprite, 0..1
+fn hash(cell: vec2<f32>) -> f32 {
+    return fract(sin(dot(cell, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+fn noise(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = mix(hash(cell), hash(cell + vec2<f32>(1.0, 0.0)), u.x);
+    let b = mix(hash(cell + vec2<f32>(0.0, 1.0)), hash(cell + vec2<f32>(1.0, 1.0)), u.x);
+    return mix(a, b, u.y);
+}
+
+// the texel's color out of the palette, its red channel is the column.
+// loaded rather than sampled, filtering would blend neighbouring indices
+fn palette_texel(in: VertexOutput) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t));
+    let index = textureLoad(t, vec2<u32>(min(texture_uv(in) * size, size - 1.0)), 0);
+    let colors = textureDimensions(palette);
+    let column = min(u32(round(index.r * 255.0)), colors.x - 1u);
+    let row = min(in.palette_row, colors.y - 1u);
+    let color = textureLoad(palette, vec2<u32>(column, row), 0);
+    return vec4<f32>(color.rgb, color.a * index.a);
+}
+
+// the tinted texel with the sprite's material on top, dissolved parts are
+// fully transparent
+fn shade(in: VertexOutput) -> vec4<f32> {
+    return shade_texel(in, textureSample(t, s, texture_uv(in)));
+}
+
+fn shade_texel(in: VertexOutput, texel: vec4<f32>) -> vec4<f32> {
+    var color = texel * in.color;
+    let dissolve = in.material.y;
+    if (dissolve > 0.0) {
+        let n = noise(in.uv * DISSOLVE_SCALE);
+        if (n < dissolve) {
+            color.a = 0.0;
+        } else if (n < dissolve + DISSOLVE_EDGE) {
+            color = vec4<f32>(in.dissolve_color, color.a);
+        }
+    }
+    return vec4<f32>(mix(color.rgb, in.flash_color, in.material.x), color.a);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+// multiply blending fades towards white where the sprite is transparent
+fn multiplied(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(mix(vec3<f32>(1.0), color.rgb, color.a), color.a);
+}
+
+fn premultiplied(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+@fragment
+fn fs_multiply(in: VertexOutput) -> @location(0) vec4<f32> {
+    return multiplied(shade(in));
+}
+
+@fragment
+fn fs_premultiplied(in: VertexOutput) -> @location(0) vec4<f32> {
+    return premultiplied(shade(in));
+}
+
+@fragment
+fn fs_palette(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade_texel(in, palette_texel(in));
+}
+
+@fragment
+fn fs_palette_multiply(in: VertexOutput) -> @location(0) vec4<f32> {
+    return multiplied(shade_texel(in, palette_texel(in)));
+}
+
+@fragment
+fn fs_palette_premultiplied(in: VertexOutput) -> @location(0) vec4<f32> {
+    return premultiplied(shade_texel(in, palette_texel(in)));
+}
+
+// mask layers only mark the opaque part of the sprite in the stencil
+@fragment
+fn fs_mask(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = shade(in);
+    if (color.a < 0.5) {
+        discard;
+    }
+    return color;
+}
+
+// ids of the mostly opaque pixels, so clicks go through transparent edges
+@fragment
+fn fs_pick(in: VertexOutput) -> @location(0) u32 {
+    let color = shade(in);
+    if (color.a < 0.5) {
+        discard;
+    }
+    return in.id;
+}
+
+// outside the sprite counts as transparent, clamping would smear the edge and
+// sampling past a region would pick up its neighbours in the sheet
+fn coverage(uv: vec2<f32>, region: vec4<f32>) -> f32 {
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return select(0.0, textureSampleLevel(t, s, region.xy + uv * region.zw, 0.0).a, inside);
+}
+
+// the sprite's alpha dilated by reach, two rings of eight samples
+@fragment
+fn fs_outline(in: VertexOutput) -> @location(0) vec4<f32> {
+    var alpha = coverage(in.uv, in.region);
+    for (var i = 0u; i < 8u; i++) {
+        let angle = f32(i) * 0.7853982;
+        let direction = vec2<f32>(cos(angle), sin(angle)) * in.reach;
+        alpha = max(alpha, coverage(in.uv + direction, in.region));
+        alpha = max(alpha, coverage(in.uv + direction * 0.5, in.region));
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * alpha);
+}
+
+// debug views, drawn over a black frame instead of the shaded sprites
+
+const WIRE_COLOR = vec4<f32>(0.3, 1.0, 0.5, 1.0);
+
+// line rasterization only reaches the fragment shader on the edges
+@fragment
+fn fs_debug_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return WIRE_COLOR;
+}
+
+// about a pixel wide around the quad and along the diagonal splitting it,
+// which runs from (0, 1) to (1, 0) in uv
+@fragment
+fn fs_debug_edges(in: VertexOutput) -> @location(0) vec4<f32> {
+    let border = min(in.uv, 1.0 - in.uv) / fwidth(in.uv);
+    let diagonal = in.uv.x + in.uv.y - 1.0;
+    let distance = min(min(border.x, border.y), abs(diagonal) / fwidth(diagonal));
+    let alpha = 1.0 - clamp(distance - 0.5, 0.0, 1.0);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    return vec4<f32>(WIRE_COLOR.rgb, alpha);
+}
+
+// the alpha as a height, its slope tilts the normal. encoded as 0.5 + n / 2
+// with y up, like a normal map
+@fragment
+fn fs_debug_normals(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t));
+    let uv = texture_uv(in);
+    let left = textureSample(t, s, uv - vec2<f32>(texel.x, 0.0)).a;
+    let right = textureSample(t, s, uv + vec2<f32>(texel.x, 0.0)).a;
+    let up = textureSample(t, s, uv - vec2<f32>(0.0, texel.y)).a;
+    let down = textureSample(t, s, uv + vec2<f32>(0.0, texel.y)).a;
+    let alpha = textureSample(t, s, uv).a;
+    let normal = normalize(vec3<f32>(left - right, down - up, 0.5));
+    return vec4<f32>(normal * 0.5 + 0.5, alpha);
+}
+
+// added up by the blend state, red saturates at ten layers and yellow to
+// white take more
+@fragment
+fn fs_debug_overdraw(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.1, 0.04, 0.02, 1.0);
+}
+
+const CHECKER_TEXELS = 8.0;
+
+// u in red and v in green over a checker of the texture's texels
+@fragment
+fn fs_debug_uv(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t));
+    let cell = floor(texture_uv(in) * size / CHECKER_TEXELS);
+    let checker = select(0.6, 1.0, (i32(cell.x) + i32(cell.y)) % 2 == 0);
+    return vec4<f32>(vec3<f32>(in.uv, 0.25) * checker, 1.0);
+}
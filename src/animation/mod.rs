@@ -0,0 +1,330 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::{Context, bail};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::animation::tracks::{FrameBox, FramePoint};
+use crate::assets::{pak, remote};
+use crate::renderer::Renderer;
+
+pub mod tracks;
+
+// one frame of a clip, drawn from a named region of the sprite's sheet
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AnimationFrame {
+    pub region: String,
+    pub duration: f32, // seconds
+    // reported when the frame starts, e.g. "footstep" or "attack"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<String>,
+    // tracks for gameplay, active while the frame is
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hitboxes: Vec<FrameBox>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub points: Vec<FramePoint>, // e.g. where projectiles spawn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sounds: Vec<String>, // cue names, reported like markers
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    #[serde(default)]
+    pub looping: bool, // otherwise it holds its last frame once finished
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    Bool { param: String, value: bool },
+    Greater { param: String, value: f32 },
+    Less { param: String, value: f32 },
+    Trigger { param: String }, // consumed by the transition it fires
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: Option<String>, // any state when None, except the one it goes to
+    pub to: String,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    #[serde(default)]
+    pub on_finish: bool, // waits for a non looping clip to play out
+}
+
+// states are clips, transitions are tried in order and the first that holds wins
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AnimationGraph {
+    pub initial: String,
+    pub clips: BTreeMap<String, AnimationClip>,
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+#[allow(dead_code)]
+impl AnimationGraph {
+    // a toml file relative to the asset directory or in the pak. remote graphs
+    // block while they download, open those with remote::spawn_open
+    pub fn open(graph_name: &str) -> anyhow::Result<Self> {
+        let bytes = match pak::mounted().and_then(|pak| pak.read(graph_name)) {
+            Some(bytes) => Some(bytes),
+            None => remote::read(graph_name).transpose()?,
+        };
+        let contents = match bytes {
+            Some(bytes) => String::from_utf8(bytes).context("animation isn't utf-8")?,
+            None => {
+                let file = format!("assets/{}", graph_name);
+                debug!("loading animation at {}", file);
+                std::fs::read_to_string(&file)
+                    .with_context(|| format!("failed to read {}", file))?
+            }
+        };
+
+        let graph: AnimationGraph = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse animation {}", graph_name))?;
+        graph
+            .validate()
+            .with_context(|| format!("animation {} is invalid", graph_name))?;
+        Ok(graph)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.clips.contains_key(&self.initial) {
+            bail!("initial clip {} doesn't exist", self.initial);
+        }
+        for (name, clip) in self.clips.iter() {
+            if clip.frames.is_empty() {
+                bail!("clip {} has no frames", name);
+            }
+            if clip.frames.iter().any(|frame| frame.duration <= 0.0) {
+                bail!("clip {} has a frame without a duration", name);
+            }
+        }
+        for transition in self.transitions.iter() {
+            for state in transition.from.iter().chain([&transition.to]) {
+                if !self.clips.contains_key(state) {
+                    bail!("transition refers to a missing clip {}", state);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum AnimationEvent {
+    Marker { clip: String, marker: String },
+    Sound { clip: String, sound: String },
+    Changed { from: String, to: String },
+    Finished { clip: String }, // non looping clips, once
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Param {
+    Bool(bool),
+    Float(f32),
+    Trigger,
+}
+
+// plays one graph for one sprite. the game sets parameters, calls update every
+// frame and handles the events; swaps between clips are instant, no blending.
+// for games, the engine animates none of its own sprites
+#[allow(dead_code)]
+pub struct Animator {
+    graph: Arc<AnimationGraph>,
+    state: String,
+    frame: usize,
+    elapsed: f32, // into the current frame
+    finished: bool,
+    params: HashMap<String, Param>,
+    applied: bool, // whether the sprite shows the current frame
+    started: bool, // the first frame is reported on the first update
+}
+
+#[allow(dead_code)]
+impl Animator {
+    // graphs are shared, many sprites can play the same one
+    pub fn new(graph: Arc<AnimationGraph>) -> Self {
+        let state = graph.initial.clone();
+        Animator {
+            graph,
+            state,
+            frame: 0,
+            elapsed: 0.0,
+            finished: false,
+            params: HashMap::new(),
+            applied: false,
+            started: false,
+        }
+    }
+
+    pub fn graph(&self) -> &Arc<AnimationGraph> {
+        &self.graph
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn region(&self) -> &str {
+        &self.clip().frames[self.frame].region
+    }
+
+    pub fn set_bool(&mut self, param: &str, value: bool) {
+        self.params.insert(param.to_string(), Param::Bool(value));
+    }
+
+    pub fn set_float(&mut self, param: &str, value: f32) {
+        self.params.insert(param.to_string(), Param::Float(value));
+    }
+
+    // stays set until a transition uses it
+    pub fn set_trigger(&mut self, param: &str) {
+        self.params.insert(param.to_string(), Param::Trigger);
+    }
+
+    pub fn reset_trigger(&mut self, param: &str) {
+        if self.params.get(param) == Some(&Param::Trigger) {
+            self.params.remove(param);
+        }
+    }
+
+    // jumps to a clip without going through a transition, the markers of its
+    // first frame come with the next update
+    pub fn play(&mut self, clip: &str) -> bool {
+        if !self.graph.clips.contains_key(clip) {
+            warn!("animation has no clip {}", clip);
+            return false;
+        }
+        self.enter(clip.to_string(), &mut Vec::new());
+        self.started = false;
+        true
+    }
+
+    pub fn update(&mut self, dt_seconds: f32) -> Vec<AnimationEvent> {
+        let mut events = Vec::new();
+        if !self.started {
+            self.started = true;
+            self.report_frame(&mut events);
+        }
+
+        self.take_transition(&mut events);
+
+        // long frames step through every frame so no marker gets skipped
+        self.elapsed += dt_seconds.max(0.0);
+        loop {
+            let clip = self.clip();
+            let duration = clip.frames[self.frame].duration;
+            if self.finished || self.elapsed < duration {
+                break;
+            }
+
+            if self.frame + 1 < clip.frames.len() {
+                self.frame += 1;
+            } else if clip.looping {
+                self.frame = 0;
+            } else {
+                self.finished = true;
+                self.elapsed = duration;
+                events.push(AnimationEvent::Finished {
+                    clip: self.state.clone(),
+                });
+                // an on finish transition goes this frame, not the next
+                self.take_transition(&mut events);
+                break;
+            }
+            self.elapsed -= duration;
+            self.applied = false;
+            self.report_frame(&mut events);
+        }
+        events
+    }
+
+    // shows the current frame on the sprite, only touching it when the frame changed
+    pub fn apply(&mut self, renderer: &mut Renderer, sprite: usize) {
+        if self.applied {
+            return;
+        }
+        self.applied = renderer.set_sprite_region(sprite, Some(self.region()));
+    }
+
+    fn clip(&self) -> &AnimationClip {
+        &self.graph.clips[&self.state]
+    }
+
+    fn enter(&mut self, state: String, events: &mut Vec<AnimationEvent>) {
+        let from = std::mem::replace(&mut self.state, state);
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.finished = false;
+        self.applied = false;
+        events.push(AnimationEvent::Changed {
+            from,
+            to: self.state.clone(),
+        });
+        self.report_frame(events);
+    }
+
+    fn report_frame(&self, events: &mut Vec<AnimationEvent>) {
+        let frame = self.current_frame();
+        for marker in frame.markers.iter() {
+            events.push(AnimationEvent::Marker {
+                clip: self.state.clone(),
+                marker: marker.clone(),
+            });
+        }
+        for sound in frame.sounds.iter() {
+            events.push(AnimationEvent::Sound {
+                clip: self.state.clone(),
+                sound: sound.clone(),
+            });
+        }
+    }
+
+    fn holds(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Bool { param, value } => {
+                self.params.get(param) == Some(&Param::Bool(*value))
+                    || (!*value && !self.params.contains_key(param))
+            }
+            Condition::Greater { param, value } => {
+                matches!(self.params.get(param), Some(Param::Float(v)) if v > value)
+            }
+            Condition::Less { param, value } => {
+                matches!(self.params.get(param), Some(Param::Float(v)) if v < value)
+            }
+            Condition::Trigger { param } => self.params.get(param) == Some(&Param::Trigger),
+        }
+    }
+
+    fn take_transition(&mut self, events: &mut Vec<AnimationEvent>) {
+        let graph = self.graph.clone();
+        let Some(transition) = graph.transitions.iter().find(|transition| {
+            let from = match &transition.from {
+                Some(from) => *from == self.state,
+                None => transition.to != self.state,
+            };
+            from && (!transition.on_finish || self.finished)
+                && transition.conditions.iter().all(|c| self.holds(c))
+        }) else {
+            return;
+        };
+
+        for condition in transition.conditions.iter() {
+            if let Condition::Trigger { param } = condition {
+                self.params.remove(param);
+            }
+        }
+        self.enter(transition.to.clone(), events);
+    }
+}
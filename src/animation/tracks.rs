@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{AnimationFrame, Animator};
+use crate::renderer::Renderer;
+use crate::world::collision::ColliderShape;
+
+// a named rect on a frame, e.g. "hurt" or "attack"
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FrameBox {
+    pub name: String,
+    pub rect: [f32; 4], // x, y, width, height in pixels of the frame's region
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FramePoint {
+    pub name: String,
+    pub position: [f32; 2], // in pixels of the frame's region
+}
+
+#[allow(dead_code)] // for games, see Animator
+impl Animator {
+    pub fn current_frame(&self) -> &AnimationFrame {
+        &self.clip().frames[self.frame]
+    }
+
+    pub fn hitboxes(&self) -> &[FrameBox] {
+        &self.current_frame().hitboxes
+    }
+
+    pub fn hitbox(&self, name: &str) -> Option<&FrameBox> {
+        self.hitboxes().iter().find(|hitbox| hitbox.name == name)
+    }
+
+    pub fn point(&self, name: &str) -> Option<&FramePoint> {
+        self.current_frame()
+            .points
+            .iter()
+            .find(|point| point.name == name)
+    }
+
+    // the active frame's hitboxes placed where the sprite is drawn, rects turn into
+    // polygons on rotated sprites. test them against bodies with overlaps_rect
+    pub fn world_hitboxes(&self, renderer: &Renderer, sprite: usize) -> Vec<(&str, ColliderShape)> {
+        self.hitboxes()
+            .iter()
+            .filter_map(|hitbox| {
+                let [x, y, width, height] = hitbox.rect;
+                let corners = [
+                    [x, y],
+                    [x + width, y],
+                    [x + width, y + height],
+                    [x, y + height],
+                ]
+                .map(|corner| self.world_position(renderer, sprite, corner));
+                let corners: Option<Vec<[f32; 2]>> = corners.into_iter().collect();
+                Some((hitbox.name.as_str(), shape(corners?)))
+            })
+            .collect()
+    }
+
+    pub fn world_point(&self, renderer: &Renderer, sprite: usize, name: &str) -> Option<[f32; 2]> {
+        let point = self.point(name)?;
+        self.world_position(renderer, sprite, point.position)
+    }
+
+    // frame pixels to world, through the size of the region the frame is drawn from.
+    // sprites without a sheet count a pixel as one unit of their size
+    fn world_position(
+        &self,
+        renderer: &Renderer,
+        sprite: usize,
+        pixel: [f32; 2],
+    ) -> Option<[f32; 2]> {
+        let drawn = renderer.sprite(sprite)?;
+        let size = match renderer.atlas_region(drawn.pool, drawn.texture, self.region()) {
+            Some([_, _, width, height]) => [width as f32, height as f32],
+            None => drawn.size.map(f32::abs),
+        };
+        if size.contains(&0.0) {
+            return None;
+        }
+        renderer.sprite_point(sprite, [pixel[0] / size[0], pixel[1] / size[1]])
+    }
+}
+
+// axis aligned corners stay a rect, so unrotated hitboxes are cheap to test
+fn shape(corners: Vec<[f32; 2]>) -> ColliderShape {
+    let aligned = corners[0][1] == corners[1][1] && corners[1][0] == corners[2][0];
+    let polygon = ColliderShape::Polygon(corners);
+    match aligned {
+        true => ColliderShape::Rect(polygon.bounds()),
+        false => polygon,
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::assets::pak;
+
+const ATLAS_EXTENSION: &str = "atlas";
+
+// named regions of a sprite sheet, written next to the texture by `nivalis-cli atlas`
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct AtlasRegions {
+    pub size: [u32; 2],
+    pub regions: BTreeMap<String, [u32; 4]>, // name -> x, y, width, height in pixels
+}
+
+impl AtlasRegions {
+    // asset path is relative to the asset directory
+    pub fn sidecar_path(asset_path: &str) -> String {
+        format!("assets/{}.{}", asset_path, ATLAS_EXTENSION)
+    }
+
+    pub fn is_sidecar(path: &str) -> bool {
+        path.ends_with(&format!(".{}", ATLAS_EXTENSION))
+    }
+
+    // None for textures that aren't sprite sheets
+    pub fn load(asset_path: &str) -> Option<AtlasRegions> {
+        let path = Self::sidecar_path(asset_path);
+        let packed = pak::mounted()
+            .and_then(|pak| pak.read(&format!("{}.{}", asset_path, ATLAS_EXTENSION)))
+            .and_then(|data| String::from_utf8(data).ok());
+        let contents = packed.or_else(|| fs::read_to_string(&path).ok())?;
+
+        match toml::from_str::<AtlasRegions>(&contents) {
+            Ok(regions) if regions.size[0] > 0 && regions.size[1] > 0 => Some(regions),
+            Ok(_) => {
+                warn!("{} has an empty size, ignoring it", path);
+                None
+            }
+            Err(e) => {
+                warn!("failed to parse {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn rect(&self, name: &str) -> Option<[u32; 4]> {
+        self.regions.get(name).copied()
+    }
+
+    // offset and size in uv, what sprites sample with
+    pub fn uv(&self, name: &str) -> Option<[f32; 4]> {
+        let [x, y, width, height] = self.rect(name)?;
+        let [sheet_width, sheet_height] = self.size.map(|size| size as f32);
+        Some([
+            x as f32 / sheet_width,
+            y as f32 / sheet_height,
+            width as f32 / sheet_width,
+            height as f32 / sheet_height,
+        ])
+    }
+}
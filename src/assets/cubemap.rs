@@ -0,0 +1,191 @@
+use std::f32::consts::PI;
+
+use anyhow::bail;
+use log::{info, warn};
+
+use crate::assets::meta::TextureMeta;
+use crate::assets::{DecodedImage, PixelFormat};
+
+// where a cubemap's faces come from
+#[derive(Clone, Copy, Debug)]
+pub enum CubemapSource<'s> {
+    Faces([&'s str; 6]), // +x, -x, +y, -y, +z, -z
+    Equirect(&'s str),   // a 2:1 panorama, like most hdr skies ship as
+}
+
+// six square faces in the order wgpu layers them, +z is behind the viewer
+pub struct DecodedCubemap {
+    pub name: String,
+    pub faces: [Vec<u8>; 6],
+    pub size: u32,
+    pub format: PixelFormat,
+    pub meta: TextureMeta, // of the first face or the panorama
+}
+
+impl DecodedCubemap {
+    pub fn open(source: CubemapSource) -> anyhow::Result<Self> {
+        match source {
+            CubemapSource::Faces(faces) => Self::from_faces(faces),
+            CubemapSource::Equirect(path) => Self::from_equirect(&DecodedImage::open(path)?),
+        }
+    }
+
+    pub fn from_faces(paths: [&str; 6]) -> anyhow::Result<Self> {
+        let mut faces: [Vec<u8>; 6] = Default::default();
+        let first = DecodedImage::open(paths[0])?;
+        let (size, height) = first.dimensions;
+        if size != height {
+            bail!(
+                "cubemap face {} is {}x{}, not square",
+                paths[0],
+                size,
+                height
+            );
+        }
+
+        for (face, path) in faces.iter_mut().zip(paths).skip(1) {
+            let image = DecodedImage::open(path)?;
+            if image.dimensions != first.dimensions {
+                bail!(
+                    "cubemap face {} is {}x{}, the first face is {}x{}",
+                    path,
+                    image.dimensions.0,
+                    image.dimensions.1,
+                    size,
+                    size
+                );
+            }
+            if image.format != first.format {
+                bail!("cubemap face {} mixes hdr and sdr faces", path);
+            }
+            *face = image.rgba;
+        }
+        faces[0] = first.rgba;
+
+        Ok(DecodedCubemap {
+            name: first.name,
+            faces,
+            size,
+            format: first.format,
+            meta: first.meta,
+        })
+    }
+
+    // resampled bilinearly into faces a quarter of the panorama wide
+    pub fn from_equirect(image: &DecodedImage) -> anyhow::Result<Self> {
+        let (width, height) = image.dimensions;
+        if width < 4 || height < 2 {
+            bail!("panorama {} is only {}x{}", image.name, width, height);
+        }
+        if width != height * 2 {
+            warn!(
+                "panorama {} is {}x{}, expected twice as wide as high",
+                image.name, width, height
+            );
+        }
+
+        let size = width / 4;
+        info!(
+            "converting panorama {} to {}x{} cubemap faces",
+            image.name, size, size
+        );
+        let panorama = Panorama { image };
+        let faces = std::array::from_fn(|face| {
+            let mut pixels =
+                Vec::with_capacity((size * size * image.format.bytes_per_pixel()) as usize);
+            for y in 0..size {
+                for x in 0..size {
+                    let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    let color = panorama.sample(face_direction(face, u, v));
+                    write_pixel(&mut pixels, image.format, color);
+                }
+            }
+            pixels
+        });
+
+        Ok(DecodedCubemap {
+            name: image.name.clone(),
+            faces,
+            size,
+            format: image.format,
+            meta: image.meta.clone(),
+        })
+    }
+
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        self.format.texture_format(&self.meta)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.size as u64 * self.size as u64 * self.format.bytes_per_pixel() as u64 * 6
+    }
+}
+
+// where a face pixel points, u and v in -1..1 from the face's top left
+fn face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+struct Panorama<'i> {
+    image: &'i DecodedImage,
+}
+
+impl Panorama<'_> {
+    // -z is the middle of the panorama, +y the top row
+    fn sample(&self, [x, y, z]: [f32; 3]) -> [f32; 4] {
+        let length = (x * x + y * y + z * z).sqrt();
+        let u = 0.5 + x.atan2(-z) / (2.0 * PI);
+        let v = (y / length).clamp(-1.0, 1.0).acos() / PI;
+
+        let (width, height) = self.image.dimensions;
+        let px = u * width as f32 - 0.5;
+        let py = (v * height as f32 - 0.5).clamp(0.0, height as f32 - 1.0);
+        let (x0, y0) = (px.floor(), py.floor());
+        let (fx, fy) = (px - x0, py - y0);
+
+        // wraps around horizontally, clamps at the poles
+        let column = |x: f32| (x as i64).rem_euclid(width as i64) as u32;
+        let row = |y: f32| (y as u32).min(height - 1);
+        let texel = |x: f32, y: f32| self.texel(column(x), row(y));
+        let top = lerp(texel(x0, y0), texel(x0 + 1.0, y0), fx);
+        let bottom = lerp(texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0), fx);
+        lerp(top, bottom, fy)
+    }
+
+    fn texel(&self, x: u32, y: u32) -> [f32; 4] {
+        let bytes = self.image.format.bytes_per_pixel() as usize;
+        let index = (y as usize * self.image.dimensions.0 as usize + x as usize) * bytes;
+        let pixel = &self.image.rgba[index..index + bytes];
+        match self.image.format {
+            PixelFormat::Rgba8 => std::array::from_fn(|i| pixel[i] as f32 / 255.0),
+            PixelFormat::Rgba16Float => std::array::from_fn(|i| {
+                half::f16::from_le_bytes([pixel[i * 2], pixel[i * 2 + 1]]).to_f32()
+            }),
+        }
+    }
+}
+
+fn lerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+fn write_pixel(pixels: &mut Vec<u8>, format: PixelFormat, color: [f32; 4]) {
+    match format {
+        PixelFormat::Rgba8 => {
+            pixels.extend(color.map(|value| (value * 255.0).round().clamp(0.0, 255.0) as u8))
+        }
+        PixelFormat::Rgba16Float => pixels.extend(
+            color
+                .into_iter()
+                .flat_map(|value| half::f16::from_f32(value).to_le_bytes()),
+        ),
+    }
+}
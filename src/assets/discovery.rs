@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::manifest::AssetManifest;
+use crate::assets::pak;
+use crate::platform::dialog::{IMAGE_FILES, SOUND_FILES};
+
+// how the assets are found at startup
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryMode {
+    // walks the asset directory and registers new files in the manifest. for
+    // development only, it writes into the asset directory
+    Scan,
+    #[default]
+    Manifest, // trusts the prebuilt manifest and a mounted pak, the directory isn't read
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AssetKind {
+    Texture,
+    Sound,
+    Other, // maps, animation graphs and whatever else is read by path
+}
+
+impl AssetKind {
+    pub fn of(path: &str) -> Self {
+        let path = Path::new(path);
+        if IMAGE_FILES.matches(path) {
+            AssetKind::Texture
+        } else if SOUND_FILES.matches(path) {
+            AssetKind::Sound
+        } else {
+            AssetKind::Other
+        }
+    }
+}
+
+pub struct DiscoveredAsset {
+    pub path: String, // relative to the asset directory, what the loaders take
+    pub kind: AssetKind,
+}
+
+// the path relative to the asset directory without its extension, so
+// textures/cat keeps working when cat.png becomes cat.webp
+pub fn logical_path(path: &str) -> &str {
+    let name = path.rfind('/').map_or(0, |slash| slash + 1);
+    match path[name..].rfind('.') {
+        Some(dot) if dot > 0 => &path[..name + dot],
+        _ => path,
+    }
+}
+
+// every asset of the manifest and the mounted pak by logical path, nothing
+// is decoded until it's asked for
+#[derive(Default)]
+pub struct AssetIndex {
+    assets: BTreeMap<String, DiscoveredAsset>,
+}
+
+impl AssetIndex {
+    pub fn build(manifest: &AssetManifest) -> Self {
+        let mut index = AssetIndex::default();
+        for (_, path) in manifest.iter() {
+            index.insert(path);
+        }
+        if let Some(pak) = pak::mounted() {
+            for path in pak.paths() {
+                index.insert(path);
+            }
+        }
+        info!("discovered {} assets", index.assets.len());
+        index
+    }
+
+    fn insert(&mut self, path: &str) {
+        let logical = logical_path(path);
+        match self.assets.get(logical) {
+            Some(known) if known.path != path => {
+                warn!(
+                    "{} and {} are both {}, keeping the first",
+                    known.path, path, logical
+                );
+            }
+            Some(_) => {}
+            None => {
+                self.assets.insert(
+                    logical.to_string(),
+                    DiscoveredAsset {
+                        path: path.to_string(),
+                        kind: AssetKind::of(path),
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &DiscoveredAsset)> {
+        self.assets
+            .iter()
+            .map(|(logical, asset)| (logical.as_str(), asset))
+    }
+
+    // a logical path, or the full path with its extension
+    pub fn resolve(&self, path: &str) -> Option<&DiscoveredAsset> {
+        self.assets.get(path).or_else(|| {
+            self.assets
+                .get(logical_path(path))
+                .filter(|asset| asset.path == path)
+        })
+    }
+}
@@ -0,0 +1,438 @@
+use anyhow::{Context, bail};
+use base64::Engine;
+use gltf::{Gltf, Node, buffer, image, mesh::Mode};
+use log::warn;
+
+use crate::assets::model::{DecodedModel, Mesh, MeshMaterial, MeshVertex, sibling_path};
+
+type Matrix = [[f32; 4]; 4]; // column major, like gltf writes it
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+// gltf or glb with its buffers, read through read by asset path. the nodes
+// of the default scene are baked into the vertices, every triangle primitive
+// becomes a mesh. points, lines, skins and morph targets are skipped
+pub fn parse(
+    name: &str,
+    bytes: &[u8],
+    read: impl Fn(&str) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<DecodedModel> {
+    let gltf = Gltf::from_slice(bytes).context("not a valid gltf file")?;
+    let buffers = gltf
+        .buffers()
+        .map(|buffer| {
+            let data = match buffer.source() {
+                buffer::Source::Bin => gltf.blob.clone().context("glb has no binary chunk")?,
+                buffer::Source::Uri(uri) => read_uri(name, uri, &read)?,
+            };
+            if data.len() < buffer.length() {
+                bail!(
+                    "buffer {} is {} bytes, expected {}",
+                    buffer.index(),
+                    data.len(),
+                    buffer.length()
+                );
+            }
+            Ok(data)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut model = DecodedModel {
+        name: name.to_string(),
+        materials: gltf
+            .materials()
+            .map(|material| parse_material(name, material))
+            .collect(),
+        ..Default::default()
+    };
+
+    let scene = gltf.default_scene().or_else(|| gltf.scenes().next());
+    match scene {
+        Some(scene) => {
+            for node in scene.nodes() {
+                add_node(&mut model, &node, IDENTITY, &buffers)?;
+            }
+        }
+        // a file with only meshes in it, nothing places them
+        None => {
+            for mesh in gltf.meshes() {
+                add_mesh(&mut model, &mesh, mesh.name(), IDENTITY, &buffers)?;
+            }
+        }
+    }
+
+    if model.meshes.is_empty() {
+        bail!("{} has no triangles", name);
+    }
+    Ok(model)
+}
+
+// data: uris are decoded in place, anything else is a file next to the model
+fn read_uri(
+    name: &str,
+    uri: &str,
+    read: impl Fn(&str) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .context("only base64 data uris are supported")?;
+        return base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .context("bad base64 in a data uri");
+    }
+    read(&sibling_path(name, &decode_uri(uri)))
+}
+
+// uris in gltf are percent-encoded, file names aren't
+fn decode_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_material(name: &str, material: gltf::Material) -> MeshMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    let texture =
+        pbr.base_color_texture()
+            .and_then(|info| match info.texture().source().source() {
+                image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                    Some(sibling_path(name, &decode_uri(uri)))
+                }
+                _ => {
+                    warn!(
+                        "{} embeds the texture of {}, only image files are read",
+                        name,
+                        material.name().unwrap_or("a material")
+                    );
+                    None
+                }
+            });
+    MeshMaterial {
+        name: material.name().unwrap_or_default().to_string(),
+        base_color: pbr.base_color_factor(),
+        emissive: material.emissive_factor(),
+        texture,
+    }
+}
+
+fn add_node(
+    model: &mut DecodedModel,
+    node: &Node,
+    parent: Matrix,
+    buffers: &[Vec<u8>],
+) -> anyhow::Result<()> {
+    let transform = multiply(parent, node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        let name = mesh.name().or(node.name());
+        add_mesh(model, &mesh, name, transform, buffers)?;
+    }
+    for child in node.children() {
+        add_node(model, &child, transform, buffers)?;
+    }
+    Ok(())
+}
+
+fn add_mesh(
+    model: &mut DecodedModel,
+    mesh: &gltf::Mesh,
+    name: Option<&str>,
+    transform: Matrix,
+    buffers: &[Vec<u8>],
+) -> anyhow::Result<()> {
+    let name = name.unwrap_or_default();
+    let (normal_matrix, mirrored) = normal_matrix(transform);
+    for primitive in mesh.primitives() {
+        if primitive.mode() != Mode::Triangles {
+            warn!(
+                "{} has {:?} primitives, only triangles are drawn",
+                name,
+                primitive.mode()
+            );
+            continue;
+        }
+        let at_primitive = || format!("mesh {} primitive {}", name, primitive.index());
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+        let mut vertices: Vec<MeshVertex> = reader
+            .read_positions()
+            .with_context(|| format!("{} has no positions", at_primitive()))?
+            .map(|position| MeshVertex {
+                position: transform_point(transform, position),
+                ..Default::default()
+            })
+            .collect();
+        // gltf uvs are from the top left already
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                vertex.uv = uv;
+            }
+        }
+
+        let mut indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..vertices.len() as u32).collect(),
+        };
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertices.len()) {
+            bail!("{}: index {} is out of range", at_primitive(), index);
+        }
+        indices.truncate(indices.len() / 3 * 3);
+        // a mirroring transform turns the triangles around
+        if mirrored {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        match reader.read_normals() {
+            Some(normals) => {
+                for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                    vertex.normal = normalize(multiply_3(normal_matrix, normal));
+                }
+            }
+            None => smooth_normals(&mut vertices, &indices),
+        }
+
+        model.meshes.push(Mesh {
+            name: name.to_string(),
+            vertices,
+            indices,
+            material: primitive.material().index(),
+        });
+    }
+    Ok(())
+}
+
+// every vertex gets the average of the faces using it, larger faces weigh more
+fn smooth_normals(vertices: &mut [MeshVertex], indices: &[u32]) {
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        let (ab, ac) = (sub(b, a), sub(c, a));
+        let normal = cross(ab, ac);
+        for &index in triangle {
+            let vertex = &mut vertices[index as usize];
+            vertex.normal = std::array::from_fn(|i| vertex.normal[i] + normal[i]);
+        }
+    }
+    for vertex in vertices.iter_mut() {
+        vertex.normal = normalize(vertex.normal);
+    }
+}
+
+fn multiply(a: Matrix, b: Matrix) -> Matrix {
+    std::array::from_fn(|column| {
+        std::array::from_fn(|row| (0..4).map(|k| a[k][row] * b[column][k]).sum())
+    })
+}
+
+fn transform_point(m: Matrix, p: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|row| m[0][row] * p[0] + m[1][row] * p[1] + m[2][row] * p[2] + m[3][row])
+}
+
+// the cofactors of the upper 3x3 turn normals like the inverse transpose
+// does, up to a scale normalize takes out and the sign of the determinant
+fn normal_matrix(m: Matrix) -> ([[f32; 3]; 3], bool) {
+    let columns = [0, 1, 2].map(|c| [m[c][0], m[c][1], m[c][2]]);
+    let cofactors = [
+        cross(columns[1], columns[2]),
+        cross(columns[2], columns[0]),
+        cross(columns[0], columns[1]),
+    ];
+    let determinant: f32 = (0..3).map(|i| columns[0][i] * cofactors[0][i]).sum();
+    let sign = determinant.signum();
+    // the cofactor rows are the columns of the normal matrix
+    (cofactors.map(|c| c.map(|x| x * sign)), determinant < 0.0)
+}
+
+fn multiply_3(columns: [[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|row| (0..3).map(|c| columns[c][row] * v[c]).sum())
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    match length > 0.0 {
+        true => v.map(|x| x / length),
+        false => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a triangle in the xy plane and its u16 indices, padded to 4 bytes
+    fn triangle_buffer() -> Vec<u8> {
+        let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let mut data: Vec<u8> = bytemuck::cast_slice(&positions).to_vec();
+        data.extend(bytemuck::cast_slice(&[0u16, 1, 2, 0]));
+        data
+    }
+
+    fn gltf_json(buffer: &str, node: &str) -> String {
+        format!(
+            r#"{{
+  "asset": {{ "version": "2.0" }},
+  "scene": 0,
+  "scenes": [{{ "nodes": [0] }}],
+  "nodes": [{{ "mesh": 0, {node} }}],
+  "meshes": [{{
+    "name": "tri",
+    "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}]
+  }}],
+  "materials": [{{
+    "name": "paint",
+    "emissiveFactor": [0.5, 0, 0],
+    "pbrMetallicRoughness": {{
+      "baseColorFactor": [1, 0.5, 0.25, 1],
+      "baseColorTexture": {{ "index": 0 }}
+    }}
+  }}],
+  "textures": [{{ "source": 0 }}],
+  "images": [{{ "uri": "textures/paint%20red.png" }}],
+  "buffers": [{{ {buffer} "byteLength": 44 }}],
+  "bufferViews": [
+    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+    {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+  ],
+  "accessors": [
+    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+       "min": [0, 0, 0], "max": [1, 1, 0] }},
+    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+  ]
+}}"#
+        )
+    }
+
+    fn read(path: &str) -> anyhow::Result<Vec<u8>> {
+        match path {
+            "models/tri data.bin" => Ok(triangle_buffer()),
+            _ => bail!("no file {}", path),
+        }
+    }
+
+    fn parse_gltf(node: &str) -> DecodedModel {
+        let json = gltf_json(r#""uri": "tri%20data.bin","#, node);
+        parse("models/tri.gltf", json.as_bytes(), read).unwrap()
+    }
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-5)
+    }
+
+    #[test]
+    fn nodes_are_baked_into_the_vertices() {
+        let model = parse_gltf(r#""translation": [1, 2, 3], "scale": [2, 3, 1]"#);
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.name, "tri");
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        let positions: Vec<_> = mesh.vertices.iter().map(|v| v.position).collect();
+        assert_eq!(
+            positions,
+            [[1.0, 2.0, 3.0], [3.0, 2.0, 3.0], [1.0, 5.0, 3.0]]
+        );
+        // no normals in the file, the face's is used
+        for vertex in mesh.vertices.iter() {
+            assert!(close(vertex.normal, [0.0, 0.0, 1.0]));
+        }
+    }
+
+    #[test]
+    fn mirrored_nodes_keep_their_triangles_counter_clockwise() {
+        let model = parse_gltf(r#""scale": [-1, 1, 1]"#);
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.indices, [0, 2, 1]);
+        for vertex in mesh.vertices.iter() {
+            assert!(close(vertex.normal, [0.0, 0.0, 1.0]));
+        }
+    }
+
+    #[test]
+    fn materials_point_at_textures_next_to_the_model() {
+        let model = parse_gltf(r#""name": "node""#);
+        assert_eq!(model.meshes[0].material, Some(0));
+        assert_eq!(
+            model.materials,
+            [MeshMaterial {
+                name: "paint".to_string(),
+                base_color: [1.0, 0.5, 0.25, 1.0],
+                emissive: [0.5, 0.0, 0.0],
+                texture: Some("models/textures/paint red.png".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn buffers_can_be_data_uris() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(triangle_buffer());
+        let buffer = format!(
+            r#""uri": "data:application/octet-stream;base64,{}","#,
+            encoded
+        );
+        let json = gltf_json(&buffer, r#""name": "node""#);
+        let model = parse("models/tri.gltf", json.as_bytes(), |path| {
+            bail!("read {}", path)
+        })
+        .unwrap();
+        assert_eq!(model.triangle_count(), 1);
+    }
+
+    #[test]
+    fn glb_reads_its_binary_chunk() {
+        let mut json = gltf_json("", r#""name": "node""#).into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let bin = triangle_buffer();
+
+        let mut glb = b"glTF".to_vec();
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(b"JSON");
+        glb.extend(&json);
+        glb.extend((bin.len() as u32).to_le_bytes());
+        glb.extend(b"BIN\0");
+        glb.extend(&bin);
+
+        let model = parse("models/tri.glb", &glb, read).unwrap();
+        assert_eq!(model.meshes[0].vertices[1].position, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn missing_buffers_are_errors() {
+        let json = gltf_json(r#""uri": "gone.bin","#, r#""name": "node""#);
+        assert!(parse("models/tri.gltf", json.as_bytes(), read).is_err());
+    }
+}
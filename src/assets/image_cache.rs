@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use image::codecs::qoi::{QoiDecoder, QoiEncoder};
+use image::{ExtendedColorType, ImageDecoder, ImageEncoder};
+use log::{debug, info, warn};
+
+use crate::assets::DecodedImage;
+use crate::assets::MipLevels;
+use crate::assets::PixelFormat;
+use crate::assets::meta::TextureMeta;
+
+const DEFAULT_LIMIT: u64 = 64 * 1024 * 1024;
+
+static CACHE: Mutex<Option<ImageCache>> = Mutex::new(None);
+
+// a decoded image kept as qoi, a few times smaller than rgba and far quicker
+// to get back than decoding the png again
+struct CachedImage {
+    qoi: Vec<u8>,
+    dimensions: (u32, u32),
+    meta: TextureMeta,
+    mips: MipLevels,
+    modified: Option<SystemTime>, // of the loose file, a newer one is decoded again
+    last_used: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImageCacheStats {
+    pub images: usize,
+    pub bytes: u64, // compressed
+    pub limit: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// decoded textures by name, so a texture evicted from vram or a pool loaded
+// again doesn't go back to the disk and the decoder. the least recently used
+// go once the compressed size is over the limit
+struct ImageCache {
+    limit: u64, // 0 caches nothing
+    images: HashMap<String, CachedImage>,
+    bytes: u64,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+fn cache() -> MutexGuard<'static, Option<ImageCache>> {
+    // a panic while holding it left nothing half written that matters
+    CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn with_cache<R>(f: impl FnOnce(&mut ImageCache) -> R) -> R {
+    let mut cache = cache();
+    f(cache.get_or_insert_with(|| ImageCache {
+        limit: DEFAULT_LIMIT,
+        images: HashMap::new(),
+        bytes: 0,
+        clock: 0,
+        hits: 0,
+        misses: 0,
+    }))
+}
+
+pub fn set_limit(bytes: u64) {
+    info!("image cache limited to {} bytes", bytes);
+    with_cache(|cache| {
+        cache.limit = bytes;
+        cache.trim();
+    });
+}
+
+pub fn stats() -> ImageCacheStats {
+    with_cache(|cache| ImageCacheStats {
+        images: cache.images.len(),
+        bytes: cache.bytes,
+        limit: cache.limit,
+        hits: cache.hits,
+        misses: cache.misses,
+    })
+}
+
+// None for images that aren't loose files, packed and remote ones don't change
+fn source_modified(name: &str) -> Option<SystemTime> {
+    std::fs::metadata(format!("assets/{}", name))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// the image as it was last decoded, None when it isn't cached or its file changed
+pub fn get(name: &str) -> Option<DecodedImage> {
+    let modified = source_modified(name);
+    let cached = with_cache(|cache| {
+        cache.clock += 1;
+        let clock = cache.clock;
+        match cache.images.get_mut(name) {
+            Some(image) if image.modified != modified => {
+                debug!("{} changed since it was cached", name);
+                cache.misses += 1;
+                if let Some(old) = cache.images.remove(name) {
+                    cache.bytes -= old.qoi.len() as u64;
+                }
+                None
+            }
+            Some(image) => {
+                image.last_used = clock;
+                cache.hits += 1;
+                Some((
+                    image.qoi.clone(),
+                    image.dimensions,
+                    image.meta.clone(),
+                    image.mips,
+                ))
+            }
+            None => {
+                cache.misses += 1;
+                None
+            }
+        }
+    })?;
+
+    // decoded outside the lock, loader tasks share the cache
+    let (qoi, dimensions, meta, mips) = cached;
+    let rgba = match decode(&qoi) {
+        Ok(rgba) => rgba,
+        Err(e) => {
+            warn!("failed to decode the cached copy of {}: {:#}", name, e);
+            forget(name);
+            return None;
+        }
+    };
+    Some(DecodedImage {
+        name: name.to_string(),
+        rgba,
+        dimensions,
+        format: PixelFormat::Rgba8,
+        meta,
+        mips,
+    })
+}
+
+pub fn insert(image: &DecodedImage) {
+    let limit = with_cache(|cache| cache.limit);
+    let (width, height) = image.dimensions;
+    // qoi has no float pixels, hdr images are decoded every time
+    if limit == 0
+        || image.format != PixelFormat::Rgba8
+        || image.rgba.len() as u64 != width as u64 * height as u64 * 4
+    {
+        return;
+    }
+
+    let mut qoi = Vec::new();
+    let encoded =
+        QoiEncoder::new(&mut qoi).write_image(&image.rgba, width, height, ExtendedColorType::Rgba8);
+    if let Err(e) = encoded {
+        debug!("not caching {}: {}", image.name, e);
+        return;
+    }
+    if qoi.len() as u64 > limit {
+        return;
+    }
+
+    with_cache(|cache| {
+        cache.clock += 1;
+        let cached = CachedImage {
+            qoi,
+            dimensions: image.dimensions,
+            meta: image.meta.clone(),
+            mips: image.mips,
+            modified: source_modified(&image.name),
+            last_used: cache.clock,
+        };
+        cache.bytes += cached.qoi.len() as u64;
+        if let Some(old) = cache.images.insert(image.name.clone(), cached) {
+            cache.bytes -= old.qoi.len() as u64;
+        }
+        cache.trim();
+    });
+}
+
+// the source changed, e.g. its import settings were edited
+pub fn forget(name: &str) {
+    with_cache(|cache| {
+        if let Some(old) = cache.images.remove(name) {
+            cache.bytes -= old.qoi.len() as u64;
+        }
+    });
+}
+
+pub fn clear() {
+    with_cache(|cache| {
+        cache.images.clear();
+        cache.bytes = 0;
+    });
+}
+
+impl ImageCache {
+    fn trim(&mut self) {
+        while self.bytes > self.limit {
+            let Some(oldest) = self
+                .images
+                .iter()
+                .min_by_key(|(_, image)| image.last_used)
+                .map(|(name, _)| name.clone())
+            else {
+                break;
+            };
+            if let Some(image) = self.images.remove(&oldest) {
+                self.bytes -= image.qoi.len() as u64;
+            }
+        }
+    }
+}
+
+fn decode(qoi: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let decoder = QoiDecoder::new(Cursor::new(qoi))?;
+    let mut rgba = vec![0; decoder.total_bytes() as usize];
+    decoder.read_image(&mut rgba)?;
+    Ok(rgba)
+}
@@ -0,0 +1,103 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use log::info;
+
+use crate::assets::sound::DecodedSound;
+use crate::assets::{DecodedImage, remote};
+use crate::tasks;
+
+enum Decoded {
+    Image(usize, DecodedImage),
+    Sound(usize, DecodedSound),
+}
+
+// decodes one pool's textures and sounds on the task pool, one task per asset.
+// remote ones download on the io workers
+pub(super) struct LoadJob {
+    pub pool: usize,
+    pub total: usize,
+    images: Vec<Option<DecodedImage>>,
+    sounds: Vec<Option<DecodedSound>>,
+    receiver: Receiver<Decoded>,
+    received: usize,
+}
+
+impl LoadJob {
+    pub fn spawn(pool: usize, paths: Vec<String>, sound_paths: Vec<String>) -> LoadJob {
+        info!(
+            "loading pool {} with {} textures and {} sounds",
+            pool,
+            paths.len(),
+            sound_paths.len()
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let images = paths.len();
+        let sounds = sound_paths.len();
+
+        for (i, path) in paths.into_iter().enumerate() {
+            let sender = sender.clone();
+            tasks::pool().execute(remote::task_kind(&path), move || {
+                // a dropped job doesn't want it anymore
+                _ = sender.send(Decoded::Image(i, DecodedImage::open_or_placeholder(&path)));
+            });
+        }
+        for (i, path) in sound_paths.into_iter().enumerate() {
+            let sender = sender.clone();
+            tasks::pool().execute(remote::task_kind(&path), move || {
+                _ = sender.send(Decoded::Sound(i, DecodedSound::open_or_silent(&path)));
+            });
+        }
+
+        LoadJob {
+            pool,
+            total: images + sounds,
+            images: (0..images).map(|_| None).collect(),
+            sounds: (0..sounds).map(|_| None).collect(),
+            receiver,
+            received: 0,
+        }
+    }
+
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    // returns true once every asset has arrived
+    pub fn poll(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Decoded::Image(i, image)) => {
+                    self.images[i] = Some(image);
+                    self.received += 1;
+                }
+                Ok(Decoded::Sound(i, sound)) => {
+                    self.sounds[i] = Some(sound);
+                    self.received += 1;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // loader died, fill the rest so the ids stay stable
+                    for image in self.images.iter_mut().filter(|i| i.is_none()) {
+                        *image = Some(DecodedImage::placeholder("missing"));
+                        self.received += 1;
+                    }
+                    for sound in self.sounds.iter_mut().filter(|s| s.is_none()) {
+                        *sound = Some(DecodedSound::silent("missing"));
+                        self.received += 1;
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.received >= self.total
+    }
+
+    pub fn into_assets(self) -> (Vec<DecodedImage>, Vec<DecodedSound>) {
+        (
+            self.images.into_iter().flatten().collect(),
+            self.sounds.into_iter().flatten().collect(),
+        )
+    }
+}
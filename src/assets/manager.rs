@@ -1,42 +1,256 @@
+use log::{error, info, warn};
+
+use crate::assets::DecodedImage;
+use crate::assets::discovery::{AssetIndex, AssetKind, DiscoveryMode};
+use crate::assets::loader::LoadJob;
+use crate::assets::manifest::AssetManifest;
+use crate::assets::pak;
+use crate::assets::sound::DecodedSound;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PoolState {
+    Registered,
+    Queued,
+    Loading,
+    Loaded,
+}
+
 pub struct AssetPool {
+    pub id: usize,
     pub textures: Vec<String>,
+    pub sounds: Vec<String>, // ids are separate from the texture ids
+    pub state: PoolState,
+}
+
+// a pool whose assets finished decoding, textures go to the renderer and sounds to audio
+pub struct LoadedPool {
+    pub id: usize,
+    pub images: Vec<DecodedImage>,
+    pub sounds: Vec<DecodedSound>,
 }
 
 impl AssetPool {
-    pub fn new() -> Self {
+    pub fn new(id: usize) -> Self {
         AssetPool {
+            id,
             textures: Vec::new(),
+            sounds: Vec::new(),
+            state: PoolState::Registered,
         }
     }
 
     pub fn register_texture(&mut self, path: &str) -> usize {
-        let full_path = format!("textures/{}", path);
+        self.register_path(&format!("textures/{}", path))
+    }
+
+    // path relative to the asset directory
+    pub fn register_path(&mut self, path: &str) -> usize {
         let id = self.textures.len();
 
-        self.textures.push(full_path);
+        self.textures.push(path.to_string());
         id
     }
 
-    pub fn unregister_texture(&mut self, id: usize) {
-        self.textures.remove(id);
+    // path relative to the asset directory
+    pub fn register_sound_path(&mut self, path: &str) -> usize {
+        self.sounds.push(path.to_string());
+        self.sounds.len() - 1
     }
 }
 
 pub struct AssetManager {
     asset_pools: Vec<AssetPool>,
+    manifest: AssetManifest,
+    index: AssetIndex,
+    discovery: DiscoveryMode,
+    discovered_pool: Option<usize>, // holds the sounds asked for by path
+    jobs: Vec<LoadJob>,
+
+    // progress of the current batch of queued pools
+    batch_total: usize,
+    batch_done: usize,
 }
 
 impl AssetManager {
-    pub fn new() -> AssetManager {
+    pub fn new(discovery: DiscoveryMode) -> AssetManager {
+        let mut manifest = AssetManifest::load();
+        match discovery {
+            DiscoveryMode::Scan => {
+                if manifest.scan()
+                    && let Err(e) = manifest.save()
+                {
+                    error!("failed to save asset manifest: {:#}", e);
+                }
+            }
+            DiscoveryMode::Manifest if manifest.is_empty() && pak::mounted().is_none() => {
+                warn!(
+                    "no assets in {}, run nivalis-cli manifest or set asset_discovery = \"scan\" in the settings",
+                    AssetManifest::path()
+                );
+            }
+            DiscoveryMode::Manifest => {}
+        }
+        let index = AssetIndex::build(&manifest);
+
         AssetManager {
             asset_pools: Vec::new(),
+            manifest,
+            index,
+            discovery,
+            discovered_pool: None,
+            jobs: Vec::new(),
+            batch_total: 0,
+            batch_done: 0,
         }
     }
 
     pub fn create_pool(&mut self) -> &mut AssetPool {
         let id = self.asset_pools.len();
 
-        self.asset_pools.push(AssetPool::new());
+        self.asset_pools.push(AssetPool::new(id));
         self.asset_pools.get_mut(id).unwrap()
     }
+
+    pub fn pool_mut(&mut self, id: usize) -> Option<&mut AssetPool> {
+        self.asset_pools.get_mut(id)
+    }
+
+    pub fn pools(&self) -> &[AssetPool] {
+        &self.asset_pools
+    }
+
+    pub fn manifest(&self) -> &AssetManifest {
+        &self.manifest
+    }
+
+    pub fn manifest_mut(&mut self) -> &mut AssetManifest {
+        &mut self.manifest
+    }
+
+    pub fn discovery_mode(&self) -> DiscoveryMode {
+        self.discovery
+    }
+
+    pub fn index(&self) -> &AssetIndex {
+        &self.index
+    }
+
+    // picks up assets imported or registered in the manifest since startup
+    pub fn refresh_index(&mut self) {
+        self.index = AssetIndex::build(&self.manifest);
+    }
+
+    // the loader path of an asset by its logical path, e.g. textures/cat for
+    // textures/cat.png, None when it isn't there or is another kind
+    pub fn resolve(&self, path: &str, kind: AssetKind) -> Option<&str> {
+        match self.index.resolve(path) {
+            Some(asset) if asset.kind == kind => Some(&asset.path),
+            Some(asset) => {
+                warn!("{} is a {:?}, not a {:?}", path, asset.kind, kind);
+                None
+            }
+            None => {
+                warn!("no asset {}", path);
+                None
+            }
+        }
+    }
+
+    // the pool sounds asked for by path go into, audio decodes them on first
+    // use instead of a load job, so it counts as loaded from the start
+    pub fn discovered_pool(&mut self) -> usize {
+        if let Some(pool) = self.discovered_pool {
+            return pool;
+        }
+        let pool = self.create_pool();
+        pool.state = PoolState::Loaded;
+        let id = pool.id;
+        self.discovered_pool = Some(id);
+        id
+    }
+
+    pub fn queue_pool(&mut self, id: usize) {
+        if let Some(pool) = self.asset_pools.get_mut(id)
+            && pool.state == PoolState::Registered
+        {
+            pool.state = PoolState::Queued;
+        }
+    }
+
+    // the renderer and audio drop the pool's assets, loading it again re-decodes them
+    pub fn unload_pool(&mut self, id: usize) -> bool {
+        match self.asset_pools.get_mut(id) {
+            Some(pool) if pool.state == PoolState::Loaded => {
+                info!("unloading asset pool {}", id);
+                pool.state = PoolState::Registered;
+                true
+            }
+            Some(pool) => {
+                warn!("asset pool {} is {:?}, not unloading it", id, pool.state);
+                false
+            }
+            None => false,
+        }
+    }
+
+    // starts a background job for every queued pool
+    pub fn load_queued(&mut self) {
+        for (id, pool) in self.asset_pools.iter_mut().enumerate() {
+            if pool.state != PoolState::Queued {
+                continue;
+            }
+
+            pool.state = PoolState::Loading;
+            self.batch_total += pool.textures.len() + pool.sounds.len();
+            self.jobs.push(LoadJob::spawn(
+                id,
+                pool.textures.clone(),
+                pool.sounds.clone(),
+            ));
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.jobs.is_empty()
+    }
+
+    // returns the pools that finished decoding since the last poll
+    pub fn poll(&mut self) -> Vec<LoadedPool> {
+        let mut loaded = Vec::new();
+        let mut i = 0;
+
+        while i < self.jobs.len() {
+            if !self.jobs[i].poll() {
+                i += 1;
+                continue;
+            }
+
+            let job = self.jobs.swap_remove(i);
+            self.batch_done += job.total;
+            if let Some(pool) = self.asset_pools.get_mut(job.pool) {
+                pool.state = PoolState::Loaded;
+            }
+
+            let id = job.pool;
+            let (images, sounds) = job.into_assets();
+            loaded.push(LoadedPool { id, images, sounds });
+        }
+
+        if self.jobs.is_empty() {
+            self.batch_total = 0;
+            self.batch_done = 0;
+        }
+
+        loaded
+    }
+
+    // 0..1 over every asset in the current batch
+    pub fn progress(&self) -> f32 {
+        if self.batch_total == 0 {
+            return 1.0;
+        }
+
+        let in_flight: usize = self.jobs.iter().map(|job| job.received()).sum();
+        (self.batch_done + in_flight) as f32 / self.batch_total as f32
+    }
 }
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::assets::atlas::AtlasRegions;
+use crate::assets::meta::TextureMeta;
+
+const ASSET_DIR: &str = "assets";
+const MANIFEST_FILE: &str = "manifest.toml";
+
+// stable id of an asset, survives renaming and moving the file
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AssetId(Uuid);
+
+impl AssetId {
+    pub fn new() -> Self {
+        AssetId(Uuid::new_v4())
+    }
+
+    pub fn parse(id: &str) -> Option<Self> {
+        Uuid::parse_str(id).ok().map(AssetId)
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// maps asset ids to paths relative to the asset directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct AssetManifest {
+    assets: BTreeMap<AssetId, String>,
+}
+
+impl AssetManifest {
+    pub fn path() -> String {
+        format!("{}/{}", ASSET_DIR, MANIFEST_FILE)
+    }
+
+    // an empty manifest when there is none yet
+    pub fn load() -> AssetManifest {
+        let path = Self::path();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                info!("no asset manifest at {}", path);
+                return AssetManifest::default();
+            }
+        };
+
+        match toml::from_str(&contents) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!("failed to parse {}: {}", path, e);
+                AssetManifest::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path();
+        fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path))?;
+
+        info!("saved asset manifest to {}", path);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (AssetId, &str)> {
+        self.assets.iter().map(|(id, path)| (*id, path.as_str()))
+    }
+
+    pub fn path_of(&self, id: AssetId) -> Option<&str> {
+        self.assets.get(&id).map(String::as_str)
+    }
+
+    pub fn id_of(&self, path: &str) -> Option<AssetId> {
+        self.assets
+            .iter()
+            .find(|(_, asset_path)| asset_path.as_str() == path)
+            .map(|(id, _)| *id)
+    }
+
+    // returns the existing id when the path is already known
+    pub fn register(&mut self, path: &str) -> AssetId {
+        if let Some(id) = self.id_of(path) {
+            return id;
+        }
+
+        let id = AssetId::new();
+        info!("registered asset {} as {}", path, id);
+        self.assets.insert(id, path.to_string());
+        id
+    }
+
+    // points an id at a moved or renamed file
+    pub fn relocate(&mut self, id: AssetId, path: &str) -> bool {
+        match self.assets.get_mut(&id) {
+            Some(asset_path) => {
+                info!("moved asset {} from {} to {}", id, asset_path, path);
+                *asset_path = path.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // copies a file from anywhere into a folder of the asset directory and
+    // registers it, returns the path pools know it by
+    pub fn import(&mut self, source: &Path, folder: &str) -> anyhow::Result<String> {
+        let name = source
+            .file_name()
+            .with_context(|| format!("{} is not a file", source.display()))?
+            .to_string_lossy();
+        let path = format!("{}/{}", folder, name);
+        let target = Path::new(ASSET_DIR).join(&path);
+        if target.exists() {
+            anyhow::bail!("{} already exists", target.display());
+        }
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        fs::copy(source, &target)
+            .with_context(|| format!("failed to copy {}", source.display()))?;
+
+        info!("imported {} as {}", source.display(), path);
+        self.register(&path);
+        Ok(path)
+    }
+
+    pub fn remove(&mut self, id: AssetId) -> Option<String> {
+        self.assets.remove(&id)
+    }
+
+    // registers every unlisted file in the asset directory, true when anything changed
+    pub fn scan(&mut self) -> bool {
+        let mut files = Vec::new();
+        collect_files(Path::new(ASSET_DIR), &mut files);
+
+        let before = self.assets.len();
+        for file in files {
+            self.register(&file);
+        }
+
+        for (id, path) in self.assets.iter() {
+            if !Path::new(ASSET_DIR).join(path).exists() {
+                warn!("asset {} points at missing file {}", id, path);
+            }
+        }
+
+        self.assets.len() != before
+    }
+}
+
+// paths relative to the asset directory, with forward slashes on every platform
+fn collect_files(dir: &Path, files: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("failed to read {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+            continue;
+        }
+
+        // import settings belong to their asset, not assets of their own
+        if path.file_name().is_some_and(|name| name == MANIFEST_FILE)
+            || TextureMeta::is_sidecar(&path.to_string_lossy())
+            || AtlasRegions::is_sidecar(&path.to_string_lossy())
+        {
+            continue;
+        }
+
+        if let Ok(relative) = path.strip_prefix(ASSET_DIR) {
+            let parts: Vec<_> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect();
+            files.push(parts.join("/"));
+        }
+    }
+
+    files.sort();
+}
@@ -0,0 +1,151 @@
+use std::fs;
+
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::pak;
+use crate::world::collision::ColliderShape;
+
+const META_EXTENSION: &str = "meta";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    Linear, // data textures like normal maps
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterMode {
+    #[default]
+    Nearest, // crisp pixel art, what textures always used
+    Linear,
+}
+
+// import options read from a `<asset>.meta` file next to the asset
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextureMeta {
+    pub color_space: ColorSpace,
+    pub filter: FilterMode,
+    pub atlas: bool,     // pack into a shared atlas instead of its own texture
+    pub pivot: [f32; 2], // 0..1 from the top left, default origin for sprites
+    pub indexed: bool,   // red holds a palette column, see renderer/palette.rs
+    // pixels of exactly this color turn transparent, for art without alpha
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_key: Option<[u8; 3]>,
+    // 9-slice borders in texture pixels: left, top, right, bottom
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slice: Option<[u32; 4]>,
+    // in texture pixels from the top left, see collider_shapes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shapes: Vec<ColliderShape>,
+}
+
+impl Default for TextureMeta {
+    fn default() -> Self {
+        TextureMeta {
+            color_space: ColorSpace::Srgb,
+            filter: FilterMode::Nearest,
+            atlas: false,
+            pivot: [0.5, 0.5],
+            indexed: false,
+            color_key: None,
+            slice: None,
+            shapes: Vec::new(),
+        }
+    }
+}
+
+impl TextureMeta {
+    // asset path is relative to the asset directory
+    pub fn sidecar_path(asset_path: &str) -> String {
+        format!("assets/{}.{}", asset_path, META_EXTENSION)
+    }
+
+    pub fn is_sidecar(path: &str) -> bool {
+        path.ends_with(&format!(".{}", META_EXTENSION))
+    }
+
+    // defaults when the asset has no sidecar
+    pub fn load(asset_path: &str) -> TextureMeta {
+        let path = Self::sidecar_path(asset_path);
+        let packed = pak::mounted()
+            .and_then(|pak| pak.read(&format!("{}.{}", asset_path, META_EXTENSION)))
+            .and_then(|data| String::from_utf8(data).ok());
+
+        let Some(contents) = packed.or_else(|| fs::read_to_string(&path).ok()) else {
+            return TextureMeta::default();
+        };
+
+        match toml::from_str(&contents) {
+            Ok(meta) => meta,
+            Err(e) => {
+                warn!("failed to parse {}: {}", path, e);
+                TextureMeta::default()
+            }
+        }
+    }
+
+    pub fn save(&self, asset_path: &str) -> anyhow::Result<()> {
+        let path = Self::sidecar_path(asset_path);
+        // inline arrays, pretty printing puts every polygon number on its own line
+        fs::write(&path, toml::to_string(self)?)
+            .with_context(|| format!("failed to write {}", path))?;
+
+        info!("saved import settings to {}", path);
+        Ok(())
+    }
+
+    // the shapes for a sprite drawing the texture over rect, x, y, width, height
+    // in world pixels
+    #[allow(dead_code)] // the game adds them to its CollisionWorld
+    pub fn collider_shapes(&self, texture_size: [f32; 2], rect: [f32; 4]) -> Vec<ColliderShape> {
+        let scale = [rect[2] / texture_size[0], rect[3] / texture_size[1]];
+        let place = |[x, y]: [f32; 2]| [rect[0] + x * scale[0], rect[1] + y * scale[1]];
+        self.shapes
+            .iter()
+            .map(|shape| match shape {
+                ColliderShape::Rect([x, y, w, h]) => {
+                    let [x, y] = place([*x, *y]);
+                    ColliderShape::Rect([x, y, w * scale[0], h * scale[1]])
+                }
+                ColliderShape::Polygon(points) => {
+                    ColliderShape::Polygon(points.iter().map(|&point| place(point)).collect())
+                }
+            })
+            .collect()
+    }
+
+    // clears the color and alpha of keyed pixels, the color too so filtering
+    // doesn't bleed it into the edges
+    pub fn apply_color_key(&self, rgba: &mut [u8]) {
+        let Some(key) = self.color_key else {
+            return;
+        };
+        for pixel in rgba.chunks_exact_mut(4) {
+            if pixel[..3] == key {
+                pixel.fill(0);
+            }
+        }
+    }
+
+    // indices have to reach the shader unconverted
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match self.color_space {
+            _ if self.indexed => wgpu::TextureFormat::Rgba8Unorm,
+            ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    pub fn filter(&self) -> wgpu::FilterMode {
+        match self.filter {
+            FilterMode::Linear => wgpu::FilterMode::Linear,
+            FilterMode::Nearest => wgpu::FilterMode::Nearest,
+        }
+    }
+}
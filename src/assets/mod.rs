@@ -1,48 +1,273 @@
-use std::collections::HashMap;
+use image::{ColorType, GenericImageView};
+use log::{debug, error};
 
-use image::GenericImageView;
-use log::debug;
+use crate::assets::atlas::AtlasRegions;
+use crate::assets::meta::TextureMeta;
 
+pub mod atlas;
+pub mod cubemap;
+pub mod discovery;
+mod gltf;
+pub mod image_cache;
+mod loader;
 pub mod manager;
+pub mod manifest;
+pub mod meta;
+pub mod model;
+mod obj;
+pub mod pak;
+pub mod primitives;
+pub mod remote;
+pub mod sound;
+
+// built in 1x1 white texture for solid colored sprites, tinted by the sprite color
+pub const WHITE_TEXTURE: &str = "builtin/white";
 
 pub struct NvTexturePool {
-    pub textures: Vec<NvTexture>,
+    pub textures: Vec<NvTextureSlot>,
     pub layout: wgpu::BindGroupLayout,
 }
 
+// a pool texture that can be evicted from vram and reloaded from its source
+pub struct NvTextureSlot {
+    pub name: String,
+    pub bytes: u64,
+    pub last_used: u64, // frame it was last drawn in
+    pub meta: TextureMeta,
+    pub regions: Option<AtlasRegions>, // when the texture is a sprite sheet
+    pub texture: Option<NvTexture>,
+}
+
+impl NvTextureSlot {
+    pub fn resident(image: &DecodedImage, texture: NvTexture) -> Self {
+        NvTextureSlot {
+            name: image.name.clone(),
+            bytes: image.bytes(),
+            last_used: 0,
+            meta: image.meta.clone(),
+            regions: AtlasRegions::load(&image.name),
+            texture: Some(texture),
+        }
+    }
+
+    // not decoded yet, the first draw loads it like an evicted texture
+    pub fn pending(texture_name: &str) -> Self {
+        NvTextureSlot {
+            name: texture_name.to_string(),
+            bytes: 0,
+            last_used: 0,
+            meta: TextureMeta::load(texture_name),
+            regions: AtlasRegions::load(texture_name),
+            texture: None,
+        }
+    }
+
+    pub fn used_in(mut self, frame: u64) -> Self {
+        self.last_used = frame;
+        self
+    }
+}
+
 pub struct NvTexture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
     pub bind_group: wgpu::BindGroup,
+    pub resident_mip: u32, // most detailed level uploaded so far
+    pub coarsest_mip: u32, // the level it was created with, levels below it are empty
+    pub mip_count: u32,
+}
+
+// the part of a texture's mip chain an image holds. large packed textures start
+// with a small level and stream in the rest, see renderer/streaming.rs
+#[derive(Clone, Copy, Debug)]
+pub struct MipLevels {
+    pub level: u32, // the level the pixels are for
+    pub count: u32, // levels in the full chain
+    pub base: (u32, u32),
+}
+
+impl MipLevels {
+    pub fn single(dimensions: (u32, u32)) -> Self {
+        MipLevels {
+            level: 0,
+            count: 1,
+            base: dimensions,
+        }
+    }
+
+    pub fn size(&self, level: u32) -> (u32, u32) {
+        ((self.base.0 >> level).max(1), (self.base.1 >> level).max(1))
+    }
+
+    // vram taken by the whole chain, resident or not
+    pub fn bytes(&self) -> u64 {
+        (0..self.count)
+            .map(|level| {
+                let (width, height) = self.size(level);
+                width as u64 * height as u64 * 4
+            })
+            .sum()
+    }
+}
+
+// how the pixels of a decoded image are stored
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PixelFormat {
+    #[default]
+    Rgba8,
+    Rgba16Float, // .hdr and .exr images, linear and brighter than white
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> u32 {
+        match self {
+            PixelFormat::Rgba8 => 4,
+            PixelFormat::Rgba16Float => 8,
+        }
+    }
+
+    // float images ignore the color space, they're always linear
+    pub fn texture_format(self, meta: &TextureMeta) -> wgpu::TextureFormat {
+        match self {
+            PixelFormat::Rgba8 => meta.format(),
+            PixelFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+}
+
+// cpu side image, decoded off the render thread
+pub struct DecodedImage {
+    pub name: String,
+    pub rgba: Vec<u8>,
+    pub dimensions: (u32, u32), // of the level in rgba
+    pub format: PixelFormat,
+    pub meta: TextureMeta,
+    pub mips: MipLevels,
+}
+
+impl DecodedImage {
+    // from the image cache when it was decoded before
+    pub fn open(texture_name: &str) -> anyhow::Result<Self> {
+        if texture_name == WHITE_TEXTURE {
+            return Ok(Self::solid(texture_name, [255, 255, 255, 255]));
+        }
+
+        if let Some(image) = image_cache::get(texture_name) {
+            return Ok(image);
+        }
+        let image = Self::decode(texture_name)?;
+        image_cache::insert(&image);
+        Ok(image)
+    }
+
+    fn decode(texture_name: &str) -> anyhow::Result<Self> {
+        if let Some(image) = pak::mounted().and_then(|pak| pak.image(texture_name)) {
+            return Ok(image);
+        }
+
+        // nivalis-cli packs float images as plain files
+        let bytes = match pak::mounted().and_then(|pak| pak.read(texture_name)) {
+            Some(bytes) => Some(bytes),
+            None => remote::read(texture_name).transpose()?,
+        };
+        let image = match bytes {
+            Some(bytes) => image::load_from_memory(&bytes)?,
+            None => {
+                let file = format!("assets/{}", texture_name);
+                debug!("loading texture at {}", file);
+                image::open(file)?
+            }
+        };
+        let meta = TextureMeta::load(texture_name);
+
+        // kept as half floats so skies and environments keep their range
+        if matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F) {
+            let rgba = image
+                .to_rgba32f()
+                .into_raw()
+                .into_iter()
+                .flat_map(|value| half::f16::from_f32(value).to_le_bytes())
+                .collect();
+            return Ok(DecodedImage {
+                name: texture_name.to_string(),
+                rgba,
+                dimensions: image.dimensions(),
+                format: PixelFormat::Rgba16Float,
+                meta,
+                mips: MipLevels::single(image.dimensions()),
+            });
+        }
+
+        let mut rgba = image.to_rgba8().into_raw();
+        meta.apply_color_key(&mut rgba); // packed images were keyed by nivalis-cli
+
+        Ok(DecodedImage {
+            name: texture_name.to_string(),
+            rgba,
+            dimensions: image.dimensions(),
+            format: PixelFormat::Rgba8,
+            meta,
+            mips: MipLevels::single(image.dimensions()),
+        })
+    }
+
+    // vram taken by the whole mip chain
+    pub fn bytes(&self) -> u64 {
+        self.mips.bytes() / 4 * self.format.bytes_per_pixel() as u64
+    }
+
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        self.format.texture_format(&self.meta)
+    }
+
+    pub fn solid(texture_name: &str, color: [u8; 4]) -> Self {
+        DecodedImage {
+            name: texture_name.to_string(),
+            rgba: color.to_vec(),
+            dimensions: (1, 1),
+            format: PixelFormat::Rgba8,
+            meta: TextureMeta::default(),
+            mips: MipLevels::single((1, 1)),
+        }
+    }
+
+    // magenta pixel so a missing file doesn't shift the texture ids
+    pub fn placeholder(texture_name: &str) -> Self {
+        Self::solid(texture_name, [255, 0, 255, 255])
+    }
+
+    pub fn open_or_placeholder(texture_name: &str) -> Self {
+        match Self::open(texture_name) {
+            Ok(image) => image,
+            Err(e) => {
+                error!("failed to load texture {}: {}", texture_name, e);
+                Self::placeholder(texture_name)
+            }
+        }
+    }
 }
 
 impl NvTexture {
-    pub fn from_name(
+    pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         bind_group_layout: &wgpu::BindGroupLayout,
-        texture_name: &str,
+        image: &DecodedImage,
     ) -> Self {
-        let file = format!("assets/{}", texture_name);
-        debug!("loading texture at {}", file);
-
-        let image = image::open(file).unwrap();
-        let rgba = image.to_rgba8();
-        let dimensions = image.dimensions();
-
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
+        let texture_name = image.name.as_str();
+        let mips = image.mips;
 
         let texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,                          // mip mapping
-            sample_count: 1,                             // multisampling
-            dimension: wgpu::TextureDimension::D2,       // 2d image
-            format: wgpu::TextureFormat::Rgba8UnormSrgb, // using rgba8
+            size: wgpu::Extent3d {
+                width: mips.base.0,
+                height: mips.base.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: mips.count,           // mip mapping
+            sample_count: 1,                       // multisampling
+            dimension: wgpu::TextureDimension::D2, // 2d image
+            format: image.texture_format(),        // rgba8, srgb unless linear
             // TEXTURE_BINDING tells wgpu that we want to use this texture in shaders
             // COPY_DST means that we want to copy data to this texture
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
@@ -50,45 +275,125 @@ impl NvTexture {
             view_formats: &[],
         });
 
-        queue.write_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &rgba,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            texture_size,
-        );
-
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-            label: Some(&format!("{}_bind_group", texture_name)),
+        let filter = image.meta.filter();
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(&format!("{}_sampler", texture_name)),
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            ..Default::default()
         });
 
+        write_mip(queue, &texture, mips.level, image.dimensions, &image.rgba);
+        let (view, bind_group) = bind_levels(
+            device,
+            bind_group_layout,
+            texture_name,
+            &texture,
+            &sampler,
+            mips.level..mips.level + 1,
+        );
+
         NvTexture {
             texture,
             view,
             sampler,
             bind_group,
+            resident_mip: mips.level,
+            coarsest_mip: mips.level,
+            mip_count: mips.count,
         }
     }
+
+    // fills in the next more detailed level, rgba sized for it
+    pub fn upload_mip(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        texture_name: &str,
+        level: u32,
+        rgba: &[u8],
+    ) {
+        let size = self
+            .texture
+            .size()
+            .mip_level_size(level, wgpu::TextureDimension::D2);
+        write_mip(queue, &self.texture, level, (size.width, size.height), rgba);
+
+        let (view, bind_group) = bind_levels(
+            device,
+            bind_group_layout,
+            texture_name,
+            &self.texture,
+            &self.sampler,
+            level..self.coarsest_mip + 1,
+        );
+        self.view = view;
+        self.bind_group = bind_group;
+        self.resident_mip = level;
+    }
+}
+
+fn write_mip(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    level: u32,
+    dimensions: (u32, u32),
+    rgba: &[u8],
+) {
+    let bytes_per_pixel = texture.format().block_copy_size(None).unwrap_or(4);
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        rgba,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(bytes_per_pixel * dimensions.0),
+            rows_per_image: Some(dimensions.1),
+        },
+        wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+// the view only covers uploaded levels so sampling never reads an empty one
+fn bind_levels(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    texture_name: &str,
+    texture: &wgpu::Texture,
+    sampler: &wgpu::Sampler,
+    levels: std::ops::Range<u32>,
+) -> (wgpu::TextureView, wgpu::BindGroup) {
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some(&format!("{}_view", texture_name)),
+        base_mip_level: levels.start,
+        mip_level_count: Some(levels.len() as u32),
+        ..Default::default()
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+        label: Some(&format!("{}_bind_group", texture_name)),
+    });
+
+    (view, bind_group)
 }
@@ -0,0 +1,113 @@
+use anyhow::{Context, bail};
+use bytemuck::{Pod, Zeroable};
+use log::debug;
+
+use crate::assets::{gltf, obj, pak, remote};
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default, Pod, Zeroable)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2], // from the top left, like textures
+}
+
+// one draw's worth of triangles, all with the same material
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Mesh {
+    pub name: String,
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,       // counter clockwise triangles
+    pub material: Option<usize>, // into the model's materials
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MeshMaterial {
+    pub name: String,
+    pub base_color: [f32; 4], // linear
+    pub emissive: [f32; 3],
+    pub texture: Option<String>, // relative to the asset directory
+}
+
+impl Default for MeshMaterial {
+    fn default() -> Self {
+        MeshMaterial {
+            name: String::new(),
+            base_color: [1.0; 4],
+            emissive: [0.0; 3],
+            texture: None,
+        }
+    }
+}
+
+// cpu side model, decoded off the render thread
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DecodedModel {
+    pub name: String,
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<MeshMaterial>,
+}
+
+impl DecodedModel {
+    // relative to the asset directory or in the pak. obj files bring their
+    // mtl libraries and gltf files their buffers along from next to them.
+    // remote models block while they download, open those with remote::spawn_open
+    pub fn open(model_name: &str) -> anyhow::Result<Self> {
+        let extension = model_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("obj") => {
+                let contents = read_text(model_name)?;
+                obj::parse(model_name, &contents, read_text)
+                    .with_context(|| format!("failed to load model {}", model_name))
+            }
+            Some("gltf" | "glb") => {
+                let bytes = read_bytes(model_name)?;
+                gltf::parse(model_name, &bytes, read_bytes)
+                    .with_context(|| format!("failed to load model {}", model_name))
+            }
+            _ => bail!("{} isn't a model format nivalis reads", model_name),
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.meshes.iter().map(|mesh| mesh.indices.len() / 3).sum()
+    }
+}
+
+fn read_text(name: &str) -> anyhow::Result<String> {
+    String::from_utf8(read_bytes(name)?).with_context(|| format!("{} isn't utf-8", name))
+}
+
+fn read_bytes(name: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = match pak::mounted().and_then(|pak| pak.read(name)) {
+        Some(bytes) => Some(bytes),
+        None => remote::read(name).transpose()?,
+    };
+    match bytes {
+        Some(bytes) => Ok(bytes),
+        None => {
+            let file = format!("assets/{}", name);
+            debug!("loading model at {}", file);
+            std::fs::read(&file).with_context(|| format!("failed to read {}", file))
+        }
+    }
+}
+
+// a path written in a file, relative to that file, as an asset path
+pub(super) fn sibling_path(of: &str, relative: &str) -> String {
+    let relative = relative.replace('\\', "/");
+    let mut parts: Vec<&str> = of.split('/').collect();
+    parts.pop();
+    for part in relative.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
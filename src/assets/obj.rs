@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, bail};
+use log::warn;
+
+use crate::assets::model::{DecodedModel, Mesh, MeshMaterial, MeshVertex, sibling_path};
+
+// position, uv and normal of a face corner, 0 based
+type Corner = (usize, Option<usize>, Option<usize>);
+
+// the triangles of one object and material so far
+#[derive(Default)]
+struct MeshBuilder {
+    mesh: Mesh,
+    corners: HashMap<Corner, u32>,
+    smooth: Vec<bool>, // no normal in the file, gets the average of its faces'
+}
+
+// the parts of the file the data needs, lines are indexed as they come
+#[derive(Default)]
+struct ObjData {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+}
+
+// wavefront obj with its mtl libraries, read through read by asset path.
+// polygons are fanned into triangles, lines and points are skipped
+pub fn parse(
+    name: &str,
+    contents: &str,
+    read: impl Fn(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<DecodedModel> {
+    let mut model = DecodedModel {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let mut data = ObjData::default();
+    let mut materials: HashMap<String, usize> = HashMap::new();
+    let mut object = String::new();
+    let mut builder = MeshBuilder::default();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let at_line = || format!("line {}", number + 1);
+
+        match keyword {
+            "v" => data.positions.push(floats(words).with_context(at_line)?),
+            "vt" => {
+                // obj puts v = 0 at the bottom
+                let [u, v] = floats(words).with_context(at_line)?;
+                data.uvs.push([u, 1.0 - v]);
+            }
+            "vn" => data.normals.push(floats(words).with_context(at_line)?),
+            "f" => {
+                let corners = words
+                    .map(|word| data.corner(word))
+                    .collect::<anyhow::Result<Vec<_>>>()
+                    .with_context(at_line)?;
+                if corners.len() < 3 {
+                    bail!("line {}: a face needs at least 3 corners", number + 1);
+                }
+                for i in 1..corners.len() - 1 {
+                    builder.triangle([corners[0], corners[i], corners[i + 1]], &data);
+                }
+            }
+            "o" | "g" => {
+                builder.finish_into(&mut model);
+                object = words.collect::<Vec<_>>().join(" ");
+                builder.mesh.name = object.clone();
+            }
+            "usemtl" => {
+                let material = words.collect::<Vec<_>>().join(" ");
+                builder.finish_into(&mut model);
+                builder.mesh.name = match object.is_empty() {
+                    true => material.clone(),
+                    false => format!("{}/{}", object, material),
+                };
+                builder.mesh.material = materials.get(&material).copied();
+                if builder.mesh.material.is_none() {
+                    warn!("{} uses material {} it doesn't define", name, material);
+                }
+            }
+            "mtllib" => {
+                for library in words {
+                    let path = sibling_path(name, library);
+                    match read(&path).and_then(|contents| parse_mtl(&path, &contents)) {
+                        Ok(library) => {
+                            for material in library {
+                                materials.insert(material.name.clone(), model.materials.len());
+                                model.materials.push(material);
+                            }
+                        }
+                        Err(e) => warn!("{:#}, {} is drawn without its materials", e, name),
+                    }
+                }
+            }
+            _ => {} // smoothing groups, lines, points and curves
+        }
+    }
+    builder.finish_into(&mut model);
+
+    if model.meshes.is_empty() {
+        bail!("{} has no faces", name);
+    }
+    Ok(model)
+}
+
+impl ObjData {
+    // v, v/vt, v//vn or v/vt/vn, 1 based or negative from the end
+    fn corner(&self, word: &str) -> anyhow::Result<Corner> {
+        let mut indices = word.split('/');
+        let position = index(indices.next(), self.positions.len())?
+            .with_context(|| format!("corner {} has no position", word))?;
+        let uv = index(indices.next(), self.uvs.len())?;
+        let normal = index(indices.next(), self.normals.len())?;
+        Ok((position, uv, normal))
+    }
+}
+
+fn index(word: Option<&str>, count: usize) -> anyhow::Result<Option<usize>> {
+    let Some(word) = word.filter(|word| !word.is_empty()) else {
+        return Ok(None);
+    };
+    let index: i64 = word
+        .parse()
+        .with_context(|| format!("bad index {}", word))?;
+    let resolved = match index {
+        1.. => index - 1,
+        ..0 => count as i64 + index,
+        0 => bail!("indices start at 1"),
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        bail!("index {} is out of range, only {} so far", index, count);
+    }
+    Ok(Some(resolved as usize))
+}
+
+// the first N numbers, vertex colors and w after them are ignored
+fn floats<'w, const N: usize>(
+    mut words: impl Iterator<Item = &'w str>,
+) -> anyhow::Result<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in values.iter_mut() {
+        let word = words.next().context("too few numbers")?;
+        *value = word
+            .parse()
+            .with_context(|| format!("bad number {}", word))?;
+    }
+    Ok(values)
+}
+
+impl MeshBuilder {
+    fn triangle(&mut self, corners: [Corner; 3], data: &ObjData) {
+        let indices = corners.map(|corner| self.vertex(corner, data));
+        let [a, b, c] = indices.map(|index| self.mesh.vertices[index as usize].position);
+        let (ab, ac) = (sub(b, a), sub(c, a));
+        // not normalized, larger faces weigh more in the average
+        let normal = [
+            ab[1] * ac[2] - ab[2] * ac[1],
+            ab[2] * ac[0] - ab[0] * ac[2],
+            ab[0] * ac[1] - ab[1] * ac[0],
+        ];
+        for index in indices {
+            if self.smooth[index as usize] {
+                let vertex = &mut self.mesh.vertices[index as usize];
+                vertex.normal = std::array::from_fn(|i| vertex.normal[i] + normal[i]);
+            }
+        }
+        self.mesh.indices.extend(indices);
+    }
+
+    fn vertex(&mut self, corner: Corner, data: &ObjData) -> u32 {
+        if let Some(&index) = self.corners.get(&corner) {
+            return index;
+        }
+        let (position, uv, normal) = corner;
+        let index = self.mesh.vertices.len() as u32;
+        self.mesh.vertices.push(MeshVertex {
+            position: data.positions[position],
+            normal: normal.map_or([0.0; 3], |normal| data.normals[normal]),
+            uv: uv.map_or([0.0; 2], |uv| data.uvs[uv]),
+        });
+        self.smooth.push(normal.is_none());
+        self.corners.insert(corner, index);
+        index
+    }
+
+    // the next mesh starts with the same name and material, o and usemtl change them
+    fn finish_into(&mut self, model: &mut DecodedModel) {
+        let mut mesh = Mesh {
+            name: self.mesh.name.clone(),
+            material: self.mesh.material,
+            ..Default::default()
+        };
+        std::mem::swap(&mut mesh, &mut self.mesh);
+        let smooth = std::mem::take(&mut self.smooth);
+        self.corners.clear();
+        if mesh.indices.is_empty() {
+            return;
+        }
+
+        for (vertex, _) in mesh.vertices.iter_mut().zip(smooth).filter(|(_, s)| *s) {
+            let length = vertex.normal.iter().map(|n| n * n).sum::<f32>().sqrt();
+            if length > 0.0 {
+                vertex.normal = vertex.normal.map(|n| n / length);
+            }
+        }
+        model.meshes.push(mesh);
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+// newmtl blocks, only what a simple unlit or pbr material maps to
+fn parse_mtl(path: &str, contents: &str) -> anyhow::Result<Vec<MeshMaterial>> {
+    let mut materials: Vec<MeshMaterial> = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            materials.push(MeshMaterial {
+                name: words.collect::<Vec<_>>().join(" "),
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+
+        let at_line = || format!("{} line {}", path, number + 1);
+        match keyword {
+            "Kd" => {
+                let [r, g, b] = floats(words).with_context(at_line)?;
+                material.base_color = [r, g, b, material.base_color[3]];
+            }
+            "d" => material.base_color[3] = floats::<1>(words).with_context(at_line)?[0],
+            "Tr" => material.base_color[3] = 1.0 - floats::<1>(words).with_context(at_line)?[0],
+            "Ke" => material.emissive = floats(words).with_context(at_line)?,
+            // options like -s come before the file name
+            "map_Kd" => material.texture = words.last().map(|file| sibling_path(path, file)),
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTL: &str = "
+newmtl red
+Kd 1 0 0
+d 0.5
+map_Kd -s 2 2 1 -bm 0.5 red.png
+
+newmtl blue # trailing comment
+Kd 0 0 1
+Tr 0.25
+";
+
+    fn read(path: &str) -> anyhow::Result<String> {
+        match path {
+            "models/box.mtl" => Ok(MTL.to_string()),
+            _ => bail!("no file {}", path),
+        }
+    }
+
+    fn parse_obj(contents: &str) -> DecodedModel {
+        parse("models/box.obj", contents, read).unwrap()
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_last_vertex() {
+        let model = parse_obj(
+            "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f -3 -2 -1
+",
+        );
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        let positions: Vec<_> = mesh.vertices.iter().map(|v| v.position).collect();
+        assert_eq!(
+            positions,
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn bad_indices_are_errors() {
+        let contents = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+        for face in ["f 0 1 2", "f 1 2 4", "f -4 1 2", "f 1 2", "f 1 2 x"] {
+            let obj = format!("{}{}", contents, face);
+            assert!(parse("models/box.obj", &obj, read).is_err(), "{}", face);
+        }
+    }
+
+    #[test]
+    fn corners_without_uvs_keep_their_normals() {
+        let model = parse_obj(
+            "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 -1
+f 1//1 2//1 3//1
+",
+        );
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.vertices.len(), 3);
+        for vertex in mesh.vertices.iter() {
+            assert_eq!(vertex.normal, [0.0, 0.0, -1.0]);
+            assert_eq!(vertex.uv, [0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn uvs_are_flipped_to_the_top_left() {
+        let model = parse_obj(
+            "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0.25
+vt 0 1
+f 1/1 2/2 3/3
+",
+        );
+        let uvs: Vec<_> = model.meshes[0].vertices.iter().map(|v| v.uv).collect();
+        assert_eq!(uvs, [[0.0, 1.0], [1.0, 0.75], [0.0, 0.0]]);
+    }
+
+    #[test]
+    fn polygons_fan_out_from_their_first_corner() {
+        let model = parse_obj(
+            "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v -1 1 0
+f 1 2 3 4 5
+",
+        );
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    #[test]
+    fn missing_normals_average_their_faces() {
+        // two faces of the same size folded square along the edge from 1 to 2
+        let model = parse_obj(
+            "
+v 0 0 0
+v 0 1 0
+v 1 0 0
+v 0 0 1
+f 1 2 3
+f 2 1 4
+",
+        );
+        let mesh = &model.meshes[0];
+        let length = |n: [f32; 3]| n.iter().map(|x| x * x).sum::<f32>().sqrt();
+        for vertex in mesh.vertices.iter() {
+            assert!((length(vertex.normal) - 1.0).abs() < 1e-5);
+        }
+        // the corners only one face uses have that face's normal
+        assert_eq!(mesh.vertices[2].normal, [0.0, 0.0, -1.0]);
+        assert_eq!(mesh.vertices[3].normal, [-1.0, 0.0, 0.0]);
+        let expected = [-1.0, 0.0, -1.0f32].map(|n| n / 2.0f32.sqrt());
+        for shared in [0, 1] {
+            let normal = mesh.vertices[shared].normal;
+            assert!((0..3).all(|i| (normal[i] - expected[i]).abs() < 1e-5));
+        }
+    }
+
+    #[test]
+    fn usemtl_starts_a_mesh_per_material() {
+        let model = parse_obj(
+            "
+mtllib box.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+o lid
+usemtl red
+f 1 2 3
+usemtl blue
+f 1 2 3
+usemtl green
+f 3 2 1
+",
+        );
+        let meshes: Vec<_> = model
+            .meshes
+            .iter()
+            .map(|mesh| (mesh.name.as_str(), mesh.material))
+            .collect();
+        assert_eq!(
+            meshes,
+            [
+                ("lid/red", Some(0)),
+                ("lid/blue", Some(1)),
+                ("lid/green", None)
+            ]
+        );
+        // each mesh has its own vertices
+        assert!(model.meshes.iter().all(|mesh| mesh.vertices.len() == 3));
+    }
+
+    #[test]
+    fn mtl_options_before_the_texture_are_skipped() {
+        let model = parse_obj("mtllib box.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        let [red, blue] = &model.materials[..] else {
+            panic!("expected two materials");
+        };
+        assert_eq!(red.name, "red");
+        assert_eq!(red.base_color, [1.0, 0.0, 0.0, 0.5]);
+        assert_eq!(red.texture.as_deref(), Some("models/red.png"));
+        assert_eq!(blue.name, "blue");
+        assert_eq!(blue.base_color, [0.0, 0.0, 1.0, 0.75]);
+        assert_eq!(blue.texture, None);
+    }
+
+    #[test]
+    fn missing_mtl_library_still_loads_the_mesh() {
+        let model = parse_obj("mtllib gone.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        assert!(model.materials.is_empty());
+        assert_eq!(model.meshes.len(), 1);
+    }
+
+    #[test]
+    fn file_without_faces_is_an_error() {
+        assert!(parse("models/box.obj", "v 0 0 0\nl 1 1\n", read).is_err());
+    }
+}
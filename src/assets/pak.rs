@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, bail};
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::assets::meta::TextureMeta;
+use crate::assets::{DecodedImage, MipLevels, PixelFormat};
+
+// written by nivalis-cli, see nivalis-cli/src/pak.rs for the layout
+const MAGIC: &[u8; 4] = b"NVPK";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 4 + 4 + 8 + 8;
+
+// larger textures load with a preview level and stream in the rest
+const STREAM_MIN_SIZE: u32 = 512;
+const PREVIEW_SIZE: u32 = 64;
+
+static MOUNTED: OnceLock<NvPak> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct PakManifest {
+    version: u32,
+    entries: Vec<PakEntry>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum PakKind {
+    Texture,
+    Raw,
+}
+
+#[derive(Deserialize)]
+struct PakEntry {
+    id: String,
+    path: String,
+    kind: PakKind,
+    offset: u64,
+    length: u64,
+    texture: Option<PakTexture>,
+}
+
+#[derive(Deserialize)]
+struct PakTexture {
+    width: u32,
+    height: u32,
+    mips: u32,
+    atlas: Option<AtlasRegion>,
+}
+
+#[derive(Deserialize)]
+struct AtlasRegion {
+    page: String,
+    rect: [u32; 4],
+}
+
+// a packed asset archive, entries are read from disk on demand
+pub struct NvPak {
+    path: PathBuf,
+    entries: Vec<PakEntry>,
+    by_path: HashMap<String, usize>,
+}
+
+impl NvPak {
+    pub fn open(path: &Path) -> anyhow::Result<NvPak> {
+        let mut file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC {
+            bail!("{} is not an nvpak archive", path.display());
+        }
+
+        let offset = u64::from_le_bytes(header[8..16].try_into()?);
+        let length = u64::from_le_bytes(header[16..24].try_into()?);
+
+        // a corrupt header shouldn't allocate whatever it claims
+        let size = file.metadata()?.len();
+        if offset.checked_add(length).is_none_or(|end| end > size) {
+            bail!(
+                "{} has a manifest past the end of the file, {} bytes at {} of {}",
+                path.display(),
+                length,
+                offset,
+                size
+            );
+        }
+
+        let mut contents = vec![0; length as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut contents)?;
+
+        let manifest: PakManifest = toml::from_str(std::str::from_utf8(&contents)?)?;
+        if manifest.version != VERSION {
+            bail!(
+                "{} is version {}, expected {}",
+                path.display(),
+                manifest.version,
+                VERSION
+            );
+        }
+
+        let by_path = manifest
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.path.clone(), i))
+            .collect();
+
+        Ok(NvPak {
+            path: path.to_path_buf(),
+            entries: manifest.entries,
+            by_path,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.by_path.contains_key(path)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.path.as_str())
+    }
+
+    pub fn path_of(&self, id: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.path.as_str())
+    }
+
+    fn entry(&self, path: &str) -> Option<&PakEntry> {
+        self.entries.get(*self.by_path.get(path)?)
+    }
+
+    fn read_range(&self, offset: u64, length: u64) -> anyhow::Result<Vec<u8>> {
+        // a fresh handle per read keeps loader threads independent
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut data = vec![0; length as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    // raw bytes of any entry, for textures the base mip level
+    pub fn read(&self, path: &str) -> Option<Vec<u8>> {
+        let entry = self.entry(path)?;
+        let length = match (&entry.kind, &entry.texture) {
+            (PakKind::Texture, Some(texture)) => texture.width as u64 * texture.height as u64 * 4,
+            _ => entry.length,
+        };
+
+        match self.read_range(entry.offset, length) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!(
+                    "failed to read {} from {}: {}",
+                    path,
+                    self.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn image(&self, path: &str) -> Option<DecodedImage> {
+        let entry = self.entry(path)?;
+        let texture = entry.texture.as_ref()?;
+
+        // atlas regions have no mips of their own
+        if let Some(region) = &texture.atlas {
+            let dimensions = (texture.width, texture.height);
+            return Some(DecodedImage {
+                name: path.to_string(),
+                rgba: self.crop(region)?,
+                dimensions,
+                format: PixelFormat::Rgba8,
+                meta: TextureMeta::load(path),
+                mips: MipLevels::single(dimensions),
+            });
+        }
+
+        let mut mips = MipLevels {
+            level: 0,
+            count: texture.mips.max(1),
+            base: (texture.width, texture.height),
+        };
+        if texture.width.max(texture.height) > STREAM_MIN_SIZE {
+            while mips.level + 1 < mips.count {
+                let (width, height) = mips.size(mips.level);
+                if width.max(height) <= PREVIEW_SIZE {
+                    break;
+                }
+                mips.level += 1;
+            }
+        }
+
+        Some(DecodedImage {
+            name: path.to_string(),
+            rgba: self.mip(path, mips.level)?,
+            dimensions: mips.size(mips.level),
+            format: PixelFormat::Rgba8,
+            meta: TextureMeta::load(path),
+            mips,
+        })
+    }
+
+    // one level of a texture's mip chain, the levels are stored largest first
+    pub fn mip(&self, path: &str, level: u32) -> Option<Vec<u8>> {
+        let entry = self.entry(path)?;
+        let texture = entry.texture.as_ref()?;
+        if texture.atlas.is_some() || level >= texture.mips.max(1) {
+            return None;
+        }
+
+        // skips the larger levels stored before it
+        let before = MipLevels {
+            level: 0,
+            count: level,
+            base: (texture.width, texture.height),
+        };
+        let offset = entry.offset + before.bytes();
+        let (width, height) = before.size(level);
+
+        match self.read_range(offset, width as u64 * height as u64 * 4) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!("failed to read mip {} of {}: {}", level, path, e);
+                None
+            }
+        }
+    }
+
+    // cuts an atlased texture back out of its page, reading the rows it spans
+    // in one go
+    fn crop(&self, region: &AtlasRegion) -> Option<Vec<u8>> {
+        let page = self.entry(&region.page)?;
+        let page_width = page.texture.as_ref()?.width as u64;
+        let [x, y, width, height] = region.rect.map(|v| v as u64);
+        if width == 0 || height == 0 {
+            return Some(Vec::new());
+        }
+
+        let offset = page.offset + (y * page_width + x) * 4;
+        let span = ((height - 1) * page_width + width) * 4;
+        let rows = match self.read_range(offset, span) {
+            Ok(rows) => rows,
+            Err(e) => {
+                warn!("failed to read atlas page {}: {}", region.page, e);
+                return None;
+            }
+        };
+
+        let (stride, row_length) = (page_width as usize * 4, width as usize * 4);
+        let mut rgba = Vec::with_capacity(row_length * height as usize);
+        for row in 0..height as usize {
+            let start = row * stride;
+            rgba.extend_from_slice(&rows[start..start + row_length]);
+        }
+
+        Some(rgba)
+    }
+}
+
+// once mounted, asset loads check the archive before the asset directory
+pub fn mount(path: &Path) -> bool {
+    match NvPak::open(path) {
+        Ok(pak) => {
+            info!("mounted {} with {} entries", path.display(), pak.len());
+            if pak.is_empty() {
+                warn!(
+                    "{} is empty, assets come from the asset directory",
+                    path.display()
+                );
+            }
+            if MOUNTED.set(pak).is_err() {
+                warn!("an archive is already mounted, ignoring {}", path.display());
+                return false;
+            }
+            true
+        }
+        Err(e) => {
+            warn!("failed to mount {}: {:#}", path.display(), e);
+            false
+        }
+    }
+}
+
+pub fn mounted() -> Option<&'static NvPak> {
+    MOUNTED.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 4x3 page with an atlas region cut from its middle
+    const PAGE_WIDTH: u32 = 4;
+    const PAGE_HEIGHT: u32 = 3;
+
+    fn write_pak(name: &str, manifest_length: Option<u64>) -> PathBuf {
+        let page: Vec<u8> = (0..PAGE_WIDTH * PAGE_HEIGHT)
+            .flat_map(|i| [i as u8; 4])
+            .collect();
+        let manifest = format!(
+            "version = {VERSION}
+
+[[entries]]
+id = \"page\"
+path = \"atlas/page.png\"
+kind = \"texture\"
+offset = {HEADER_SIZE}
+length = {}
+texture = {{ width = {PAGE_WIDTH}, height = {PAGE_HEIGHT}, mips = 1 }}
+
+[[entries]]
+id = \"region\"
+path = \"textures/region.png\"
+kind = \"texture\"
+offset = 0
+length = 0
+texture = {{ width = 2, height = 2, mips = 1, atlas = {{ page = \"atlas/page.png\", rect = [1, 1, 2, 2] }} }}
+",
+            page.len()
+        );
+
+        let offset = (HEADER_SIZE + page.len()) as u64;
+        let mut contents = MAGIC.to_vec();
+        contents.extend(VERSION.to_le_bytes());
+        contents.extend(offset.to_le_bytes());
+        contents.extend(
+            manifest_length
+                .unwrap_or(manifest.len() as u64)
+                .to_le_bytes(),
+        );
+        contents.extend(&page);
+        contents.extend(manifest.as_bytes());
+
+        let path =
+            std::env::temp_dir().join(format!("nivalis-pak-{}-{}.nvpak", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn crops_atlas_regions_out_of_their_page() {
+        let path = write_pak("crop", None);
+        let pak = NvPak::open(&path).unwrap();
+        let image = pak.image("textures/region.png").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(image.dimensions, (2, 2));
+        let pixels: Vec<u8> = image.rgba.chunks(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(pixels, [5, 6, 9, 10]);
+    }
+
+    #[test]
+    fn rejects_a_manifest_past_the_end_of_the_file() {
+        let path = write_pak("corrupt", Some(u64::MAX / 2));
+        let result = NvPak::open(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
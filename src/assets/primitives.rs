@@ -0,0 +1,172 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::assets::model::{Mesh, MeshVertex};
+
+// meshes for prototyping and debug views, centered on the origin with y up.
+// uvs run left to right and top to bottom as the face is seen from outside
+
+// in the xy plane, facing +z
+pub fn quad(size: [f32; 2]) -> Mesh {
+    let [width, height] = size;
+    grid("builtin/quad", 1, 1, |u, v| MeshVertex {
+        position: [(u - 0.5) * width, (0.5 - v) * height, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        uv: [u, v],
+    })
+}
+
+// in the xz plane, facing +y, split into cells for vertex lighting or displacement
+pub fn plane(size: [f32; 2], subdivisions: u32) -> Mesh {
+    let [width, depth] = size;
+    let cells = subdivisions + 1;
+    grid("builtin/plane", cells, cells, |u, v| MeshVertex {
+        position: [(u - 0.5) * width, 0.0, (v - 0.5) * depth],
+        normal: [0.0, 1.0, 0.0],
+        uv: [u, v],
+    })
+}
+
+// flat shaded, each face has its own vertices and the whole texture
+pub fn cube(size: [f32; 3]) -> Mesh {
+    // outward normal and the face's right, down is right x normal
+    const FACES: [([f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0]),
+    ];
+
+    let mut mesh = Mesh {
+        name: "builtin/cube".to_string(),
+        ..Default::default()
+    };
+    for (normal, right) in FACES {
+        let down = cross(right, normal);
+        let face = grid("", 1, 1, |u, v| MeshVertex {
+            position: std::array::from_fn(|i| {
+                (normal[i] * 0.5 + right[i] * (u - 0.5) + down[i] * (v - 0.5)) * size[i]
+            }),
+            normal,
+            uv: [u, v],
+        });
+        append(&mut mesh, face);
+    }
+    mesh
+}
+
+// a uv sphere, segments around and rings from pole to pole
+pub fn sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let rings = rings.max(2);
+    let profile = (0..=rings)
+        .map(|ring| {
+            let angle = ring as f32 / rings as f32 * PI;
+            let (sin, cos) = angle.sin_cos();
+            ProfilePoint {
+                radius: sin * radius,
+                y: cos * radius,
+                normal: [sin, cos],
+            }
+        })
+        .collect();
+    lathe("builtin/sphere", profile, segments)
+}
+
+// a cylinder with hemisphere caps along y, height includes the caps
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    // rings per cap
+    let rings = (rings / 2).max(1);
+    let half_body = (height * 0.5 - radius).max(0.0);
+    let cap = |ring: u32, offset: f32, from: f32| {
+        let angle = from + ring as f32 / rings as f32 * FRAC_PI_2;
+        let (sin, cos) = angle.sin_cos();
+        ProfilePoint {
+            radius: sin * radius,
+            y: cos * radius + offset,
+            normal: [sin, cos],
+        }
+    };
+
+    // the two equator rings are the body's top and bottom edges
+    let profile = (0..=rings)
+        .map(|ring| cap(ring, half_body, 0.0))
+        .chain((0..=rings).map(|ring| cap(ring, -half_body, FRAC_PI_2)))
+        .collect();
+    lathe("builtin/capsule", profile, segments)
+}
+
+// a point of the outline a lathe spins around y
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    normal: [f32; 2], // away from the axis and up
+}
+
+// spun around y from +z towards +x, v follows the profile by length
+fn lathe(name: &str, profile: Vec<ProfilePoint>, segments: u32) -> Mesh {
+    let segments = segments.max(3);
+    let mut lengths = vec![0.0];
+    for pair in profile.windows(2) {
+        let length = (pair[1].radius - pair[0].radius).hypot(pair[1].y - pair[0].y);
+        lengths.push(lengths[lengths.len() - 1] + length);
+    }
+    let total = lengths[lengths.len() - 1].max(f32::EPSILON);
+
+    let rows = profile.len() as u32 - 1;
+    grid(name, segments, rows, |u, v| {
+        let row = (v * rows as f32).round() as usize;
+        let point = &profile[row];
+        let (sin, cos) = (u * TAU).sin_cos();
+        MeshVertex {
+            position: [sin * point.radius, point.y, cos * point.radius],
+            normal: [
+                sin * point.normal[0],
+                point.normal[1],
+                cos * point.normal[0],
+            ],
+            uv: [u, lengths[row] / total],
+        }
+    })
+}
+
+// columns x rows quads from a vertex at each corner, u and v in 0..1. the
+// vertex at v + 1 has to be below the one at u + 1 from outside
+fn grid(name: &str, columns: u32, rows: u32, vertex: impl Fn(f32, f32) -> MeshVertex) -> Mesh {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let vertices = (0..=rows)
+        .flat_map(|row| (0..=columns).map(move |column| (column, row)))
+        .map(|(column, row)| vertex(column as f32 / columns as f32, row as f32 / rows as f32))
+        .collect();
+
+    let stride = columns + 1;
+    let indices = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| row * stride + column))
+        .flat_map(|corner| {
+            let (right, below) = (corner + 1, corner + stride);
+            [corner, below, right, right, below, below + 1]
+        })
+        .collect();
+
+    Mesh {
+        name: name.to_string(),
+        vertices,
+        indices,
+        material: None,
+    }
+}
+
+fn append(mesh: &mut Mesh, other: Mesh) {
+    let offset = mesh.vertices.len() as u32;
+    mesh.vertices.extend(other.vertices);
+    mesh.indices
+        .extend(other.indices.into_iter().map(|index| index + offset));
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
@@ -0,0 +1,410 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use log::{debug, info, warn};
+use ureq::Agent;
+
+use crate::assets::pak;
+use crate::settings::Settings;
+use crate::tasks::{self, Task, TaskKind};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: u32 = 5;
+const MAX_BODY: u64 = 256 * 1024 * 1024; // larger downloads are refused
+
+static MOUNTED: OnceLock<RemoteSource> = OnceLock::new();
+static AGENT: OnceLock<Agent> = OnceLock::new();
+
+// assets served over http, for downloadable content and builds that ship
+// without an asset directory. downloads are kept in the cache directory and
+// revalidated with the server on every load, so they still load offline
+pub struct RemoteSource {
+    base: String, // without a trailing slash
+}
+
+// the parts of a response the cache cares about
+struct Response {
+    status: u16,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: Vec<u8>,
+}
+
+pub fn is_url(name: &str) -> bool {
+    name.starts_with("http://") || name.starts_with("https://")
+}
+
+// once mounted, assets missing from the pak and the asset directory are
+// fetched from base_url, e.g. http://cdn.example.com/game/assets
+pub fn mount(base_url: &str) -> bool {
+    if !is_url(base_url) {
+        warn!("{} is not an http url, not mounting it", base_url);
+        return false;
+    }
+    let source = RemoteSource {
+        base: base_url.trim_end_matches('/').to_string(),
+    };
+    info!("fetching missing assets from {}", source.base);
+    if MOUNTED.set(source).is_err() {
+        warn!("a remote source is already mounted, ignoring {}", base_url);
+        return false;
+    }
+    true
+}
+
+pub fn mounted() -> Option<&'static RemoteSource> {
+    MOUNTED.get()
+}
+
+// whether reading the asset goes to the network, the pak and the asset
+// directory come first
+pub fn is_remote(name: &str) -> bool {
+    is_url(name)
+        || (mounted().is_some()
+            && !pak::mounted().is_some_and(|pak| pak.contains(name))
+            && !Path::new("assets").join(name).exists())
+}
+
+// bytes of an asset that isn't on disk: fetched by url, or by path from the
+// mounted source. None for local assets, they load as they always did.
+// this blocks until the download is done, see spawn_open
+pub fn read(name: &str) -> Option<anyhow::Result<Vec<u8>>> {
+    if is_url(name) {
+        return Some(fetch(name));
+    }
+    let source = mounted()?;
+    if !is_remote(name) {
+        return None;
+    }
+    Some(source.read(name))
+}
+
+// remote assets wait on the network, so they load on the io workers
+pub fn task_kind(name: &str) -> TaskKind {
+    match is_remote(name) {
+        true => TaskKind::Io,
+        false => TaskKind::Compute,
+    }
+}
+
+// opens an asset on the task pool, e.g. spawn_open("maps/town.tmx", TiledMap::open),
+// so a slow server doesn't stall the frame that asked for it
+#[allow(dead_code)] // maps and models are opened by the game
+pub fn spawn_open<T: Send + 'static>(
+    name: &str,
+    open: fn(&str) -> anyhow::Result<T>,
+) -> Task<anyhow::Result<T>> {
+    let name = name.to_string();
+    tasks::pool().spawn(task_kind(&name), move || open(&name))
+}
+
+impl RemoteSource {
+    pub fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    // path relative to the asset directory
+    pub fn read(&self, path: &str) -> anyhow::Result<Vec<u8>> {
+        fetch(&self.url(path))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base, encode_path(path))
+    }
+}
+
+// percent-encodes everything but unreserved characters, keeping the slashes
+// between directories
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// where downloads are kept, None on platforms without a cache directory
+pub fn cache_dir() -> Option<PathBuf> {
+    Some(Settings::project_dirs()?.cache_dir().join("remote"))
+}
+
+// a cached copy is only downloaded again when the server says it changed,
+// and used as is when the server can't be reached. blocks like read
+pub fn fetch(url: &str) -> anyhow::Result<Vec<u8>> {
+    fetch_into(url, cache_dir().as_deref(), MAX_BODY)
+}
+
+fn fetch_into(url: &str, cache: Option<&Path>, limit: u64) -> anyhow::Result<Vec<u8>> {
+    let cached = cache.and_then(|dir| CachedFile::read(dir, url));
+    let response = match get(url, cached.as_ref(), limit) {
+        Ok(response) => response,
+        Err(e) => match cached {
+            Some(cached) => {
+                warn!("{:#}, using the cached copy of {}", e, url);
+                return Ok(cached.body);
+            }
+            None => return Err(e),
+        },
+    };
+
+    match (response.status, cached) {
+        (304, Some(cached)) => {
+            debug!("{} is unchanged, using the cached copy", url);
+            Ok(cached.body)
+        }
+        (200..=299, _) => {
+            if let Some(dir) = cache
+                && let Err(e) = CachedFile::write(dir, url, &response)
+            {
+                warn!("failed to cache {}: {:#}", url, e);
+            }
+            Ok(response.body)
+        }
+        (status, Some(cached)) => {
+            warn!("{} answered {}, using the cached copy", url, status);
+            Ok(cached.body)
+        }
+        (status, None) => bail!("{} answered {}", url, status),
+    }
+}
+
+// a download and what the server said to revalidate it with
+struct CachedFile {
+    body: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl CachedFile {
+    // named after a hash of the url, the validators sit next to it
+    fn paths(dir: &Path, url: &str) -> (PathBuf, PathBuf) {
+        let hash = url.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        let name = format!("{:016x}", hash);
+        (dir.join(&name), dir.join(format!("{}.validators", name)))
+    }
+
+    fn read(dir: &Path, url: &str) -> Option<CachedFile> {
+        let (body, validators) = Self::paths(dir, url);
+        let body = fs::read(body).ok()?;
+        let validators = fs::read_to_string(validators).unwrap_or_default();
+        let mut lines = validators.lines().map(|line| match line {
+            "" => None,
+            line => Some(line.to_string()),
+        });
+        Some(CachedFile {
+            body,
+            etag: lines.next().flatten(),
+            last_modified: lines.next().flatten(),
+        })
+    }
+
+    fn write(dir: &Path, url: &str, response: &Response) -> anyhow::Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let (body, validators) = Self::paths(dir, url);
+        fs::write(&body, &response.body)?;
+        let etag = response.etag.as_deref().unwrap_or_default();
+        let last_modified = response.last_modified.as_deref().unwrap_or_default();
+        fs::write(&validators, format!("{}\n{}\n", etag, last_modified))?;
+        Ok(())
+    }
+}
+
+// https through rustls, redirects followed. error statuses are responses too,
+// the cache decides what to do with them
+fn agent() -> &'static Agent {
+    AGENT.get_or_init(|| {
+        Agent::config_builder()
+            .timeout_global(Some(TIMEOUT))
+            .max_redirects(MAX_REDIRECTS)
+            .http_status_as_error(false)
+            .user_agent("nivalis")
+            .build()
+            .into()
+    })
+}
+
+fn get(url: &str, cached: Option<&CachedFile>, limit: u64) -> anyhow::Result<Response> {
+    let mut request = agent().get(url);
+    if let Some(etag) = cached.and_then(|c| c.etag.as_ref()) {
+        request = request.header("If-None-Match", etag);
+    }
+    if let Some(modified) = cached.and_then(|c| c.last_modified.as_ref()) {
+        request = request.header("If-Modified-Since", modified);
+    }
+    let mut response = request
+        .call()
+        .with_context(|| format!("failed to fetch {}", url))?;
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header("etag");
+    let last_modified = header("last-modified");
+    let body = response
+        .body_mut()
+        .with_config()
+        .limit(limit)
+        .read_to_vec()
+        .with_context(|| format!("failed to read the response from {}", url))?;
+    Ok(Response {
+        status: response.status().as_u16(),
+        etag,
+        last_modified,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    use super::*;
+
+    // answers one connection per response, handing back the requests it got
+    fn serve(responses: Vec<&'static str>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/file", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        let read = stream.read(&mut buffer).unwrap();
+                        request.extend_from_slice(&buffer[..read]);
+                    }
+                    stream.write_all(response.as_bytes()).unwrap();
+                    String::from_utf8(request).unwrap()
+                })
+                .collect()
+        });
+        (url, server)
+    }
+
+    fn cache(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("nivalis-remote-{}-{}", name, std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn asset_paths_are_percent_encoded() {
+        let source = RemoteSource {
+            base: "http://cdn.example.com/assets".to_string(),
+        };
+        assert_eq!(
+            source.url("textures/big cat#2?.png"),
+            "http://cdn.example.com/assets/textures/big%20cat%232%3F.png"
+        );
+        assert_eq!(
+            source.url("sounds/café.wav"),
+            "http://cdn.example.com/assets/sounds/caf%C3%A9.wav"
+        );
+    }
+
+    #[test]
+    fn chunked_body_is_cached_with_its_validators() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\n\r\n",
+        ]);
+        let dir = cache("chunked");
+
+        assert_eq!(
+            fetch_into(&url, Some(&dir), MAX_BODY).unwrap(),
+            b"hello world"
+        );
+        server.join().unwrap();
+        let cached = CachedFile::read(&dir, &url).unwrap();
+        assert_eq!(cached.body, b"hello world");
+        assert_eq!(cached.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(cached.last_modified, None);
+    }
+
+    #[test]
+    fn unchanged_file_comes_from_the_cache() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nETag: \"v1\"\r\nLast-Modified: Wed, 21 Oct 2015 07:28:00 GMT\r\nConnection: close\r\n\r\nold",
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+        ]);
+        let dir = cache("unchanged");
+
+        assert_eq!(fetch_into(&url, Some(&dir), MAX_BODY).unwrap(), b"old");
+        assert_eq!(fetch_into(&url, Some(&dir), MAX_BODY).unwrap(), b"old");
+        let requests = server.join().unwrap();
+        let revalidation = requests[1].to_ascii_lowercase();
+        assert!(revalidation.contains("if-none-match: \"v1\""));
+        assert!(revalidation.contains("if-modified-since: wed, 21 oct 2015 07:28:00 gmt"));
+    }
+
+    #[test]
+    fn error_status_falls_back_to_the_cache() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\nold",
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let dir = cache("error");
+
+        assert_eq!(fetch_into(&url, Some(&dir), MAX_BODY).unwrap(), b"old");
+        assert_eq!(fetch_into(&url, Some(&dir), MAX_BODY).unwrap(), b"old");
+        assert!(fetch_into(&url, None, MAX_BODY).is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn body_over_the_limit_is_refused() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\n0123456789abcdef",
+        ]);
+        let dir = cache("limit");
+
+        assert!(fetch_into(&url, Some(&dir), 8).is_err());
+        server.join().unwrap();
+        assert!(CachedFile::read(&dir, &url).is_none());
+    }
+
+    #[test]
+    fn redirects_are_followed() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /moved\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nmoved",
+        ]);
+
+        assert_eq!(fetch_into(&url, None, MAX_BODY).unwrap(), b"moved");
+        let requests = server.join().unwrap();
+        assert!(requests[1].starts_with("GET /moved "));
+    }
+
+    #[test]
+    fn unreachable_server_falls_back_to_the_cache() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\nold",
+        ]);
+        let dir = cache("unreachable");
+
+        assert_eq!(fetch_into(&url, Some(&dir), MAX_BODY).unwrap(), b"old");
+        // the listener is dropped with the server thread
+        server.join().unwrap();
+        assert_eq!(fetch_into(&url, Some(&dir), MAX_BODY).unwrap(), b"old");
+        assert!(fetch_into(&url, None, MAX_BODY).is_err());
+    }
+}
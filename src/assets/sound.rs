@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use anyhow::{Context, bail};
+use log::{debug, error};
+
+use crate::assets::{pak, remote};
+
+// cpu side sound, decoded off the main thread like textures. samples are
+// interleaved floats in -1..1 and shared with whatever is playing them
+#[derive(Clone)]
+pub struct DecodedSound {
+    pub name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Arc<[f32]>,
+}
+
+impl DecodedSound {
+    pub fn open(sound_name: &str) -> anyhow::Result<Self> {
+        let bytes = match pak::mounted().and_then(|pak| pak.read(sound_name)) {
+            Some(bytes) => bytes,
+            None => match remote::read(sound_name) {
+                Some(bytes) => bytes?,
+                None => {
+                    let file = format!("assets/{}", sound_name);
+                    debug!("loading sound at {}", file);
+                    std::fs::read(&file).with_context(|| format!("failed to read {}", file))?
+                }
+            },
+        };
+
+        let (sample_rate, channels, samples) = decode_wav(&bytes)?;
+        Ok(DecodedSound {
+            name: sound_name.to_string(),
+            sample_rate,
+            channels,
+            samples: samples.into(),
+        })
+    }
+
+    // no samples, so a missing file doesn't shift the sound ids
+    pub fn silent(sound_name: &str) -> Self {
+        DecodedSound {
+            name: sound_name.to_string(),
+            sample_rate: 48_000,
+            channels: 1,
+            samples: Arc::new([]),
+        }
+    }
+
+    pub fn open_or_silent(sound_name: &str) -> Self {
+        match Self::open(sound_name) {
+            Ok(sound) => sound,
+            Err(e) => {
+                error!("failed to load sound {}: {:#}", sound_name, e);
+                Self::silent(sound_name)
+            }
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    pub fn seconds(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    // voices playing this sound, they hold on to the samples
+    pub fn references(&self) -> usize {
+        Arc::strong_count(&self.samples) - 1
+    }
+
+    pub fn bytes(&self) -> u64 {
+        (self.samples.len() * size_of::<f32>()) as u64
+    }
+}
+
+// riff wave with integer pcm or 32 bit float samples, the format every editor exports
+fn decode_wav(bytes: &[u8]) -> anyhow::Result<(u32, u16, Vec<f32>)> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        bail!("not a wav file");
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let length = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+        let start = offset + 8;
+        let end = start.saturating_add(length).min(bytes.len());
+        match id {
+            b"fmt " if end - start >= 16 => format = Some(&bytes[start..end]),
+            b"data" => data = Some(&bytes[start..end]),
+            _ => {}
+        }
+        // chunks are padded to an even length
+        offset = start.saturating_add(length + (length & 1));
+    }
+
+    let (Some(format), Some(data)) = (format, data) else {
+        bail!("wav file has no fmt or data chunk");
+    };
+    let mut tag = u16::from_le_bytes([format[0], format[1]]);
+    let channels = u16::from_le_bytes([format[2], format[3]]);
+    let sample_rate = u32::from_le_bytes(format[4..8].try_into()?);
+    let bits = u16::from_le_bytes([format[14], format[15]]);
+    // extensible files keep the real tag at the start of their subformat guid
+    if tag == 0xfffe && format.len() >= 26 {
+        tag = u16::from_le_bytes([format[24], format[25]]);
+    }
+    if channels == 0 || sample_rate == 0 {
+        bail!("wav file has {} channels at {} hz", channels, sample_rate);
+    }
+
+    let samples = match (tag, bits) {
+        (1, 8) => data.iter().map(|&s| (s as f32 - 128.0) / 128.0).collect(),
+        (1, 16) => data
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0)
+            .collect(),
+        (1, 24) => data
+            .chunks_exact(3)
+            .map(|s| i32::from_le_bytes([0, s[0], s[1], s[2]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        (1, 32) => data
+            .chunks_exact(4)
+            .map(|s| i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        (3, 32) => data
+            .chunks_exact(4)
+            .map(|s| f32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+            .collect(),
+        _ => bail!("unsupported wav encoding {} with {} bits", tag, bits),
+    };
+    Ok((sample_rate, channels, samples))
+}
@@ -0,0 +1,264 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::assets::sound::DecodedSound;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bus {
+    Master, // scales the other two, nothing plays on it directly
+    Music,
+    Sfx,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 3] = [Bus::Master, Bus::Music, Bus::Sfx];
+
+    fn index(self) -> usize {
+        match self {
+            Bus::Master => 0,
+            Bus::Music => 1,
+            Bus::Sfx => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BusLevel {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for BusLevel {
+    fn default() -> Self {
+        BusLevel {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl BusLevel {
+    fn gain(self) -> f32 {
+        match self.muted {
+            true => 0.0,
+            false => self.volume,
+        }
+    }
+}
+
+// a playing sound, ids are never reused
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct VoiceId(u64);
+
+// a linear ramp of a voice's gain, in seconds of mixed output
+#[derive(Clone, Copy, Debug)]
+struct Fade {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+    stop: bool, // the voice ends when the ramp does, for fade outs
+}
+
+impl Fade {
+    fn gain(&self) -> f32 {
+        let t = (self.elapsed / self.duration).min(1.0);
+        self.from + (self.to - self.from) * t
+    }
+
+    fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+struct Voice {
+    id: VoiceId,
+    samples: Arc<[f32]>,
+    channels: usize,
+    sample_rate: u32,
+    position: f64, // in source frames, fractional when resampling
+    bus: Bus,
+    gain: f32,
+    looping: bool,
+    fade: Option<Fade>,
+}
+
+impl Voice {
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    fn current_gain(&self) -> f32 {
+        self.fade.map_or(self.gain, |fade| fade.gain())
+    }
+
+    // linearly interpolated, mono plays on every output channel
+    fn sample(&self, channel: usize) -> f32 {
+        let frames = self.frames();
+        let index = self.position as usize;
+        let next = match index + 1 < frames {
+            true => index + 1,
+            false if self.looping => 0,
+            false => index,
+        };
+        let channel = channel.min(self.channels - 1);
+        let t = self.position.fract() as f32;
+        let a = self.samples[index * self.channels + channel];
+        let b = self.samples[next * self.channels + channel];
+        a + (b - a) * t
+    }
+}
+
+// sums playing voices through their bus into an interleaved output buffer.
+// whatever drives the output device calls mix, nothing here blocks
+#[derive(Default)]
+pub struct Mixer {
+    buses: [BusLevel; 3],
+    voices: Vec<Voice>,
+    music: Option<VoiceId>,
+    next_id: u64,
+}
+
+impl Mixer {
+    pub fn bus(&self, bus: Bus) -> BusLevel {
+        self.buses[bus.index()]
+    }
+
+    pub fn set_bus(&mut self, bus: Bus, level: BusLevel) {
+        self.buses[bus.index()] = BusLevel {
+            volume: level.volume.max(0.0),
+            ..level
+        };
+    }
+
+    pub fn play(&mut self, sound: &DecodedSound, bus: Bus, gain: f32, looping: bool) -> VoiceId {
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        if sound.frames() == 0 {
+            return id; // silent placeholder, finishes right away
+        }
+
+        self.voices.push(Voice {
+            id,
+            samples: sound.samples.clone(),
+            channels: sound.channels as usize,
+            sample_rate: sound.sample_rate,
+            position: 0.0,
+            bus,
+            gain,
+            looping,
+            fade: None,
+        });
+        id
+    }
+
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.voices.iter().any(|voice| voice.id == id)
+    }
+
+    // fades to silence over the given seconds, 0 cuts it off
+    pub fn stop(&mut self, id: VoiceId, fade_seconds: f32) {
+        if fade_seconds <= 0.0 {
+            self.voices.retain(|voice| voice.id != id);
+            return;
+        }
+        if let Some(voice) = self.voices.iter_mut().find(|voice| voice.id == id) {
+            voice.fade = Some(Fade {
+                from: voice.current_gain(),
+                to: 0.0,
+                elapsed: 0.0,
+                duration: fade_seconds,
+                stop: true,
+            });
+        }
+    }
+
+    pub fn music(&self) -> Option<VoiceId> {
+        self.music
+    }
+
+    // loops the track on the music bus, the previous one fades out while this one fades in
+    pub fn play_music(&mut self, sound: &DecodedSound, crossfade_seconds: f32) -> VoiceId {
+        self.stop_music(crossfade_seconds);
+
+        let id = self.play(sound, Bus::Music, 1.0, true);
+        if crossfade_seconds > 0.0
+            && let Some(voice) = self.voices.iter_mut().find(|voice| voice.id == id)
+        {
+            voice.fade = Some(Fade {
+                from: 0.0,
+                to: 1.0,
+                elapsed: 0.0,
+                duration: crossfade_seconds,
+                stop: false,
+            });
+        }
+        self.music = Some(id);
+        id
+    }
+
+    pub fn stop_music(&mut self, fade_seconds: f32) {
+        if let Some(id) = self.music.take() {
+            self.stop(id, fade_seconds);
+        }
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    // adds every voice into out, which holds interleaved frames of `channels` samples
+    pub fn mix(&mut self, out: &mut [f32], channels: usize, sample_rate: u32) {
+        out.fill(0.0);
+        if channels == 0 || sample_rate == 0 {
+            return;
+        }
+
+        let master = self.buses[Bus::Master.index()].gain();
+        let step_seconds = 1.0 / sample_rate as f32;
+        let buses = self.buses;
+
+        self.voices.retain_mut(|voice| {
+            let bus_gain = master * buses[voice.bus.index()].gain();
+            let step = voice.sample_rate as f64 / sample_rate as f64;
+            let frames = voice.frames() as f64;
+
+            for frame in out.chunks_exact_mut(channels) {
+                let gain = voice.current_gain() * bus_gain;
+                if gain != 0.0 {
+                    for (channel, sample) in frame.iter_mut().enumerate() {
+                        *sample += voice.sample(channel) * gain;
+                    }
+                }
+
+                if let Some(fade) = &mut voice.fade {
+                    fade.elapsed += step_seconds;
+                    if fade.is_done() {
+                        if fade.stop {
+                            return false;
+                        }
+                        voice.gain = fade.to;
+                        voice.fade = None;
+                    }
+                }
+
+                voice.position += step;
+                if voice.position >= frames {
+                    if !voice.looping {
+                        return false;
+                    }
+                    voice.position -= frames;
+                }
+            }
+            true
+        });
+
+        if let Some(id) = self.music
+            && !self.voices.iter().any(|voice| voice.id == id)
+        {
+            self.music = None;
+        }
+    }
+}
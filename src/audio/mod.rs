@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use log::{info, warn};
+
+use crate::assets::sound::DecodedSound;
+use crate::audio::mixer::{Bus, BusLevel, Mixer, VoiceId};
+
+pub mod mixer;
+
+// bus levels and the default music crossfade, from the settings file
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AudioConfig {
+    pub master: BusLevel,
+    pub music: BusLevel,
+    pub sfx: BusLevel,
+    pub crossfade_seconds: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            master: BusLevel::default(),
+            music: BusLevel::default(),
+            sfx: BusLevel::default(),
+            crossfade_seconds: 2.0,
+        }
+    }
+}
+
+// a sound by the asset pool it was registered in and its id there
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SoundHandle {
+    pub pool: usize,
+    pub sound: usize,
+}
+
+// decoded sounds of every loaded asset pool and the mixer playing them. there
+// is no output device yet, it pulls mixed samples through mix
+#[derive(Default)]
+pub struct Audio {
+    pools: HashMap<usize, Vec<DecodedSound>>, // asset pool id -> sounds by id
+    mixer: Mixer,
+    config: AudioConfig,
+    background_muted: bool, // the master bus, on top of the config
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // loading a pool again swaps its sounds in place, handles stay valid
+    pub fn insert_loaded_pool(&mut self, pool: usize, sounds: Vec<DecodedSound>) {
+        info!("adding {} sounds from asset pool {}", sounds.len(), pool);
+        self.pools.insert(pool, sounds);
+    }
+
+    pub fn unload_pool(&mut self, pool: usize) {
+        if self.pools.remove(&pool).is_none() {
+            warn!("asset pool {} has no sounds loaded", pool);
+        }
+    }
+
+    // empty when the pool has no sounds or isn't loaded
+    pub fn pool_sounds(&self, pool: usize) -> &[DecodedSound] {
+        self.pools.get(&pool).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn sound(&self, handle: SoundHandle) -> Option<&DecodedSound> {
+        self.pools.get(&handle.pool)?.get(handle.sound)
+    }
+
+    // path relative to the asset directory, as it was registered
+    pub fn find(&self, pool: usize, path: &str) -> Option<SoundHandle> {
+        let sound = self
+            .pools
+            .get(&pool)?
+            .iter()
+            .position(|sound| sound.name == path)?;
+        Some(SoundHandle { pool, sound })
+    }
+
+    // decodes a sound into the pool on first use, later calls find it again
+    pub fn find_or_load(&mut self, pool: usize, path: &str) -> SoundHandle {
+        if let Some(handle) = self.find(pool, path) {
+            return handle;
+        }
+        let sounds = self.pools.entry(pool).or_default();
+        sounds.push(DecodedSound::open_or_silent(path));
+        SoundHandle {
+            pool,
+            sound: sounds.len() - 1,
+        }
+    }
+
+    // reads the file again after it changed on disk, playing copies keep the old samples
+    pub fn reload(&mut self, handle: SoundHandle) -> bool {
+        let Some(slot) = self
+            .pools
+            .get_mut(&handle.pool)
+            .and_then(|sounds| sounds.get_mut(handle.sound))
+        else {
+            warn!("no sound {:?} to reload", handle);
+            return false;
+        };
+
+        info!("reloading sound {}", slot.name);
+        match DecodedSound::open(&slot.name) {
+            Ok(sound) => {
+                *slot = sound;
+                true
+            }
+            Err(e) => {
+                warn!(
+                    "failed to reload sound {}, keeping the old one: {:#}",
+                    slot.name, e
+                );
+                false
+            }
+        }
+    }
+
+    pub fn resident_bytes(&self) -> u64 {
+        self.pools.values().flatten().map(DecodedSound::bytes).sum()
+    }
+
+    // cheap to call every frame, only changes are applied
+    pub fn configure(&mut self, config: AudioConfig) {
+        if config == self.config {
+            return;
+        }
+        info!(
+            "audio buses: master {:?}, music {:?}, sfx {:?}",
+            config.master, config.music, config.sfx
+        );
+        let muted = config.master.muted || self.background_muted;
+        self.mixer.set_bus(
+            Bus::Master,
+            BusLevel {
+                muted,
+                ..config.master
+            },
+        );
+        self.mixer.set_bus(Bus::Music, config.music);
+        self.mixer.set_bus(Bus::Sfx, config.sfx);
+        self.config = config;
+    }
+
+    pub fn config(&self) -> AudioConfig {
+        self.config
+    }
+
+    pub fn bus(&self, bus: Bus) -> BusLevel {
+        self.mixer.bus(bus)
+    }
+
+    // until the next configure with different settings
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        let level = self.mixer.bus(bus);
+        self.mixer.set_bus(bus, BusLevel { volume, ..level });
+    }
+
+    pub fn set_bus_muted(&mut self, bus: Bus, muted: bool) {
+        let level = self.mixer.bus(bus);
+        self.mixer.set_bus(bus, BusLevel { muted, ..level });
+    }
+
+    // silences everything while the window is in the background, without
+    // touching the configured mute
+    pub fn set_background_muted(&mut self, muted: bool) {
+        self.background_muted = muted;
+        self.set_bus_muted(Bus::Master, self.config.master.muted || muted);
+    }
+
+    pub fn play(&mut self, handle: SoundHandle, bus: Bus) -> Option<VoiceId> {
+        if bus == Bus::Master {
+            warn!("sounds play on the music or sfx bus, not master");
+            return None;
+        }
+        let Some(sound) = self
+            .pools
+            .get(&handle.pool)
+            .and_then(|s| s.get(handle.sound))
+        else {
+            warn!("no sound {:?} to play", handle);
+            return None;
+        };
+        Some(self.mixer.play(sound, bus, 1.0, false))
+    }
+
+    // crossfades from the current track over the configured duration
+    pub fn play_music(&mut self, handle: SoundHandle) -> Option<VoiceId> {
+        self.play_music_with_fade(handle, self.config.crossfade_seconds)
+    }
+
+    pub fn play_music_with_fade(
+        &mut self,
+        handle: SoundHandle,
+        crossfade_seconds: f32,
+    ) -> Option<VoiceId> {
+        let Some(sound) = self
+            .pools
+            .get(&handle.pool)
+            .and_then(|s| s.get(handle.sound))
+        else {
+            warn!("no sound {:?} to play as music", handle);
+            return None;
+        };
+        info!(
+            "playing music {} with a {}s crossfade",
+            sound.name, crossfade_seconds
+        );
+        Some(self.mixer.play_music(sound, crossfade_seconds))
+    }
+
+    pub fn stop_music(&mut self, fade_seconds: f32) {
+        self.mixer.stop_music(fade_seconds);
+    }
+
+    pub fn mixer(&mut self) -> &mut Mixer {
+        &mut self.mixer
+    }
+
+    // interleaved output for a device or a file writer. the game brings the
+    // device, the engine opens none of its own
+    #[allow(dead_code)]
+    pub fn mix(&mut self, out: &mut [f32], channels: usize, sample_rate: u32) {
+        self.mixer.mix(out, channels, sample_rate);
+    }
+}
@@ -0,0 +1,1829 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::Context;
+use log::{Level, LevelFilter};
+use winit::dpi::PhysicalSize;
+
+use crate::assets::cubemap::{CubemapSource, DecodedCubemap};
+use crate::assets::image_cache;
+use crate::assets::manifest::AssetId;
+use crate::assets::model::DecodedModel;
+use crate::assets::pak;
+use crate::assets::primitives;
+use crate::assets::remote;
+use crate::audio::mixer::Bus;
+use crate::cvars::Cvars;
+use crate::input::rumble::Rumble;
+use crate::integrations::Presence;
+use crate::logging;
+use crate::platform::clipboard;
+use crate::platform::window::WindowOptions;
+use crate::renderer::Renderer;
+use crate::renderer::accessibility::{CommandSpeech, SpeechBackend};
+use crate::renderer::asset_browser::format_bytes;
+use crate::renderer::camera::{Camera2D, PixelSnap};
+use crate::renderer::debug_view::DebugView;
+use crate::renderer::layer::{LayerMask, SortMode, Sprite};
+use crate::renderer::lighting::LightingEnvironment;
+use crate::renderer::material::MaterialParam;
+use crate::renderer::outline::Outline;
+use crate::renderer::palette::SpritePalette;
+use crate::renderer::particles::ParticleCurve;
+use crate::renderer::present::Vsync;
+use crate::renderer::recorder::RecordingOptions;
+use crate::renderer::skybox::SkyView;
+use crate::renderer::trails::{Trail, TrailCurve};
+use crate::renderer::viewport::AspectRatio;
+use crate::renderer::weather::{Weather, WeatherKind};
+use crate::state::StateContext;
+use crate::ui::theme::BUILTIN_THEMES;
+use crate::util::curve::Curve;
+use crate::util::tween::Easing;
+use crate::world::registry::NamePolicy;
+
+// the builtins that take a cvar name first
+const CVAR_COMMANDS: [&str; 3] = ["set", "get", "reset"];
+
+type Run = Box<dyn FnMut(&mut StateContext, &[String]) -> anyhow::Result<String>>;
+
+struct Command {
+    help: String,
+    run: Run,
+}
+
+// the commands the developer console runs, by name. what a command returns is
+// printed under it, errors in red
+pub struct Console {
+    commands: BTreeMap<String, Command>,
+    changed: bool, // since the panel last got the names to complete
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Console::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        let mut console = Console {
+            commands: BTreeMap::new(),
+            changed: true,
+        };
+        console.register_builtins();
+        console
+    }
+
+    // replaces a command of the same name
+    pub fn register(
+        &mut self,
+        name: &str,
+        help: &str,
+        run: impl FnMut(&mut StateContext, &[String]) -> anyhow::Result<String> + 'static,
+    ) {
+        self.commands.insert(
+            name.to_string(),
+            Command {
+                help: help.to_string(),
+                run: Box::new(run),
+            },
+        );
+        self.changed = true;
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.commands.keys().cloned().collect();
+        names.push("help".to_string());
+        names.sort();
+        names
+    }
+
+    // what tab completes to, the commands and the cvar commands with each cvar
+    pub fn completions(&self, cvars: &Cvars) -> Vec<String> {
+        let mut completions = self.names();
+        for command in CVAR_COMMANDS {
+            completions.extend(
+                cvars
+                    .iter()
+                    .map(|cvar| format!("{} {}", command, cvar.name)),
+            );
+        }
+        completions
+    }
+
+    pub(crate) fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    // runs one line as typed, printing to the console panel
+    pub fn execute(&mut self, ctx: &mut StateContext, line: &str) {
+        let words = split_words(line);
+        let Some((name, args)) = words.split_first() else {
+            return;
+        };
+
+        if name == "help" {
+            let text = self.help(args.first().map(String::as_str));
+            ctx.renderer.console_print(Level::Info, &text);
+            return;
+        }
+        let Some(command) = self.commands.get_mut(name) else {
+            ctx.renderer
+                .console_print(Level::Error, &format!("unknown command {}, try help", name));
+            return;
+        };
+        match (command.run)(ctx, args) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => ctx.renderer.console_print(Level::Info, &output),
+            Err(e) => ctx
+                .renderer
+                .console_print(Level::Error, &format!("{:#}", e)),
+        }
+    }
+
+    fn help(&self, name: Option<&str>) -> String {
+        match name {
+            Some(name) => match self.commands.get(name) {
+                Some(command) => format!("{}: {}", name, command.help),
+                None => format!("unknown command {}", name),
+            },
+            None => self
+                .commands
+                .iter()
+                .map(|(name, command)| format!("{}: {}", name, command.help))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    fn register_builtins(&mut self) {
+        self.register("clear", "clears the console", |ctx, _| {
+            ctx.renderer.clear_console();
+            Ok(String::new())
+        });
+        self.register(
+            "clear_color",
+            "clear_color <r> <g> <b> [a], from 0 to 1",
+            |ctx, args| {
+                let channel = |index| arg::<f64>(args, index, "a color channel");
+                let alpha = match args.get(3) {
+                    Some(_) => channel(3)?,
+                    None => 1.0,
+                };
+                ctx.renderer.set_clear_color(wgpu::Color {
+                    r: channel(0)?,
+                    g: channel(1)?,
+                    b: channel(2)?,
+                    a: alpha,
+                });
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "spawn",
+            "spawn <layer> <asset pool> <texture> [x y], a sprite",
+            |ctx, args| {
+                let asset_pool = arg(args, 1, "an asset pool")?;
+                let (pool, texture) = ctx
+                    .pool_texture(asset_pool, arg(args, 2, "a texture")?)
+                    .with_context(|| format!("asset pool {} isn't loaded", asset_pool))?;
+                let mut sprite = Sprite {
+                    pool,
+                    texture,
+                    ..Default::default()
+                };
+                if args.len() > 3 {
+                    sprite.position = [arg(args, 3, "x")?, arg(args, 4, "y")?];
+                }
+                let layer = arg(args, 0, "a sprite layer")?;
+                let id = ctx
+                    .renderer
+                    .add_sprite(layer, sprite)
+                    .with_context(|| format!("no sprite layer {}", layer))?;
+                Ok(format!("spawned sprite {}", id))
+            },
+        );
+        self.register(
+            "layer",
+            "layer new [zindex] [sort], or layer <id> <zindex|sort|camera|mask|clip> <value>",
+            |ctx, args| {
+                let sort = |index| -> anyhow::Result<SortMode> {
+                    let name: String = arg(args, index, "a sort mode")?;
+                    SortMode::from_name(&name)
+                        .with_context(|| format!("no sort mode called {}", name))
+                };
+                if args.first().is_some_and(|arg| arg == "new") {
+                    let zindex = match args.get(1) {
+                        Some(_) => arg(args, 1, "a zindex")?,
+                        None => 0,
+                    };
+                    let sort = match args.get(2) {
+                        Some(_) => sort(2)?,
+                        None => SortMode::Insertion,
+                    };
+                    let id = ctx.renderer.create_layer(zindex, sort);
+                    return Ok(format!("created sprite layer {}", id));
+                }
+
+                let layer = arg(args, 0, "a sprite layer")?;
+                if layer >= ctx.renderer.layer_count() {
+                    anyhow::bail!("no sprite layer {}", layer);
+                }
+                match args.get(1).map(String::as_str) {
+                    Some("zindex") => ctx
+                        .renderer
+                        .set_layer_zindex(layer, arg(args, 2, "a zindex")?),
+                    Some("sort") => ctx.renderer.set_layer_sort(layer, sort(2)?),
+                    Some("camera") => {
+                        let camera = arg(args, 2, "a camera")?;
+                        if ctx.renderer.camera(camera).is_none() {
+                            anyhow::bail!("no camera {}", camera);
+                        }
+                        ctx.renderer.set_layer_camera(layer, camera);
+                    }
+                    // none, write <value>, test <value> or distort
+                    Some("mask") => {
+                        let mask = match args.get(2).map(String::as_str) {
+                            Some("none") => LayerMask::None,
+                            Some("write") => LayerMask::Write(arg(args, 3, "a stencil value")?),
+                            Some("test") => LayerMask::Test(arg(args, 3, "a stencil value")?),
+                            Some("distort") => LayerMask::Distort,
+                            _ => anyhow::bail!("expected none, write, test or distort"),
+                        };
+                        ctx.renderer.set_layer_mask(layer, mask);
+                    }
+                    // none or x y width height, in physical pixels
+                    Some("clip") => {
+                        let clip = match args.get(2).map(String::as_str) {
+                            Some("none") => None,
+                            _ => Some([
+                                arg(args, 2, "x")?,
+                                arg(args, 3, "y")?,
+                                arg(args, 4, "a width")?,
+                                arg(args, 5, "a height")?,
+                            ]),
+                        };
+                        ctx.renderer.set_layer_clip(layer, clip);
+                    }
+                    Some(option) => anyhow::bail!("no layer option {}", option),
+                    None => anyhow::bail!("expected zindex, sort, camera, mask or clip"),
+                }
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "asset",
+            "asset [id|path], or asset <move|forget> <id> [path], the asset manifest",
+            |ctx, args| {
+                let id = |index| -> anyhow::Result<AssetId> {
+                    let text: String = arg(args, index, "an asset id")?;
+                    AssetId::parse(&text).with_context(|| format!("{} is not an asset id", text))
+                };
+                let manifest = ctx.assets.manifest_mut();
+                let changed = match args.first().map(String::as_str) {
+                    None => return Ok(format!("{} assets in the manifest", manifest.len())),
+                    Some("move") => {
+                        let (id, path) = (id(1)?, arg::<String>(args, 2, "a path")?);
+                        if !manifest.relocate(id, &path) {
+                            anyhow::bail!("no asset {}", id);
+                        }
+                        format!("{} is now {}", id, path)
+                    }
+                    Some("forget") => {
+                        let id = id(1)?;
+                        let path = manifest
+                            .remove(id)
+                            .with_context(|| format!("no asset {}", id))?;
+                        format!("forgot {}, it was {}", id, path)
+                    }
+                    Some(text) => {
+                        return match AssetId::parse(text) {
+                            // packed builds only know their ids from the archive
+                            Some(id) => manifest
+                                .path_of(id)
+                                .or_else(|| pak::mounted()?.path_of(&id.to_string()))
+                                .map(|path| format!("{}: {}", id, path))
+                                .with_context(|| format!("no asset {}", id)),
+                            None => manifest
+                                .id_of(text)
+                                .map(|id| format!("{}: {}", id, text))
+                                .with_context(|| format!("{} is not in the manifest", text)),
+                        };
+                    }
+                };
+
+                // scenes find their textures through the renderer's copy
+                manifest.save()?;
+                ctx.assets.refresh_index();
+                ctx.renderer.set_asset_manifest(ctx.assets.manifest());
+                Ok(changed)
+            },
+        );
+        self.register(
+            "assets",
+            "assets [prefix], what discovery found by logical path",
+            |ctx, args| {
+                let index = ctx.assets.index();
+                if index.is_empty() {
+                    anyhow::bail!("no assets found by {:?}", ctx.assets.discovery_mode());
+                }
+                let prefix = args.first().map_or("", String::as_str);
+                let mut lines = vec![format!(
+                    "{} assets found by {:?}",
+                    index.len(),
+                    ctx.assets.discovery_mode()
+                )];
+                if let Some(source) = remote::mounted() {
+                    lines.push(format!("missing ones from {}", source.base_url()));
+                }
+                lines.extend(
+                    index
+                        .iter()
+                        .filter(|(logical, _)| logical.starts_with(prefix))
+                        .map(|(logical, asset)| {
+                            format!("{}: {:?} {}", logical, asset.kind, asset.path)
+                        }),
+                );
+                Ok(lines.join("\n"))
+            },
+        );
+        self.register(
+            "texture",
+            "texture <path>, the pool and id of a texture by logical path",
+            |ctx, args| {
+                let path: String = arg(args, 0, "a path")?;
+                let (pool, id) = ctx
+                    .texture(&path)
+                    .with_context(|| format!("no texture {}", path))?;
+                Ok(format!("texture {} of pool {}", id, pool))
+            },
+        );
+        self.register(
+            "camera",
+            "camera new, or camera <id> [x y [zoom] [seconds] [easing] | snap <on|off> | stop]",
+            |ctx, args| {
+                if args.first().is_some_and(|arg| arg == "new") {
+                    let id = ctx.renderer.create_camera(Camera2D::default());
+                    return Ok(describe_camera(ctx.renderer, id));
+                }
+
+                let id = arg(args, 0, "a camera")?;
+                let zoom = ctx
+                    .renderer
+                    .camera(id)
+                    .with_context(|| format!("no camera {}", id))?
+                    .zoom;
+                match args.get(1).map(String::as_str) {
+                    None => {}
+                    Some("snap") => {
+                        let snap = match on_off(args, 2)? {
+                            true => PixelSnap::Snapped,
+                            false => PixelSnap::Smooth,
+                        };
+                        ctx.renderer.camera_mut(id).context("no camera")?.snap = snap;
+                    }
+                    Some("stop") => ctx.renderer.stop_camera(id),
+                    Some(_) => {
+                        let position = [arg(args, 1, "x")?, arg(args, 2, "y")?];
+                        let zoom = match args.get(3) {
+                            Some(_) => arg(args, 3, "a zoom")?,
+                            None => zoom,
+                        };
+                        let seconds = match args.get(4) {
+                            Some(_) => arg(args, 4, "a duration in seconds")?,
+                            None => 0.0,
+                        };
+                        let easing = easing_arg(ctx.renderer, args, 5)?;
+                        ctx.renderer
+                            .move_camera(id, position, zoom, seconds, easing);
+                    }
+                }
+                Ok(describe_camera(ctx.renderer, id))
+            },
+        );
+        self.register(
+            "material",
+            "material <sprite> [flash|dissolve <v> [seconds [easing]] | hit r g b seconds | stop]",
+            |ctx, args| {
+                let id = sprite_arg(ctx.renderer, args, 0)?;
+                match args.get(1).map(String::as_str) {
+                    None => {}
+                    Some("stop") => ctx.renderer.stop_material_animation(id, None),
+                    Some("hit") => {
+                        let color = [
+                            arg(args, 2, "red")?,
+                            arg(args, 3, "green")?,
+                            arg(args, 4, "blue")?,
+                        ];
+                        let seconds = arg(args, 5, "a duration in seconds")?;
+                        ctx.renderer.flash_sprite(id, color, seconds);
+                    }
+                    Some(name) => {
+                        let param = match name {
+                            "flash" => MaterialParam::Flash,
+                            "dissolve" => MaterialParam::Dissolve,
+                            _ => anyhow::bail!("no material parameter {}", name),
+                        };
+                        let value = arg(args, 2, "a value")?;
+                        match args.get(3) {
+                            Some(_) => {
+                                let seconds = arg(args, 3, "a duration in seconds")?;
+                                let easing = easing_arg(ctx.renderer, args, 4)?;
+                                ctx.renderer
+                                    .animate_material(id, param, value, seconds, easing);
+                            }
+                            None => {
+                                let mut material =
+                                    ctx.renderer.sprite_material(id).context("no sprite")?;
+                                material.set(param, value);
+                                ctx.renderer.set_sprite_material(id, material);
+                            }
+                        }
+                    }
+                }
+                let material = ctx.renderer.sprite_material(id).context("no sprite")?;
+                Ok(format!(
+                    "flash {:.2}, dissolve {:.2}{}",
+                    material.get(MaterialParam::Flash),
+                    material.get(MaterialParam::Dissolve),
+                    match ctx.renderer.is_material_animating(id) {
+                        true => ", animating",
+                        false => "",
+                    }
+                ))
+            },
+        );
+        self.register(
+            "trail",
+            "trail add <sprite> [width] | trail <id> <clear|remove|emitting|width|alpha> [value]",
+            |ctx, args| {
+                if args.first().is_some_and(|arg| arg == "add") {
+                    let sprite = sprite_arg(ctx.renderer, args, 1)?;
+                    let mut trail = Trail {
+                        camera: ctx.renderer.sprite_camera(sprite).context("no sprite")?,
+                        ..Default::default()
+                    };
+                    if args.get(2).is_some() {
+                        trail.width = arg(args, 2, "a width")?;
+                    }
+                    let id = ctx.renderer.add_trail(sprite, trail).context("no sprite")?;
+                    return Ok(format!("trail {}", id));
+                }
+                let id = arg(args, 0, "a trail")?;
+                if ctx.renderer.trail(id).is_none() {
+                    anyhow::bail!("no trail {}", id);
+                }
+                // the default when no curve is given
+                let curve = || match args.get(2) {
+                    Some(_) => arg(args, 2, "a curve").map(Some),
+                    None => Ok(None),
+                };
+                match args.get(1).map(String::as_str) {
+                    Some("clear") => ctx.renderer.clear_trail(id),
+                    Some("remove") => ctx.renderer.remove_trail(id),
+                    Some("emitting") => {
+                        let emitting = on_off(args, 2)?;
+                        ctx.renderer.trail(id).context("no trail")?.emitting = emitting;
+                    }
+                    Some("width") => ctx
+                        .renderer
+                        .set_trail_curve(id, TrailCurve::Width, curve()?),
+                    Some("alpha") => ctx
+                        .renderer
+                        .set_trail_curve(id, TrailCurve::Alpha, curve()?),
+                    _ => anyhow::bail!("expected clear, remove, emitting, width or alpha"),
+                }
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "curve",
+            "curve <id> [<from> <to>], samples the curve or makes it a straight line",
+            |ctx, args| {
+                let id = arg(args, 0, "a curve")?;
+                if args.len() > 1 {
+                    let line = Curve::linear(arg(args, 1, "a start")?, arg(args, 2, "an end")?);
+                    ctx.renderer.set_curve(id, line);
+                }
+                let curve = ctx
+                    .renderer
+                    .curve(id)
+                    .with_context(|| format!("no curve {}", id))?;
+                Ok(format!(
+                    "{} keys, {:.2} {:.2} {:.2} at the start, middle and end",
+                    curve.keys().len(),
+                    curve.sample(0.0),
+                    curve.sample(0.5),
+                    curve.sample(1.0)
+                ))
+            },
+        );
+        self.register(
+            "particle_curve",
+            "particle_curve <system> <size|alpha> [curve], the default without a curve",
+            |ctx, args| {
+                let system = arg(args, 0, "a particle system")?;
+                if ctx.renderer.particle_emitter(system).is_none() {
+                    anyhow::bail!("no particle system {}", system);
+                }
+                let target = match args.get(1).map(String::as_str) {
+                    Some("size") => ParticleCurve::Size,
+                    Some("alpha") => ParticleCurve::Alpha,
+                    _ => anyhow::bail!("expected size or alpha"),
+                };
+                let curve = match args.get(2) {
+                    Some(_) => Some(arg(args, 2, "a curve")?),
+                    None => None,
+                };
+                ctx.renderer.set_particle_curve(system, target, curve);
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "sprite",
+            "sprite <id|name> [<option> <value>], visible, opacity, outline, palette, name or tag",
+            |ctx, args| {
+                let id = sprite_arg(ctx.renderer, args, 0)?;
+                match args.get(1).map(String::as_str) {
+                    None => {}
+                    Some("outline") => {
+                        let outline = match args.get(2).map(String::as_str) {
+                            Some("off") => None,
+                            _ => Some(Outline {
+                                width: arg(args, 2, "a width in pixels")?,
+                                ..Default::default()
+                            }),
+                        };
+                        ctx.renderer.set_sprite_outline(id, outline);
+                    }
+                    Some("name") => {
+                        let name: String = arg(args, 2, "a name")?;
+                        ctx.renderer.registry_mut().set_name(id, &name);
+                    }
+                    Some("tag") => {
+                        let tag: String = arg(args, 2, "a tag")?;
+                        ctx.renderer.registry_mut().add_tag(id, &tag);
+                    }
+                    Some("palette") => match args.get(2).map(String::as_str) {
+                        Some("off") => ctx.renderer.set_sprite_palette(id, None),
+                        Some("row") => ctx
+                            .renderer
+                            .set_sprite_palette_row(id, arg(args, 3, "a palette row")?),
+                        _ => {
+                            let mut palette = SpritePalette::new(
+                                arg(args, 2, "a texture pool")?,
+                                arg(args, 3, "a palette texture")?,
+                            );
+                            if args.get(4).is_some() {
+                                palette = palette.row(arg(args, 4, "a palette row")?);
+                            }
+                            ctx.renderer.set_sprite_palette(id, Some(palette));
+                        }
+                    },
+                    Some("visible") => ctx.renderer.set_sprite_visible(id, on_off(args, 2)?),
+                    Some("opacity") => ctx
+                        .renderer
+                        .set_sprite_opacity(id, arg(args, 2, "an opacity")?),
+                    Some(option) => anyhow::bail!("no sprite option {}", option),
+                }
+                Ok(describe_sprite(ctx.renderer, id))
+            },
+        );
+        self.register(
+            "find",
+            "find <name>, find tag <tags>, or find policy <unique|suffix|duplicates>",
+            |ctx, args| {
+                let registry = ctx.renderer.registry_mut();
+                let ids: Vec<usize> = match args.first().map(String::as_str) {
+                    Some("tag") => {
+                        let tags: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+                        registry.with_tags(&tags).collect()
+                    }
+                    // what naming a sprite does when the name is taken
+                    Some("policy") => {
+                        registry.policy = match args.get(1).map(String::as_str) {
+                            Some("unique") => NamePolicy::Unique,
+                            Some("suffix") => NamePolicy::Suffix,
+                            Some("duplicates") => NamePolicy::Duplicates,
+                            _ => anyhow::bail!("expected unique, suffix or duplicates"),
+                        };
+                        return Ok(String::new());
+                    }
+                    Some(name) => registry.find_all_by_name(name),
+                    None => anyhow::bail!("missing a name"),
+                };
+                if ids.is_empty() {
+                    return Ok("no sprites found".to_string());
+                }
+                Ok(ids
+                    .iter()
+                    .map(|id| describe_sprite(ctx.renderer, *id))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            },
+        );
+        self.register(
+            "spatial",
+            "spatial <layer> [x y [distance]], the layer's spatial index or its nearest sprite",
+            |ctx, args| {
+                let layer = arg(args, 0, "a sprite layer")?;
+                let Some(grid) = ctx.renderer.layer_grid(layer) else {
+                    anyhow::bail!("no sprite layer {}", layer);
+                };
+                if args.len() == 1 {
+                    return Ok(format!(
+                        "{} sprites in {}px cells, {} overlapping pairs",
+                        grid.len(),
+                        grid.cell_size(),
+                        grid.pairs().len()
+                    ));
+                }
+                let point = [arg(args, 1, "x")?, arg(args, 2, "y")?];
+                let distance = match args.get(3) {
+                    Some(_) => arg(args, 3, "a distance")?,
+                    None => f32::INFINITY,
+                };
+                match grid.nearest(point, distance) {
+                    Some((id, distance)) => {
+                        let [x, y, width, height] = grid.bounds(id).unwrap_or_default();
+                        Ok(format!(
+                            "sprite {} is {:.1} away, {:.1}, {:.1} {:.1}x{:.1}",
+                            id, distance, x, y, width, height
+                        ))
+                    }
+                    None => Ok("no sprite in reach".to_string()),
+                }
+            },
+        );
+        self.register("load_scene", "load_scene <path>", |ctx, args| {
+            let path: String = arg(args, 0, "a path")?;
+            let ids = ctx.renderer.load_scene(&path)?;
+            if ctx.renderer.is_scene_downloading() {
+                return Ok(format!("downloading {}", path));
+            }
+            Ok(format!("loaded {}, {} entities", path, ids.len()))
+        });
+        self.register("save_scene", "save_scene [path]", |ctx, args| {
+            match args.first() {
+                Some(path) => ctx.renderer.save_scene_as(path)?,
+                None => ctx.renderer.save_scene()?,
+            }
+            Ok(String::new())
+        });
+        self.register(
+            "close_scene",
+            "close_scene, keeps the sprites but stops tracking them as the scene",
+            |ctx, _| {
+                let path = ctx.renderer.scene_path().context("no scene is open")?;
+                let closed = format!("closed {}", path.display());
+                ctx.renderer.close_scene();
+                Ok(closed)
+            },
+        );
+        self.register("passes", "lists the custom render passes", |ctx, _| {
+            let passes = ctx.renderer.render_passes();
+            if passes.is_empty() {
+                return Ok("no custom render passes".to_string());
+            }
+            Ok(passes
+                .iter()
+                .map(|(id, name, stage, enabled)| {
+                    let state = if *enabled { "on" } else { "off" };
+                    format!("{}: {} at {:?}, {}", id, name, stage, state)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        });
+        self.register("pass", "pass <id> <on|off|remove>", |ctx, args| {
+            let id = arg(args, 0, "a pass id")?;
+            if !ctx.renderer.render_passes().iter().any(|pass| pass.0 == id) {
+                anyhow::bail!("no render pass {}", id);
+            }
+            match args.get(1).map(String::as_str) {
+                Some("remove") => ctx.renderer.remove_render_pass(id),
+                _ => ctx.renderer.set_render_pass_enabled(id, on_off(args, 1)?),
+            }
+            Ok(String::new())
+        });
+        self.register(
+            "shader_errors",
+            "shader_errors [clear], the shaders that failed to build with their reports",
+            |ctx, args| {
+                if args.first().is_some_and(|arg| arg == "clear") {
+                    ctx.renderer.clear_shader_errors();
+                }
+                let errors = ctx.renderer.shader_errors();
+                if errors.is_empty() {
+                    return Ok("no shader errors".to_string());
+                }
+                Ok(errors
+                    .iter()
+                    .map(|diagnostic| format!("{}\n{}", diagnostic, diagnostic.report))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            },
+        );
+        self.register(
+            "present",
+            "present [vsync <off|on|adaptive|mailbox> | latency <frames> | alpha <mode>]",
+            |ctx, args| {
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("vsync") => {
+                        let vsync = match args.get(1).map(String::as_str) {
+                            Some("off") => Vsync::Off,
+                            Some("on") => Vsync::On,
+                            Some("adaptive") => Vsync::Adaptive,
+                            Some("mailbox") => Vsync::Mailbox,
+                            _ => anyhow::bail!("expected off, on, adaptive or mailbox"),
+                        };
+                        ctx.renderer.set_vsync(vsync);
+                    }
+                    Some("latency") => {
+                        ctx.renderer
+                            .set_frame_latency(arg(args, 1, "a number of frames")?)
+                    }
+                    Some("alpha") => {
+                        let mode = match args.get(1).map(String::as_str) {
+                            Some("auto") => wgpu::CompositeAlphaMode::Auto,
+                            Some("opaque") => wgpu::CompositeAlphaMode::Opaque,
+                            Some("premultiplied") => wgpu::CompositeAlphaMode::PreMultiplied,
+                            Some("postmultiplied") => wgpu::CompositeAlphaMode::PostMultiplied,
+                            Some("inherit") => wgpu::CompositeAlphaMode::Inherit,
+                            _ => anyhow::bail!("expected an alpha mode"),
+                        };
+                        if !ctx.renderer.set_alpha_mode(mode) {
+                            anyhow::bail!("the surface can't use {:?}", mode);
+                        }
+                    }
+                    Some(option) => anyhow::bail!("no present option {}", option),
+                }
+                Ok(format!(
+                    "{:?} for {:?} vsync, latency {}, alpha {:?} of {:?}",
+                    ctx.renderer.present_mode(),
+                    ctx.renderer.vsync(),
+                    ctx.renderer.frame_latency(),
+                    ctx.renderer.alpha_mode(),
+                    ctx.renderer.supported_alpha_modes()
+                ))
+            },
+        );
+        self.register(
+            "pick_buffer",
+            "pick_buffer [on|off], exact gizmo picking through an id buffer",
+            |ctx, args| {
+                if !args.is_empty() {
+                    ctx.renderer.set_pick_buffer(on_off(args, 0)?);
+                }
+                Ok(match ctx.renderer.has_pick_buffer() {
+                    true => "picking through the id buffer".to_string(),
+                    false => "picking by sprite bounds".to_string(),
+                })
+            },
+        );
+        self.register(
+            "latency",
+            "latency [on|off], measures input to present latency, or prints what it measured",
+            |ctx, args| {
+                if !args.is_empty() {
+                    ctx.renderer.set_latency_measurement(on_off(args, 0)?);
+                }
+                if !ctx.renderer.is_measuring_latency() {
+                    return Ok("not measuring".to_string());
+                }
+                let report = ctx.renderer.latency_report();
+                Ok(format!(
+                    "{} samples, {:.2}ms avg, {:.2}ms p95, {:.2}-{:.2}ms, {:.2}ms waiting",
+                    report.samples,
+                    report.average_ms,
+                    report.p95_ms,
+                    report.min_ms,
+                    report.max_ms,
+                    report.waiting_ms
+                ))
+            },
+        );
+        self.register(
+            "record",
+            "record [directory], starts recording frames there, or stops the recording",
+            |ctx, args| {
+                if ctx.renderer.is_recording() {
+                    let status = ctx.renderer.stop_recording().context("no recording")?;
+                    return Ok(format!(
+                        "recorded {} frames, {} dropped",
+                        status.frames, status.dropped
+                    ));
+                }
+                let directory = args.first().map_or(PathBuf::from("."), PathBuf::from);
+                let path = ctx
+                    .renderer
+                    .start_recording(RecordingOptions {
+                        directory,
+                        ..Default::default()
+                    })
+                    .context("failed to start recording")?;
+                Ok(format!("recording to {}", path.display()))
+            },
+        );
+        self.register(
+            "profile",
+            "profile [filter], the last frame's cpu scopes in ms, last, average and peak",
+            |ctx, args| {
+                let filter = args.first().map_or("", String::as_str);
+                Ok(ctx
+                    .renderer
+                    .profile_entries()
+                    .iter()
+                    .filter(|entry| entry.path.contains(filter))
+                    .map(|entry| {
+                        format!(
+                            "{}: {:.2} {:.2} {:.2}",
+                            entry.path, entry.last_ms, entry.average_ms, entry.peak_ms
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            },
+        );
+        self.register(
+            "dump_frame",
+            "dump_frame [path], the next frame's passes and draws as toml, or text for .txt",
+            |ctx, args| {
+                let path = args.first().map(PathBuf::from);
+                let target = path
+                    .as_ref()
+                    .map_or("frame-<index>.toml".to_string(), |path| {
+                        path.display().to_string()
+                    });
+                ctx.renderer.dump_next_frame(path);
+                Ok(format!("dumping the next frame to {}", target))
+            },
+        );
+        self.register(
+            "copy",
+            "copy, the selected sprite to the os clipboard as toml",
+            |ctx, _| {
+                if !ctx.renderer.copy_selection() {
+                    anyhow::bail!("nothing selected");
+                }
+                let text = ctx.renderer.entity_clipboard().context("nothing copied")?;
+                match clipboard::set(text) {
+                    true => Ok("copied".to_string()),
+                    false => anyhow::bail!("the os clipboard is unavailable"),
+                }
+            },
+        );
+        self.register(
+            "paste",
+            "paste <layer>, sprites copied to the os clipboard where they were",
+            |ctx, args| {
+                let layer = arg(args, 0, "a sprite layer")?;
+                let text = clipboard::get().context("the os clipboard is empty")?;
+                if !ctx.renderer.set_entity_clipboard(&text) {
+                    anyhow::bail!("the clipboard doesn't hold sprites");
+                }
+                let ids = ctx.renderer.paste_entities(layer, [0.0, 0.0]);
+                Ok(format!("pasted {} sprites", ids.len()))
+            },
+        );
+        self.register(
+            "screenshot",
+            "screenshot <path>, saves the next frame without the debug ui",
+            |ctx, args| {
+                let path: String = arg(args, 0, "a path")?;
+                ctx.renderer.request_screenshot(&path);
+                Ok(format!("saving the next frame to {}", path))
+            },
+        );
+        self.register(
+            "capture",
+            "capture, the next frame in renderdoc or xcode when one is attached",
+            |ctx, _| {
+                ctx.renderer.capture_next_frame();
+                Ok("capturing the next frame".to_string())
+            },
+        );
+        self.register(
+            "sound",
+            "sound <path> [pool], decodes the sound once, found by logical path without a pool",
+            |ctx, args| {
+                let path: String = arg(args, 0, "a path")?;
+                let handle = match args.get(1) {
+                    Some(_) => ctx
+                        .audio
+                        .find_or_load(arg(args, 1, "an asset pool")?, &path),
+                    None => ctx
+                        .sound(&path)
+                        .with_context(|| format!("no sound {}", path))?,
+                };
+                let sound = ctx.audio.sound(handle).context("no sound")?;
+                Ok(format!(
+                    "{:?}: {:.2}s of {} frames, {}hz, {} channels, {} of sounds resident",
+                    handle,
+                    sound.seconds(),
+                    sound.frames(),
+                    sound.sample_rate,
+                    sound.channels,
+                    format_bytes(ctx.audio.resident_bytes())
+                ))
+            },
+        );
+        self.register(
+            "audio",
+            "audio [play|music <path> [fade] | stop [fade] | <bus> <volume|mute|unmute>]",
+            |ctx, args| {
+                let fade = |index| match args.get(index) {
+                    Some(_) => arg(args, index, "a fade in seconds"),
+                    None => Ok(ctx.audio.config().crossfade_seconds),
+                };
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("play") => {
+                        let path: String = arg(args, 1, "a path")?;
+                        let handle = ctx.audio.find_or_load(0, &path);
+                        ctx.audio
+                            .play(handle, Bus::Sfx)
+                            .with_context(|| format!("failed to play {}", path))?;
+                    }
+                    Some("music") => {
+                        let path: String = arg(args, 1, "a path")?;
+                        let fade = fade(2)?;
+                        let handle = ctx.audio.find_or_load(0, &path);
+                        let voice = match args.get(2) {
+                            Some(_) => ctx.audio.play_music_with_fade(handle, fade),
+                            None => ctx.audio.play_music(handle),
+                        };
+                        voice.with_context(|| format!("failed to play {}", path))?;
+                    }
+                    Some("stop") => {
+                        let fade = fade(1)?;
+                        ctx.audio.stop_music(fade);
+                    }
+                    Some(name) => {
+                        let bus = Bus::ALL
+                            .into_iter()
+                            .find(|bus| format!("{:?}", bus).eq_ignore_ascii_case(name))
+                            .with_context(|| format!("no audio bus {}", name))?;
+                        match args.get(1).map(String::as_str) {
+                            Some("mute") => ctx.audio.set_bus_muted(bus, true),
+                            Some("unmute") => ctx.audio.set_bus_muted(bus, false),
+                            _ => ctx.audio.set_bus_volume(bus, arg(args, 1, "a volume")?),
+                        }
+                    }
+                }
+                let mut lines: Vec<String> = Bus::ALL
+                    .into_iter()
+                    .map(|bus| {
+                        let level = ctx.audio.bus(bus);
+                        let muted = if level.muted { ", muted" } else { "" };
+                        format!("{:?} {:.2}{}", bus, level.volume, muted)
+                    })
+                    .collect();
+                let mixer = ctx.audio.mixer();
+                let music = mixer.music().filter(|voice| mixer.is_playing(*voice));
+                lines.push(format!(
+                    "{} voices, music {}",
+                    mixer.voice_count(),
+                    if music.is_some() {
+                        "playing"
+                    } else {
+                        "stopped"
+                    }
+                ));
+                Ok(lines.join("\n"))
+            },
+        );
+        self.register(
+            "weather",
+            "weather <rain|snow|fog|clear> [intensity] [wind], wind in pixels per second",
+            |ctx, args| {
+                let name: String = arg(args, 0, "a kind of weather")?;
+                if name == "clear" {
+                    ctx.renderer.clear_weather();
+                    return Ok(String::new());
+                }
+                let kind = WeatherKind::from_name(&name)
+                    .with_context(|| format!("no weather called {}", name))?;
+                let intensity = match args.get(1) {
+                    Some(_) => arg(args, 1, "an intensity")?,
+                    None => 1.0,
+                };
+                let id = match ctx.renderer.find_weather(kind) {
+                    Some(id) => id,
+                    None => ctx
+                        .renderer
+                        .add_weather(Weather::new(kind))
+                        .context("failed to create the weather's particles")?,
+                };
+                let weather = ctx.renderer.weather(id).context("no weather")?;
+                weather.intensity = intensity;
+                if args.len() > 2 {
+                    weather.wind = arg(args, 2, "a wind speed")?;
+                }
+                Ok(format!("{} at {}", name, intensity))
+            },
+        );
+        self.register(
+            "lighting",
+            "lighting [r g b [exposure] [grading] [seconds]], or lighting lut <path|none>",
+            |ctx, args| {
+                if args.first().is_some_and(|arg| arg == "lut") {
+                    let path: String = arg(args, 1, "a path or none")?;
+                    let path = (path != "none").then_some(path.as_str());
+                    ctx.renderer.set_color_grading_lut(path);
+                    return Ok(String::new());
+                }
+                if !args.is_empty() {
+                    let current = ctx.renderer.lighting();
+                    let optional = |index, default, what| match args.get(index) {
+                        Some(_) => arg(args, index, what),
+                        None => Ok(default),
+                    };
+                    let environment = LightingEnvironment {
+                        ambient: [
+                            arg(args, 0, "red")?,
+                            arg(args, 1, "green")?,
+                            arg(args, 2, "blue")?,
+                        ],
+                        exposure: optional(3, current.exposure, "an exposure")?,
+                        grading: optional(4, current.grading, "a grading amount")?,
+                    };
+                    match args.get(5) {
+                        Some(_) => ctx.renderer.transition_lighting(
+                            environment,
+                            arg(args, 5, "a duration in seconds")?,
+                            Easing::default(),
+                        ),
+                        None => ctx.renderer.set_lighting(environment),
+                    }
+                }
+                let lighting = ctx.renderer.lighting();
+                Ok(format!(
+                    "ambient {:.2} {:.2} {:.2}, exposure {:.2}, grading {:.2}",
+                    lighting.ambient[0],
+                    lighting.ambient[1],
+                    lighting.ambient[2],
+                    lighting.exposure,
+                    lighting.grading
+                ))
+            },
+        );
+        self.register(
+            "distortion",
+            "distortion off, or distortion <pool> <texture> [strength] [scale], the offset map",
+            |ctx, args| {
+                if args.first().is_some_and(|arg| arg == "off") {
+                    ctx.renderer.set_distortion(None);
+                    return Ok(String::new());
+                }
+                if !args.is_empty() {
+                    let mut distortion = ctx.renderer.distortion().unwrap_or_default();
+                    distortion.pool = arg(args, 0, "a pool")?;
+                    distortion.texture = arg(args, 1, "a texture")?;
+                    if args.len() > 2 {
+                        distortion.strength = arg(args, 2, "a strength in pixels")?;
+                    }
+                    if args.len() > 3 {
+                        distortion.scale = arg(args, 3, "a scale in pixels")?;
+                    }
+                    ctx.renderer.set_distortion(Some(distortion));
+                }
+                Ok(match ctx.renderer.distortion() {
+                    Some(distortion) => format!(
+                        "texture {} of pool {}, {:.1}px strength, repeats every {:.1}px",
+                        distortion.texture, distortion.pool, distortion.strength, distortion.scale
+                    ),
+                    None => "distortion off".to_string(),
+                })
+            },
+        );
+        self.register(
+            "accessibility",
+            "accessibility [on|off|events], or <speech|contrast> <on|off>, or say <text>",
+            |ctx, args| {
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("on") => ctx.renderer.set_accessibility_enabled(true),
+                    Some("off") => ctx.renderer.set_accessibility_enabled(false),
+                    // drains them, a game listening for them misses these
+                    Some("events") => {
+                        return Ok(ctx
+                            .renderer
+                            .take_accessibility_events()
+                            .iter()
+                            .map(|event| format!("{:?}", event))
+                            .collect::<Vec<_>>()
+                            .join("\n"));
+                    }
+                    Some("speech") => {
+                        let speech = match on_off(args, 1)? {
+                            true => Some(Box::new(
+                                CommandSpeech::detect().context("no speech command found")?,
+                            ) as Box<dyn SpeechBackend>),
+                            false => None,
+                        };
+                        ctx.renderer.set_speech_backend(speech);
+                    }
+                    Some("contrast") => ctx.renderer.set_high_contrast(on_off(args, 1)?),
+                    Some("say") => ctx.renderer.announce(&args[1..].join(" ")),
+                    Some(option) => anyhow::bail!("no accessibility option {}", option),
+                }
+                let state = |on| if on { "on" } else { "off" };
+                Ok(format!(
+                    "accessibility {}, high contrast {}",
+                    state(ctx.renderer.is_accessibility_enabled()),
+                    state(ctx.renderer.is_high_contrast())
+                ))
+            },
+        );
+        self.register(
+            "theme",
+            "theme [name], switches the ui theme, settings overrides stay on top",
+            |ctx, args| {
+                if let Some(name) = args.first() {
+                    if !BUILTIN_THEMES.contains(&name.as_str()) {
+                        anyhow::bail!("no theme called {}", name);
+                    }
+                    ctx.settings.theme = name.clone();
+                    ctx.renderer.set_theme(ctx.settings.theme());
+                }
+                Ok(format!(
+                    "{}, of {}",
+                    ctx.settings.theme,
+                    BUILTIN_THEMES.join(", ")
+                ))
+            },
+        );
+        self.register(
+            "rumble",
+            "rumble [strong weak seconds], rumble stop [id], or rumble <id> to check on it",
+            |ctx, args| {
+                match args.len() {
+                    0 => return Ok(format!("rumble scale {:.2}", ctx.input.rumble_scale())),
+                    1 | 2 if args[0] == "stop" => match args.get(1) {
+                        Some(_) => ctx.input.stop_rumble(arg(args, 1, "a rumble id")?),
+                        None => ctx.input.stop_all_rumble(),
+                    },
+                    1 => {
+                        let id = arg(args, 0, "a rumble id")?;
+                        return Ok(match ctx.input.is_rumbling(id) {
+                            true => format!("rumble {} is playing", id),
+                            false => format!("rumble {} has stopped", id),
+                        });
+                    }
+                    _ => {
+                        let id = ctx.input.rumble(None, rumble_arg(args, 0)?);
+                        return Ok(format!("rumble {}", id));
+                    }
+                }
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "haptic",
+            "haptic <name> [strong weak seconds], defines the named effect or plays it",
+            |ctx, args| {
+                let name: String = arg(args, 0, "a haptic name")?;
+                if args.len() > 1 {
+                    ctx.input.set_haptic(&name, rumble_arg(args, 1)?);
+                    return Ok(String::new());
+                }
+                let id = ctx
+                    .input
+                    .trigger_haptic(&name, None)
+                    .with_context(|| format!("no haptic named {}", name))?;
+                Ok(format!("rumble {}", id))
+            },
+        );
+        self.register(
+            "timeline",
+            "timeline [frames|live], steps through the recorded history, negative is back",
+            |ctx, args| {
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("live") => ctx.renderer.resume_timeline(),
+                    Some(_) => ctx
+                        .renderer
+                        .step_timeline(arg(args, 0, "a number of frames")?),
+                }
+                let recorded = ctx.renderer.timeline_len();
+                Ok(match ctx.renderer.is_time_travelling() {
+                    true => format!("in the past, {} frames recorded", recorded),
+                    false => format!("live, {} frames recorded", recorded),
+                })
+            },
+        );
+        self.register(
+            "flash",
+            "flash [seconds], flashes the screen red and splits its colors",
+            |ctx, args| {
+                let seconds = match args.first() {
+                    Some(_) => arg(args, 0, "a duration in seconds")?,
+                    None => 0.3,
+                };
+                ctx.renderer.flash_screen([1.0, 0.1, 0.1, 0.6], seconds);
+                ctx.renderer.pulse_chromatic_aberration(8.0, seconds);
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "net",
+            "net [lead <ticks> | resync], the server clock's tick, rtt and drift",
+            |ctx, args| {
+                let clock = ctx
+                    .net_clock
+                    .as_deref_mut()
+                    .context("no net clock, start with --net-tick-rate")?;
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("lead") => clock.set_lead(arg(args, 1, "a number of ticks")?),
+                    Some("resync") => clock.reset_sync(),
+                    Some(option) => anyhow::bail!("no net option {}", option),
+                }
+                if !clock.is_synced() {
+                    return Ok(format!("tick {}, waiting for a pong", clock.tick()));
+                }
+                Ok(format!(
+                    "tick {} of the server's {}, rtt {:.1}ms ±{:.1}, offset {:.3}s, speed {:.3}",
+                    clock.tick(),
+                    clock.server_tick().unwrap_or_default(),
+                    clock.rtt().unwrap_or_default().as_secs_f64() * 1000.0,
+                    clock.jitter().as_secs_f64() * 1000.0,
+                    clock.offset().unwrap_or_default(),
+                    clock.dilation()
+                ))
+            },
+        );
+        self.register(
+            "integrations",
+            "integrations [presence <str> | unlock <id> | progress <id> <n> <max> | remove <name>]",
+            |ctx, args| {
+                let integrations = &mut *ctx.integrations;
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("presence") => integrations.set_presence(Presence {
+                        details: args[1..].join(" "),
+                        ..integrations.presence().clone()
+                    }),
+                    Some("unlock") => {
+                        let id: String = arg(args, 1, "an achievement")?;
+                        if integrations.is_achievement_unlocked(&id) {
+                            return Ok(format!("{} is already unlocked", id));
+                        }
+                        integrations.unlock_achievement(&id);
+                    }
+                    Some("progress") => integrations.set_achievement_progress(
+                        &arg::<String>(args, 1, "an achievement")?,
+                        arg(args, 2, "the progress")?,
+                        arg(args, 3, "the maximum")?,
+                    ),
+                    Some("remove") => integrations.remove(&arg::<String>(args, 1, "a name")?),
+                    Some(option) => anyhow::bail!("no integrations option {}", option),
+                }
+                let names: Vec<_> = integrations.names().collect();
+                let mut lines = vec![match names.is_empty() {
+                    true => "no integrations".to_string(),
+                    false => names.join(", "),
+                }];
+                let presence = integrations.presence();
+                if presence != &Presence::default() {
+                    lines.push(format!("presence: {} {}", presence.details, presence.state));
+                }
+                Ok(lines.join("\n"))
+            },
+        );
+        self.register(
+            "viewport",
+            "viewport [aspect <fill|ratio> | bars r g b | insets l t r b | hud <off|ratio>]",
+            |ctx, args| {
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("aspect") => {
+                        let aspect = match args.get(1).map(String::as_str) {
+                            Some("fill") => AspectRatio::Fill,
+                            _ => AspectRatio::Fixed(arg(args, 1, "an aspect ratio")?),
+                        };
+                        ctx.renderer.set_aspect_ratio(aspect);
+                    }
+                    Some("bars") => ctx.renderer.set_letterbox_color(wgpu::Color {
+                        r: arg(args, 1, "red")?,
+                        g: arg(args, 2, "green")?,
+                        b: arg(args, 3, "blue")?,
+                        a: 1.0,
+                    }),
+                    Some("insets") => ctx.renderer.set_safe_area_insets([
+                        arg(args, 1, "a left inset")?,
+                        arg(args, 2, "a top inset")?,
+                        arg(args, 3, "a right inset")?,
+                        arg(args, 4, "a bottom inset")?,
+                    ]),
+                    Some("hud") => {
+                        let aspect = match args.get(1).map(String::as_str) {
+                            Some("off") => None,
+                            _ => Some(arg(args, 1, "an aspect ratio")?),
+                        };
+                        ctx.renderer.set_max_hud_aspect(aspect);
+                    }
+                    Some(option) => anyhow::bail!("no viewport option {}", option),
+                }
+                let [x, y, width, height] = ctx.renderer.viewport();
+                let [left, top, safe_width, safe_height] = ctx.renderer.safe_area();
+                Ok(format!(
+                    "{:?} at {}, {} {}x{}, hud in {}, {} {}x{}",
+                    ctx.renderer.aspect_ratio(),
+                    x,
+                    y,
+                    width,
+                    height,
+                    left,
+                    top,
+                    safe_width,
+                    safe_height
+                ))
+            },
+        );
+        self.register(
+            "fade",
+            "fade [r g b amount [seconds [easing]] | clear], clear drops every screen effect",
+            |ctx, args| {
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("clear") => ctx.renderer.clear_screen_fx(),
+                    Some(_) => {
+                        let color = [
+                            arg(args, 0, "red")?,
+                            arg(args, 1, "green")?,
+                            arg(args, 2, "blue")?,
+                        ];
+                        let amount = arg(args, 3, "an amount")?;
+                        let seconds = match args.get(4) {
+                            Some(_) => arg(args, 4, "a duration in seconds")?,
+                            None => 0.0,
+                        };
+                        let easing = easing_arg(ctx.renderer, args, 5)?;
+                        ctx.renderer.fade_screen(color, amount, seconds, easing);
+                    }
+                }
+                Ok(format!("faded {:.2}", ctx.renderer.screen_fade()))
+            },
+        );
+        self.register(
+            "skybox",
+            "skybox [<panorama> | <6 faces> | off | exposure <stops> | look <yaw> <pitch> [fov]]",
+            |ctx, args| {
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("off") => ctx.renderer.set_skybox(None),
+                    Some("exposure") => {
+                        ctx.renderer
+                            .set_skybox_exposure(arg(args, 1, "an exposure in stops")?)
+                    }
+                    Some("look") => {
+                        let view = ctx.renderer.sky_view();
+                        let degrees = |index, what| -> anyhow::Result<f32> {
+                            Ok(arg::<f32>(args, index, what)?.to_radians())
+                        };
+                        ctx.renderer.set_sky_view(SkyView {
+                            yaw: degrees(1, "a yaw in degrees")?,
+                            pitch: degrees(2, "a pitch in degrees")?,
+                            fov: match args.get(3) {
+                                Some(_) => degrees(3, "a field of view in degrees")?,
+                                None => view.fov,
+                            },
+                        });
+                    }
+                    Some(_) => {
+                        let source = match args.len() {
+                            1 => CubemapSource::Equirect(&args[0]),
+                            6 => CubemapSource::Faces(std::array::from_fn(|i| args[i].as_str())),
+                            _ => anyhow::bail!("expected a panorama or six faces"),
+                        };
+                        let cubemap = DecodedCubemap::open(source)?;
+                        let bytes = cubemap.bytes();
+                        ctx.renderer.set_skybox(Some(cubemap));
+                        return Ok(format!("skybox of {}", format_bytes(bytes)));
+                    }
+                }
+                if !ctx.renderer.has_skybox() {
+                    return Ok("no skybox".to_string());
+                }
+                let view = ctx.renderer.sky_view();
+                Ok(format!(
+                    "looking at {:.0}° {:.0}° with {:.0}° fov, exposure {:+.1}",
+                    view.yaw.to_degrees(),
+                    view.pitch.to_degrees(),
+                    view.fov.to_degrees(),
+                    ctx.renderer.skybox_exposure()
+                ))
+            },
+        );
+        self.register(
+            "model",
+            "model <name|quad|plane|cube|sphere|capsule>, lists an obj, gltf or builtin model",
+            |_, args| {
+                let name: String = arg(args, 0, "a model")?;
+                // the builtin ones at unit size
+                let builtin = match name.as_str() {
+                    "quad" => Some(primitives::quad([1.0, 1.0])),
+                    "plane" => Some(primitives::plane([1.0, 1.0], 4)),
+                    "cube" => Some(primitives::cube([1.0, 1.0, 1.0])),
+                    "sphere" => Some(primitives::sphere(0.5, 16, 8)),
+                    "capsule" => Some(primitives::capsule(0.5, 2.0, 16, 8)),
+                    _ => None,
+                };
+                let model = match builtin {
+                    Some(mesh) => DecodedModel {
+                        name: mesh.name.clone(),
+                        meshes: vec![mesh],
+                        materials: Vec::new(),
+                    },
+                    None => DecodedModel::open(&name)?,
+                };
+                let mut lines = vec![format!(
+                    "{}: {} meshes, {} triangles",
+                    model.name,
+                    model.meshes.len(),
+                    model.triangle_count()
+                )];
+                for mesh in model.meshes.iter() {
+                    let material = mesh.material.and_then(|index| model.materials.get(index));
+                    lines.push(format!(
+                        "  {}: {} vertices, {} triangles, {}",
+                        mesh.name,
+                        mesh.vertices.len(),
+                        mesh.indices.len() / 3,
+                        material.map_or("no material", |material| material.name.as_str())
+                    ));
+                }
+                for material in model.materials.iter() {
+                    lines.push(format!(
+                        "  {}: color {:?}, emissive {:?}, {}",
+                        material.name,
+                        material.base_color,
+                        material.emissive,
+                        material.texture.as_deref().unwrap_or("no texture")
+                    ));
+                }
+                Ok(lines.join("\n"))
+            },
+        );
+        self.register(
+            "grid",
+            "grid [on|off|spacing <units>|snap <x> <y>], toggles the editor grid without one",
+            |ctx, args| {
+                match args.first().map(String::as_str) {
+                    None => _ = ctx.renderer.toggle_grid(),
+                    Some("spacing") => {
+                        let mut overlay = ctx.renderer.grid_overlay();
+                        overlay.spacing = arg(args, 1, "a spacing")?;
+                        ctx.renderer.set_grid_overlay(overlay);
+                    }
+                    Some("snap") => {
+                        let [x, y] = ctx
+                            .renderer
+                            .snap_to_grid([arg(args, 1, "x")?, arg(args, 2, "y")?]);
+                        return Ok(format!("{:.2}, {:.2}", x, y));
+                    }
+                    Some(_) => ctx.renderer.show_grid(on_off(args, 0)?),
+                }
+                let shown = ctx.renderer.is_grid_visible();
+                Ok(format!(
+                    "grid {}, {} units apart",
+                    if shown { "shown" } else { "hidden" },
+                    ctx.renderer.grid_overlay().spacing
+                ))
+            },
+        );
+        self.register(
+            "debug_view",
+            "debug_view [off|wireframe|normals|overdraw|uv], what the sprite pass draws",
+            |ctx, args| {
+                let Some(name) = args.first() else {
+                    return Ok(format!("debug view {}", ctx.renderer.debug_view().name()));
+                };
+                let view = DebugView::from_name(name)
+                    .with_context(|| format!("no debug view called {}", name))?;
+                ctx.renderer.set_debug_view(view);
+                if view == DebugView::Wireframe && !ctx.renderer.supports_wireframe() {
+                    return Ok(
+                        "no line rasterization here, edges are found from the uvs".to_string()
+                    );
+                }
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "window",
+            "window [title|min_size|max_size|resizable|on_top|decorations|transparent] [value]",
+            |ctx, args| {
+                let size = |index| -> anyhow::Result<_> {
+                    match args.get(index).map(String::as_str) {
+                        Some("none") => Ok(None),
+                        _ => Ok(Some(PhysicalSize::new(
+                            arg(args, index, "a width")?,
+                            arg(args, index + 1, "a height")?,
+                        ))),
+                    }
+                };
+                match args.first().map(String::as_str) {
+                    None => {}
+                    Some("title") => ctx.window.set_title(&arg::<String>(args, 1, "a title")?),
+                    Some("min_size") => ctx.window.set_min_size(size(1)?),
+                    Some("max_size") => ctx.window.set_max_size(size(1)?),
+                    Some("resizable") => ctx.window.set_resizable(on_off(args, 1)?),
+                    Some("on_top") => ctx.window.set_always_on_top(on_off(args, 1)?),
+                    Some("decorations") => ctx.window.set_decorations(on_off(args, 1)?),
+                    Some("transparent") => ctx.window.set_transparent(on_off(args, 1)?),
+                    Some(option) => anyhow::bail!("no window option {}", option),
+                }
+                Ok(describe_window(ctx.window.options()))
+            },
+        );
+        self.register(
+            "dpi_test",
+            "dpi_test [on|off], pixel patterns and text sizes for checking the scale factor",
+            |ctx, args| {
+                match args.is_empty() {
+                    true => _ = ctx.renderer.toggle_dpi_test(),
+                    false => ctx.renderer.show_dpi_test(on_off(args, 0)?),
+                }
+                let shown = ctx.renderer.is_dpi_test_visible();
+                Ok(format!(
+                    "dpi test page {}",
+                    if shown { "shown" } else { "hidden" }
+                ))
+            },
+        );
+        self.register(
+            "image_cache",
+            "image_cache [clear], what the decoded image cache holds",
+            |_, args| {
+                if args.first().is_some_and(|arg| arg == "clear") {
+                    image_cache::clear();
+                }
+                let stats = image_cache::stats();
+                Ok(format!(
+                    "{} images in {:.1} of {:.1} mb, {} hits and {} misses",
+                    stats.images,
+                    stats.bytes as f64 / (1024.0 * 1024.0),
+                    stats.limit as f64 / (1024.0 * 1024.0),
+                    stats.hits,
+                    stats.misses
+                ))
+            },
+        );
+        self.register("pause", "pauses the game", |ctx, _| {
+            ctx.renderer.pause_simulation();
+            Ok(String::new())
+        });
+        self.register("resume", "resumes the game", |ctx, _| {
+            ctx.renderer.resume_simulation();
+            Ok(String::new())
+        });
+        self.register("step", "advances the paused game by one tick", |ctx, _| {
+            ctx.renderer.step_simulation();
+            let tick = ctx.renderer.simulation_tick_length();
+            Ok(format!("stepping {:.2}ms", tick.as_secs_f64() * 1000.0))
+        });
+        self.register("set", "set <cvar> <value>", |ctx, args| {
+            let name: String = arg(args, 0, "a cvar")?;
+            let value: String = arg(args, 1, "a value")?;
+            ctx.cvars.set_from_str(&name, &value)?;
+            Ok(describe_cvar(ctx.cvars, &name))
+        });
+        self.register("get", "get <cvar>", |ctx, args| {
+            let name: String = arg(args, 0, "a cvar")?;
+            Ok(describe_cvar(ctx.cvars, &name))
+        });
+        self.register("reset", "reset <cvar>, back to its default", |ctx, args| {
+            let name: String = arg(args, 0, "a cvar")?;
+            ctx.cvars.reset(&name)?;
+            Ok(describe_cvar(ctx.cvars, &name))
+        });
+        self.register("cvars", "cvars [filter], lists the cvars", |ctx, args| {
+            let filter = args.first().map_or("", String::as_str);
+            let lines: Vec<String> = ctx
+                .cvars
+                .iter()
+                .filter(|cvar| cvar.name.contains(filter))
+                .map(|cvar| format!("{} = {}, {}", cvar.name, cvar.value, cvar.help))
+                .collect();
+            match lines.is_empty() {
+                true => Ok("no cvars".to_string()),
+                false => Ok(lines.join("\n")),
+            }
+        });
+        self.register(
+            "notify",
+            "notify <level> <text>, shows a toast for a few seconds, or notify clear",
+            |ctx, args| {
+                if args.first().is_some_and(|arg| arg == "clear") {
+                    ctx.renderer.clear_notifications();
+                    return Ok(String::new());
+                }
+                let level: Level = arg(args, 0, "a level")?;
+                if args.len() < 2 {
+                    anyhow::bail!("missing the text");
+                }
+                ctx.renderer.notify(args[1..].join(" "), level, 4.0);
+                Ok(String::new())
+            },
+        );
+        self.register(
+            "log",
+            "log [module] <level|reset>, without arguments lists the levels and the file",
+            |_, args| {
+                match args {
+                    [] => {}
+                    [level] => logging::set_level(None, parse_level(level)?),
+                    [module, reset] if reset == "reset" => logging::clear_level(module),
+                    [module, level] => logging::set_level(Some(module), parse_level(level)?),
+                    _ => anyhow::bail!("expected a module and a level"),
+                }
+                let (default, modules) = logging::levels();
+                let mut lines = vec![format!("default: {}", default)];
+                if let Some(path) = logging::log_file() {
+                    lines.push(format!("file: {}", path.display()));
+                }
+                lines.extend(
+                    modules
+                        .iter()
+                        .map(|(module, level)| format!("{}: {}", module, level)),
+                );
+                Ok(lines.join("\n"))
+            },
+        );
+    }
+}
+
+fn describe_camera(renderer: &Renderer, id: usize) -> String {
+    let Some(camera) = renderer.camera(id) else {
+        return format!("no camera {}", id);
+    };
+    let snap = match camera.snap {
+        PixelSnap::Smooth => "smooth",
+        PixelSnap::Snapped => "snapped",
+    };
+    let moving = match renderer.is_camera_moving(id) {
+        true => ", moving",
+        false => "",
+    };
+    format!(
+        "camera {} at ({:.1}, {:.1}), zoom {:.2}, {}{}",
+        id, camera.position[0], camera.position[1], camera.zoom, snap, moving
+    )
+}
+
+fn describe_sprite(renderer: &Renderer, id: usize) -> String {
+    let (Some(sprite), Some(layer)) = (renderer.sprite(id), renderer.sprite_layer(id)) else {
+        return format!("no sprite {}", id);
+    };
+    let visible = match sprite.visibility.visible {
+        true => "visible",
+        false => "hidden",
+    };
+    let [x, y, width, height] = renderer.sprite_bounds(id).unwrap_or_default();
+    let mut text = format!(
+        "sprite {} on layer {} at ({:.1}, {:.1}) {:.1}x{:.1}, {}, opacity {:.2}",
+        id, layer, x, y, width, height, visible, sprite.visibility.opacity
+    );
+    if let Some(name) = renderer.registry().name(id) {
+        text += &format!(", named {}", name);
+    }
+    let tags: Vec<&str> = renderer.registry().tags(id).collect();
+    if !tags.is_empty() {
+        text += &format!(", tagged {}", tags.join(" "));
+    }
+    if let Some(palette) = renderer.sprite_palette(id) {
+        text += &format!(
+            ", palette {} of pool {} row {}",
+            palette.texture, palette.pool, palette.row
+        );
+    }
+    text
+}
+
+// strong, weak and seconds from index on, on every pad
+fn rumble_arg(args: &[String], index: usize) -> anyhow::Result<Rumble> {
+    let mut rumble = Rumble::default();
+    rumble.motors.strong = arg(args, index, "a strong motor strength")?;
+    rumble.motors.weak = arg(args, index + 1, "a weak motor strength")?;
+    rumble.duration = arg(args, index + 2, "a duration in seconds")?;
+    Ok(rumble)
+}
+
+// linear, smooth, in, out or a curve id, smooth when left out
+fn easing_arg(renderer: &Renderer, args: &[String], index: usize) -> anyhow::Result<Easing> {
+    Ok(match args.get(index).map(String::as_str) {
+        None | Some("smooth") => Easing::SmoothStep,
+        Some("linear") => Easing::Linear,
+        Some("in") => Easing::EaseIn,
+        Some("out") => Easing::EaseOut,
+        Some(_) => {
+            let id = arg(args, index, "an easing or a curve")?;
+            renderer
+                .curve_easing(id)
+                .with_context(|| format!("no curve {}", id))?
+        }
+    })
+}
+
+// a sprite by id, or by the name it was given
+fn sprite_arg(renderer: &Renderer, args: &[String], index: usize) -> anyhow::Result<usize> {
+    let Some(text) = args.get(index) else {
+        anyhow::bail!("missing a sprite");
+    };
+    let id = match text.parse() {
+        Ok(id) => id,
+        Err(_) => renderer
+            .find_sprite(text)
+            .with_context(|| format!("no sprite called {}", text))?,
+    };
+    if renderer.sprite(id).is_none() {
+        anyhow::bail!("no sprite {}", id);
+    }
+    Ok(id)
+}
+
+fn describe_cvar(cvars: &Cvars, name: &str) -> String {
+    match cvars.iter().find(|cvar| cvar.name == name) {
+        Some(cvar) => match cvar.range {
+            Some([min, max]) => format!(
+                "{} = {} (default {}, {} to {})",
+                name, cvar.value, cvar.default, min, max
+            ),
+            None => format!("{} = {} (default {})", name, cvar.value, cvar.default),
+        },
+        None => format!("no cvar {}", name),
+    }
+}
+
+fn describe_window(options: &WindowOptions) -> String {
+    let size = |size: Option<PhysicalSize<u32>>| {
+        size.map_or("none".to_string(), |size| {
+            format!("{}x{}", size.width, size.height)
+        })
+    };
+    format!(
+        "{}, min size {}, max size {}, resizable {}, on top {}, decorations {}, transparent {}",
+        options.title,
+        size(options.min_size),
+        size(options.max_size),
+        options.resizable,
+        options.always_on_top,
+        options.decorations,
+        options.transparent
+    )
+}
+
+fn arg<T: FromStr>(args: &[String], index: usize, what: &str) -> anyhow::Result<T> {
+    let Some(text) = args.get(index) else {
+        anyhow::bail!("missing {}", what);
+    };
+    text.parse()
+        .map_err(|_| anyhow::anyhow!("{} is not {}", text, what))
+}
+
+fn on_off(args: &[String], index: usize) -> anyhow::Result<bool> {
+    match args.get(index).map(String::as_str) {
+        Some("on") => Ok(true),
+        Some("off") => Ok(false),
+        _ => anyhow::bail!("expected on or off"),
+    }
+}
+
+fn parse_level(text: &str) -> anyhow::Result<LevelFilter> {
+    LevelFilter::from_str(text).map_err(|_| anyhow::anyhow!("{} is not a log level", text))
+}
+
+// on whitespace, double quotes keep a path with spaces in one word
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut word));
+                    started = false;
+                }
+            }
+            c => {
+                word.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        words.push(word);
+    }
+    words
+}
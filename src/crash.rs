@@ -0,0 +1,152 @@
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, ThreadId};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::error;
+
+use crate::settings::Settings;
+use crate::tasks;
+
+const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// filled in once the renderer picked a gpu
+static ADAPTER_INFO: OnceLock<String> = OnceLock::new();
+
+// message boxes only open on the main thread
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+// the report of a thread that died off the main thread, waiting to be shown.
+// the inner None is a report that couldn't be written
+static UNSHOWN_REPORT: Mutex<Option<Option<PathBuf>>> = Mutex::new(None);
+
+pub fn set_adapter_info(info: &wgpu::AdapterInfo) {
+    let _ = ADAPTER_INFO.set(format!(
+        "{} ({:?}) on {} {} with {}",
+        info.name, info.device_type, info.driver, info.driver_info, info.backend
+    ));
+}
+
+// called from the main thread
+pub fn install_panic_hook() {
+    let _ = MAIN_THREAD.set(thread::current().id());
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |panic_info| {
+        // keep the usual stderr output
+        default_hook(panic_info);
+
+        // the task pool catches these and keeps going, it's no crash
+        if tasks::is_worker_thread() {
+            error!("a task panicked: {}", panic_message(panic_info));
+            return;
+        }
+
+        let report = build_report(panic_info);
+        let path = write_report(&report);
+
+        match &path {
+            Some(path) => error!("crash report written to {}", path.display()),
+            None => error!("failed to write crash report"),
+        }
+
+        if MAIN_THREAD.get() == Some(&thread::current().id()) {
+            show_message_box(path);
+        } else {
+            let mut unshown = UNSHOWN_REPORT.lock().unwrap_or_else(|e| e.into_inner());
+            unshown.get_or_insert(path);
+        }
+    }));
+}
+
+// shows the report of a thread that crashed since the last call, from the main
+// thread's event loop
+pub fn show_unshown_report() {
+    let unshown = UNSHOWN_REPORT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    if let Some(path) = unshown {
+        show_message_box(path);
+    }
+}
+
+fn panic_message(panic_info: &PanicHookInfo) -> String {
+    match panic_info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match panic_info.payload().downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "unknown panic payload".to_string(),
+        },
+    }
+}
+
+fn build_report(panic_info: &PanicHookInfo) -> String {
+    let message = panic_message(panic_info);
+
+    let location = match panic_info.location() {
+        Some(location) => format!("{}:{}", location.file(), location.line()),
+        None => "unknown location".to_string(),
+    };
+
+    let thread = thread::current();
+
+    format!(
+        "nivalis {} crashed\n\n\
+         message: {}\n\
+         location: {}\n\
+         thread: {}\n\
+         os: {} {}\n\
+         adapter: {}\n\n\
+         backtrace:\n{}\n",
+        ENGINE_VERSION,
+        message,
+        location,
+        thread.name().unwrap_or("unnamed"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        ADAPTER_INFO
+            .get()
+            .map_or("not created yet", |info| info.as_str()),
+        Backtrace::force_capture(),
+    )
+}
+
+fn write_report(report: &str) -> Option<PathBuf> {
+    let dir = Settings::project_dirs()?.data_dir().join("crashes");
+    fs::create_dir_all(&dir).ok()?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let path = dir.join(format!("crash-{}.log", timestamp));
+    fs::write(&path, report).ok()?;
+
+    Some(path)
+}
+
+#[cfg(feature = "native-dialogs")]
+fn show_message_box(path: Option<PathBuf>) {
+    let description = match path {
+        Some(path) => format!(
+            "nivalis ran into a fatal error.\n\nA crash report was saved to:\n{}",
+            path.display()
+        ),
+        None => "nivalis ran into a fatal error.\n\nThe crash report could not be saved.".into(),
+    };
+
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("nivalis crashed")
+        .set_description(description)
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+#[cfg(not(feature = "native-dialogs"))]
+fn show_message_box(_path: Option<PathBuf>) {}
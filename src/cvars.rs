@@ -0,0 +1,523 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::state::StateContext;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum CvarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    String(String),
+}
+
+impl fmt::Display for CvarValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CvarValue::Bool(value) => value.fmt(f),
+            CvarValue::Int(value) => value.fmt(f),
+            CvarValue::Float(value) => value.fmt(f),
+            CvarValue::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+impl CvarValue {
+    fn type_name(&self) -> &'static str {
+        match self {
+            CvarValue::Bool(_) => "a bool",
+            CvarValue::Int(_) => "an int",
+            CvarValue::Float(_) => "a float",
+            CvarValue::String(_) => "a string",
+        }
+    }
+
+    // text as typed in the console, read as the same type as like
+    fn parse_like(like: &CvarValue, text: &str) -> Option<CvarValue> {
+        match like {
+            CvarValue::Bool(_) => match text {
+                "1" | "true" | "on" => Some(CvarValue::Bool(true)),
+                "0" | "false" | "off" => Some(CvarValue::Bool(false)),
+                _ => None,
+            },
+            CvarValue::Int(_) => text.parse().ok().map(CvarValue::Int),
+            CvarValue::Float(_) => text.parse().ok().map(CvarValue::Float),
+            CvarValue::String(_) => Some(CvarValue::String(text.to_string())),
+        }
+    }
+
+    fn from_toml(like: &CvarValue, value: &toml::Value) -> Option<CvarValue> {
+        match (like, value) {
+            (CvarValue::Bool(_), toml::Value::Boolean(value)) => Some(CvarValue::Bool(*value)),
+            (CvarValue::Int(_), toml::Value::Integer(value)) => Some(CvarValue::Int(*value)),
+            (CvarValue::Float(_), toml::Value::Float(value)) => {
+                Some(CvarValue::Float(*value as f32))
+            }
+            (CvarValue::Float(_), toml::Value::Integer(value)) => {
+                Some(CvarValue::Float(*value as f32))
+            }
+            (CvarValue::String(_), toml::Value::String(value)) => {
+                Some(CvarValue::String(value.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    fn to_toml(&self) -> toml::Value {
+        match self {
+            CvarValue::Bool(value) => toml::Value::Boolean(*value),
+            CvarValue::Int(value) => toml::Value::Integer(*value),
+            CvarValue::Float(value) => toml::Value::Float(*value as f64),
+            CvarValue::String(value) => toml::Value::String(value.clone()),
+        }
+    }
+
+    fn clamped(self, range: Option<[f64; 2]>) -> CvarValue {
+        let Some([min, max]) = range else {
+            return self;
+        };
+        match self {
+            CvarValue::Int(value) => CvarValue::Int(value.clamp(min as i64, max as i64)),
+            CvarValue::Float(value) => CvarValue::Float(value.clamp(min as f32, max as f32)),
+            value => value,
+        }
+    }
+}
+
+type Callback = Box<dyn FnMut(&mut StateContext, &CvarValue)>;
+
+struct Cvar {
+    help: String,
+    value: CvarValue,
+    default: CvarValue,
+    range: Option<[f64; 2]>, // ints and floats are clamped into it
+}
+
+// what the console, the settings file and the imgui panel show of a cvar
+pub struct CvarInfo<'c> {
+    pub name: &'c str,
+    pub help: &'c str,
+    pub value: &'c CvarValue,
+    pub default: &'c CvarValue,
+    pub range: Option<[f64; 2]>,
+}
+
+// named, typed values to tune while running. the [cvars] table of the settings
+// file sets them at startup, the console and the cvars window after that, and
+// whoever cares hears about changes once per frame
+#[derive(Default)]
+pub struct Cvars {
+    cvars: BTreeMap<String, Cvar>,
+    callbacks: BTreeMap<String, Vec<Callback>>,
+    pending: toml::Table, // from the settings file, for cvars not registered yet
+    changed: Vec<String>,
+    revision: u64, // bumped on every change, for the imgui panel
+}
+
+impl Cvars {
+    pub fn register_int(
+        &mut self,
+        name: &str,
+        help: &str,
+        default: i64,
+        range: RangeInclusive<i64>,
+    ) {
+        let range = [*range.start() as f64, *range.end() as f64];
+        self.register(name, help, CvarValue::Int(default), Some(range));
+    }
+
+    pub fn register_float(
+        &mut self,
+        name: &str,
+        help: &str,
+        default: f32,
+        range: RangeInclusive<f32>,
+    ) {
+        let range = [*range.start() as f64, *range.end() as f64];
+        self.register(name, help, CvarValue::Float(default), Some(range));
+    }
+
+    // a value from the settings file wins over the default. every cvar counts
+    // as changed once, so callbacks get to apply the starting value
+    fn register(&mut self, name: &str, help: &str, default: CvarValue, range: Option<[f64; 2]>) {
+        if self.cvars.contains_key(name) {
+            warn!("cvar {} registered twice, keeping the first", name);
+            return;
+        }
+        let value = match self.pending.remove(name) {
+            Some(configured) => match CvarValue::from_toml(&default, &configured) {
+                Some(value) => value.clamped(range),
+                None => {
+                    warn!(
+                        "cvar {} in the settings should be {}, using the default",
+                        name,
+                        default.type_name()
+                    );
+                    default.clone()
+                }
+            },
+            None => default.clone(),
+        };
+        self.cvars.insert(
+            name.to_string(),
+            Cvar {
+                help: help.to_string(),
+                value,
+                default,
+                range,
+            },
+        );
+        self.mark_changed(name);
+    }
+
+    // called with the new value on the frame after every change
+    pub fn on_change(
+        &mut self,
+        name: &str,
+        callback: impl FnMut(&mut StateContext, &CvarValue) + 'static,
+    ) {
+        if !self.cvars.contains_key(name) {
+            warn!("no cvar {} to watch", name);
+        }
+        self.callbacks
+            .entry(name.to_string())
+            .or_default()
+            .push(Box::new(callback));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CvarValue> {
+        self.cvars.get(name).map(|cvar| &cvar.value)
+    }
+
+    // has to be the cvar's type, numbers are clamped into its range
+    pub fn set(&mut self, name: &str, value: CvarValue) -> anyhow::Result<()> {
+        let Some(cvar) = self.cvars.get_mut(name) else {
+            anyhow::bail!("no cvar {}", name);
+        };
+        if std::mem::discriminant(&value) != std::mem::discriminant(&cvar.default) {
+            anyhow::bail!("{} is {}", name, cvar.default.type_name());
+        }
+        let value = value.clamped(cvar.range);
+        if value != cvar.value {
+            cvar.value = value;
+            self.mark_changed(name);
+        }
+        Ok(())
+    }
+
+    // callbacks run once a frame however often it changed
+    fn mark_changed(&mut self, name: &str) {
+        if !self.changed.iter().any(|changed| changed == name) {
+            self.changed.push(name.to_string());
+        }
+        self.revision += 1;
+    }
+
+    pub fn set_from_str(&mut self, name: &str, text: &str) -> anyhow::Result<()> {
+        let Some(cvar) = self.cvars.get(name) else {
+            anyhow::bail!("no cvar {}", name);
+        };
+        let Some(value) = CvarValue::parse_like(&cvar.default, text) else {
+            anyhow::bail!("{} is {}, not {}", name, cvar.default.type_name(), text);
+        };
+        self.set(name, value)
+    }
+
+    pub fn reset(&mut self, name: &str) -> anyhow::Result<()> {
+        let Some(cvar) = self.cvars.get(name) else {
+            anyhow::bail!("no cvar {}", name);
+        };
+        self.set(name, cvar.default.clone())
+    }
+
+    // by name
+    pub fn iter(&self) -> impl Iterator<Item = CvarInfo<'_>> {
+        self.cvars.iter().map(|(name, cvar)| CvarInfo {
+            name,
+            help: &cvar.help,
+            value: &cvar.value,
+            default: &cvar.default,
+            range: cvar.range,
+        })
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // the settings file's [cvars] table, applied to registered cvars now and
+    // to the others once they are registered
+    pub fn load_table(&mut self, table: &toml::Table) {
+        for (name, configured) in table.iter() {
+            let Some(cvar) = self.cvars.get(name) else {
+                self.pending.insert(name.clone(), configured.clone());
+                continue;
+            };
+            let Some(value) = CvarValue::from_toml(&cvar.default, configured) else {
+                warn!(
+                    "cvar {} in the settings should be {}",
+                    name,
+                    cvar.default.type_name()
+                );
+                continue;
+            };
+            let _ = self.set(name, value);
+        }
+    }
+
+    // the values that differ from their defaults, for the settings file.
+    // ones that were never registered stay as they were
+    pub fn to_table(&self) -> toml::Table {
+        let mut table = self.pending.clone();
+        for (name, cvar) in self.cvars.iter() {
+            if cvar.value != cvar.default {
+                table.insert(name.clone(), cvar.value.to_toml());
+            }
+        }
+        table
+    }
+
+    // runs the callbacks of everything changed since the last call
+    pub(crate) fn dispatch_changes(ctx: &mut StateContext) {
+        if ctx.cvars.changed.is_empty() {
+            return;
+        }
+        let changed = std::mem::take(&mut ctx.cvars.changed);
+        // taken out while they run, so they can use the cvars in ctx
+        let mut callbacks = std::mem::take(&mut ctx.cvars.callbacks);
+        for name in changed.iter() {
+            let (Some(value), Some(callbacks)) =
+                (ctx.cvars.get(name).cloned(), callbacks.get_mut(name))
+            else {
+                continue;
+            };
+            for callback in callbacks.iter_mut() {
+                callback(ctx, &value);
+            }
+        }
+        for (name, mut added) in std::mem::take(&mut ctx.cvars.callbacks) {
+            callbacks.entry(name).or_default().append(&mut added);
+        }
+        ctx.cvars.callbacks = callbacks;
+    }
+}
+
+// for games, the engine's own tunables are all numbers it applies on change
+#[allow(dead_code)]
+impl Cvars {
+    pub fn register_bool(&mut self, name: &str, help: &str, default: bool) {
+        self.register(name, help, CvarValue::Bool(default), None);
+    }
+
+    pub fn register_string(&mut self, name: &str, help: &str, default: &str) {
+        self.register(name, help, CvarValue::String(default.to_string()), None);
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            CvarValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            CvarValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn float(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            CvarValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            CvarValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+// the engine's own tunables
+pub(crate) fn register_engine_cvars(cvars: &mut Cvars) {
+    cvars.register_float(
+        "sim_tick_ms",
+        "how long a paused step advances the game by without a fixed delta",
+        16.667,
+        1.0..=100.0,
+    );
+    cvars.on_change("sim_tick_ms", |ctx, value| {
+        if let CvarValue::Float(ms) = value {
+            ctx.renderer
+                .set_simulation_tick_length(Duration::from_secs_f32(ms / 1000.0));
+        }
+    });
+
+    cvars.register_float(
+        "upload_budget_ms",
+        "time spent uploading loaded textures per frame, 0 uploads them all at once",
+        2.0,
+        0.0..=50.0,
+    );
+    cvars.on_change("upload_budget_ms", |ctx, value| {
+        if let CvarValue::Float(ms) = value {
+            let budget = (*ms > 0.0).then(|| Duration::from_secs_f32(ms / 1000.0));
+            ctx.renderer.set_upload_budget(budget);
+        }
+    });
+
+    cvars.register_float(
+        "pacing_missed_vsyncs",
+        "frames longer than this many refresh intervals count as stutters",
+        1.5,
+        1.05..=4.0,
+    );
+    cvars.on_change("pacing_missed_vsyncs", |ctx, value| {
+        if let CvarValue::Float(vsyncs) = value {
+            ctx.renderer.set_missed_vsync_threshold(*vsyncs);
+        }
+    });
+
+    cvars.register_float(
+        "console_height",
+        "the console's share of the window height",
+        0.4,
+        0.1..=1.0,
+    );
+    cvars.on_change("console_height", |ctx, value| {
+        if let CvarValue::Float(height) = value {
+            ctx.renderer.set_console_height(*height);
+        }
+    });
+
+    cvars.register_int(
+        "timeline_frames",
+        "frames of history to scrub through while paused, 0 records none",
+        0,
+        0..=3600,
+    );
+    cvars.on_change("timeline_frames", |ctx, value| {
+        if let CvarValue::Int(frames) = value {
+            ctx.renderer.set_timeline_capacity(*frames as usize);
+        }
+    });
+
+    cvars.register_int(
+        "toast_limit",
+        "notifications shown at once, older ones make room",
+        5,
+        1..=20,
+    );
+    cvars.on_change("toast_limit", |ctx, value| {
+        if let CvarValue::Int(limit) = value {
+            ctx.renderer.set_notification_limit(*limit as usize);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn numbers_are_clamped_into_their_range() {
+        let mut cvars = Cvars::default();
+        cvars.register_int("limit", "", 5, 1..=20);
+        cvars.register_float("scale", "", 1.0, 0.5..=2.0);
+
+        cvars.set("limit", CvarValue::Int(100)).unwrap();
+        assert_eq!(cvars.int("limit"), Some(20));
+        cvars.set_from_str("limit", "-3").unwrap();
+        assert_eq!(cvars.int("limit"), Some(1));
+        cvars.set_from_str("scale", "0.1").unwrap();
+        assert_eq!(cvars.float("scale"), Some(0.5));
+        cvars.set_from_str("scale", "1.25").unwrap();
+        assert_eq!(cvars.float("scale"), Some(1.25));
+    }
+
+    #[test]
+    fn values_of_another_type_are_refused() {
+        let mut cvars = Cvars::default();
+        cvars.register_bool("vsync", "", true);
+        cvars.register_int("limit", "", 5, 1..=20);
+        cvars.register_string("name", "", "nivalis");
+
+        assert!(cvars.set("vsync", CvarValue::Int(0)).is_err());
+        assert!(cvars.set("limit", CvarValue::Float(3.0)).is_err());
+        assert!(cvars.set_from_str("vsync", "maybe").is_err());
+        assert!(cvars.set_from_str("limit", "1.5").is_err());
+        assert!(cvars.set_from_str("missing", "1").is_err());
+        assert_eq!(cvars.bool("vsync"), Some(true));
+        assert_eq!(cvars.int("limit"), Some(5));
+        // getters of another type find nothing
+        assert_eq!(cvars.float("limit"), None);
+
+        cvars.set_from_str("vsync", "off").unwrap();
+        assert_eq!(cvars.bool("vsync"), Some(false));
+        cvars.set_from_str("name", "1").unwrap();
+        assert_eq!(cvars.string("name"), Some("1"));
+    }
+
+    #[test]
+    fn settings_apply_to_cvars_registered_later() {
+        let mut cvars = Cvars::default();
+        cvars.register_int("limit", "", 5, 1..=20);
+        cvars.load_table(&table(
+            "limit = 50\nscale = 3\nvsync = \"yes\"\nunknown = 1\n",
+        ));
+        assert_eq!(cvars.int("limit"), Some(20));
+
+        // an int setting is fine for a float, and clamped on register
+        cvars.register_float("scale", "", 1.0, 0.5..=2.0);
+        assert_eq!(cvars.float("scale"), Some(2.0));
+        cvars.register_bool("vsync", "", true);
+        assert_eq!(cvars.bool("vsync"), Some(true));
+
+        // only the one nobody registered is left over
+        assert_eq!(cvars.pending.keys().collect::<Vec<_>>(), ["unknown"]);
+    }
+
+    #[test]
+    fn table_keeps_changed_and_unregistered_values() {
+        let mut cvars = Cvars::default();
+        cvars.register_int("limit", "", 5, 1..=20);
+        cvars.register_bool("vsync", "", true);
+        cvars.load_table(&table("unknown = \"kept\"\n"));
+        cvars.set("limit", CvarValue::Int(8)).unwrap();
+
+        assert_eq!(cvars.to_table(), table("limit = 8\nunknown = \"kept\"\n"));
+        cvars.reset("limit").unwrap();
+        assert_eq!(cvars.to_table(), table("unknown = \"kept\"\n"));
+    }
+
+    #[test]
+    fn changes_are_marked_once_per_cvar() {
+        let mut cvars = Cvars::default();
+        cvars.register_int("limit", "", 5, 1..=20);
+        cvars.register_int("limit", "", 7, 1..=20);
+        assert_eq!(cvars.int("limit"), Some(5));
+        cvars.changed.clear();
+        let revision = cvars.revision();
+
+        cvars.set("limit", CvarValue::Int(5)).unwrap();
+        assert!(cvars.changed.is_empty());
+        assert_eq!(cvars.revision(), revision);
+
+        cvars.set("limit", CvarValue::Int(6)).unwrap();
+        cvars.set("limit", CvarValue::Int(7)).unwrap();
+        assert_eq!(cvars.changed, ["limit"]);
+        assert_eq!(cvars.revision(), revision + 2);
+    }
+}
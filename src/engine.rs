@@ -1,25 +1,95 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use winit::{dpi::PhysicalSize, event::WindowEvent, window::Window};
+use log::{Level, error, info, warn};
+use winit::{
+    dpi::PhysicalSize,
+    event::{DeviceEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    window::Window,
+};
 
-use crate::{assets::manager::AssetManager, renderer::Renderer};
+use crate::{
+    assets::image_cache,
+    assets::manager::AssetManager,
+    assets::manager::PoolState,
+    audio::{Audio, SoundHandle},
+    console::Console,
+    cvars::{Cvars, register_engine_cvars},
+    input::{Input, events::EngineEvent},
+    integrations::Integrations,
+    net::clock::Clock,
+    platform::dialog::{self, IMAGE_FILES, SOUND_FILES},
+    platform::focus::FocusState,
+    platform::window::{PlatformWindow, WindowOptions},
+    renderer::{
+        Renderer,
+        asset_browser::{AssetBrowserEvent, AssetKind, AssetLoadState, BrowserAsset, BrowserPool},
+        cvar_panel::CvarRow,
+    },
+    settings::Settings,
+    state::{GameState, StateContext, StateStack},
+    ui::{gizmo::Gizmo, key_remap::KeyRemapScreen},
+};
+
+const MONITOR_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const CONTROLS_ACTION: &str = "controls"; // opens the key rebinding screen
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EngineState {
+    Loading, // waiting on queued asset pools
+    Running,
+}
 
 pub struct Engine<'a> {
+    window: PlatformWindow,
     renderer: Renderer<'a>,
     assets: AssetManager,
+    audio: Audio,
+    settings: Settings,
+    state: EngineState,
+    states: StateStack,
+    input: Input,
+    console: Console,
+    cvars: Cvars,
+    cvar_revision: Option<u64>, // what the cvars window and completions last got
+    last_monitor_check: Instant,
+    focus: FocusState,
+    net_clock: Option<Clock>,
+    integrations: Integrations,
+    gizmo: Gizmo, // moves the selected sprite from the debug ui
 }
 
 impl<'a> Engine<'a> {
-    pub fn new(window: Arc<Window>) -> Engine<'a> {
-        let mut renderer = Renderer::new(window.clone());
-        let mut asset_manager = AssetManager::new();
+    pub fn new(
+        window: Arc<Window>,
+        window_options: WindowOptions,
+        settings: Settings,
+    ) -> Engine<'a> {
+        let mut renderer = Renderer::new(
+            window.clone(),
+            window_options.transparent,
+            settings.hdr_output,
+        );
+        renderer.set_texture_budget(settings.texture_budget());
+        image_cache::set_limit(settings.image_cache_mb * 1024 * 1024);
+        renderer.set_theme(settings.theme());
+        renderer.set_hdr(settings.hdr);
+        renderer.set_tonemapping(settings.tonemapping);
+        renderer.set_exposure(settings.exposure);
+        renderer.set_hdr_brightness(settings.paper_white_nits, settings.peak_nits);
+        renderer.set_vsync(settings.vsync);
+        renderer.set_frame_latency(settings.frame_latency);
+        let mut asset_manager = AssetManager::new(settings.asset_discovery);
+        renderer.set_asset_manifest(asset_manager.manifest());
 
         let pool = asset_manager.create_pool();
         pool.register_texture("cat.png");
         pool.register_texture("eyyab.webp");
         pool.register_texture("idiot.png");
 
-        renderer.insert_pool(pool);
+        let pool_id = pool.id;
+        asset_manager.queue_pool(pool_id);
 
         // test
         renderer.add_text(
@@ -32,18 +102,552 @@ impl<'a> Engine<'a> {
             1.15,
         );
 
-        Engine {
+        let mut cvars = Cvars::default();
+        cvars.load_table(&settings.cvars);
+        register_engine_cvars(&mut cvars);
+
+        let mut engine = Engine {
+            window: PlatformWindow::new(window, window_options),
             renderer,
             assets: asset_manager,
+            audio: Audio::new(),
+            settings,
+            state: EngineState::Running,
+            states: StateStack::new(),
+            input: Input::new(),
+            console: Console::new(),
+            cvars,
+            cvar_revision: None,
+            last_monitor_check: Instant::now(),
+            focus: FocusState::default(),
+            net_clock: None,
+            integrations: Integrations::default(),
+            gizmo: Gizmo::default(),
+        };
+
+        engine.input.set_rumble_scale(engine.settings.rumble);
+        engine.input.load_keybinds(&engine.settings.keybinds);
+        engine
+            .input
+            .register_action(CONTROLS_ACTION, "Controls", KeyCode::F1);
+        engine.audio.configure(engine.settings.audio());
+        engine.begin_loading();
+        engine
+    }
+
+    pub fn push_state(&mut self, state: Box<dyn GameState>) {
+        self.states.push(state);
+    }
+
+    // loads every queued pool behind the loading screen
+    pub fn begin_loading(&mut self) {
+        self.assets.load_queued();
+
+        if self.assets.is_loading() {
+            info!("entering loading state");
+            self.state = EngineState::Loading;
+            self.renderer
+                .set_loading_progress(Some(self.assets.progress()));
+        }
+    }
+
+    fn update_loading(&mut self) {
+        self.receive_loaded_pools();
+
+        // the loading screen stays up until the textures are on the gpu too
+        if self.assets.is_loading() || self.renderer.pending_uploads() > 0 {
+            self.renderer
+                .set_loading_progress(Some(self.assets.progress()));
+            return;
+        }
+
+        info!("assets loaded, entering running state");
+        self.renderer.set_loading_progress(None);
+        self.state = EngineState::Running;
+    }
+
+    // a pool loaded again goes back into its old texture pool, so sprites keep working
+    fn receive_loaded_pools(&mut self) {
+        for mut pool in self.assets.poll() {
+            if !pool.sounds.is_empty() {
+                self.audio
+                    .insert_loaded_pool(pool.id, std::mem::take(&mut pool.sounds));
+            }
+            self.renderer.queue_loaded_pool(pool);
+        }
+    }
+
+    // copies picked files into the asset directory and adds them to the pool,
+    // a loaded pool loads again to pick them up
+    fn import_assets(&mut self, pool: usize) {
+        let files = dialog::pick_files(&[IMAGE_FILES, SOUND_FILES]);
+        if files.is_empty() {
+            return;
+        }
+
+        let mut imported = 0;
+        for file in files.iter() {
+            let texture = IMAGE_FILES.matches(file);
+            let folder = if texture { "textures" } else { "sounds" };
+            let path = match self.assets.manifest_mut().import(file, folder) {
+                Ok(path) => path,
+                Err(e) => {
+                    error!("{:#}", e);
+                    self.renderer.notify(format!("{:#}", e), Level::Error, 4.0);
+                    continue;
+                }
+            };
+            let Some(asset_pool) = self.assets.pool_mut(pool) else {
+                return;
+            };
+            match texture {
+                true => asset_pool.register_path(&path),
+                false => asset_pool.register_sound_path(&path),
+            };
+            imported += 1;
+        }
+        if imported == 0 {
+            return;
         }
+
+        if let Err(e) = self.assets.manifest().save() {
+            error!("{:#}", e);
+        }
+        self.assets.refresh_index();
+        self.renderer.set_asset_manifest(self.assets.manifest());
+        if self.assets.unload_pool(pool) {
+            self.assets.queue_pool(pool);
+            self.assets.load_queued();
+        }
+        self.renderer.notify(
+            format!("imported {} assets into pool {}", imported, pool),
+            Level::Info,
+            2.0,
+        );
+    }
+
+    // rows for the imgui asset browser, and whatever was clicked in it last frame
+    fn update_asset_browser(&mut self) {
+        if !self.renderer.has_debug_ui() {
+            return;
+        }
+
+        for event in self.renderer.take_asset_browser_events() {
+            match event {
+                AssetBrowserEvent::LoadPool(id) => {
+                    // in the background, the game keeps running
+                    self.assets.queue_pool(id);
+                    self.assets.load_queued();
+                }
+                AssetBrowserEvent::UnloadPool(id) => {
+                    if self.assets.unload_pool(id) {
+                        if let Some(textures) = self.renderer.texture_pool(id) {
+                            self.renderer.evict_pool(textures);
+                        }
+                        self.audio.unload_pool(id);
+                    }
+                }
+                AssetBrowserEvent::Evict { pool, index } => {
+                    if let Some(textures) = self.renderer.texture_pool(pool) {
+                        self.renderer.evict_texture(textures, index);
+                    }
+                }
+                AssetBrowserEvent::Reload { pool, kind, index } => match kind {
+                    AssetKind::Texture => {
+                        if let Some(textures) = self.renderer.texture_pool(pool) {
+                            self.renderer.reload_texture(textures, index);
+                        }
+                    }
+                    AssetKind::Sound => {
+                        if self.audio.reload(SoundHandle { pool, sound: index }) {
+                            self.renderer.notify("reloaded sound", Level::Info, 2.0);
+                        }
+                    }
+                },
+                AssetBrowserEvent::Import(pool) => self.import_assets(pool),
+            }
+        }
+
+        let references = self.renderer.texture_references();
+        let pools = self
+            .assets
+            .pools()
+            .iter()
+            .map(|pool| {
+                let textures = self.renderer.texture_pool(pool.id);
+                let pending = match pool.state {
+                    PoolState::Queued | PoolState::Loading => AssetLoadState::Loading,
+                    _ => AssetLoadState::Unloaded,
+                };
+
+                let mut assets: Vec<BrowserAsset> = pool
+                    .textures
+                    .iter()
+                    .enumerate()
+                    .map(|(index, path)| {
+                        let residency = textures
+                            .filter(|_| pool.state == PoolState::Loaded)
+                            .and_then(|textures| self.renderer.texture_residency(textures, index));
+                        let (state, bytes) = match residency {
+                            Some(residency) if residency.resident => {
+                                (AssetLoadState::Resident, residency.bytes)
+                            }
+                            Some(_) => (AssetLoadState::Evicted, 0),
+                            None => (pending, 0),
+                        };
+                        BrowserAsset {
+                            kind: AssetKind::Texture,
+                            index,
+                            path: path.clone(),
+                            state,
+                            references: textures
+                                .and_then(|textures| references.get(&(textures, index)))
+                                .copied()
+                                .unwrap_or(0),
+                            bytes,
+                        }
+                    })
+                    .collect();
+
+                let sounds = self.audio.pool_sounds(pool.id);
+                assets.extend(pool.sounds.iter().enumerate().map(|(index, path)| {
+                    let sound = sounds.get(index);
+                    BrowserAsset {
+                        kind: AssetKind::Sound,
+                        index,
+                        path: path.clone(),
+                        state: match sound {
+                            Some(_) => AssetLoadState::Resident,
+                            None => pending,
+                        },
+                        references: sound.map_or(0, |sound| sound.references()),
+                        bytes: sound.map_or(0, |sound| sound.bytes()),
+                    }
+                }));
+
+                BrowserPool {
+                    id: pool.id,
+                    state: pool.state,
+                    assets,
+                }
+            })
+            .collect();
+        self.renderer.set_asset_browser(pools);
+    }
+
+    // the rebinding screen opens over whatever is running
+    fn open_controls(&mut self) {
+        if self.input.action_pressed(CONTROLS_ACTION)
+            && !self.input.is_text_input()
+            && self
+                .states
+                .top()
+                .is_none_or(|state| state.name() != "key_remap")
+        {
+            self.push_state(Box::new(KeyRemapScreen::new()));
+        }
+    }
+
+    // ime is only allowed while something wants text
+    fn sync_window_input(&mut self) {
+        self.window.set_ime_allowed(self.input.is_text_input());
+        // an overlay needs the cursor
+        let relative = self.input.is_relative_mouse() && !self.integrations.is_overlay_active();
+        self.window.set_relative_mouse(relative);
+
+        if let Some((position, size)) = self.input.ime_area() {
+            self.window.set_ime_cursor_area(position, size);
+        }
+    }
+
+    pub fn renderer(&mut self) -> &mut Renderer<'a> {
+        &mut self.renderer
+    }
+
+    // fixed ticks for the states' updates, paced by a server. None goes back
+    // to one update per frame
+    pub fn set_net_clock(&mut self, clock: Option<Clock>) {
+        self.net_clock = clock;
+    }
+
+    // with the cvars that differ from their defaults
+    pub fn save_settings(&mut self) -> anyhow::Result<()> {
+        self.settings.cvars = self.cvars.to_table();
+        self.settings.keybinds = self.input.keybinds();
+        self.settings.save()
     }
 
     pub fn handle_redraw(&mut self) {
-        self.renderer.handle_redraw().unwrap()
+        self.focus.begin_frame();
+        if let Some(active) = self.integrations.update() {
+            self.input.queue_mut().push(EngineEvent::Overlay { active });
+        }
+        self.update_cvars();
+        if self.state == EngineState::Loading {
+            self.update_loading();
+        } else {
+            let dt_seconds = self.renderer.delta_time().as_secs_f32();
+            // the gizmo takes the clicks before the game, while paused to pick
+            // something or while something is selected
+            if self.renderer.has_debug_ui()
+                && (self.renderer.is_simulation_paused()
+                    || self.renderer.selected_sprite().is_some())
+            {
+                self.gizmo.update(&mut self.input, &mut self.renderer);
+            } else {
+                self.gizmo.release(&mut self.input);
+            }
+            let mut ctx = StateContext {
+                renderer: &mut self.renderer,
+                window: &mut self.window,
+                assets: &mut self.assets,
+                audio: &mut self.audio,
+                input: &mut self.input,
+                settings: &mut self.settings,
+                cvars: &mut self.cvars,
+                integrations: &mut self.integrations,
+                net_clock: self.net_clock.as_mut(),
+            };
+
+            // history is shown while the timeline is scrubbed, the game waits.
+            // paused it only moves on when stepped, by one fixed tick
+            if !ctx.renderer.is_time_travelling()
+                && let Some(tick) = ctx.renderer.simulation_tick()
+            {
+                ctx.renderer.profile_begin("update");
+                let paused = ctx.renderer.is_simulation_paused();
+                match ctx.net_clock.as_deref_mut() {
+                    // the server's ticks set the pace, a step runs one of them
+                    Some(clock) => {
+                        match paused {
+                            true => clock.step(),
+                            false => clock.advance(ctx.renderer.delta_time()),
+                        }
+                        let tick = clock.tick_length().as_secs_f32();
+                        while ctx
+                            .net_clock
+                            .as_deref_mut()
+                            .and_then(Clock::next_tick)
+                            .is_some()
+                        {
+                            self.states.update(&mut ctx, tick);
+                        }
+                    }
+                    None => self.states.update(&mut ctx, tick),
+                }
+                ctx.renderer.profile_end();
+            }
+            ctx.renderer.profile_begin("render");
+            self.states.render(&mut ctx);
+            ctx.renderer.profile_end();
+            self.input.update_rumble(dt_seconds);
+            // states may have changed the volume settings
+            self.audio.configure(self.settings.audio());
+
+            // pools loaded from the asset browser arrive while the game runs
+            if self.assets.is_loading() {
+                self.receive_loaded_pools();
+            }
+            self.update_asset_browser();
+            self.run_console_commands();
+            self.open_controls();
+        }
+
+        self.sync_window_input();
+        self.input.end_frame();
+
+        // a dropped frame is logged by the renderer, the next one tries again
+        _ = self.renderer.handle_redraw();
+        self.check_monitors();
+    }
+
+    // edits from the cvars window, then whoever watches what changed
+    fn update_cvars(&mut self) {
+        for (name, value) in self.renderer.take_cvar_edits() {
+            if let Err(e) = self.cvars.set(&name, value) {
+                warn!("{:#}", e);
+            }
+        }
+
+        let mut ctx = StateContext {
+            renderer: &mut self.renderer,
+            window: &mut self.window,
+            assets: &mut self.assets,
+            audio: &mut self.audio,
+            input: &mut self.input,
+            settings: &mut self.settings,
+            cvars: &mut self.cvars,
+            integrations: &mut self.integrations,
+            net_clock: self.net_clock.as_mut(),
+        };
+        Cvars::dispatch_changes(&mut ctx);
+
+        if self.renderer.has_debug_ui() && self.cvar_revision != Some(self.cvars.revision()) {
+            self.cvar_revision = Some(self.cvars.revision());
+            let rows = self
+                .cvars
+                .iter()
+                .map(|cvar| CvarRow {
+                    name: cvar.name.to_string(),
+                    help: cvar.help.to_string(),
+                    value: cvar.value.clone(),
+                    default: cvar.default.clone(),
+                    range: cvar.range,
+                })
+                .collect();
+            self.renderer.set_cvar_rows(rows);
+            self.renderer
+                .set_console_completions(self.console.completions(&self.cvars));
+        }
+    }
+
+    fn run_console_commands(&mut self) {
+        if self.console.take_changed() {
+            self.renderer
+                .set_console_completions(self.console.completions(&self.cvars));
+        }
+        let lines = self.renderer.take_console_commands();
+        if lines.is_empty() {
+            return;
+        }
+
+        let mut ctx = StateContext {
+            renderer: &mut self.renderer,
+            window: &mut self.window,
+            assets: &mut self.assets,
+            audio: &mut self.audio,
+            input: &mut self.input,
+            settings: &mut self.settings,
+            cvars: &mut self.cvars,
+            integrations: &mut self.integrations,
+            net_clock: self.net_clock.as_mut(),
+        };
+        for line in lines.iter() {
+            self.console.execute(&mut ctx, line);
+        }
+    }
+
+    // a lost surface usually means the display changed, don't wait for the next poll
+    fn check_monitors(&mut self) {
+        let surface_lost = self.renderer.take_surface_lost();
+        if !surface_lost && self.last_monitor_check.elapsed() < MONITOR_CHECK_INTERVAL {
+            return;
+        }
+
+        self.last_monitor_check = Instant::now();
+        if !self.window.check_monitors() && surface_lost {
+            self.window.ensure_on_monitor();
+        }
     }
 
     pub fn handle_event(&mut self, event: &WindowEvent) {
         self.renderer.handle_imgui_event(event);
+
+        // the console key never reaches the game, unless it's typed into a text field
+        if let WindowEvent::KeyboardInput { event: key, .. } = event
+            && key.physical_key == PhysicalKey::Code(KeyCode::Backquote)
+            && !self.input.is_text_input()
+        {
+            if key.state.is_pressed() && !key.repeat {
+                self.renderer.toggle_console();
+            }
+            return;
+        }
+
+        if let WindowEvent::Focused(focused) = event {
+            self.window.handle_focus(*focused);
+        }
+
+        let queued = self.input.queue().events().len();
+        self.input.handle_event(event);
+        if let WindowEvent::Focused(focused) = event {
+            self.apply_focus_loss_policy(*focused);
+        }
+
+        let received = Instant::now();
+        for queued in &self.input.queue().events()[queued..] {
+            if queued.event.is_keyboard() || queued.event.is_mouse() || queued.event.is_touch() {
+                self.renderer.record_input(received);
+            }
+        }
+
+        // ui sits first in the queue order, states see what it didn't take
+        let (mouse, keyboard) = self.renderer.imgui_captures();
+        for queued in self.input.queue_mut().events_mut()[queued..].iter_mut() {
+            if (mouse && queued.event.is_mouse()) || (keyboard && queued.event.is_keyboard()) {
+                queued.consume("imgui");
+            }
+        }
+
+        if self.state == EngineState::Running {
+            let mut ctx = StateContext {
+                renderer: &mut self.renderer,
+                window: &mut self.window,
+                assets: &mut self.assets,
+                audio: &mut self.audio,
+                input: &mut self.input,
+                settings: &mut self.settings,
+                cvars: &mut self.cvars,
+                integrations: &mut self.integrations,
+                net_clock: self.net_clock.as_mut(),
+            };
+            self.states.handle_event(&mut ctx, event);
+        }
+    }
+
+    // pauses, mutes and throttles as the settings ask while in the background,
+    // undoing only what it did itself once focus is back
+    fn apply_focus_loss_policy(&mut self, focused: bool) {
+        let policy = self.settings.focus_loss;
+        if !self.focus.set_focused(focused, policy) || !policy.is_active() {
+            return;
+        }
+        info!(
+            "{} the focus loss policy {:?}",
+            if focused { "undoing" } else { "applying" },
+            policy
+        );
+
+        if focused {
+            if std::mem::take(&mut self.focus.paused) {
+                self.renderer.resume_simulation();
+            }
+            if std::mem::take(&mut self.focus.muted) {
+                self.audio.set_background_muted(false);
+            }
+        } else {
+            // a game that paused itself stays paused when focus comes back
+            if policy.pause && !self.renderer.is_simulation_paused() {
+                self.renderer.pause_simulation();
+                self.focus.paused = true;
+            }
+            if policy.mute {
+                self.audio.set_background_muted(true);
+                self.focus.muted = true;
+            }
+        }
+
+        self.input.queue_mut().push(EngineEvent::Background {
+            active: !focused,
+            policy,
+        });
+    }
+
+    // when the next frame may start, None when it can start right away. only
+    // set while a background frame rate cap applies, a platform overlay lifts it
+    pub fn next_frame_at(&self) -> Option<Instant> {
+        if self.integrations.is_overlay_active() {
+            return None;
+        }
+        self.focus.next_frame_at()
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        let queued = self.input.queue().events().len();
+        self.input.handle_device_event(event);
+        if self.input.queue().events().len() > queued {
+            self.renderer.record_input(Instant::now());
+        }
     }
 
     pub fn handle_resize(&mut self, size: PhysicalSize<u32>) {
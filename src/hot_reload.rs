@@ -0,0 +1,181 @@
+use std::ffi::c_void;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use libloading::Library;
+use log::{Level, error, info, warn};
+
+use crate::state::{GameState, StateContext, Transition};
+
+// what the game library exports, built against the same engine and compiler:
+//
+//   #[unsafe(no_mangle)]
+//   pub fn nivalis_game_api() -> GameApi { ... }
+pub const GAME_API_SYMBOL: &[u8] = b"nivalis_game_api";
+pub const GAME_API_VERSION: u32 = 1;
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+// the game's callbacks. state is created once by the first library and handed to
+// every reloaded one, so its layout has to stay the same between reloads
+#[derive(Clone, Copy)]
+pub struct GameApi {
+    pub version: u32,
+    pub create: fn(ctx: &mut StateContext) -> *mut c_void,
+    pub destroy: fn(state: *mut c_void),
+    pub reloaded: fn(state: *mut c_void, ctx: &mut StateContext), // after a new library took over
+    pub update: fn(state: *mut c_void, ctx: &mut StateContext, dt_seconds: f32),
+    pub render: fn(state: *mut c_void, ctx: &mut StateContext),
+}
+
+struct LoadedGame {
+    api: GameApi,
+    library: Library, // dropped after api is no longer called
+}
+
+// a game state whose logic lives in a cdylib, reloaded whenever the file changes.
+// the library is copied before loading so the compiler can overwrite the original
+pub struct HotReloadState {
+    path: PathBuf,
+    game: Option<LoadedGame>,
+    state: *mut c_void, // the game's own state, kept across reloads
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    generation: u32, // names the copies so the os doesn't hand back a cached library
+}
+
+impl HotReloadState {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        HotReloadState {
+            path: path.into(),
+            game: None,
+            state: std::ptr::null_mut(),
+            modified: None,
+            last_check: Instant::now(),
+            generation: 0,
+        }
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    fn check_reload(&mut self, ctx: &mut StateContext) {
+        if self.game.is_some() && self.last_check.elapsed() < CHECK_INTERVAL {
+            return;
+        }
+        self.last_check = Instant::now();
+
+        let modified = self.modified_time();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+
+        // a failed load keeps the old library running
+        let game = match self.load() {
+            Ok(game) => game,
+            Err(e) => {
+                error!(
+                    "failed to load game library {}: {:#}",
+                    self.path.display(),
+                    e
+                );
+                self.modified = modified; // don't retry until it changes again
+                return;
+            }
+        };
+        self.modified = modified;
+
+        let api = game.api;
+        let reloaded = self.game.replace(game).is_some();
+        if self.state.is_null() {
+            self.state = (api.create)(ctx);
+        } else if reloaded {
+            (api.reloaded)(self.state, ctx);
+        }
+        info!(
+            "{} game library {} (generation {})",
+            if reloaded { "reloaded" } else { "loaded" },
+            self.path.display(),
+            self.generation
+        );
+        if reloaded {
+            ctx.renderer
+                .notify("reloaded the game library", Level::Info, 2.0);
+        }
+    }
+
+    fn load(&mut self) -> anyhow::Result<LoadedGame> {
+        self.generation += 1;
+        let extension = self
+            .path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or(std::env::consts::DLL_EXTENSION);
+        let copy = std::env::temp_dir().join(format!(
+            "nivalis-game-{}-{}.{}",
+            std::process::id(),
+            self.generation,
+            extension
+        ));
+        fs::copy(&self.path, &copy)?;
+
+        // the library's initialisers run here, a game library is trusted like the engine itself
+        let library = unsafe { Library::new(&copy)? };
+        let api = unsafe {
+            let entry = library.get::<fn() -> GameApi>(GAME_API_SYMBOL)?;
+            entry()
+        };
+        if api.version != GAME_API_VERSION {
+            anyhow::bail!(
+                "game api version {} doesn't match the engine's {}",
+                api.version,
+                GAME_API_VERSION
+            );
+        }
+
+        // linux and macos keep the mapping alive, the copy can go right away
+        if let Err(e) = fs::remove_file(&copy)
+            && !cfg!(target_os = "windows")
+        {
+            warn!("failed to remove {}: {}", copy.display(), e);
+        }
+
+        Ok(LoadedGame { api, library })
+    }
+}
+
+impl GameState for HotReloadState {
+    fn name(&self) -> &str {
+        "hot reload"
+    }
+
+    fn update(&mut self, ctx: &mut StateContext, dt_seconds: f32) -> Transition {
+        self.check_reload(ctx);
+        if let Some(game) = &self.game {
+            (game.api.update)(self.state, ctx, dt_seconds);
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, ctx: &mut StateContext) {
+        if let Some(game) = &self.game {
+            (game.api.render)(self.state, ctx);
+        }
+    }
+}
+
+impl Drop for HotReloadState {
+    fn drop(&mut self) {
+        let Some(game) = self.game.take() else {
+            return;
+        };
+        if !self.state.is_null() {
+            (game.api.destroy)(self.state);
+        }
+        drop(game.library);
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use log::{info, warn};
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use serde::de::value::{Error, StrDeserializer};
+
+use crate::input::{Input, KeyCode};
+
+// something the player does, bound to one key. gameplay asks about actions
+// instead of keys so players can rebind them
+#[derive(Clone, PartialEq, Debug)]
+pub struct Action {
+    pub name: String,  // also its key in the settings' keybinds
+    pub label: String, // what the rebinding screen shows
+    pub default: KeyCode,
+    pub key: Option<KeyCode>, // None once the player cleared it
+}
+
+#[derive(Default)]
+pub(super) struct Actions {
+    actions: Vec<Action>, // in the order they were registered, as the screen lists them
+    // keybinds from the settings file, applied as actions get registered and
+    // kept for actions this run never registers
+    saved: BTreeMap<String, String>,
+}
+
+// Space, KeyW, ArrowLeft, as winit names the physical keys
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let deserializer: StrDeserializer<Error> = name.into_deserializer();
+    KeyCode::deserialize(deserializer).ok()
+}
+
+// an empty name is a binding the player cleared
+fn saved_key(action: &str, name: &str) -> Option<Option<KeyCode>> {
+    if name.is_empty() {
+        return Some(None);
+    }
+    match parse_key(name) {
+        Some(key) => Some(Some(key)),
+        None => {
+            warn!(
+                "unknown key {} bound to {}, using its default",
+                name, action
+            );
+            None
+        }
+    }
+}
+
+impl Input {
+    // registering an action again updates its label and default, the player's
+    // binding stays
+    pub fn register_action(&mut self, name: &str, label: &str, default: KeyCode) {
+        let actions = &mut self.actions;
+        if let Some(action) = actions.actions.iter_mut().find(|a| a.name == name) {
+            action.label = label.to_string();
+            action.default = default;
+            return;
+        }
+
+        let key = actions
+            .saved
+            .get(name)
+            .and_then(|saved| saved_key(name, saved))
+            .unwrap_or(Some(default));
+        actions.actions.push(Action {
+            name: name.to_string(),
+            label: label.to_string(),
+            default,
+            key,
+        });
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions.actions
+    }
+
+    pub fn action(&self, name: &str) -> Option<&Action> {
+        self.actions
+            .actions
+            .iter()
+            .find(|action| action.name == name)
+    }
+
+    // None leaves the action unbound. false when there is no such action
+    pub fn bind_action(&mut self, name: &str, key: Option<KeyCode>) -> bool {
+        let Some(action) = self.actions.actions.iter_mut().find(|a| a.name == name) else {
+            warn!("no action {} to bind", name);
+            return false;
+        };
+        info!("binding {} to {:?}", name, key);
+        action.key = key;
+        true
+    }
+
+    pub fn reset_actions(&mut self) {
+        for action in self.actions.actions.iter_mut() {
+            action.key = Some(action.default);
+        }
+    }
+
+    // the other actions bound to the same key as this one
+    pub fn action_conflicts(&self, name: &str) -> Vec<&str> {
+        let Some(key) = self.action(name).and_then(|action| action.key) else {
+            return Vec::new();
+        };
+        self.actions
+            .actions
+            .iter()
+            .filter(|action| action.name != name && action.key == Some(key))
+            .map(|action| action.name.as_str())
+            .collect()
+    }
+
+    pub fn has_action_conflicts(&self) -> bool {
+        self.actions
+            .actions
+            .iter()
+            .any(|action| !self.action_conflicts(&action.name).is_empty())
+    }
+
+    pub fn action_pressed(&self, name: &str) -> bool {
+        self.action_key(name)
+            .is_some_and(|key| self.key_pressed(key))
+    }
+
+    fn action_key(&self, name: &str) -> Option<KeyCode> {
+        self.action(name)?.key
+    }
+
+    // action name to key name, as the settings file keeps them
+    pub fn load_keybinds(&mut self, keybinds: &BTreeMap<String, String>) {
+        self.actions.saved = keybinds.clone();
+        for action in self.actions.actions.iter_mut() {
+            if let Some(key) = keybinds
+                .get(&action.name)
+                .and_then(|saved| saved_key(&action.name, saved))
+            {
+                action.key = key;
+            }
+        }
+    }
+
+    // only bindings that differ from their default, so changed defaults reach
+    // players who never rebound them
+    pub fn keybinds(&self) -> BTreeMap<String, String> {
+        let mut keybinds = self.actions.saved.clone();
+        for action in self.actions.actions.iter() {
+            match action.key {
+                Some(key) if key == action.default => {
+                    keybinds.remove(&action.name);
+                }
+                key => {
+                    keybinds.insert(action.name.clone(), key.map(key_name).unwrap_or_default());
+                }
+            }
+        }
+        keybinds
+    }
+}
@@ -0,0 +1,244 @@
+use log::trace;
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, MouseScrollDelta, WindowEvent};
+use winit::keyboard::PhysicalKey;
+
+use crate::input::touch::{Gesture, TouchPhase};
+use crate::input::{KeyCode, TextInputEvent};
+use crate::platform::focus::FocusLossPolicy;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+impl From<winit::event::MouseButton> for MouseButton {
+    fn from(button: winit::event::MouseButton) -> Self {
+        match button {
+            winit::event::MouseButton::Left => MouseButton::Left,
+            winit::event::MouseButton::Right => MouseButton::Right,
+            winit::event::MouseButton::Middle => MouseButton::Middle,
+            winit::event::MouseButton::Back => MouseButton::Back,
+            winit::event::MouseButton::Forward => MouseButton::Forward,
+            winit::event::MouseButton::Other(id) => MouseButton::Other(id),
+        }
+    }
+}
+
+// window input translated into engine terms, positions in physical pixels
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineEvent {
+    KeyPressed {
+        key: KeyCode,
+        repeat: bool,
+    },
+    KeyReleased {
+        key: KeyCode,
+    },
+    MouseMoved {
+        position: [f32; 2],
+    },
+    // raw device movement, only in relative mode
+    MouseMotion {
+        delta: [f32; 2],
+    },
+    MousePressed {
+        button: MouseButton,
+        position: [f32; 2],
+    },
+    MouseReleased {
+        button: MouseButton,
+        position: [f32; 2],
+    },
+    Scrolled {
+        delta: [f32; 2],
+    }, // in lines
+    Text(TextInputEvent),
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        position: [f32; 2],
+    },
+    Gesture(Gesture), // recognised from touches, after the touch that completed it
+    Focused(bool),
+    // the engine applied the settings' focus loss policy, or undid it with
+    // focus back. after the Focused event that caused it
+    Background {
+        active: bool,
+        policy: FocusLossPolicy,
+    },
+    // a platform overlay opened or closed, see Integrations
+    Overlay {
+        active: bool,
+    },
+    Resized {
+        size: [u32; 2],
+    },
+    CloseRequested,
+    // gameplay triggered a named haptic, see Input::trigger_haptic
+    Haptic {
+        name: String,
+        gamepad: Option<usize>,
+    },
+}
+
+const PIXELS_PER_LINE: f32 = 20.0;
+
+impl EngineEvent {
+    // None for window events the engine doesn't surface
+    pub fn from_window_event(event: &WindowEvent, mouse_position: [f32; 2]) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                let PhysicalKey::Code(key) = event.physical_key else {
+                    return None;
+                };
+
+                Some(match event.state {
+                    ElementState::Pressed => EngineEvent::KeyPressed {
+                        key,
+                        repeat: event.repeat,
+                    },
+                    ElementState::Released => EngineEvent::KeyReleased { key },
+                })
+            }
+            WindowEvent::CursorMoved { position, .. } => Some(EngineEvent::MouseMoved {
+                position: [position.x as f32, position.y as f32],
+            }),
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = MouseButton::from(*button);
+                Some(match state {
+                    ElementState::Pressed => EngineEvent::MousePressed {
+                        button,
+                        position: mouse_position,
+                    },
+                    ElementState::Released => EngineEvent::MouseReleased {
+                        button,
+                        position: mouse_position,
+                    },
+                })
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => [*x, *y],
+                    MouseScrollDelta::PixelDelta(position) => [
+                        position.x as f32 / PIXELS_PER_LINE,
+                        position.y as f32 / PIXELS_PER_LINE,
+                    ],
+                };
+                Some(EngineEvent::Scrolled { delta })
+            }
+            WindowEvent::Touch(touch) => Some(EngineEvent::Touch {
+                id: touch.id,
+                phase: TouchPhase::from(touch.phase),
+                position: [touch.location.x as f32, touch.location.y as f32],
+            }),
+            WindowEvent::Focused(focused) => Some(EngineEvent::Focused(*focused)),
+            WindowEvent::Resized(PhysicalSize { width, height }) => Some(EngineEvent::Resized {
+                size: [*width, *height],
+            }),
+            WindowEvent::CloseRequested => Some(EngineEvent::CloseRequested),
+            _ => None,
+        }
+    }
+
+    pub fn is_keyboard(&self) -> bool {
+        matches!(
+            self,
+            EngineEvent::KeyPressed { .. } | EngineEvent::KeyReleased { .. } | EngineEvent::Text(_)
+        )
+    }
+
+    pub fn is_mouse(&self) -> bool {
+        matches!(
+            self,
+            EngineEvent::MouseMoved { .. }
+                | EngineEvent::MouseMotion { .. }
+                | EngineEvent::MousePressed { .. }
+                | EngineEvent::MouseReleased { .. }
+                | EngineEvent::Scrolled { .. }
+        )
+    }
+
+    pub fn is_touch(&self) -> bool {
+        matches!(self, EngineEvent::Touch { .. } | EngineEvent::Gesture(_))
+    }
+}
+
+pub struct QueuedEvent {
+    pub sequence: u64, // strictly increasing over the whole run
+    pub frame: u64,
+    pub event: EngineEvent,
+    consumed_by: Option<&'static str>,
+}
+
+impl QueuedEvent {
+    pub fn is_consumed(&self) -> bool {
+        self.consumed_by.is_some()
+    }
+
+    pub fn consumed_by(&self) -> Option<&'static str> {
+        self.consumed_by
+    }
+
+    // stops propagation to consumers after this one, the first consumer wins
+    pub fn consume(&mut self, consumer: &'static str) {
+        if self.consumed_by.is_none() {
+            self.consumed_by = Some(consumer);
+        }
+    }
+}
+
+// this frame's events in arrival order, consumers walk it front to back:
+// ui first, then game states, then scripting
+#[derive(Default)]
+pub struct EventQueue {
+    events: Vec<QueuedEvent>,
+    next_sequence: u64,
+    frame: u64,
+}
+
+impl EventQueue {
+    pub fn push(&mut self, event: EngineEvent) {
+        self.events.push(QueuedEvent {
+            sequence: self.next_sequence,
+            frame: self.frame,
+            event,
+            consumed_by: None,
+        });
+        self.next_sequence += 1;
+    }
+
+    pub fn events(&self) -> &[QueuedEvent] {
+        &self.events
+    }
+
+    pub fn events_mut(&mut self) -> &mut [QueuedEvent] {
+        &mut self.events
+    }
+
+    pub fn unconsumed_mut(&mut self) -> impl Iterator<Item = &mut QueuedEvent> {
+        self.events
+            .iter_mut()
+            .filter(|queued| !queued.is_consumed())
+    }
+
+    // `log nivalis::input::events trace` shows who took which event
+    pub fn end_frame(&mut self) {
+        for queued in self.events.iter() {
+            trace!(
+                "frame {} event {}: {:?}, consumed by {}",
+                queued.frame,
+                queued.sequence,
+                queued.event,
+                queued.consumed_by().unwrap_or("nobody")
+            );
+        }
+        self.events.clear();
+        self.frame += 1;
+    }
+}
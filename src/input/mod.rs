@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+
+use log::info;
+use winit::event::{DeviceEvent, ElementState, Ime, WindowEvent};
+use winit::keyboard::{Key, NamedKey, PhysicalKey};
+
+pub use winit::keyboard::KeyCode;
+
+use crate::input::actions::Actions;
+use crate::input::events::{EngineEvent, EventQueue};
+use crate::input::rumble::Haptics;
+use crate::input::touch::Touches;
+
+pub mod actions;
+pub mod events;
+pub mod rumble;
+pub mod touch;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextInputEvent {
+    Commit(String),
+    // in progress ime text, cursor is a byte range into it
+    Composition {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Submit,
+}
+
+#[derive(Default)]
+pub struct Input {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+
+    mouse_position: [f32; 2], // physical pixels, where the queue's clicks happened
+    mouse_delta: [f32; 2],
+    relative_mouse: bool,
+
+    text_events: Vec<TextInputEvent>,
+    text_input: bool,
+    ime_active: bool,
+    ime_area: Option<([f32; 2], [f32; 2])>,
+
+    queue: EventQueue,
+    actions: Actions,
+    haptics: Haptics,
+    touches: Touches,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Input::default()
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        if let Some(engine_event) = EngineEvent::from_window_event(event, self.mouse_position) {
+            self.queue.push(engine_event);
+        }
+
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    match event.state {
+                        ElementState::Pressed => {
+                            if self.keys_down.insert(code) {
+                                self.keys_pressed.insert(code);
+                            }
+                        }
+                        ElementState::Released => {
+                            self.keys_down.remove(&code);
+                        }
+                    }
+                }
+
+                if self.text_input && event.state == ElementState::Pressed {
+                    self.handle_text_key(&event.logical_key, event.text.as_deref());
+                }
+            }
+            WindowEvent::Ime(ime) => self.handle_ime(ime),
+            WindowEvent::Touch(touch) => self.handle_touch(touch),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_position = [position.x as f32, position.y as f32];
+            }
+            WindowEvent::Focused(false) => {
+                // keys released while unfocused never arrive
+                self.keys_down.clear();
+                self.clear_touches();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        // deltas also arrive while the cursor is free, only relative mode wants them
+        if let DeviceEvent::MouseMotion { delta } = event
+            && self.relative_mouse
+        {
+            let delta = [delta.0 as f32, delta.1 as f32];
+            self.mouse_delta[0] += delta[0];
+            self.mouse_delta[1] += delta[1];
+            self.queue.push(EngineEvent::MouseMotion { delta });
+        }
+    }
+
+    fn handle_text_key(&mut self, key: &Key, text: Option<&str>) {
+        let event = match key {
+            Key::Named(NamedKey::Backspace) => TextInputEvent::Backspace,
+            Key::Named(NamedKey::Delete) => TextInputEvent::Delete,
+            Key::Named(NamedKey::ArrowLeft) => TextInputEvent::Left,
+            Key::Named(NamedKey::ArrowRight) => TextInputEvent::Right,
+            Key::Named(NamedKey::Home) => TextInputEvent::Home,
+            Key::Named(NamedKey::End) => TextInputEvent::End,
+            Key::Named(NamedKey::Enter) => TextInputEvent::Submit,
+            _ => {
+                // the ime delivers typed text itself through commit events
+                if self.ime_active {
+                    return;
+                }
+
+                let Some(text) = text else {
+                    return;
+                };
+
+                let text: String = text.chars().filter(|c| !c.is_control()).collect();
+                if text.is_empty() {
+                    return;
+                }
+
+                TextInputEvent::Commit(text)
+            }
+        };
+
+        self.push_text_event(event);
+    }
+
+    fn handle_ime(&mut self, ime: &Ime) {
+        match ime {
+            Ime::Enabled => self.ime_active = true,
+            Ime::Disabled => self.ime_active = false,
+            Ime::Preedit(text, cursor) => self.push_text_event(TextInputEvent::Composition {
+                text: text.clone(),
+                cursor: *cursor,
+            }),
+            Ime::Commit(text) => self.push_text_event(TextInputEvent::Commit(text.clone())),
+        }
+    }
+
+    fn push_text_event(&mut self, event: TextInputEvent) {
+        self.queue.push(EngineEvent::Text(event.clone()));
+        self.text_events.push(event);
+    }
+
+    // called by the engine after every state had its update
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.mouse_delta = [0.0, 0.0];
+        self.text_events.clear();
+        self.queue.end_frame();
+    }
+
+    // every event of this frame in order, with consumption flags
+    pub fn queue(&self) -> &EventQueue {
+        &self.queue
+    }
+
+    pub fn queue_mut(&mut self) -> &mut EventQueue {
+        &mut self.queue
+    }
+
+    pub fn key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    // accumulated device movement this frame, zero outside of relative mode
+    pub fn mouse_delta(&self) -> [f32; 2] {
+        self.mouse_delta
+    }
+
+    pub fn is_relative_mouse(&self) -> bool {
+        self.relative_mouse
+    }
+
+    // the engine locks and hides the cursor while this is on
+    pub fn set_relative_mouse(&mut self, relative: bool) {
+        self.relative_mouse = relative;
+        self.mouse_delta = [0.0, 0.0];
+    }
+
+    pub fn text_events(&self) -> &[TextInputEvent] {
+        &self.text_events
+    }
+
+    pub fn is_text_input(&self) -> bool {
+        self.text_input
+    }
+
+    // the engine allows the ime on the window while this is on
+    pub fn start_text_input(&mut self) {
+        if !self.text_input {
+            info!("starting text input");
+        }
+        self.text_input = true;
+    }
+
+    pub fn stop_text_input(&mut self) {
+        self.text_input = false;
+        self.ime_area = None;
+    }
+
+    // where the ime candidate window should go, in physical pixels
+    pub fn set_ime_area(&mut self, position: [f32; 2], size: [f32; 2]) {
+        self.ime_area = Some((position, size));
+    }
+
+    pub fn ime_area(&self) -> Option<([f32; 2], [f32; 2])> {
+        self.ime_area
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use log::warn;
+
+use crate::input::Input;
+use crate::input::events::EngineEvent;
+
+// motor strengths, 0 to 1
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Motors {
+    pub strong: f32,        // low frequency, the heavy rumble
+    pub weak: f32,          // high frequency, the buzz
+    pub triggers: [f32; 2], // left and right trigger motors where the pad has them
+}
+
+impl Motors {
+    fn scaled(self, scale: f32) -> Motors {
+        Motors {
+            strong: self.strong * scale,
+            weak: self.weak * scale,
+            triggers: self.triggers.map(|trigger| trigger * scale),
+        }
+    }
+
+    // effects on the same pad add up, clipping at full strength
+    fn add(self, other: Motors) -> Motors {
+        Motors {
+            strong: (self.strong + other.strong).min(1.0),
+            weak: (self.weak + other.weak).min(1.0),
+            triggers: [
+                (self.triggers[0] + other.triggers[0]).min(1.0),
+                (self.triggers[1] + other.triggers[1]).min(1.0),
+            ],
+        }
+    }
+}
+
+// a rumble effect: ramps up over attack, holds for duration, then fades out over release
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rumble {
+    pub motors: Motors,
+    pub attack: f32, // seconds
+    pub duration: f32,
+    pub release: f32,
+}
+
+impl Default for Rumble {
+    fn default() -> Self {
+        Rumble {
+            motors: Motors {
+                strong: 0.5,
+                weak: 0.5,
+                triggers: [0.0, 0.0],
+            },
+            attack: 0.0,
+            duration: 0.2,
+            release: 0.1,
+        }
+    }
+}
+
+impl Rumble {
+    pub fn length(&self) -> f32 {
+        self.attack + self.duration + self.release
+    }
+
+    // how far the effect is along its envelope, 0 to 1
+    fn envelope(&self, time: f32) -> f32 {
+        if time < self.attack {
+            time / self.attack
+        } else if time < self.attack + self.duration {
+            1.0
+        } else if self.release > 0.0 {
+            (1.0 - (time - self.attack - self.duration) / self.release).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+// drives the motors of real controllers, the engine only mixes effects
+pub trait RumbleBackend {
+    // connected pads that can rumble
+    fn gamepads(&self) -> Vec<usize>;
+    fn set_motors(&mut self, gamepad: usize, motors: Motors);
+}
+
+struct ActiveRumble {
+    id: usize,
+    gamepad: Option<usize>, // None plays on every pad
+    rumble: Rumble,
+    time: f32,
+}
+
+pub(super) struct Haptics {
+    backend: Option<Box<dyn RumbleBackend>>,
+    scale: f32, // from the settings, 0 turns rumble off
+    effects: Vec<ActiveRumble>,
+    next_id: usize,
+    named: HashMap<String, Rumble>,
+    sent: HashMap<usize, Motors>, // what every pad was last told
+}
+
+impl Default for Haptics {
+    fn default() -> Self {
+        Haptics {
+            backend: None,
+            scale: 1.0,
+            effects: Vec::new(),
+            next_id: 0,
+            named: HashMap::new(),
+            sent: HashMap::new(),
+        }
+    }
+}
+
+impl Input {
+    // effects keep playing without one, nothing is felt.
+    // games bring the backend, the engine reads no gamepads itself
+    #[allow(dead_code)]
+    pub fn set_rumble_backend(&mut self, backend: Option<Box<dyn RumbleBackend>>) {
+        self.stop_all_rumble();
+        self.update_rumble(0.0);
+        self.haptics.backend = backend;
+        self.haptics.sent.clear();
+    }
+
+    pub fn set_rumble_scale(&mut self, scale: f32) {
+        self.haptics.scale = scale.clamp(0.0, 1.0);
+    }
+
+    pub fn rumble_scale(&self) -> f32 {
+        self.haptics.scale
+    }
+
+    // returns an id for stop_rumble, gamepad None plays on every pad
+    pub fn rumble(&mut self, gamepad: Option<usize>, rumble: Rumble) -> usize {
+        let id = self.haptics.next_id;
+        self.haptics.next_id += 1;
+        self.haptics.effects.push(ActiveRumble {
+            id,
+            gamepad,
+            rumble,
+            time: 0.0,
+        });
+        id
+    }
+
+    pub fn stop_rumble(&mut self, id: usize) {
+        self.haptics.effects.retain(|effect| effect.id != id);
+    }
+
+    pub fn stop_all_rumble(&mut self) {
+        self.haptics.effects.clear();
+    }
+
+    pub fn is_rumbling(&self, id: usize) -> bool {
+        self.haptics.effects.iter().any(|effect| effect.id == id)
+    }
+
+    // names gameplay can trigger without knowing the effect, e.g. "hit" or "explosion"
+    pub fn set_haptic(&mut self, name: &str, rumble: Rumble) {
+        self.haptics.named.insert(name.to_string(), rumble);
+    }
+
+    // plays the named effect and queues a Haptic event for anything else that reacts to it
+    pub fn trigger_haptic(&mut self, name: &str, gamepad: Option<usize>) -> Option<usize> {
+        let Some(rumble) = self.haptics.named.get(name).copied() else {
+            warn!("no haptic named {}", name);
+            return None;
+        };
+
+        self.queue.push(EngineEvent::Haptic {
+            name: name.to_string(),
+            gamepad,
+        });
+        Some(self.rumble(gamepad, rumble))
+    }
+
+    // advances every effect and tells the backend about pads whose motors changed
+    pub(crate) fn update_rumble(&mut self, dt_seconds: f32) {
+        let haptics = &mut self.haptics;
+        for effect in &mut haptics.effects {
+            effect.time += dt_seconds;
+        }
+        haptics
+            .effects
+            .retain(|effect| effect.time < effect.rumble.length());
+
+        let Some(backend) = &mut haptics.backend else {
+            return;
+        };
+
+        for gamepad in backend.gamepads() {
+            let motors = haptics
+                .effects
+                .iter()
+                .filter(|effect| effect.gamepad.is_none_or(|target| target == gamepad))
+                .map(|effect| {
+                    effect
+                        .rumble
+                        .motors
+                        .scaled(effect.rumble.envelope(effect.time) * haptics.scale)
+                })
+                .fold(Motors::default(), Motors::add);
+
+            let sent = haptics.sent.get(&gamepad).copied().unwrap_or_default();
+            if sent != motors {
+                backend.set_motors(gamepad, motors);
+                haptics.sent.insert(gamepad, motors);
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::time::{Duration, Instant};
+
+use crate::input::Input;
+use crate::input::events::EngineEvent;
+use crate::renderer::camera::Camera2D;
+
+const TAP_TIME: Duration = Duration::from_millis(300);
+const TAP_DISTANCE: f32 = 16.0; // physical pixels a tap may wander before it's a drag
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled, // the os took the touch away, never a tap
+}
+
+impl From<winit::event::TouchPhase> for TouchPhase {
+    fn from(phase: winit::event::TouchPhase) -> Self {
+        match phase {
+            winit::event::TouchPhase::Started => TouchPhase::Started,
+            winit::event::TouchPhase::Moved => TouchPhase::Moved,
+            winit::event::TouchPhase::Ended => TouchPhase::Ended,
+            winit::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TouchPoint {
+    pub id: u64, // stays the same from start to end of one finger
+    pub position: [f32; 2],
+    pub start: [f32; 2],
+    started_at: Instant,
+    moved: bool, // past the tap distance or part of a pinch
+}
+
+// recognised from the raw touches, positions in physical pixels
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Gesture {
+    Tap {
+        position: [f32; 2],
+    },
+    // one finger moving
+    Drag {
+        position: [f32; 2],
+        delta: [f32; 2],
+    },
+    // two fingers, scale is this event's spread over the last one's
+    Pinch {
+        center: [f32; 2],
+        delta: [f32; 2],
+        scale: f32,
+    },
+}
+
+impl Gesture {
+    // drags and pinches pan the camera, pinches zoom it around their center
+    pub fn apply_to_camera(&self, camera: &mut Camera2D) {
+        match *self {
+            Gesture::Tap { .. } => {}
+            Gesture::Drag { delta, .. } => camera.pan(delta),
+            Gesture::Pinch {
+                center,
+                delta,
+                scale,
+            } => {
+                camera.pan(delta);
+                camera.zoom_around(center, scale);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Touches {
+    active: Vec<TouchPoint>,
+    pinch: Option<([f32; 2], f32)>, // last center and spread of the two fingers
+}
+
+impl Input {
+    pub(super) fn handle_touch(&mut self, touch: &winit::event::Touch) {
+        let position = [touch.location.x as f32, touch.location.y as f32];
+        let touches = &mut self.touches;
+
+        match TouchPhase::from(touch.phase) {
+            TouchPhase::Started => touches.active.push(TouchPoint {
+                id: touch.id,
+                position,
+                start: position,
+                started_at: Instant::now(),
+                moved: false,
+            }),
+            TouchPhase::Moved => {
+                let Some(point) = touches.active.iter_mut().find(|point| point.id == touch.id)
+                else {
+                    return;
+                };
+                let delta = [
+                    position[0] - point.position[0],
+                    position[1] - point.position[1],
+                ];
+                point.position = position;
+
+                if touches.active.len() == 1 {
+                    let point = &mut touches.active[0];
+                    let travelled = distance(point.start, position);
+                    point.moved |= travelled > TAP_DISTANCE;
+                    if point.moved {
+                        self.push_gesture(Gesture::Drag { position, delta });
+                    }
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let Some(index) = touches.active.iter().position(|point| point.id == touch.id)
+                else {
+                    return;
+                };
+                let point = touches.active.remove(index);
+
+                let is_tap = touch.phase == winit::event::TouchPhase::Ended
+                    && !point.moved
+                    && point.started_at.elapsed() < TAP_TIME;
+                if is_tap {
+                    self.push_gesture(Gesture::Tap { position });
+                }
+            }
+        }
+
+        self.update_pinch();
+    }
+
+    fn update_pinch(&mut self) {
+        let touches = &mut self.touches;
+        let [first, second] = touches.active[..] else {
+            touches.pinch = None;
+            return;
+        };
+
+        let center = [
+            (first.position[0] + second.position[0]) * 0.5,
+            (first.position[1] + second.position[1]) * 0.5,
+        ];
+        let spread = distance(first.position, second.position).max(1.0);
+
+        let Some((last_center, last_spread)) = touches.pinch.replace((center, spread)) else {
+            return; // the second finger just landed
+        };
+        if last_center == center && last_spread == spread {
+            return;
+        }
+
+        // neither finger taps anymore once they pinched
+        for point in &mut touches.active {
+            point.moved = true;
+        }
+        self.push_gesture(Gesture::Pinch {
+            center,
+            delta: [center[0] - last_center[0], center[1] - last_center[1]],
+            scale: spread / last_spread,
+        });
+    }
+
+    fn push_gesture(&mut self, gesture: Gesture) {
+        self.queue.push(EngineEvent::Gesture(gesture));
+    }
+
+    pub(super) fn clear_touches(&mut self) {
+        self.touches.active.clear();
+        self.touches.pinch = None;
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeSet;
+use std::time::SystemTime;
+
+use log::{info, warn};
+
+// what the player is doing, as friends lists show it
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Presence {
+    pub details: String,             // the first line, e.g. "forest, level 3"
+    pub state: String,               // the second line, e.g. "in a party"
+    pub party: Option<[u32; 2]>,     // size and capacity
+    pub started: Option<SystemTime>, // shown as time elapsed
+    pub image: Option<String>,       // an asset key uploaded to the platform
+}
+
+// glue for a platform sdk like steamworks or discord. every hook has a default
+// that does nothing, so a backend only implements what its platform has. the
+// engine never links an sdk itself
+pub trait Integration {
+    fn name(&self) -> &str;
+
+    // runs the sdk's callbacks, once a frame
+    fn update(&mut self) {}
+
+    fn set_presence(&mut self, _presence: &Presence) {}
+
+    fn unlock_achievement(&mut self, _id: &str) {}
+
+    fn set_achievement_progress(&mut self, _id: &str, _current: u32, _max: u32) {}
+
+    // whether the platform draws its overlay over the game right now
+    fn is_overlay_active(&self) -> bool {
+        false
+    }
+}
+
+// every registered backend, fed the same presence and achievements. backends
+// added late get what the game already said
+#[derive(Default)]
+pub struct Integrations {
+    backends: Vec<Box<dyn Integration>>,
+    presence: Presence,
+    unlocked: BTreeSet<String>, // this run
+    overlay: bool,
+}
+
+impl Integrations {
+    #[allow(dead_code)] // the game adds the backends of its platforms
+    pub fn add(&mut self, mut backend: Box<dyn Integration>) {
+        info!("adding the {} integration", backend.name());
+        if self.presence != Presence::default() {
+            backend.set_presence(&self.presence);
+        }
+        for id in self.unlocked.iter() {
+            backend.unlock_achievement(id);
+        }
+        self.backends.push(backend);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        let count = self.backends.len();
+        self.backends.retain(|backend| backend.name() != name);
+        if self.backends.len() == count {
+            warn!("no integration {} to remove", name);
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.backends.iter().map(|backend| backend.name())
+    }
+
+    pub fn presence(&self) -> &Presence {
+        &self.presence
+    }
+
+    // only reaches the backends when it changed, platforms rate limit updates
+    pub fn set_presence(&mut self, presence: Presence) {
+        if presence == self.presence {
+            return;
+        }
+        for backend in self.backends.iter_mut() {
+            backend.set_presence(&presence);
+        }
+        self.presence = presence;
+    }
+
+    pub fn unlock_achievement(&mut self, id: &str) {
+        if !self.unlocked.insert(id.to_string()) {
+            return;
+        }
+        info!("unlocking achievement {}", id);
+        for backend in self.backends.iter_mut() {
+            backend.unlock_achievement(id);
+        }
+    }
+
+    pub fn is_achievement_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    // reaching max unlocks it
+    pub fn set_achievement_progress(&mut self, id: &str, current: u32, max: u32) {
+        if current >= max {
+            self.unlock_achievement(id);
+            return;
+        }
+        for backend in self.backends.iter_mut() {
+            backend.set_achievement_progress(id, current, max);
+        }
+    }
+
+    // while an overlay is up the engine keeps drawing every frame and lets go
+    // of the mouse, so the overlay stays responsive
+    pub fn is_overlay_active(&self) -> bool {
+        self.overlay
+    }
+
+    // Some with the new state when an overlay opened or closed
+    pub(crate) fn update(&mut self) -> Option<bool> {
+        for backend in self.backends.iter_mut() {
+            backend.update();
+        }
+        let overlay = self
+            .backends
+            .iter()
+            .any(|backend| backend.is_overlay_active());
+        if overlay == self.overlay {
+            return None;
+        }
+        info!(
+            "platform overlay {}",
+            if overlay { "opened" } else { "closed" }
+        );
+        self.overlay = overlay;
+        Some(overlay)
+    }
+}
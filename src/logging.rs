@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+
+use log::{LevelFilter, Log, Metadata, Record, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+const LOG_FILE: &str = "nivalis";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+// the log table in the settings file, RUST_LOG style directives in the
+// environment win over it
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: LogLevel,                     // modules without their own entry
+    pub modules: BTreeMap<String, LogLevel>, // e.g. "nivalis::renderer" = "trace"
+    pub file: bool,                          // also write to the user data dir
+    pub max_file_mb: u64,                    // rolls over to a new file past this
+    pub kept_files: usize,                   // older logs next to the current one
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: LogLevel::Error,
+            modules: BTreeMap::from([("nivalis".to_string(), LogLevel::Debug)]),
+            file: true,
+            max_file_mb: 8,
+            kept_files: 4,
+        }
+    }
+}
+
+// the most specific module wins, a module covers everything under it
+struct Filter {
+    default: LevelFilter,
+    modules: BTreeMap<String, LevelFilter>,
+}
+
+impl Filter {
+    fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module.as_str()
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max(&self) -> LevelFilter {
+        self.modules.values().copied().fold(self.default, Ord::max)
+    }
+}
+
+// writes through to the file, starting a new one when it gets too big. the
+// previous ones are shifted to nivalis.1.log, nivalis.2.log and so on
+struct RollingFile {
+    dir: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    kept: usize,
+}
+
+impl RollingFile {
+    fn open(dir: &Path, max_bytes: u64, kept: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        rotate(dir, kept);
+        Ok(RollingFile {
+            dir: dir.to_path_buf(),
+            file: File::create(log_path(dir, 0))?,
+            written: 0,
+            max_bytes,
+            kept,
+        })
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.file.flush()?;
+            rotate(&self.dir, self.kept);
+            self.file = File::create(log_path(&self.dir, 0))?;
+            self.written = 0;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn log_path(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(format!("{}.log", LOG_FILE)),
+        index => dir.join(format!("{}.{}.log", LOG_FILE, index)),
+    }
+}
+
+fn rotate(dir: &Path, kept: usize) {
+    let _ = fs::remove_file(log_path(dir, kept));
+    for index in (0..kept).rev() {
+        let _ = fs::rename(log_path(dir, index), log_path(dir, index + 1));
+    }
+}
+
+struct FileSink {
+    path: PathBuf,
+    logger: env_logger::Logger,
+}
+
+// env_logger does the formatting, this decides what gets through and where it
+// goes, so levels can change while running
+struct Logger {
+    filter: RwLock<Filter>,
+    stderr: env_logger::Logger,
+    file: Mutex<Option<FileSink>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let filter = self.filter.read().unwrap();
+        metadata.level() <= filter.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.stderr.log(record);
+        if let Some(sink) = self.file.lock().unwrap().as_ref() {
+            sink.logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Some(sink) = self.file.lock().unwrap().as_ref() {
+            sink.logger.flush();
+        }
+    }
+}
+
+fn formatter() -> env_logger::Builder {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(LevelFilter::Trace);
+    builder
+}
+
+// with the default levels and no file, so the settings can log while loading
+pub fn init() {
+    let logger = LOGGER.get_or_init(|| Logger {
+        filter: RwLock::new(Filter {
+            default: LevelFilter::Error,
+            modules: BTreeMap::new(),
+        }),
+        stderr: formatter().build(),
+        file: Mutex::new(None),
+    });
+    if log::set_logger(logger).is_err() {
+        warn!("a logger was already installed");
+        return;
+    }
+    apply(&LogConfig::default());
+}
+
+// the levels and file sink from the settings, can be called again
+pub fn configure(config: &LogConfig) {
+    apply(config);
+
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let sink = match config.file {
+        true => open_file(config),
+        false => None,
+    };
+    if let Some(sink) = &sink {
+        info!("logging to {}", sink.path.display());
+    }
+    *logger.file.lock().unwrap() = sink;
+}
+
+fn apply(config: &LogConfig) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let mut filter = Filter {
+        default: config.level.into(),
+        modules: config
+            .modules
+            .iter()
+            .map(|(module, level)| (module.clone(), (*level).into()))
+            .collect(),
+    };
+    if let Ok(directives) = std::env::var("RUST_LOG") {
+        parse_directives(&directives, &mut filter);
+    }
+    log::set_max_level(filter.max());
+    *logger.filter.write().unwrap() = filter;
+}
+
+// "info,nivalis::audio=trace", a bare level sets the default
+fn parse_directives(directives: &str, filter: &mut Filter) {
+    for directive in directives.split(',').map(str::trim) {
+        if directive.is_empty() {
+            continue;
+        }
+        let (module, level) = match directive.split_once('=') {
+            Some((module, level)) => (Some(module.trim()), level.trim()),
+            None => (None, directive),
+        };
+        let level = match level.parse::<LevelFilter>() {
+            Ok(level) => level,
+            Err(_) if module.is_none() => {
+                // a bare module turns everything in it on, like env_logger
+                filter
+                    .modules
+                    .insert(directive.to_string(), LevelFilter::Trace);
+                continue;
+            }
+            Err(_) => {
+                eprintln!("ignoring log directive {:?}, unknown level", directive);
+                continue;
+            }
+        };
+        match module {
+            Some(module) => {
+                filter.modules.insert(module.to_string(), level);
+            }
+            None => filter.default = level,
+        }
+    }
+}
+
+fn open_file(config: &LogConfig) -> Option<FileSink> {
+    let Some(dirs) = Settings::project_dirs() else {
+        warn!("no data directory on this platform, not logging to a file");
+        return None;
+    };
+    let dir = dirs.data_dir().join("logs");
+    let max_bytes = config.max_file_mb.max(1) * 1024 * 1024;
+    match RollingFile::open(&dir, max_bytes, config.kept_files) {
+        Ok(file) => Some(FileSink {
+            path: log_path(&dir, 0),
+            logger: formatter()
+                .write_style(env_logger::WriteStyle::Never)
+                .target(env_logger::Target::Pipe(Box::new(file)))
+                .build(),
+        }),
+        Err(e) => {
+            warn!("failed to open a log file in {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+// changes a module's level while running, None for the default
+pub fn set_level(module: Option<&str>, level: LevelFilter) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let mut filter = logger.filter.write().unwrap();
+    match module {
+        Some(module) => {
+            filter.modules.insert(module.to_string(), level);
+        }
+        None => filter.default = level,
+    }
+    log::set_max_level(filter.max());
+}
+
+// back to the default level for it
+pub fn clear_level(module: &str) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let mut filter = logger.filter.write().unwrap();
+    filter.modules.remove(module);
+    log::set_max_level(filter.max());
+}
+
+// the default and each module's level
+pub fn levels() -> (LevelFilter, Vec<(String, LevelFilter)>) {
+    let Some(logger) = LOGGER.get() else {
+        return (LevelFilter::Off, Vec::new());
+    };
+    let filter = logger.filter.read().unwrap();
+    let modules = filter
+        .modules
+        .iter()
+        .map(|(module, level)| (module.clone(), *level))
+        .collect();
+    (filter.default, modules)
+}
+
+pub fn log_file() -> Option<PathBuf> {
+    let logger = LOGGER.get()?;
+    let sink = logger.file.lock().unwrap();
+    sink.as_ref().map(|sink| sink.path.clone())
+}
@@ -1,36 +1,110 @@
 use std::sync::Arc;
 
-use log::warn;
+use log::{error, warn};
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{DeviceEvent, StartCause, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     window::{Window, WindowAttributes, WindowId},
 };
 
-use crate::engine::Engine;
+use crate::{
+    engine::Engine,
+    net::clock::Clock,
+    platform::window::WindowOptions,
+    renderer::particles::ParticleEmitter,
+    renderer::recorder::{RecordingFormat, RecordingOptions},
+    settings::Settings,
+};
 
+mod animation;
 mod assets;
+mod audio;
+mod console;
+mod crash;
+mod cvars;
 mod engine;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod input;
+mod integrations;
+mod logging;
+mod net;
+mod platform;
 mod renderer;
+mod settings;
+mod state;
+mod tasks;
+#[cfg(any(feature = "golden-tests", feature = "bench"))]
+mod testing;
+mod ui;
 mod util;
+mod world;
 
 #[derive(Default)]
 struct App<'a> {
     window: Option<Arc<Window>>,
     engine: Option<Engine<'a>>,
     attributes: WindowAttributes,
+    window_options: WindowOptions,
+    settings: Settings,
+    #[cfg(feature = "hot-reload")]
+    game_library: Option<std::path::PathBuf>, // from --game <path>
+    measure_latency: bool,              // from --measure-latency
+    particle_demo: bool,                // from --particle-demo
+    record: Option<std::path::PathBuf>, // from --record <directory>
+    record_format: RecordingFormat,     // from --record-format <png|qoi|y4m>
+    net_tick_rate: Option<u32>,         // from --net-tick-rate <hz>
 }
 
 impl<'a> ApplicationHandler for App<'a> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let mut attributes = WindowAttributes::default();
-        attributes.title = "nivalis".to_string();
-
-        let window = Arc::new(event_loop.create_window(attributes).unwrap());
+        let window = Arc::new(event_loop.create_window(self.attributes.clone()).unwrap());
 
         self.window = Some(window.clone());
-        self.engine = Some(Engine::new(window));
+        self.engine = Some(Engine::new(
+            window,
+            self.window_options.clone(),
+            self.settings.clone(),
+        ));
+
+        if let Some(engine) = &mut self.engine
+            && self.measure_latency
+        {
+            engine.renderer().set_latency_measurement(true);
+        }
+        if let (Some(engine), Some(directory)) = (&mut self.engine, &self.record) {
+            engine.renderer().start_recording(RecordingOptions {
+                directory: directory.clone(),
+                format: self.record_format,
+                ..Default::default()
+            });
+        }
+
+        // the game's transport syncs the clock through ctx.net_clock
+        if let (Some(engine), Some(rate)) = (&mut self.engine, self.net_tick_rate) {
+            engine.set_net_clock(Some(Clock::new(rate)));
+        }
+
+        // a fountain of particles for eyeballing the particle system's throughput
+        if let Some(engine) = &mut self.engine
+            && self.particle_demo
+        {
+            engine.renderer().create_particle_system(
+                200_000,
+                ParticleEmitter {
+                    position: [0.0, -0.6],
+                    color: [1.0, 0.6, 0.2, 1.0],
+                    rate: 50_000.0,
+                    ..Default::default()
+                },
+            );
+        }
+
+        #[cfg(feature = "hot-reload")]
+        if let (Some(engine), Some(path)) = (&mut self.engine, &self.game_library) {
+            engine.push_state(Box::new(hot_reload::HotReloadState::new(path)));
+        }
         self.window.as_ref().unwrap().request_redraw();
     }
 
@@ -42,6 +116,11 @@ impl<'a> ApplicationHandler for App<'a> {
         match event {
             WindowEvent::CloseRequested => {
                 warn!("stopping app");
+                if let Some(engine) = &mut self.engine
+                    && let Err(e) = engine.save_settings()
+                {
+                    error!("failed to save settings: {:#}", e);
+                }
                 event_loop.exit();
             }
             WindowEvent::Resized(size) => {
@@ -52,23 +131,123 @@ impl<'a> ApplicationHandler for App<'a> {
             WindowEvent::RedrawRequested => {
                 if let (Some(engine), Some(window)) = (&mut self.engine, &self.window) {
                     engine.handle_redraw();
+                    // a background frame rate cap waits for the next frame
+                    match engine.next_frame_at() {
+                        Some(at) => event_loop.set_control_flow(ControlFlow::WaitUntil(at)),
+                        None => {
+                            event_loop.set_control_flow(ControlFlow::Poll);
+                            window.request_redraw();
+                        }
+                    }
+                }
+            }
+            WindowEvent::Focused(true) => {
+                if let Some(window) = &self.window {
                     window.request_redraw();
                 }
             }
             _ => {}
         }
     }
+
+    fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: StartCause) {
+        crash::show_unshown_report();
+
+        if let (StartCause::ResumeTimeReached { .. }, Some(window)) = (cause, &self.window) {
+            window.request_redraw();
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _id: winit::event::DeviceId,
+        event: DeviceEvent,
+    ) {
+        if let Some(engine) = &mut self.engine {
+            engine.handle_device_event(&event);
+        }
+    }
 }
 
+const PAK_FILE: &str = "assets.nvpak";
+
 fn main() {
-    env_logger::Builder::new()
-        .filter_module("nivalis", log::LevelFilter::Debug)
-        .init();
+    logging::init();
+
+    crash::install_panic_hook();
+
+    // renders the golden scenes headless instead of opening a window
+    #[cfg(feature = "golden-tests")]
+    if std::env::args().any(|arg| arg == "--golden") {
+        let passed = testing::golden::run();
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    // measures headless renderer throughput against the last run
+    #[cfg(feature = "bench")]
+    if std::env::args().any(|arg| arg == "--bench") {
+        let passed = testing::bench::run();
+        std::process::exit(if passed { 0 } else { 1 });
+    }
 
     // begin nieuwe frame na input
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = App::<'static>::default();
+    // packed assets from nivalis-cli win over the loose asset directory
+    let pak_path = std::path::Path::new(PAK_FILE);
+    if pak_path.exists() {
+        assets::pak::mount(pak_path);
+    }
+
+    // settings are needed before the window exists
+    let settings = Settings::load();
+    logging::configure(&settings.log);
+    tasks::configure(settings.task_pool());
+    if let Some(url) = &settings.remote_assets {
+        assets::remote::mount(url);
+    }
+    let window_options = if std::env::args().any(|arg| arg == "--capture") {
+        let [width, height] = settings.resolution;
+        WindowOptions::capture("nivalis", winit::dpi::PhysicalSize::new(width, height))
+    } else if std::env::args().any(|arg| arg == "--overlay") {
+        // the renderer clears to transparent and outputs premultiplied alpha
+        WindowOptions::overlay("nivalis")
+    } else {
+        WindowOptions::default()
+    };
+    let mut app = App::<'static> {
+        attributes: window_options.apply_to(settings.window_attributes()),
+        window_options,
+        settings,
+        measure_latency: std::env::args().any(|arg| arg == "--measure-latency"),
+        particle_demo: std::env::args().any(|arg| arg == "--particle-demo"),
+        record: std::env::args()
+            .skip_while(|arg| arg != "--record")
+            .nth(1)
+            .map(Into::into),
+        record_format: match std::env::args()
+            .skip_while(|arg| arg != "--record-format")
+            .nth(1)
+            .as_deref()
+        {
+            Some("qoi") => RecordingFormat::QoiSequence,
+            Some("y4m") => RecordingFormat::Y4m,
+            _ => RecordingFormat::PngSequence,
+        },
+        net_tick_rate: std::env::args()
+            .skip_while(|arg| arg != "--net-tick-rate")
+            .nth(1)
+            .and_then(|rate| rate.parse().ok()),
+        ..Default::default()
+    };
+    // game logic from a library that is reloaded when it is rebuilt
+    #[cfg(feature = "hot-reload")]
+    {
+        let mut args = std::env::args().skip_while(|arg| arg != "--game").skip(1);
+        app.game_library = args.next().map(Into::into);
+    }
+
     _ = event_loop.run_app(&mut app);
 }
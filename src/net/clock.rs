@@ -0,0 +1,360 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+const SAMPLES: usize = 16; // the latest pongs the estimate is made from
+const BEST_SAMPLES: usize = 4; // the lowest rtt ones, least skewed by queueing
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const SYNC_PING_INTERVAL: Duration = Duration::from_millis(200); // until BEST_SAMPLES arrived
+const RTT_SMOOTHING: f64 = 0.1;
+const MAX_TICKS_PER_FRAME: u32 = 8; // a long hitch is skipped rather than caught up on
+const SNAP_TICKS: f64 = 8.0; // further off than this jumps instead of dilating
+const DILATION_GAIN: f64 = 0.02; // speed change per tick off
+const MAX_DILATION: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    offset: f64, // server minus local seconds
+    rtt: f64,
+}
+
+// the server's clock as seen from here, and the fixed ticks to simulate by it.
+// pings carry a local timestamp the server echoes along with its own time,
+// offset and rtt are estimated from those ntp style. the local tick runs ahead
+// of the server by half the rtt and a few ticks of lead, so input for a tick
+// reaches the server before it simulates it. small drift is caught up on by
+// running ticks slightly faster or slower, large jumps snap
+pub struct Clock {
+    epoch: Instant,
+    tick_length: Duration,
+    lead: u32, // ticks
+    samples: VecDeque<Sample>,
+    offset: Option<f64>,
+    rtt: Option<f64>, // smoothed
+    jitter: f64,      // smoothed distance from the rtt
+    last_ping: Option<Instant>,
+    tick: u64,        // the latest tick started, 0 before the first
+    pending: u32,     // ticks due this frame, not started yet
+    accumulator: f64, // seconds toward the next tick
+    dilation: f64,
+}
+
+impl Clock {
+    pub fn new(tick_rate: u32) -> Self {
+        Clock {
+            epoch: Instant::now(),
+            tick_length: Duration::from_secs_f64(1.0 / tick_rate.max(1) as f64),
+            lead: 2,
+            samples: VecDeque::with_capacity(SAMPLES),
+            offset: None,
+            rtt: None,
+            jitter: 0.0,
+            last_ping: None,
+            tick: 0,
+            pending: 0,
+            accumulator: 0.0,
+            dilation: 1.0,
+        }
+    }
+
+    pub fn tick_length(&self) -> Duration {
+        self.tick_length
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn set_lead(&mut self, ticks: u32) {
+        self.lead = ticks;
+    }
+
+    // whether a pong arrived since creation or the last reset
+    pub fn is_synced(&self) -> bool {
+        self.offset.is_some()
+    }
+
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt.map(Duration::from_secs_f64)
+    }
+
+    pub fn jitter(&self) -> Duration {
+        Duration::from_secs_f64(self.jitter)
+    }
+
+    // seconds the server's clock is ahead of ours, negative when behind
+    pub fn offset(&self) -> Option<f64> {
+        self.offset
+    }
+
+    // how fast ticks run against real time, 1 when in step with the server
+    pub fn dilation(&self) -> f64 {
+        self.dilation
+    }
+
+    // seconds since the clock was made, what pings are stamped with
+    pub fn local_time(&self) -> f64 {
+        self.epoch.elapsed().as_secs_f64()
+    }
+
+    pub fn server_time(&self) -> Option<f64> {
+        Some(self.local_time() + self.offset?)
+    }
+
+    // the tick the server is simulating right now
+    pub fn server_tick(&self) -> Option<u64> {
+        Some(self.exact_server_tick()? as u64)
+    }
+
+    // with how far into it the server is, what replicated entities are sampled at
+    pub fn exact_server_tick(&self) -> Option<f64> {
+        Some(self.server_time()? / self.tick_length.as_secs_f64())
+    }
+
+    // a timestamp to send when a ping is due, more often until synced
+    #[allow(dead_code)] // the game's transport sends it and hands back the pong
+    pub fn ping(&mut self) -> Option<f64> {
+        let interval = match self.samples.len() < BEST_SAMPLES {
+            true => SYNC_PING_INTERVAL,
+            false => PING_INTERVAL,
+        };
+        if self.last_ping.is_some_and(|at| at.elapsed() < interval) {
+            return None;
+        }
+        self.last_ping = Some(Instant::now());
+        Some(self.local_time())
+    }
+
+    // the server's answer to a ping: the timestamp it carried and the server's
+    // time in seconds when it answered
+    #[allow(dead_code)] // see ping
+    pub fn record_pong(&mut self, sent: f64, server_time: f64) {
+        let received = self.local_time();
+        let rtt = received - sent;
+        if !(0.0..60.0).contains(&rtt) {
+            warn!("ignoring a pong with a round trip of {:.3}s", rtt);
+            return;
+        }
+
+        // the server answered halfway through the round trip
+        let offset = server_time + rtt * 0.5 - received;
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample { offset, rtt });
+
+        let mut best: Vec<Sample> = self.samples.iter().copied().collect();
+        best.sort_by(|a, b| a.rtt.total_cmp(&b.rtt));
+        best.truncate(BEST_SAMPLES);
+        let offset = best.iter().map(|s| s.offset).sum::<f64>() / best.len() as f64;
+
+        match self.rtt {
+            Some(smoothed) => {
+                self.jitter += ((rtt - smoothed).abs() - self.jitter) * RTT_SMOOTHING;
+                self.rtt = Some(smoothed + (rtt - smoothed) * RTT_SMOOTHING);
+            }
+            None => {
+                info!(
+                    "net clock synced, offset {:.3}s, rtt {:.0}ms",
+                    offset,
+                    rtt * 1000.0
+                );
+                self.rtt = Some(rtt);
+            }
+        }
+        self.offset = Some(offset);
+    }
+
+    // forgets the server, e.g. after reconnecting to another one
+    pub fn reset_sync(&mut self) {
+        self.samples.clear();
+        self.offset = None;
+        self.rtt = None;
+        self.jitter = 0.0;
+        self.last_ping = None;
+        self.dilation = 1.0;
+    }
+
+    // where the local tick should be: ahead of the server by the time input
+    // takes to get there, plus the lead
+    pub fn target_tick(&self) -> Option<f64> {
+        let ahead = self.rtt? * 0.5 + self.jitter;
+        let ahead = ahead / self.tick_length.as_secs_f64();
+        Some(self.exact_server_tick()? + ahead + self.lead as f64)
+    }
+
+    // queues the ticks a frame of dt fits, see next_tick
+    pub fn advance(&mut self, dt: Duration) {
+        let length = self.tick_length.as_secs_f64();
+        if let Some(target) = self.target_tick() {
+            let at = (self.tick + self.pending as u64) as f64 + self.accumulator / length;
+            let behind = target - at;
+            if behind.abs() > SNAP_TICKS {
+                info!(
+                    "net clock {:.1} ticks off, jumping to tick {}",
+                    behind, target as u64
+                );
+                self.tick = target as u64;
+                self.pending = 0;
+                self.accumulator = target.fract() * length;
+                self.dilation = 1.0;
+            } else {
+                self.dilation = 1.0 + (behind * DILATION_GAIN).clamp(-MAX_DILATION, MAX_DILATION);
+            }
+        }
+
+        self.accumulator += dt.as_secs_f64() * self.dilation;
+        let ticks = (self.accumulator / length).floor();
+        self.accumulator -= ticks * length;
+
+        let ticks = self.pending + ticks as u32;
+        if ticks > MAX_TICKS_PER_FRAME {
+            warn!("net clock skipping {} ticks", ticks - MAX_TICKS_PER_FRAME);
+            self.tick += (ticks - MAX_TICKS_PER_FRAME) as u64;
+        }
+        self.pending = ticks.min(MAX_TICKS_PER_FRAME);
+    }
+
+    // one tick regardless of time, for stepping while paused
+    pub fn step(&mut self) {
+        self.pending += 1;
+    }
+
+    // starts the next queued tick and returns its number, None once the
+    // frame's ticks ran
+    pub fn next_tick(&mut self) -> Option<u64> {
+        if self.pending == 0 {
+            return None;
+        }
+        self.pending -= 1;
+        self.tick += 1;
+        Some(self.tick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a pong as if the ping left rtt seconds ago and the server's clock is
+    // offset seconds ahead
+    fn pong(clock: &mut Clock, rtt: f64, offset: f64) {
+        let now = clock.local_time();
+        clock.record_pong(now - rtt, now - rtt * 0.5 + offset);
+    }
+
+    fn near(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    fn ticks(clock: &mut Clock) -> Vec<u64> {
+        std::iter::from_fn(|| clock.next_tick()).collect()
+    }
+
+    #[test]
+    fn offset_comes_from_the_fastest_round_trips() {
+        let mut clock = Clock::new(60);
+        assert!(!clock.is_synced());
+        for _ in 0..BEST_SAMPLES {
+            pong(&mut clock, 0.05, 2.0);
+        }
+        assert!(clock.is_synced());
+        assert!(near(clock.offset().unwrap(), 2.0));
+        assert!(near(clock.rtt().unwrap().as_secs_f64(), 0.05));
+        assert!(near(clock.jitter().as_secs_f64(), 0.0));
+
+        // a slow pong queued somewhere on the way is skewed, it doesn't move the offset
+        pong(&mut clock, 0.5, 2.3);
+        assert!(near(clock.offset().unwrap(), 2.0));
+        assert!(near(clock.rtt().unwrap().as_secs_f64(), 0.095));
+        assert!(near(clock.jitter().as_secs_f64(), 0.045));
+        assert!(near(clock.server_time().unwrap() - clock.local_time(), 2.0));
+    }
+
+    #[test]
+    fn offset_follows_the_newest_samples() {
+        let mut clock = Clock::new(60);
+        for _ in 0..SAMPLES {
+            pong(&mut clock, 0.05, 1.0);
+        }
+        for _ in 0..SAMPLES {
+            pong(&mut clock, 0.06, -1.0);
+        }
+        assert!(near(clock.offset().unwrap(), -1.0));
+    }
+
+    #[test]
+    fn impossible_round_trips_are_ignored() {
+        let mut clock = Clock::new(60);
+        let now = clock.local_time();
+        clock.record_pong(now + 1.0, 5.0);
+        clock.record_pong(now - 120.0, 5.0);
+        assert!(!clock.is_synced());
+
+        pong(&mut clock, 0.05, 1.0);
+        clock.reset_sync();
+        assert!(!clock.is_synced());
+        assert_eq!(clock.rtt(), None);
+    }
+
+    #[test]
+    fn far_off_clock_snaps_to_the_target() {
+        let mut clock = Clock::new(60);
+        pong(&mut clock, 0.05, 10.0);
+        let target = clock.target_tick().unwrap();
+        // 600 server ticks, 1.5 for half the rtt and the lead of 2
+        assert!((target - 603.5).abs() < 0.1);
+
+        clock.advance(Duration::ZERO);
+        assert_eq!(clock.tick(), target as u64);
+        assert_eq!(clock.dilation(), 1.0);
+        assert!(ticks(&mut clock).is_empty());
+    }
+
+    #[test]
+    fn small_drift_dilates_ticks() {
+        let mut clock = Clock::new(60);
+        // 3.5 ticks behind the target
+        pong(&mut clock, 0.05, 0.0);
+        clock.advance(Duration::ZERO);
+        assert_eq!(clock.tick(), 0);
+        assert!((clock.dilation() - 1.07).abs() < 0.01);
+
+        // ahead of it by 4.5 ticks, running slower
+        clock.reset_sync();
+        clock.set_lead(0);
+        pong(&mut clock, 0.05, -0.1);
+        clock.advance(Duration::ZERO);
+        assert!((clock.dilation() - 0.91).abs() < 0.01);
+
+        // 7.5 behind is still dilated, but no faster than the cap
+        clock.reset_sync();
+        clock.set_lead(6);
+        pong(&mut clock, 0.05, 0.0);
+        clock.advance(Duration::ZERO);
+        assert_eq!(clock.tick(), 0);
+        assert_eq!(clock.dilation(), 1.0 + MAX_DILATION);
+    }
+
+    #[test]
+    fn long_frames_skip_ticks() {
+        let mut clock = Clock::new(60);
+        clock.advance(Duration::from_millis(40));
+        assert_eq!(ticks(&mut clock), [1, 2]);
+
+        // a second is 60 ticks, only the last 8 run
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(ticks(&mut clock), (55..=62).collect::<Vec<_>>());
+
+        clock.step();
+        assert_eq!(ticks(&mut clock), [63]);
+    }
+
+    #[test]
+    fn pings_are_spaced_out() {
+        let mut clock = Clock::new(60);
+        assert!(clock.ping().is_some());
+        assert!(clock.ping().is_none());
+    }
+}
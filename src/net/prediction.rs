@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+use log::{debug, warn};
+
+const MAX_INPUTS: usize = 256; // about four seconds at 60 ticks without an answer
+
+// the local player's state simulated ahead of the server from its own input.
+// inputs are kept until the server has simulated their tick, when an
+// authoritative state arrives the prediction rolls back to it and replays the
+// inputs the server hasn't seen yet. simulate must be the same step the
+// server runs, or every reconcile mispredicts.
+// for games, the engine has no player of its own to predict
+#[allow(dead_code)]
+pub struct Prediction<S, I> {
+    state: S,
+    tick: u64,                     // of the state
+    inputs: VecDeque<(u64, I, S)>, // input of a tick and the state it predicted
+    acknowledged: Option<u64>,     // the newest tick the server sent a state for
+    mispredictions: u64,
+}
+
+#[allow(dead_code)]
+impl<S: Clone + PartialEq, I: Clone> Prediction<S, I> {
+    pub fn new(tick: u64, state: S) -> Self {
+        Prediction {
+            state,
+            tick,
+            inputs: VecDeque::new(),
+            acknowledged: None,
+            mispredictions: 0,
+        }
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn mispredictions(&self) -> u64 {
+        self.mispredictions
+    }
+
+    // inputs the server hasn't acknowledged, to send again with the next one
+    pub fn unacknowledged(&self) -> impl Iterator<Item = (u64, &I)> {
+        self.inputs.iter().map(|(tick, input, _)| (*tick, input))
+    }
+
+    // runs the tick the clock started with this tick's input
+    pub fn step(&mut self, tick: u64, input: I, mut simulate: impl FnMut(&mut S, &I)) {
+        if tick <= self.tick {
+            warn!("predicting tick {} again after {}", tick, self.tick);
+            return;
+        }
+        simulate(&mut self.state, &input);
+        self.tick = tick;
+        self.inputs.push_back((tick, input, self.state.clone()));
+        if self.inputs.len() > MAX_INPUTS {
+            self.inputs.pop_front();
+        }
+    }
+
+    // the server's state after simulating tick. returns whether the prediction
+    // for it was off, in which case the inputs after it were replayed. states
+    // older than one already reconciled arrived out of order and are ignored
+    pub fn reconcile(&mut self, tick: u64, state: S, mut simulate: impl FnMut(&mut S, &I)) -> bool {
+        if self
+            .acknowledged
+            .is_some_and(|acknowledged| tick <= acknowledged)
+        {
+            return false;
+        }
+        self.acknowledged = Some(tick);
+
+        let predicted = self
+            .inputs
+            .iter()
+            .find(|(t, _, _)| *t == tick)
+            .map(|(_, _, predicted)| predicted);
+        let mispredicted = predicted.is_none_or(|predicted| *predicted != state);
+        while self.inputs.front().is_some_and(|(t, _, _)| *t <= tick) {
+            self.inputs.pop_front();
+        }
+        if !mispredicted {
+            return false;
+        }
+
+        self.mispredictions += 1;
+        debug!(
+            "mispredicted tick {}, replaying {} inputs",
+            tick,
+            self.inputs.len()
+        );
+        self.state = state;
+        for (_, input, predicted) in self.inputs.iter_mut() {
+            simulate(&mut self.state, input);
+            *predicted = self.state.clone();
+        }
+        self.tick = self.tick.max(tick);
+        true
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::{HashMap, VecDeque};
+
+use log::debug;
+
+use crate::renderer::Renderer;
+use crate::util::tween::Lerp;
+
+const SNAPSHOTS: usize = 32; // per entity, about half a second at 60 ticks
+const MAX_EXTRAPOLATION: f64 = 4.0; // ticks past the newest snapshot, then it holds
+
+// what the server said an entity looked like, one state per tick, oldest first.
+// sampled between two snapshots it interpolates, past the newest it keeps
+// going the way the last two were heading for a few ticks
+pub struct SnapshotBuffer<T> {
+    snapshots: VecDeque<(u64, T)>,
+}
+
+impl<T> Default for SnapshotBuffer<T> {
+    fn default() -> Self {
+        SnapshotBuffer {
+            snapshots: VecDeque::with_capacity(SNAPSHOTS),
+        }
+    }
+}
+
+#[allow(dead_code)] // games read them through ReplicatedWorld::snapshots
+impl<T: Lerp> SnapshotBuffer<T> {
+    // snapshots may arrive out of order, one older than everything kept is dropped
+    pub fn push(&mut self, tick: u64, state: T) {
+        let index = self.snapshots.partition_point(|(t, _)| *t < tick);
+        let full = self.snapshots.len() == SNAPSHOTS;
+        match self.snapshots.get_mut(index) {
+            Some((t, kept)) if *t == tick => *kept = state,
+            _ if index == 0 && full => {}
+            _ => {
+                self.snapshots.insert(index, (tick, state));
+                if self.snapshots.len() > SNAPSHOTS {
+                    self.snapshots.pop_front();
+                }
+            }
+        }
+    }
+
+    pub fn newest(&self) -> Option<(u64, T)> {
+        self.snapshots.back().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    // the state at a fractional tick, None until the first snapshot
+    pub fn sample(&self, tick: f64) -> Option<T> {
+        let (first_tick, first) = *self.snapshots.front()?;
+        if tick <= first_tick as f64 {
+            return Some(first);
+        }
+
+        let index = self.snapshots.partition_point(|(t, _)| (*t as f64) <= tick);
+        let (from_tick, from) = self.snapshots[index - 1];
+        let (to_tick, to) = match self.snapshots.get(index) {
+            Some(next) => *next,
+            None => return Some(self.extrapolate(tick)),
+        };
+        let t = (tick - from_tick as f64) / (to_tick - from_tick) as f64;
+        Some(from.lerp(&to, t as f32))
+    }
+
+    fn extrapolate(&self, tick: f64) -> T {
+        let len = self.snapshots.len();
+        let (to_tick, to) = self.snapshots[len - 1];
+        if len < 2 {
+            return to;
+        }
+        let (from_tick, from) = self.snapshots[len - 2];
+        let ahead = (tick - to_tick as f64).min(MAX_EXTRAPOLATION);
+        let t = 1.0 + ahead / (to_tick - from_tick) as f64;
+        from.lerp(&to, t as f32)
+    }
+}
+
+// turns replicated states into sprites, lights or whatever the game shows them
+// with. spawn runs on an entity's first snapshot, apply every update with the
+// interpolated state
+pub trait ReplicationHooks<T> {
+    fn spawn(&mut self, entity: u64, state: &T, renderer: &mut Renderer);
+
+    fn apply(&mut self, entity: u64, state: &T, renderer: &mut Renderer);
+
+    fn despawn(&mut self, _entity: u64, _renderer: &mut Renderer) {}
+}
+
+// every entity the server replicates to us, shown a little in the past so
+// there is almost always a snapshot on either side. the local player is
+// predicted instead, see Prediction.
+// for games, the engine replicates nothing of its own
+#[allow(dead_code)]
+pub struct ReplicatedWorld<T> {
+    pub delay: f64, // ticks behind the server's estimated tick
+    entities: HashMap<u64, SnapshotBuffer<T>>,
+    spawned: Vec<u64>, // waiting for their first apply
+    despawned: Vec<u64>,
+    hooks: Box<dyn ReplicationHooks<T>>,
+}
+
+#[allow(dead_code)]
+impl<T: Lerp> ReplicatedWorld<T> {
+    pub fn new(hooks: Box<dyn ReplicationHooks<T>>) -> Self {
+        ReplicatedWorld {
+            delay: 2.0,
+            entities: HashMap::new(),
+            spawned: Vec::new(),
+            despawned: Vec::new(),
+            hooks,
+        }
+    }
+
+    // a state for an entity at a server tick
+    pub fn receive(&mut self, entity: u64, tick: u64, state: T) {
+        let buffer = self.entities.entry(entity).or_insert_with(|| {
+            self.spawned.push(entity);
+            SnapshotBuffer::default()
+        });
+        buffer.push(tick, state);
+    }
+
+    // the server stopped replicating it, despawned on the next update
+    pub fn remove(&mut self, entity: u64) {
+        if self.entities.remove(&entity).is_some() {
+            self.spawned.retain(|id| *id != entity);
+            self.despawned.push(entity);
+        }
+    }
+
+    pub fn contains(&self, entity: u64) -> bool {
+        self.entities.contains_key(&entity)
+    }
+
+    pub fn snapshots(&self, entity: u64) -> Option<&SnapshotBuffer<T>> {
+        self.entities.get(&entity)
+    }
+
+    // where entities are shown, server_tick being Clock::exact_server_tick
+    pub fn render_tick(&self, server_tick: f64) -> f64 {
+        server_tick - self.delay
+    }
+
+    pub fn update(&mut self, renderer: &mut Renderer, server_tick: f64) {
+        for entity in self.despawned.drain(..) {
+            debug!("despawning replicated entity {}", entity);
+            self.hooks.despawn(entity, renderer);
+        }
+
+        let tick = server_tick - self.delay;
+        for entity in self.spawned.drain(..) {
+            let Some(state) = self.entities.get(&entity).and_then(|b| b.sample(tick)) else {
+                continue;
+            };
+            debug!("spawning replicated entity {}", entity);
+            self.hooks.spawn(entity, &state, renderer);
+        }
+        for (entity, buffer) in self.entities.iter() {
+            if let Some(state) = buffer.sample(tick) {
+                self.hooks.apply(*entity, &state, renderer);
+            }
+        }
+    }
+}
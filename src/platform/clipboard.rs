@@ -0,0 +1,52 @@
+use std::cell::RefCell;
+
+use log::{error, warn};
+
+thread_local! {
+    // opened on first use, the os clipboard is tied to the main thread
+    static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+}
+
+fn with_clipboard<T>(f: impl FnOnce(&mut arboard::Clipboard) -> Option<T>) -> Option<T> {
+    CLIPBOARD.with_borrow_mut(|clipboard| {
+        if clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(c) => *clipboard = Some(c),
+                Err(e) => {
+                    warn!("clipboard unavailable: {}", e);
+                    return None;
+                }
+            }
+        }
+
+        f(clipboard.as_mut()?)
+    })
+}
+
+pub fn get() -> Option<String> {
+    with_clipboard(|clipboard| clipboard.get_text().ok())
+}
+
+pub fn set(text: &str) -> bool {
+    with_clipboard(|clipboard| match clipboard.set_text(text) {
+        Ok(()) => Some(()),
+        Err(e) => {
+            error!("failed to set clipboard: {}", e);
+            None
+        }
+    })
+    .is_some()
+}
+
+// lets imgui text fields copy and paste through the os clipboard
+pub(crate) struct ImguiClipboard;
+
+impl imgui::ClipboardBackend for ImguiClipboard {
+    fn get(&mut self) -> Option<String> {
+        get()
+    }
+
+    fn set(&mut self, value: &str) {
+        set(value);
+    }
+}
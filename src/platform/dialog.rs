@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+#[cfg(not(feature = "native-dialogs"))]
+use log::warn;
+
+// what a dialog lists, e.g. the images filter shows png, jpeg and webp files
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileFilter {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+}
+
+pub const SCENE_FILES: FileFilter = FileFilter {
+    name: "scenes",
+    extensions: &["toml"],
+};
+
+pub const IMAGE_FILES: FileFilter = FileFilter {
+    name: "images",
+    extensions: &[
+        "png", "jpg", "jpeg", "webp", "bmp", "tga", "qoi", "hdr", "exr",
+    ],
+};
+
+pub const SOUND_FILES: FileFilter = FileFilter {
+    name: "sounds",
+    extensions: &["wav"],
+};
+
+impl FileFilter {
+    pub fn matches(&self, path: &std::path::Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                self.extensions
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(extension))
+            })
+    }
+}
+
+// false without the native-dialogs feature, the editor hides its browse buttons then
+pub fn is_available() -> bool {
+    cfg!(feature = "native-dialogs")
+}
+
+// the dialogs block until closed, so they belong between frames and not in the
+// middle of building one. None when cancelled
+#[cfg(feature = "native-dialogs")]
+pub fn pick_file(filters: &[FileFilter]) -> Option<PathBuf> {
+    dialog(filters).pick_file()
+}
+
+#[cfg(feature = "native-dialogs")]
+pub fn pick_files(filters: &[FileFilter]) -> Vec<PathBuf> {
+    dialog(filters).pick_files().unwrap_or_default()
+}
+
+#[cfg(feature = "native-dialogs")]
+pub fn save_file(filters: &[FileFilter], file_name: &str) -> Option<PathBuf> {
+    dialog(filters).set_file_name(file_name).save_file()
+}
+
+#[cfg(feature = "native-dialogs")]
+fn dialog(filters: &[FileFilter]) -> rfd::FileDialog {
+    let mut dialog = rfd::FileDialog::new();
+    if let Ok(dir) = std::env::current_dir() {
+        dialog = dialog.set_directory(dir);
+    }
+    for filter in filters {
+        dialog = dialog.add_filter(filter.name, filter.extensions);
+    }
+    dialog
+}
+
+#[cfg(not(feature = "native-dialogs"))]
+pub fn pick_file(_filters: &[FileFilter]) -> Option<PathBuf> {
+    warn!("built without native-dialogs, no file dialog");
+    None
+}
+
+#[cfg(not(feature = "native-dialogs"))]
+pub fn pick_files(_filters: &[FileFilter]) -> Vec<PathBuf> {
+    warn!("built without native-dialogs, no file dialog");
+    Vec::new()
+}
+
+#[cfg(not(feature = "native-dialogs"))]
+pub fn save_file(_filters: &[FileFilter], _file_name: &str) -> Option<PathBuf> {
+    warn!("built without native-dialogs, no file dialog");
+    None
+}
@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+// what the engine does while the window is in the background. the parts
+// combine, all of them off keeps the game running as if it were focused
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FocusLossPolicy {
+    pub pause: bool, // the simulation, resumed with focus unless it was paused before
+    pub mute: bool,  // the master bus
+    pub background_fps: Option<u32>, // caps the frame rate, None draws as fast as focused
+}
+
+impl FocusLossPolicy {
+    pub fn is_active(&self) -> bool {
+        self.pause || self.mute || self.background_fps.is_some()
+    }
+}
+
+// what of the policy is applied right now, so focus coming back only undoes that
+pub struct FocusState {
+    focused: bool,
+    pub(crate) paused: bool, // the simulation, by the policy rather than the game
+    pub(crate) muted: bool,
+    frame_interval: Option<Duration>,
+    last_frame: Instant,
+}
+
+impl Default for FocusState {
+    fn default() -> Self {
+        FocusState {
+            focused: true,
+            paused: false,
+            muted: false,
+            frame_interval: None,
+            last_frame: Instant::now(),
+        }
+    }
+}
+
+impl FocusState {
+    // false when focus didn't change, e.g. a second event for the same window
+    pub(crate) fn set_focused(&mut self, focused: bool, policy: FocusLossPolicy) -> bool {
+        if self.focused == focused {
+            return false;
+        }
+        self.focused = focused;
+        self.frame_interval = match focused {
+            true => None,
+            false => policy
+                .background_fps
+                .filter(|fps| *fps > 0)
+                .map(|fps| Duration::from_secs_f64(1.0 / fps as f64)),
+        };
+        true
+    }
+
+    pub(crate) fn begin_frame(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    // when the next frame may start under the background cap, None for right away
+    pub fn next_frame_at(&self) -> Option<Instant> {
+        Some(self.last_frame + self.frame_interval?)
+    }
+}
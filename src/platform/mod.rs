@@ -0,0 +1,4 @@
+pub mod clipboard;
+pub mod dialog;
+pub mod focus;
+pub mod window;
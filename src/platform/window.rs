@@ -0,0 +1,285 @@
+use std::sync::Arc;
+
+use log::{info, warn};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+use winit::window::{CursorGrabMode, Fullscreen, Window, WindowAttributes, WindowLevel};
+
+#[derive(Clone)]
+pub struct WindowOptions {
+    pub title: String,
+    pub min_size: Option<PhysicalSize<u32>>,
+    pub max_size: Option<PhysicalSize<u32>>,
+    pub resizable: bool,
+    pub always_on_top: bool,
+    pub transparent: bool,
+    pub decorations: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        WindowOptions {
+            title: "nivalis".to_string(),
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            always_on_top: false,
+            transparent: false,
+            decorations: true,
+        }
+    }
+}
+
+impl WindowOptions {
+    // borderless and see-through, for overlays
+    pub fn overlay(title: &str) -> Self {
+        WindowOptions {
+            title: title.to_string(),
+            transparent: true,
+            decorations: false,
+            ..Default::default()
+        }
+    }
+
+    // borderless and locked to one size, so screen recorders and streaming
+    // software capture exactly the rendered frame without window chrome
+    pub fn capture(title: &str, size: PhysicalSize<u32>) -> Self {
+        WindowOptions {
+            title: title.to_string(),
+            min_size: Some(size),
+            max_size: Some(size),
+            resizable: false,
+            decorations: false,
+            ..Default::default()
+        }
+    }
+
+    // used once before the window is created
+    pub fn apply_to(&self, mut attributes: WindowAttributes) -> WindowAttributes {
+        attributes = attributes
+            .with_title(self.title.clone())
+            .with_resizable(self.resizable)
+            .with_transparent(self.transparent)
+            .with_decorations(self.decorations)
+            .with_window_level(window_level(self.always_on_top));
+
+        if let Some(size) = self.min_size {
+            attributes = attributes.with_min_inner_size(size);
+        }
+        if let Some(size) = self.max_size {
+            attributes = attributes.with_max_inner_size(size);
+        }
+
+        attributes
+    }
+}
+
+fn window_level(always_on_top: bool) -> WindowLevel {
+    match always_on_top {
+        true => WindowLevel::AlwaysOnTop,
+        false => WindowLevel::Normal,
+    }
+}
+
+// the engine's handle to the os window, keeps track of what was set
+pub struct PlatformWindow {
+    window: Arc<Window>,
+    options: WindowOptions,
+    ime_allowed: bool,
+    relative_mouse: bool,
+    monitors: Vec<MonitorInfo>,
+}
+
+// what we compare to notice a monitor being unplugged or changing its mode
+#[derive(Clone, PartialEq, Debug)]
+struct MonitorInfo {
+    name: Option<String>,
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    refresh_rate: Option<u32>,
+}
+
+impl MonitorInfo {
+    fn of(monitor: &MonitorHandle) -> Self {
+        MonitorInfo {
+            name: monitor.name(),
+            position: monitor.position(),
+            size: monitor.size(),
+            refresh_rate: monitor.refresh_rate_millihertz(),
+        }
+    }
+}
+
+impl PlatformWindow {
+    pub fn new(window: Arc<Window>, options: WindowOptions) -> Self {
+        let monitors = window
+            .available_monitors()
+            .map(|m| MonitorInfo::of(&m))
+            .collect();
+
+        PlatformWindow {
+            window,
+            options,
+            ime_allowed: false,
+            relative_mouse: false,
+            monitors,
+        }
+    }
+
+    pub fn options(&self) -> &WindowOptions {
+        &self.options
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
+        self.options.title = title.to_string();
+    }
+
+    pub fn set_min_size(&mut self, size: Option<PhysicalSize<u32>>) {
+        self.window.set_min_inner_size(size);
+        self.options.min_size = size;
+    }
+
+    pub fn set_max_size(&mut self, size: Option<PhysicalSize<u32>>) {
+        self.window.set_max_inner_size(size);
+        self.options.max_size = size;
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.window.set_resizable(resizable);
+        self.options.resizable = resizable;
+    }
+
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.window.set_window_level(window_level(always_on_top));
+        self.options.always_on_top = always_on_top;
+    }
+
+    // the renderer picks its surface alpha mode at creation, so this only
+    // changes the window hint on platforms that support it
+    pub fn set_transparent(&mut self, transparent: bool) {
+        info!("setting window transparency to {}", transparent);
+        self.window.set_transparent(transparent);
+        self.options.transparent = transparent;
+    }
+
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.window.set_decorations(decorations);
+        self.options.decorations = decorations;
+    }
+
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        if self.ime_allowed != allowed {
+            self.window.set_ime_allowed(allowed);
+            self.ime_allowed = allowed;
+        }
+    }
+
+    pub fn set_ime_cursor_area(&self, position: [f32; 2], size: [f32; 2]) {
+        self.window.set_ime_cursor_area(
+            PhysicalPosition::new(position[0], position[1]),
+            PhysicalSize::new(size[0], size[1]),
+        );
+    }
+
+    // locks and hides the cursor, movement then only arrives as device deltas
+    pub fn set_relative_mouse(&mut self, relative: bool) {
+        if self.relative_mouse == relative {
+            return;
+        }
+
+        info!("setting relative mouse to {}", relative);
+        self.relative_mouse = relative;
+        self.apply_cursor_grab();
+    }
+
+    // the os drops the grab when the window loses focus
+    pub fn handle_focus(&mut self, focused: bool) {
+        if focused && self.relative_mouse {
+            self.apply_cursor_grab();
+        }
+    }
+
+    fn apply_cursor_grab(&self) {
+        if !self.relative_mouse {
+            if let Err(e) = self.window.set_cursor_grab(CursorGrabMode::None) {
+                warn!("failed to release cursor: {}", e);
+            }
+            self.window.set_cursor_visible(true);
+            return;
+        }
+
+        // not every platform can lock, confining is close enough there
+        let grabbed = self
+            .window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Confined));
+        if let Err(e) = grabbed {
+            warn!("failed to grab cursor: {}", e);
+        }
+        self.window.set_cursor_visible(false);
+    }
+
+    // winit has no event for monitor changes, so the engine polls this. returns
+    // whether the setup changed since the last call
+    pub fn check_monitors(&mut self) -> bool {
+        let monitors: Vec<MonitorInfo> = self
+            .window
+            .available_monitors()
+            .map(|m| MonitorInfo::of(&m))
+            .collect();
+        if monitors == self.monitors {
+            return false;
+        }
+
+        info!(
+            "monitors changed: {:?}",
+            monitors.iter().map(|m| &m.name).collect::<Vec<_>>()
+        );
+        self.monitors = monitors;
+        self.ensure_on_monitor();
+        true
+    }
+
+    // moves the window to an available display when the one it was on went away
+    pub fn ensure_on_monitor(&self) {
+        let available: Vec<MonitorHandle> = self.window.available_monitors().collect();
+        let current = self
+            .window
+            .current_monitor()
+            .filter(|monitor| available.contains(monitor));
+        if current.is_some() {
+            return;
+        }
+
+        let Some(target) = self.window.primary_monitor().or(available.first().cloned()) else {
+            warn!("no monitors available, leaving the window where it is");
+            return;
+        };
+        info!("moving window to monitor {:?}", target.name());
+
+        match self.window.fullscreen() {
+            Some(Fullscreen::Exclusive(mode)) => {
+                // keep the resolution when the new display offers it
+                let same_size = target.video_modes().find(|candidate| {
+                    candidate.size() == mode.size()
+                        && candidate.refresh_rate_millihertz() == mode.refresh_rate_millihertz()
+                });
+                let fullscreen = match same_size.or(target.video_modes().next()) {
+                    Some(mode) => Fullscreen::Exclusive(mode),
+                    None => Fullscreen::Borderless(Some(target)),
+                };
+                self.window.set_fullscreen(Some(fullscreen));
+            }
+            Some(Fullscreen::Borderless(_)) => {
+                self.window
+                    .set_fullscreen(Some(Fullscreen::Borderless(Some(target))));
+            }
+            None => {
+                let position = target.position();
+                self.window
+                    .set_outer_position(PhysicalPosition::new(position.x + 50, position.y + 50));
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::process::{Child, Command, Stdio};
+
+use log::{info, warn};
+
+use crate::renderer::Renderer;
+
+// what screen readers and speech need to know about the ui. recorded once
+// enabled and drained by the game, or spoken by a speech backend
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccessibilityEvent {
+    TextShown { id: usize, text: String },
+    TextChanged { id: usize, text: String },
+    TextHidden { id: usize },
+    FocusChanged { label: String, value: String }, // the newly focused widget
+    FocusLost,
+    Announcement(String), // from the game, e.g. "level complete"
+}
+
+impl AccessibilityEvent {
+    // the text a speech backend reads out, None for events it skips.
+    // plain text changes are left to the game, a fps counter shouldn't talk
+    pub fn spoken(&self) -> Option<String> {
+        match self {
+            AccessibilityEvent::FocusChanged { label, value } if value.is_empty() => {
+                Some(label.clone())
+            }
+            AccessibilityEvent::FocusChanged { label, value } => {
+                Some(format!("{}, {}", label, value))
+            }
+            AccessibilityEvent::Announcement(text) => Some(text.clone()),
+            _ => None,
+        }
+    }
+}
+
+pub trait SpeechBackend {
+    // interrupt cuts off whatever is still being said
+    fn speak(&mut self, text: &str, interrupt: bool);
+}
+
+// speaks through the platform's command line voice: say on macos, spd-say on
+// linux and the speech synthesizer through powershell on windows
+pub struct CommandSpeech {
+    program: &'static str,
+    args: &'static [&'static str],
+    speaking: Option<Child>,
+}
+
+impl CommandSpeech {
+    // None when the platform has no voice we know how to reach
+    pub fn detect() -> Option<Self> {
+        let (program, args): (&'static str, &'static [&'static str]) = if cfg!(target_os = "macos")
+        {
+            ("say", &[])
+        } else if cfg!(target_os = "windows") {
+            (
+                "powershell",
+                &[
+                    "-NoProfile",
+                    "-Command",
+                    "Add-Type -AssemblyName System.Speech; \
+                     (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($args[0])",
+                ],
+            )
+        } else {
+            ("spd-say", &["--wait"])
+        };
+
+        let available = Command::new(program)
+            .arg(if program == "powershell" {
+                "-Help"
+            } else {
+                "--version"
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok();
+        if !available {
+            info!("no speech command found, {} is not installed", program);
+            return None;
+        }
+
+        Some(CommandSpeech {
+            program,
+            args,
+            speaking: None,
+        })
+    }
+}
+
+impl SpeechBackend for CommandSpeech {
+    fn speak(&mut self, text: &str, interrupt: bool) {
+        if let Some(mut child) = self.speaking.take() {
+            if interrupt {
+                _ = child.kill();
+            }
+            _ = child.try_wait(); // reaps it when it already finished
+        }
+
+        match Command::new(self.program)
+            .args(self.args)
+            .arg(text)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => self.speaking = Some(child),
+            Err(e) => warn!("failed to run {}: {}", self.program, e),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Accessibility {
+    enabled: bool, // events are only kept while something listens
+    events: Vec<AccessibilityEvent>,
+    speech: Option<Box<dyn SpeechBackend>>,
+    high_contrast: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn set_accessibility_enabled(&mut self, enabled: bool) {
+        self.accessibility.enabled = enabled;
+        if !enabled {
+            self.accessibility.events.clear();
+        }
+    }
+
+    pub fn is_accessibility_enabled(&self) -> bool {
+        self.accessibility.enabled
+    }
+
+    // focus changes and announcements are spoken through it, None stops speaking
+    pub fn set_speech_backend(&mut self, speech: Option<Box<dyn SpeechBackend>>) {
+        self.accessibility.speech = speech;
+    }
+
+    // everything since the last call, in order
+    pub fn take_accessibility_events(&mut self) -> Vec<AccessibilityEvent> {
+        std::mem::take(&mut self.accessibility.events)
+    }
+
+    pub fn announce(&mut self, text: &str) {
+        self.accessibility_event(AccessibilityEvent::Announcement(text.to_string()));
+    }
+
+    pub fn accessibility_event(&mut self, event: AccessibilityEvent) {
+        let accessibility = &mut self.accessibility;
+        if !accessibility.enabled {
+            return;
+        }
+
+        if let (Some(speech), Some(text)) = (&mut accessibility.speech, event.spoken()) {
+            let interrupt = !matches!(event, AccessibilityEvent::Announcement(_));
+            speech.speak(&text, interrupt);
+        }
+        accessibility.events.push(event);
+    }
+
+    // swaps the theme for the high contrast one until turned off again
+    pub fn set_high_contrast(&mut self, high_contrast: bool) {
+        if self.accessibility.high_contrast != high_contrast {
+            self.accessibility.high_contrast = high_contrast;
+            self.theme_revision += 1;
+        }
+    }
+
+    pub fn is_high_contrast(&self) -> bool {
+        self.accessibility.high_contrast
+    }
+}
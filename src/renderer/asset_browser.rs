@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use imgui::{TableColumnFlags, TableColumnSetup, TableFlags, TreeNodeFlags, Ui};
+
+use crate::assets::manager::PoolState;
+use crate::platform::dialog;
+use crate::renderer::Renderer;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AssetKind {
+    Texture,
+    Sound,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AssetLoadState {
+    Unloaded, // registered, its pool was never loaded or got unloaded
+    Loading,
+    Resident,
+    Evicted, // over the texture budget, reloads when drawn
+}
+
+#[derive(Clone, Debug)]
+pub struct BrowserAsset {
+    pub kind: AssetKind,
+    pub index: usize, // id within its pool and kind
+    pub path: String,
+    pub state: AssetLoadState,
+    pub references: usize, // sprites drawing a texture, voices playing a sound
+    pub bytes: u64,
+}
+
+// one asset pool as the engine sees it this frame
+#[derive(Clone, Debug)]
+pub struct BrowserPool {
+    pub id: usize,
+    pub state: PoolState,
+    pub assets: Vec<BrowserAsset>,
+}
+
+// what was clicked, the engine applies it to the asset manager next frame
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AssetBrowserEvent {
+    LoadPool(usize),
+    UnloadPool(usize),
+    // textures only, sounds stay in memory while their pool is loaded
+    Evict {
+        pool: usize,
+        index: usize,
+    },
+    Reload {
+        pool: usize,
+        kind: AssetKind,
+        index: usize,
+    },
+    Import(usize), // picks files to copy into the asset directory and add to the pool
+}
+
+#[derive(Default)]
+pub(super) struct AssetBrowser {
+    pools: Vec<BrowserPool>,
+    events: Vec<AssetBrowserEvent>,
+}
+
+impl<'a> Renderer<'a> {
+    // false when headless, the engine can skip building the rows then
+    pub fn has_debug_ui(&self) -> bool {
+        self.imgui_renderer.is_some()
+    }
+
+    pub fn set_asset_browser(&mut self, pools: Vec<BrowserPool>) {
+        self.asset_browser.pools = pools;
+    }
+
+    pub fn take_asset_browser_events(&mut self) -> Vec<AssetBrowserEvent> {
+        std::mem::take(&mut self.asset_browser.events)
+    }
+
+    // sprites using each (pool, texture), hidden ones included
+    pub fn texture_references(&self) -> HashMap<(usize, usize), usize> {
+        let mut references = HashMap::new();
+        for sprite in self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.instances.values())
+        {
+            *references.entry((sprite.pool, sprite.texture)).or_default() += 1;
+        }
+        references
+    }
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+pub(super) fn asset_browser_window(ui: &Ui, browser: &mut AssetBrowser) {
+    if browser.pools.is_empty() {
+        return;
+    }
+
+    let AssetBrowser { pools, events } = browser;
+    ui.window("assets")
+        .size([520.0, 320.0], imgui::Condition::FirstUseEver)
+        .position([100.0, 860.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            let total: u64 = pools.iter().flat_map(|p| &p.assets).map(|a| a.bytes).sum();
+            ui.text(format!(
+                "{} pools, {} resident",
+                pools.len(),
+                format_bytes(total)
+            ));
+
+            for pool in pools.iter() {
+                let bytes: u64 = pool.assets.iter().map(|asset| asset.bytes).sum();
+                let label = format!(
+                    "pool {}: {:?}, {} assets, {}###pool{}",
+                    pool.id,
+                    pool.state,
+                    pool.assets.len(),
+                    format_bytes(bytes),
+                    pool.id
+                );
+                let open = ui.collapsing_header(&label, TreeNodeFlags::DEFAULT_OPEN);
+
+                match pool.state {
+                    PoolState::Registered => {
+                        if ui.small_button(format!("load##pool{}", pool.id)) {
+                            events.push(AssetBrowserEvent::LoadPool(pool.id));
+                        }
+                    }
+                    PoolState::Loaded => {
+                        if ui.small_button(format!("unload##pool{}", pool.id)) {
+                            events.push(AssetBrowserEvent::UnloadPool(pool.id));
+                        }
+                    }
+                    PoolState::Queued | PoolState::Loading => ui.text_disabled("loading"),
+                }
+                if dialog::is_available()
+                    && !matches!(pool.state, PoolState::Queued | PoolState::Loading)
+                {
+                    ui.same_line();
+                    if ui.small_button(format!("import##pool{}", pool.id)) {
+                        events.push(AssetBrowserEvent::Import(pool.id));
+                    }
+                }
+                if !open {
+                    continue;
+                }
+
+                let flags = TableFlags::RESIZABLE | TableFlags::ROW_BG | TableFlags::BORDERS;
+                let Some(_table) = ui.begin_table_with_flags(format!("pool{}", pool.id), 5, flags)
+                else {
+                    continue;
+                };
+                for (index, name) in ["asset", "state", "refs", "size", ""].iter().enumerate() {
+                    let mut column = TableColumnSetup::new(*name);
+                    if index == 0 {
+                        column.flags = TableColumnFlags::WIDTH_STRETCH;
+                    }
+                    ui.table_setup_column_with(column);
+                }
+                ui.table_headers_row();
+
+                for asset in &pool.assets {
+                    ui.table_next_row();
+                    ui.table_next_column();
+                    ui.text(&asset.path);
+                    ui.table_next_column();
+                    ui.text(format!("{:?}", asset.state));
+                    ui.table_next_column();
+                    ui.text(asset.references.to_string());
+                    ui.table_next_column();
+                    ui.text(format_bytes(asset.bytes));
+                    ui.table_next_column();
+
+                    let id = format!("{}-{:?}-{}", pool.id, asset.kind, asset.index);
+                    let loaded = matches!(
+                        asset.state,
+                        AssetLoadState::Resident | AssetLoadState::Evicted
+                    );
+                    if loaded && ui.small_button(format!("reload##{}", id)) {
+                        events.push(AssetBrowserEvent::Reload {
+                            pool: pool.id,
+                            kind: asset.kind,
+                            index: asset.index,
+                        });
+                    }
+                    if asset.kind == AssetKind::Texture && asset.state == AssetLoadState::Resident {
+                        ui.same_line();
+                        if ui.small_button(format!("evict##{}", id)) {
+                            events.push(AssetBrowserEvent::Evict {
+                                pool: pool.id,
+                                index: asset.index,
+                            });
+                        }
+                    }
+                }
+            }
+        });
+}
@@ -0,0 +1,169 @@
+use log::{info, warn};
+
+use crate::assets::NvTexture;
+use crate::assets::atlas::AtlasRegions;
+use crate::assets::image_cache;
+use crate::assets::meta::TextureMeta;
+use crate::renderer::Renderer;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextureResidency {
+    pub resident: bool,
+    pub bytes: u64, // of the whole mip chain, taken while resident
+}
+
+impl<'a> Renderer<'a> {
+    // None keeps every pool texture resident
+    pub fn set_texture_budget(&mut self, bytes: Option<u64>) {
+        info!("texture budget set to {:?} bytes", bytes);
+        self.texture_budget = bytes;
+    }
+
+    pub fn texture_budget(&self) -> Option<u64> {
+        self.texture_budget
+    }
+
+    // bytes of pool textures currently in vram
+    pub fn texture_memory(&self) -> u64 {
+        self.loaded_pools
+            .iter()
+            .flat_map(|pool| pool.textures.iter())
+            .filter(|slot| slot.texture.is_some())
+            .map(|slot| slot.bytes)
+            .sum()
+    }
+
+    pub fn texture_residency(&self, pool: usize, index: usize) -> Option<TextureResidency> {
+        let slot = self.loaded_pools.get(pool)?.textures.get(index)?;
+        Some(TextureResidency {
+            resident: slot.texture.is_some(),
+            bytes: slot.bytes,
+        })
+    }
+
+    // frees the vram until the texture is drawn again
+    pub fn evict_texture(&mut self, pool: usize, index: usize) {
+        let Some(slot) = self
+            .loaded_pools
+            .get_mut(pool)
+            .and_then(|pool| pool.textures.get_mut(index))
+        else {
+            warn!("no texture {} in pool {} to evict", index, pool);
+            return;
+        };
+        if slot.texture.take().is_some() {
+            info!("evicted texture {} ({} bytes)", slot.name, slot.bytes);
+        }
+    }
+
+    pub fn evict_pool(&mut self, pool: usize) {
+        let count = self
+            .loaded_pools
+            .get(pool)
+            .map_or(0, |pool| pool.textures.len());
+        for index in 0..count {
+            self.evict_texture(pool, index);
+        }
+    }
+
+    // a texture being reloaded is drawn with the placeholder
+    pub(super) fn texture(&self, pool: usize, index: usize) -> Option<&NvTexture> {
+        let slot = self.loaded_pools.get(pool)?.textures.get(index)?;
+        match &slot.texture {
+            Some(texture) => Some(texture),
+            None if self.is_reloading(pool, index) => self.uploads.placeholder(),
+            None => None,
+        }
+    }
+
+    // marks a texture as drawn this frame, reloading it when it was evicted.
+    // one still queued for upload is hurried along instead
+    pub(super) fn touch_texture(&mut self, pool: usize, index: usize) {
+        let queued = self.prioritize_upload(pool, index);
+        let Some(slot) = self
+            .loaded_pools
+            .get_mut(pool)
+            .and_then(|loaded_pool| loaded_pool.textures.get_mut(index))
+        else {
+            return;
+        };
+
+        slot.last_used = self.frame_index;
+
+        if slot.texture.is_none() && !queued {
+            self.queue_reload(pool, index, false);
+        }
+    }
+
+    pub fn texture_meta(&self, pool: usize, index: usize) -> Option<&TextureMeta> {
+        Some(&self.loaded_pools.get(pool)?.textures.get(index)?.meta)
+    }
+
+    // re-imports a texture, e.g. after its import settings changed. the old one
+    // is drawn until the new one is up, which is notified
+    pub fn reload_texture(&mut self, pool: usize, index: usize) -> bool {
+        let Some(slot) = self
+            .loaded_pools
+            .get_mut(pool)
+            .and_then(|pool| pool.textures.get_mut(index))
+        else {
+            return false;
+        };
+
+        slot.regions = AtlasRegions::load(&slot.name);
+        image_cache::forget(&slot.name);
+        self.queue_reload(pool, index, true);
+        self.refresh_sprite_regions(pool, index);
+        true
+    }
+
+    // unloads least recently drawn textures until the budget fits
+    pub(super) fn enforce_texture_budget(&mut self) {
+        let Some(budget) = self.texture_budget else {
+            self.over_texture_budget = false;
+            return;
+        };
+
+        let frame = self.frame_index;
+        let mut resident = self.texture_memory();
+        if resident > budget {
+            // textures drawn this frame are never evicted
+            let mut candidates: Vec<(u64, usize, usize)> = self
+                .loaded_pools
+                .iter()
+                .enumerate()
+                .flat_map(|(pool, loaded_pool)| {
+                    loaded_pool
+                        .textures
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, slot)| slot.texture.is_some() && slot.last_used < frame)
+                        .map(move |(index, slot)| (slot.last_used, pool, index))
+                })
+                .collect();
+            candidates.sort_unstable();
+
+            for (_, pool, index) in candidates {
+                if resident <= budget {
+                    break;
+                }
+
+                let slot = &mut self.loaded_pools[pool].textures[index];
+                slot.texture = None;
+                resident -= slot.bytes;
+                info!("evicted texture {} ({} bytes)", slot.name, slot.bytes);
+            }
+        }
+
+        let over = resident > budget;
+        if over && !self.over_texture_budget {
+            warn!(
+                "textures drawn this frame use {} bytes, over the {} byte budget",
+                resident, budget
+            );
+        } else if !over && self.over_texture_budget {
+            info!("textures fit the {} byte budget again", budget);
+        }
+        self.over_texture_budget = over;
+    }
+}
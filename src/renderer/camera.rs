@@ -0,0 +1,167 @@
+use log::warn;
+
+use crate::renderer::Renderer;
+use crate::util::tween::{Easing, Tween};
+
+// camera that unpositioned layers and text are drawn with
+pub const SCREEN_CAMERA: usize = 0;
+
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 64.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PixelSnap {
+    #[default]
+    Smooth, // sub-pixel positions for smooth motion
+    Snapped, // whole physical pixels, for pixel art
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Camera2D {
+    pub position: [f32; 2], // world pixel at the top left of the screen
+    pub zoom: f32,
+    pub snap: PixelSnap,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Camera2D {
+            position: [0.0, 0.0],
+            zoom: 1.0,
+            snap: PixelSnap::Smooth,
+        }
+    }
+}
+
+// a camera easing to a position and zoom, x, y and zoom tweened together
+pub(super) struct CameraMove {
+    camera: usize,
+    tween: Tween<[f32; 3]>,
+}
+
+impl Camera2D {
+    pub fn is_snapped(&self) -> bool {
+        self.snap == PixelSnap::Snapped
+    }
+
+    // the world pixel under a point in physical pixels
+    pub fn screen_to_world(&self, screen_point: [f32; 2]) -> [f32; 2] {
+        let zoom = self.zoom.max(f32::EPSILON);
+        [
+            self.position[0] + screen_point[0] / zoom,
+            self.position[1] + screen_point[1] / zoom,
+        ]
+    }
+
+    // the inverse of screen_to_world, in physical pixels
+    pub fn world_to_screen(&self, world_point: [f32; 2]) -> [f32; 2] {
+        [
+            (world_point[0] - self.position[0]) * self.zoom,
+            (world_point[1] - self.position[1]) * self.zoom,
+        ]
+    }
+
+    // follows a drag of delta physical pixels, the world moves with it
+    pub fn pan(&mut self, delta: [f32; 2]) {
+        let zoom = self.zoom.max(f32::EPSILON);
+        self.position[0] -= delta[0] / zoom;
+        self.position[1] -= delta[1] / zoom;
+    }
+
+    // scales zoom while the world under screen_point, in physical pixels, stays put
+    pub fn zoom_around(&mut self, screen_point: [f32; 2], factor: f32) {
+        let world = self.screen_to_world(screen_point);
+
+        self.zoom = (self.zoom.max(f32::EPSILON) * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.position = [
+            world[0] - screen_point[0] / self.zoom,
+            world[1] - screen_point[1] / self.zoom,
+        ];
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn create_camera(&mut self, camera: Camera2D) -> usize {
+        let id = self.cameras.len();
+        self.cameras.push(camera);
+        id
+    }
+
+    pub fn camera(&self, id: usize) -> Option<&Camera2D> {
+        self.cameras.get(id)
+    }
+
+    pub fn camera_mut(&mut self, id: usize) -> Option<&mut Camera2D> {
+        self.cameras.get_mut(id)
+    }
+
+    // the world rect a camera shows, x, y, width, height in world pixels
+    pub fn camera_view(&self, id: usize) -> Option<[f32; 4]> {
+        let camera = self.cameras.get(id)?;
+        let zoom = camera.zoom.max(f32::EPSILON);
+        let [width, height] = self.viewport_size();
+
+        Some([
+            camera.position[0],
+            camera.position[1],
+            width / zoom,
+            height / zoom,
+        ])
+    }
+
+    // eases from where the camera is now, replacing a move it's already making.
+    // a curve from curve_easing gives it any shape
+    pub fn move_camera(
+        &mut self,
+        id: usize,
+        position: [f32; 2],
+        zoom: f32,
+        seconds: f32,
+        easing: Easing,
+    ) {
+        let Some(camera) = self.cameras.get(id) else {
+            warn!("no camera {}", id);
+            return;
+        };
+        let mut tween = Tween::new([camera.position[0], camera.position[1], camera.zoom]);
+        tween.start(
+            [position[0], position[1], zoom.clamp(MIN_ZOOM, MAX_ZOOM)],
+            seconds,
+            easing,
+        );
+        self.camera_moves.retain(|other| other.camera != id);
+        self.camera_moves.push(CameraMove { camera: id, tween });
+    }
+
+    pub fn is_camera_moving(&self, id: usize) -> bool {
+        self.camera_moves.iter().any(|other| other.camera == id)
+    }
+
+    pub fn stop_camera(&mut self, id: usize) {
+        self.camera_moves.retain(|other| other.camera != id);
+    }
+
+    pub(super) fn update_camera_moves(&mut self, dt_seconds: f32) {
+        let cameras = &mut self.cameras;
+        self.camera_moves.retain_mut(|movement| {
+            let [x, y, zoom] = movement.tween.update(dt_seconds);
+            if let Some(camera) = cameras.get_mut(movement.camera) {
+                camera.position = [x, y];
+                camera.zoom = zoom.max(f32::EPSILON);
+            }
+            !movement.tween.is_finished()
+        });
+    }
+
+    pub fn set_layer_camera(&mut self, layer: usize, camera: usize) {
+        if camera >= self.cameras.len() {
+            warn!("no camera {}", camera);
+            return;
+        }
+
+        match self.layers.get_mut(layer) {
+            Some(layer) => layer.camera = camera,
+            None => warn!("no sprite layer {}", layer),
+        }
+    }
+}
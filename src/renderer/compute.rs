@@ -0,0 +1,140 @@
+use log::{error, info};
+use wgpu::util::DeviceExt;
+
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::shader::ShaderError;
+use crate::renderer::{FrameContext, Renderer};
+
+pub struct ComputePipeline {
+    pub(super) pipeline: wgpu::ComputePipeline,
+    pub(super) label: String,
+}
+
+// one queued dispatch, recorded at the start of the next frame
+pub struct ComputeDispatch {
+    pub pipeline: usize,
+    pub bind_groups: Vec<wgpu::BindGroup>,
+    pub workgroups: [u32; 3],
+}
+
+impl<'a> Renderer<'a> {
+    pub fn create_compute_pipeline(
+        &mut self,
+        label: &str,
+        source: &str,
+        entry_point: &str,
+    ) -> Result<usize, ShaderError> {
+        info!("creating compute pipeline {}", label);
+
+        let shader = self.create_shader_module(label, source)?;
+
+        // catch pipeline errors instead of letting the device panic
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        // layout is derived from the shader bindings
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: None,
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        if let Some(e) = pollster::block_on(self.device.pop_error_scope()) {
+            error!("failed to create compute pipeline {}: {}", label, e);
+            return Err(ShaderError::Device(e));
+        }
+
+        let id = self.compute_pipelines.len();
+        self.compute_pipelines.push(ComputePipeline {
+            pipeline,
+            label: label.to_string(),
+        });
+
+        Ok(id)
+    }
+
+    pub fn create_compute_bind_group(
+        &self,
+        pipeline: usize,
+        group: u32,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> Option<wgpu::BindGroup> {
+        let compute = match self.compute_pipelines.get(pipeline) {
+            Some(compute) => compute,
+            None => {
+                error!("no compute pipeline with id {}", pipeline);
+                return None;
+            }
+        };
+
+        Some(self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{}_bind_group_{}", compute.label, group)),
+            layout: &compute.pipeline.get_bind_group_layout(group),
+            entries,
+        }))
+    }
+
+    // storage buffer that can also be read back or used as vertex input
+    pub fn create_storage_buffer(&self, label: &str, contents: &[u8]) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            })
+    }
+
+    pub fn dispatch_compute(&mut self, dispatch: ComputeDispatch) {
+        self.compute_queue.push(dispatch);
+    }
+
+    // runs before any render pass in the same encoder, so wgpu orders
+    // the storage writes before they are read by the draws
+    pub(super) fn run_compute(&mut self, context: &mut FrameContext) {
+        if self.compute_queue.is_empty() {
+            return;
+        }
+
+        let mut pass = context
+            .encoder
+            .begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass"),
+                timestamp_writes: None,
+            });
+        self.frame_dump
+            .pass("Compute Pass", PassKind::Compute, "storage", false);
+
+        for dispatch in self.compute_queue.drain(..) {
+            let compute = match self.compute_pipelines.get(dispatch.pipeline) {
+                Some(compute) => compute,
+                None => {
+                    error!("no compute pipeline with id {}", dispatch.pipeline);
+                    continue;
+                }
+            };
+
+            pass.set_pipeline(&compute.pipeline);
+            for (i, bind_group) in dispatch.bind_groups.iter().enumerate() {
+                pass.set_bind_group(i as u32, bind_group, &[]);
+            }
+
+            let [x, y, z] = dispatch.workgroups;
+            pass.dispatch_workgroups(x, y, z);
+            self.frame_dump.draw(|| {
+                let record = DrawRecord::new(DrawCall::Dispatch, compute.label.clone());
+                (0..dispatch.bind_groups.len())
+                    .fold(record, |record, i| {
+                        record.bind_group(format!("group {}", i))
+                    })
+                    .workgroups(dispatch.workgroups)
+            });
+        }
+    }
+}
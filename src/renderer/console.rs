@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+
+use imgui::{
+    Condition, HistoryDirection, InputTextCallback, InputTextCallbackHandler, TextCallbackData, Ui,
+    WindowFlags,
+};
+use log::Level;
+
+use crate::renderer::Renderer;
+use crate::ui::theme::Theme;
+
+const MAX_LINES: usize = 500;
+const MAX_HISTORY: usize = 100;
+const HEIGHT: f32 = 0.4; // of the window, the console_height cvar
+
+// the drop down panel of the developer console. it only collects lines, the
+// engine runs them against its commands and prints back here
+pub(super) struct ConsolePanel {
+    open: bool,
+    focus: bool, // the input, on the frame after opening
+    input: String,
+    lines: VecDeque<(Level, String)>,
+    scroll: bool, // to the newest line
+    history: Vec<String>,
+    history_index: Option<usize>, // while going back through it
+    submitted: Vec<String>,
+    completions: Vec<String>, // command names, some with their first argument
+    height: f32,
+}
+
+impl Default for ConsolePanel {
+    fn default() -> Self {
+        ConsolePanel {
+            open: false,
+            focus: false,
+            input: String::new(),
+            lines: VecDeque::new(),
+            scroll: false,
+            history: Vec::new(),
+            history_index: None,
+            submitted: Vec::new(),
+            completions: Vec::new(),
+            height: HEIGHT,
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn toggle_console(&mut self) -> bool {
+        let panel = &mut self.console;
+        panel.open = !panel.open;
+        panel.focus = panel.open;
+        panel.history_index = None;
+        panel.open
+    }
+
+    #[allow(dead_code)] // for games holding back their own hotkeys while it's typed into
+    pub fn is_console_open(&self) -> bool {
+        self.console.open
+    }
+
+    pub fn console_print(&mut self, level: Level, text: &str) {
+        let panel = &mut self.console;
+        for line in text.lines() {
+            if panel.lines.len() >= MAX_LINES {
+                panel.lines.pop_front();
+            }
+            panel.lines.push_back((level, line.to_string()));
+        }
+        panel.scroll = true;
+    }
+
+    // its share of the window height
+    pub fn set_console_height(&mut self, height: f32) {
+        self.console.height = height.clamp(0.1, 1.0);
+    }
+
+    pub fn clear_console(&mut self) {
+        self.console.lines.clear();
+    }
+
+    // what was entered since last asked, oldest first
+    pub fn take_console_commands(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.console.submitted)
+    }
+
+    // what tab completes the first word to
+    pub fn set_console_completions(&mut self, names: Vec<String>) {
+        self.console.completions = names;
+    }
+}
+
+struct InputCallbacks<'c> {
+    history: &'c [String],
+    history_index: &'c mut Option<usize>,
+    completions: &'c [String],
+    lines: &'c mut VecDeque<(Level, String)>,
+}
+
+impl InputTextCallbackHandler for InputCallbacks<'_> {
+    // the toggle key would end up in the input otherwise
+    fn char_filter(&mut self, c: char) -> Option<char> {
+        (!matches!(c, '`' | '~')).then_some(c)
+    }
+
+    // completes the word being typed, entries like "set sim_tick_ms" complete
+    // the second word once the first is there
+    fn on_completion(&mut self, mut data: TextCallbackData) {
+        let text = data.str().to_string();
+        let words = text.split(' ').count();
+        let matches: Vec<&String> = self
+            .completions
+            .iter()
+            .filter(|entry| entry.starts_with(&text) && entry.split(' ').count() == words)
+            .collect();
+        let completed = match matches.as_slice() {
+            [] => return,
+            [name] => format!("{} ", name),
+            [first, rest @ ..] => {
+                let last_words: Vec<&str> = matches
+                    .iter()
+                    .filter_map(|entry| entry.rsplit(' ').next())
+                    .collect();
+                self.lines.push_back((Level::Debug, last_words.join("  ")));
+                let common = rest.iter().fold(first.len(), |common, name| {
+                    first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(common)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                first[..common].to_string()
+            }
+        };
+        data.clear();
+        data.push_str(&completed);
+    }
+
+    fn on_history(&mut self, direction: HistoryDirection, mut data: TextCallbackData) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        *self.history_index = match (direction, *self.history_index) {
+            (HistoryDirection::Up, None) => Some(last),
+            (HistoryDirection::Up, Some(index)) => Some(index.saturating_sub(1)),
+            (HistoryDirection::Down, Some(index)) if index < last => Some(index + 1),
+            (HistoryDirection::Down, _) => None,
+        };
+        data.clear();
+        if let Some(index) = *self.history_index {
+            data.push_str(&self.history[index]);
+        }
+    }
+}
+
+fn level_color(level: Level, theme: &Theme) -> [f32; 4] {
+    match level {
+        Level::Error => theme.error,
+        Level::Warn => theme.warning,
+        Level::Info => theme.text,
+        Level::Debug | Level::Trace => theme.text_muted,
+    }
+}
+
+pub(super) fn console_window(ui: &Ui, panel: &mut ConsolePanel, theme: &Theme) {
+    if !panel.open {
+        return;
+    }
+
+    let [width, height] = ui.io().display_size;
+    let flags = WindowFlags::NO_TITLE_BAR
+        | WindowFlags::NO_RESIZE
+        | WindowFlags::NO_MOVE
+        | WindowFlags::NO_COLLAPSE
+        | WindowFlags::NO_SAVED_SETTINGS;
+    ui.window("console")
+        .flags(flags)
+        .position([0.0, 0.0], Condition::Always)
+        .size([width, height * panel.height], Condition::Always)
+        .build(|| {
+            let input_height = ui.frame_height_with_spacing();
+            ui.child_window("console_lines")
+                .size([0.0, -input_height])
+                .build(|| {
+                    for (level, line) in panel.lines.iter() {
+                        ui.text_colored(level_color(*level, theme), line);
+                    }
+                    if panel.scroll {
+                        ui.set_scroll_here_y_with_ratio(1.0);
+                        panel.scroll = false;
+                    }
+                });
+
+            if panel.focus {
+                ui.set_keyboard_focus_here();
+                panel.focus = false;
+            }
+            ui.set_next_item_width(-1.0);
+            let callbacks = InputCallbacks {
+                history: &panel.history,
+                history_index: &mut panel.history_index,
+                completions: &panel.completions,
+                lines: &mut panel.lines,
+            };
+            let entered = ui
+                .input_text("##console_input", &mut panel.input)
+                .enter_returns_true(true)
+                .callback(
+                    InputTextCallback::CHAR_FILTER
+                        | InputTextCallback::COMPLETION
+                        | InputTextCallback::HISTORY,
+                    callbacks,
+                )
+                .build();
+            if !entered {
+                return;
+            }
+
+            let line = std::mem::take(&mut panel.input).trim().to_string();
+            panel.focus = true; // enter drops it
+            panel.history_index = None;
+            if line.is_empty() {
+                return;
+            }
+            panel.lines.push_back((Level::Info, format!("> {}", line)));
+            panel.scroll = true;
+            if panel.history.last() != Some(&line) {
+                if panel.history.len() >= MAX_HISTORY {
+                    panel.history.remove(0);
+                }
+                panel.history.push(line.clone());
+            }
+            panel.submitted.push(line);
+        });
+}
@@ -0,0 +1,283 @@
+use std::sync::Arc;
+
+use imgui::{MouseButton, TreeNodeFlags, Ui};
+use log::warn;
+
+use crate::renderer::Renderer;
+use crate::ui::theme::Theme;
+use crate::util::curve::{Curve, CurveKey};
+use crate::util::tween::Easing;
+
+const CANVAS_SIZE: [f32; 2] = [300.0, 150.0]; // logical pixels
+const HIT_RADIUS: f32 = 6.0;
+const KEY_RADIUS: f32 = 4.0;
+const SAMPLES: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum KeyPart {
+    Key,
+    In,
+    Out,
+}
+
+struct NamedCurve {
+    name: String,
+    curve: Curve,
+    selected: Option<usize>, // key
+}
+
+struct CurveDrag {
+    curve: usize,
+    key: usize,
+    part: KeyPart,
+    range: [f32; 4], // the view when the drag started, so it doesn't run off
+}
+
+// curves the game hands out by id and reads back every frame, so edits in the
+// curves window show up while it runs
+#[derive(Default)]
+pub(super) struct CurveLibrary {
+    curves: Vec<NamedCurve>,
+    drag: Option<CurveDrag>,
+}
+
+impl CurveLibrary {
+    pub(super) fn get(&self, id: usize) -> Option<&Curve> {
+        self.curves.get(id).map(|named| &named.curve)
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn add_curve(&mut self, name: &str, curve: Curve) -> usize {
+        let id = self.curves.curves.len();
+        self.curves.curves.push(NamedCurve {
+            name: name.to_string(),
+            curve,
+            selected: None,
+        });
+        id
+    }
+
+    pub fn curve(&self, id: usize) -> Option<&Curve> {
+        self.curves.get(id)
+    }
+
+    pub fn set_curve(&mut self, id: usize, curve: Curve) {
+        match self.curves.curves.get_mut(id) {
+            Some(named) => {
+                named.curve = curve;
+                named.selected = None;
+            }
+            None => warn!("no curve {}", id),
+        }
+    }
+
+    // the curve as it is now for a tween, later edits don't reach it
+    pub fn curve_easing(&self, id: usize) -> Option<Easing> {
+        Some(Easing::Curve(Arc::new(self.curve(id)?.clone())))
+    }
+}
+
+pub(super) fn curves_window(ui: &Ui, library: &mut CurveLibrary, theme: &Theme) {
+    if library.curves.is_empty() {
+        return;
+    }
+
+    ui.window("curves")
+        .size([340.0, 420.0], imgui::Condition::FirstUseEver)
+        .position([1420.0, 680.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.text_disabled("double click adds a key, right click removes it");
+            for id in 0..library.curves.len() {
+                let label = format!("{}###curve{}", library.curves[id].name, id);
+                if !ui.collapsing_header(&label, TreeNodeFlags::DEFAULT_OPEN) {
+                    continue;
+                }
+                canvas(ui, library, id, theme);
+
+                let named = &mut library.curves[id];
+                let Some(index) = named.selected else {
+                    continue;
+                };
+                let Some(mut key) = named.curve.keys().get(index).copied() else {
+                    named.selected = None;
+                    continue;
+                };
+                let mut point = [key.time, key.value];
+                if ui
+                    .input_float2(format!("key##curve{}", id), &mut point)
+                    .build()
+                {
+                    [key.time, key.value] = point;
+                    named.curve.set_key(index, key);
+                }
+                if named.curve.keys().len() > 1
+                    && ui.small_button(format!("remove key##curve{}", id))
+                {
+                    named.curve.remove(index);
+                    named.selected = None;
+                }
+            }
+        });
+}
+
+// the time and value range shown, a bit wider than the keys and at least 0..1
+fn view_range(curve: &Curve) -> [f32; 4] {
+    let keys = curve.keys();
+    let fold = |pick: fn(&CurveKey) -> f32, start: f32, min: bool| {
+        keys.iter().map(pick).fold(start, |a, b| match min {
+            true => a.min(b),
+            false => a.max(b),
+        })
+    };
+    let [t0, t1] = [fold(|k| k.time, 0.0, true), fold(|k| k.time, 1.0, false)];
+    let [v0, v1] = [fold(|k| k.value, 0.0, true), fold(|k| k.value, 1.0, false)];
+    let margin = (v1 - v0) * 0.1;
+    [t0, t1, v0 - margin, v1 + margin]
+}
+
+fn canvas(ui: &Ui, library: &mut CurveLibrary, id: usize, theme: &Theme) {
+    let dragging = library.drag.as_ref().filter(|drag| drag.curve == id);
+    let range = dragging.map_or_else(|| view_range(&library.curves[id].curve), |drag| drag.range);
+    let [t0, t1, v0, v1] = range;
+    let origin = ui.cursor_screen_pos();
+    let [width, height] = CANVAS_SIZE;
+    let to_screen = |[t, v]: [f32; 2]| {
+        [
+            origin[0] + (t - t0) / (t1 - t0) * width,
+            origin[1] + height - (v - v0) / (v1 - v0) * height,
+        ]
+    };
+    let to_curve = |[x, y]: [f32; 2]| {
+        [
+            t0 + (x - origin[0]) / width * (t1 - t0),
+            v0 + (origin[1] + height - y) / height * (v1 - v0),
+        ]
+    };
+
+    ui.invisible_button(format!("##curve_canvas{}", id), CANVAS_SIZE);
+    let hovered = ui.is_item_hovered();
+    let mouse = ui.io().mouse_pos;
+    let named = &mut library.curves[id];
+
+    // what's under the mouse, the selected key's handles first
+    let parts = |key: &CurveKey| {
+        let point = [key.time, key.value];
+        let handle = |offset: [f32; 2]| [point[0] + offset[0], point[1] + offset[1]];
+        [
+            (KeyPart::Key, point),
+            (KeyPart::In, handle(key.in_handle)),
+            (KeyPart::Out, handle(key.out_handle)),
+        ]
+    };
+    let near = |point: [f32; 2]| {
+        let [x, y] = to_screen(point);
+        (x - mouse[0]).hypot(y - mouse[1]) <= HIT_RADIUS
+    };
+    let hit = named
+        .selected
+        .and_then(|index| {
+            let key = named.curve.keys().get(index)?;
+            parts(key)[1..]
+                .iter()
+                .find(|(_, point)| near(*point))
+                .map(|(part, _)| (index, *part))
+        })
+        .or_else(|| {
+            named
+                .curve
+                .keys()
+                .iter()
+                .position(|key| near([key.time, key.value]))
+                .map(|index| (index, KeyPart::Key))
+        });
+
+    if hovered && ui.is_mouse_double_clicked(MouseButton::Left) && hit.is_none() {
+        let [time, value] = to_curve(mouse);
+        named.selected = Some(named.curve.insert(CurveKey::new(time, value)));
+    } else if ui.is_item_clicked() {
+        named.selected = hit.map(|(index, _)| index);
+        library.drag = hit.map(|(key, part)| CurveDrag {
+            curve: id,
+            key,
+            part,
+            range,
+        });
+    }
+    if ui.is_item_clicked_with_button(MouseButton::Right)
+        && let Some((index, KeyPart::Key)) = hit
+        && named.curve.keys().len() > 1
+    {
+        named.curve.remove(index);
+        named.selected = None;
+    }
+
+    if let Some(drag) = library.drag.as_ref().filter(|drag| drag.curve == id) {
+        match ui.is_mouse_down(MouseButton::Left) {
+            true => {
+                if let Some(mut key) = named.curve.keys().get(drag.key).copied() {
+                    let [time, value] = to_curve(mouse);
+                    let offset = [time - key.time, value - key.value];
+                    match drag.part {
+                        KeyPart::Key => [key.time, key.value] = [time, value],
+                        KeyPart::In => key.in_handle = [offset[0].min(0.0), offset[1]],
+                        KeyPart::Out => key.out_handle = [offset[0].max(0.0), offset[1]],
+                    }
+                    named.curve.set_key(drag.key, key);
+                }
+            }
+            false => library.drag = None,
+        }
+    }
+
+    let draw_list = ui.get_window_draw_list();
+    let end = [origin[0] + width, origin[1] + height];
+    draw_list
+        .add_rect(origin, end, theme.surface)
+        .filled(true)
+        .build();
+    for value in [0.0, 1.0] {
+        draw_list
+            .add_line(to_screen([t0, value]), to_screen([t1, value]), theme.border)
+            .build();
+    }
+    for time in [0.0, 1.0] {
+        draw_list
+            .add_line(to_screen([time, v0]), to_screen([time, v1]), theme.border)
+            .build();
+    }
+
+    let named = &library.curves[id];
+    let points: Vec<[f32; 2]> = (0..=SAMPLES)
+        .map(|i| {
+            let time = t0 + (t1 - t0) * i as f32 / SAMPLES as f32;
+            to_screen([time, named.curve.sample(time)])
+        })
+        .collect();
+    draw_list
+        .add_polyline(points, theme.accent)
+        .thickness(2.0)
+        .build();
+
+    for (index, key) in named.curve.keys().iter().enumerate() {
+        let [(_, point), (_, handle_in), (_, handle_out)] = parts(key);
+        let selected = named.selected == Some(index);
+        if selected {
+            let handle_color = theme.debug_palette[2];
+            for handle in [handle_in, handle_out] {
+                draw_list
+                    .add_line(to_screen(point), to_screen(handle), handle_color)
+                    .build();
+                draw_list
+                    .add_circle(to_screen(handle), KEY_RADIUS - 1.0, handle_color)
+                    .filled(true)
+                    .build();
+            }
+        }
+        let color = if selected { theme.accent } else { theme.text };
+        draw_list
+            .add_circle(to_screen(point), KEY_RADIUS, color)
+            .filled(true)
+            .build();
+    }
+}
@@ -0,0 +1,108 @@
+use imgui::{TableColumnFlags, TableColumnSetup, TableFlags, Ui};
+
+use crate::cvars::CvarValue;
+use crate::renderer::Renderer;
+
+// a cvar as the engine last said it was
+#[derive(Clone, Debug)]
+pub struct CvarRow {
+    pub name: String,
+    pub help: String,
+    pub value: CvarValue,
+    pub default: CvarValue,
+    pub range: Option<[f64; 2]>,
+}
+
+// edits go back to the engine's registry, which may clamp them, and come back
+// as new rows
+#[derive(Default)]
+pub(super) struct CvarPanel {
+    rows: Vec<CvarRow>,
+    edits: Vec<(String, CvarValue)>,
+    filter: String,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn set_cvar_rows(&mut self, rows: Vec<CvarRow>) {
+        self.cvar_panel.rows = rows;
+    }
+
+    pub fn take_cvar_edits(&mut self) -> Vec<(String, CvarValue)> {
+        std::mem::take(&mut self.cvar_panel.edits)
+    }
+}
+
+pub(super) fn cvars_window(ui: &Ui, panel: &mut CvarPanel) {
+    if panel.rows.is_empty() {
+        return;
+    }
+
+    let CvarPanel {
+        rows,
+        edits,
+        filter,
+    } = panel;
+    ui.window("cvars")
+        .size([420.0, 260.0], imgui::Condition::FirstUseEver)
+        .position([1100.0, 100.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.input_text("filter##cvars", filter).build();
+
+            let flags = TableFlags::RESIZABLE | TableFlags::ROW_BG | TableFlags::BORDERS;
+            let Some(_table) = ui.begin_table_with_flags("cvar_table", 3, flags) else {
+                return;
+            };
+            for (index, name) in ["cvar", "value", ""].iter().enumerate() {
+                let mut column = TableColumnSetup::new(*name);
+                if index == 1 {
+                    column.flags = TableColumnFlags::WIDTH_STRETCH;
+                }
+                ui.table_setup_column_with(column);
+            }
+            ui.table_headers_row();
+
+            for row in rows.iter_mut() {
+                if !row.name.contains(filter.trim()) {
+                    continue;
+                }
+                ui.table_next_row();
+                ui.table_next_column();
+                ui.text(&row.name);
+                if ui.is_item_hovered() && !row.help.is_empty() {
+                    ui.tooltip_text(&row.help);
+                }
+
+                ui.table_next_column();
+                ui.set_next_item_width(-1.0);
+                let label = format!("##cvar_{}", row.name);
+                let changed = match (&mut row.value, row.range) {
+                    (CvarValue::Bool(value), _) => ui.checkbox(&label, value),
+                    (CvarValue::Int(value), Some([min, max])) => {
+                        ui.slider(&label, min as i64, max as i64, value)
+                    }
+                    (CvarValue::Int(value), None) => {
+                        let mut narrow = (*value).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+                        let changed = ui.input_int(&label, &mut narrow).build();
+                        *value = narrow as i64;
+                        changed
+                    }
+                    (CvarValue::Float(value), Some([min, max])) => {
+                        ui.slider(&label, min as f32, max as f32, value)
+                    }
+                    (CvarValue::Float(value), None) => ui.input_float(&label, value).build(),
+                    (CvarValue::String(value), _) => ui
+                        .input_text(&label, value)
+                        .enter_returns_true(true)
+                        .build(),
+                };
+                if changed {
+                    edits.push((row.name.clone(), row.value.clone()));
+                }
+
+                ui.table_next_column();
+                if row.value != row.default && ui.small_button(format!("reset##{}", row.name)) {
+                    edits.push((row.name.clone(), row.default.clone()));
+                }
+            }
+        });
+}
@@ -0,0 +1,298 @@
+use bytemuck::{Pod, Zeroable};
+use log::{error, info, warn};
+
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+
+const INITIAL_CAPACITY: usize = 256;
+// lines pile up while no frame is drawn, e.g. minimized, so they stop somewhere
+const MAX_LINES: usize = 1 << 16;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct DebugParams {
+    resolution: [f32; 2],
+    _pad: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct DebugLine {
+    start: [f32; 2], // physical pixels
+    end: [f32; 2],
+    color: [f32; 4],
+    width: f32,
+    _pad: [f32; 3],
+}
+
+struct DebugGpu {
+    layout: wgpu::BindGroupLayout,
+    params: wgpu::Buffer,
+    lines: wgpu::Buffer,
+    capacity: usize,
+    bind_group: wgpu::BindGroup,
+}
+
+// immediate mode lines over the finished scene, queued during the frame and
+// gone after it's drawn. widths are in physical pixels whatever the zoom
+#[derive(Default)]
+pub(super) struct DebugDraw {
+    lines: Vec<DebugLine>,
+    gpu: Option<DebugGpu>,
+    overflowed: bool,
+}
+
+// for games, the engine's own overlays are pinned to the screen
+#[allow(dead_code)]
+impl<'a> Renderer<'a> {
+    // a segment between two world points as the camera sees them
+    pub fn draw_debug_line(
+        &mut self,
+        camera: usize,
+        start: [f32; 2],
+        end: [f32; 2],
+        color: [f32; 4],
+        width: f32,
+    ) {
+        let Some(camera) = self.cameras.get(camera) else {
+            warn!("no camera {}", camera);
+            return;
+        };
+        let [start, end] = [start, end].map(|point| camera.world_to_screen(point));
+        self.draw_screen_line(start, end, color, width);
+    }
+
+    // x, y, width, height in world pixels
+    pub fn draw_debug_rect(&mut self, camera: usize, rect: [f32; 4], color: [f32; 4], width: f32) {
+        let [x, y, w, h] = rect;
+        let corners = [[x, y], [x + w, y], [x + w, y + h], [x, y + h]];
+        self.draw_debug_polygon(camera, &corners, color, width);
+    }
+
+    // closed, the last point connects back to the first
+    pub fn draw_debug_polygon(
+        &mut self,
+        camera: usize,
+        points: &[[f32; 2]],
+        color: [f32; 4],
+        width: f32,
+    ) {
+        for (i, &start) in points.iter().enumerate() {
+            let end = points[(i + 1) % points.len()];
+            self.draw_debug_line(camera, start, end, color, width);
+        }
+    }
+
+    // segments follow the radius on screen so big circles stay round
+    pub fn draw_debug_circle(
+        &mut self,
+        camera: usize,
+        center: [f32; 2],
+        radius: f32,
+        color: [f32; 4],
+        width: f32,
+    ) {
+        let Some(zoom) = self.cameras.get(camera).map(|camera| camera.zoom) else {
+            warn!("no camera {}", camera);
+            return;
+        };
+        let segments = ((radius * zoom).abs() * 0.5).clamp(12.0, 96.0) as usize;
+        let points: Vec<[f32; 2]> = (0..segments)
+            .map(|i| {
+                let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+                [
+                    center[0] + radius * angle.cos(),
+                    center[1] + radius * angle.sin(),
+                ]
+            })
+            .collect();
+        self.draw_debug_polygon(camera, &points, color, width);
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // in physical pixels, for overlays pinned to the screen
+    pub fn draw_screen_line(
+        &mut self,
+        start: [f32; 2],
+        end: [f32; 2],
+        color: [f32; 4],
+        width: f32,
+    ) {
+        let debug = &mut self.debug_draw;
+        if debug.lines.len() >= MAX_LINES {
+            if !debug.overflowed {
+                warn!(
+                    "more than {} debug lines queued, dropping the rest",
+                    MAX_LINES
+                );
+                debug.overflowed = true;
+            }
+            return;
+        }
+        debug.lines.push(DebugLine {
+            start,
+            end,
+            color,
+            width,
+            _pad: [0.0; 3],
+        });
+    }
+
+    pub(super) fn render_debug_draw(&mut self, context: &mut FrameContext) {
+        self.queue_grid_lines();
+        let count = self.debug_draw.lines.len();
+        if count == 0 {
+            return;
+        }
+        self.debug_draw.overflowed = false;
+
+        let Some(mut gpu) = self
+            .debug_draw
+            .gpu
+            .take()
+            .or_else(|| self.create_debug_gpu())
+        else {
+            return;
+        };
+        if count > gpu.capacity {
+            gpu.capacity = count.next_power_of_two();
+            info!("growing debug line buffer to {}", gpu.capacity);
+            gpu.lines = self.create_debug_line_buffer(gpu.capacity);
+            gpu.bind_group = self.create_debug_bind_group(&gpu.layout, &gpu.params, &gpu.lines);
+        }
+
+        let params = DebugParams {
+            resolution: self.viewport_size(),
+            _pad: [0.0; 2],
+        };
+        self.queue
+            .write_buffer(&gpu.params, 0, bytemuck::cast_slice(&[params]));
+        self.queue
+            .write_buffer(&gpu.lines, 0, bytemuck::cast_slice(&self.debug_draw.lines));
+
+        if let Some(pipeline) = self.pipelines.get(&PipelineType::DebugLines) {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Debug Draw Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &context.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            pass.set_pipeline(pipeline);
+            self.apply_viewport(&mut pass);
+            pass.set_bind_group(0, &gpu.bind_group, &[]);
+            pass.draw(0..6, 0..count as u32);
+
+            self.frame_dump
+                .pass("Debug Draw Render Pass", PassKind::Render, "frame", false);
+            self.frame_dump.draw(|| {
+                DrawRecord::new(DrawCall::Draw, PipelineType::DebugLines.label())
+                    .bind_group("debug lines")
+                    .vertices(0..6)
+                    .instances(0..count as u32)
+            });
+        }
+
+        self.debug_draw.gpu = Some(gpu);
+        self.debug_draw.lines.clear();
+    }
+
+    fn create_debug_gpu(&mut self) -> Option<DebugGpu> {
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Debug Draw Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        match self.create_pipeline(&PipelineType::DebugLines, &[&layout], &[]) {
+            Ok(pipeline) => {
+                self.pipelines.insert(PipelineType::DebugLines, pipeline);
+            }
+            Err(e) => {
+                error!("failed to create debug line pipeline: {}", e);
+                return None;
+            }
+        }
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Draw Params Buffer"),
+            size: std::mem::size_of::<DebugParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let lines = self.create_debug_line_buffer(INITIAL_CAPACITY);
+        let bind_group = self.create_debug_bind_group(&layout, &params, &lines);
+
+        Some(DebugGpu {
+            layout,
+            params,
+            lines,
+            capacity: INITIAL_CAPACITY,
+            bind_group,
+        })
+    }
+
+    fn create_debug_line_buffer(&self, capacity: usize) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Line Buffer"),
+            size: (capacity * std::mem::size_of::<DebugLine>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_debug_bind_group(
+        &self,
+        layout: &wgpu::BindGroupLayout,
+        params: &wgpu::Buffer,
+        lines: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Debug Draw Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: lines.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use imgui::Ui;
+use log::info;
+
+use crate::renderer::Renderer;
+
+// what the sprite pass draws instead of the shaded sprites, to look at what's
+// under them. it clears the frame first, the passes after it draw as usual
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum DebugView {
+    #[default]
+    Off,
+    Wireframe, // the triangles' edges, as lines where the gpu can draw them
+    Normals,   // sprites are flat, so the alpha is taken as a height
+    Overdraw,  // brighter the more sprites cover a pixel, transparent parts too
+    UvChecker, // cells of 8 texels, stretched or mismatched uvs stand out
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 5] = [
+        DebugView::Off,
+        DebugView::Wireframe,
+        DebugView::Normals,
+        DebugView::Overdraw,
+        DebugView::UvChecker,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugView::Off => "off",
+            DebugView::Wireframe => "wireframe",
+            DebugView::Normals => "normals",
+            DebugView::Overdraw => "overdraw",
+            DebugView::UvChecker => "uv",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        DebugView::ALL.into_iter().find(|view| view.name() == name)
+    }
+
+    pub(super) fn fragment_entry(&self) -> &'static str {
+        match self {
+            DebugView::Off => "fs_main",
+            DebugView::Wireframe => "fs_debug_wireframe",
+            DebugView::Normals => "fs_debug_normals",
+            DebugView::Overdraw => "fs_debug_overdraw",
+            DebugView::UvChecker => "fs_debug_uv",
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    // the pipeline for a view is built the first frame it's drawn
+    pub fn set_debug_view(&mut self, view: DebugView) {
+        if view != self.debug_view {
+            info!("debug view {}", view.name());
+        }
+        self.debug_view = view;
+    }
+
+    // without line rasterization wireframes find the edges from the sprites' uvs
+    pub fn supports_wireframe(&self) -> bool {
+        self.device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+    }
+}
+
+// the view picker in the debug window
+pub(super) fn debug_view_combo(ui: &Ui, view: &mut DebugView) {
+    if let Some(_combo) = ui.begin_combo("view", view.name()) {
+        for option in DebugView::ALL {
+            if ui
+                .selectable_config(option.name())
+                .selected(option == *view)
+                .build()
+            {
+                *view = option;
+            }
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use bytemuck::{Pod, Zeroable};
+use log::error;
+
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+
+// water and heat haze. the screen shifts by an offset map wherever layers with
+// LayerMask::Distort have drawn, so only those regions ripple
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Distortion {
+    pub pool: usize,
+    pub texture: usize,   // offset map, red and green hold x and y around 0.5
+    pub strength: f32,    // pixels at full offset
+    pub scale: f32,       // screen pixels per offset map repeat
+    pub scroll: [f32; 2], // offset map repeats per second
+}
+
+impl Default for Distortion {
+    fn default() -> Self {
+        Distortion {
+            pool: 0,
+            texture: 0,
+            strength: 8.0,
+            scale: 256.0,
+            scroll: [0.05, 0.02],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct DistortionParams {
+    resolution: [f32; 2],
+    scroll: [f32; 2],
+    time: f32,
+    strength: f32,
+    scale: f32,
+    _pad: f32,
+}
+
+// gpu side of the distortion pass, rebuilt with the surface size
+struct DistortionPass {
+    params: wgpu::Buffer,
+    scene: wgpu::Texture, // the world renders here, then gets distorted onto the target
+    scene_view: wgpu::TextureView,
+    mask_view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+#[derive(Default)]
+pub(super) struct DistortionState {
+    settings: Option<Distortion>,
+    time: f32,
+    pass: Option<DistortionPass>,
+}
+
+impl<'a> Renderer<'a> {
+    // None turns the pass off
+    pub fn set_distortion(&mut self, distortion: Option<Distortion>) {
+        self.distortion.settings = distortion;
+        if distortion.is_none() {
+            self.distortion.pass = None;
+        }
+    }
+
+    pub fn distortion(&self) -> Option<Distortion> {
+        self.distortion.settings
+    }
+
+    // like begin_lighting, the two nest so the world is distorted before it is graded
+    pub(super) fn begin_distortion(
+        &mut self,
+        context: &mut FrameContext,
+        dt_seconds: f32,
+    ) -> Option<wgpu::TextureView> {
+        let settings = self.distortion.settings?;
+        self.distortion.time += dt_seconds;
+        self.touch_texture(settings.pool, settings.texture);
+
+        let size = [self.surface_config.width, self.surface_config.height];
+        let format = self.scene_format();
+        let stale = self.distortion.pass.as_ref().is_none_or(|pass| {
+            let scene = pass.scene.size();
+            [scene.width, scene.height] != size || pass.scene.format() != format
+        });
+        if stale {
+            self.distortion.pass = self.create_distortion_pass();
+        }
+        let pass = self.distortion.pass.as_ref()?;
+
+        // nothing guarantees the first world pass clears
+        self.frame_dump.pass(
+            "Distortion Scene Clear Pass",
+            PassKind::Render,
+            "distortion scene",
+            true,
+        );
+        context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Distortion Scene Clear Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &pass.scene_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+        Some(std::mem::replace(
+            &mut context.view,
+            pass.scene_view.clone(),
+        ))
+    }
+
+    pub(super) fn apply_distortion(
+        &mut self,
+        context: &mut FrameContext,
+        target: wgpu::TextureView,
+    ) {
+        context.view = target;
+
+        let (Some(settings), Some(pass)) = (self.distortion.settings, &self.distortion.pass) else {
+            return;
+        };
+        self.render_distortion_mask(&mut context.encoder, &pass.mask_view);
+
+        let pipeline = match self.pipelines.get(&PipelineType::Distortion) {
+            Some(pipeline) => pipeline,
+            None => {
+                error!("No distortion pipeline");
+                return;
+            }
+        };
+        let Some(offset_map) = self.texture(settings.pool, settings.texture) else {
+            error!(
+                "No distortion texture {} in pool {}",
+                settings.texture, settings.pool
+            );
+            return;
+        };
+
+        let params = DistortionParams {
+            resolution: [
+                self.surface_config.width as f32,
+                self.surface_config.height as f32,
+            ],
+            scroll: settings.scroll,
+            time: self.distortion.time,
+            strength: settings.strength,
+            scale: settings.scale.max(1.0),
+            _pad: 0.0,
+        };
+        self.queue
+            .write_buffer(&pass.params, 0, bytemuck::cast_slice(&[params]));
+
+        let mut render_pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Distortion Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &pass.bind_group, &[]);
+        render_pass.set_bind_group(1, &offset_map.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        self.frame_dump
+            .pass("Distortion Render Pass", PassKind::Render, "frame", false);
+        self.frame_dump.draw(|| {
+            DrawRecord::new(DrawCall::Draw, PipelineType::Distortion.label())
+                .bind_group("distortion")
+                .bind_group(format!("texture {}/{}", settings.pool, settings.texture))
+                .vertices(0..3)
+                .instances(0..1)
+        });
+    }
+
+    fn create_distortion_pass(&mut self) -> Option<DistortionPass> {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Distortion Bind Group Layout"),
+                entries: &[
+                    texture_entry(0),
+                    texture_entry(1),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        if !self.pipelines.contains_key(&PipelineType::Distortion) {
+            let texture_layout = self.bind_group_layouts.first()?.clone();
+            let pipeline = match self.create_pipeline(
+                &PipelineType::Distortion,
+                &[&layout, &texture_layout],
+                &[],
+            ) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    error!("failed to create distortion pipeline: {}", e);
+                    return None;
+                }
+            };
+            self.pipelines.insert(PipelineType::Distortion, pipeline);
+        }
+
+        let screen_texture = |label| {
+            self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: self.surface_config.width,
+                    height: self.surface_config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                // the mask is drawn with the sprite pipelines, so it shares their format
+                format: self.scene_format(),
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let scene = screen_texture("Distortion Scene Texture");
+        let scene_view = scene.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Distortion Scene View"),
+            ..Default::default()
+        });
+        let mask_view =
+            screen_texture("Distortion Mask Texture").create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Distortion Mask View"),
+                ..Default::default()
+            });
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Distortion Params Buffer"),
+            size: std::mem::size_of::<DistortionParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Distortion Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Distortion Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&scene_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&mask_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        });
+
+        Some(DistortionPass {
+            params,
+            scene,
+            scene_view,
+            mask_view,
+            bind_group,
+        })
+    }
+}
@@ -0,0 +1,281 @@
+use log::info;
+use winit::window::Fullscreen;
+
+use crate::assets::manager::LoadedPool;
+use crate::assets::meta::TextureMeta;
+use crate::assets::{DecodedImage, MipLevels, PixelFormat};
+use crate::renderer::Renderer;
+use crate::renderer::layer::{Pivot, SortMode, Sprite};
+
+const MARGIN: f32 = 20.0; // logical pixels in from the safe area
+const INFO_SIZE: f32 = 14.0;
+const FONT_SIZES: [f32; 5] = [8.0, 12.0, 16.0, 24.0, 32.0]; // logical pixels
+const CHECKER_SIZE: u32 = 32; // texels, 1 pixel squares
+const STRIPES: u32 = 32; // pixels per stripe block
+const RULER_LENGTH: f32 = 200.0;
+
+// rows of the page, in logical pixels from its top left
+const STRIPES_TOP: f32 = 40.0;
+const RULERS_TOP: f32 = 100.0;
+const SPRITES_TOP: f32 = 160.0;
+const TEXT_TOP: f32 = 220.0;
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+// a page of patterns that only look right when every pixel lands where it
+// should: 1 pixel stripes, rulers in physical and logical pixels, a checkered
+// sprite at 1:1 and at the scale factor, and text at known sizes. moving the
+// window to a monitor of another dpi keeps the physical parts the same size
+// and scales the logical ones
+#[derive(Default)]
+pub(super) struct DpiTest {
+    visible: bool,
+    info: Option<(usize, String)>, // reshaped only when the text changes
+    labels: Vec<DpiLabel>,
+    layer: Option<usize>,
+    sprites: Vec<usize>, // the 1:1 checker, then the scaled one
+}
+
+struct DpiLabel {
+    id: usize,
+    offset: [f32; 2], // logical pixels from the page's top left
+}
+
+fn checker() -> DecodedImage {
+    let rgba = (0..CHECKER_SIZE * CHECKER_SIZE)
+        .flat_map(|i| {
+            let value = match (i % CHECKER_SIZE + i / CHECKER_SIZE) % 2 {
+                0 => 255,
+                _ => 0,
+            };
+            [value, value, value, 255]
+        })
+        .collect();
+    DecodedImage {
+        name: "dpi_test/checker".to_string(),
+        rgba,
+        dimensions: (CHECKER_SIZE, CHECKER_SIZE),
+        format: PixelFormat::Rgba8,
+        meta: TextureMeta::default(),
+        mips: MipLevels::single((CHECKER_SIZE, CHECKER_SIZE)),
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn show_dpi_test(&mut self, visible: bool) {
+        if visible == self.dpi_test.visible {
+            return;
+        }
+        info!(
+            "{} the dpi test page",
+            if visible { "showing" } else { "hiding" }
+        );
+        self.dpi_test.visible = visible;
+
+        match visible {
+            true => self.create_dpi_test(),
+            false => {
+                let labels = std::mem::take(&mut self.dpi_test.labels);
+                for label in labels {
+                    self.remove_text(label.id);
+                }
+                if let Some((id, _)) = self.dpi_test.info.take() {
+                    self.remove_text(id);
+                }
+                for id in self.dpi_test.sprites.clone() {
+                    self.set_sprite_visible(id, false);
+                }
+            }
+        }
+    }
+
+    pub fn is_dpi_test_visible(&self) -> bool {
+        self.dpi_test.visible
+    }
+
+    // returns whether it is now shown
+    pub fn toggle_dpi_test(&mut self) -> bool {
+        self.show_dpi_test(!self.dpi_test.visible);
+        self.dpi_test.visible
+    }
+
+    fn create_dpi_test(&mut self) {
+        self.dpi_test.info = self
+            .add_text("", INFO_SIZE, 1.2)
+            .map(|id| (id, String::new()));
+
+        let mut labels = vec![
+            (
+                "1 physical pixel stripes, then 1 logical".to_string(),
+                12.0,
+                [0.0, STRIPES_TOP - 16.0],
+            ),
+            (
+                format!("{} physical pixels", RULER_LENGTH),
+                12.0,
+                [RULER_LENGTH + 10.0, RULERS_TOP],
+            ),
+            (
+                format!("{} logical pixels", RULER_LENGTH),
+                12.0,
+                [RULER_LENGTH + 10.0, RULERS_TOP + 24.0],
+            ),
+            (
+                "checker sprite at 1:1, then at the scale factor".to_string(),
+                12.0,
+                [0.0, SPRITES_TOP - 16.0],
+            ),
+        ];
+        let mut top = TEXT_TOP;
+        for size in FONT_SIZES {
+            labels.push((
+                format!("{} px: the quick brown fox", size),
+                size,
+                [0.0, top],
+            ));
+            top += size * 1.2 + 4.0;
+        }
+        self.dpi_test.labels = labels
+            .into_iter()
+            .filter_map(|(text, size, offset)| {
+                let id = self.add_text(&text, size, 1.2)?;
+                Some(DpiLabel { id, offset })
+            })
+            .collect();
+
+        // the sprites are made once and hidden with the page
+        if self.dpi_test.layer.is_none() {
+            // not an asset pool, so it stays out of texture_pool's ids
+            let textures = self.upload_pool(&LoadedPool {
+                id: usize::MAX,
+                images: vec![checker()],
+                sounds: Vec::new(),
+            });
+            let pool = self.loaded_pools.len();
+            self.loaded_pools.push(textures);
+            let layer = self.create_layer(i32::MAX, SortMode::Insertion);
+            let sprite = Sprite {
+                pool,
+                texture: 0,
+                pivot: Pivot::TopLeft,
+                ..Default::default()
+            };
+            self.dpi_test.sprites = (0..2)
+                .filter_map(|_| self.add_sprite(layer, sprite))
+                .collect();
+            self.dpi_test.layer = Some(layer);
+        }
+        for id in self.dpi_test.sprites.clone() {
+            self.set_sprite_visible(id, true);
+        }
+    }
+
+    // what the page says about the window and the monitor it's on
+    fn dpi_test_info(&self) -> String {
+        let scale = self.scale_factor();
+        let (width, height) = (self.surface_config.width, self.surface_config.height);
+        let mut info = format!(
+            "scale {:.2}, {}x{} physical, {:.0}x{:.0} logical",
+            scale,
+            width,
+            height,
+            width as f32 / scale,
+            height as f32 / scale
+        );
+        let Some(window) = &self.window else {
+            return info;
+        };
+
+        let mode = match window.fullscreen() {
+            Some(Fullscreen::Borderless(_)) => "borderless fullscreen",
+            Some(Fullscreen::Exclusive(_)) => "exclusive fullscreen",
+            None if !window.is_decorated() => "borderless window",
+            None => "window",
+        };
+        info.push_str(&format!(", {}", mode));
+        if let Some(monitor) = window.current_monitor() {
+            info.push_str(&format!(
+                " on {} at {:.2}",
+                monitor
+                    .name()
+                    .unwrap_or_else(|| "an unnamed monitor".to_string()),
+                monitor.scale_factor()
+            ));
+        }
+        info
+    }
+
+    // lines and positions follow the safe area and the scale factor every frame
+    pub(super) fn queue_dpi_test(&mut self) {
+        if !self.dpi_test.visible {
+            return;
+        }
+        let scale = self.scale_factor();
+        let [x, y, _, _] = self.safe_area();
+        // physical top left of the page, snapped so the stripes stay crisp
+        let origin = [(x + MARGIN * scale).round(), (y + MARGIN * scale).round()];
+        let logical =
+            |offset: [f32; 2]| [origin[0] / scale + offset[0], origin[1] / scale + offset[1]];
+
+        let info = self.dpi_test_info();
+        if let Some((id, shown)) = &mut self.dpi_test.info {
+            let id = *id;
+            if *shown != info {
+                *shown = info.clone();
+                self.set_text(id, &info);
+            }
+            self.set_text_position(id, Some(logical([0.0, 0.0])));
+        }
+        for index in 0..self.dpi_test.labels.len() {
+            let DpiLabel { id, offset } = self.dpi_test.labels[index];
+            self.set_text_position(id, Some(logical(offset)));
+        }
+
+        let sizes = [CHECKER_SIZE as f32, CHECKER_SIZE as f32 * scale];
+        let mut left = origin[0];
+        for (index, id) in self.dpi_test.sprites.clone().into_iter().enumerate() {
+            if let Some(sprite) = self.sprite_mut(id) {
+                sprite.position = [left, origin[1] + SPRITES_TOP * scale];
+                sprite.size = [sizes[index]; 2];
+            }
+            left += sizes[index] + 10.0 * scale;
+        }
+
+        let top = origin[1] + STRIPES_TOP * scale;
+        let mut left = origin[0];
+        for width in [1.0, scale] {
+            self.queue_stripes([left, top], width);
+            left += STRIPES as f32 * 2.0 * width + 10.0 * scale;
+        }
+
+        let top = origin[1] + RULERS_TOP * scale;
+        self.queue_ruler([origin[0], top], 1.0);
+        self.queue_ruler([origin[0], top + 24.0 * scale], scale);
+    }
+
+    // vertical stripes then horizontal ones, each width physical pixels wide
+    fn queue_stripes(&mut self, top_left: [f32; 2], width: f32) {
+        let extent = STRIPES as f32 * width;
+        for index in 0..STRIPES {
+            let color = if index % 2 == 0 { WHITE } else { BLACK };
+            let along = (index as f32 + 0.5) * width;
+            let [x, y] = top_left;
+            self.draw_screen_line([x + along, y], [x + along, y + extent], color, width);
+            let x = x + extent;
+            self.draw_screen_line([x, y + along], [x + extent, y + along], color, width);
+        }
+    }
+
+    // RULER_LENGTH units of unit physical pixels, a tick every 10 and a long one every 50
+    fn queue_ruler(&mut self, start: [f32; 2], unit: f32) {
+        let [x, y] = start;
+        let length = RULER_LENGTH * unit;
+        self.draw_screen_line([x, y + 0.5], [x + length, y + 0.5], WHITE, 1.0);
+        for tick in (0..=RULER_LENGTH as u32).step_by(10) {
+            let height = if tick % 50 == 0 { 12.0 } else { 6.0 };
+            let at = (x + tick as f32 * unit).round() + 0.5;
+            self.draw_screen_line([at, y], [at, y + height], WHITE, 1.0);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use log::{Level, error, info};
+use serde::Serialize;
+
+use crate::renderer::Renderer;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassKind {
+    Render,
+    Compute,
+    Custom, // a registered RenderPass, whatever it draws isn't seen here
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawCall {
+    Draw,
+    DrawIndexed,
+    DrawIndirect, // counts live on the gpu
+    Dispatch,
+    Text,  // glyphon's own draws
+    Imgui, // imgui-wgpu's own draws
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DrawRecord {
+    pub call: DrawCall,
+    pub pipeline: String,
+    pub bind_groups: Vec<String>, // by slot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertices: Option<[u32; 2]>, // indices for draw_indexed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instances: Option<[u32; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workgroups: Option<[u32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scissor: Option<[u32; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stencil_reference: Option<u32>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+impl DrawRecord {
+    pub fn new(call: DrawCall, pipeline: impl Into<String>) -> Self {
+        DrawRecord {
+            call,
+            pipeline: pipeline.into(),
+            bind_groups: Vec::new(),
+            vertices: None,
+            instances: None,
+            workgroups: None,
+            scissor: None,
+            stencil_reference: None,
+            note: String::new(),
+        }
+    }
+
+    pub fn bind_group(mut self, name: impl Into<String>) -> Self {
+        self.bind_groups.push(name.into());
+        self
+    }
+
+    pub fn vertices(mut self, range: Range<u32>) -> Self {
+        self.vertices = Some([range.start, range.end]);
+        self
+    }
+
+    pub fn instances(mut self, range: Range<u32>) -> Self {
+        self.instances = Some([range.start, range.end]);
+        self
+    }
+
+    pub fn workgroups(mut self, workgroups: [u32; 3]) -> Self {
+        self.workgroups = Some(workgroups);
+        self
+    }
+
+    pub fn scissor(mut self, rect: [u32; 4]) -> Self {
+        self.scissor = Some(rect);
+        self
+    }
+
+    pub fn stencil_reference(mut self, reference: u32) -> Self {
+        self.stencil_reference = Some(reference);
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = note.into();
+        self
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PassRecord {
+    pub group: String, // the debug groups it was recorded in, joined with /
+    pub label: String,
+    pub kind: PassKind,
+    pub target: String,
+    pub clears: bool,
+    pub draws: Vec<DrawRecord>,
+}
+
+// draws and dispatches by pipeline and bind group over the whole frame
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FrameUsage {
+    pub passes: usize,
+    pub draws: usize,
+    pub pipelines: BTreeMap<String, usize>,
+    pub bind_groups: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FrameReport {
+    pub frame: u64,
+    pub size: [u32; 2],
+    pub surface_format: String,
+    pub scene_format: String,
+    pub usage: FrameUsage,
+    pub passes: Vec<PassRecord>,
+}
+
+impl FrameReport {
+    fn count_usage(&mut self) {
+        let mut usage = FrameUsage {
+            passes: self.passes.len(),
+            ..Default::default()
+        };
+        for draw in self.passes.iter().flat_map(|pass| pass.draws.iter()) {
+            usage.draws += 1;
+            *usage.pipelines.entry(draw.pipeline.clone()).or_default() += 1;
+            for bind_group in draw.bind_groups.iter() {
+                *usage.bind_groups.entry(bind_group.clone()).or_default() += 1;
+            }
+        }
+        self.usage = usage;
+    }
+
+    // one line per pass and per draw, for reading without tools
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "frame {} at {}x{}, surface {} scene {}",
+            self.frame, self.size[0], self.size[1], self.surface_format, self.scene_format
+        );
+        let _ = writeln!(
+            text,
+            "{} passes, {} draws",
+            self.usage.passes, self.usage.draws
+        );
+        for pass in self.passes.iter() {
+            let load = if pass.clears { "clear" } else { "load" };
+            let _ = writeln!(
+                text,
+                "\n[{}] {} ({:?}) into {}, {}",
+                pass.group, pass.label, pass.kind, pass.target, load
+            );
+            for draw in pass.draws.iter() {
+                let _ = write!(text, "  {:?} {}", draw.call, draw.pipeline);
+                if !draw.bind_groups.is_empty() {
+                    let _ = write!(text, " [{}]", draw.bind_groups.join(", "));
+                }
+                if let Some([start, end]) = draw.vertices {
+                    let _ = write!(text, " vertices {}..{}", start, end);
+                }
+                if let Some([start, end]) = draw.instances {
+                    let _ = write!(text, " instances {}..{}", start, end);
+                }
+                if let Some([x, y, z]) = draw.workgroups {
+                    let _ = write!(text, " workgroups {}x{}x{}", x, y, z);
+                }
+                if let Some([x, y, w, h]) = draw.scissor {
+                    let _ = write!(text, " scissor {},{} {}x{}", x, y, w, h);
+                }
+                if let Some(reference) = draw.stencil_reference {
+                    let _ = write!(text, " stencil {}", reference);
+                }
+                if !draw.note.is_empty() {
+                    let _ = write!(text, ", {}", draw.note);
+                }
+                text.push('\n');
+            }
+        }
+        text.push_str("\npipelines\n");
+        for (pipeline, count) in self.usage.pipelines.iter() {
+            let _ = writeln!(text, "  {}: {}", pipeline, count);
+        }
+        text.push_str("bind groups\n");
+        for (bind_group, count) in self.usage.bind_groups.iter() {
+            let _ = writeln!(text, "  {}: {}", bind_group, count);
+        }
+        text
+    }
+
+    // toml unless the path ends in .txt
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let text = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("txt") => self.to_text(),
+            _ => toml::to_string(self)?,
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+struct Recording {
+    path: PathBuf,
+    report: FrameReport,
+    groups: Vec<String>,
+}
+
+// what one frame recorded, pass by pass, for reading offline. the recording
+// paths only take &self, so sprite batches can note their draws as they go
+#[derive(Default)]
+pub(super) struct FrameDump {
+    requested: Option<Option<PathBuf>>, // None picks a name from the frame
+    recording: RefCell<Option<Recording>>,
+}
+
+impl FrameDump {
+    fn record(&self, f: impl FnOnce(&mut Recording)) {
+        if let Some(recording) = self.recording.borrow_mut().as_mut() {
+            f(recording);
+        }
+    }
+
+    pub(super) fn push_group(&self, label: &str) {
+        self.record(|recording| recording.groups.push(label.to_string()));
+    }
+
+    pub(super) fn pop_group(&self) {
+        self.record(|recording| {
+            recording.groups.pop();
+        });
+    }
+
+    pub(super) fn pass(&self, label: &str, kind: PassKind, target: &str, clears: bool) {
+        self.record(|recording| {
+            recording.report.passes.push(PassRecord {
+                group: recording.groups.join("/"),
+                label: label.to_string(),
+                kind,
+                target: target.to_string(),
+                clears,
+                draws: Vec::new(),
+            });
+        });
+    }
+
+    // into the last pass, built only while recording
+    pub(super) fn draw(&self, draw: impl FnOnce() -> DrawRecord) {
+        self.record(|recording| match recording.report.passes.last_mut() {
+            Some(pass) => pass.draws.push(draw()),
+            None => error!("frame dump: a draw outside of any pass"),
+        });
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // writes the next frame's passes and draws to path, toml or .txt text.
+    // without a path it goes to frame-<index>.toml in the working directory
+    pub fn dump_next_frame(&mut self, path: Option<PathBuf>) {
+        self.frame_dump.requested = Some(path);
+    }
+
+    pub(super) fn begin_frame_dump(&mut self) {
+        let Some(path) = self.frame_dump.requested.take() else {
+            return;
+        };
+        let path =
+            path.unwrap_or_else(|| PathBuf::from(format!("frame-{}.toml", self.frame_index)));
+        let report = FrameReport {
+            frame: self.frame_index,
+            size: [self.surface_config.width, self.surface_config.height],
+            surface_format: format!("{:?}", self.surface_config.format),
+            scene_format: format!("{:?}", self.scene_format()),
+            usage: FrameUsage::default(),
+            passes: Vec::new(),
+        };
+        *self.frame_dump.recording.borrow_mut() = Some(Recording {
+            path,
+            report,
+            groups: Vec::new(),
+        });
+    }
+
+    pub(super) fn finish_frame_dump(&mut self) {
+        let Some(Recording {
+            path, mut report, ..
+        }) = self.frame_dump.recording.take()
+        else {
+            return;
+        };
+        report.count_usage();
+        match report.write(&path) {
+            Ok(()) => {
+                info!(
+                    "dumped frame {}, {} passes and {} draws, to {}",
+                    report.frame,
+                    report.usage.passes,
+                    report.usage.draws,
+                    path.display()
+                );
+                self.notify(
+                    format!("frame dumped to {}", path.display()),
+                    Level::Info,
+                    3.0,
+                );
+            }
+            Err(e) => {
+                error!("failed to dump frame to {}: {:#}", path.display(), e);
+                self.notify(format!("frame dump failed: {}", e), Level::Error, 5.0);
+            }
+        }
+    }
+}
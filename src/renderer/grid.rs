@@ -0,0 +1,265 @@
+use log::warn;
+
+use crate::renderer::Renderer;
+use crate::renderer::camera::SCREEN_CAMERA;
+
+const RULER_SIZE: f32 = 20.0; // physical pixels
+const MAJOR_TICK: f32 = 10.0;
+const MINOR_TICK: f32 = 4.0;
+
+// lines every spacing world units, drawn through the given camera. zooming out
+// multiplies the step by subdivisions whenever lines would get closer than
+// min_spacing on screen, zooming in divides it, and the finer level fades in
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GridOverlay {
+    pub camera: usize,
+    pub spacing: f32,      // world units between lines at zoom 1
+    pub subdivisions: u32, // minor lines per major cell
+    pub min_spacing: f32,  // physical pixels
+    pub rulers: bool,      // world coordinates along the top and left edges
+}
+
+impl Default for GridOverlay {
+    fn default() -> Self {
+        GridOverlay {
+            camera: SCREEN_CAMERA,
+            spacing: 32.0,
+            subdivisions: 4,
+            min_spacing: 8.0,
+            rulers: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct GridState {
+    overlay: GridOverlay,
+    visible: bool,
+}
+
+// a ruler label in physical pixels, drawn by imgui when there is one
+pub(super) struct GridLabel {
+    pub(super) position: [f32; 2],
+    pub(super) text: String,
+}
+
+struct GridLevel {
+    step: f32,   // world units between minor lines
+    factor: u32, // minor lines per major one
+    fade: f32,   // 0 when minor lines are at min_spacing, 1 a level later
+}
+
+// nearest multiple of step, step 0 leaves the value alone
+pub fn snap(value: f32, step: f32) -> f32 {
+    match step > 0.0 {
+        true => (value / step).round() * step,
+        false => value,
+    }
+}
+
+pub fn snap_point(point: [f32; 2], step: f32) -> [f32; 2] {
+    point.map(|value| snap(value, step))
+}
+
+// radians to the nearest step_degrees
+pub fn snap_angle(radians: f32, step_degrees: f32) -> f32 {
+    snap(radians.to_degrees(), step_degrees).to_radians()
+}
+
+impl GridOverlay {
+    fn level(&self, zoom: f32) -> Option<GridLevel> {
+        if self.spacing <= 0.0 || zoom <= 0.0 || self.min_spacing <= 0.0 {
+            return None;
+        }
+        let factor = self.subdivisions.max(2);
+        let scale = factor as f32;
+
+        let mut step = self.spacing;
+        // bounded so odd settings can't spin, zoom and spacing would have to span 2^64
+        for _ in 0..64 {
+            if step * zoom < self.min_spacing {
+                step *= scale;
+            } else if step / scale * zoom >= self.min_spacing {
+                step /= scale;
+            } else {
+                break;
+            }
+        }
+        let fade = (step * zoom - self.min_spacing) / (self.min_spacing * (scale - 1.0));
+        Some(GridLevel {
+            step,
+            factor,
+            fade: fade.clamp(0.0, 1.0),
+        })
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn set_grid_overlay(&mut self, overlay: GridOverlay) {
+        if self.cameras.get(overlay.camera).is_none() {
+            warn!("no camera {} for the grid", overlay.camera);
+            return;
+        }
+        self.grid.overlay = overlay;
+    }
+
+    pub fn grid_overlay(&self) -> GridOverlay {
+        self.grid.overlay
+    }
+
+    pub fn show_grid(&mut self, visible: bool) {
+        self.grid.visible = visible;
+    }
+
+    pub fn is_grid_visible(&self) -> bool {
+        self.grid.visible
+    }
+
+    // returns whether it is now shown
+    pub fn toggle_grid(&mut self) -> bool {
+        self.grid.visible = !self.grid.visible;
+        self.grid.visible
+    }
+
+    // world units between the finest lines at the grid camera's current zoom,
+    // whether or not the grid is shown
+    pub fn grid_step(&self) -> Option<f32> {
+        let camera = self.cameras.get(self.grid.overlay.camera)?;
+        Some(self.grid.overlay.level(camera.zoom)?.step)
+    }
+
+    // a world point moved to the nearest grid line crossing, for dragging things
+    // around the editor. unchanged when the grid has no step
+    pub fn snap_to_grid(&self, point: [f32; 2]) -> [f32; 2] {
+        match self.grid_step() {
+            Some(step) => snap_point(point, step),
+            None => point,
+        }
+    }
+
+    pub(super) fn queue_grid_lines(&mut self) {
+        if !self.grid.visible {
+            return;
+        }
+        let overlay = self.grid.overlay;
+        let Some(camera) = self.cameras.get(overlay.camera).copied() else {
+            return;
+        };
+        let Some(level) = overlay.level(camera.zoom) else {
+            return;
+        };
+
+        let theme = self.theme();
+        let size = self.viewport_size();
+        let minor = with_alpha(theme.border, theme.border[3] * level.fade);
+        let major = theme.text_muted;
+        // x axis red and y axis green, like most editors
+        let axes = [theme.debug_palette[0], theme.debug_palette[1]];
+        let top_left = camera.screen_to_world([0.0, 0.0]);
+        let bottom_right = camera.screen_to_world(size);
+
+        // axis 0 draws the vertical lines at each x, axis 1 the horizontal ones
+        for axis in 0..2 {
+            let first = (top_left[axis] / level.step).floor() as i64;
+            let last = (bottom_right[axis] / level.step).ceil() as i64;
+            for index in first..=last {
+                let world = index as f32 * level.step;
+                let screen = camera.world_to_screen([world, world])[axis];
+                let (color, width) = match index {
+                    0 => (axes[1 - axis], 2.0),
+                    _ if index % level.factor as i64 == 0 => (major, 1.0),
+                    _ if level.fade > 0.0 => (minor, 1.0),
+                    _ => continue,
+                };
+                let (start, end) = match axis {
+                    0 => ([screen, 0.0], [screen, size[1]]),
+                    _ => ([0.0, screen], [size[0], screen]),
+                };
+                self.draw_screen_line(start, end, color, width);
+            }
+        }
+
+        if overlay.rulers {
+            self.queue_ruler_lines(&level, size);
+        }
+    }
+
+    // bands along the top and left edge with a tick at every line
+    fn queue_ruler_lines(&mut self, level: &GridLevel, size: [f32; 2]) {
+        let Some(camera) = self.cameras.get(self.grid.overlay.camera).copied() else {
+            return;
+        };
+        let theme = self.theme();
+        let band = RULER_SIZE * 0.5;
+        self.draw_screen_line([0.0, band], [size[0], band], theme.background, RULER_SIZE);
+        self.draw_screen_line(
+            [band, RULER_SIZE],
+            [band, size[1]],
+            theme.background,
+            RULER_SIZE,
+        );
+
+        let top_left = camera.screen_to_world([RULER_SIZE, RULER_SIZE]);
+        let bottom_right = camera.screen_to_world(size);
+        for axis in 0..2 {
+            let first = (top_left[axis] / level.step).ceil() as i64;
+            let last = (bottom_right[axis] / level.step).floor() as i64;
+            for index in first..=last {
+                let world = index as f32 * level.step;
+                let screen = camera.world_to_screen([world, world])[axis];
+                let tick = match index % level.factor as i64 == 0 {
+                    true => MAJOR_TICK,
+                    false if level.fade > 0.0 => MINOR_TICK,
+                    false => continue,
+                };
+                let (start, end) = match axis {
+                    0 => ([screen, RULER_SIZE - tick], [screen, RULER_SIZE]),
+                    _ => ([RULER_SIZE - tick, screen], [RULER_SIZE, screen]),
+                };
+                self.draw_screen_line(start, end, theme.text_muted, 1.0);
+            }
+        }
+    }
+
+    // world coordinates of the major lines, next to their ruler ticks
+    pub(super) fn grid_labels(&self) -> Vec<GridLabel> {
+        let overlay = self.grid.overlay;
+        if !self.grid.visible || !overlay.rulers {
+            return Vec::new();
+        }
+        let Some(camera) = self.cameras.get(overlay.camera) else {
+            return Vec::new();
+        };
+        let Some(level) = overlay.level(camera.zoom) else {
+            return Vec::new();
+        };
+        let major = level.step * level.factor as f32;
+        let decimals = (-major.log10()).ceil().max(0.0) as usize;
+        let size = self.viewport_size();
+
+        let top_left = camera.screen_to_world([RULER_SIZE, RULER_SIZE]);
+        let bottom_right = camera.screen_to_world(size);
+        let mut labels = Vec::new();
+        for axis in 0..2 {
+            let first = (top_left[axis] / major).ceil() as i64;
+            let last = (bottom_right[axis] / major).floor() as i64;
+            for index in first..=last {
+                let world = index as f32 * major;
+                let screen = camera.world_to_screen([world, world])[axis];
+                let position = match axis {
+                    0 => [screen + 2.0, 0.0],
+                    _ => [0.0, screen + 2.0],
+                };
+                labels.push(GridLabel {
+                    position,
+                    text: format!("{:.*}", decimals, world),
+                });
+            }
+        }
+        labels
+    }
+}
+
+fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
+    [color[0], color[1], color[2], alpha]
+}
@@ -0,0 +1,176 @@
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+use crate::renderer::pipeline::PipelineType;
+
+// bright sprites and additive particles can go past white here and get
+// tonemapped back down instead of clipping
+pub(super) const HDR_SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// extended linear srgb, 1.0 is 80 nits. wgpu can't pick the hdr10 (pq) colorspace yet,
+// so this is what surfaces that advertise hdr hand out: scrgb on windows, edr on macos
+pub(super) const HDR_OUTPUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const SCRGB_WHITE_NITS: f32 = 80.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tonemapping {
+    #[default]
+    None, // clips at white
+    Reinhard, // soft rolloff, flattens contrast
+    Aces,     // filmic, the usual pick for hdr scenes
+}
+
+impl Tonemapping {
+    // matches grading.wgsl
+    pub(super) fn index(self) -> u32 {
+        match self {
+            Tonemapping::None => 0,
+            Tonemapping::Reinhard => 1,
+            Tonemapping::Aces => 2,
+        }
+    }
+}
+
+pub(super) struct Hdr {
+    scene: bool,
+    output: bool, // the surface is float, decided when the renderer is created
+    tonemapping: Tonemapping,
+    exposure: f32,    // in stops, on top of the lighting environment's
+    paper_white: f32, // nits of ui white on hdr output
+    peak: f32,        // nits the tonemapper rolls off towards on hdr output
+}
+
+impl Default for Hdr {
+    fn default() -> Self {
+        Hdr {
+            scene: false,
+            output: false,
+            tonemapping: Tonemapping::None,
+            exposure: 0.0,
+            paper_white: 200.0,
+            peak: 1000.0,
+        }
+    }
+}
+
+impl Hdr {
+    pub(super) fn with_output(output: bool) -> Self {
+        Hdr {
+            output,
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // renders the world to a float target, resolved by the grading pass
+    pub fn set_hdr(&mut self, enabled: bool) {
+        if self.hdr.scene == enabled {
+            return;
+        }
+        info!(
+            "{} hdr scene rendering",
+            if enabled { "enabling" } else { "disabling" }
+        );
+        self.hdr.scene = enabled;
+        self.rebuild_scene_pipelines();
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.hdr.scene
+    }
+
+    // whether the surface took the hdr format, only asked for on creation
+    pub fn is_hdr_output(&self) -> bool {
+        self.hdr.output
+    }
+
+    pub fn tonemapping(&self) -> Tonemapping {
+        self.hdr.tonemapping
+    }
+
+    pub fn set_tonemapping(&mut self, tonemapping: Tonemapping) {
+        self.hdr.tonemapping = tonemapping;
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.hdr.exposure
+    }
+
+    // in stops, e.g. a brightness setting or an eye adapting to the dark
+    pub fn set_exposure(&mut self, stops: f32) {
+        self.hdr.exposure = stops;
+    }
+
+    // only used on hdr output, sdr displays put white wherever the user set them
+    pub fn set_hdr_brightness(&mut self, paper_white_nits: f32, peak_nits: f32) {
+        if paper_white_nits <= 0.0 {
+            warn!("paper white of {} nits, ignoring it", paper_white_nits);
+            return;
+        }
+        if peak_nits < paper_white_nits {
+            warn!(
+                "peak of {} nits is below paper white, clamping to {}",
+                peak_nits, paper_white_nits
+            );
+        }
+        self.hdr.paper_white = paper_white_nits;
+        self.hdr.peak = peak_nits.max(paper_white_nits);
+    }
+
+    // what the world pipelines and scene textures render to
+    pub(super) fn scene_format(&self) -> wgpu::TextureFormat {
+        match self.hdr.scene {
+            true => HDR_SCENE_FORMAT,
+            false => self.surface_config.format,
+        }
+    }
+
+    // the grading pass runs even under neutral lighting, nothing else gets a float scene to the surface
+    pub(super) fn needs_tonemapping(&self) -> bool {
+        self.hdr.scene
+            || self.hdr.output
+            || self.hdr.tonemapping != Tonemapping::None
+            || self.hdr.exposure != 0.0
+    }
+
+    // scene exposure as a linear multiplier
+    pub(super) fn exposure_scale(&self, environment_stops: f32) -> f32 {
+        (environment_stops + self.hdr.exposure).exp2()
+    }
+
+    // white on the surface and the tonemapper's peak relative to it, 1 on sdr
+    pub(super) fn output_range(&self) -> (f32, f32) {
+        match self.hdr.output {
+            true => (
+                self.hdr.paper_white / SCRGB_WHITE_NITS,
+                self.hdr.peak / self.hdr.paper_white,
+            ),
+            false => (1.0, 1.0),
+        }
+    }
+
+    // sprite, distortion and scene texture formats catch up on their next use
+    fn rebuild_scene_pipelines(&mut self) {
+        self.pipelines
+            .retain(|pipeline_type, _| !pipeline_type.draws_scene());
+
+        match self.create_basic_2d_pipeline() {
+            Ok(pipeline) => {
+                self.pipelines.insert(PipelineType::Basic2D, pipeline);
+            }
+            Err(e) => error!("failed to create basic 2d render pipeline: {}", e),
+        }
+
+        if let Some(layout) = self.particle_bind_group_layout.clone() {
+            match self.create_pipeline(&PipelineType::Particles, &[&layout], &[]) {
+                Ok(pipeline) => {
+                    self.pipelines.insert(PipelineType::Particles, pipeline);
+                }
+                Err(e) => error!("failed to create particle render pipeline: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+#[cfg(any(feature = "golden-tests", feature = "bench"))]
+use log::{error, info};
+#[cfg(any(feature = "golden-tests", feature = "bench"))]
+use wgpu::MultisampleState;
+#[cfg(any(feature = "golden-tests", feature = "bench"))]
+use winit::dpi::PhysicalSize;
+
+use crate::renderer::Renderer;
+#[cfg(any(feature = "golden-tests", feature = "bench"))]
+use crate::renderer::{RenderTarget, SWAPCHAIN_FORMAT, request_device};
+
+pub(super) fn create_offscreen_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+// only the goldens and the bench render without a window
+#[cfg(any(feature = "golden-tests", feature = "bench"))]
+impl Renderer<'static> {
+    // renders into an offscreen texture instead of a window, no imgui
+    pub fn new_headless(width: u32, height: u32) -> Option<Self> {
+        info!("creating headless renderer");
+
+        let instance = wgpu::Instance::default();
+        let adapter =
+            match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })) {
+                Ok(adapter) => adapter,
+                Err(e) => {
+                    error!("failed to find graphical adapter: {}", e);
+                    return None;
+                }
+            };
+
+        let (device, queue) = request_device(&adapter)?;
+
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: SWAPCHAIN_FORMAT,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        let offscreen = create_offscreen_texture(&device, &surface_config);
+
+        let mut renderer = Self::build(
+            device,
+            queue,
+            adapter.get_info(),
+            surface_config,
+            RenderTarget {
+                surface: None,
+                window: None,
+                offscreen: Some(offscreen),
+            },
+            false,
+        );
+
+        renderer.text_renderer = Some(renderer.create_text_renderer(
+            MultisampleState::default(),
+            1.0,
+            PhysicalSize::new(width, height),
+            SWAPCHAIN_FORMAT,
+        ));
+
+        // windows get this from their first resize event
+        if let Some(text_renderer) = &mut renderer.text_renderer {
+            text_renderer
+                .viewport
+                .update(&renderer.queue, glyphon::Resolution { width, height });
+        }
+
+        info!("headless renderer created");
+        Some(renderer)
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn is_headless(&self) -> bool {
+        self.offscreen.is_some()
+    }
+
+    // reads the last rendered offscreen frame back as rgba, blocking until it's there
+    #[cfg(feature = "golden-tests")]
+    pub fn read_frame(&mut self) -> Option<image::RgbaImage> {
+        let Some(texture) = &self.offscreen else {
+            error!("[rf] only headless renderers can read frames back");
+            return None;
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        let id = self
+            .readbacks
+            .read_texture(&self.device, &mut encoder, texture, None)?;
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.wait_readback(id)?.to_rgba()
+    }
+}
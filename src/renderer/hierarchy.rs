@@ -0,0 +1,282 @@
+use imgui::{TreeNodeFlags, Ui};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+use crate::renderer::layer::Sprite;
+
+// world pixels a duplicate lands from its original, so it doesn't hide under it
+const DUPLICATE_OFFSET: [f32; 2] = [16.0, 16.0];
+
+// everything that makes up an entity: the sprite and what the registry and the
+// sheet know about it. pool and texture are ids, so snapshots only make sense in
+// the session that took them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub sprite: Sprite,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ClipboardContents {
+    entities: Vec<EntitySnapshot>,
+}
+
+// a row of the hierarchy panel
+pub(super) struct HierarchyRow {
+    id: usize,
+    label: String,
+}
+
+pub(super) struct HierarchyLayer {
+    id: usize,
+    camera: usize,
+    rows: Vec<HierarchyRow>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum HierarchyRequest {
+    Select(usize),
+    Copy(usize),
+    Duplicate(usize),
+    Paste(usize), // into a layer
+}
+
+// copied entities as toml, so they paste into any layer and survive the
+// originals being removed
+#[derive(Default)]
+pub(super) struct Hierarchy {
+    clipboard: Option<String>,
+}
+
+impl Hierarchy {
+    pub(super) fn has_clipboard(&self) -> bool {
+        self.clipboard.is_some()
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn snapshot_entity(&self, id: usize) -> Option<EntitySnapshot> {
+        let sprite = *self.sprite(id)?;
+        Some(EntitySnapshot {
+            sprite,
+            name: self.registry.name(id).map(str::to_string),
+            tags: self.registry.tags(id).map(str::to_string).collect(),
+            region: self.sprite_regions.get(&id).cloned(),
+        })
+    }
+
+    // a new sprite in the layer, a name that's taken gets a number after it
+    pub fn spawn_entity(&mut self, layer: usize, snapshot: &EntitySnapshot) -> Option<usize> {
+        let id = self.add_sprite(layer, snapshot.sprite)?;
+        if let Some(name) = &snapshot.name {
+            let name = match self.registry.is_taken(name) {
+                true => {
+                    // copies of "crate 2" go on to "crate 3"
+                    let base = match name.rsplit_once(' ') {
+                        Some((base, number)) if number.parse::<u32>().is_ok() => base,
+                        _ => name.as_str(),
+                    };
+                    (2..)
+                        .map(|n| format!("{} {}", base, n))
+                        .find(|candidate| !self.registry.is_taken(candidate))?
+                }
+                false => name.clone(),
+            };
+            self.registry.set_name(id, &name);
+        }
+        for tag in snapshot.tags.iter() {
+            self.registry.add_tag(id, tag);
+        }
+        if let Some(region) = &snapshot.region {
+            self.set_sprite_region(id, Some(region));
+        }
+        Some(id)
+    }
+
+    // replaces the clipboard, missing ids are skipped
+    pub fn copy_entities(&mut self, ids: &[usize]) -> bool {
+        let contents = ClipboardContents {
+            entities: ids
+                .iter()
+                .filter_map(|&id| self.snapshot_entity(id))
+                .collect(),
+        };
+        if contents.entities.is_empty() {
+            warn!("nothing to copy");
+            return false;
+        }
+        match toml::to_string(&contents) {
+            Ok(text) => {
+                self.hierarchy.clipboard = Some(text);
+                true
+            }
+            Err(e) => {
+                error!("failed to copy entities: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn copy_selection(&mut self) -> bool {
+        match self.selection {
+            Some(id) => self.copy_entities(&[id]),
+            None => false,
+        }
+    }
+
+    // the clipboard's entities offset by world pixels, the last one gets selected
+    pub fn paste_entities(&mut self, layer: usize, offset: [f32; 2]) -> Vec<usize> {
+        let Some(text) = &self.hierarchy.clipboard else {
+            return Vec::new();
+        };
+        let contents: ClipboardContents = match toml::from_str(text) {
+            Ok(contents) => contents,
+            Err(e) => {
+                error!("clipboard doesn't hold entities: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let ids: Vec<usize> = contents
+            .entities
+            .into_iter()
+            .filter_map(|mut snapshot| {
+                snapshot.sprite.position[0] += offset[0];
+                snapshot.sprite.position[1] += offset[1];
+                self.spawn_entity(layer, &snapshot)
+            })
+            .collect();
+        if let Some(&last) = ids.last() {
+            self.selection = Some(last);
+        }
+        ids
+    }
+
+    // a copy next to the selection in its layer, the clipboard is left alone
+    pub fn duplicate_selection(&mut self) -> Option<usize> {
+        let id = self.selection?;
+        let layer = self.sprite_layer(id)?;
+        let mut snapshot = self.snapshot_entity(id)?;
+        snapshot.sprite.position[0] += DUPLICATE_OFFSET[0];
+        snapshot.sprite.position[1] += DUPLICATE_OFFSET[1];
+        let duplicate = self.spawn_entity(layer, &snapshot)?;
+        self.selection = Some(duplicate);
+        Some(duplicate)
+    }
+
+    // the clipboard as text, e.g. for the system clipboard
+    pub fn entity_clipboard(&self) -> Option<&str> {
+        self.hierarchy.clipboard.as_deref()
+    }
+
+    // text from entity_clipboard, checked before it replaces the clipboard
+    pub fn set_entity_clipboard(&mut self, text: &str) -> bool {
+        if let Err(e) = toml::from_str::<ClipboardContents>(text) {
+            warn!("not pasting, the text isn't entities: {}", e);
+            return false;
+        }
+        self.hierarchy.clipboard = Some(text.to_string());
+        true
+    }
+
+    pub(super) fn hierarchy_layers(&self) -> Vec<HierarchyLayer> {
+        self.layers
+            .iter()
+            .enumerate()
+            .map(|(id, layer)| HierarchyLayer {
+                id,
+                camera: layer.camera,
+                rows: layer
+                    .instances
+                    .keys()
+                    .map(|&sprite| HierarchyRow {
+                        id: sprite,
+                        label: match self.registry.name(sprite) {
+                            Some(name) => format!("{} ({})", name, sprite),
+                            None => format!("sprite {}", sprite),
+                        },
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    // the panel's clicks, applied once imgui is done with the frame
+    pub(super) fn apply_hierarchy_request(&mut self, request: HierarchyRequest) {
+        match request {
+            HierarchyRequest::Select(id) => self.select_sprite(Some(id)),
+            HierarchyRequest::Copy(id) => {
+                self.copy_entities(&[id]);
+            }
+            HierarchyRequest::Duplicate(id) => {
+                self.selection = Some(id);
+                self.duplicate_selection();
+            }
+            HierarchyRequest::Paste(layer) => {
+                self.paste_entities(layer, DUPLICATE_OFFSET);
+            }
+        }
+    }
+}
+
+pub(super) fn hierarchy_window(
+    ui: &Ui,
+    layers: &[HierarchyLayer],
+    selection: Option<usize>,
+    can_paste: bool,
+) -> Option<HierarchyRequest> {
+    if layers.is_empty() {
+        return None;
+    }
+
+    let mut request = None;
+    ui.window("hierarchy")
+        .size([280.0, 360.0], imgui::Condition::FirstUseEver)
+        .position([1120.0, 100.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            if let Some(id) = selection {
+                if ui.small_button("copy") {
+                    request = Some(HierarchyRequest::Copy(id));
+                }
+                ui.same_line();
+                if ui.small_button("duplicate") {
+                    request = Some(HierarchyRequest::Duplicate(id));
+                }
+                ui.separator();
+            }
+
+            for layer in layers.iter() {
+                let label = format!(
+                    "layer {}, camera {}, {} sprites###layer{}",
+                    layer.id,
+                    layer.camera,
+                    layer.rows.len(),
+                    layer.id
+                );
+                let open = ui.collapsing_header(&label, TreeNodeFlags::empty());
+                if can_paste && ui.small_button(format!("paste##layer{}", layer.id)) {
+                    request = Some(HierarchyRequest::Paste(layer.id));
+                }
+                if !open {
+                    continue;
+                }
+                for row in layer.rows.iter() {
+                    let selected = selection == Some(row.id);
+                    if ui
+                        .selectable_config(format!("{}##sprite{}", row.label, row.id))
+                        .selected(selected)
+                        .build()
+                    {
+                        request = Some(HierarchyRequest::Select(row.id));
+                    }
+                }
+            }
+        });
+    request
+}
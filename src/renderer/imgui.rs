@@ -4,17 +4,22 @@ use imgui_winit_support::WinitPlatform;
 use log::info;
 use winit::event::{Event, WindowEvent};
 
+use crate::platform::clipboard::ImguiClipboard;
+use crate::renderer::theme::IMGUI_FONT_SIZE;
+
 pub(super) struct ImguiRenderer {
     pub context: imgui::Context,
     pub renderer: imgui_wgpu::Renderer,
     pub platform: WinitPlatform,
     pub demo_open: bool,
     pub last_cursor: Option<MouseCursor>,
+    pub theme_revision: Option<u64>, // the theme its style was last built from
 }
 
 #[derive(Debug)]
 pub(super) enum ImguiError {
     TextRendererNotInitialized,
+    NoWindow,
 }
 
 impl<'a> crate::renderer::Renderer<'a> {
@@ -26,17 +31,23 @@ impl<'a> crate::renderer::Renderer<'a> {
             None => Err(ImguiError::TextRendererNotInitialized)?,
         };
 
+        let window = match &self.window {
+            Some(w) => w,
+            None => Err(ImguiError::NoWindow)?,
+        };
+
         let mut context = imgui::Context::create();
         let mut platform = imgui_winit_support::WinitPlatform::new(&mut context);
         platform.attach_window(
             context.io_mut(),
-            &self.window,
+            window,
             imgui_winit_support::HiDpiMode::Default,
         );
         context.set_ini_filename(None);
+        context.set_clipboard_backend(ImguiClipboard);
 
-        let font_size = (13.0 * text_renderer.scale_factor) as f32;
-        context.io_mut().font_global_scale = (1.0 / text_renderer.scale_factor) as f32;
+        let font_size = IMGUI_FONT_SIZE * text_renderer.scale_factor;
+        context.io_mut().font_global_scale = 1.0 / text_renderer.scale_factor;
 
         context.fonts().add_font(&[FontSource::DefaultFontData {
             config: Some(imgui::FontConfig {
@@ -63,16 +74,48 @@ impl<'a> crate::renderer::Renderer<'a> {
             renderer,
             demo_open,
             last_cursor,
+            theme_revision: None,
         })
     }
 
+    // whether imgui wants the mouse and the keyboard for itself, as of its last frame
+    pub fn imgui_captures(&self) -> (bool, bool) {
+        match &self.imgui_renderer {
+            Some(imgui_renderer) => {
+                let io = imgui_renderer.context.io();
+                (io.want_capture_mouse, io.want_capture_keyboard)
+            }
+            None => (false, false),
+        }
+    }
+
+    // the font atlas and editor previews, what imgui keeps in vram. previews
+    // share their texture with the sprite that's inspected
+    pub(super) fn imgui_texture_bytes(&mut self) -> (u64, usize) {
+        let Some(imgui) = &mut self.imgui_renderer else {
+            return (0, 0);
+        };
+        let font = imgui.context.fonts().tex_id;
+        let ids = [Some(font), self.texture_editor.preview()];
+        let textures: Vec<&imgui_wgpu::Texture> = ids
+            .into_iter()
+            .flatten()
+            .filter_map(|id| imgui.renderer.textures.get(id))
+            .collect();
+        let bytes = textures
+            .iter()
+            .map(|texture| texture.width() as u64 * texture.height() as u64 * 4)
+            .sum();
+        (bytes, textures.len())
+    }
+
     pub fn handle_imgui_event(&mut self, event: &WindowEvent) {
-        if let Some(imgui_renderer) = &mut self.imgui_renderer {
+        if let (Some(imgui_renderer), Some(window)) = (&mut self.imgui_renderer, &self.window) {
             imgui_renderer.platform.handle_event::<WindowEvent>(
                 imgui_renderer.context.io_mut(),
-                &self.window,
+                window,
                 &Event::WindowEvent {
-                    window_id: self.window.id(),
+                    window_id: window.id(),
                     event: event.clone(),
                 },
             );
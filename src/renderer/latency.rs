@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use imgui::Ui;
+use log::info;
+
+use crate::renderer::Renderer;
+
+const HISTORY: usize = 240; // samples kept for the report
+
+// one input event from arriving to the present of the first frame it affected
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    waiting_ms: f32, // until the frame that handled it started rendering
+    total_ms: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LatencyReport {
+    pub samples: usize,
+    pub average_ms: f32,
+    pub min_ms: f32,
+    pub max_ms: f32,
+    pub p95_ms: f32,
+    pub waiting_ms: f32, // average part spent before the frame began
+}
+
+// stamps input events as the engine receives them, the os and the display add
+// their own delay on either side. off unless asked for, it costs a vec per frame
+#[derive(Default)]
+pub(super) struct LatencyProbe {
+    enabled: bool,
+    pending: Vec<Instant>, // received since the last frame started
+    in_flight: Vec<Instant>,
+    frame_start: Option<Instant>,
+    samples: VecDeque<Sample>,
+}
+
+impl LatencyProbe {
+    // events pending now were handled by the update that just ran
+    pub(super) fn begin_frame(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.in_flight.append(&mut self.pending);
+        self.frame_start = Some(Instant::now());
+    }
+
+    pub(super) fn record_present(&mut self) {
+        let Some(frame_start) = self.frame_start.take() else {
+            return;
+        };
+        let now = Instant::now();
+        for received in self.in_flight.drain(..) {
+            if self.samples.len() == HISTORY {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(Sample {
+                waiting_ms: (frame_start - received).as_secs_f32() * 1000.0,
+                total_ms: (now - received).as_secs_f32() * 1000.0,
+            });
+        }
+    }
+
+    fn report(&self) -> LatencyReport {
+        if self.samples.is_empty() {
+            return LatencyReport::default();
+        }
+
+        let mut totals: Vec<f32> = self.samples.iter().map(|sample| sample.total_ms).collect();
+        totals.sort_by(f32::total_cmp);
+        let count = totals.len();
+        let p95 = ((count as f32 * 0.95).ceil() as usize).clamp(1, count) - 1;
+
+        LatencyReport {
+            samples: count,
+            average_ms: totals.iter().sum::<f32>() / count as f32,
+            min_ms: totals[0],
+            max_ms: totals[count - 1],
+            p95_ms: totals[p95],
+            waiting_ms: self
+                .samples
+                .iter()
+                .map(|sample| sample.waiting_ms)
+                .sum::<f32>()
+                / count as f32,
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn set_latency_measurement(&mut self, enabled: bool) {
+        info!(
+            "{} input latency measurement",
+            if enabled { "starting" } else { "stopping" }
+        );
+        self.latency = LatencyProbe {
+            enabled,
+            ..Default::default()
+        };
+    }
+
+    pub fn is_measuring_latency(&self) -> bool {
+        self.latency.enabled
+    }
+
+    // called by the engine for every input event as it arrives
+    pub fn record_input(&mut self, received: Instant) {
+        if self.latency.enabled {
+            self.latency.pending.push(received);
+        }
+    }
+
+    pub fn latency_report(&self) -> LatencyReport {
+        self.latency.report()
+    }
+}
+
+// the statistics next to the settings they depend on
+pub(super) fn latency_window(ui: &Ui, probe: &LatencyProbe, present: &str) {
+    if !probe.enabled {
+        return;
+    }
+
+    let report = probe.report();
+    ui.window("input latency")
+        .size([300.0, 140.0], imgui::Condition::FirstUseEver)
+        .position([800.0, 260.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.text(present);
+            if report.samples == 0 {
+                ui.text("press keys or move the mouse");
+                return;
+            }
+            ui.text(format!(
+                "{:.2}ms avg, {:.2}ms p95 over {} events",
+                report.average_ms, report.p95_ms, report.samples
+            ));
+            ui.text(format!(
+                "{:.2}-{:.2}ms, {:.2}ms before the frame began",
+                report.min_ms, report.max_ms, report.waiting_ms
+            ));
+        });
+}
@@ -1,25 +1,512 @@
-use crate::assets::NvTexture;
+use std::collections::BTreeMap;
 
-pub struct Transform {
-    position: [f32; 3],
-    rotation: [f32; 3],
-    scale: [f32; 3],
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+use crate::renderer::camera::SCREEN_CAMERA;
+use crate::renderer::material::SpriteMaterial;
+use crate::renderer::outline::Outline;
+use crate::renderer::palette::SpritePalette;
+use crate::world::registry::Registry;
+use crate::world::spatial::SpatialGrid;
+
+// how sprites inside a layer are ordered before batching, later draws on top
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SortMode {
+    #[default]
+    Insertion, // the order sprites were added in
+    ZIndex,
+    YSort, // lower pivots draw in front, for top-down games
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 3] = [SortMode::Insertion, SortMode::ZIndex, SortMode::YSort];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortMode::Insertion => "insertion",
+            SortMode::ZIndex => "zindex",
+            SortMode::YSort => "ysort",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        SortMode::ALL.into_iter().find(|sort| sort.name() == name)
+    }
+}
+
+// how a sprite's color combines with what is already drawn
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Alpha,
+    Additive, // glow and fire
+    Multiply, // shadows and decals, only darkens
+    Screen,   // only lightens
+}
+
+// stencil masking between layers, e.g. a circle mask layer under a minimap layer.
+// the stencil is cleared every frame and shared by all layers
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LayerMask {
+    #[default]
+    None,
+    Write(u8), // sprites mark their opaque pixels with the value instead of drawing
+    Test(u8),  // sprites only draw where the stencil holds the value
+    // sprites mark where the distortion pass bends the screen, weighted by their
+    // alpha, instead of drawing. pair it with the visible water or heat layer
+    Distort,
+}
+
+// the point of a sprite that sits at its position and that it rotates around
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Pivot {
+    #[default]
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Custom([f32; 2]), // 0..1 from the top left
+    Texture,          // the pivot from the texture's import settings
+}
+
+impl Pivot {
+    // None for pivots that depend on the texture
+    pub fn offset(&self) -> Option<[f32; 2]> {
+        match self {
+            Pivot::Center => Some([0.5, 0.5]),
+            Pivot::TopLeft => Some([0.0, 0.0]),
+            Pivot::TopRight => Some([1.0, 0.0]),
+            Pivot::BottomLeft => Some([0.0, 1.0]),
+            Pivot::BottomRight => Some([1.0, 1.0]),
+            Pivot::Custom(offset) => Some(*offset),
+            Pivot::Texture => None,
+        }
+    }
+}
+
+// hidden sprites are skipped when batching, opacity scales the tint's alpha
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Visibility {
+    pub visible: bool,
+    pub opacity: f32, // 0..1
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility {
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl Visibility {
+    pub fn hidden() -> Self {
+        Visibility {
+            visible: false,
+            ..Default::default()
+        }
+    }
+
+    // whether anything of the sprite would end up on screen
+    pub fn is_drawn(&self) -> bool {
+        self.visible && self.opacity > 0.0
+    }
 }
 
+pub const FULL_UV: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+// physical pixels the culled view grows by, so outlines grown past a sprite's
+// bounds aren't cut off with it
+const CULL_MARGIN: f32 = 32.0;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Sprite {
-    transform: Transform,
-    texture: NvTexture,
+    pub pool: usize, // texture pool, see Renderer::texture_pool for an asset pool's
+    pub texture: usize,
+    pub position: [f32; 2], // of the pivot, in pixels from the top left
+    pub size: [f32; 2],
+    pub pivot: Pivot,
+    pub rotation: f32, // radians
+    pub color: [f32; 4],
+    pub z_index: i32,
+    pub visibility: Visibility,
+    pub blend: BlendMode,
+    pub outline: Option<Outline>, // only drawn on unmasked layers
+    pub uv: [f32; 4], // part of the texture drawn, offset and size. see set_sprite_region
+    pub material: SpriteMaterial,
+    pub palette: Option<SpritePalette>, // for indexed textures, see palette.rs
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Sprite {
+            pool: 0,
+            texture: 0,
+            position: [0.0, 0.0],
+            size: [64.0, 64.0],
+            pivot: Pivot::Center,
+            rotation: 0.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            z_index: 0,
+            visibility: Visibility::default(),
+            blend: BlendMode::Alpha,
+            outline: None,
+            uv: FULL_UV,
+            material: SpriteMaterial::default(),
+            palette: None,
+        }
+    }
+}
+
+impl Sprite {
+    // world space box around the rotated sprite, x, y, width, height
+    pub fn bounds(&self, pivot: [f32; 2]) -> [f32; 4] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+
+        for corner in [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]] {
+            let local = [
+                (corner[0] - pivot[0]) * self.size[0],
+                (corner[1] - pivot[1]) * self.size[1],
+            ];
+            let point = [
+                self.position[0] + local[0] * cos - local[1] * sin,
+                self.position[1] + local[0] * sin + local[1] * cos,
+            ];
+            for axis in 0..2 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+
+        [min[0], min[1], max[0] - min[0], max[1] - min[1]]
+    }
 }
 
 pub(super) struct Layer<I> {
-    pub instances: Vec<I>,
-    pub zindex: u32,
+    pub instances: BTreeMap<usize, I>,
+    pub zindex: i32,
+    pub sort: SortMode,
+    pub camera: usize,
+    pub clip: Option<[u32; 4]>, // x, y, width, height in physical pixels of the viewport
+    pub mask: LayerMask,
+    pub grid: SpatialGrid, // the instances' world bounds, updated every frame
+}
+
+impl Layer<Sprite> {
+    // sorts are stable, so ties keep insertion order
+    pub(super) fn sorted(&self) -> Vec<(usize, &Sprite)> {
+        self.sort(
+            self.instances
+                .iter()
+                .map(|(id, sprite)| (*id, sprite))
+                .collect(),
+        )
+    }
+
+    // like sorted, for only some of the sprites. the ids have to be ascending
+    pub(super) fn sorted_only(&self, ids: &[usize]) -> Vec<(usize, &Sprite)> {
+        self.sort(
+            ids.iter()
+                .filter_map(|id| Some((*id, self.instances.get(id)?)))
+                .collect(),
+        )
+    }
+
+    fn sort<'s>(&self, mut sprites: Vec<(usize, &'s Sprite)>) -> Vec<(usize, &'s Sprite)> {
+        match self.sort {
+            SortMode::Insertion => {}
+            SortMode::ZIndex => sprites.sort_by_key(|(_, sprite)| sprite.z_index),
+            SortMode::YSort => sprites.sort_by(|(_, a), (_, b)| {
+                a.position[1]
+                    .total_cmp(&b.position[1])
+                    .then(a.z_index.cmp(&b.z_index))
+            }),
+        }
+
+        sprites
+    }
 }
 
-impl<I> Layer<I> {
-    fn draw(&self, encoder: &mut wgpu::CommandEncoder) {
-        self.instances.iter().for_each(move |instance| {
-            println!("Drawing instance");
+impl<'a> Renderer<'a> {
+    // layers draw from low to high zindex
+    pub fn create_layer(&mut self, zindex: i32, sort: SortMode) -> usize {
+        let id = self.layers.len();
+        info!("creating sprite layer {} at zindex {}", id, zindex);
+
+        self.layers.push(Layer {
+            instances: BTreeMap::new(),
+            zindex,
+            sort,
+            camera: SCREEN_CAMERA,
+            clip: None,
+            mask: LayerMask::None,
+            grid: SpatialGrid::default(),
         });
+
+        id
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    // the layer's sprite bounds as of the last frame
+    pub fn layer_grid(&self, layer: usize) -> Option<&SpatialGrid> {
+        self.layers.get(layer).map(|layer| &layer.grid)
+    }
+
+    pub fn set_layer_sort(&mut self, layer: usize, sort: SortMode) {
+        match self.layers.get_mut(layer) {
+            Some(layer) => layer.sort = sort,
+            None => warn!("no sprite layer {}", layer),
+        }
+    }
+
+    pub fn set_layer_zindex(&mut self, layer: usize, zindex: i32) {
+        match self.layers.get_mut(layer) {
+            Some(layer) => layer.zindex = zindex,
+            None => warn!("no sprite layer {}", layer),
+        }
+    }
+
+    // sprites outside the rect are cut off, for scroll views and the like
+    pub fn set_layer_clip(&mut self, layer: usize, clip: Option<[u32; 4]>) {
+        match self.layers.get_mut(layer) {
+            Some(layer) => layer.clip = clip,
+            None => warn!("no sprite layer {}", layer),
+        }
+    }
+
+    pub fn set_layer_mask(&mut self, layer: usize, mask: LayerMask) {
+        match self.layers.get_mut(layer) {
+            Some(layer) => layer.mask = mask,
+            None => warn!("no sprite layer {}", layer),
+        }
+    }
+
+    pub fn add_sprite(&mut self, layer: usize, sprite: Sprite) -> Option<usize> {
+        let Some(layer) = self.layers.get_mut(layer) else {
+            warn!("no sprite layer {}", layer);
+            return None;
+        };
+
+        let id = self.next_sprite_id;
+        self.next_sprite_id += 1;
+
+        layer.instances.insert(id, sprite);
+        Some(id)
+    }
+
+    pub fn sprite(&self, id: usize) -> Option<&Sprite> {
+        self.layers
+            .iter()
+            .find_map(|layer| layer.instances.get(&id))
+    }
+
+    pub fn sprite_mut(&mut self, id: usize) -> Option<&mut Sprite> {
+        self.layers
+            .iter_mut()
+            .find_map(|layer| layer.instances.get_mut(&id))
+    }
+
+    pub fn sprite_layer(&self, id: usize) -> Option<usize> {
+        self.layers
+            .iter()
+            .position(|layer| layer.instances.contains_key(&id))
+    }
+
+    // the camera of the layer the sprite is in
+    pub fn sprite_camera(&self, id: usize) -> Option<usize> {
+        self.layers
+            .iter()
+            .find(|layer| layer.instances.contains_key(&id))
+            .map(|layer| layer.camera)
+    }
+
+    // the pivot a sprite is drawn with, resolving texture pivots
+    pub(super) fn sprite_pivot(&self, sprite: &Sprite) -> [f32; 2] {
+        sprite.pivot.offset().unwrap_or_else(|| {
+            self.texture_meta(sprite.pool, sprite.texture)
+                .map_or([0.5, 0.5], |meta| meta.pivot)
+        })
+    }
+
+    pub fn sprite_bounds(&self, id: usize) -> Option<[f32; 4]> {
+        let sprite = self.sprite(id)?;
+        Some(sprite.bounds(self.sprite_pivot(sprite)))
+    }
+
+    // a point on a sprite's quad in world space, 0..1 across it from the top left.
+    // follows the pivot and rotation it is drawn with
+    pub fn sprite_point(&self, id: usize, point: [f32; 2]) -> Option<[f32; 2]> {
+        let sprite = self.sprite(id)?;
+        let pivot = self.sprite_pivot(sprite);
+        let local = [0, 1].map(|axis| (point[axis] - pivot[axis]) * sprite.size[axis]);
+        let (sin, cos) = sprite.rotation.sin_cos();
+        Some([
+            sprite.position[0] + local[0] * cos - local[1] * sin,
+            sprite.position[1] + local[0] * sin + local[1] * cos,
+        ])
+    }
+
+    // bounds of every sprite in a layer, for building spatial indices
+    pub fn layer_bounds(&self, layer: usize) -> Vec<(usize, [f32; 4])> {
+        let Some(layer) = self.layers.get(layer) else {
+            warn!("no sprite layer {}", layer);
+            return Vec::new();
+        };
+
+        layer
+            .instances
+            .iter()
+            .map(|(id, sprite)| (*id, sprite.bounds(self.sprite_pivot(sprite))))
+            .collect()
+    }
+
+    // moves every layer's grid to where its sprites are now, for culling and picking
+    pub(super) fn update_sprite_grids(&mut self) {
+        for layer in 0..self.layers.len() {
+            let bounds = self.layer_bounds(layer);
+            self.layers[layer].grid.sync(bounds);
+        }
+    }
+
+    // the layer's sprites its camera sees, sorted for drawing
+    pub(super) fn visible_sprites<'l>(&self, layer: &'l Layer<Sprite>) -> Vec<(usize, &'l Sprite)> {
+        let (Some(camera), Some(view)) = (
+            self.cameras.get(layer.camera),
+            self.camera_view(layer.camera),
+        ) else {
+            return layer.sorted();
+        };
+        let margin = CULL_MARGIN / camera.zoom.max(f32::EPSILON);
+        let view = [
+            view[0] - margin,
+            view[1] - margin,
+            view[2] + margin * 2.0,
+            view[3] + margin * 2.0,
+        ];
+        layer.sorted_only(&layer.grid.query_rect(view))
+    }
+
+    pub fn set_sprite_visible(&mut self, id: usize, visible: bool) {
+        match self.sprite_mut(id) {
+            Some(sprite) => sprite.visibility.visible = visible,
+            None => warn!("no sprite {}", id),
+        }
+    }
+
+    pub fn set_sprite_opacity(&mut self, id: usize, opacity: f32) {
+        match self.sprite_mut(id) {
+            Some(sprite) => sprite.visibility.opacity = opacity.clamp(0.0, 1.0),
+            None => warn!("no sprite {}", id),
+        }
+    }
+
+    // draws one named region of a sprite sheet, None draws the whole texture.
+    // the name is kept, so reloading the sheet moves the sprite along with its region
+    pub fn set_sprite_region(&mut self, id: usize, region: Option<&str>) -> bool {
+        let Some(sprite) = self.sprite(id) else {
+            warn!("no sprite {}", id);
+            return false;
+        };
+
+        let uv = match region {
+            Some(name) => match self.atlas_uv(sprite.pool, sprite.texture, name) {
+                Some(uv) => uv,
+                None => {
+                    warn!("texture {} has no region {}", sprite.texture, name);
+                    return false;
+                }
+            },
+            None => FULL_UV,
+        };
+        match region {
+            Some(name) => self.sprite_regions.insert(id, name.to_string()),
+            None => self.sprite_regions.remove(&id),
+        };
+        if let Some(sprite) = self.sprite_mut(id) {
+            sprite.uv = uv;
+        }
+        true
+    }
+
+    // in pixels, for sizing a sprite to its region
+    pub fn atlas_region(&self, pool: usize, texture: usize, name: &str) -> Option<[u32; 4]> {
+        self.loaded_pools
+            .get(pool)?
+            .textures
+            .get(texture)?
+            .regions
+            .as_ref()?
+            .rect(name)
+    }
+
+    fn atlas_uv(&self, pool: usize, texture: usize, name: &str) -> Option<[f32; 4]> {
+        self.loaded_pools
+            .get(pool)?
+            .textures
+            .get(texture)?
+            .regions
+            .as_ref()?
+            .uv(name)
+    }
+
+    // after a sheet was reloaded, regions that went away fall back to the whole texture
+    pub(super) fn refresh_sprite_regions(&mut self, pool: usize, texture: usize) {
+        let regions: Vec<(usize, String)> = self
+            .sprite_regions
+            .iter()
+            .filter(|(id, _)| {
+                self.sprite(**id)
+                    .is_some_and(|sprite| sprite.pool == pool && sprite.texture == texture)
+            })
+            .map(|(id, name)| (*id, name.clone()))
+            .collect();
+
+        for (id, name) in regions {
+            let uv = self.atlas_uv(pool, texture, &name);
+            if uv.is_none() {
+                warn!("region {} of sprite {} is gone after reloading", name, id);
+                self.sprite_regions.remove(&id);
+            }
+            if let Some(sprite) = self.sprite_mut(id) {
+                sprite.uv = uv.unwrap_or(FULL_UV);
+            }
+        }
+    }
+
+    // names and tags of sprites, see world::registry
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn registry_mut(&mut self) -> &mut Registry {
+        &mut self.registry
+    }
+
+    pub fn find_sprite(&self, name: &str) -> Option<usize> {
+        self.registry.find_by_name(name)
+    }
+
+    pub fn remove_sprite(&mut self, id: usize) {
+        self.registry.remove(id);
+        self.sprite_regions.remove(&id);
+        if self.selection == Some(id) {
+            self.selection = None;
+        }
+        for layer in self.layers.iter_mut() {
+            if layer.instances.remove(&id).is_some() {
+                return;
+            }
+        }
     }
 }
@@ -0,0 +1,492 @@
+use bytemuck::{Pod, Zeroable};
+use log::{error, info, warn};
+
+use crate::assets::meta::TextureMeta;
+use crate::assets::{DecodedImage, MipLevels, PixelFormat};
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+use crate::util::tween::{Easing, Lerp, Tween};
+
+const IDENTITY_LUT_SIZE: u32 = 16;
+
+// the look of the world before ui is drawn over it, e.g. blue nights and warm evenings
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LightingEnvironment {
+    pub ambient: [f32; 3], // multiplies the scene, white leaves it as is
+    pub exposure: f32,     // in stops
+    pub grading: f32,      // 0..1 how much of the color grading lut applies
+}
+
+impl Default for LightingEnvironment {
+    fn default() -> Self {
+        LightingEnvironment {
+            ambient: [1.0, 1.0, 1.0],
+            exposure: 0.0,
+            grading: 0.0,
+        }
+    }
+}
+
+impl LightingEnvironment {
+    // neutral environments skip the grading pass
+    pub fn is_neutral(&self) -> bool {
+        *self == LightingEnvironment::default()
+    }
+}
+
+impl Lerp for LightingEnvironment {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        LightingEnvironment {
+            ambient: self.ambient.lerp(&other.ambient, t),
+            exposure: self.exposure.lerp(&other.exposure, t),
+            grading: self.grading.lerp(&other.grading, t),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct GradingParams {
+    ambient: [f32; 3],
+    exposure: f32,
+    grading: f32,
+    lut_size: f32,
+    scene_linear: u32,
+    output_linear: u32,
+    tonemapping: u32,
+    output_scale: f32,
+    peak: f32,
+    chromatic: f32,
+    flash: [f32; 4], // srgb color and how much of it covers the scene
+    fade: [f32; 4],
+}
+
+// gpu side of the grading pass, which also tonemaps. created the first time it's needed
+struct GradingPass {
+    layout: wgpu::BindGroupLayout,
+    params: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    scene: wgpu::Texture, // the world renders here, then gets graded onto the target
+    scene_view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+pub(super) struct Lighting {
+    environment: Tween<LightingEnvironment>,
+    lut: Option<(wgpu::TextureView, u32)>, // view and cells per axis, None for identity
+    pass: Option<GradingPass>,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            environment: Tween::new(LightingEnvironment::default()),
+            lut: None,
+            pass: None,
+        }
+    }
+}
+
+// srgb and float formats store linear values, unorm ones hold encoded color as is
+pub(super) fn is_linear(format: wgpu::TextureFormat) -> bool {
+    format.is_srgb() || format == wgpu::TextureFormat::Rgba16Float
+}
+
+// lut_size slices of lut_size squared laid out left to right, blue picks the slice
+fn identity_lut() -> DecodedImage {
+    let size = IDENTITY_LUT_SIZE;
+    let level = |cell: u32| (cell * 255 / (size - 1)) as u8;
+
+    let mut rgba = Vec::with_capacity((size * size * size * 4) as usize);
+    for g in 0..size {
+        for b in 0..size {
+            for r in 0..size {
+                rgba.extend([level(r), level(g), level(b), 255]);
+            }
+        }
+    }
+
+    DecodedImage {
+        name: "builtin/identity_lut".to_string(),
+        rgba,
+        dimensions: (size * size, size),
+        format: PixelFormat::Rgba8,
+        meta: TextureMeta::default(),
+        mips: MipLevels::single((size * size, size)),
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn lighting(&self) -> LightingEnvironment {
+        self.lighting.environment.value()
+    }
+
+    pub fn set_lighting(&mut self, environment: LightingEnvironment) {
+        self.lighting.environment.set(environment);
+    }
+
+    // eases from the current environment, e.g. over a day night cycle
+    pub fn transition_lighting(
+        &mut self,
+        environment: LightingEnvironment,
+        seconds: f32,
+        easing: Easing,
+    ) {
+        self.lighting
+            .environment
+            .start(environment, seconds, easing);
+    }
+
+    // a strip of n slices of n by n cells, as exported by most grading tools.
+    // None goes back to the identity lut
+    pub fn set_color_grading_lut(&mut self, path: Option<&str>) {
+        let Some(path) = path else {
+            self.lighting.lut = None;
+            self.rebind_grading();
+            return;
+        };
+
+        let image = match DecodedImage::open(path) {
+            Ok(image) => image,
+            Err(e) => {
+                error!("failed to load color grading lut {}: {}", path, e);
+                return;
+            }
+        };
+
+        let (width, height) = image.dimensions;
+        if height < 2 || width != height * height {
+            warn!(
+                "color grading lut {} is {}x{}, expected a strip of {} slices",
+                path, width, height, height
+            );
+            return;
+        }
+
+        info!("using color grading lut {}", path);
+        let view = self.create_lut_view(&image);
+        self.lighting.lut = Some((view, height));
+        self.rebind_grading();
+    }
+
+    pub(super) fn update_lighting(&mut self, dt_seconds: f32) {
+        self.lighting.environment.update(dt_seconds);
+    }
+
+    // points the frame at the scene texture while the world is drawn, returns the
+    // real target for apply_lighting. None when lighting is neutral, nothing needs
+    // tonemapping and no screen effect is showing
+    pub(super) fn begin_lighting(
+        &mut self,
+        context: &mut FrameContext,
+    ) -> Option<wgpu::TextureView> {
+        if self.lighting().is_neutral() && !self.needs_tonemapping() && !self.screen_fx.is_active()
+        {
+            return None;
+        }
+
+        let size = [self.surface_config.width, self.surface_config.height];
+        let format = self.scene_format();
+        let stale = self.lighting.pass.as_ref().is_none_or(|pass| {
+            let scene = pass.scene.size();
+            [scene.width, scene.height] != size || pass.scene.format() != format
+        });
+        if stale {
+            self.lighting.pass = self.create_grading_pass();
+        }
+        let pass = self.lighting.pass.as_ref()?;
+
+        // nothing guarantees the first world pass clears
+        self.frame_dump
+            .pass("Scene Clear Pass", PassKind::Render, "scene", true);
+        context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Scene Clear Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &pass.scene_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+        Some(std::mem::replace(
+            &mut context.view,
+            pass.scene_view.clone(),
+        ))
+    }
+
+    pub(super) fn apply_lighting(&mut self, context: &mut FrameContext, target: wgpu::TextureView) {
+        context.view = target;
+
+        let Some(pass) = &self.lighting.pass else {
+            return;
+        };
+        let pipeline = match self.pipelines.get(&PipelineType::Grading) {
+            Some(pipeline) => pipeline,
+            None => {
+                error!("No grading pipeline");
+                return;
+            }
+        };
+
+        let environment = self.lighting();
+        let (output_scale, peak) = self.output_range();
+        let params = GradingParams {
+            ambient: environment.ambient,
+            exposure: self.exposure_scale(environment.exposure),
+            grading: environment.grading.clamp(0.0, 1.0),
+            lut_size: self
+                .lighting
+                .lut
+                .as_ref()
+                .map_or(IDENTITY_LUT_SIZE, |(_, size)| *size) as f32,
+            scene_linear: is_linear(self.scene_format()) as u32,
+            output_linear: is_linear(self.surface_config.format) as u32,
+            tonemapping: self.tonemapping().index(),
+            output_scale,
+            peak,
+            chromatic: self.screen_fx.chromatic(),
+            flash: self.screen_fx.flash(),
+            fade: self.screen_fx.fade(),
+        };
+        self.queue
+            .write_buffer(&pass.params, 0, bytemuck::cast_slice(&[params]));
+
+        let mut render_pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Grading Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &pass.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        self.frame_dump
+            .pass("Grading Render Pass", PassKind::Render, "frame", false);
+        self.frame_dump.draw(|| {
+            DrawRecord::new(DrawCall::Draw, PipelineType::Grading.label())
+                .bind_group("grading")
+                .vertices(0..3)
+                .instances(0..1)
+                .note(format!("{:?} tonemapping", self.tonemapping()))
+        });
+    }
+
+    fn create_grading_pass(&mut self) -> Option<GradingPass> {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Grading Bind Group Layout"),
+                entries: &[
+                    texture_entry(0),
+                    texture_entry(1),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        if !self.pipelines.contains_key(&PipelineType::Grading) {
+            let pipeline = match self.create_pipeline(&PipelineType::Grading, &[&layout], &[]) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    error!("failed to create grading pipeline: {}", e);
+                    return None;
+                }
+            };
+            self.pipelines.insert(PipelineType::Grading, pipeline);
+        }
+
+        let scene = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Scene Texture"),
+            size: wgpu::Extent3d {
+                width: self.surface_config.width,
+                height: self.surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.scene_format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let scene_view = scene.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Scene View"),
+            ..Default::default()
+        });
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Grading Params Buffer"),
+            size: std::mem::size_of::<GradingParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Grading Lut Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let lut = match &self.lighting.lut {
+            Some((view, _)) => view.clone(),
+            None => self.create_lut_view(&identity_lut()),
+        };
+        let bind_group =
+            self.create_grading_bind_group(&layout, &scene_view, &lut, &sampler, &params);
+
+        Some(GradingPass {
+            layout,
+            params,
+            sampler,
+            scene,
+            scene_view,
+            bind_group,
+        })
+    }
+
+    // after the lut changed
+    fn rebind_grading(&mut self) {
+        let lut = match &self.lighting.lut {
+            Some((view, _)) => view.clone(),
+            None => self.create_lut_view(&identity_lut()),
+        };
+        let Some(pass) = &self.lighting.pass else {
+            return;
+        };
+
+        let bind_group = self.create_grading_bind_group(
+            &pass.layout,
+            &pass.scene_view,
+            &lut,
+            &pass.sampler,
+            &pass.params,
+        );
+        if let Some(pass) = &mut self.lighting.pass {
+            pass.bind_group = bind_group;
+        }
+    }
+
+    fn create_grading_bind_group(
+        &self,
+        layout: &wgpu::BindGroupLayout,
+        scene: &wgpu::TextureView,
+        lut: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        params: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Grading Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(scene),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(lut),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    // luts hold srgb encoded values and are sampled without decoding, float
+    // ones can grade past white for the hdr scene
+    fn create_lut_view(&self, image: &DecodedImage) -> wgpu::TextureView {
+        let (width, height) = image.dimensions;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&image.name),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: match image.format {
+                PixelFormat::Rgba8 => wgpu::TextureFormat::Rgba8Unorm,
+                PixelFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+            },
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &image.rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(image.format.bytes_per_pixel() * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(&format!("{}_view", image.name)),
+            ..Default::default()
+        })
+    }
+}
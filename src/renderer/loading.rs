@@ -0,0 +1,142 @@
+use bytemuck::{Pod, Zeroable};
+use log::error;
+
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct LoadingParams {
+    resolution: [f32; 2],
+    progress: f32,
+    _pad: f32,
+}
+
+pub(super) struct LoadingScreen {
+    params: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pub(super) progress: f32,
+}
+
+impl<'a> Renderer<'a> {
+    pub(super) fn create_loading_screen(&mut self) -> Option<LoadingScreen> {
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Loading Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let pipeline = match self.create_pipeline(&PipelineType::Loading, &[&layout], &[]) {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                error!("failed to create loading pipeline: {}", e);
+                return None;
+            }
+        };
+        self.pipelines.insert(PipelineType::Loading, pipeline);
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Loading Params Buffer"),
+            size: std::mem::size_of::<LoadingParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Loading Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            }],
+        });
+
+        Some(LoadingScreen {
+            params,
+            bind_group,
+            progress: 0.0,
+        })
+    }
+
+    // Some(progress) replaces the scene with the loading bar
+    pub fn set_loading_progress(&mut self, progress: Option<f32>) {
+        match progress {
+            Some(progress) => {
+                if self.loading_screen.is_none() {
+                    self.loading_screen = self.create_loading_screen();
+                }
+
+                if let Some(loading) = &mut self.loading_screen {
+                    loading.progress = progress.clamp(0.0, 1.0);
+                }
+            }
+            None => self.loading_screen = None,
+        }
+    }
+
+    pub(super) fn render_loading(&mut self, context: &mut FrameContext) {
+        let Some(loading) = &self.loading_screen else {
+            return;
+        };
+
+        let pipeline = match self.pipelines.get(&PipelineType::Loading) {
+            Some(pipeline) => pipeline,
+            None => {
+                error!("No loading pipeline");
+                return;
+            }
+        };
+
+        let params = LoadingParams {
+            resolution: [
+                self.surface_config.width as f32,
+                self.surface_config.height as f32,
+            ],
+            progress: loading.progress,
+            _pad: 0.0,
+        };
+        self.queue
+            .write_buffer(&loading.params, 0, bytemuck::cast_slice(&[params]));
+
+        let mut pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Loading Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &loading.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+
+        self.frame_dump
+            .pass("Loading Render Pass", PassKind::Render, "frame", true);
+        self.frame_dump.draw(|| {
+            DrawRecord::new(DrawCall::Draw, PipelineType::Loading.label())
+                .bind_group("loading")
+                .vertices(0..3)
+                .instances(0..1)
+        });
+    }
+}
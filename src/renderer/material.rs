@@ -0,0 +1,135 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+use crate::util::tween::{Easing, Tween};
+
+// per sprite shader parameters, written with the sprite's instance so every
+// change lands in the one upload prepare_sprites does each frame
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpriteMaterial {
+    pub flash: f32, // 0..1 towards flash_color, e.g. on a hit
+    pub flash_color: [f32; 3],
+    pub dissolve: f32,            // 0..1 of the sprite eaten away by noise
+    pub dissolve_color: [f32; 3], // the burning edge
+}
+
+impl Default for SpriteMaterial {
+    fn default() -> Self {
+        SpriteMaterial {
+            flash: 0.0,
+            flash_color: [1.0, 1.0, 1.0],
+            dissolve: 0.0,
+            dissolve_color: [1.0, 0.5, 0.1],
+        }
+    }
+}
+
+// the parameters that can be tweened
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaterialParam {
+    Flash,
+    Dissolve,
+}
+
+impl SpriteMaterial {
+    pub fn get(&self, param: MaterialParam) -> f32 {
+        match param {
+            MaterialParam::Flash => self.flash,
+            MaterialParam::Dissolve => self.dissolve,
+        }
+    }
+
+    pub fn set(&mut self, param: MaterialParam, value: f32) {
+        let value = value.clamp(0.0, 1.0);
+        match param {
+            MaterialParam::Flash => self.flash = value,
+            MaterialParam::Dissolve => self.dissolve = value,
+        }
+    }
+}
+
+// one parameter of one sprite easing to a value
+pub(super) struct MaterialTween {
+    sprite: usize,
+    param: MaterialParam,
+    tween: Tween<f32>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn sprite_material(&self, id: usize) -> Option<SpriteMaterial> {
+        self.sprite(id).map(|sprite| sprite.material)
+    }
+
+    // stops whatever was animating it
+    pub fn set_sprite_material(&mut self, id: usize, material: SpriteMaterial) {
+        self.stop_material_animation(id, None);
+        match self.sprite_mut(id) {
+            Some(sprite) => sprite.material = material,
+            None => warn!("no sprite {}", id),
+        }
+    }
+
+    // starts from the current value, replacing an animation of the same parameter
+    pub fn animate_material(
+        &mut self,
+        id: usize,
+        param: MaterialParam,
+        to: f32,
+        seconds: f32,
+        easing: Easing,
+    ) {
+        let Some(material) = self.sprite_material(id) else {
+            warn!("no sprite {}", id);
+            return;
+        };
+        let mut tween = Tween::new(material.get(param));
+        tween.start(to.clamp(0.0, 1.0), seconds, easing);
+        self.stop_material_animation(id, Some(param));
+        self.material_tweens.push(MaterialTween {
+            sprite: id,
+            param,
+            tween,
+        });
+    }
+
+    // full flash in color, fading out over seconds
+    pub fn flash_sprite(&mut self, id: usize, color: [f32; 3], seconds: f32) {
+        let Some(sprite) = self.sprite_mut(id) else {
+            warn!("no sprite {}", id);
+            return;
+        };
+        sprite.material.flash_color = color;
+        sprite.material.flash = 1.0;
+        self.animate_material(id, MaterialParam::Flash, 0.0, seconds, Easing::EaseOut);
+    }
+
+    pub fn is_material_animating(&self, id: usize) -> bool {
+        self.material_tweens.iter().any(|other| other.sprite == id)
+    }
+
+    // every parameter of the sprite when None, leaving the values where they are
+    pub fn stop_material_animation(&mut self, id: usize, param: Option<MaterialParam>) {
+        self.material_tweens
+            .retain(|other| other.sprite != id || param.is_some_and(|param| other.param != param));
+    }
+
+    pub(super) fn update_material_tweens(&mut self, dt_seconds: f32) {
+        if self.material_tweens.is_empty() {
+            return;
+        }
+        let mut tweens = std::mem::take(&mut self.material_tweens);
+        // removed sprites drop their animations
+        tweens.retain_mut(|animation| {
+            let value = animation.tween.update(dt_seconds);
+            let Some(sprite) = self.sprite_mut(animation.sprite) else {
+                return false;
+            };
+            sprite.material.set(animation.param, value);
+            !animation.tween.is_finished()
+        });
+        self.material_tweens = tweens;
+    }
+}
@@ -1,10 +1,12 @@
 use ::imgui as imgui_lib;
 use imgui_lib::Condition;
 
-use glyphon::{Metrics, TextArea, TextBounds};
-use log::{error, info};
+use bytemuck::{Pod, Zeroable};
+use glyphon::{TextArea, TextBounds};
+use log::{Level, error, info, warn};
 use rand::Rng;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use wgpu::util::DeviceExt;
@@ -12,28 +14,133 @@ use wgpu::{AdapterInfo, BindGroupLayout, BindGroupLayoutEntry, MultisampleState}
 use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
-use crate::assets::manager::AssetPool;
-use crate::assets::{NvTexture, NvTexturePool};
+use crate::assets::manager::{AssetPool, LoadedPool};
+use crate::assets::{DecodedImage, NvTexture, NvTexturePool, NvTextureSlot, WHITE_TEXTURE};
+use crate::crash;
+use crate::renderer::accessibility::{Accessibility, AccessibilityEvent};
+use crate::renderer::asset_browser::{AssetBrowser, asset_browser_window, format_bytes};
+use crate::renderer::camera::{Camera2D, CameraMove, SCREEN_CAMERA};
+use crate::renderer::compute::{ComputeDispatch, ComputePipeline};
+use crate::renderer::console::{ConsolePanel, console_window};
+use crate::renderer::curves::{CurveLibrary, curves_window};
+use crate::renderer::cvar_panel::{CvarPanel, cvars_window};
+use crate::renderer::debug_draw::DebugDraw;
+use crate::renderer::debug_view::{DebugView, debug_view_combo};
+use crate::renderer::distortion::DistortionState;
+use crate::renderer::dpi_test::DpiTest;
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, FrameDump, PassKind};
+use crate::renderer::grid::GridState;
+use crate::renderer::hdr::{HDR_OUTPUT_FORMAT, Hdr};
+use crate::renderer::headless::create_offscreen_texture;
+use crate::renderer::hierarchy::{Hierarchy, hierarchy_window};
 use crate::renderer::imgui::ImguiRenderer;
+use crate::renderer::latency::{LatencyProbe, latency_window};
+use crate::renderer::layer::{Layer, Sprite};
+use crate::renderer::lighting::Lighting;
+use crate::renderer::loading::LoadingScreen;
+use crate::renderer::material::MaterialTween;
+use crate::renderer::notifications::{Notifications, notifications_overlay};
+use crate::renderer::pacing::FramePacing;
+use crate::renderer::particles::ParticleSystem;
+use crate::renderer::passes::{CustomPasses, RenderStage};
+use crate::renderer::picking::PickBuffer;
 use crate::renderer::pipeline::PipelineType;
-use crate::renderer::text::TextRenderer;
-
+use crate::renderer::present::{SurfaceSupport, Vsync};
+use crate::renderer::profiler::{Profiler, profiler_window};
+use crate::renderer::readback::{ReadbackId, ReadbackQueue};
+use crate::renderer::recorder::Recorder;
+use crate::renderer::scene::{SceneEditor, scene_window};
+use crate::renderer::screen_fx::ScreenFx;
+use crate::renderer::sdf::SdfShapes;
+use crate::renderer::shader::ShaderDiagnostic;
+use crate::renderer::shapes::Shapes;
+use crate::renderer::simulation::Simulation;
+use crate::renderer::skybox::Skybox;
+use crate::renderer::sprites::SpriteRenderer;
+use crate::renderer::streaming::MipStreamer;
+use crate::renderer::text::{TextEntry, TextRenderer};
+use crate::renderer::texture_editor::{TextureEditor, texture_editor_window};
+use crate::renderer::timeline::{Timeline, timeline_window};
+use crate::renderer::trails::Trails;
+use crate::renderer::uploads::UploadQueue;
+use crate::renderer::viewport::{AspectRatio, Viewport};
+use crate::renderer::weather::WeatherState;
+use crate::tasks;
+use crate::ui::theme::Theme;
+use crate::world::registry::Registry;
+
+pub mod accessibility;
+pub mod asset_browser;
+mod budget;
+pub mod camera;
+pub mod compute;
+mod console;
+mod curves;
+pub mod cvar_panel;
+mod debug_draw;
+pub mod debug_view;
+pub mod distortion;
+mod dpi_test;
+pub mod frame_dump;
+pub mod grid;
+pub mod hdr;
+mod headless;
+pub mod hierarchy;
 mod imgui;
-mod layer;
+pub mod latency;
+pub mod layer;
+pub mod lighting;
+mod loading;
+pub mod material;
+mod notifications;
+pub mod outline;
+pub mod pacing;
+pub mod palette;
+pub mod particles;
+pub mod passes;
+mod picking;
 mod pipeline;
+pub mod polyline;
+pub mod present;
+mod profiler;
+pub mod readback;
+pub mod recorder;
+mod scene;
+mod screen_fx;
+pub mod sdf;
+pub mod shader;
+mod shapes;
+mod simulation;
+pub mod skybox;
+mod sprites;
+mod streaming;
 mod text;
+mod texture_editor;
+mod theme;
+mod timeline;
+pub mod trails;
+mod uploads;
+pub mod viewport;
+pub mod weather;
 
 const SWAPCHAIN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
 
 pub struct Renderer<'a> {
-    surface: wgpu::Surface<'a>,
+    surface: Option<wgpu::Surface<'a>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    window: Arc<Window>,
+    window: Option<Arc<Window>>,
+    offscreen: Option<wgpu::Texture>, // headless render target
     surface_config: wgpu::SurfaceConfiguration,
     loaded_pools: Vec<NvTexturePool>,
     bind_group_layouts: Vec<BindGroupLayout>,
     pipelines: HashMap<PipelineType, wgpu::RenderPipeline>,
+    compute_pipelines: Vec<ComputePipeline>,
+    compute_queue: Vec<ComputeDispatch>,
+
+    particle_systems: Vec<ParticleSystem>,
+    particle_update_pipeline: Option<usize>,
+    particle_bind_group_layout: Option<BindGroupLayout>,
 
     rng: rand::rngs::ThreadRng,
 
@@ -41,23 +148,88 @@ pub struct Renderer<'a> {
     index_buffer: wgpu::Buffer,
 
     pub adapter_info: AdapterInfo,
+    transparent: bool,
+    clear_color: wgpu::Color,
 
     // renderers
     text_renderer: Option<TextRenderer<'a>>,
     imgui_renderer: Option<ImguiRenderer>,
+    loading_screen: Option<LoadingScreen>,
 
     last_frame_time: Option<Instant>,
     delta_time: Duration,
+    fixed_delta: Option<Duration>,
+    texture_budget: Option<u64>, // bytes of pool textures kept in vram
+    over_texture_budget: bool,   // so the warning is logged once, not every frame
+    frame_index: u64,
+    surface_lost: bool, // set when the swapchain had to be rebuilt, e.g. after a display change
+
+    layers: Vec<Layer<Sprite>>,
+    cameras: Vec<Camera2D>,
+    camera_moves: Vec<CameraMove>,
+    material_tweens: Vec<MaterialTween>,
+    next_sprite_id: usize,
+    sprite_renderer: Option<SpriteRenderer>,
+    white_pool: Option<usize>,
+    discovered_pool: Option<usize>, // textures asked for by path, see discovered_texture
+    asset_pools: HashMap<usize, usize>, // asset pool id -> texture pool, see texture_pool
+    custom_passes: CustomPasses,
+    shader_errors: Vec<ShaderDiagnostic>, // shown until dismissed in imgui
+    capture_frame: bool,
+    pacing: FramePacing,
+    streamer: MipStreamer,
+    uploads: UploadQueue,
+    lighting: Lighting,
+    screen_fx: ScreenFx,
+    viewport: Viewport,
+    distortion: DistortionState,
+    accessibility: Accessibility,
+    theme: Theme,
+    theme_revision: u64,
+    timeline: Timeline,
+    registry: Registry,
+    profiler: Profiler,
+    hdr: Hdr,
+    vsync: Vsync,
+    surface_support: SurfaceSupport,
+    latency: LatencyProbe,
+    selection: Option<usize>, // sprite id outlined in the accent color
+    pending_resize: Option<PhysicalSize<u32>>,
+    recorder: Option<Recorder>,
+    asset_browser: AssetBrowser,
+    sprite_regions: HashMap<usize, String>, // sprite id -> sheet region, see set_sprite_region
+    readbacks: ReadbackQueue,
+    screenshots: Vec<ReadbackId>, // copied from the next frame
+    screenshot_saves: Vec<(ReadbackId, PathBuf)>,
+    pick_buffer: PickBuffer,
+    debug_draw: DebugDraw,
+    debug_view: DebugView,
+    shapes: Shapes,
+    sdf_shapes: SdfShapes,
+    skybox: Skybox,
+    trails: Trails,
+    weather: Vec<WeatherState>,
+    grid: GridState,
+    dpi_test: DpiTest,
+    hierarchy: Hierarchy,
+    simulation: Simulation,
+    scene: SceneEditor,
+    texture_editor: TextureEditor,
+    curves: CurveLibrary,
+    notifications: Notifications,
+    console: ConsolePanel,
+    cvar_panel: CvarPanel,
+    frame_dump: FrameDump,
 }
 
 struct FrameContext {
-    frame: wgpu::SurfaceTexture,
+    frame: Option<wgpu::SurfaceTexture>, // None when rendering offscreen
     view: wgpu::TextureView,
     encoder: wgpu::CommandEncoder,
 }
 
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Pod, Zeroable)]
 struct Vertex {
     position: [f32; 3],
     uv: [f32; 2],
@@ -84,8 +256,68 @@ const VERTICES: &[Vertex] = &[
 
 const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
 
+fn choose_alpha_mode(
+    alpha_modes: &[wgpu::CompositeAlphaMode],
+    transparent: bool,
+) -> wgpu::CompositeAlphaMode {
+    if !transparent {
+        return wgpu::CompositeAlphaMode::Auto;
+    }
+
+    let preferred = [
+        wgpu::CompositeAlphaMode::PreMultiplied,
+        wgpu::CompositeAlphaMode::PostMultiplied,
+        wgpu::CompositeAlphaMode::Inherit,
+    ];
+
+    match preferred.iter().find(|mode| alpha_modes.contains(mode)) {
+        Some(mode) => *mode,
+        None => {
+            warn!("surface does not support transparency, falling back to opaque");
+            wgpu::CompositeAlphaMode::Auto
+        }
+    }
+}
+
+fn request_device(adapter: &wgpu::Adapter) -> Option<(wgpu::Device, wgpu::Queue)> {
+    // show gpu info
+    let info = adapter.get_info();
+    info!(
+        "{} on {} {} with {}",
+        info.name, info.driver, info.driver_info, info.backend
+    );
+    crash::set_adapter_info(&info);
+
+    // connect to gpu
+    let device = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+        label: Some("nivalis device"),
+        // only for the wireframe debug view, which has a fallback
+        required_features: adapter.features() & wgpu::Features::POLYGON_MODE_LINE,
+        required_limits: wgpu::Limits::default(),
+        memory_hints: wgpu::MemoryHints::default(),
+        trace: wgpu::Trace::default(),
+    }));
+
+    match device {
+        Ok(device) => Some(device),
+        Err(e) => {
+            error!("failed to request graphical device: {}", e);
+            None
+        }
+    }
+}
+
+// where frames end up, a window surface or an offscreen texture
+struct RenderTarget<'a> {
+    surface: Option<wgpu::Surface<'a>>,
+    window: Option<Arc<Window>>,
+    offscreen: Option<wgpu::Texture>,
+}
+
 impl<'a> Renderer<'a> {
-    pub fn new(window: Arc<Window>) -> Self {
+    // a transparent renderer outputs premultiplied alpha for see-through windows,
+    // hdr output uses a float surface when the display advertises one
+    pub fn new(window: Arc<Window>, transparent: bool, hdr_output: bool) -> Self {
         info!("creating renderer");
 
         let instance = wgpu::Instance::default();
@@ -99,22 +331,7 @@ impl<'a> Renderer<'a> {
         }))
         .expect("failed to find graphical adapter");
 
-        // show gpu info
-        let info = adapter.get_info();
-        info!(
-            "{} on {} {} with {}",
-            info.name, info.driver, info.driver_info, info.backend
-        );
-
-        // connect to gpu
-        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
-            label: None,
-            required_features: wgpu::Features::empty(),
-            required_limits: wgpu::Limits::default(),
-            memory_hints: wgpu::MemoryHints::default(),
-            trace: wgpu::Trace::default(),
-        }))
-        .expect("failed to request graphical device");
+        let (device, queue) = request_device(&adapter).expect("failed to request graphical device");
 
         // create surface configuration
         let size = window.clone().inner_size();
@@ -123,123 +340,256 @@ impl<'a> Renderer<'a> {
         let mut surface_config = surface
             .get_default_config(&adapter, size.width, size.height)
             .unwrap();
-        surface_config.format = SWAPCHAIN_FORMAT;
-        surface_config.present_mode = wgpu::PresentMode::Mailbox;
+        let capabilities = surface.get_capabilities(&adapter);
+        let hdr_output = hdr_output && capabilities.formats.contains(&HDR_OUTPUT_FORMAT);
+        surface_config.format = match hdr_output {
+            true => HDR_OUTPUT_FORMAT,
+            false => SWAPCHAIN_FORMAT,
+        };
+        info!("using surface format {:?}", surface_config.format);
+        // lets the recorder copy frames out, not every surface allows it
+        if capabilities.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            surface_config.usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        let support = SurfaceSupport::from_capabilities(&capabilities);
+        surface_config.present_mode = support.present_mode(Vsync::default());
+
+        surface_config.alpha_mode = choose_alpha_mode(&capabilities.alpha_modes, transparent);
+        info!("using surface alpha mode {:?}", surface_config.alpha_mode);
 
         surface.configure(&device, &surface_config);
 
-        let mut bind_layouts = Vec::new();
-        bind_layouts.push(
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("NvTexturePool Bind Group Layout"),
-                entries: &[
-                    // texture binding
-                    BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    // sampler binding
-                    BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-            }),
+        let scale_factor = window.scale_factor() as f32;
+        let mut renderer = Self::build(
+            device,
+            queue,
+            adapter.get_info(),
+            surface_config,
+            RenderTarget {
+                surface: Some(surface),
+                window: Some(window),
+                offscreen: None,
+            },
+            transparent,
         );
+        renderer.hdr = Hdr::with_output(hdr_output);
+        renderer.surface_support = support;
+
+        let format = renderer.surface_config.format;
+        renderer.text_renderer = Some(renderer.create_text_renderer(
+            MultisampleState::default(),
+            scale_factor,
+            size,
+            format,
+        ));
+        renderer.imgui_renderer = Some(
+            renderer
+                .create_imgui_renderer()
+                .expect("failed to create imgui renderer"),
+        );
+        renderer.update_pacing_target();
+
+        info!("renderer created");
+        renderer
+    }
+
+    // shared by the windowed and headless constructors
+    fn build(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        adapter_info: AdapterInfo,
+        surface_config: wgpu::SurfaceConfiguration,
+        target: RenderTarget<'a>,
+        transparent: bool,
+    ) -> Self {
+        let bind_layouts =
+            vec![
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("NvTexturePool Bind Group Layout"),
+                    entries: &[
+                        // texture binding
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        // sampler binding
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                }),
+            ];
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
-            contents: unsafe {
-                std::slice::from_raw_parts(
-                    VERTICES.as_ptr() as *const u8,
-                    std::mem::size_of_val(VERTICES),
-                )
-            },
+            contents: bytemuck::cast_slice(VERTICES),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            contents: unsafe {
-                std::slice::from_raw_parts(
-                    INDICES.as_ptr() as *const u8,
-                    std::mem::size_of_val(INDICES),
-                )
-            },
+            contents: bytemuck::cast_slice(INDICES),
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        let scale_factor = window.clone().scale_factor() as f32;
-
         let mut renderer = Renderer {
-            surface: surface,
-            device: device,
-            queue: queue,
-            surface_config: surface_config,
-            window: window,
+            surface: target.surface,
+            device,
+            queue,
+            surface_config,
+            window: target.window,
+            offscreen: target.offscreen,
             loaded_pools: Vec::new(),
             bind_group_layouts: bind_layouts,
             pipelines: HashMap::new(),
+            compute_pipelines: Vec::new(),
+            compute_queue: Vec::new(),
+
+            particle_systems: Vec::new(),
+            particle_update_pipeline: None,
+            particle_bind_group_layout: None,
 
             vertex_buffer,
             index_buffer,
             rng: rand::rng(),
 
-            adapter_info: adapter.get_info(),
+            adapter_info,
+            transparent,
+            clear_color: match transparent {
+                true => wgpu::Color::TRANSPARENT,
+                false => wgpu::Color::BLACK,
+            },
 
             imgui_renderer: None,
             text_renderer: None,
+            loading_screen: None,
 
             last_frame_time: None,
             delta_time: Duration::from_secs_f32(0.0),
+            fixed_delta: None,
+            texture_budget: None,
+            over_texture_budget: false,
+            frame_index: 0,
+            surface_lost: false,
+            layers: Vec::new(),
+            cameras: vec![Camera2D::default()],
+            camera_moves: Vec::new(),
+            material_tweens: Vec::new(),
+            next_sprite_id: 0,
+            sprite_renderer: None,
+            white_pool: None,
+            discovered_pool: None,
+            asset_pools: HashMap::new(),
+            custom_passes: CustomPasses::default(),
+            shader_errors: Vec::new(),
+            capture_frame: false,
+            pacing: FramePacing::default(),
+            streamer: MipStreamer::default(),
+            uploads: UploadQueue::default(),
+            lighting: Lighting::default(),
+            screen_fx: ScreenFx::default(),
+            viewport: Viewport::default(),
+            distortion: DistortionState::default(),
+            accessibility: Accessibility::default(),
+            theme: Theme::default(),
+            theme_revision: 0,
+            timeline: Timeline::default(),
+            registry: Registry::default(),
+            profiler: Profiler::default(),
+            hdr: Hdr::default(),
+            vsync: Vsync::default(),
+            surface_support: SurfaceSupport::default(),
+            latency: LatencyProbe::default(),
+            selection: None,
+            pending_resize: None,
+            recorder: None,
+            asset_browser: AssetBrowser::default(),
+            sprite_regions: HashMap::new(),
+            readbacks: ReadbackQueue::default(),
+            screenshots: Vec::new(),
+            screenshot_saves: Vec::new(),
+            pick_buffer: PickBuffer::default(),
+            debug_draw: DebugDraw::default(),
+            debug_view: DebugView::Off,
+            shapes: Shapes::default(),
+            sdf_shapes: SdfShapes::default(),
+            skybox: Skybox::default(),
+            trails: Trails::default(),
+            weather: Vec::new(),
+            grid: GridState::default(),
+            dpi_test: DpiTest::default(),
+            hierarchy: Hierarchy::default(),
+            simulation: Simulation::default(),
+            scene: SceneEditor::default(),
+            texture_editor: TextureEditor::default(),
+            curves: CurveLibrary::default(),
+            notifications: Notifications::default(),
+            console: ConsolePanel::default(),
+            cvar_panel: CvarPanel::default(),
+            frame_dump: FrameDump::default(),
         };
 
         info!("creating pipelines");
 
         let basic_2d_pipeline = renderer
-            .create_pipeline(
-                renderer
-                    .bind_group_layouts
-                    .clone()
-                    .iter()
-                    .collect::<Vec<&BindGroupLayout>>()
-                    .as_slice(),
-                &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Vertex>() as u64,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2],
-                }],
-            )
+            .create_basic_2d_pipeline()
             .expect("failed to create basic 2d render pipeline");
 
         renderer
             .pipelines
             .insert(PipelineType::Basic2D, basic_2d_pipeline);
 
-        renderer.text_renderer = Some(renderer.create_text_renderer(
-            MultisampleState::default(),
-            scale_factor,
-            size,
-            SWAPCHAIN_FORMAT,
-        ));
-        renderer.imgui_renderer = Some(
-            renderer
-                .create_imgui_renderer()
-                .expect("failed to create imgui renderer"),
-        );
-
-        info!("renderer created");
         renderer
     }
 
+    fn create_basic_2d_pipeline(&mut self) -> Result<wgpu::RenderPipeline, wgpu::Error> {
+        let layouts = self.bind_group_layouts.clone();
+        self.create_pipeline(
+            &PipelineType::Basic2D,
+            layouts.iter().collect::<Vec<&BindGroupLayout>>().as_slice(),
+            &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<Vertex>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2],
+            }],
+        )
+    }
+
+    // the bench waits on the gpu between frames
+    #[cfg(feature = "bench")]
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    #[cfg(feature = "bench")]
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+    }
+
+    // deterministic frame times, used by headless rendering
+    #[cfg(feature = "golden-tests")]
+    pub fn set_fixed_delta(&mut self, delta: Option<Duration>) {
+        self.fixed_delta = delta;
+    }
+
+    // time between the last two frames
+    pub fn delta_time(&self) -> Duration {
+        self.delta_time
+    }
+
     pub fn insert_pool(&mut self, pool: &mut AssetPool) -> usize {
         info!("adding new asset pool");
 
@@ -253,7 +603,11 @@ impl<'a> Renderer<'a> {
             textures: pool
                 .textures
                 .iter()
-                .map(|path| NvTexture::from_name(&self.device, &self.queue, &layout, path))
+                .map(|path| {
+                    let image = DecodedImage::open_or_placeholder(path);
+                    let texture = NvTexture::from_image(&self.device, &self.queue, layout, &image);
+                    NvTextureSlot::resident(&image, texture).used_in(self.frame_index)
+                })
                 .collect(),
             layout: layout.clone(),
         });
@@ -261,11 +615,141 @@ impl<'a> Renderer<'a> {
         id
     }
 
+    // the texture pool sprites draw the asset pool's textures from. an asset
+    // pool inserted again replaces its old textures in the same texture pool.
+    // the goldens and the bench want every texture up before the next frame
+    #[cfg(any(feature = "golden-tests", feature = "bench"))]
+    pub fn insert_loaded_pool(&mut self, pool: LoadedPool) -> usize {
+        info!("uploading loaded asset pool {}", pool.id);
+
+        let textures = self.upload_pool(&pool);
+        match self.asset_pools.get(&pool.id) {
+            Some(&id) => {
+                self.loaded_pools[id] = textures;
+                id
+            }
+            None => {
+                let id = self.loaded_pools.len();
+                self.loaded_pools.push(textures);
+                self.asset_pools.insert(pool.id, id);
+                id
+            }
+        }
+    }
+
+    // asset pools load in whatever order they finish, so their texture pools
+    // don't share their ids. None until the asset pool was inserted or queued
+    pub fn texture_pool(&self, asset_pool: usize) -> Option<usize> {
+        self.asset_pools.get(&asset_pool).copied()
+    }
+
+    // a pool loaded again after an unload, sprites keep their texture ids. the
+    // new textures go up within the upload budget like queue_loaded_pool's
+    pub fn replace_loaded_pool(&mut self, id: usize, pool: LoadedPool) {
+        if id >= self.loaded_pools.len() {
+            warn!("no texture pool {} to replace, adding it instead", id);
+            self.queue_loaded_pool(pool);
+            return;
+        }
+        info!("queueing reloaded asset pool {} into {}", pool.id, id);
+        let asset_pool = pool.id;
+        self.queue_pool_uploads(id, pool);
+        for texture in 0..self.loaded_pools[id].textures.len() {
+            self.refresh_sprite_regions(id, texture);
+        }
+        self.notify(
+            format!("reloaded asset pool {}", asset_pool),
+            Level::Info,
+            2.0,
+        );
+    }
+
+    pub(super) fn upload_pool(&self, pool: &LoadedPool) -> NvTexturePool {
+        let layout = self
+            .bind_group_layouts
+            .first()
+            .expect("there is no bind group layout");
+
+        NvTexturePool {
+            textures: pool
+                .images
+                .iter()
+                .map(|image| {
+                    let texture = NvTexture::from_image(&self.device, &self.queue, layout, image);
+                    NvTextureSlot::resident(image, texture).used_in(self.frame_index)
+                })
+                .collect(),
+            layout: layout.clone(),
+        }
+    }
+
+    // pool and texture of the built in white texture, uploaded on first use
+    pub fn white_texture(&mut self) -> (usize, usize) {
+        if let Some(pool) = self.white_pool {
+            return (pool, 0);
+        }
+
+        let mut white = AssetPool::new(self.loaded_pools.len());
+        white.register_path(WHITE_TEXTURE);
+        let pool = self.insert_pool(&mut white);
+        self.white_pool = Some(pool);
+        (pool, 0)
+    }
+
+    // pool and texture of an asset by its path relative to the asset directory.
+    // nothing is decoded until a sprite first draws with it, asking again for
+    // the same path gives the same texture
+    pub fn discovered_texture(&mut self, path: &str) -> (usize, usize) {
+        let pool = match self.discovered_pool {
+            Some(pool) => pool,
+            None => {
+                let layout = self
+                    .bind_group_layouts
+                    .first()
+                    .expect("there is no bind group layout");
+                self.loaded_pools.push(NvTexturePool {
+                    textures: Vec::new(),
+                    layout: layout.clone(),
+                });
+                let pool = self.loaded_pools.len() - 1;
+                self.discovered_pool = Some(pool);
+                pool
+            }
+        };
+
+        let textures = &mut self.loaded_pools[pool].textures;
+        let index = match textures.iter().position(|slot| slot.name == path) {
+            Some(index) => index,
+            None => {
+                textures.push(NvTextureSlot::pending(path));
+                textures.len() - 1
+            }
+        };
+        (pool, index)
+    }
+
+    // interactive resizes send many events between frames, only the last one is
+    // applied when the next frame starts
     pub fn handle_resize(&mut self, size: PhysicalSize<u32>) {
         if size.height == 0 || size.width == 0 {
             return; // window size invalid
         }
 
+        self.pending_resize = Some(size);
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn apply_pending_resize(&mut self) {
+        if let Some(size) = self.pending_resize.take()
+            && [size.width, size.height] != [self.surface_config.width, self.surface_config.height]
+        {
+            self.apply_resize(size);
+        }
+    }
+
+    fn apply_resize(&mut self, size: PhysicalSize<u32>) {
         let text_renderer = match self.text_renderer {
             Some(ref mut text_renderer) => text_renderer,
             None => {
@@ -277,48 +761,144 @@ impl<'a> Renderer<'a> {
         // adjust surface config based on width and height
         self.surface_config.width = size.width;
         self.surface_config.height = size.height;
-        self.surface.configure(&self.device, &self.surface_config);
-        self.window.request_redraw();
-
-        // adjust text renderer viewport to new surface config
-        text_renderer.viewport.update(
-            &self.queue,
-            glyphon::Resolution {
-                width: self.surface_config.width,
-                height: self.surface_config.height,
-            },
-        );
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
+        if self.offscreen.is_some() {
+            self.offscreen = Some(create_offscreen_texture(&self.device, &self.surface_config));
+        }
 
         // adjust the text renderer scale and size
-        text_renderer.scale_factor = self.window.scale_factor() as f32;
+        if let Some(window) = &self.window {
+            text_renderer.scale_factor = window.scale_factor() as f32;
+        }
         text_renderer.physical_size = size.cast();
+        text_renderer.resize_entries();
+    }
 
-        let logical_width = size.width as f32 / text_renderer.scale_factor;
-
-        // resize font based on new surface config
-        for (_, b) in text_renderer.buffers.iter_mut() {
-            b.set_size(
-                &mut text_renderer.font_system,
-                Some(logical_width - 20.0),
-                None,
+    pub fn handle_redraw(&mut self) -> Option<()> {
+        self.latency.begin_frame();
+        self.apply_pending_resize();
+        self.poll_scene_file();
+        self.poll_scene_download();
+
+        // the capture has to wrap both recording and submission
+        let capturing = std::mem::take(&mut self.capture_frame);
+        if capturing {
+            info!(
+                "capturing frame {} for a graphics debugger",
+                self.frame_index
             );
-            b.shape_until_scroll(&mut text_renderer.font_system, false);
+            // no other capture is active, they only start here for one frame
+            unsafe { self.device.start_graphics_debugger_capture() };
+        }
+
+        self.begin_frame_dump();
+        let frame = self.record_frame();
+        self.finish_frame_dump();
+
+        if capturing {
+            unsafe { self.device.stop_graphics_debugger_capture() };
         }
+        self.profiler.finish_frame();
+        self.frame_index += 1;
+        frame
     }
 
-    pub fn handle_redraw(&mut self) -> Option<()> {
+    fn record_frame(&mut self) -> Option<()> {
         let mut context = self.begin_frame()?;
         let dt_seconds = self.delta_time.as_secs_f32();
-
-        self.render_image(&mut context);
-        self.display_text(&mut context, dt_seconds);
-        self.display_imgui(&mut context, dt_seconds);
+        self.profile("uploads", Self::upload_queued_textures);
+
+        if self.loading_screen.is_some() {
+            self.debug_group(&mut context, "loading", Self::render_loading);
+        } else {
+            self.record_snapshot();
+            self.update_weather();
+            if !self.is_time_travelling()
+                && let Some(tick) = self.simulation_tick()
+            {
+                self.update_particles(tick);
+            }
+            self.update_lighting(dt_seconds);
+            self.update_screen_fx(dt_seconds);
+            self.update_camera_moves(dt_seconds);
+            self.update_material_tweens(dt_seconds);
+            self.update_trails(dt_seconds);
+            self.queue_dpi_test();
+            let graded_target = self.begin_lighting(&mut context);
+            let distorted_target = self.begin_distortion(&mut context, dt_seconds);
+            self.debug_group(&mut context, "compute", Self::run_compute);
+            self.profile("spatial", Self::update_sprite_grids);
+            self.profile("batching", Self::prepare_sprites);
+            self.record_sprite_sizes();
+            self.profile("streaming", Self::stream_textures);
+            self.debug_group(&mut context, "background", Self::render_image);
+            self.debug_group(&mut context, "skybox", Self::render_skybox);
+            self.debug_group(&mut context, "before sprites", |renderer, context| {
+                renderer.run_custom_passes(context, RenderStage::BeforeSprites, dt_seconds)
+            });
+            self.debug_group(&mut context, "sprites", Self::render_sprites);
+            self.debug_group(&mut context, "shapes", Self::render_shapes);
+            self.debug_group(&mut context, "sdf shapes", Self::render_sdf_shapes);
+            self.debug_group(&mut context, "particles", Self::render_particles);
+            self.debug_group(&mut context, "after sprites", |renderer, context| {
+                renderer.run_custom_passes(context, RenderStage::AfterSprites, dt_seconds)
+            });
+            self.debug_group(&mut context, "picking", Self::render_pick_buffer);
+            if let Some(target) = distorted_target {
+                self.debug_group(&mut context, "distortion", |renderer, context| {
+                    renderer.apply_distortion(context, target)
+                });
+            }
+            if let Some(target) = graded_target {
+                self.debug_group(&mut context, "lighting", |renderer, context| {
+                    renderer.apply_lighting(context, target)
+                });
+            }
+            self.debug_group(&mut context, "letterbox", Self::render_letterbox);
+        }
+        self.debug_group(&mut context, "debug draw", Self::render_debug_draw);
+        self.debug_group(&mut context, "before ui", |renderer, context| {
+            renderer.run_custom_passes(context, RenderStage::BeforeUi, dt_seconds)
+        });
+        self.debug_group(&mut context, "text", |renderer, context| {
+            renderer.display_text(context, dt_seconds)
+        });
+        self.capture_screenshots(&mut context);
+        self.capture_recording(&mut context, false);
+        self.debug_group(&mut context, "imgui", |renderer, context| {
+            renderer.display_imgui(context, dt_seconds)
+        });
+        self.capture_recording(&mut context, true);
 
         self.end_frame(context);
+        self.enforce_texture_budget();
 
         Some(())
     }
 
+    // names the passes recorded by f in renderdoc and friends
+    fn debug_group(
+        &mut self,
+        context: &mut FrameContext,
+        label: &str,
+        f: impl FnOnce(&mut Self, &mut FrameContext),
+    ) {
+        context.encoder.push_debug_group(label);
+        self.frame_dump.push_group(label);
+        self.profile_begin(label);
+        f(self, context);
+        self.profile_end();
+        self.frame_dump.pop_group();
+        context.encoder.pop_debug_group();
+    }
+
+    // wraps the next frame in a renderdoc or xcode capture, when one is attached
+    pub fn capture_next_frame(&mut self) {
+        self.capture_frame = true;
+    }
+
     pub fn add_text(&mut self, text: &str, font_size: f32, line_height: f32) -> Option<usize> {
         let text_renderer = match &mut self.text_renderer {
             Some(t) => t,
@@ -328,53 +908,80 @@ impl<'a> Renderer<'a> {
             }
         };
 
-        let logical_width = text_renderer.physical_size.width as f32 / text_renderer.scale_factor;
-
-        let mut text_buffer = glyphon::Buffer::new(
-            &mut text_renderer.font_system,
-            Metrics::relative(font_size, line_height),
-        );
-        text_buffer.set_size(
-            &mut text_renderer.font_system,
-            Some(logical_width - 20.0),
-            None,
-        );
-        text_buffer.set_text(
-            &mut text_renderer.font_system,
-            text,
-            &text_renderer.base_font,
-            glyphon::Shaping::Advanced,
-        );
-        text_buffer.shape_until_scroll(&mut text_renderer.font_system, false);
-
-        let id = text_renderer.buffers.len();
-        text_renderer.buffers.insert(id.to_string(), text_buffer);
+        // shaped with everything else that changed before the next draw
+        let width = text_renderer.wrap_width();
+        let id = text_renderer.next_id;
+        text_renderer.next_id += 1;
+        text_renderer
+            .buffers
+            .insert(id, TextEntry::new(text, font_size, line_height, width));
 
         info!("adding text {} with id {}", text, id);
+        if self.is_accessibility_enabled() {
+            self.accessibility_event(AccessibilityEvent::TextShown {
+                id,
+                text: text.to_string(),
+            });
+        }
         Some(id)
     }
 
+    pub fn set_text(&mut self, id: usize, text: &str) {
+        let Some(text_renderer) = &mut self.text_renderer else {
+            return;
+        };
+
+        let Some(entry) = text_renderer.buffers.get_mut(&id) else {
+            error!("no text with id {}", id);
+            return;
+        };
+
+        // unchanged text every frame is free
+        if !entry.set_spans(vec![(text.to_string(), None)]) {
+            return;
+        }
+
+        if self.is_accessibility_enabled() {
+            self.accessibility_event(AccessibilityEvent::TextChanged {
+                id,
+                text: text.to_string(),
+            });
+        }
+    }
+
+    pub fn remove_text(&mut self, id: usize) {
+        let Some(text_renderer) = &mut self.text_renderer else {
+            return;
+        };
+        if text_renderer.buffers.remove(&id).is_some() && self.is_accessibility_enabled() {
+            self.accessibility_event(AccessibilityEvent::TextHidden { id });
+        }
+    }
+
     fn render_image(&mut self, context: &mut FrameContext) {
-        let pipeline = match self.pipelines.get(&PipelineType::Basic2D) {
-            Some(pipeline) => pipeline,
+        let count = match self.loaded_pools.first() {
+            Some(pool) => pool.textures.len(),
             None => {
-                error!("No render pipeline");
+                error!("No texture pool");
                 return;
             }
         };
 
-        let pool = match self.loaded_pools.get(0) {
-            Some(pool) => pool,
+        // reloads the texture if it was evicted
+        let index = self.rng.random_range(0..count);
+        self.touch_texture(0, index);
+        let extent = self.surface_config.width.max(self.surface_config.height);
+        self.record_screen_size(0, index, extent as f32);
+
+        let pipeline = match self.pipelines.get(&PipelineType::Basic2D) {
+            Some(pipeline) => pipeline,
             None => {
-                error!("No texture pool");
+                error!("No render pipeline");
                 return;
             }
         };
 
-        let texture = match pool
-            .textures
-            .get(self.rng.random_range(0..pool.textures.len()))
-        {
+        let texture = match self.texture(0, index) {
             Some(texture) => texture,
             None => {
                 error!("No texture");
@@ -390,12 +997,7 @@ impl<'a> Renderer<'a> {
                     view: &context.view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
@@ -405,13 +1007,26 @@ impl<'a> Renderer<'a> {
             });
 
         pass.set_pipeline(pipeline);
+        self.apply_viewport(&mut pass);
         pass.set_bind_group(0, &texture.bind_group, &[]);
         pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
         pass.draw_indexed(0..6, 0, 0..1);
+
+        self.frame_dump
+            .pass("Image Render Pass", PassKind::Render, "frame", true);
+        self.frame_dump.draw(|| {
+            DrawRecord::new(DrawCall::DrawIndexed, PipelineType::Basic2D.label())
+                .bind_group(format!("texture {}/{}", 0, index))
+                .vertices(0..6)
+                .instances(0..1)
+        });
     }
 
     fn display_text(&mut self, context: &mut FrameContext, _dt_seconds: f32) {
+        // text is hud, laid out in the viewport like the screen camera
+        let letterboxed = self.aspect_ratio() != AspectRatio::Fill;
+        let [viewport_x, viewport_y, viewport_width, viewport_height] = self.viewport();
         let text_renderer = match &mut self.text_renderer {
             Some(t) => t,
             None => {
@@ -420,18 +1035,38 @@ impl<'a> Renderer<'a> {
             }
         };
 
+        text_renderer.shape_dirty(self.frame_index);
+        text_renderer.viewport.update(
+            &self.queue,
+            glyphon::Resolution {
+                width: viewport_width as u32,
+                height: viewport_height as u32,
+            },
+        );
         let scale_factor = text_renderer.scale_factor;
+        let snap = self.cameras[SCREEN_CAMERA].is_snapped();
 
-        let left = 10.0 * scale_factor;
-        let mut top = 10.0 * scale_factor;
+        // unpositioned text is stacked from the top left
+        let stack_left = 10.0 * scale_factor;
+        let mut stack_top = 10.0 * scale_factor;
 
-        let bounds_left = left.floor() as i32;
-        let bounds_right = (text_renderer.physical_size.width - 10) as i32;
+        let bounds_right = viewport_width as i32 - 10;
 
         let text_areas: Vec<TextArea> = text_renderer
             .buffers
-            .iter()
-            .map(|(_, b)| {
+            .values()
+            .map(|entry| {
+                let b = &entry.buffer;
+                let (mut left, mut top) = match entry.position {
+                    Some([x, y]) => (x * scale_factor, y * scale_factor),
+                    None => (stack_left, stack_top),
+                };
+                if snap {
+                    left = left.round();
+                    top = top.round();
+                }
+                let bounds_left = left.floor() as i32;
+
                 let a = TextArea {
                     buffer: b,
                     left,
@@ -441,34 +1076,42 @@ impl<'a> Renderer<'a> {
                         left: bounds_left,
                         top: top.floor() as i32,
                         right: bounds_right,
-                        bottom: top.floor() as i32 + text_renderer.physical_size.height as i32,
+                        bottom: top.floor() as i32 + viewport_height as i32,
                     },
                     default_color: glyphon::Color::rgb(255, 255, 255),
                     custom_glyphs: &[],
                 };
 
-                let total_lines = b
-                    .layout_runs()
-                    .fold(0usize, |total_lines, _| total_lines + 1);
+                if entry.position.is_none() {
+                    let total_lines = b
+                        .layout_runs()
+                        .fold(0usize, |total_lines, _| total_lines + 1);
 
-                top += (total_lines as f32 * b.metrics().line_height + 5.0) * scale_factor;
+                    stack_top +=
+                        (total_lines as f32 * b.metrics().line_height + 5.0) * scale_factor;
+                }
 
                 a
             })
             .collect();
 
-        text_renderer
-            .renderer
-            .prepare(
-                &self.device,
-                &self.queue,
-                &mut text_renderer.font_system,
-                &mut text_renderer.atlas,
-                &text_renderer.viewport,
-                text_areas,
-                &mut text_renderer.swash_cache,
-            )
-            .unwrap();
+        text_renderer.atlas_usage.measure(
+            &mut text_renderer.font_system,
+            &mut text_renderer.swash_cache,
+            &text_areas,
+        );
+        // a full atlas drops this frame's text instead of taking the game down
+        if let Err(e) = text_renderer.renderer.prepare(
+            &self.device,
+            &self.queue,
+            &mut text_renderer.font_system,
+            &mut text_renderer.atlas,
+            &text_renderer.viewport,
+            text_areas,
+            &mut text_renderer.swash_cache,
+        ) {
+            error!("failed to prepare text: {}", e);
+        }
 
         let mut pass = context
             .encoder
@@ -486,16 +1129,62 @@ impl<'a> Renderer<'a> {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
+        if letterboxed {
+            pass.set_viewport(
+                viewport_x,
+                viewport_y,
+                viewport_width,
+                viewport_height,
+                0.0,
+                1.0,
+            );
+        }
 
         text_renderer
             .renderer
             .render(&text_renderer.atlas, &text_renderer.viewport, &mut pass)
             .unwrap();
+
+        self.frame_dump
+            .pass("Text Render Pass", PassKind::Render, "frame", false);
+        self.frame_dump.draw(|| {
+            DrawRecord::new(DrawCall::Text, "glyphon")
+                .note(format!("{} text buffers", text_renderer.buffers.len()))
+        });
     }
 
     fn display_imgui(&mut self, context: &mut FrameContext, dt_seconds: f32) {
-        let Some(imgui) = &mut self.imgui_renderer else {
-            return; // not ready
+        self.apply_imgui_theme();
+        let theme = self.theme();
+        let recording = self.recording_status();
+        let text_atlas = self.text_atlas_stats();
+        let (shape_hits, shape_misses, shapes) = self.text_shape_stats();
+        let (imgui_bytes, imgui_textures) = self.imgui_texture_bytes();
+        let (texture_bytes, texture_budget) = (self.texture_memory(), self.texture_budget());
+        let pacing = self.pacing_report();
+        let streaming = self.streaming_textures();
+        let readbacks = self.readbacks.pending_count();
+        let (uploads, upload_budget) = (self.pending_uploads(), self.upload_budget());
+        let present = format!(
+            "present: {:?} for {:?} vsync, latency {}, alpha {:?}",
+            self.present_mode(),
+            self.vsync(),
+            self.frame_latency(),
+            self.alpha_mode()
+        );
+        let hdr = format!(
+            "hdr scene {}, output {}, {:?} tonemapping, exposure {:.1}",
+            self.is_hdr(),
+            self.is_hdr_output(),
+            self.tonemapping(),
+            self.exposure()
+        );
+        let grid_labels = self.grid_labels();
+        let hierarchy = self.hierarchy_layers();
+        let scene = self.scene_status();
+        self.prepare_texture_preview();
+        let (Some(imgui), Some(window)) = (&mut self.imgui_renderer, &self.window) else {
+            return; // not ready or headless
         };
 
         // update imgui's dt time
@@ -507,21 +1196,128 @@ impl<'a> Renderer<'a> {
         // preparing frame
         imgui
             .platform
-            .prepare_frame(imgui.context.io_mut(), &self.window)
+            .prepare_frame(imgui.context.io_mut(), window)
             .expect("Failed to prepare frame");
 
         // draw ui
         let ui = imgui.context.frame();
+        let timeline_request;
+        let hierarchy_request;
+        let scene_request;
+        let texture_editor_request;
+        let mut simulation_request = None;
         {
+            if !grid_labels.is_empty() {
+                let draw_list = ui.get_background_draw_list();
+                let [scale_x, scale_y] = ui.io().display_framebuffer_scale;
+                for label in grid_labels.iter() {
+                    let [x, y] = label.position;
+                    draw_list.add_text([x / scale_x, y / scale_y], theme.text_muted, &label.text);
+                }
+            }
+
+            notifications_overlay(ui, &mut self.notifications, &theme);
+
             let window = ui.window("nivalis debug");
             window
                 .movable(true)
-                .size([300.0, 100.0], Condition::FirstUseEver)
+                .size([300.0, 150.0], Condition::FirstUseEver)
                 .position([800.0, 100.0], Condition::FirstUseEver)
                 .build(|| {
+                    let [average, jitter, target, ..] = theme.debug_palette;
                     ui.text("we all love imgui");
                     ui.text(format!("Frametime: {dt_seconds:?}"));
+                    ui.text_colored(
+                        average,
+                        format!(
+                            "pacing: {:.2}ms avg, {:.2}ms jitter, {:.2}-{:.2}ms",
+                            pacing.average_ms, pacing.jitter_ms, pacing.min_ms, pacing.max_ms
+                        ),
+                    );
+                    match pacing.target_ms {
+                        Some(target_ms) => ui.text_colored(
+                            target,
+                            format!(
+                                "target {:.2}ms, {} stutters, {} missed vsyncs",
+                                target_ms, pacing.stutters, pacing.missed_intervals
+                            ),
+                        ),
+                        None => ui.text_colored(
+                            jitter,
+                            format!("refresh unknown, {} stutters", pacing.stutters),
+                        ),
+                    }
+                    ui.text(&present);
+                    ui.text(&hdr);
+                    if let Some(status) = recording {
+                        ui.text_colored(
+                            theme.error,
+                            format!(
+                                "recording {:.1}s, {} frames, {} dropped",
+                                status.seconds, status.frames, status.dropped
+                            ),
+                        );
+                    }
+                    if let Some(atlas) = text_atlas {
+                        let color = match atlas.is_nearly_full() {
+                            true => theme.warning,
+                            false => theme.text,
+                        };
+                        for (name, page) in [("mask", atlas.mask), ("color", atlas.color)] {
+                            ui.text_colored(
+                                color,
+                                format!(
+                                    "text atlas {}: {}px, {} glyphs, {:.0}% full",
+                                    name,
+                                    page.size,
+                                    page.glyphs,
+                                    page.fill * 100.0
+                                ),
+                            );
+                        }
+                    }
+                    ui.text(format!(
+                        "text shaping: {} reused, {} shaped, {} kept",
+                        shape_hits, shape_misses, shapes
+                    ));
+                    ui.text(format!(
+                        "imgui textures: {}, {}",
+                        imgui_textures,
+                        format_bytes(imgui_bytes)
+                    ));
+                    match texture_budget {
+                        Some(budget) => ui.text(format!(
+                            "pool textures: {} of {}",
+                            format_bytes(texture_bytes),
+                            format_bytes(budget)
+                        )),
+                        None => ui.text(format!("pool textures: {}", format_bytes(texture_bytes))),
+                    }
+                    if streaming > 0 {
+                        ui.text(format!("streaming {} textures", streaming));
+                    }
+                    if readbacks > 0 {
+                        ui.text(format!("{} readbacks in flight", readbacks));
+                    }
+                    if uploads > 0 {
+                        match upload_budget {
+                            Some(budget) => ui.text(format!(
+                                "{} uploads queued, {:.1}ms a frame",
+                                uploads,
+                                budget.as_secs_f64() * 1000.0
+                            )),
+                            None => ui.text(format!("{} uploads queued", uploads)),
+                        }
+                    }
+                    let tasks = tasks::pool();
+                    ui.text(format!(
+                        "tasks: {} compute, {} io threads",
+                        tasks.compute_threads(),
+                        tasks.io_threads()
+                    ));
+                    debug_view_combo(ui, &mut self.debug_view);
                     ui.separator();
+                    simulation_request = self.simulation.controls(ui);
                     let mouse_pos = ui.io().mouse_pos;
                     ui.text(format!(
                         "position: ({:.1},{:.1})",
@@ -530,19 +1326,54 @@ impl<'a> Renderer<'a> {
                 });
 
             ui.show_metrics_window(&mut imgui.demo_open);
+            timeline_request = timeline_window(ui, &self.timeline);
+            profiler_window(ui, &mut self.profiler);
+            latency_window(ui, &self.latency, &present);
+            asset_browser_window(ui, &mut self.asset_browser);
+            hierarchy_request = hierarchy_window(
+                ui,
+                &hierarchy,
+                self.selection,
+                self.hierarchy.has_clipboard(),
+            );
+            scene_request = scene_window(ui, scene.as_ref(), &mut self.scene);
+            texture_editor_request =
+                texture_editor_window(ui, &mut self.texture_editor, &self.loaded_pools, &theme);
+            curves_window(ui, &mut self.curves, &theme);
+            cvars_window(ui, &mut self.cvar_panel);
+
+            if !self.shader_errors.is_empty() {
+                let mut open = true;
+                ui.window("shader errors")
+                    .opened(&mut open)
+                    .size([600.0, 300.0], Condition::FirstUseEver)
+                    .position([100.0, 100.0], Condition::FirstUseEver)
+                    .build(|| {
+                        for diagnostic in self.shader_errors.iter() {
+                            ui.text_colored(theme.error, diagnostic.to_string());
+                            ui.text(&diagnostic.report);
+                            ui.separator();
+                        }
+                    });
+                if !open {
+                    self.shader_errors.clear();
+                }
+            }
+
+            console_window(ui, &mut self.console, &theme);
         }
 
         // update cursor position
         if imgui.last_cursor != ui.mouse_cursor() {
             imgui.last_cursor = ui.mouse_cursor();
-            imgui.platform.prepare_render(ui, &self.window);
+            imgui.platform.prepare_render(ui, window);
         }
 
         // make a renderpass for imgui
         let mut rpass = context
             .encoder
             .begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
+                label: Some("Imgui Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &context.view,
                     resolve_target: None,
@@ -557,38 +1388,85 @@ impl<'a> Renderer<'a> {
             });
 
         // give imgui the renderpass
+        let draw_data = imgui.context.render();
+        self.frame_dump
+            .pass("Imgui Render Pass", PassKind::Render, "frame", false);
+        self.frame_dump.draw(|| {
+            DrawRecord::new(DrawCall::Imgui, "imgui-wgpu").note(format!(
+                "{} draw lists, {} vertices, {} indices",
+                draw_data.draw_lists_count(),
+                draw_data.total_vtx_count,
+                draw_data.total_idx_count
+            ))
+        });
         imgui
             .renderer
-            .render(
-                imgui.context.render(),
-                &self.queue,
-                &self.device,
-                &mut rpass,
-            )
+            .render(draw_data, &self.queue, &self.device, &mut rpass)
             .expect("Rendering failed");
 
         // drop it after cuz its already queued
         drop(rpass);
+
+        if let Some(request) = timeline_request {
+            self.apply_timeline_request(request);
+        }
+        if let Some(request) = hierarchy_request {
+            self.apply_hierarchy_request(request);
+        }
+        if let Some(request) = texture_editor_request {
+            self.apply_texture_editor_request(request);
+        }
+        if let Some(request) = scene_request {
+            self.apply_scene_request(request);
+        }
+        if let Some(request) = simulation_request {
+            self.apply_simulation_request(request);
+        }
     }
 
     fn begin_frame(&mut self) -> Option<FrameContext> {
         let now = Instant::now();
-        if let Some(last_time) = self.last_frame_time {
+        if let Some(fixed) = self.fixed_delta {
+            self.delta_time = fixed;
+        } else if let Some(last_time) = self.last_frame_time {
             self.delta_time = now.duration_since(last_time);
         }
         self.last_frame_time = Some(now);
 
-        let frame = match self.surface.get_current_texture() {
+        // headless frames go straight into the offscreen texture
+        if let Some(texture) = &self.offscreen {
+            let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Offscreen View"),
+                ..Default::default()
+            });
+            let encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+            return Some(FrameContext {
+                frame: None,
+                view,
+                encoder,
+            });
+        }
+
+        let Some(surface) = &self.surface else {
+            error!("[bf] renderer has no surface or offscreen target");
+            return None;
+        };
+
+        let frame = match surface.get_current_texture() {
             Ok(frame) => frame,
             Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
-                self.surface.configure(&self.device, &self.surface_config);
-                match self.surface.get_current_texture() {
-                    Ok(frame) => frame,
-                    Err(e) => {
-                        error!("[bf] failed after configuring: {}", e);
-                        return None;
-                    }
-                }
+                warn!("[bf] surface became invalid, reconfiguring");
+                self.surface_lost = true;
+                self.reconfigure_surface()?
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                warn!("[bf] timed out acquiring swap chain texture, skipping frame");
+                return None;
             }
             Err(e) => {
                 error!("[bf] failed to acquire next swap chain texture: {:?}", e);
@@ -597,9 +1475,10 @@ impl<'a> Renderer<'a> {
         };
 
         // interpretation of texture
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Surface View"),
+            ..Default::default()
+        });
 
         // enqueue texture
         let encoder = self
@@ -609,20 +1488,58 @@ impl<'a> Renderer<'a> {
             });
 
         Some(FrameContext {
-            frame,
+            frame: Some(frame),
             view,
             encoder,
         })
     }
 
+    // the display mode may have changed under us, so size the swapchain to the window again
+    fn reconfigure_surface(&mut self) -> Option<wgpu::SurfaceTexture> {
+        self.update_pacing_target();
+        self.pacing.reset_timing();
+
+        let size = self.window.as_ref()?.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return None; // minimized or between monitors, try again next frame
+        }
+
+        if size.width != self.surface_config.width || size.height != self.surface_config.height {
+            self.pending_resize = None;
+            self.apply_resize(size);
+        } else {
+            self.surface
+                .as_ref()?
+                .configure(&self.device, &self.surface_config);
+        }
+
+        match self.surface.as_ref()?.get_current_texture() {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                error!("[bf] failed after configuring: {}", e);
+                None
+            }
+        }
+    }
+
+    // whether the surface was lost since the last call
+    pub fn take_surface_lost(&mut self) -> bool {
+        std::mem::take(&mut self.surface_lost)
+    }
+
     fn end_frame(&mut self, context: FrameContext) {
         self.queue.submit(std::iter::once(context.encoder.finish()));
 
-        context.frame.present();
+        if let Some(frame) = context.frame {
+            frame.present();
+            self.pacing.record_present();
+            self.latency.record_present();
+        }
+        self.poll_readbacks();
+        self.finish_recording_frame();
 
-        match &mut self.text_renderer {
-            Some(t) => t.atlas.trim(),
-            None => (),
+        if let Some(t) = &mut self.text_renderer {
+            t.atlas.trim()
         };
     }
 }
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use imgui::Ui;
+use log::Level;
+
+use crate::renderer::Renderer;
+use crate::renderer::accessibility::AccessibilityEvent;
+use crate::ui::theme::Theme;
+
+const MAX_TOASTS: usize = 5; // older ones make room, the toast_limit cvar
+const FADE_SECONDS: f32 = 0.3;
+const MARGIN: f32 = 16.0; // logical pixels from the window corner
+const PADDING: [f32; 2] = [10.0, 6.0];
+const STRIPE: f32 = 3.0; // the level colored bar on the left
+
+struct Toast {
+    text: String,
+    level: Level,
+    shown: Instant,
+    seconds: f32,
+}
+
+impl Toast {
+    // 0 once it's gone, fading out over its last moments
+    fn opacity(&self, now: Instant) -> f32 {
+        let left = self.seconds - now.duration_since(self.shown).as_secs_f32();
+        (left / FADE_SECONDS).clamp(0.0, 1.0)
+    }
+}
+
+// short messages stacked in the bottom right corner, from the game or the
+// editor, each gone after its time
+pub(super) struct Notifications {
+    toasts: VecDeque<Toast>,
+    limit: usize,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Notifications {
+            toasts: VecDeque::new(),
+            limit: MAX_TOASTS,
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn notify(&mut self, text: impl Into<String>, level: Level, seconds: f32) {
+        let text = text.into();
+        if self.is_accessibility_enabled() {
+            self.accessibility_event(AccessibilityEvent::Announcement(text.clone()));
+        }
+
+        // the same message again shows the one toast longer
+        let Notifications { toasts, limit } = &mut self.notifications;
+        if let Some(last) = toasts.back_mut()
+            && last.text == text
+            && last.level == level
+        {
+            last.shown = Instant::now();
+            last.seconds = seconds;
+            return;
+        }
+        while toasts.len() >= *limit {
+            toasts.pop_front();
+        }
+        toasts.push_back(Toast {
+            text,
+            level,
+            shown: Instant::now(),
+            seconds,
+        });
+    }
+
+    // shown at once, the oldest go first
+    pub fn set_notification_limit(&mut self, limit: usize) {
+        let notifications = &mut self.notifications;
+        notifications.limit = limit.max(1);
+        while notifications.toasts.len() > notifications.limit {
+            notifications.toasts.pop_front();
+        }
+    }
+
+    pub fn clear_notifications(&mut self) {
+        self.notifications.toasts.clear();
+    }
+}
+
+fn level_color(level: Level, theme: &Theme) -> [f32; 4] {
+    match level {
+        Level::Error => theme.error,
+        Level::Warn => theme.warning,
+        Level::Info => theme.accent,
+        Level::Debug | Level::Trace => theme.text_muted,
+    }
+}
+
+fn faded([r, g, b, a]: [f32; 4], opacity: f32) -> [f32; 4] {
+    [r, g, b, a * opacity]
+}
+
+pub(super) fn notifications_overlay(ui: &Ui, notifications: &mut Notifications, theme: &Theme) {
+    let now = Instant::now();
+    notifications
+        .toasts
+        .retain(|toast| toast.opacity(now) > 0.0);
+    if notifications.toasts.is_empty() {
+        return;
+    }
+
+    let draw_list = ui.get_foreground_draw_list();
+    let [width, height] = ui.io().display_size;
+    let mut bottom = height - MARGIN;
+    for toast in notifications.toasts.iter().rev() {
+        let opacity = toast.opacity(now);
+        let [text_width, text_height] = ui.calc_text_size(&toast.text);
+        let size = [
+            text_width + PADDING[0] * 2.0 + STRIPE,
+            text_height + PADDING[1] * 2.0,
+        ];
+        let min = [width - MARGIN - size[0], bottom - size[1]];
+        let max = [width - MARGIN, bottom];
+
+        draw_list
+            .add_rect(min, max, faded(theme.background, opacity))
+            .filled(true)
+            .build();
+        draw_list
+            .add_rect(
+                min,
+                [min[0] + STRIPE, max[1]],
+                faded(level_color(toast.level, theme), opacity),
+            )
+            .filled(true)
+            .build();
+        draw_list.add_text(
+            [min[0] + STRIPE + PADDING[0], min[1] + PADDING[1]],
+            faded(theme.text, opacity),
+            &toast.text,
+        );
+
+        bottom = min[1] - theme.spacing;
+    }
+}
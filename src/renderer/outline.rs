@@ -0,0 +1,109 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+use crate::renderer::layer::LayerMask;
+
+const SELECTION_WIDTH: f32 = 2.0;
+
+// a silhouette behind the sprite, grown by width screen pixels. the sprite's
+// texture decides the shape, so it follows transparent edges instead of the quad
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Outline {
+    pub color: [f32; 4],
+    pub width: f32, // in physical pixels, whatever the camera's zoom
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Outline {
+            color: [1.0, 1.0, 1.0, 1.0],
+            width: SELECTION_WIDTH,
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn set_sprite_outline(&mut self, id: usize, outline: Option<Outline>) {
+        match self.sprite_mut(id) {
+            Some(sprite) => sprite.outline = outline,
+            None => warn!("no sprite {}", id),
+        }
+    }
+
+    // outlined in the theme's accent color on top of any outline of its own
+    pub fn select_sprite(&mut self, id: Option<usize>) {
+        if let Some(id) = id
+            && self.sprite(id).is_none()
+        {
+            warn!("no sprite {} to select", id);
+            return;
+        }
+        self.selection = id;
+    }
+
+    pub fn selected_sprite(&self) -> Option<usize> {
+        self.selection
+    }
+
+    // the outline batching draws for a sprite, the selection wins
+    pub(super) fn sprite_outline(&self, id: usize, own: Option<Outline>) -> Option<Outline> {
+        match self.selection == Some(id) {
+            true => Some(Outline {
+                color: self.theme().accent,
+                width: SELECTION_WIDTH,
+            }),
+            false => own,
+        }
+    }
+
+    // the topmost drawn sprite under a point in physical pixels of the window, for
+    // click to select. rotation is taken into account, transparent pixels are not.
+    // sprites are found through the layers' grids, which catch up with sprites
+    // that moved since the last frame first
+    pub fn pick_sprite(&mut self, screen_point: [f32; 2]) -> Option<usize> {
+        self.update_sprite_grids();
+        let screen_point = self.window_to_viewport(screen_point);
+        let mut layers: Vec<_> = self
+            .layers
+            .iter()
+            .filter(|layer| matches!(layer.mask, LayerMask::None | LayerMask::Test(_)))
+            .collect();
+        layers.sort_by_key(|layer| layer.zindex);
+
+        for layer in layers.iter().rev() {
+            let Some(camera) = self.cameras.get(layer.camera) else {
+                continue;
+            };
+            let point = camera.screen_to_world(screen_point);
+            let mut under = layer.grid.query_point(point);
+            under.sort_unstable();
+
+            let hit = layer
+                .sorted_only(&under)
+                .into_iter()
+                .rev()
+                .find(|(_, sprite)| {
+                    if !sprite.visibility.is_drawn() {
+                        return false;
+                    }
+                    let pivot = self.sprite_pivot(sprite);
+                    let offset = [point[0] - sprite.position[0], point[1] - sprite.position[1]];
+                    let (sin, cos) = (-sprite.rotation).sin_cos();
+                    let local = [
+                        offset[0] * cos - offset[1] * sin,
+                        offset[0] * sin + offset[1] * cos,
+                    ];
+                    (0..2).all(|axis| {
+                        let t = local[axis] / sprite.size[axis] + pivot[axis];
+                        (0.0..=1.0).contains(&t)
+                    })
+                });
+            if let Some((id, _)) = hit {
+                return Some(id);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+
+use crate::renderer::Renderer;
+
+const HISTORY: usize = 240; // frames kept for the report
+const MISSED_THRESHOLD: f32 = 1.5; // intervals longer than this many vsyncs missed one
+
+// present to present timing against the display's refresh interval
+pub(super) struct FramePacing {
+    target: Option<Duration>,
+    last_present: Option<Instant>,
+    intervals: VecDeque<Duration>,
+    missed_intervals: u64,
+    stutters: u64,
+    missed_threshold: f32, // the pacing_missed_vsyncs cvar
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        FramePacing {
+            target: None,
+            last_present: None,
+            intervals: VecDeque::new(),
+            missed_intervals: 0,
+            stutters: 0,
+            missed_threshold: MISSED_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PacingReport {
+    pub target_ms: Option<f32>, // None when the refresh rate is unknown
+    pub average_ms: f32,
+    pub min_ms: f32,
+    pub max_ms: f32,
+    pub jitter_ms: f32, // standard deviation of the interval
+    pub missed_intervals: u64,
+    pub stutters: u64, // frames that missed at least one vsync
+}
+
+impl FramePacing {
+    pub(super) fn set_target(&mut self, refresh_millihertz: Option<u32>) {
+        self.target = refresh_millihertz
+            .filter(|mhz| *mhz > 0)
+            .map(|mhz| Duration::from_secs_f64(1000.0 / mhz as f64));
+        info!("frame pacing target {:?}", self.target);
+    }
+
+    pub(super) fn record_present(&mut self) {
+        let now = Instant::now();
+        let Some(last) = self.last_present.replace(now) else {
+            return;
+        };
+        let interval = now - last;
+
+        if self.intervals.len() == HISTORY {
+            self.intervals.pop_front();
+        }
+        self.intervals.push_back(interval);
+
+        // without a refresh rate, twice the recent average counts as a hitch
+        let target = self.target.unwrap_or_else(|| self.average() * 2);
+        if target.is_zero() {
+            return;
+        }
+
+        let vsyncs = interval.as_secs_f32() / target.as_secs_f32();
+        if vsyncs > self.missed_threshold {
+            let missed = (vsyncs.round() as u64).saturating_sub(1).max(1);
+            self.missed_intervals += missed;
+            self.stutters += 1;
+            debug!(
+                "frame took {:?}, missed {} vsync intervals",
+                interval, missed
+            );
+        }
+    }
+
+    // a hitch from a window drag or a breakpoint shouldn't count
+    pub(super) fn reset_timing(&mut self) {
+        self.last_present = None;
+    }
+
+    fn average(&self) -> Duration {
+        if self.intervals.is_empty() {
+            return Duration::ZERO;
+        }
+        self.intervals.iter().sum::<Duration>() / self.intervals.len() as u32
+    }
+
+    pub(super) fn report(&self) -> PacingReport {
+        let ms: Vec<f32> = self
+            .intervals
+            .iter()
+            .map(|interval| interval.as_secs_f32() * 1000.0)
+            .collect();
+        if ms.is_empty() {
+            return PacingReport {
+                target_ms: self.target.map(|t| t.as_secs_f32() * 1000.0),
+                ..Default::default()
+            };
+        }
+
+        let average = ms.iter().sum::<f32>() / ms.len() as f32;
+        let variance = ms.iter().map(|m| (m - average).powi(2)).sum::<f32>() / ms.len() as f32;
+
+        PacingReport {
+            target_ms: self.target.map(|t| t.as_secs_f32() * 1000.0),
+            average_ms: average,
+            min_ms: ms.iter().copied().fold(f32::MAX, f32::min),
+            max_ms: ms.iter().copied().fold(0.0, f32::max),
+            jitter_ms: variance.sqrt(),
+            missed_intervals: self.missed_intervals,
+            stutters: self.stutters,
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn pacing_report(&self) -> PacingReport {
+        self.pacing.report()
+    }
+
+    // frames longer than this many refresh intervals count as stutters
+    pub fn set_missed_vsync_threshold(&mut self, vsyncs: f32) {
+        self.pacing.missed_threshold = vsyncs.max(1.0);
+    }
+
+    // the refresh rate can change with the monitor the window is on
+    pub(super) fn update_pacing_target(&mut self) {
+        let refresh = self
+            .window
+            .as_ref()
+            .and_then(|window| window.current_monitor())
+            .and_then(|monitor| monitor.refresh_rate_millihertz());
+        self.pacing.set_target(refresh);
+    }
+}
@@ -0,0 +1,63 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+
+// recolors a sprite drawing an indexed texture, one imported with `indexed = true`.
+// the red channel of each texel picks a column of the palette texture and row
+// picks the variant, so one sheet serves every color scheme of a character
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct SpritePalette {
+    pub pool: usize,
+    pub texture: usize, // a strip of colors per row, read unfiltered
+    pub row: u32,
+}
+
+impl SpritePalette {
+    pub fn new(pool: usize, texture: usize) -> Self {
+        SpritePalette {
+            pool,
+            texture,
+            row: 0,
+        }
+    }
+
+    pub fn row(mut self, row: u32) -> Self {
+        self.row = row;
+        self
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn sprite_palette(&self, id: usize) -> Option<SpritePalette> {
+        self.sprite(id).and_then(|sprite| sprite.palette)
+    }
+
+    // None draws the texture as it is
+    pub fn set_sprite_palette(&mut self, id: usize, palette: Option<SpritePalette>) {
+        if let Some(palette) = palette
+            && self.texture_meta(palette.pool, palette.texture).is_none()
+        {
+            warn!(
+                "no palette texture {} in pool {}",
+                palette.texture, palette.pool
+            );
+            return;
+        }
+        match self.sprite_mut(id) {
+            Some(sprite) => sprite.palette = palette,
+            None => warn!("no sprite {}", id),
+        }
+    }
+
+    // picks another variant of the sprite's palette, e.g. for a team color
+    pub fn set_sprite_palette_row(&mut self, id: usize, row: u32) {
+        match self
+            .sprite_mut(id)
+            .and_then(|sprite| sprite.palette.as_mut())
+        {
+            Some(palette) => palette.row = row,
+            None => warn!("sprite {} has no palette", id),
+        }
+    }
+}
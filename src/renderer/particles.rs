@@ -0,0 +1,427 @@
+use bytemuck::{Pod, Zeroable};
+use log::{error, info, warn};
+use wgpu::util::{DeviceExt, DrawIndirectArgs};
+
+use crate::renderer::compute::ComputeDispatch;
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+use crate::util::curve::Curve;
+
+const WORKGROUP_SIZE: u32 = 256;
+const UPDATE_SHADER: &str = include_str!("../../shaders/particles_update.wgsl");
+const CURVE_SAMPLES: usize = 16; // matches the shaders' arrays of 4 vec4s
+
+// positions and velocities are in clip space, so emitters stay on screen
+// whatever the cameras do
+#[derive(Clone, Copy)]
+pub struct ParticleEmitter {
+    pub position: [f32; 2],
+    pub extent: [f32; 2], // half size of the box particles spawn in around position
+    pub gravity: [f32; 2],
+    pub wind: [f32; 2], // moves every live particle, unlike what it spawned with
+    pub color: [f32; 4],
+    pub rate: f32, // particles per second
+    pub lifetime: f32,
+    pub speed: f32,
+    pub direction: f32,      // radians, straight up by default
+    pub spread: f32,         // radians around direction
+    pub sway: f32,           // side to side drift, like snow
+    pub ground: Option<f32>, // y particles die below
+    pub size: f32,
+    pub stretch: f32,  // seconds of motion a particle smears over, for rain streaks
+    pub softness: f32, // 0 for hard squares, up to 1 for round blobs fading out
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        ParticleEmitter {
+            position: [0.0, 0.0],
+            extent: [0.0, 0.0],
+            gravity: [0.0, -0.5],
+            wind: [0.0, 0.0],
+            color: [1.0, 1.0, 1.0, 1.0],
+            rate: 1000.0,
+            lifetime: 2.0,
+            speed: 0.5,
+            direction: std::f32::consts::FRAC_PI_2,
+            spread: 1.0,
+            sway: 0.0,
+            ground: None,
+            size: 0.005,
+            stretch: 0.0,
+            softness: 0.0,
+        }
+    }
+}
+
+// what a curve drives over a particle's life, 0 when spawned and 1 when gone
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParticleCurve {
+    Size,  // times the emitter's size, 1 without a curve
+    Alpha, // times the color's alpha, fading from 1 to 0 without a curve
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    life: f32,
+    max_life: f32,
+    seed: f32,
+    _pad: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct ParticleParams {
+    emitter: [f32; 2],
+    gravity: [f32; 2],
+    color: [f32; 4],
+    dt: f32,
+    time: f32,
+    lifetime: f32,
+    speed: f32,
+    spread: f32,
+    size: f32,
+    spawn: u32,
+    capacity: u32,
+    aspect: f32,
+    sway: f32,
+    ground: f32,
+    stretch: f32,
+    extent: [f32; 2],
+    wind: [f32; 2],
+    direction: f32,
+    softness: f32,
+    _pad: [f32; 2],
+    size_curve: [[f32; 4]; CURVE_SAMPLES / 4],
+    alpha_curve: [[f32; 4]; CURVE_SAMPLES / 4],
+}
+
+pub(super) struct ParticleSystem {
+    pub(super) emitter: ParticleEmitter,
+    size_curve: Option<usize>, // from the curve library, baked every update
+    alpha_curve: Option<usize>,
+    capacity: u32,
+    params: wgpu::Buffer,
+    args: [wgpu::Buffer; 2],
+    update_bind_groups: [wgpu::BindGroup; 2], // keep the two particle buffers alive
+    render_bind_groups: [wgpu::BindGroup; 2],
+    current: usize, // buffer holding the latest simulated state
+    spawn_accumulator: f32,
+    time: f32,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn create_particle_system(
+        &mut self,
+        capacity: u32,
+        emitter: ParticleEmitter,
+    ) -> Option<usize> {
+        info!("creating particle system with {} particles", capacity);
+
+        let update_pipeline = match self.particle_update_pipeline {
+            Some(id) => id,
+            None => self.create_particle_pipelines()?,
+        };
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Params Buffer"),
+            size: std::mem::size_of::<ParticleParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // zeroed particles have no life left, the update spawns over them
+        let dead = vec![Particle::zeroed(); capacity as usize];
+        let particles = [0, 1].map(|i| {
+            self.create_storage_buffer(
+                &format!("Particle Buffer {}", i),
+                bytemuck::cast_slice(&dead),
+            )
+        });
+
+        // instance count doubles as the alive count for the next update
+        let empty_args = DrawIndirectArgs {
+            vertex_count: 6,
+            instance_count: 0,
+            first_vertex: 0,
+            first_instance: 0,
+        };
+        let args = [0, 1].map(|i| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("Particle Indirect Buffer {}", i)),
+                    contents: empty_args.as_bytes(),
+                    usage: wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::INDIRECT
+                        | wgpu::BufferUsages::COPY_DST,
+                })
+        });
+
+        // bind group i reads buffer i and writes the other one
+        let mut update_bind_groups = Vec::with_capacity(2);
+        for (src, dst) in [(0, 1), (1, 0)] {
+            update_bind_groups.push(self.create_compute_bind_group(
+                update_pipeline,
+                0,
+                &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: particles[src].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: particles[dst].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: args[src].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: args[dst].as_entire_binding(),
+                    },
+                ],
+            )?);
+        }
+
+        let layout = self.particle_bind_group_layout.as_ref()?;
+        let render_bind_groups = [0, 1].map(|i| {
+            self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("Particle Render Bind Group {}", i)),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: particles[i].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let id = self.particle_systems.len();
+        self.particle_systems.push(ParticleSystem {
+            emitter,
+            size_curve: None,
+            alpha_curve: None,
+            capacity,
+            params,
+            args,
+            update_bind_groups: update_bind_groups.try_into().ok()?,
+            render_bind_groups,
+            current: 0,
+            spawn_accumulator: 0.0,
+            time: 0.0,
+        });
+
+        Some(id)
+    }
+
+    pub fn particle_emitter(&mut self, id: usize) -> Option<&mut ParticleEmitter> {
+        self.particle_systems
+            .get_mut(id)
+            .map(|system| &mut system.emitter)
+    }
+
+    // a curve from add_curve over each particle's life, None for the default
+    pub fn set_particle_curve(&mut self, id: usize, target: ParticleCurve, curve: Option<usize>) {
+        let Some(system) = self.particle_systems.get_mut(id) else {
+            warn!("no particle system {}", id);
+            return;
+        };
+        match target {
+            ParticleCurve::Size => system.size_curve = curve,
+            ParticleCurve::Alpha => system.alpha_curve = curve,
+        }
+    }
+
+    // shared by every particle system, created on first use
+    fn create_particle_pipelines(&mut self) -> Option<usize> {
+        let update_pipeline =
+            match self.create_compute_pipeline("Particle Update", UPDATE_SHADER, "cs_update") {
+                Ok(id) => id,
+                Err(e) => {
+                    error!("failed to create particle update pipeline: {}", e);
+                    return None;
+                }
+            };
+
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Particle Render Bind Group Layout"),
+                entries: &[
+                    // params uniform
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // particle storage
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let render_pipeline = match self.create_pipeline(&PipelineType::Particles, &[&layout], &[])
+        {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                error!("failed to create particle render pipeline: {}", e);
+                return None;
+            }
+        };
+
+        self.pipelines
+            .insert(PipelineType::Particles, render_pipeline);
+        self.particle_bind_group_layout = Some(layout);
+        self.particle_update_pipeline = Some(update_pipeline);
+
+        Some(update_pipeline)
+    }
+
+    pub(super) fn update_particles(&mut self, dt_seconds: f32) {
+        let Some(pipeline) = self.particle_update_pipeline else {
+            return; // no particle systems yet
+        };
+
+        let [width, height] = self.viewport_size();
+        let aspect = width / height;
+        let constant = Curve::constant(1.0);
+        let fade = Curve::linear(1.0, 0.0);
+
+        // queued after the loop, the systems borrow the renderer
+        let mut dispatches = Vec::with_capacity(self.particle_systems.len());
+        for system in self.particle_systems.iter_mut() {
+            let emitter = &system.emitter;
+            let bake = |id: Option<usize>, default: &Curve| {
+                let curve = id.and_then(|id| self.curves.get(id)).unwrap_or(default);
+                let samples: [f32; CURVE_SAMPLES] = curve.bake();
+                std::array::from_fn(|i| std::array::from_fn(|j| samples[i * 4 + j]))
+            };
+
+            system.time += dt_seconds;
+            system.spawn_accumulator += emitter.rate * dt_seconds;
+            let spawn = system.spawn_accumulator.floor();
+            system.spawn_accumulator -= spawn;
+
+            let params = ParticleParams {
+                emitter: emitter.position,
+                gravity: emitter.gravity,
+                color: emitter.color,
+                dt: dt_seconds,
+                time: system.time,
+                lifetime: emitter.lifetime,
+                speed: emitter.speed,
+                spread: emitter.spread,
+                size: emitter.size,
+                spawn: (spawn as u32).min(system.capacity),
+                capacity: system.capacity,
+                aspect,
+                sway: emitter.sway,
+                ground: emitter.ground.unwrap_or(f32::MIN),
+                stretch: emitter.stretch,
+                extent: emitter.extent,
+                wind: emitter.wind,
+                direction: emitter.direction,
+                softness: emitter.softness,
+                _pad: [0.0; 2],
+                size_curve: bake(system.size_curve, &constant),
+                alpha_curve: bake(system.alpha_curve, &fade),
+            };
+            self.queue
+                .write_buffer(&system.params, 0, bytemuck::cast_slice(&[params]));
+
+            // reset the destination counter before the update runs
+            let next = 1 - system.current;
+            let empty_args = DrawIndirectArgs {
+                vertex_count: 6,
+                instance_count: 0,
+                first_vertex: 0,
+                first_instance: 0,
+            };
+            self.queue
+                .write_buffer(&system.args[next], 0, empty_args.as_bytes());
+
+            dispatches.push(ComputeDispatch {
+                pipeline,
+                bind_groups: vec![system.update_bind_groups[system.current].clone()],
+                workgroups: [system.capacity.div_ceil(WORKGROUP_SIZE), 1, 1],
+            });
+
+            system.current = next;
+        }
+        for dispatch in dispatches {
+            self.dispatch_compute(dispatch);
+        }
+    }
+
+    pub(super) fn render_particles(&mut self, context: &mut FrameContext) {
+        if self.particle_systems.is_empty() {
+            return;
+        }
+
+        let pipeline = match self.pipelines.get(&PipelineType::Particles) {
+            Some(pipeline) => pipeline,
+            None => {
+                error!("No particle render pipeline");
+                return;
+            }
+        };
+
+        let mut pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Particle Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+        pass.set_pipeline(pipeline);
+        self.apply_viewport(&mut pass);
+        self.frame_dump
+            .pass("Particle Render Pass", PassKind::Render, "frame", false);
+        for (i, system) in self.particle_systems.iter().enumerate() {
+            pass.set_bind_group(0, &system.render_bind_groups[system.current], &[]);
+            pass.draw_indirect(&system.args[system.current], 0);
+            self.frame_dump.draw(|| {
+                DrawRecord::new(DrawCall::DrawIndirect, PipelineType::Particles.label())
+                    .bind_group(format!("particles {}/{}", i, system.current))
+                    .note(format!("up to {} particles", system.capacity))
+            });
+        }
+    }
+}
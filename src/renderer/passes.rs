@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use log::{info, warn};
+
+use crate::renderer::frame_dump::PassKind;
+use crate::renderer::readback::ReadbackQueue;
+use crate::renderer::{FrameContext, Renderer};
+
+// where in the frame a custom pass is recorded
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderStage {
+    BeforeSprites, // after the background image
+    AfterSprites,  // after sprites and particles
+    BeforeUi,      // under text and imgui
+}
+
+// what a custom pass gets to record into, the view is this frame's target.
+// only the game's passes read it, the engine adds none of its own
+#[allow(dead_code)]
+pub struct PassContext<'f> {
+    pub device: &'f wgpu::Device,
+    pub queue: &'f wgpu::Queue,
+    pub encoder: &'f mut wgpu::CommandEncoder,
+    pub readbacks: &'f mut ReadbackQueue, // copies are mapped once the frame is submitted
+    pub view: &'f wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub size: [u32; 2],
+    pub dt_seconds: f32,
+    pub frame: u64,
+}
+
+pub trait CustomPass {
+    fn name(&self) -> &str;
+
+    fn render(&mut self, ctx: &mut PassContext);
+}
+
+pub(super) struct RegisteredPass {
+    stage: RenderStage,
+    pass: Box<dyn CustomPass>,
+    enabled: bool,
+}
+
+// passes of the same stage run in the order they were added
+#[derive(Default)]
+pub(super) struct CustomPasses {
+    passes: BTreeMap<usize, RegisteredPass>,
+    next_id: usize,
+}
+
+impl<'a> Renderer<'a> {
+    #[allow(dead_code)] // called by games, see PassContext
+    pub fn add_render_pass(&mut self, stage: RenderStage, pass: Box<dyn CustomPass>) -> usize {
+        let id = self.custom_passes.next_id;
+        self.custom_passes.next_id += 1;
+
+        info!(
+            "adding render pass {} at {:?} with id {}",
+            pass.name(),
+            stage,
+            id
+        );
+        self.custom_passes.passes.insert(
+            id,
+            RegisteredPass {
+                stage,
+                pass,
+                enabled: true,
+            },
+        );
+        id
+    }
+
+    // a disabled pass stays registered but isn't recorded
+    pub fn set_render_pass_enabled(&mut self, id: usize, enabled: bool) {
+        match self.custom_passes.passes.get_mut(&id) {
+            Some(registered) => registered.enabled = enabled,
+            None => warn!("no render pass {}", id),
+        }
+    }
+
+    // id, name, stage and whether it's enabled, in recording order
+    pub fn render_passes(&self) -> Vec<(usize, String, RenderStage, bool)> {
+        self.custom_passes
+            .passes
+            .iter()
+            .map(|(id, registered)| {
+                (
+                    *id,
+                    registered.pass.name().to_string(),
+                    registered.stage,
+                    registered.enabled,
+                )
+            })
+            .collect()
+    }
+
+    pub fn remove_render_pass(&mut self, id: usize) {
+        if self.custom_passes.passes.remove(&id).is_none() {
+            warn!("no render pass {}", id);
+        }
+    }
+
+    pub(super) fn run_custom_passes(
+        &mut self,
+        context: &mut FrameContext,
+        stage: RenderStage,
+        dt_seconds: f32,
+    ) {
+        let format = match stage {
+            RenderStage::BeforeUi => self.surface_config.format,
+            _ => self.scene_format(),
+        };
+        let mut ctx = PassContext {
+            device: &self.device,
+            queue: &self.queue,
+            encoder: &mut context.encoder,
+            readbacks: &mut self.readbacks,
+            view: &context.view,
+            format,
+            size: [self.surface_config.width, self.surface_config.height],
+            dt_seconds,
+            frame: self.frame_index,
+        };
+
+        for registered in self.custom_passes.passes.values_mut() {
+            if registered.stage == stage && registered.enabled {
+                ctx.encoder.push_debug_group(registered.pass.name());
+                self.frame_dump.pass(
+                    registered.pass.name(),
+                    PassKind::Custom,
+                    if stage == RenderStage::BeforeUi {
+                        "frame"
+                    } else {
+                        "scene"
+                    },
+                    false,
+                );
+                registered.pass.render(&mut ctx);
+                ctx.encoder.pop_debug_group();
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use log::warn;
+
+use crate::renderer::pipeline::PICK_FORMAT;
+use crate::renderer::readback::ReadbackId;
+use crate::renderer::{FrameContext, Renderer};
+
+// an id buffer drawn on frames with a pick request. slower to answer than
+// pick_sprite, but exact to the pixel for rotated and transparent sprites
+#[derive(Default)]
+pub(super) struct PickBuffer {
+    enabled: bool,
+    target: Option<wgpu::Texture>,
+    requests: Vec<(ReadbackId, [f32; 2])>, // answered by the next frame
+    misses: HashSet<ReadbackId>,           // outside the surface or nothing drawn
+}
+
+impl<'a> Renderer<'a> {
+    pub fn set_pick_buffer(&mut self, enabled: bool) {
+        self.pick_buffer.enabled = enabled;
+        if !enabled {
+            self.pick_buffer.target = None;
+            self.pick_buffer.requests.clear();
+        }
+    }
+
+    pub fn has_pick_buffer(&self) -> bool {
+        self.pick_buffer.enabled
+    }
+
+    // the sprite under a point in physical pixels, answered a frame or two later
+    // through take_pick
+    pub fn request_pick(&mut self, screen_point: [f32; 2]) -> Option<ReadbackId> {
+        if !self.pick_buffer.enabled {
+            warn!("the pick buffer is off, turn it on with set_pick_buffer");
+            return None;
+        }
+        let id = self.readbacks.reserve();
+        self.pick_buffer.requests.push((id, screen_point));
+        Some(id)
+    }
+
+    // None while the gpu is still on it, then the sprite if there was one
+    pub fn take_pick(&mut self, id: ReadbackId) -> Option<Option<usize>> {
+        if self.pick_buffer.misses.remove(&id) {
+            return Some(None);
+        }
+        let bytes = self.readbacks.take(id)?.bytes;
+        let value = bytes.get(..4).and_then(|value| value.try_into().ok());
+        Some(
+            value
+                .and_then(|value| u32::from_le_bytes(value).checked_sub(1))
+                .map(|id| id as usize),
+        )
+    }
+
+    pub(super) fn render_pick_buffer(&mut self, context: &mut FrameContext) {
+        if self.pick_buffer.requests.is_empty() {
+            return;
+        }
+        let size = [self.surface_config.width, self.surface_config.height];
+        let target = match self.pick_buffer.target.take() {
+            Some(texture) if [texture.width(), texture.height()] == size => texture,
+            _ => self.create_pick_target(size),
+        };
+
+        let view = target.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Pick View"),
+            ..Default::default()
+        });
+        let drawn = self.sprite_renderer.is_some();
+        if drawn {
+            self.draw_pick_batches(&mut context.encoder, &view);
+        }
+
+        for (id, point) in std::mem::take(&mut self.pick_buffer.requests) {
+            let [x, y] = point.map(|coordinate| coordinate.floor());
+            let inside = x >= 0.0 && y >= 0.0 && x < size[0] as f32 && y < size[1] as f32;
+            let copied = drawn
+                && inside
+                && self.readbacks.read_texture_into(
+                    id,
+                    &self.device,
+                    &mut context.encoder,
+                    &target,
+                    Some([x as u32, y as u32, 1, 1]),
+                );
+            if !copied {
+                self.pick_buffer.misses.insert(id);
+            }
+        }
+        self.pick_buffer.target = Some(target);
+    }
+
+    fn create_pick_target(&self, size: [u32; 2]) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Pick Texture"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: PICK_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+}
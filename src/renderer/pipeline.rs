@@ -4,30 +4,334 @@ use log::info;
 use wgpu::{RenderPipeline, ShaderSource};
 
 use crate::renderer::Renderer;
+use crate::renderer::debug_view::DebugView;
+use crate::renderer::layer::BlendMode;
 
 static BASIC_SHADER: ShaderSource =
     ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/basic.wgsl")));
+static LOADING_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/loading.wgsl")));
+static PARTICLE_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/particles.wgsl")));
+static GRADING_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/grading.wgsl")));
+static DISTORTION_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/distortion.wgsl")));
+static SPRITE_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/sprite.wgsl")));
+static DEBUG_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/debug.wgsl")));
+static SHAPE_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/shapes.wgsl")));
+static SDF_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/sdf.wgsl")));
+static LETTERBOX_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/letterbox.wgsl")));
+static SKYBOX_SHADER: ShaderSource =
+    ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../shaders/skybox.wgsl")));
 
 #[derive(Hash, Eq, PartialEq)]
 pub(super) enum PipelineType {
     Basic2D,
-    Basic3D,
+    Particles,
+    Sprites(BlendMode),
+    SpriteMask,               // only writes the stencil
+    SpriteMasked(BlendMode),  // only draws where the stencil matches
+    SpritePalette(BlendMode), // indexed textures colored by a palette texture
+    SpritePaletteMasked(BlendMode),
+    SpriteOutline,          // a grown silhouette behind the sprite
+    SpritePick,             // sprite ids into the pick buffer, unblended
+    SpriteDebug(DebugView), // every sprite batch while a debug view is on
+    Loading,
+    Grading,
+    Distortion,
+    DebugLines, // over the finished scene, under the ui
+    Shapes,     // untextured triangles in the scene
+    SdfShapes,  // anti-aliased rounded boxes, circles and rings
+    Letterbox,  // the bars around a fixed aspect viewport, in the blend constant
+    Skybox,     // a cubemap behind the world, seen from the sky view
+}
+
+// the sprite pass always has a stencil attachment, so every sprite pipeline declares one
+pub(super) const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+pub(super) const PICK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+impl PipelineType {
+    pub(super) fn label(&self) -> String {
+        match self {
+            PipelineType::Basic2D => "Basic 2D".to_string(),
+            PipelineType::Particles => "Particles".to_string(),
+            PipelineType::Sprites(blend) => format!("{:?} Sprites", blend),
+            PipelineType::SpriteMask => "Sprite Mask".to_string(),
+            PipelineType::SpriteMasked(blend) => format!("{:?} Sprite Masked", blend),
+            PipelineType::SpritePalette(blend) => format!("{:?} Sprite Palette", blend),
+            PipelineType::SpritePaletteMasked(blend) => {
+                format!("{:?} Sprite Palette Masked", blend)
+            }
+            PipelineType::SpriteOutline => "Sprite Outline".to_string(),
+            PipelineType::SpritePick => "Sprite Pick".to_string(),
+            PipelineType::SpriteDebug(view) => format!("Sprite Debug {:?}", view),
+            PipelineType::Loading => "Loading".to_string(),
+            PipelineType::Grading => "Grading".to_string(),
+            PipelineType::Distortion => "Distortion".to_string(),
+            PipelineType::DebugLines => "Debug Lines".to_string(),
+            PipelineType::Shapes => "Shapes".to_string(),
+            PipelineType::SdfShapes => "SDF Shapes".to_string(),
+            PipelineType::Letterbox => "Letterbox".to_string(),
+            PipelineType::Skybox => "Skybox".to_string(),
+        }
+    }
+
+    fn shader(&self) -> &'static ShaderSource<'static> {
+        match self {
+            PipelineType::Basic2D => &BASIC_SHADER,
+            PipelineType::Particles => &PARTICLE_SHADER,
+            PipelineType::Sprites(_)
+            | PipelineType::SpriteMask
+            | PipelineType::SpriteMasked(_)
+            | PipelineType::SpritePalette(_)
+            | PipelineType::SpritePaletteMasked(_)
+            | PipelineType::SpriteOutline
+            | PipelineType::SpritePick
+            | PipelineType::SpriteDebug(_) => &SPRITE_SHADER,
+            PipelineType::Loading => &LOADING_SHADER,
+            PipelineType::Grading => &GRADING_SHADER,
+            PipelineType::Distortion => &DISTORTION_SHADER,
+            PipelineType::DebugLines => &DEBUG_SHADER,
+            PipelineType::Shapes => &SHAPE_SHADER,
+            PipelineType::SdfShapes => &SDF_SHADER,
+            PipelineType::Letterbox => &LETTERBOX_SHADER,
+            PipelineType::Skybox => &SKYBOX_SHADER,
+        }
+    }
+
+    fn blend(&self) -> wgpu::BlendState {
+        match self {
+            PipelineType::Sprites(blend)
+            | PipelineType::SpriteMasked(blend)
+            | PipelineType::SpritePalette(blend)
+            | PipelineType::SpritePaletteMasked(blend) => blend.state(),
+            PipelineType::SpriteOutline => BlendMode::Alpha.state(),
+            // every covering fragment adds the same amount
+            PipelineType::SpriteDebug(DebugView::Overdraw) => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+            PipelineType::SpriteDebug(_) => wgpu::BlendState::ALPHA_BLENDING,
+            PipelineType::Particles
+            | PipelineType::DebugLines
+            | PipelineType::Shapes
+            | PipelineType::SdfShapes => wgpu::BlendState::ALPHA_BLENDING,
+            // white from the shader times the constant, replacing what's there
+            PipelineType::Letterbox => {
+                let component = wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Constant,
+                    dst_factor: wgpu::BlendFactor::Zero,
+                    operation: wgpu::BlendOperation::Add,
+                };
+                wgpu::BlendState {
+                    color: component,
+                    alpha: component,
+                }
+            }
+            _ => wgpu::BlendState::REPLACE,
+        }
+    }
+
+    fn fragment_entry(&self) -> &'static str {
+        match self {
+            PipelineType::SpriteMask => "fs_mask",
+            PipelineType::SpriteOutline => "fs_outline",
+            PipelineType::SpritePick => "fs_pick",
+            PipelineType::SpriteDebug(view) => view.fragment_entry(),
+            PipelineType::Sprites(blend) | PipelineType::SpriteMasked(blend) => {
+                blend.fragment_entry()
+            }
+            PipelineType::SpritePalette(blend) | PipelineType::SpritePaletteMasked(blend) => {
+                blend.palette_entry()
+            }
+            _ => "fs_main",
+        }
+    }
+
+    // palette pipelines take the palette texture as a third bind group
+    pub(super) fn samples_palette(&self) -> bool {
+        matches!(
+            self,
+            PipelineType::SpritePalette(_) | PipelineType::SpritePaletteMasked(_)
+        )
+    }
+
+    fn polygon_mode(&self) -> wgpu::PolygonMode {
+        match self {
+            PipelineType::SpriteDebug(DebugView::Wireframe) => wgpu::PolygonMode::Line,
+            _ => wgpu::PolygonMode::Fill,
+        }
+    }
+
+    fn write_mask(&self) -> wgpu::ColorWrites {
+        match self {
+            PipelineType::SpriteMask => wgpu::ColorWrites::empty(),
+            _ => wgpu::ColorWrites::ALL,
+        }
+    }
+
+    // everything but the loading screen, the grading pass, debug lines and the
+    // letterbox draws into the scene
+    pub(super) fn draws_scene(&self) -> bool {
+        !matches!(
+            self,
+            PipelineType::Loading
+                | PipelineType::Grading
+                | PipelineType::DebugLines
+                | PipelineType::Letterbox
+        )
+    }
+
+    // the reference value is set per draw with set_stencil_reference
+    fn depth_stencil(&self) -> Option<wgpu::DepthStencilState> {
+        let (compare, pass_op) = match self {
+            PipelineType::Sprites(_)
+            | PipelineType::SpritePalette(_)
+            | PipelineType::SpriteOutline
+            | PipelineType::SpriteDebug(_) => {
+                (wgpu::CompareFunction::Always, wgpu::StencilOperation::Keep)
+            }
+            PipelineType::SpriteMask => (
+                wgpu::CompareFunction::Always,
+                wgpu::StencilOperation::Replace,
+            ),
+            PipelineType::SpriteMasked(_) | PipelineType::SpritePaletteMasked(_) => {
+                (wgpu::CompareFunction::Equal, wgpu::StencilOperation::Keep)
+            }
+            _ => return None,
+        };
+
+        let face = wgpu::StencilFaceState {
+            compare,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op,
+        };
+
+        Some(wgpu::DepthStencilState {
+            format: STENCIL_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState {
+                front: face,
+                back: face,
+                read_mask: 0xff,
+                write_mask: 0xff,
+            },
+            bias: wgpu::DepthBiasState::default(),
+        })
+    }
+}
+
+impl BlendMode {
+    // alpha always blends over, so transparent windows stay premultiplied
+    fn state(&self) -> wgpu::BlendState {
+        let color = match self {
+            BlendMode::Alpha => return wgpu::BlendState::ALPHA_BLENDING,
+            // straight color scaled by its alpha, added to the target
+            BlendMode::Additive => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            // fs_multiply already faded the color towards white by its alpha
+            BlendMode::Multiply => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Dst,
+                dst_factor: wgpu::BlendFactor::Zero,
+                operation: wgpu::BlendOperation::Add,
+            },
+            // 1 - (1 - dst) * (1 - src), with src premultiplied by fs_premultiplied
+            BlendMode::Screen => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::OneMinusDst,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+
+        wgpu::BlendState {
+            color,
+            alpha: wgpu::BlendComponent::OVER,
+        }
+    }
+
+    fn fragment_entry(&self) -> &'static str {
+        match self {
+            BlendMode::Alpha | BlendMode::Additive => "fs_main",
+            BlendMode::Multiply => "fs_multiply",
+            BlendMode::Screen => "fs_premultiplied",
+        }
+    }
+
+    fn palette_entry(&self) -> &'static str {
+        match self {
+            BlendMode::Alpha | BlendMode::Additive => "fs_palette",
+            BlendMode::Multiply => "fs_palette_multiply",
+            BlendMode::Screen => "fs_palette_premultiplied",
+        }
+    }
 }
 
 impl<'a> Renderer<'a> {
+    // replaced pixels would keep straight alpha, blending over the
+    // transparent clear color keeps a transparent surface premultiplied
+    fn blend_for(&self, pipeline_type: &PipelineType) -> wgpu::BlendState {
+        match pipeline_type.blend() {
+            // post effects copy the already premultiplied scene
+            _ if matches!(
+                pipeline_type,
+                PipelineType::Grading | PipelineType::Distortion
+            ) =>
+            {
+                wgpu::BlendState::REPLACE
+            }
+            wgpu::BlendState::REPLACE if self.transparent => wgpu::BlendState::ALPHA_BLENDING,
+            blend => blend,
+        }
+    }
+
     pub(super) fn create_pipeline(
         &mut self,
+        pipeline_type: &PipelineType,
         bind_group_layouts: &[&wgpu::BindGroupLayout],
         vertex_buffer_layouts: &[wgpu::VertexBufferLayout],
     ) -> Result<RenderPipeline, wgpu::Error> {
-        info!("creating render pipeline");
+        info!("creating {} render pipeline", pipeline_type.label());
+
+        let format = match pipeline_type {
+            PipelineType::SpritePick => PICK_FORMAT,
+            _ if pipeline_type.draws_scene() => self.scene_format(),
+            _ => self.surface_config.format,
+        };
+        // integer targets can't blend
+        let blend = match pipeline_type {
+            PipelineType::SpritePick => None,
+            _ => Some(self.blend_for(pipeline_type)),
+        };
+
+        // without line rasterization the fill finds the edges itself
+        let (polygon_mode, fragment_entry) = match pipeline_type.polygon_mode() {
+            wgpu::PolygonMode::Line if !self.supports_wireframe() => {
+                (wgpu::PolygonMode::Fill, "fs_debug_edges")
+            }
+            mode => (mode, pipeline_type.fragment_entry()),
+        };
 
-        // load basic shader
+        // load shader for this pipeline
         let shader = self
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("Basic Shader"),
-                source: BASIC_SHADER.clone(),
+                label: Some(&format!("{} Shader", pipeline_type.label())),
+                source: pipeline_type.shader().clone(),
             });
 
         // create pipeline layout
@@ -43,7 +347,7 @@ impl<'a> Renderer<'a> {
         Ok(self
             .device
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
+                label: Some(&format!("{} Render Pipeline", pipeline_type.label())),
                 layout: Some(&render_pipeline_layout),
                 vertex: wgpu::VertexState {
                     module: &shader,
@@ -53,11 +357,11 @@ impl<'a> Renderer<'a> {
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
-                    entry_point: Some("fs_main"),
+                    entry_point: Some(fragment_entry),
                     targets: &[Some(wgpu::ColorTargetState {
-                        format: self.surface_config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend,
+                        write_mask: pipeline_type.write_mask(),
                     })],
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 }),
@@ -66,11 +370,11 @@ impl<'a> Renderer<'a> {
                     strip_index_format: None,
                     front_face: wgpu::FrontFace::Ccw,
                     cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
+                    polygon_mode,
                     unclipped_depth: false,
                     conservative: false,
                 },
-                depth_stencil: None,
+                depth_stencil: pipeline_type.depth_stencil(),
                 multisample: wgpu::MultisampleState {
                     count: 1,
                     mask: !0,
@@ -0,0 +1,330 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+use crate::util::tween::Lerp;
+
+type Triangle = [[f32; 2]; 3];
+
+// how two segments meet on the outside of the turn
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineJoin {
+    #[default]
+    Miter, // sharp, beveled once longer than the style's miter_limit
+    Round,
+    Bevel,
+}
+
+// how open ends and every dash finish
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineCap {
+    #[default]
+    Butt, // flat at the end point
+    Round,
+    Square, // flat, half the width past the end point
+}
+
+// on and off lengths along the line in world pixels
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Dash {
+    pub length: f32,
+    pub gap: f32,
+    #[serde(default)]
+    pub offset: f32, // how far into the pattern the line starts, move it to march
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineStyle {
+    pub color: [f32; 4],
+    pub width: f32, // world pixels, scaled with the zoom
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32, // longest miter as a multiple of half the width
+    pub dash: Option<Dash>,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle {
+            color: [1.0, 1.0, 1.0, 1.0],
+            width: 1.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dash: None,
+        }
+    }
+}
+
+#[allow(dead_code)] // styles come from the game, see draw_polyline
+impl LineStyle {
+    pub fn new(color: [f32; 4], width: f32) -> Self {
+        LineStyle {
+            color,
+            width,
+            ..Default::default()
+        }
+    }
+}
+
+// for games, the engine's own lines are debug lines of a fixed width on screen
+#[allow(dead_code)]
+impl<'a> Renderer<'a> {
+    // queued with the other shapes. segments overlap where they join, so
+    // translucent lines come out darker at the corners
+    pub fn draw_polyline(&mut self, camera: usize, points: &[[f32; 2]], style: &LineStyle) {
+        self.queue_polyline(camera, points, false, style);
+    }
+
+    // joined back to the first point, without caps
+    pub fn draw_closed_polyline(&mut self, camera: usize, points: &[[f32; 2]], style: &LineStyle) {
+        self.queue_polyline(camera, points, true, style);
+    }
+
+    fn queue_polyline(
+        &mut self,
+        camera: usize,
+        points: &[[f32; 2]],
+        closed: bool,
+        style: &LineStyle,
+    ) {
+        let Some(zoom) = self.cameras.get(camera).map(|camera| camera.zoom) else {
+            warn!("no camera {}", camera);
+            return;
+        };
+        let half = style.width.abs() * 0.5;
+        if half * zoom <= f32::EPSILON {
+            return;
+        }
+
+        let mut stroke = Stroke {
+            half,
+            join: style.join,
+            cap: style.cap,
+            miter_limit: style.miter_limit.max(1.0),
+            // like draw_circle, segments follow the radius on screen
+            circle_segments: (half * zoom * 0.5).clamp(12.0, 96.0),
+            triangles: Vec::new(),
+        };
+        match style.dash {
+            Some(dash) if dash.length > 0.0 && dash.gap > 0.0 => {
+                // dashes shorter than a pixel would only flicker
+                let pixel = 1.0 / zoom;
+                let dash = Dash {
+                    length: dash.length.max(pixel),
+                    gap: dash.gap.max(pixel),
+                    offset: dash.offset,
+                };
+                let mut path = points.to_vec();
+                if closed && let Some(first) = points.first() {
+                    path.push(*first);
+                }
+                for run in dashes(&path, dash) {
+                    stroke.stroke(&run, false);
+                }
+            }
+            _ => stroke.stroke(points, closed),
+        }
+        self.queue_shape_triangles(camera, &stroke.triangles, style.color);
+    }
+}
+
+struct Stroke {
+    half: f32,
+    join: LineJoin,
+    cap: LineCap,
+    miter_limit: f32,
+    circle_segments: f32,
+    triangles: Vec<Triangle>,
+}
+
+impl Stroke {
+    fn stroke(&mut self, path: &[[f32; 2]], closed: bool) {
+        let mut points: Vec<[f32; 2]> = Vec::with_capacity(path.len());
+        for point in path.iter().copied() {
+            if points
+                .last()
+                .is_none_or(|last| distance(*last, point) > f32::EPSILON)
+            {
+                points.push(point);
+            }
+        }
+        if closed
+            && points.len() > 2
+            && distance(points[0], points[points.len() - 1]) <= f32::EPSILON
+        {
+            points.pop();
+        }
+        let count = points.len();
+        if count < 2 {
+            return;
+        }
+        let closed = closed && count > 2;
+
+        let segments = if closed { count } else { count - 1 };
+        for i in 0..segments {
+            let [mut start, mut end] = [points[i], points[(i + 1) % count]];
+            let direction = direction(start, end);
+            if !closed && self.cap == LineCap::Square {
+                if i == 0 {
+                    start = add(start, scale(direction, -self.half));
+                }
+                if i == segments - 1 {
+                    end = add(end, scale(direction, self.half));
+                }
+            }
+            let normal = scale(normal(direction), self.half);
+            let corners = [
+                add(start, normal),
+                add(end, normal),
+                sub(end, normal),
+                sub(start, normal),
+            ];
+            self.triangles.push([corners[0], corners[1], corners[2]]);
+            self.triangles.push([corners[0], corners[2], corners[3]]);
+        }
+
+        let joints = if closed { 0..count } else { 1..count - 1 };
+        for i in joints {
+            let previous = points[(i + count - 1) % count];
+            self.join(previous, points[i], points[(i + 1) % count]);
+        }
+
+        if !closed && self.cap == LineCap::Round {
+            let first = normal(direction(points[0], points[1]));
+            self.fan(points[0], scale(first, self.half), std::f32::consts::PI);
+            let last = normal(direction(points[count - 2], points[count - 1]));
+            self.fan(
+                points[count - 1],
+                scale(last, -self.half),
+                std::f32::consts::PI,
+            );
+        }
+    }
+
+    // fills the wedge the two segment quads leave open outside the turn
+    fn join(&mut self, previous: [f32; 2], at: [f32; 2], next: [f32; 2]) {
+        let [incoming, outgoing] = [direction(previous, at), direction(at, next)];
+        let turn = incoming[0] * outgoing[1] - incoming[1] * outgoing[0];
+        let angle = turn.atan2(dot(incoming, outgoing));
+        if angle.abs() < 1e-3 {
+            return;
+        }
+        let side = if turn > 0.0 { -self.half } else { self.half };
+        let [from, to] = [incoming, outgoing].map(|direction| scale(normal(direction), side));
+        let [outer_from, outer_to] = [add(at, from), add(at, to)];
+
+        match self.join {
+            LineJoin::Round => self.fan(at, from, angle),
+            LineJoin::Miter => {
+                let bisector = add(from, to);
+                let cosine = dot(bisector, from) / (length(bisector) * self.half);
+                if length(bisector) <= f32::EPSILON || 1.0 / cosine > self.miter_limit {
+                    self.triangles.push([at, outer_from, outer_to]);
+                } else {
+                    let tip = add(at, scale(bisector, self.half / (cosine * length(bisector))));
+                    self.triangles.push([at, outer_from, tip]);
+                    self.triangles.push([at, tip, outer_to]);
+                }
+            }
+            LineJoin::Bevel => self.triangles.push([at, outer_from, outer_to]),
+        }
+    }
+
+    // triangles around center sweeping offset by angle radians
+    fn fan(&mut self, center: [f32; 2], offset: [f32; 2], angle: f32) {
+        let steps = (angle.abs() / std::f32::consts::TAU * self.circle_segments)
+            .ceil()
+            .max(1.0) as usize;
+        let mut previous = add(center, offset);
+        for step in 1..=steps {
+            let (sin, cos) = (angle * step as f32 / steps as f32).sin_cos();
+            let rotated = [
+                offset[0] * cos - offset[1] * sin,
+                offset[0] * sin + offset[1] * cos,
+            ];
+            let point = add(center, rotated);
+            self.triangles.push([center, previous, point]);
+            previous = point;
+        }
+    }
+}
+
+// the pieces of path that are on, each stroked as an open polyline
+fn dashes(path: &[[f32; 2]], dash: Dash) -> Vec<Vec<[f32; 2]>> {
+    let period = dash.length + dash.gap;
+    let mut phase = dash.offset.rem_euclid(period);
+    let mut runs = Vec::new();
+    let mut run = Vec::new();
+    if let Some(first) = path.first()
+        && phase < dash.length
+    {
+        run.push(*first);
+    }
+    for pair in path.windows(2) {
+        let [start, end] = [pair[0], pair[1]];
+        let total = distance(start, end);
+        let mut along = 0.0;
+        loop {
+            let on = phase < dash.length;
+            let step = if on { dash.length } else { period } - phase;
+            if along + step > total {
+                phase += total - along;
+                break;
+            }
+            along += step;
+            let point = start.lerp(&end, along / total);
+            run.push(point);
+            if on {
+                runs.push(std::mem::take(&mut run));
+                phase = dash.length;
+            } else {
+                phase = 0.0;
+            }
+        }
+        if phase < dash.length {
+            run.push(end);
+        }
+    }
+    if run.len() > 1 {
+        runs.push(run);
+    }
+    runs
+}
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: [f32; 2], factor: f32) -> [f32; 2] {
+    [a[0] * factor, a[1] * factor]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn length(a: [f32; 2]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    length(sub(b, a))
+}
+
+fn direction(from: [f32; 2], to: [f32; 2]) -> [f32; 2] {
+    let delta = sub(to, from);
+    scale(delta, 1.0 / length(delta).max(f32::EPSILON))
+}
+
+// a quarter turn of direction
+fn normal(direction: [f32; 2]) -> [f32; 2] {
+    [-direction[1], direction[0]]
+}
@@ -0,0 +1,124 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+
+const MAX_FRAME_LATENCY: u32 = 3; // more only adds input lag
+
+// how frames wait for the display, trading latency against tearing
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Vsync {
+    Off,      // presents right away, tears
+    On,       // waits for every vertical blank, always supported
+    Adaptive, // like on, but tears instead of waiting a whole interval when late
+    #[default]
+    Mailbox, // no tearing, the newest frame replaces a queued one
+}
+
+impl Vsync {
+    pub fn present_mode(self) -> wgpu::PresentMode {
+        match self {
+            Vsync::Off => wgpu::PresentMode::Immediate,
+            Vsync::On => wgpu::PresentMode::Fifo,
+            Vsync::Adaptive => wgpu::PresentMode::FifoRelaxed,
+            Vsync::Mailbox => wgpu::PresentMode::Mailbox,
+        }
+    }
+}
+
+// what the surface said it can do, empty when headless
+#[derive(Default)]
+pub(super) struct SurfaceSupport {
+    pub(super) present_modes: Vec<wgpu::PresentMode>,
+    pub(super) alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+}
+
+impl SurfaceSupport {
+    pub(super) fn from_capabilities(capabilities: &wgpu::SurfaceCapabilities) -> Self {
+        SurfaceSupport {
+            present_modes: capabilities.present_modes.clone(),
+            alpha_modes: capabilities.alpha_modes.clone(),
+        }
+    }
+
+    // fifo is the one mode every surface has
+    pub(super) fn present_mode(&self, vsync: Vsync) -> wgpu::PresentMode {
+        let mode = vsync.present_mode();
+        if self.present_modes.is_empty() || self.present_modes.contains(&mode) {
+            return mode;
+        }
+        warn!("surface does not support {:?}, falling back to fifo", mode);
+        wgpu::PresentMode::Fifo
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn vsync(&self) -> Vsync {
+        self.vsync
+    }
+
+    pub fn set_vsync(&mut self, vsync: Vsync) {
+        self.vsync = vsync;
+        let mode = self.surface_support.present_mode(vsync);
+        if mode == self.surface_config.present_mode {
+            return;
+        }
+        info!("using present mode {:?}", mode);
+        self.surface_config.present_mode = mode;
+        self.configure_surface();
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.surface_config.present_mode
+    }
+
+    pub fn frame_latency(&self) -> u32 {
+        self.surface_config.desired_maximum_frame_latency
+    }
+
+    // frames queued ahead of the display, 1 reacts fastest, 2 or 3 hides hitches.
+    // drivers treat this as a hint
+    pub fn set_frame_latency(&mut self, frames: u32) {
+        let frames = frames.clamp(1, MAX_FRAME_LATENCY);
+        if frames == self.surface_config.desired_maximum_frame_latency {
+            return;
+        }
+        info!("using a frame latency of {}", frames);
+        self.surface_config.desired_maximum_frame_latency = frames;
+        self.configure_surface();
+    }
+
+    pub fn alpha_mode(&self) -> wgpu::CompositeAlphaMode {
+        self.surface_config.alpha_mode
+    }
+
+    pub fn supported_alpha_modes(&self) -> &[wgpu::CompositeAlphaMode] {
+        &self.surface_support.alpha_modes
+    }
+
+    // how the compositor blends the window, only premultiplied and postmultiplied
+    // show through on a transparent renderer. false when the surface can't do it
+    pub fn set_alpha_mode(&mut self, mode: wgpu::CompositeAlphaMode) -> bool {
+        let support = &self.surface_support.alpha_modes;
+        if mode != wgpu::CompositeAlphaMode::Auto && !support.is_empty() && !support.contains(&mode)
+        {
+            warn!("surface does not support alpha mode {:?}", mode);
+            return false;
+        }
+        if mode == self.surface_config.alpha_mode {
+            return true;
+        }
+        info!("using surface alpha mode {:?}", mode);
+        self.surface_config.alpha_mode = mode;
+        self.configure_surface();
+        true
+    }
+
+    fn configure_surface(&mut self) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
+        self.pacing.reset_timing();
+    }
+}
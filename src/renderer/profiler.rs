@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use imgui::{TableColumnFlags, TableColumnSetup, TableFlags, TableSortDirection, Ui};
+use log::warn;
+
+use crate::renderer::Renderer;
+
+const SMOOTHING: f32 = 0.1; // weight of the newest frame in the average
+const PEAK_DECAY: f32 = 0.98; // per frame, so a spike fades over a few seconds
+
+// cpu time of one scope over the last finished frame
+#[derive(Clone, Debug)]
+pub struct ProfileEntry {
+    pub path: String, // parent scopes joined with /, e.g. "update/gameplay"
+    pub depth: usize,
+    pub last_ms: f32, // summed when a scope ran more than once
+    pub average_ms: f32,
+    pub peak_ms: f32,
+}
+
+impl ProfileEntry {
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+struct OpenScope {
+    entry: usize, // into current
+    start: Instant,
+}
+
+struct Smoothed {
+    average_ms: f32,
+    peak_ms: f32,
+}
+
+// nested cpu scopes around engine systems and the recording of render passes,
+// gpu time isn't measured here. a frame runs from one handle_redraw to the next
+pub(super) struct Profiler {
+    open: Vec<OpenScope>,
+    current: Vec<ProfileEntry>, // in the order scopes first opened, parents before children
+    entries: Vec<ProfileEntry>,
+    smoothed: HashMap<String, Smoothed>,
+    frame_start: Instant,
+    frame_ms: f32,
+    sort: Option<(usize, TableSortDirection)>, // column and direction picked in the table
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler {
+            open: Vec::new(),
+            current: Vec::new(),
+            entries: Vec::new(),
+            smoothed: HashMap::new(),
+            frame_start: Instant::now(),
+            frame_ms: 0.0,
+            sort: None,
+        }
+    }
+}
+
+impl Profiler {
+    pub(super) fn finish_frame(&mut self) {
+        if !self.open.is_empty() {
+            warn!(
+                "{} profile scopes still open at the end of the frame",
+                self.open.len()
+            );
+            self.open.clear();
+        }
+
+        let now = Instant::now();
+        self.frame_ms = (now - self.frame_start).as_secs_f32() * 1000.0;
+        self.frame_start = now;
+
+        for entry in &mut self.current {
+            let smoothed = self.smoothed.entry(entry.path.clone()).or_insert(Smoothed {
+                average_ms: entry.last_ms,
+                peak_ms: 0.0,
+            });
+            smoothed.average_ms += (entry.last_ms - smoothed.average_ms) * SMOOTHING;
+            smoothed.peak_ms = entry.last_ms.max(smoothed.peak_ms * PEAK_DECAY);
+            entry.average_ms = smoothed.average_ms;
+            entry.peak_ms = smoothed.peak_ms;
+        }
+        self.entries = std::mem::take(&mut self.current);
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn profile_begin(&mut self, name: &str) {
+        let profiler = &mut self.profiler;
+        let path = match profiler.open.last() {
+            Some(parent) => format!("{}/{}", profiler.current[parent.entry].path, name),
+            None => name.to_string(),
+        };
+
+        let entry = match profiler.current.iter().position(|entry| entry.path == path) {
+            Some(entry) => entry,
+            None => {
+                profiler.current.push(ProfileEntry {
+                    path,
+                    depth: profiler.open.len(),
+                    last_ms: 0.0,
+                    average_ms: 0.0,
+                    peak_ms: 0.0,
+                });
+                profiler.current.len() - 1
+            }
+        };
+        profiler.open.push(OpenScope {
+            entry,
+            start: Instant::now(),
+        });
+    }
+
+    pub fn profile_end(&mut self) {
+        let profiler = &mut self.profiler;
+        let Some(scope) = profiler.open.pop() else {
+            warn!("profile_end without an open scope");
+            return;
+        };
+        profiler.current[scope.entry].last_ms += scope.start.elapsed().as_secs_f32() * 1000.0;
+    }
+
+    pub fn profile<R>(&mut self, name: &str, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.profile_begin(name);
+        let result = f(self);
+        self.profile_end();
+        result
+    }
+
+    // the last finished frame, parents before their children
+    pub fn profile_entries(&self) -> &[ProfileEntry] {
+        &self.profiler.entries
+    }
+}
+
+// the sortable breakdown in the debug overlay
+pub(super) fn profiler_window(ui: &Ui, profiler: &mut Profiler) {
+    ui.window("profiler")
+        .size([420.0, 260.0], imgui::Condition::FirstUseEver)
+        .position([100.0, 560.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.text(format!("cpu frame {:.2}ms", profiler.frame_ms));
+
+            let flags = TableFlags::SORTABLE
+                | TableFlags::RESIZABLE
+                | TableFlags::ROW_BG
+                | TableFlags::BORDERS
+                | TableFlags::SCROLL_Y;
+            let Some(_table) = ui.begin_table_with_flags("scopes", 5, flags) else {
+                return;
+            };
+
+            let columns = ["system", "ms", "avg ms", "peak ms", "% frame"];
+            for (index, name) in columns.iter().enumerate() {
+                let mut column = TableColumnSetup::new(*name);
+                if index == 0 {
+                    column.flags = TableColumnFlags::WIDTH_STRETCH;
+                } else {
+                    column.flags = TableColumnFlags::PREFER_SORT_DESCENDING;
+                }
+                ui.table_setup_column_with(column);
+            }
+            ui.table_setup_scroll_freeze(0, 1);
+            ui.table_headers_row();
+
+            if let Some(specs) = ui.table_sort_specs_mut() {
+                specs.conditional_sort(|specs| {
+                    profiler.sort = specs
+                        .iter()
+                        .next()
+                        .and_then(|spec| Some((spec.column_idx(), spec.sort_direction()?)));
+                });
+            }
+
+            // unsorted keeps the tree, sorting by system groups children under their parent
+            let mut rows: Vec<&ProfileEntry> = profiler.entries.iter().collect();
+            if let Some((column, direction)) = profiler.sort {
+                rows.sort_by(|a, b| {
+                    let order = match column {
+                        0 => a.path.cmp(&b.path),
+                        1 | 4 => a.last_ms.total_cmp(&b.last_ms),
+                        2 => a.average_ms.total_cmp(&b.average_ms),
+                        _ => a.peak_ms.total_cmp(&b.peak_ms),
+                    };
+                    match direction {
+                        TableSortDirection::Ascending => order,
+                        TableSortDirection::Descending => order.reverse(),
+                    }
+                });
+            }
+
+            let indent_by_depth = profiler.sort.is_none_or(|(column, _)| column == 0);
+            for entry in rows {
+                ui.table_next_row();
+                ui.table_next_column();
+                match indent_by_depth {
+                    true => ui.text(format!("{}{}", "  ".repeat(entry.depth), entry.name())),
+                    false => ui.text(&entry.path),
+                }
+                ui.table_next_column();
+                ui.text(format!("{:.3}", entry.last_ms));
+                ui.table_next_column();
+                ui.text(format!("{:.3}", entry.average_ms));
+                ui.table_next_column();
+                ui.text(format!("{:.3}", entry.peak_ms));
+                ui.table_next_column();
+                ui.text(format!(
+                    "{:.1}",
+                    entry.last_ms / profiler.frame_ms.max(f32::EPSILON) * 100.0
+                ));
+            }
+        });
+}
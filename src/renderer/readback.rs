@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use log::{error, info, warn};
+
+use crate::renderer::{FrameContext, Renderer};
+use crate::tasks::{self, TaskKind};
+
+// copies need rows aligned to this many bytes
+const ROW_ALIGNMENT: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ReadbackId(u64);
+
+// texels as the texture stores them, rows without the copy padding
+#[derive(Clone, Debug)]
+pub struct ReadbackTexture {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub bytes: Vec<u8>,
+}
+
+impl ReadbackTexture {
+    // 8 bit color formats, float ones would need tonemapping first
+    pub fn is_color(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+                | wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+        )
+    }
+
+    pub fn to_rgba(&self) -> Option<image::RgbaImage> {
+        if !Self::is_color(self.format) {
+            error!("can't turn a {:?} readback into an image", self.format);
+            return None;
+        }
+        let bgra = matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let mut pixels = self.bytes.clone();
+        if bgra {
+            // a row per item, screenshots are large enough to be worth spreading
+            let row_bytes = (self.width as usize * 4).max(4);
+            let mut rows: Vec<&mut [u8]> = pixels.chunks_exact_mut(row_bytes).collect();
+            tasks::pool().parallel_for(&mut rows, |row| {
+                row.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+            });
+        }
+        image::RgbaImage::from_raw(self.width, self.height, pixels)
+    }
+}
+
+struct Layout {
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    row_bytes: u32,
+    padded_row: u32,
+}
+
+struct PendingReadback {
+    id: ReadbackId,
+    buffer: wgpu::Buffer,
+    layout: Layout,
+    mapped: Option<Arc<OnceLock<Result<(), wgpu::BufferAsyncError>>>>, // once submitted
+}
+
+// copies gpu data into mappable buffers and maps them once the frame is submitted.
+// the renderer polls without blocking every frame, results wait until taken
+#[derive(Default)]
+pub struct ReadbackQueue {
+    pending: Vec<PendingReadback>,
+    done: HashMap<ReadbackId, ReadbackTexture>,
+    next_id: u64,
+}
+
+impl ReadbackQueue {
+    // records a copy of a texture, or a rect of it, into the encoder.
+    // only uncompressed single plane formats
+    pub fn read_texture(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        rect: Option<[u32; 4]>,
+    ) -> Option<ReadbackId> {
+        let id = self.reserve();
+        self.read_texture_into(id, device, encoder, texture, rect)
+            .then_some(id)
+    }
+
+    pub(super) fn reserve(&mut self) -> ReadbackId {
+        let id = ReadbackId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    pub(super) fn read_texture_into(
+        &mut self,
+        id: ReadbackId,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        rect: Option<[u32; 4]>,
+    ) -> bool {
+        let format = texture.format();
+        let Some(texel_bytes) = format.block_copy_size(None) else {
+            error!("can't read back {:?} textures", format);
+            return false;
+        };
+        let [x, y, width, height] = rect.unwrap_or([0, 0, texture.width(), texture.height()]);
+        // caller supplied, so a huge rect mustn't overflow past the check
+        let inside = |start: u32, length: u32, size: u32| {
+            length > 0 && start.checked_add(length).is_some_and(|end| end <= size)
+        };
+        if !inside(x, width, texture.width()) || !inside(y, height, texture.height()) {
+            warn!(
+                "readback rect {:?} is outside the texture",
+                [x, y, width, height]
+            );
+            return false;
+        }
+
+        let padded = width.checked_mul(texel_bytes).and_then(|row_bytes| {
+            Some((
+                row_bytes,
+                row_bytes.checked_next_multiple_of(ROW_ALIGNMENT)?,
+            ))
+        });
+        let Some((row_bytes, padded_row)) = padded else {
+            warn!("readback rows of {} texels are too large", width);
+            return false;
+        };
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.push(
+            id,
+            buffer,
+            Layout {
+                width,
+                height,
+                format,
+                row_bytes,
+                padded_row,
+            },
+        );
+        true
+    }
+
+    pub fn is_ready(&self, id: ReadbackId) -> bool {
+        self.done.contains_key(&id)
+    }
+
+    pub fn take(&mut self, id: ReadbackId) -> Option<ReadbackTexture> {
+        self.done.remove(&id)
+    }
+
+    pub fn is_pending(&self, id: ReadbackId) -> bool {
+        self.pending.iter().any(|pending| pending.id == id)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn push(&mut self, id: ReadbackId, buffer: wgpu::Buffer, layout: Layout) {
+        self.pending.push(PendingReadback {
+            id,
+            buffer,
+            layout,
+            mapped: None,
+        });
+    }
+
+    // maps every copy recorded so far, call after submitting the encoders they went into
+    pub(super) fn submitted(&mut self) {
+        for pending in self.pending.iter_mut().filter(|p| p.mapped.is_none()) {
+            let mapped = Arc::new(OnceLock::new());
+            let result = mapped.clone();
+            pending
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |mapping| {
+                    let _ = result.set(mapping);
+                });
+            pending.mapped = Some(mapped);
+        }
+    }
+
+    // picks up whatever the gpu finished, never blocks
+    pub(super) fn poll(&mut self, device: &wgpu::Device) {
+        if self.pending.is_empty() {
+            return;
+        }
+        if let Err(e) = device.poll(wgpu::PollType::Poll) {
+            error!("failed to poll readbacks: {}", e);
+        }
+        self.collect();
+    }
+
+    // blocks until one readback is done, for tools and tests
+    pub(super) fn wait(
+        &mut self,
+        device: &wgpu::Device,
+        id: ReadbackId,
+    ) -> Option<ReadbackTexture> {
+        if self.is_pending(id) {
+            self.submitted();
+            if let Err(e) = device.poll(wgpu::PollType::Wait) {
+                error!("failed to wait for readback: {}", e);
+            }
+            self.collect();
+        }
+        self.take(id)
+    }
+
+    fn collect(&mut self) {
+        let mut index = 0;
+        while index < self.pending.len() {
+            let result = self.pending[index]
+                .mapped
+                .as_ref()
+                .and_then(|mapped| mapped.get());
+            match result {
+                None => index += 1,
+                Some(Err(e)) => {
+                    error!("failed to map readback buffer: {}", e);
+                    self.pending.remove(index);
+                }
+                Some(Ok(())) => {
+                    let pending = self.pending.remove(index);
+                    let data = pending.read();
+                    self.done.insert(pending.id, data);
+                }
+            }
+        }
+    }
+}
+
+impl PendingReadback {
+    fn read(&self) -> ReadbackTexture {
+        let layout = &self.layout;
+        let mut bytes = Vec::with_capacity(layout.row_bytes as usize * layout.height as usize);
+        for row in self
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks(layout.padded_row as usize)
+        {
+            bytes.extend_from_slice(&row[..layout.row_bytes as usize]);
+        }
+        self.buffer.unmap();
+        ReadbackTexture {
+            width: layout.width,
+            height: layout.height,
+            format: layout.format,
+            bytes,
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // blocks, only for tools and tests. the frame loop polls readbacks on its own
+    #[cfg(feature = "golden-tests")]
+    pub fn wait_readback(&mut self, id: ReadbackId) -> Option<ReadbackTexture> {
+        self.readbacks.wait(&self.device, id)
+    }
+
+    // a copy of the next frame as the player sees it, without the debug ui.
+    // written to path once the gpu is done with it, usually a frame or two later
+    pub fn request_screenshot(&mut self, path: impl Into<PathBuf>) -> ReadbackId {
+        let id = self.readbacks.reserve();
+        self.screenshots.push(id);
+        self.screenshot_saves.push((id, path.into()));
+        id
+    }
+
+    pub(super) fn capture_screenshots(&mut self, context: &mut FrameContext) {
+        if self.screenshots.is_empty() {
+            return;
+        }
+        let texture = match &context.frame {
+            Some(frame) => &frame.texture,
+            None => match &self.offscreen {
+                Some(texture) => texture,
+                None => return,
+            },
+        };
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            error!("this surface can't be read back, screenshots are unavailable");
+            self.screenshots.clear();
+            return;
+        }
+        for id in std::mem::take(&mut self.screenshots) {
+            self.readbacks
+                .read_texture_into(id, &self.device, &mut context.encoder, texture, None);
+        }
+    }
+
+    // after submitting the frame, maps its copies and collects finished ones
+    pub(super) fn poll_readbacks(&mut self) {
+        self.readbacks.submitted();
+        self.readbacks.poll(&self.device);
+        self.save_screenshots();
+    }
+
+    fn save_screenshots(&mut self) {
+        let (readbacks, screenshots) = (&mut self.readbacks, &self.screenshots);
+        self.screenshot_saves.retain(|(id, path)| {
+            if !readbacks.is_ready(*id) {
+                // dropped when the surface couldn't be copied
+                return screenshots.contains(id) || readbacks.is_pending(*id);
+            }
+            let Some(image) = readbacks.take(*id).and_then(|texture| texture.to_rgba()) else {
+                error!("can't save a screenshot of this surface format");
+                return false;
+            };
+            let path = path.clone();
+            tasks::pool().execute(TaskKind::Io, move || match image.save(&path) {
+                Ok(()) => info!("saved a screenshot to {}", path.display()),
+                Err(e) => error!("failed to write {}: {}", path.display(), e),
+            });
+            false
+        });
+    }
+}
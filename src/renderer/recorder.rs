@@ -0,0 +1,411 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::readback::{ReadbackId, ReadbackTexture};
+use crate::renderer::{FrameContext, Renderer};
+use crate::tasks::{self, TaskKind};
+
+const MAX_IN_FLIGHT: usize = 8; // encoded frames queued before new ones get dropped
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingFormat {
+    #[default]
+    PngSequence, // lossless, one file per frame
+    QoiSequence, // lossless too, larger files but encoded several times faster than png
+    Y4m,         // raw yuv 4:4:4 that ffmpeg and most editors open, large but cheap to write
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordingOptions {
+    pub format: RecordingFormat,
+    pub framerate: u32,
+    pub directory: PathBuf, // a recording-<time> file or directory goes in here
+    pub include_overlay: bool, // the imgui debug windows, text is always in
+    // steps the game by exactly one recorded frame per rendered frame. it plays
+    // slower than real time, but trailers come out without dropped or doubled frames
+    pub fixed_timestep: bool,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        RecordingOptions {
+            format: RecordingFormat::default(),
+            framerate: 60,
+            directory: PathBuf::from("recordings"),
+            include_overlay: false,
+            fixed_timestep: false,
+        }
+    }
+}
+
+// frames count every slot at the recording framerate, a frame rendered late fills
+// several. dropped ones were frozen on the next frame that made it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RecordingStatus {
+    pub frames: u64,
+    pub dropped: u64,
+    pub seconds: f32,
+}
+
+// y4m frames have to land in order, but encode on whichever io thread is free
+struct Y4mWriter {
+    file: BufWriter<File>,
+    next: u64,
+    waiting: BTreeMap<u64, (Vec<u8>, u64)>, // sequence -> frame, times to write it
+    failed: bool,
+}
+
+impl Y4mWriter {
+    fn create(path: &Path, width: u32, height: u32, framerate: u32) -> std::io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(
+            file,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=LIMITED",
+            width, height, framerate
+        )?;
+        Ok(Y4mWriter {
+            file,
+            next: 0,
+            waiting: BTreeMap::new(),
+            failed: false,
+        })
+    }
+
+    fn push(&mut self, sequence: u64, planes: Vec<u8>, repeats: u64) {
+        self.waiting.insert(sequence, (planes, repeats));
+        while let Some((planes, repeats)) = self.waiting.remove(&self.next) {
+            self.next += 1;
+            if self.failed {
+                continue;
+            }
+            let written = (0..repeats).try_for_each(|_| {
+                self.file.write_all(b"FRAME\n")?;
+                self.file.write_all(&planes)
+            });
+            if let Err(e) = written.and_then(|_| self.file.flush()) {
+                error!("failed to write recording: {}", e);
+                self.failed = true;
+            }
+        }
+    }
+}
+
+// bt.601 in limited range, what players assume for y4m without a colorspace tag
+fn yuv_planes(image: &image::RgbaImage) -> Vec<u8> {
+    let count = (image.width() * image.height()) as usize;
+    let mut planes = vec![0u8; count * 3];
+    let (y, chroma) = planes.split_at_mut(count);
+    let (u, v) = chroma.split_at_mut(count);
+
+    for (index, pixel) in image.pixels().enumerate() {
+        let [r, g, b] = [0, 1, 2].map(|channel| pixel[channel] as f32 / 255.0);
+        let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+        y[index] = (16.0 + 219.0 * luma).round() as u8;
+        u[index] = (128.0 + 112.0 * (b - luma) / 0.886).round() as u8;
+        v[index] = (128.0 + 112.0 * (r - luma) / 0.701).round() as u8;
+    }
+    planes
+}
+
+enum Sink {
+    Images(PathBuf, &'static str), // directory and extension, the encoder goes by it
+    Y4m(Arc<Mutex<Y4mWriter>>),
+}
+
+pub(super) struct Recorder {
+    options: RecordingOptions,
+    path: PathBuf,
+    sink: Option<Sink>, // opened with the first frame, when its size is known
+    size: Option<[u32; 2]>,
+    started: Instant,
+    slots: u64,    // frame slots at the recording framerate filled so far
+    owed: u64,     // slots of dropped frames, filled by the next frame encoded
+    sequence: u64, // captured frames, some fill several slots
+    dropped: u64,
+    in_flight: Arc<AtomicUsize>,
+    previous_delta: Option<Duration>, // restored after a fixed timestep recording
+    pending: VecDeque<(ReadbackId, u64)>, // copied, waiting on the gpu in order
+}
+
+impl Recorder {
+    // how many slots the frame being rendered fills, 0 when it's ahead of the framerate
+    fn due(&self) -> u64 {
+        if self.options.fixed_timestep {
+            return 1;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        ((elapsed * self.options.framerate as f64) as u64 + 1)
+            .saturating_sub(self.slots + self.owed)
+    }
+
+    fn open_sink(&mut self, width: u32, height: u32) -> bool {
+        if let Some(size) = self.size {
+            // a y4m stream has one size, image sequences get whatever was drawn
+            if size != [width, height] && self.options.format == RecordingFormat::Y4m {
+                warn!("resized from {:?} while recording, stopping", size);
+                return false;
+            }
+        } else {
+            let sink =
+                match self.options.format {
+                    RecordingFormat::PngSequence => fs::create_dir_all(&self.path)
+                        .map(|_| Sink::Images(self.path.clone(), "png")),
+                    RecordingFormat::QoiSequence => fs::create_dir_all(&self.path)
+                        .map(|_| Sink::Images(self.path.clone(), "qoi")),
+                    RecordingFormat::Y4m => fs::create_dir_all(&self.options.directory)
+                        .and_then(|_| {
+                            Y4mWriter::create(&self.path, width, height, self.options.framerate)
+                        })
+                        .map(|writer| Sink::Y4m(Arc::new(Mutex::new(writer)))),
+                };
+            match sink {
+                Ok(sink) => self.sink = Some(sink),
+                Err(e) => {
+                    error!("failed to create recording {}: {}", self.path.display(), e);
+                    return false;
+                }
+            }
+            self.size = Some([width, height]);
+        }
+        true
+    }
+
+    fn drop_frame(&mut self, repeats: u64) {
+        self.dropped += repeats;
+        self.owed += repeats;
+    }
+
+    // false when the recording can't be written at all
+    fn encode(&mut self, image: image::RgbaImage, repeats: u64) -> bool {
+        if self.in_flight.load(Ordering::Acquire) >= MAX_IN_FLIGHT {
+            self.drop_frame(repeats);
+            return true;
+        }
+        if !self.open_sink(image.width(), image.height()) {
+            return false;
+        }
+
+        let first_slot = self.slots;
+        let repeats = repeats + std::mem::take(&mut self.owed);
+        self.slots += repeats;
+        let Some(sink) = &self.sink else {
+            return false;
+        };
+        let in_flight = self.in_flight.clone();
+        in_flight.fetch_add(1, Ordering::AcqRel);
+
+        match sink {
+            Sink::Images(directory, extension) => {
+                let directory = directory.clone();
+                let extension = *extension;
+                tasks::pool().execute(TaskKind::Io, move || {
+                    let first = directory.join(format!("frame-{:06}.{}", first_slot, extension));
+                    match image.save(&first) {
+                        Ok(()) => {
+                            for slot in first_slot + 1..first_slot + repeats {
+                                let path =
+                                    directory.join(format!("frame-{:06}.{}", slot, extension));
+                                if let Err(e) = fs::copy(&first, &path) {
+                                    error!("failed to write {}: {}", path.display(), e);
+                                }
+                            }
+                        }
+                        Err(e) => error!("failed to write {}: {}", first.display(), e),
+                    }
+                    in_flight.fetch_sub(1, Ordering::AcqRel);
+                });
+            }
+            Sink::Y4m(writer) => {
+                let writer = writer.clone();
+                let sequence = self.sequence;
+                self.sequence += 1;
+                tasks::pool().execute(TaskKind::Io, move || {
+                    let planes = yuv_planes(&image);
+                    match writer.lock() {
+                        Ok(mut writer) => writer.push(sequence, planes, repeats),
+                        Err(_) => error!("recording writer poisoned, dropping frame"),
+                    }
+                    in_flight.fetch_sub(1, Ordering::AcqRel);
+                });
+            }
+        }
+        true
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // records what's drawn to a png sequence or a y4m video until stopped.
+    // returns where the recording goes
+    pub fn start_recording(&mut self, options: RecordingOptions) -> Option<PathBuf> {
+        if self.recorder.is_some() {
+            warn!("already recording");
+            return None;
+        }
+        if options.framerate == 0 {
+            error!("can't record at 0 fps");
+            return None;
+        }
+        if !self.is_headless()
+            && !self
+                .surface_config
+                .usage
+                .contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            error!("this surface can't be read back, recording is unavailable");
+            return None;
+        }
+        if !ReadbackTexture::is_color(self.surface_config.format) {
+            error!(
+                "can't record a {:?} surface, turn hdr output off to record",
+                self.surface_config.format
+            );
+            return None;
+        }
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+        let name = match options.format {
+            RecordingFormat::PngSequence | RecordingFormat::QoiSequence => {
+                format!("recording-{}", time)
+            }
+            RecordingFormat::Y4m => format!("recording-{}.y4m", time),
+        };
+        let path = options.directory.join(name);
+        info!(
+            "recording {:?} at {} fps to {}",
+            options.format,
+            options.framerate,
+            path.display()
+        );
+
+        let previous_delta = self.fixed_delta;
+        if options.fixed_timestep {
+            self.fixed_delta = Some(Duration::from_secs_f64(1.0 / options.framerate as f64));
+        }
+        self.recorder = Some(Recorder {
+            options,
+            path: path.clone(),
+            sink: None,
+            size: None,
+            started: Instant::now(),
+            slots: 0,
+            owed: 0,
+            sequence: 0,
+            dropped: 0,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            previous_delta,
+            pending: VecDeque::new(),
+        });
+        Some(path)
+    }
+
+    // frames still encoding finish on the io threads
+    pub fn stop_recording(&mut self) -> Option<RecordingStatus> {
+        self.write_recorded_frames(true);
+        let status = self.recording_status()?;
+        let recorder = self.recorder.take()?;
+        if recorder.options.fixed_timestep {
+            self.fixed_delta = recorder.previous_delta;
+        }
+
+        info!(
+            "recorded {} frames to {}, {} dropped",
+            status.frames,
+            recorder.path.display(),
+            status.dropped
+        );
+        Some(status)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn recording_status(&self) -> Option<RecordingStatus> {
+        let recorder = self.recorder.as_ref()?;
+        Some(RecordingStatus {
+            frames: recorder.slots,
+            dropped: recorder.dropped,
+            seconds: (recorder.slots + recorder.owed) as f32 / recorder.options.framerate as f32,
+        })
+    }
+
+    // copies the frame as it is now, before or after the overlay depending on the options
+    pub(super) fn capture_recording(&mut self, context: &mut FrameContext, overlay_drawn: bool) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        if recorder.options.include_overlay != overlay_drawn {
+            return;
+        }
+        let repeats = recorder.due();
+        if repeats == 0 {
+            return;
+        }
+        if recorder.pending.len() >= MAX_IN_FLIGHT {
+            recorder.drop_frame(repeats);
+            return;
+        }
+
+        let texture = match &context.frame {
+            Some(frame) => &frame.texture,
+            None => match &self.offscreen {
+                Some(texture) => texture,
+                None => return,
+            },
+        };
+        match self
+            .readbacks
+            .read_texture(&self.device, &mut context.encoder, texture, None)
+        {
+            Some(id) => recorder.pending.push_back((id, repeats)),
+            None => recorder.drop_frame(repeats),
+        }
+    }
+
+    // after submitting, hands frames the gpu is done copying to an io thread
+    pub(super) fn finish_recording_frame(&mut self) {
+        if !self.write_recorded_frames(false) {
+            self.stop_recording();
+        }
+    }
+
+    // in capture order, waiting for the rest when stopping. false when the
+    // recording can't be written
+    fn write_recorded_frames(&mut self, wait: bool) -> bool {
+        let Some(recorder) = &mut self.recorder else {
+            return true;
+        };
+        while let Some(&(id, repeats)) = recorder.pending.front() {
+            let data = match self.readbacks.is_pending(id) {
+                true if wait => self.readbacks.wait(&self.device, id),
+                true => break,
+                false => self.readbacks.take(id),
+            };
+            recorder.pending.pop_front();
+
+            let image = data.and_then(|texture| texture.to_rgba());
+            let written = match image {
+                Some(image) => recorder.encode(image, repeats),
+                None => {
+                    recorder.drop_frame(repeats);
+                    true
+                }
+            };
+            if !written {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,487 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context;
+use imgui::Ui;
+use log::{Level, error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::manifest::{AssetId, AssetManifest};
+use crate::assets::remote;
+use crate::platform::dialog::{self, SCENE_FILES};
+use crate::renderer::Renderer;
+use crate::renderer::hierarchy::EntitySnapshot;
+use crate::tasks::{self, Task, TaskKind};
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+// a scene owns whole sprite layers, what's in them when saving is the scene.
+// textures are kept by asset id and found again through the manifest on load,
+// whichever pool they end up in
+#[derive(Default, Serialize, Deserialize)]
+struct SceneFile {
+    layers: Vec<SceneLayer>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneLayer {
+    layer: usize,
+    #[serde(default)]
+    entities: Vec<SceneEntity>,
+}
+
+// the sprite's pool and texture are only written for textures the manifest
+// doesn't know, like generated ones, and only load in the same session
+#[derive(Serialize, Deserialize)]
+struct SceneEntity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    texture: Option<AssetId>,
+    #[serde(flatten)]
+    snapshot: EntitySnapshot,
+}
+
+struct OpenScene {
+    path: PathBuf,
+    layers: Vec<usize>,
+    saved: String, // the layers as last loaded or saved, edits make them differ
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    warned: bool, // about changes on disk while there are edits
+}
+
+// what the scene window shows, taken before imgui borrows the renderer
+pub(super) struct SceneStatus {
+    path: String,
+    dirty: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(super) enum SceneRequest {
+    Open, // picks the file in a dialog
+    Save,
+    SaveAs(PathBuf),
+    Browse, // save as, picking the path in a dialog
+    Revert,
+}
+
+#[derive(Default)]
+pub(super) struct SceneEditor {
+    open: Option<OpenScene>,
+    save_as: String,                   // the scene window's path field
+    assets: BTreeMap<AssetId, String>, // the manifest, see set_asset_manifest
+    download: Option<(PathBuf, Task<anyhow::Result<Vec<u8>>>)>,
+}
+
+impl<'a> Renderer<'a> {
+    // the asset ids scenes keep textures by, given again when the manifest changes
+    pub fn set_asset_manifest(&mut self, manifest: &AssetManifest) {
+        self.scene.assets = manifest
+            .iter()
+            .map(|(id, path)| (id, path.to_string()))
+            .collect();
+    }
+
+    // replaces everything in the layers the scene lists, they have to exist.
+    // returns the spawned sprites. an http url is downloaded on an io task, see
+    // assets::remote, and loaded the frame it arrives, nothing is spawned yet then
+    pub fn load_scene(&mut self, path: impl AsRef<Path>) -> anyhow::Result<Vec<usize>> {
+        let path = path.as_ref();
+        if let Some(url) = path.to_str().filter(|path| remote::is_url(path)) {
+            info!("downloading scene {}", url);
+            let url = url.to_string();
+            let download = tasks::pool().spawn(TaskKind::Io, move || remote::fetch(&url));
+            self.scene.download = Some((path.to_path_buf(), download));
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read scene {}", path.display()))?;
+        self.apply_scene(path, &text)
+    }
+
+    // whether a scene is still downloading
+    pub fn is_scene_downloading(&self) -> bool {
+        self.scene.download.is_some()
+    }
+
+    pub(super) fn poll_scene_download(&mut self) {
+        let Some((_, download)) = &mut self.scene.download else {
+            return;
+        };
+        let Some(result) = download.try_take() else {
+            return;
+        };
+        let Some((path, _)) = self.scene.download.take() else {
+            return;
+        };
+        let result = result
+            .and_then(|bytes| String::from_utf8(bytes).context("scene isn't utf-8"))
+            .and_then(|text| self.apply_scene(&path, &text));
+        if let Err(e) = result {
+            error!("failed to load scene {}: {:#}", path.display(), e);
+            self.notify(
+                format!("failed to load {}", path.display()),
+                Level::Error,
+                4.0,
+            );
+        }
+    }
+
+    fn apply_scene(&mut self, path: &Path, text: &str) -> anyhow::Result<Vec<usize>> {
+        let scene: SceneFile = toml::from_str(text)
+            .with_context(|| format!("failed to parse scene {}", path.display()))?;
+        if let Some(missing) = scene
+            .layers
+            .iter()
+            .find(|scene_layer| self.layers.get(scene_layer.layer).is_none())
+        {
+            anyhow::bail!(
+                "scene {} is for sprite layer {}, which doesn't exist",
+                path.display(),
+                missing.layer
+            );
+        }
+
+        let layers: Vec<usize> = scene.layers.iter().map(|layer| layer.layer).collect();
+        for &layer in layers.iter() {
+            let ids: Vec<usize> = self.layers[layer].instances.keys().copied().collect();
+            for id in ids {
+                self.remove_sprite(id);
+            }
+        }
+        let entities: Vec<(usize, EntitySnapshot)> = scene
+            .layers
+            .into_iter()
+            .flat_map(|layer| {
+                layer
+                    .entities
+                    .into_iter()
+                    .map(move |entity| (layer.layer, entity))
+            })
+            .filter_map(|(layer, entity)| Some((layer, self.resolve_scene_entity(entity)?)))
+            .collect();
+        let ids: Vec<usize> = entities
+            .iter()
+            .filter_map(|(layer, snapshot)| self.spawn_entity(*layer, snapshot))
+            .collect();
+
+        let saved = self.scene_text(&layers)?;
+        info!(
+            "loaded scene {}, {} entities in {} layers",
+            path.display(),
+            ids.len(),
+            layers.len()
+        );
+        self.scene.open = Some(OpenScene {
+            path: path.to_path_buf(),
+            layers,
+            saved,
+            modified: modified_time(path),
+            last_check: Instant::now(),
+            warned: false,
+        });
+        self.scene.save_as = path.display().to_string();
+        Ok(ids)
+    }
+
+    pub fn save_scene(&mut self) -> anyhow::Result<()> {
+        let path = self
+            .scene
+            .open
+            .as_ref()
+            .map(|scene| scene.path.clone())
+            .context("no scene is open")?;
+        self.save_scene_as(path)
+    }
+
+    // the open scene's layers to a new file, which is the scene's from then on
+    pub fn save_scene_as(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let layers = match &self.scene.open {
+            Some(scene) => scene.layers.clone(),
+            None => anyhow::bail!("no scene is open"),
+        };
+        let text = self.scene_text(&layers)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        fs::write(path, &text).with_context(|| format!("failed to write {}", path.display()))?;
+
+        info!("saved scene to {}", path.display());
+        self.notify(format!("saved {}", path.display()), Level::Info, 2.0);
+        if let Some(scene) = &mut self.scene.open {
+            scene.path = path.to_path_buf();
+            scene.saved = text;
+            scene.modified = modified_time(path);
+            scene.warned = false;
+        }
+        self.scene.save_as = path.display().to_string();
+        Ok(())
+    }
+
+    // throws the edits away and loads the file again
+    pub fn revert_scene(&mut self) -> anyhow::Result<Vec<usize>> {
+        let path = self
+            .scene
+            .open
+            .as_ref()
+            .map(|scene| scene.path.clone())
+            .context("no scene is open")?;
+        self.load_scene(path)
+    }
+
+    // leaves the sprites where they are, they just aren't a scene anymore
+    pub fn close_scene(&mut self) {
+        self.scene.open = None;
+    }
+
+    pub fn scene_path(&self) -> Option<&Path> {
+        self.scene.open.as_ref().map(|scene| scene.path.as_path())
+    }
+
+    // whether the scene's layers differ from the file, however they were changed
+    pub fn is_scene_dirty(&self) -> bool {
+        let Some(scene) = &self.scene.open else {
+            return false;
+        };
+        match self.scene_text(&scene.layers) {
+            Ok(text) => text != scene.saved,
+            Err(_) => true,
+        }
+    }
+
+    // a scene changed on disk is loaded again, unless that would lose edits
+    pub(super) fn poll_scene_file(&mut self) {
+        let Some(scene) = &mut self.scene.open else {
+            return;
+        };
+        if scene.last_check.elapsed() < CHECK_INTERVAL {
+            return;
+        }
+        scene.last_check = Instant::now();
+
+        let modified = modified_time(&scene.path);
+        if modified.is_none() || modified == scene.modified {
+            return;
+        }
+        let path = scene.path.clone();
+        if self.is_scene_dirty() {
+            let Some(scene) = &mut self.scene.open else {
+                return;
+            };
+            if !scene.warned {
+                warn!(
+                    "scene {} changed on disk, keeping the unsaved edits",
+                    scene.path.display()
+                );
+                scene.warned = true;
+            }
+            return;
+        }
+
+        info!("scene {} changed on disk, reloading", path.display());
+        if let Err(e) = self.load_scene(&path) {
+            error!("failed to reload scene: {:#}", e);
+            // don't retry until it changes again
+            if let Some(scene) = &mut self.scene.open {
+                scene.modified = modified;
+            }
+        }
+    }
+
+    pub(super) fn scene_status(&self) -> Option<SceneStatus> {
+        let scene = self.scene.open.as_ref()?;
+        Some(SceneStatus {
+            path: scene.path.display().to_string(),
+            dirty: self.is_scene_dirty(),
+        })
+    }
+
+    pub(super) fn apply_scene_request(&mut self, request: SceneRequest) {
+        let result = match request {
+            SceneRequest::Open => match dialog::pick_file(&[SCENE_FILES]) {
+                Some(path) => self.load_scene(path).map(|_| ()),
+                None => Ok(()),
+            },
+            SceneRequest::Save => self.save_scene(),
+            SceneRequest::SaveAs(path) => self.save_scene_as(path),
+            SceneRequest::Browse => {
+                let name = self
+                    .scene_path()
+                    .and_then(|path| path.file_name())
+                    .map_or("scene.toml".into(), |name| name.to_string_lossy());
+                match dialog::save_file(&[SCENE_FILES], &name) {
+                    Some(path) => self.save_scene_as(path),
+                    None => Ok(()),
+                }
+            }
+            SceneRequest::Revert => self.revert_scene().map(|_| ()),
+        };
+        if let Err(e) = result {
+            error!("{:#}", e);
+        }
+    }
+
+    fn scene_text(&self, layers: &[usize]) -> anyhow::Result<String> {
+        let scene = SceneFile {
+            layers: layers
+                .iter()
+                .map(|&layer| SceneLayer {
+                    layer,
+                    entities: self
+                        .layers
+                        .get(layer)
+                        .map(|layer| {
+                            layer
+                                .instances
+                                .keys()
+                                .filter_map(|&id| self.snapshot_entity(id))
+                                .map(|snapshot| self.scene_entity(snapshot))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        };
+
+        // ids of the session aren't written for textures found by asset id
+        let mut scene = toml::Table::try_from(&scene)?;
+        for layer in scene_tables(&mut scene, "layers") {
+            for entity in scene_tables(layer, "entities") {
+                if entity.contains_key("texture")
+                    && let Some(toml::Value::Table(sprite)) = entity.get_mut("sprite")
+                {
+                    sprite.remove("pool");
+                    sprite.remove("texture");
+                }
+            }
+        }
+        Ok(toml::to_string(&scene)?)
+    }
+
+    fn scene_entity(&self, snapshot: EntitySnapshot) -> SceneEntity {
+        let sprite = snapshot.sprite;
+        let texture = self
+            .loaded_pools
+            .get(sprite.pool)
+            .and_then(|pool| pool.textures.get(sprite.texture))
+            .and_then(|slot| {
+                self.scene
+                    .assets
+                    .iter()
+                    .find(|(_, path)| **path == slot.name)
+            })
+            .map(|(id, _)| *id);
+        SceneEntity { texture, snapshot }
+    }
+
+    // points the sprite at the asset's texture, in whichever pool has it loaded
+    // or decoded on first draw. None when the manifest lost the asset
+    fn resolve_scene_entity(&mut self, entity: SceneEntity) -> Option<EntitySnapshot> {
+        let mut snapshot = entity.snapshot;
+        let Some(asset) = entity.texture else {
+            return Some(snapshot);
+        };
+        let Some(path) = self.scene.assets.get(&asset).cloned() else {
+            warn!(
+                "asset {} of a scene entity is not in the manifest, skipping it",
+                asset
+            );
+            return None;
+        };
+
+        let loaded = self
+            .loaded_pools
+            .iter()
+            .enumerate()
+            .find_map(|(pool, loaded_pool)| {
+                let index = loaded_pool
+                    .textures
+                    .iter()
+                    .position(|slot| slot.name == path)?;
+                Some((pool, index))
+            });
+        let (pool, texture) = loaded.unwrap_or_else(|| self.discovered_texture(&path));
+        snapshot.sprite.pool = pool;
+        snapshot.sprite.texture = texture;
+        Some(snapshot)
+    }
+}
+
+// the tables of an array in a table, none when it isn't one
+fn scene_tables<'t>(
+    table: &'t mut toml::Table,
+    key: &str,
+) -> impl Iterator<Item = &'t mut toml::Table> {
+    let values = match table.get_mut(key) {
+        Some(toml::Value::Array(values)) => values.as_mut_slice(),
+        _ => &mut [],
+    };
+    values.iter_mut().filter_map(toml::Value::as_table_mut)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+pub(super) fn scene_window(
+    ui: &Ui,
+    status: Option<&SceneStatus>,
+    editor: &mut SceneEditor,
+) -> Option<SceneRequest> {
+    // without dialogs there is nothing to do until the game loads a scene
+    if status.is_none() && !dialog::is_available() {
+        return None;
+    }
+
+    let mut request = None;
+    ui.window("scene")
+        .size([420.0, 110.0], imgui::Condition::FirstUseEver)
+        .position([100.0, 740.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            if dialog::is_available() {
+                if ui.button("open") {
+                    request = Some(SceneRequest::Open);
+                }
+                if status.is_some() {
+                    ui.same_line();
+                }
+            }
+            let Some(status) = status else {
+                ui.same_line();
+                ui.text_disabled("no scene open");
+                return;
+            };
+
+            let marker = if status.dirty { "*" } else { "" };
+            ui.text(format!("{}{}", status.path, marker));
+            if status.dirty {
+                if ui.button("save") {
+                    request = Some(SceneRequest::Save);
+                }
+                ui.same_line();
+                if ui.button("revert") {
+                    request = Some(SceneRequest::Revert);
+                }
+            } else {
+                ui.text_disabled("no unsaved edits");
+            }
+
+            ui.input_text("##save_as", &mut editor.save_as).build();
+            ui.same_line();
+            if ui.button("save as") && !editor.save_as.trim().is_empty() {
+                request = Some(SceneRequest::SaveAs(PathBuf::from(editor.save_as.trim())));
+            }
+            if dialog::is_available() {
+                ui.same_line();
+                if ui.button("browse") {
+                    request = Some(SceneRequest::Browse);
+                }
+            }
+        });
+    request
+}
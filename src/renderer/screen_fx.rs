@@ -0,0 +1,97 @@
+use log::warn;
+
+use crate::renderer::Renderer;
+use crate::util::tween::{Easing, Tween};
+
+// full screen feedback over the world, under the ui. composited by the grading
+// pass, which runs while any of them is showing
+pub(super) struct ScreenFx {
+    flash_color: [f32; 3],
+    flash: Tween<f32>, // 0..1 of flash_color over the scene
+    fade_color: [f32; 3],
+    fade: Tween<f32>,
+    chromatic: Tween<f32>, // pixels red and blue split apart at the edges
+}
+
+impl Default for ScreenFx {
+    fn default() -> Self {
+        ScreenFx {
+            flash_color: [1.0, 1.0, 1.0],
+            flash: Tween::new(0.0),
+            fade_color: [0.0, 0.0, 0.0],
+            fade: Tween::new(0.0),
+            chromatic: Tween::new(0.0),
+        }
+    }
+}
+
+impl ScreenFx {
+    pub(super) fn is_active(&self) -> bool {
+        [&self.flash, &self.fade, &self.chromatic]
+            .iter()
+            .any(|tween| tween.value() > 0.0 || tween.target() > 0.0)
+    }
+
+    // srgb color and amount of the flash and the fade, as the grading pass takes them
+    pub(super) fn flash(&self) -> [f32; 4] {
+        let [r, g, b] = self.flash_color;
+        [r, g, b, self.flash.value()]
+    }
+
+    pub(super) fn fade(&self) -> [f32; 4] {
+        let [r, g, b] = self.fade_color;
+        [r, g, b, self.fade.value()]
+    }
+
+    pub(super) fn chromatic(&self) -> f32 {
+        self.chromatic.value()
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // the screen jumps to color and fades back out over seconds, e.g. on a hit.
+    // the color's alpha is how far it covers the scene at the start
+    pub fn flash_screen(&mut self, color: [f32; 4], seconds: f32) {
+        let fx = &mut self.screen_fx;
+        fx.flash_color = [color[0], color[1], color[2]];
+        fx.flash.set(color[3].clamp(0.0, 1.0));
+        fx.flash.start(0.0, seconds, Easing::EaseOut);
+    }
+
+    // eases towards covering the scene with color by amount and stays there,
+    // e.g. to black between levels and back to 0 once the next one is in
+    pub fn fade_screen(&mut self, color: [f32; 3], amount: f32, seconds: f32, easing: Easing) {
+        let fx = &mut self.screen_fx;
+        // a fade to another color starts from the old color's amount
+        fx.fade_color = color;
+        fx.fade.start(amount.clamp(0.0, 1.0), seconds, easing);
+    }
+
+    pub fn screen_fade(&self) -> f32 {
+        self.screen_fx.fade.value()
+    }
+
+    // splits the color channels apart by pixels at the screen's edges, easing
+    // back together over seconds
+    pub fn pulse_chromatic_aberration(&mut self, pixels: f32, seconds: f32) {
+        if pixels < 0.0 {
+            warn!("chromatic aberration of {} pixels, ignoring it", pixels);
+            return;
+        }
+        let chromatic = &mut self.screen_fx.chromatic;
+        chromatic.set(pixels.max(chromatic.value()));
+        chromatic.start(0.0, seconds, Easing::EaseOut);
+    }
+
+    // drops every effect at once, fades included
+    pub fn clear_screen_fx(&mut self) {
+        self.screen_fx = ScreenFx::default();
+    }
+
+    pub(super) fn update_screen_fx(&mut self, dt_seconds: f32) {
+        let fx = &mut self.screen_fx;
+        fx.flash.update(dt_seconds);
+        fx.fade.update(dt_seconds);
+        fx.chromatic.update(dt_seconds);
+    }
+}
@@ -0,0 +1,292 @@
+use bytemuck::{Pod, Zeroable};
+use log::{error, info, warn};
+
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+
+const INITIAL_CAPACITY: usize = 256;
+// shapes pile up while no frame is drawn, e.g. on the loading screen
+const MAX_SHAPES: usize = 1 << 16;
+
+// how a shape is colored, the border sits inside its edge
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ShapeStyle {
+    pub fill: [f32; 4],
+    pub border_color: [f32; 4],
+    pub border_width: f32, // world pixels, 0 for none
+}
+
+impl ShapeStyle {
+    pub fn filled(fill: [f32; 4]) -> Self {
+        ShapeStyle {
+            fill,
+            border_color: [0.0; 4],
+            border_width: 0.0,
+        }
+    }
+
+    pub fn bordered(fill: [f32; 4], border_color: [f32; 4], border_width: f32) -> Self {
+        ShapeStyle {
+            fill,
+            border_color,
+            border_width,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct SdfParams {
+    resolution: [f32; 2],
+    _pad: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct SdfShape {
+    center: [f32; 2], // physical pixels
+    half_size: [f32; 2],
+    fill: [f32; 4],
+    border_color: [f32; 4],
+    radius: f32,
+    border: f32,
+    ring: f32, // thickness, 0 fills the shape
+    _pad: f32,
+}
+
+struct SdfGpu {
+    layout: wgpu::BindGroupLayout,
+    params: wgpu::Buffer,
+    shapes: wgpu::Buffer,
+    capacity: usize,
+    bind_group: wgpu::BindGroup,
+}
+
+// immediate mode shapes drawn from signed distances, so edges stay smooth at
+// any size. one instanced quad each over the triangle shapes, the panels and
+// buttons of the game ui are made of them
+#[derive(Default)]
+pub(super) struct SdfShapes {
+    shapes: Vec<SdfShape>,
+    gpu: Option<SdfGpu>,
+    overflowed: bool,
+}
+
+impl<'a> Renderer<'a> {
+    // x, y, width, height in world pixels, radius of the corners
+    pub fn draw_sdf_rect(&mut self, camera: usize, rect: [f32; 4], radius: f32, style: ShapeStyle) {
+        let [x, y, w, h] = rect;
+        let center = [x + w * 0.5, y + h * 0.5];
+        self.queue_sdf_shape(camera, center, [w * 0.5, h * 0.5], radius, 0.0, style);
+    }
+
+    pub fn draw_sdf_circle(
+        &mut self,
+        camera: usize,
+        center: [f32; 2],
+        radius: f32,
+        style: ShapeStyle,
+    ) {
+        self.queue_sdf_shape(camera, center, [radius; 2], radius, 0.0, style);
+    }
+
+    // a circle hollowed out to a band of thickness inside radius
+    pub fn draw_sdf_ring(
+        &mut self,
+        camera: usize,
+        center: [f32; 2],
+        radius: f32,
+        thickness: f32,
+        style: ShapeStyle,
+    ) {
+        let thickness = thickness.clamp(f32::EPSILON, radius.max(f32::EPSILON));
+        self.queue_sdf_shape(camera, center, [radius; 2], radius, thickness, style);
+    }
+
+    fn queue_sdf_shape(
+        &mut self,
+        camera: usize,
+        center: [f32; 2],
+        half_size: [f32; 2],
+        radius: f32,
+        ring: f32,
+        style: ShapeStyle,
+    ) {
+        let Some(camera) = self.cameras.get(camera) else {
+            warn!("no camera {}", camera);
+            return;
+        };
+        let sdf = &mut self.sdf_shapes;
+        if sdf.shapes.len() >= MAX_SHAPES {
+            if !sdf.overflowed {
+                warn!(
+                    "more than {} sdf shapes queued, dropping the rest",
+                    MAX_SHAPES
+                );
+                sdf.overflowed = true;
+            }
+            return;
+        }
+        let zoom = camera.zoom;
+        sdf.shapes.push(SdfShape {
+            center: camera.world_to_screen(center),
+            half_size: half_size.map(|half| half.abs() * zoom),
+            fill: style.fill,
+            border_color: style.border_color,
+            radius: radius.max(0.0) * zoom,
+            border: style.border_width.max(0.0) * zoom,
+            ring: ring * zoom,
+            _pad: 0.0,
+        });
+    }
+
+    pub(super) fn render_sdf_shapes(&mut self, context: &mut FrameContext) {
+        let count = self.sdf_shapes.shapes.len();
+        if count == 0 {
+            return;
+        }
+        self.sdf_shapes.overflowed = false;
+
+        // hdr changes drop the pipeline with the other scene ones
+        let has_pipeline = self.pipelines.contains_key(&PipelineType::SdfShapes);
+        let gpu = self.sdf_shapes.gpu.take().filter(|_| has_pipeline);
+        let Some(mut gpu) = gpu.or_else(|| self.create_sdf_gpu()) else {
+            self.sdf_shapes.shapes.clear();
+            return;
+        };
+        if count > gpu.capacity {
+            gpu.capacity = count.next_power_of_two();
+            info!("growing sdf shape buffer to {}", gpu.capacity);
+            gpu.shapes = self.create_sdf_shape_buffer(gpu.capacity);
+            gpu.bind_group = self.create_sdf_bind_group(&gpu.layout, &gpu.params, &gpu.shapes);
+        }
+
+        let params = SdfParams {
+            resolution: self.viewport_size(),
+            _pad: [0.0; 2],
+        };
+        self.queue
+            .write_buffer(&gpu.params, 0, bytemuck::cast_slice(&[params]));
+        self.queue.write_buffer(
+            &gpu.shapes,
+            0,
+            bytemuck::cast_slice(&self.sdf_shapes.shapes),
+        );
+
+        if let Some(pipeline) = self.pipelines.get(&PipelineType::SdfShapes) {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("SDF Shape Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &context.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            pass.set_pipeline(pipeline);
+            self.apply_viewport(&mut pass);
+            pass.set_bind_group(0, &gpu.bind_group, &[]);
+            pass.draw(0..6, 0..count as u32);
+
+            self.frame_dump
+                .pass("SDF Shape Render Pass", PassKind::Render, "frame", false);
+            self.frame_dump.draw(|| {
+                DrawRecord::new(DrawCall::Draw, PipelineType::SdfShapes.label())
+                    .bind_group("sdf shapes")
+                    .vertices(0..6)
+                    .instances(0..count as u32)
+            });
+        }
+
+        self.sdf_shapes.gpu = Some(gpu);
+        self.sdf_shapes.shapes.clear();
+    }
+
+    fn create_sdf_gpu(&mut self) -> Option<SdfGpu> {
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("SDF Shape Bind Group Layout"),
+                entries: &[
+                    buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                    buffer_entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                ],
+            });
+
+        match self.create_pipeline(&PipelineType::SdfShapes, &[&layout], &[]) {
+            Ok(pipeline) => {
+                self.pipelines.insert(PipelineType::SdfShapes, pipeline);
+            }
+            Err(e) => {
+                error!("failed to create sdf shape pipeline: {}", e);
+                return None;
+            }
+        }
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SDF Shape Params Buffer"),
+            size: std::mem::size_of::<SdfParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let shapes = self.create_sdf_shape_buffer(INITIAL_CAPACITY);
+        let bind_group = self.create_sdf_bind_group(&layout, &params, &shapes);
+
+        Some(SdfGpu {
+            layout,
+            params,
+            shapes,
+            capacity: INITIAL_CAPACITY,
+            bind_group,
+        })
+    }
+
+    fn create_sdf_shape_buffer(&self, capacity: usize) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SDF Shape Buffer"),
+            size: (capacity * std::mem::size_of::<SdfShape>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_sdf_bind_group(
+        &self,
+        layout: &wgpu::BindGroupLayout,
+        params: &wgpu::Buffer,
+        shapes: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("SDF Shape Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: shapes.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
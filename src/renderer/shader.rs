@@ -0,0 +1,122 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use log::{Level, error};
+
+use crate::renderer::Renderer;
+
+// a wgsl error with where it happened, shown in the log and the imgui error window
+#[derive(Clone, Debug)]
+pub struct ShaderDiagnostic {
+    pub label: String,
+    pub message: String,
+    pub line: Option<u32>, // 1-based
+    pub column: Option<u32>,
+    pub report: String, // the full report with the offending source lines
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{}:{}:{}: {}", self.label, line, column, self.message)
+            }
+            _ => write!(f, "{}: {}", self.label, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ShaderDiagnostic {}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Invalid(ShaderDiagnostic), // caught by naga before wgpu saw it
+    Device(wgpu::Error),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::Invalid(diagnostic) => diagnostic.fmt(f),
+            ShaderError::Device(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+// parses and validates like wgpu would, without a device
+pub fn validate_wgsl(label: &str, source: &str) -> Result<(), ShaderDiagnostic> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| {
+        let location = e.location(source);
+        ShaderDiagnostic {
+            label: label.to_string(),
+            message: e.message().to_string(),
+            line: location.map(|l| l.line_number),
+            column: location.map(|l| l.line_position),
+            report: e.emit_to_string_with_path(source, label),
+        }
+    })?;
+
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    );
+    validator.validate(&module).map_err(|e| {
+        let location = e.location(source);
+        ShaderDiagnostic {
+            label: label.to_string(),
+            message: e.as_inner().to_string(),
+            line: location.map(|l| l.line_number),
+            column: location.map(|l| l.line_position),
+            report: e.emit_to_string_with_path(source, label),
+        }
+    })?;
+
+    Ok(())
+}
+
+impl<'a> Renderer<'a> {
+    // for shaders that come from user code or files, the engine's own are checked by ci
+    pub fn create_shader_module(
+        &mut self,
+        label: &str,
+        source: &str,
+    ) -> Result<wgpu::ShaderModule, ShaderError> {
+        if let Err(diagnostic) = validate_wgsl(label, source) {
+            self.report_shader_error(diagnostic.clone());
+            return Err(ShaderError::Invalid(diagnostic));
+        }
+
+        // anything naga let through but the device still rejects
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(Cow::Owned(source.to_string())),
+            });
+
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(e) => {
+                error!("failed to create shader {}: {}", label, e);
+                Err(ShaderError::Device(e))
+            }
+            None => Ok(module),
+        }
+    }
+
+    pub fn shader_errors(&self) -> &[ShaderDiagnostic] {
+        &self.shader_errors
+    }
+
+    pub fn clear_shader_errors(&mut self) {
+        self.shader_errors.clear();
+    }
+
+    fn report_shader_error(&mut self, diagnostic: ShaderDiagnostic) {
+        error!("shader error in {}\n{}", diagnostic, diagnostic.report);
+        self.notify(format!("shader error in {}", diagnostic), Level::Error, 5.0);
+        self.shader_errors.push(diagnostic);
+    }
+}
@@ -0,0 +1,347 @@
+use bytemuck::{Pod, Zeroable};
+use log::{error, info, warn};
+
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+
+const INITIAL_CAPACITY: usize = 1024; // vertices
+// shapes pile up while no frame is drawn, e.g. on the loading screen
+const MAX_VERTICES: usize = 1 << 18;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct ShapeParams {
+    resolution: [f32; 2],
+    _pad: [f32; 2],
+}
+
+// a world space position and its color
+pub(super) type ShapePoint = ([f32; 2], [f32; 4]);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ShapeVertex {
+    position: [f32; 2], // physical pixels
+    color: [f32; 4],
+}
+
+struct ShapeGpu {
+    params: wgpu::Buffer,
+    vertices: wgpu::Buffer,
+    capacity: usize,
+    bind_group: wgpu::BindGroup,
+}
+
+// immediate mode filled shapes in world space, for prototyping without
+// textures. queued during the frame as triangles, drawn over the sprites in one
+// call and gone after
+#[derive(Default)]
+pub(super) struct Shapes {
+    vertices: Vec<ShapeVertex>,
+    gpu: Option<ShapeGpu>,
+    overflowed: bool,
+}
+
+// for games, the engine's own overlays are lines and textured sprites
+#[allow(dead_code)]
+impl<'a> Renderer<'a> {
+    // x, y, width, height in world pixels
+    pub fn draw_rect(&mut self, camera: usize, rect: [f32; 4], color: [f32; 4]) {
+        let [x, y, w, h] = rect;
+        let corners = [[x, y], [x + w, y], [x + w, y + h], [x, y + h]];
+        self.draw_polygon(camera, &corners, color);
+    }
+
+    // segments follow the radius on screen so big circles stay round
+    pub fn draw_circle(&mut self, camera: usize, center: [f32; 2], radius: f32, color: [f32; 4]) {
+        let Some(zoom) = self.cameras.get(camera).map(|camera| camera.zoom) else {
+            warn!("no camera {}", camera);
+            return;
+        };
+        let segments = ((radius * zoom).abs() * 0.5).clamp(12.0, 96.0) as usize;
+        let points: Vec<[f32; 2]> = (0..segments)
+            .map(|i| {
+                let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+                [
+                    center[0] + radius * angle.cos(),
+                    center[1] + radius * angle.sin(),
+                ]
+            })
+            .collect();
+        self.draw_polygon(camera, &points, color);
+    }
+
+    // width in world pixels, scaled with the zoom unlike debug lines
+    pub fn draw_line(
+        &mut self,
+        camera: usize,
+        start: [f32; 2],
+        end: [f32; 2],
+        color: [f32; 4],
+        width: f32,
+    ) {
+        let delta = [end[0] - start[0], end[1] - start[1]];
+        let length = (delta[0] * delta[0] + delta[1] * delta[1]).sqrt();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let half = width * 0.5 / length;
+        let normal = [-delta[1] * half, delta[0] * half];
+        let corners = [
+            [start[0] + normal[0], start[1] + normal[1]],
+            [end[0] + normal[0], end[1] + normal[1]],
+            [end[0] - normal[0], end[1] - normal[1]],
+            [start[0] - normal[0], start[1] - normal[1]],
+        ];
+        self.draw_polygon(camera, &corners, color);
+    }
+
+    // filled, convex or not, as long as the edges don't cross
+    pub fn draw_polygon(&mut self, camera: usize, points: &[[f32; 2]], color: [f32; 4]) {
+        if points.len() < 3 {
+            return;
+        }
+        let triangles: Vec<[[f32; 2]; 3]> = triangulate(points)
+            .into_iter()
+            .map(|triangle| triangle.map(|i| points[i]))
+            .collect();
+        self.queue_shape_triangles(camera, &triangles, color);
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // world space triangles wound either way, all or none of them are queued
+    pub(super) fn queue_shape_triangles(
+        &mut self,
+        camera: usize,
+        triangles: &[[[f32; 2]; 3]],
+        color: [f32; 4],
+    ) {
+        let colored: Vec<[ShapePoint; 3]> = triangles
+            .iter()
+            .map(|triangle| triangle.map(|point| (point, color)))
+            .collect();
+        self.queue_colored_triangles(camera, &colored);
+    }
+
+    // the color of each corner blends across the triangle
+    pub(super) fn queue_colored_triangles(&mut self, camera: usize, triangles: &[[ShapePoint; 3]]) {
+        let Some(camera) = self.cameras.get(camera) else {
+            warn!("no camera {}", camera);
+            return;
+        };
+        let shapes = &mut self.shapes;
+        if shapes.vertices.len() + triangles.len() * 3 > MAX_VERTICES {
+            if !shapes.overflowed {
+                warn!(
+                    "more than {} shape vertices queued, dropping the rest",
+                    MAX_VERTICES
+                );
+                shapes.overflowed = true;
+            }
+            return;
+        }
+        for triangle in triangles {
+            // counter clockwise once y is flipped into clip space
+            let [a, b, c] = triangle.map(|(point, color)| ShapeVertex {
+                position: camera.world_to_screen(point),
+                color,
+            });
+            let [b, c] = match cross(a.position, b.position, c.position) {
+                area if area < 0.0 => [b, c],
+                area if area > 0.0 => [c, b],
+                _ => continue,
+            };
+            shapes.vertices.extend([a, b, c]);
+        }
+    }
+
+    pub(super) fn render_shapes(&mut self, context: &mut FrameContext) {
+        let count = self.shapes.vertices.len();
+        if count == 0 {
+            return;
+        }
+        self.shapes.overflowed = false;
+
+        // hdr changes drop the pipeline with the other scene ones
+        let has_pipeline = self.pipelines.contains_key(&PipelineType::Shapes);
+        let gpu = self.shapes.gpu.take().filter(|_| has_pipeline);
+        let Some(mut gpu) = gpu.or_else(|| self.create_shape_gpu()) else {
+            self.shapes.vertices.clear();
+            return;
+        };
+        if count > gpu.capacity {
+            gpu.capacity = count.next_power_of_two();
+            info!("growing shape vertex buffer to {}", gpu.capacity);
+            gpu.vertices = self.create_shape_vertex_buffer(gpu.capacity);
+        }
+
+        let params = ShapeParams {
+            resolution: self.viewport_size(),
+            _pad: [0.0; 2],
+        };
+        self.queue
+            .write_buffer(&gpu.params, 0, bytemuck::cast_slice(&[params]));
+        self.queue.write_buffer(
+            &gpu.vertices,
+            0,
+            bytemuck::cast_slice(&self.shapes.vertices),
+        );
+
+        if let Some(pipeline) = self.pipelines.get(&PipelineType::Shapes) {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Shape Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &context.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            pass.set_pipeline(pipeline);
+            self.apply_viewport(&mut pass);
+            pass.set_bind_group(0, &gpu.bind_group, &[]);
+            pass.set_vertex_buffer(0, gpu.vertices.slice(..));
+            pass.draw(0..count as u32, 0..1);
+
+            self.frame_dump
+                .pass("Shape Render Pass", PassKind::Render, "frame", false);
+            self.frame_dump.draw(|| {
+                DrawRecord::new(DrawCall::Draw, PipelineType::Shapes.label())
+                    .bind_group("shapes")
+                    .vertices(0..count as u32)
+                    .instances(0..1)
+            });
+        }
+
+        self.shapes.gpu = Some(gpu);
+        self.shapes.vertices.clear();
+    }
+
+    fn create_shape_gpu(&mut self) -> Option<ShapeGpu> {
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Shape Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let vertex_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ShapeVertex>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+        };
+        match self.create_pipeline(&PipelineType::Shapes, &[&layout], &[vertex_layout]) {
+            Ok(pipeline) => {
+                self.pipelines.insert(PipelineType::Shapes, pipeline);
+            }
+            Err(e) => {
+                error!("failed to create shape pipeline: {}", e);
+                return None;
+            }
+        }
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shape Params Buffer"),
+            size: std::mem::size_of::<ShapeParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shape Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            }],
+        });
+
+        Some(ShapeGpu {
+            params,
+            vertices: self.create_shape_vertex_buffer(INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            bind_group,
+        })
+    }
+
+    fn create_shape_vertex_buffer(&self, capacity: usize) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shape Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<ShapeVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+// twice the signed area of the triangle, positive when counter clockwise with y up
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn contains(triangle: [[f32; 2]; 3], point: [f32; 2]) -> bool {
+    let [a, b, c] = triangle;
+    let sides = [cross(a, b, point), cross(b, c, point), cross(c, a, point)];
+    sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+}
+
+// ear clipping, indices into points. crossing edges fall back to a fan for
+// whatever is left instead of looping forever
+fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let area: f32 = (0..points.len())
+        .map(|i| {
+            let [a, b] = [points[i], points[(i + 1) % points.len()]];
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum();
+    let winding = area.signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let [prev, at, next] = [
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            ];
+            let triangle = [points[prev], points[at], points[next]];
+            cross(triangle[0], triangle[1], triangle[2]) * winding > 0.0
+                && !remaining
+                    .iter()
+                    .filter(|other| ![prev, at, next].contains(other))
+                    .any(|&other| contains(triangle, points[other]))
+        });
+        let Some(i) = ear else {
+            break;
+        };
+        triangles.push([
+            remaining[(i + count - 1) % count],
+            remaining[i],
+            remaining[(i + 1) % count],
+        ]);
+        remaining.remove(i);
+    }
+    for i in 1..remaining.len().saturating_sub(1) {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
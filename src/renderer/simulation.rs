@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use imgui::Ui;
+use log::info;
+
+use crate::renderer::Renderer;
+
+// a step's length when there is no fixed delta, the sim_tick_ms cvar
+const DEFAULT_TICK: Duration = Duration::from_micros(16_667);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum SimulationRequest {
+    Play,
+    Pause,
+    Step,
+}
+
+// whether game states and particles advance. paused, the frame is still drawn
+// and every step lets one fixed tick through
+pub(super) struct Simulation {
+    paused: bool,
+    steps: u32,
+    ticks: u64,                          // stepped while paused, for the debug window
+    current: Option<(u64, Option<f32>)>, // frame index and what it advanced by
+    tick: Duration,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation {
+            paused: false,
+            steps: 0,
+            ticks: 0,
+            current: None,
+            tick: DEFAULT_TICK,
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn pause_simulation(&mut self) {
+        if !self.simulation.paused {
+            info!("pausing the simulation");
+        }
+        self.simulation.paused = true;
+    }
+
+    pub fn resume_simulation(&mut self) {
+        if self.simulation.paused {
+            info!("resuming the simulation");
+        }
+        self.simulation.paused = false;
+        self.simulation.steps = 0;
+    }
+
+    pub fn is_simulation_paused(&self) -> bool {
+        self.simulation.paused
+    }
+
+    // one fixed tick on a coming frame, pausing first when running
+    pub fn step_simulation(&mut self) {
+        self.pause_simulation();
+        self.simulation.steps += 1;
+    }
+
+    // how long a step is, the fixed delta when there is one
+    pub fn simulation_tick_length(&self) -> Duration {
+        self.fixed_delta.unwrap_or(self.simulation.tick)
+    }
+
+    // a step's length without a fixed delta
+    pub fn set_simulation_tick_length(&mut self, tick: Duration) {
+        self.simulation.tick = tick.max(Duration::from_micros(100));
+    }
+
+    // seconds the game should update by this frame, None while paused between
+    // steps. stays the same for the whole frame however often it's asked
+    pub fn simulation_tick(&mut self) -> Option<f32> {
+        if let Some((frame, tick)) = self.simulation.current
+            && frame == self.frame_index
+        {
+            return tick;
+        }
+
+        let simulation = &mut self.simulation;
+        let tick = if !simulation.paused {
+            Some(self.delta_time.as_secs_f32())
+        } else if simulation.steps > 0 {
+            simulation.steps -= 1;
+            simulation.ticks += 1;
+            Some(self.fixed_delta.unwrap_or(simulation.tick).as_secs_f32())
+        } else {
+            None
+        };
+        simulation.current = Some((self.frame_index, tick));
+        tick
+    }
+
+    pub(super) fn apply_simulation_request(&mut self, request: SimulationRequest) {
+        match request {
+            SimulationRequest::Play => self.resume_simulation(),
+            SimulationRequest::Pause => self.pause_simulation(),
+            SimulationRequest::Step => self.step_simulation(),
+        }
+    }
+}
+
+impl Simulation {
+    // play, pause and step buttons, for the debug window
+    pub(super) fn controls(&self, ui: &Ui) -> Option<SimulationRequest> {
+        let mut request = None;
+        match self.paused {
+            true => {
+                if ui.button("play") {
+                    request = Some(SimulationRequest::Play);
+                }
+            }
+            false => {
+                if ui.button("pause") {
+                    request = Some(SimulationRequest::Pause);
+                }
+            }
+        }
+        ui.same_line();
+        if ui.button("step") {
+            request = Some(SimulationRequest::Step);
+        }
+        if self.paused {
+            ui.same_line();
+            ui.text(format!("paused, {} ticks stepped", self.ticks));
+        }
+        request
+    }
+}
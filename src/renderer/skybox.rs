@@ -0,0 +1,326 @@
+use bytemuck::{Pod, Zeroable};
+use log::{error, info};
+
+use crate::assets::cubemap::DecodedCubemap;
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::lighting::is_linear;
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+
+// where the sky is seen from. it has no position, only a direction, so it
+// stays put behind the world however the 2d cameras move
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SkyView {
+    pub yaw: f32,   // radians, 0 looks down -z and positive turns right
+    pub pitch: f32, // radians, positive looks up
+    pub fov: f32,   // vertical, in radians
+}
+
+impl Default for SkyView {
+    fn default() -> Self {
+        SkyView {
+            yaw: 0.0,
+            pitch: 0.0,
+            fov: 60f32.to_radians(),
+        }
+    }
+}
+
+impl SkyView {
+    // forward, right and up, the last two scaled to the edges of a view
+    fn basis(&self, aspect: f32) -> [[f32; 3]; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let forward = [sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch];
+        let right = [cos_yaw, 0.0, sin_yaw];
+        let up = [
+            right[1] * forward[2] - right[2] * forward[1],
+            right[2] * forward[0] - right[0] * forward[2],
+            right[0] * forward[1] - right[1] * forward[0],
+        ];
+
+        let half_height = (self.fov.clamp(0.01, 3.0) * 0.5).tan();
+        let half_width = half_height * aspect;
+        [
+            forward,
+            right.map(|axis| axis * half_width),
+            up.map(|axis| axis * half_height),
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct SkyParams {
+    forward: [f32; 4],
+    right: [f32; 4],
+    up: [f32; 4],
+    intensity: f32,
+    output_linear: u32,
+    _pad: [f32; 2],
+}
+
+struct SkyboxTexture {
+    name: String,
+    view: wgpu::TextureView,
+}
+
+struct SkyboxGpu {
+    layout: wgpu::BindGroupLayout,
+    params: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group: Option<wgpu::BindGroup>, // for the current cubemap
+}
+
+// a cubemap drawn behind the world, before any sprite. it goes into the
+// scene target, so hdr skies keep their range until the grading pass
+// tonemaps them with everything else
+#[derive(Default)]
+pub(super) struct Skybox {
+    view: SkyView,
+    exposure: f32, // in stops, on top of the scene's
+    cubemap: Option<SkyboxTexture>,
+    gpu: Option<SkyboxGpu>,
+}
+
+impl<'a> Renderer<'a> {
+    // None takes the sky away, the background shows again
+    pub fn set_skybox(&mut self, cubemap: Option<DecodedCubemap>) {
+        if let Some(gpu) = &mut self.skybox.gpu {
+            gpu.bind_group = None;
+        }
+        let Some(cubemap) = cubemap else {
+            self.skybox.cubemap = None;
+            return;
+        };
+
+        info!(
+            "using skybox {}, {}x{} faces",
+            cubemap.name, cubemap.size, cubemap.size
+        );
+        let view = self.create_cubemap_view(&cubemap);
+        self.skybox.cubemap = Some(SkyboxTexture {
+            name: cubemap.name,
+            view,
+        });
+    }
+
+    pub fn has_skybox(&self) -> bool {
+        self.skybox.cubemap.is_some()
+    }
+
+    pub fn sky_view(&self) -> SkyView {
+        self.skybox.view
+    }
+
+    pub fn set_sky_view(&mut self, view: SkyView) {
+        self.skybox.view = view;
+    }
+
+    pub fn skybox_exposure(&self) -> f32 {
+        self.skybox.exposure
+    }
+
+    // in stops, e.g. to dim a bright hdr sky to match the sprites
+    pub fn set_skybox_exposure(&mut self, stops: f32) {
+        self.skybox.exposure = stops;
+    }
+
+    fn create_cubemap_view(&self, cubemap: &DecodedCubemap) -> wgpu::TextureView {
+        let size = wgpu::Extent3d {
+            width: cubemap.size,
+            height: cubemap.size,
+            depth_or_array_layers: 6,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&cubemap.name),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: cubemap.texture_format(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        for (layer, face) in cubemap.faces.iter().enumerate() {
+            self.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                face,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(cubemap.format.bytes_per_pixel() * cubemap.size),
+                    rows_per_image: Some(cubemap.size),
+                },
+                wgpu::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..size
+                },
+            );
+        }
+
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(&format!("{}_view", cubemap.name)),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        })
+    }
+
+    pub(super) fn render_skybox(&mut self, context: &mut FrameContext) {
+        if self.skybox.cubemap.is_none() {
+            return;
+        }
+
+        // hdr changes drop the pipeline with the other scene ones
+        let has_pipeline = self.pipelines.contains_key(&PipelineType::Skybox);
+        let gpu = self.skybox.gpu.take().filter(|_| has_pipeline);
+        let Some(mut gpu) = gpu.or_else(|| self.create_skybox_gpu()) else {
+            return;
+        };
+        let Some(cubemap) = &self.skybox.cubemap else {
+            return;
+        };
+
+        let [width, height] = self.viewport_size();
+        let [forward, right, up] = self.skybox.view.basis(width / height.max(1.0));
+        let params = SkyParams {
+            forward: [forward[0], forward[1], forward[2], 0.0],
+            right: [right[0], right[1], right[2], 0.0],
+            up: [up[0], up[1], up[2], 0.0],
+            intensity: self.skybox.exposure.exp2(),
+            output_linear: is_linear(self.scene_format()) as u32,
+            _pad: [0.0; 2],
+        };
+        self.queue
+            .write_buffer(&gpu.params, 0, bytemuck::cast_slice(&[params]));
+        let bind_group = gpu.bind_group.get_or_insert_with(|| {
+            self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Skybox Bind Group"),
+                layout: &gpu.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: gpu.params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&cubemap.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&gpu.sampler),
+                    },
+                ],
+            })
+        });
+
+        if let Some(pipeline) = self.pipelines.get(&PipelineType::Skybox) {
+            let mut pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Skybox Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &context.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            pass.set_pipeline(pipeline);
+            self.apply_viewport(&mut pass);
+            pass.set_bind_group(0, &*bind_group, &[]);
+            pass.draw(0..3, 0..1);
+
+            self.frame_dump
+                .pass("Skybox Render Pass", PassKind::Render, "frame", false);
+            self.frame_dump.draw(|| {
+                DrawRecord::new(DrawCall::Draw, PipelineType::Skybox.label())
+                    .bind_group(format!("skybox {}", cubemap.name))
+                    .vertices(0..3)
+            });
+        }
+
+        self.skybox.gpu = Some(gpu);
+    }
+
+    fn create_skybox_gpu(&mut self) -> Option<SkyboxGpu> {
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Skybox Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        match self.create_pipeline(&PipelineType::Skybox, &[&layout], &[]) {
+            Ok(pipeline) => {
+                self.pipelines.insert(PipelineType::Skybox, pipeline);
+            }
+            Err(e) => {
+                error!("failed to create skybox pipeline: {}", e);
+                return None;
+            }
+        }
+
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Skybox Params Buffer"),
+            size: std::mem::size_of::<SkyParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // seams between faces only disappear with linear filtering
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Skybox Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Some(SkyboxGpu {
+            layout,
+            params,
+            sampler,
+            bind_group: None,
+        })
+    }
+}
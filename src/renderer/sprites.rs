@@ -0,0 +1,659 @@
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use log::{error, info};
+
+use crate::renderer::debug_view::DebugView;
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::layer::{BlendMode, LayerMask};
+use crate::renderer::pipeline::{PipelineType, STENCIL_FORMAT};
+use crate::renderer::{FrameContext, Renderer};
+
+const INITIAL_CAPACITY: usize = 1024;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct CameraUniform {
+    resolution: [f32; 2],
+    position: [f32; 2],
+    zoom: f32,
+    snap: u32,
+    _pad: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct SpriteInstance {
+    position: [f32; 2],
+    size: [f32; 2],
+    color: [f32; 4],
+    pivot: [f32; 2],
+    rotation: f32,
+    outline: f32, // screen pixels the silhouette grows by, 0 draws the sprite itself
+    uv: [f32; 4],
+    id: u32, // sprite id plus one, for the pick buffer
+    flash: f32,
+    dissolve: f32,
+    palette_row: u32,
+    flash_color: [f32; 4],
+    dissolve_color: [f32; 4],
+}
+
+// consecutive sprites sharing a texture, camera and layer state, drawn with one call
+#[derive(Clone)]
+struct SpriteBatch {
+    camera: usize,
+    pool: usize,
+    texture: usize,
+    clip: Option<[u32; 4]>,
+    mask: LayerMask,
+    blend: BlendMode,
+    outline: bool,
+    palette: Option<(usize, usize)>, // pool and texture
+    instances: Range<u32>,
+}
+
+impl SpriteBatch {
+    fn pipeline(&self) -> PipelineType {
+        if self.outline {
+            return PipelineType::SpriteOutline;
+        }
+        match (self.mask, self.palette.is_some()) {
+            (LayerMask::None | LayerMask::Distort, false) => PipelineType::Sprites(self.blend),
+            (LayerMask::None | LayerMask::Distort, true) => PipelineType::SpritePalette(self.blend),
+            // the mask only looks at alpha, which indexed textures keep
+            (LayerMask::Write(_), _) => PipelineType::SpriteMask,
+            (LayerMask::Test(_), false) => PipelineType::SpriteMasked(self.blend),
+            (LayerMask::Test(_), true) => PipelineType::SpritePaletteMasked(self.blend),
+        }
+    }
+
+    fn continues(&self, other: &SpriteBatch) -> bool {
+        self.camera == other.camera
+            && self.pool == other.pool
+            && self.texture == other.texture
+            && self.clip == other.clip
+            && self.mask == other.mask
+            && self.blend == other.blend
+            && self.outline == other.outline
+            && self.palette == other.palette
+    }
+
+    fn stencil_reference(&self) -> u32 {
+        match self.mask {
+            LayerMask::None | LayerMask::Distort => 0,
+            LayerMask::Write(value) | LayerMask::Test(value) => value as u32,
+        }
+    }
+}
+
+fn rgb([r, g, b]: [f32; 3]) -> [f32; 4] {
+    [r, g, b, 1.0]
+}
+
+// extends the last batch when the instance can share its draw call
+fn push_instance(
+    instances: &mut Vec<SpriteInstance>,
+    batches: &mut Vec<SpriteBatch>,
+    instance: SpriteInstance,
+    mut batch: SpriteBatch,
+) {
+    let index = instances.len() as u32;
+    instances.push(instance);
+
+    match batches.last_mut() {
+        Some(last) if last.continues(&batch) => last.instances.end = index + 1,
+        _ => {
+            batch.instances = index..index + 1;
+            batches.push(batch);
+        }
+    }
+}
+
+pub(super) struct SpriteRenderer {
+    layout: wgpu::BindGroupLayout,
+    cameras: wgpu::Buffer, // one uniform per camera at camera_stride
+    camera_stride: u64,
+    camera_capacity: usize,
+    instances: wgpu::Buffer,
+    capacity: usize,
+    bind_group: wgpu::BindGroup,
+    batches: Vec<SpriteBatch>,
+    stencil: wgpu::TextureView,
+    stencil_size: [u32; 2],
+}
+
+impl<'a> Renderer<'a> {
+    fn create_sprite_renderer(&mut self) -> Option<SpriteRenderer> {
+        let layout = self
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sprite Bind Group Layout"),
+                entries: &[
+                    // camera uniform, offset per batch
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // sprite instances
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        if !self.create_sprite_pipeline(PipelineType::Sprites(BlendMode::Alpha), &layout) {
+            return None;
+        }
+
+        let alignment = self.device.limits().min_uniform_buffer_offset_alignment as u64;
+        let camera_stride =
+            (std::mem::size_of::<CameraUniform>() as u64).div_ceil(alignment) * alignment;
+        let camera_capacity = self.cameras.len();
+        let cameras = self.create_camera_buffer(camera_stride, camera_capacity);
+
+        let instances = self.create_sprite_instance_buffer(INITIAL_CAPACITY);
+        let bind_group = self.create_sprite_bind_group(&layout, &cameras, &instances);
+        let stencil_size = [self.surface_config.width, self.surface_config.height];
+        let stencil = self.create_stencil_view(stencil_size);
+
+        Some(SpriteRenderer {
+            layout,
+            cameras,
+            camera_stride,
+            camera_capacity,
+            instances,
+            capacity: INITIAL_CAPACITY,
+            bind_group,
+            batches: Vec::new(),
+            stencil,
+            stencil_size,
+        })
+    }
+
+    // blend and mask variants are only built once a batch needs them
+    fn create_sprite_pipeline(
+        &mut self,
+        pipeline_type: PipelineType,
+        layout: &wgpu::BindGroupLayout,
+    ) -> bool {
+        let texture_layout = self
+            .bind_group_layouts
+            .first()
+            .expect("there is no bind group layout")
+            .clone();
+
+        let layouts: &[&wgpu::BindGroupLayout] = match pipeline_type.samples_palette() {
+            true => &[&texture_layout, layout, &texture_layout],
+            false => &[&texture_layout, layout],
+        };
+        match self.create_pipeline(&pipeline_type, layouts, &[]) {
+            Ok(pipeline) => {
+                self.pipelines.insert(pipeline_type, pipeline);
+                true
+            }
+            Err(e) => {
+                error!("failed to create sprite pipeline: {}", e);
+                false
+            }
+        }
+    }
+
+    fn create_stencil_view(&self, size: [u32; 2]) -> wgpu::TextureView {
+        self.device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Sprite Stencil Texture"),
+                size: wgpu::Extent3d {
+                    width: size[0],
+                    height: size[1],
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: STENCIL_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Sprite Stencil View"),
+                ..Default::default()
+            })
+    }
+
+    fn create_sprite_instance_buffer(&self, capacity: usize) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Instance Buffer"),
+            size: (capacity * std::mem::size_of::<SpriteInstance>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_camera_buffer(&self, stride: u64, capacity: usize) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Camera Buffer"),
+            size: stride * capacity as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_sprite_bind_group(
+        &self,
+        layout: &wgpu::BindGroupLayout,
+        cameras: &wgpu::Buffer,
+        instances: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sprite Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: cameras,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: instances.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    // sorts every layer, culled to what its camera sees, and groups its sprites
+    // into per texture batches
+    fn build_sprite_batches(&self) -> (Vec<SpriteInstance>, Vec<SpriteBatch>) {
+        let mut layers: Vec<_> = self.layers.iter().collect();
+        layers.sort_by_key(|layer| layer.zindex);
+
+        let mut instances = Vec::new();
+        let mut batches: Vec<SpriteBatch> = Vec::new();
+
+        let sprites = layers.iter().flat_map(|layer| {
+            self.visible_sprites(layer)
+                .into_iter()
+                .filter(|(_, sprite)| sprite.visibility.is_drawn())
+                .map(|(id, sprite)| (*layer, id, sprite))
+        });
+
+        for (layer, id, sprite) in sprites {
+            let pivot = self.sprite_pivot(sprite);
+            let opacity = sprite.visibility.opacity.clamp(0.0, 1.0);
+            let instance = |color: [f32; 4], outline| SpriteInstance {
+                position: sprite.position,
+                size: sprite.size,
+                color: [color[0], color[1], color[2], color[3] * opacity],
+                pivot,
+                rotation: sprite.rotation,
+                outline,
+                uv: sprite.uv,
+                id: id as u32 + 1,
+                flash: sprite.material.flash,
+                dissolve: sprite.material.dissolve,
+                palette_row: sprite.palette.map_or(0, |palette| palette.row),
+                flash_color: rgb(sprite.material.flash_color),
+                dissolve_color: rgb(sprite.material.dissolve_color),
+            };
+            let batch = SpriteBatch {
+                camera: layer.camera,
+                pool: sprite.pool,
+                texture: sprite.texture,
+                clip: layer.clip,
+                mask: layer.mask,
+                blend: sprite.blend,
+                outline: false,
+                palette: sprite
+                    .palette
+                    .map(|palette| (palette.pool, palette.texture)),
+                instances: 0..0,
+            };
+
+            // drawn first so the sprite covers all but the grown edge
+            if layer.mask == LayerMask::None
+                && let Some(outline) = self.sprite_outline(id, sprite.outline)
+                && outline.width > 0.0
+            {
+                let outline_batch = SpriteBatch {
+                    blend: BlendMode::Alpha,
+                    outline: true,
+                    palette: None,
+                    ..batch.clone()
+                };
+                push_instance(
+                    &mut instances,
+                    &mut batches,
+                    instance(outline.color, outline.width),
+                    outline_batch,
+                );
+            }
+            push_instance(
+                &mut instances,
+                &mut batches,
+                instance(sprite.color, 0.0),
+                batch,
+            );
+        }
+
+        (instances, batches)
+    }
+
+    pub(super) fn prepare_sprites(&mut self) {
+        if self.layers.iter().all(|layer| layer.instances.is_empty()) {
+            if let Some(sprites) = &mut self.sprite_renderer {
+                sprites.batches.clear();
+            }
+            return;
+        }
+
+        if self.sprite_renderer.is_none() {
+            self.sprite_renderer = self.create_sprite_renderer();
+        }
+
+        let (instances, batches) = self.build_sprite_batches();
+
+        // reloads evicted textures before they are bound
+        for batch in batches.iter() {
+            self.touch_texture(batch.pool, batch.texture);
+            if let Some((pool, texture)) = batch.palette {
+                self.touch_texture(pool, texture);
+            }
+        }
+
+        let layout = self
+            .sprite_renderer
+            .as_ref()
+            .map(|sprites| sprites.layout.clone());
+        if let Some(layout) = layout {
+            for batch in batches.iter() {
+                if !self.pipelines.contains_key(&batch.pipeline()) {
+                    self.create_sprite_pipeline(batch.pipeline(), &layout);
+                }
+            }
+            let debug = PipelineType::SpriteDebug(self.debug_view);
+            if self.debug_view != DebugView::Off && !self.pipelines.contains_key(&debug) {
+                self.create_sprite_pipeline(debug, &layout);
+            }
+        }
+
+        let Some(mut sprites) = self.sprite_renderer.take() else {
+            return;
+        };
+
+        let mut rebind = false;
+        if instances.len() > sprites.capacity {
+            let capacity = instances.len().next_power_of_two();
+            info!("growing sprite instance buffer to {}", capacity);
+
+            sprites.instances = self.create_sprite_instance_buffer(capacity);
+            sprites.capacity = capacity;
+            rebind = true;
+        }
+        if self.cameras.len() > sprites.camera_capacity {
+            sprites.camera_capacity = self.cameras.len().next_power_of_two();
+            sprites.cameras =
+                self.create_camera_buffer(sprites.camera_stride, sprites.camera_capacity);
+            rebind = true;
+        }
+        let size = [self.surface_config.width, self.surface_config.height];
+        if sprites.stencil_size != size {
+            sprites.stencil = self.create_stencil_view(size);
+            sprites.stencil_size = size;
+        }
+        if rebind {
+            sprites.bind_group = self.create_sprite_bind_group(
+                &sprites.layout,
+                &sprites.cameras,
+                &sprites.instances,
+            );
+        }
+
+        let resolution = self.viewport_size();
+        for (i, camera) in self.cameras.iter().enumerate() {
+            let uniform = CameraUniform {
+                resolution,
+                position: camera.position,
+                zoom: camera.zoom,
+                snap: camera.is_snapped() as u32,
+                _pad: [0.0; 2],
+            };
+            self.queue.write_buffer(
+                &sprites.cameras,
+                i as u64 * sprites.camera_stride,
+                bytemuck::cast_slice(&[uniform]),
+            );
+        }
+        self.queue
+            .write_buffer(&sprites.instances, 0, bytemuck::cast_slice(&instances));
+
+        sprites.batches = batches;
+        self.sprite_renderer = Some(sprites);
+    }
+
+    pub(super) fn render_sprites(&mut self, context: &mut FrameContext) {
+        let has_batches = self.sprite_renderer.as_ref().is_some_and(|sprites| {
+            sprites
+                .batches
+                .iter()
+                .any(|batch| batch.mask != LayerMask::Distort)
+        });
+        // debug views replace the background along with the sprites
+        let load = match self.debug_view {
+            DebugView::Off => wgpu::LoadOp::Load,
+            _ => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+        };
+        if has_batches {
+            self.draw_sprite_batches(&mut context.encoder, &context.view, load, false);
+        }
+    }
+
+    // distort layers into the distortion pass' mask, cleared even without any
+    pub(super) fn render_distortion_mask(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        self.draw_sprite_batches(
+            encoder,
+            view,
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            true,
+        );
+    }
+
+    fn draw_sprite_batches(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        distort: bool,
+    ) {
+        let Some(sprites) = &self.sprite_renderer else {
+            return; // no sprites yet
+        };
+
+        let label = if distort {
+            "Distortion Mask Render Pass"
+        } else {
+            "Sprite Render Pass"
+        };
+        let target = if distort { "distortion mask" } else { "frame" };
+        let clears = matches!(load, wgpu::LoadOp::Clear(_));
+        self.frame_dump
+            .pass(label, PassKind::Render, target, clears);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &sprites.stencil,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        self.apply_viewport(&mut pass);
+
+        let batches = sprites
+            .batches
+            .iter()
+            .filter(|batch| (batch.mask == LayerMask::Distort) == distort);
+        for batch in batches {
+            // debug views ignore masks to show everything drawn, the
+            // distortion mask keeps its own pipelines
+            let pipeline_type = match self.debug_view {
+                view if view != DebugView::Off && !distort => PipelineType::SpriteDebug(view),
+                _ => batch.pipeline(),
+            };
+            let Some(pipeline) = self.pipelines.get(&pipeline_type) else {
+                error!("No {} pipeline for {:?}", pipeline_type.label(), batch.mask);
+                continue;
+            };
+            pass.set_pipeline(pipeline);
+            pass.set_stencil_reference(batch.stencil_reference());
+            self.draw_sprite_batch(&mut pass, sprites, batch, &pipeline_type);
+        }
+    }
+
+    // ids of every pickable sprite into an r32uint target the size of the surface,
+    // the same layers pick_sprite looks at
+    pub(super) fn draw_pick_batches(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let Some(layout) = self
+            .sprite_renderer
+            .as_ref()
+            .map(|sprites| sprites.layout.clone())
+        else {
+            return;
+        };
+        if !self.pipelines.contains_key(&PipelineType::SpritePick)
+            && !self.create_sprite_pipeline(PipelineType::SpritePick, &layout)
+        {
+            return;
+        }
+
+        let (Some(sprites), Some(pipeline)) = (
+            &self.sprite_renderer,
+            self.pipelines.get(&PipelineType::SpritePick),
+        ) else {
+            return;
+        };
+        self.frame_dump.pass(
+            "Sprite Pick Render Pass",
+            PassKind::Render,
+            "pick buffer",
+            true,
+        );
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Sprite Pick Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(pipeline);
+        self.apply_viewport(&mut pass);
+
+        let batches = sprites.batches.iter().filter(|batch| {
+            !batch.outline && matches!(batch.mask, LayerMask::None | LayerMask::Test(_))
+        });
+        for batch in batches {
+            self.draw_sprite_batch(&mut pass, sprites, batch, &PipelineType::SpritePick);
+        }
+    }
+
+    fn draw_sprite_batch(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        sprites: &SpriteRenderer,
+        batch: &SpriteBatch,
+        pipeline: &PipelineType, // for the frame dump
+    ) {
+        // scissor rects have to stay inside the target, and layer clips inside
+        // the viewport they're relative to
+        let [width, height] = sprites.stencil_size;
+        let [vx, vy, vw, vh] = self.viewport().map(|value| value as u32);
+        let (right, bottom) = ((vx + vw).min(width), (vy + vh).min(height));
+        let [x, y, w, h] = batch
+            .clip
+            .map_or([vx, vy, vw, vh], |[x, y, w, h]| [vx + x, vy + y, w, h]);
+        let (x, y) = (x.clamp(vx, right), y.clamp(vy, bottom));
+        let (w, h) = (w.min(right - x), h.min(bottom - y));
+        if w == 0 || h == 0 {
+            return;
+        }
+        pass.set_scissor_rect(x, y, w, h);
+
+        let offset = (batch.camera as u64 * sprites.camera_stride) as u32;
+        pass.set_bind_group(1, &sprites.bind_group, &[offset]);
+
+        let Some(texture) = self.texture(batch.pool, batch.texture) else {
+            error!("No texture {} in pool {}", batch.texture, batch.pool);
+            return;
+        };
+
+        pass.set_bind_group(0, &texture.bind_group, &[]);
+
+        let palette = batch.palette.filter(|_| pipeline.samples_palette());
+        if let Some((pool, index)) = palette {
+            let Some(palette) = self.texture(pool, index) else {
+                error!("No palette texture {} in pool {}", index, pool);
+                return;
+            };
+            pass.set_bind_group(2, &palette.bind_group, &[]);
+        }
+
+        pass.draw(0..6, batch.instances.clone());
+        self.frame_dump.draw(|| {
+            let mut record = DrawRecord::new(DrawCall::Draw, pipeline.label())
+                .bind_group(format!("texture {}/{}", batch.pool, batch.texture))
+                .bind_group(format!("sprite camera {}", batch.camera));
+            if let Some((pool, index)) = palette {
+                record = record.bind_group(format!("palette {}/{}", pool, index));
+            }
+            record
+                .vertices(0..6)
+                .instances(batch.instances.clone())
+                .scissor([x, y, w, h])
+                .stencil_reference(batch.stencil_reference())
+        });
+    }
+}
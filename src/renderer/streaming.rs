@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use log::debug;
+
+use crate::assets::pak;
+use crate::renderer::Renderer;
+use crate::tasks::{self, TaskKind};
+
+const UPLOAD_BUDGET: u64 = 4 * 1024 * 1024; // bytes of mip levels uploaded per frame
+
+struct MipRequest {
+    pool: usize,
+    index: usize,
+    name: String,
+    level: u32,
+}
+
+struct MipData {
+    pool: usize,
+    index: usize,
+    level: u32,
+    rgba: Option<Vec<u8>>,
+}
+
+// streams in the detailed mip levels of packed textures, one level per texture at
+// a time, until they match the size they are drawn at. levels are read by io
+// tasks and uploaded within a per frame budget
+#[derive(Default)]
+pub(super) struct MipStreamer {
+    results: Option<(Sender<MipData>, Receiver<MipData>)>,
+    in_flight: HashSet<(usize, usize)>,
+    screen_sizes: HashMap<(usize, usize), f32>, // largest on screen extent this frame, in pixels
+}
+
+impl MipStreamer {
+    fn results(&mut self) -> &(Sender<MipData>, Receiver<MipData>) {
+        self.results.get_or_insert_with(mpsc::channel)
+    }
+
+    fn request(&mut self, request: MipRequest) {
+        let sender = self.results().0.clone();
+        self.in_flight.insert((request.pool, request.index));
+
+        tasks::pool().execute(TaskKind::Io, move || {
+            let rgba = pak::mounted().and_then(|pak| pak.mip(&request.name, request.level));
+            // the renderer may be gone by now
+            _ = sender.send(MipData {
+                pool: request.pool,
+                index: request.index,
+                level: request.level,
+                rgba,
+            });
+        });
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // how large a texture is drawn this frame, the largest draw wins
+    pub(super) fn record_screen_size(&mut self, pool: usize, index: usize, extent: f32) {
+        let size = self.streamer.screen_sizes.entry((pool, index)).or_default();
+        *size = size.max(extent);
+    }
+
+    // textures waiting on a mip level
+    pub fn streaming_textures(&self) -> usize {
+        self.streamer.in_flight.len()
+    }
+
+    pub(super) fn record_sprite_sizes(&mut self) {
+        let mut sizes = Vec::new();
+        for layer in &self.layers {
+            let zoom = self
+                .cameras
+                .get(layer.camera)
+                .map_or(1.0, |camera| camera.zoom);
+            for sprite in layer.instances.values() {
+                if sprite.visibility.is_drawn() {
+                    let extent = sprite.size[0].abs().max(sprite.size[1].abs()) * zoom;
+                    sizes.push((sprite.pool, sprite.texture, extent));
+                }
+            }
+        }
+
+        for (pool, index, extent) in sizes {
+            self.record_screen_size(pool, index, extent);
+        }
+    }
+
+    pub(super) fn stream_textures(&mut self) {
+        self.upload_streamed_mips();
+
+        let screen_sizes: Vec<_> = self.streamer.screen_sizes.drain().collect();
+        for ((pool, index), extent) in screen_sizes {
+            if self.streamer.in_flight.contains(&(pool, index)) {
+                continue;
+            }
+
+            let Some(slot) = self
+                .loaded_pools
+                .get(pool)
+                .and_then(|pool| pool.textures.get(index))
+            else {
+                continue;
+            };
+            let Some(texture) = &slot.texture else {
+                continue;
+            };
+
+            // the level whose size is closest to the drawn size without going under
+            let size = texture.texture.size();
+            let ratio = size.width.max(size.height) as f32 / extent.max(1.0);
+            let wanted = (ratio.log2().floor().max(0.0) as u32).min(texture.mip_count - 1);
+            if texture.resident_mip <= wanted {
+                continue;
+            }
+
+            let request = MipRequest {
+                pool,
+                index,
+                name: slot.name.clone(),
+                level: texture.resident_mip - 1,
+            };
+            self.streamer.request(request);
+        }
+    }
+
+    fn upload_streamed_mips(&mut self) {
+        let Some((_, receiver)) = &self.streamer.results else {
+            return;
+        };
+
+        let mut uploaded = 0;
+        while uploaded < UPLOAD_BUDGET {
+            let data = match receiver.try_recv() {
+                Ok(data) => data,
+                // the streamer keeps a sender itself, it never disconnects
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+            };
+            self.streamer.in_flight.remove(&(data.pool, data.index));
+
+            let Some(rgba) = data.rgba else {
+                continue;
+            };
+            let Some(pool) = self.loaded_pools.get_mut(data.pool) else {
+                continue;
+            };
+            let Some(slot) = pool.textures.get_mut(data.index) else {
+                continue;
+            };
+
+            // the texture may have been evicted or reloaded since the request
+            match &mut slot.texture {
+                Some(texture) if texture.resident_mip == data.level + 1 => {
+                    debug!("streamed mip {} of {}", data.level, slot.name);
+                    texture.upload_mip(
+                        &self.device,
+                        &self.queue,
+                        &pool.layout,
+                        &slot.name,
+                        data.level,
+                        &rgba,
+                    );
+                    uploaded += rgba.len() as u64;
+                }
+                _ => {}
+            }
+        }
+    }
+}
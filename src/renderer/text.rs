@@ -1,10 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use glyphon::{Attrs, Cache, FontSystem, SwashCache, TextAtlas};
+use glyphon::{
+    Attrs, Cache, CacheKey, FontSystem, Metrics, SwashCache, SwashContent, TextArea, TextAtlas,
+};
+use log::{debug, error, warn};
 use wgpu::MultisampleState;
 use winit::dpi::PhysicalSize;
 
 use crate::renderer::Renderer;
+use crate::renderer::accessibility::AccessibilityEvent;
 
 pub(super) struct TextRenderer<'a> {
     pub(super) physical_size: PhysicalSize<u32>,
@@ -15,10 +19,276 @@ pub(super) struct TextRenderer<'a> {
     pub(super) viewport: glyphon::Viewport,
     pub(super) atlas: TextAtlas,
     pub(super) renderer: glyphon::TextRenderer,
-    pub(super) buffers: HashMap<String, glyphon::Buffer>,
+    pub(super) buffers: BTreeMap<usize, TextEntry>,
+    pub(super) next_id: usize,
+    shapes: HashMap<ShapeKey, ShapedText>,
+    shape_hits: u64,
+    shape_misses: u64,
+    pub(super) atlas_usage: AtlasUsage,
+}
+
+pub(super) struct TextEntry {
+    pub(super) buffer: glyphon::Buffer,
+    pub(super) position: Option<[f32; 2]>, // logical pixels, None is stacked
+    key: ShapeKey, // what the buffer should hold, shaped before the next draw when dirty
+    dirty: bool,
+}
+
+// everything shaping depends on, floats as bits so it can be hashed
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ShapeKey {
+    spans: Vec<(String, Option<[u8; 4]>)>,
+    font_size: u32,
+    line_height: u32,
+    width: Option<u32>,
+}
+
+// a shaped buffer other entries with the same key can copy instead of shaping
+struct ShapedText {
+    buffer: glyphon::Buffer,
+    last_used: u64, // frame
 }
 
 const COLOR_MODE: glyphon::ColorMode = glyphon::ColorMode::Accurate;
+const SHAPE_CACHE_CAPACITY: usize = 256;
+const ATLAS_INITIAL_SIZE: u32 = 256; // what glyphon starts its atlases at
+const ATLAS_NEARLY_FULL: f32 = 0.75;
+
+// one of glyphon's two atlas textures, mask glyphs or color ones like emoji
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TextAtlasPage {
+    pub size: u32,     // side in pixels
+    pub glyphs: usize, // drawn this frame
+    pub fill: f32,     // 0..1 of the page those glyphs cover
+}
+
+// glyphon keeps its atlases private, so this follows them from the glyphs
+// drawn: a page doubles like glyphon's once its glyphs no longer fit. packing
+// wastes some space, so the real fill is a bit higher
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TextAtlasStats {
+    pub mask: TextAtlasPage,
+    pub color: TextAtlasPage,
+    pub max_size: u32, // the device limit pages can't grow past
+}
+
+impl TextAtlasStats {
+    pub fn pages(&self) -> [TextAtlasPage; 2] {
+        [self.mask, self.color]
+    }
+
+    // a page that can't grow anymore and is mostly used, more text overflows it
+    pub fn is_nearly_full(&self) -> bool {
+        self.pages()
+            .iter()
+            .any(|page| page.size >= self.max_size && page.fill >= ATLAS_NEARLY_FULL)
+    }
+}
+
+pub(super) struct AtlasUsage {
+    stats: TextAtlasStats,
+    warned: bool,
+}
+
+impl AtlasUsage {
+    fn new(max_size: u32) -> Self {
+        let page = TextAtlasPage {
+            size: ATLAS_INITIAL_SIZE.min(max_size),
+            ..Default::default()
+        };
+        AtlasUsage {
+            stats: TextAtlasStats {
+                mask: page,
+                color: page,
+                max_size,
+            },
+            warned: false,
+        }
+    }
+
+    // the glyphs the areas draw, rasterized through the cache glyphon uses so
+    // it finds them there when it prepares
+    pub(super) fn measure(
+        &mut self,
+        font_system: &mut FontSystem,
+        cache: &mut SwashCache,
+        areas: &[TextArea],
+    ) {
+        let mut keys: HashSet<CacheKey> = HashSet::new();
+        for area in areas {
+            for run in area.buffer.layout_runs() {
+                for glyph in run.glyphs.iter() {
+                    keys.insert(glyph.physical((area.left, area.top), area.scale).cache_key);
+                }
+            }
+        }
+
+        let mut glyphs = [0usize; 2];
+        let mut area = [0u64; 2];
+        for key in keys {
+            let Some(image) = cache.get_image(font_system, key) else {
+                continue;
+            };
+            let pixels = image.placement.width as u64 * image.placement.height as u64;
+            if pixels == 0 {
+                continue; // spaces and the like take no room
+            }
+            let page = match image.content {
+                SwashContent::Color => 1,
+                SwashContent::Mask | SwashContent::SubpixelMask => 0,
+            };
+            glyphs[page] += 1;
+            area[page] += pixels;
+        }
+
+        let max_size = self.stats.max_size;
+        let pages = [&mut self.stats.mask, &mut self.stats.color];
+        for (page, (glyphs, area)) in pages.into_iter().zip(glyphs.into_iter().zip(area)) {
+            while area > page.size as u64 * page.size as u64 && page.size < max_size {
+                page.size = (page.size * 2).min(max_size);
+            }
+            page.glyphs = glyphs;
+            page.fill = area as f32 / (page.size as f32 * page.size as f32);
+        }
+
+        let nearly_full = self.stats.is_nearly_full();
+        if nearly_full && !self.warned {
+            warn!(
+                "text atlas is nearly full at {}px, more glyphs at once will overflow it",
+                max_size
+            );
+        }
+        self.warned = nearly_full;
+    }
+}
+
+impl TextEntry {
+    pub(super) fn new(text: &str, font_size: f32, line_height: f32, width: f32) -> Self {
+        let key = ShapeKey {
+            spans: vec![(text.to_string(), None)],
+            font_size: font_size.to_bits(),
+            line_height: line_height.to_bits(),
+            width: Some(width.to_bits()),
+        };
+        TextEntry {
+            buffer: glyphon::Buffer::new_empty(Metrics::relative(font_size, line_height)),
+            position: None,
+            key,
+            dirty: true,
+        }
+    }
+
+    // false when the spans are what the entry already shows
+    pub(super) fn set_spans(&mut self, spans: Vec<(String, Option<[u8; 4]>)>) -> bool {
+        if self.key.spans == spans {
+            return false;
+        }
+        self.key.spans = spans;
+        self.dirty = true;
+        true
+    }
+
+    fn set_width(&mut self, width: f32) {
+        let width = Some(width.to_bits());
+        if self.key.width != width {
+            self.key.width = width;
+            self.dirty = true;
+        }
+    }
+}
+
+impl<'a> TextRenderer<'a> {
+    // shapes every entry whose content or width changed, once per frame at most
+    pub(super) fn shape_dirty(&mut self, frame: u64) {
+        let mut dirty = Vec::new();
+        for (id, entry) in self.buffers.iter_mut() {
+            if entry.dirty {
+                entry.dirty = false;
+                dirty.push((*id, entry.key.clone()));
+            }
+        }
+
+        for (id, key) in dirty {
+            let buffer = match self.shapes.get_mut(&key) {
+                Some(shaped) => {
+                    shaped.last_used = frame;
+                    self.shape_hits += 1;
+                    shaped.buffer.clone()
+                }
+                None => {
+                    self.shape_misses += 1;
+                    let buffer = self.shape(&key);
+                    self.shapes.insert(
+                        key,
+                        ShapedText {
+                            buffer: buffer.clone(),
+                            last_used: frame,
+                        },
+                    );
+                    buffer
+                }
+            };
+            if let Some(entry) = self.buffers.get_mut(&id) {
+                entry.buffer = buffer;
+            }
+        }
+
+        if self.shapes.len() > SHAPE_CACHE_CAPACITY {
+            let mut ages: Vec<u64> = self
+                .shapes
+                .values()
+                .map(|shaped| shaped.last_used)
+                .collect();
+            ages.sort_unstable();
+            let cutoff = ages[self.shapes.len() - SHAPE_CACHE_CAPACITY];
+            self.shapes.retain(|_, shaped| shaped.last_used >= cutoff);
+            debug!("text shape cache trimmed to {}", self.shapes.len());
+        }
+    }
+
+    // a fresh buffer, so the content and the size are only shaped once together
+    fn shape(&mut self, key: &ShapeKey) -> glyphon::Buffer {
+        let metrics = Metrics::relative(
+            f32::from_bits(key.font_size),
+            f32::from_bits(key.line_height),
+        );
+        let mut buffer = glyphon::Buffer::new(&mut self.font_system, metrics);
+        buffer.set_size(&mut self.font_system, key.width.map(f32::from_bits), None);
+
+        let base_font = self.base_font.clone();
+        let spans = key.spans.iter().map(|(text, color)| {
+            let attrs = match color {
+                Some([r, g, b, a]) => base_font
+                    .clone()
+                    .color(glyphon::Color::rgba(*r, *g, *b, *a)),
+                None => base_font.clone(),
+            };
+            (text.as_str(), attrs)
+        });
+        buffer.set_rich_text(
+            &mut self.font_system,
+            spans,
+            &base_font,
+            glyphon::Shaping::Advanced,
+            None,
+        );
+        buffer
+    }
+
+    // stacked and positioned text wraps at the window's edge
+    pub(super) fn wrap_width(&self) -> f32 {
+        self.physical_size.width as f32 / self.scale_factor - 20.0
+    }
+
+    // after the window resized or moved to a display with another scale,
+    // only entries whose wrap width changed get shaped again
+    pub(super) fn resize_entries(&mut self) {
+        let width = self.wrap_width();
+        for entry in self.buffers.values_mut() {
+            entry.set_width(width);
+        }
+    }
+}
 
 impl<'a> Renderer<'a> {
     pub(super) fn create_text_renderer(
@@ -59,7 +329,85 @@ impl<'a> Renderer<'a> {
             viewport,
             atlas,
             renderer: text_renderer,
-            buffers: HashMap::new(),
+            buffers: BTreeMap::new(),
+            next_id: 0,
+            shapes: HashMap::new(),
+            shape_hits: 0,
+            shape_misses: 0,
+            atlas_usage: AtlasUsage::new(self.device.limits().max_texture_dimension_2d),
+        }
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn scale_factor(&self) -> f32 {
+        match &self.text_renderer {
+            Some(text_renderer) => text_renderer.scale_factor,
+            None => self
+                .window
+                .as_ref()
+                .map_or(1.0, |window| window.scale_factor() as f32),
+        }
+    }
+
+    // entries that copied an earlier shaping, entries that shaped, shapings kept
+    pub fn text_shape_stats(&self) -> (u64, u64, usize) {
+        self.text_renderer.as_ref().map_or((0, 0, 0), |text| {
+            (text.shape_hits, text.shape_misses, text.shapes.len())
+        })
+    }
+
+    pub fn text_atlas_stats(&self) -> Option<TextAtlasStats> {
+        Some(self.text_renderer.as_ref()?.atlas_usage.stats)
+    }
+
+    pub fn add_text_at(
+        &mut self,
+        text: &str,
+        font_size: f32,
+        line_height: f32,
+        position: [f32; 2],
+    ) -> Option<usize> {
+        let id = self.add_text(text, font_size, line_height)?;
+        self.set_text_position(id, Some(position));
+        Some(id)
+    }
+
+    pub fn set_text_position(&mut self, id: usize, position: Option<[f32; 2]>) {
+        let Some(text_renderer) = &mut self.text_renderer else {
+            return;
+        };
+
+        match text_renderer.buffers.get_mut(&id) {
+            Some(entry) => entry.position = position,
+            None => error!("no text with id {}", id),
+        }
+    }
+
+    // spans with their own rgba color, None uses the default color
+    pub fn set_text_spans(&mut self, id: usize, spans: &[(&str, Option<[u8; 4]>)]) {
+        let Some(text_renderer) = &mut self.text_renderer else {
+            return;
+        };
+
+        let Some(entry) = text_renderer.buffers.get_mut(&id) else {
+            error!("no text with id {}", id);
+            return;
+        };
+
+        let owned = spans
+            .iter()
+            .map(|(text, color)| (text.to_string(), *color))
+            .collect();
+        if !entry.set_spans(owned) {
+            return;
+        }
+
+        if self.is_accessibility_enabled() {
+            self.accessibility_event(AccessibilityEvent::TextChanged {
+                id,
+                text: spans.iter().map(|(text, _)| *text).collect(),
+            });
         }
     }
 }
@@ -0,0 +1,405 @@
+use std::sync::Arc;
+
+use imgui::{MouseButton, TextureId, Ui};
+use log::{error, info, warn};
+
+use crate::assets::NvTexturePool;
+use crate::assets::meta::TextureMeta;
+use crate::renderer::Renderer;
+use crate::ui::theme::Theme;
+use crate::world::collision::ColliderShape;
+
+const PREVIEW_SIZE: f32 = 256.0; // logical pixels the longest side gets
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum EditorTool {
+    #[default]
+    Pivot, // click to move it
+    Rect,    // drag out a collision rect
+    Polygon, // click the points, then close it
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum TextureEditorRequest {
+    Inspect(usize, usize), // pool, texture
+    Save,
+    Revert,
+}
+
+// sidecar editing for one texture at a time. the draft is what the panel shows,
+// the slot keeps what's saved until the draft is written to the sidecar
+#[derive(Default)]
+pub(super) struct TextureEditor {
+    target: Option<(usize, usize)>,
+    draft: TextureMeta,
+    preview: Option<TextureId>, // the texture registered with imgui
+    size: [f32; 2],             // texture pixels
+    tool: EditorTool,
+    drag_start: Option<[f32; 2]>, // texture pixels, while dragging a rect
+    points: Vec<[f32; 2]>,        // polygon being clicked together
+}
+
+impl TextureEditor {
+    pub(super) fn preview(&self) -> Option<TextureId> {
+        self.preview
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // opens the texture editor panel on a pool texture
+    pub fn inspect_texture(&mut self, pool: usize, index: usize) {
+        let Some(slot) = self
+            .loaded_pools
+            .get(pool)
+            .and_then(|pool| pool.textures.get(index))
+        else {
+            warn!("no texture {} in pool {} to inspect", index, pool);
+            return;
+        };
+        self.texture_editor.draft = slot.meta.clone();
+        self.texture_editor.target = Some((pool, index));
+        self.texture_editor.drag_start = None;
+        self.texture_editor.points.clear();
+        self.drop_texture_preview();
+    }
+
+    // the texture's import settings, written to its sidecar. a changed filter,
+    // color space or color key re-imports it
+    pub fn set_texture_meta(&mut self, pool: usize, index: usize, meta: TextureMeta) -> bool {
+        let Some(slot) = self
+            .loaded_pools
+            .get_mut(pool)
+            .and_then(|pool| pool.textures.get_mut(index))
+        else {
+            warn!("no texture {} in pool {}", index, pool);
+            return false;
+        };
+        if let Err(e) = meta.save(&slot.name) {
+            error!("failed to save import settings: {:#}", e);
+            return false;
+        }
+
+        let reimport = slot.meta.filter != meta.filter
+            || slot.meta.color_space != meta.color_space
+            || slot.meta.indexed != meta.indexed
+            || slot.meta.color_key != meta.color_key;
+        slot.meta = meta;
+        if reimport {
+            info!("import settings of {} changed, reloading it", slot.name);
+            self.reload_texture(pool, index);
+        }
+        true
+    }
+
+    // registers the inspected texture with imgui, once it's resident. imgui
+    // holds on to it while it's inspected, evicted or not
+    pub(super) fn prepare_texture_preview(&mut self) {
+        let editor = &mut self.texture_editor;
+        let (Some((pool, index)), None) = (editor.target, editor.preview) else {
+            return;
+        };
+        let Some(imgui) = &mut self.imgui_renderer else {
+            return;
+        };
+        let Some(texture) = self
+            .loaded_pools
+            .get(pool)
+            .and_then(|pool| pool.textures.get(index))
+            .and_then(|slot| slot.texture.as_ref())
+        else {
+            return; // evicted, drawing it reloads it
+        };
+
+        let size = texture.texture.size();
+        let config = imgui_wgpu::RawTextureConfig {
+            label: Some("Texture Editor Preview"),
+            sampler_desc: wgpu::SamplerDescriptor {
+                label: Some("Texture Editor Preview Sampler"),
+                ..Default::default()
+            },
+        };
+        let preview = imgui_wgpu::Texture::from_raw_parts(
+            &self.device,
+            &imgui.renderer,
+            Arc::new(texture.texture.clone()),
+            Arc::new(texture.view.clone()),
+            None,
+            Some(&config),
+            size,
+        );
+        editor.preview = Some(imgui.renderer.textures.insert(preview));
+        editor.size = [size.width as f32, size.height as f32];
+    }
+
+    pub(super) fn apply_texture_editor_request(&mut self, request: TextureEditorRequest) {
+        match request {
+            TextureEditorRequest::Inspect(pool, index) => self.inspect_texture(pool, index),
+            TextureEditorRequest::Save => {
+                if let Some((pool, index)) = self.texture_editor.target {
+                    let meta = self.texture_editor.draft.clone();
+                    self.set_texture_meta(pool, index, meta);
+                }
+            }
+            TextureEditorRequest::Revert => {
+                if let Some((pool, index)) = self.texture_editor.target {
+                    self.inspect_texture(pool, index);
+                }
+            }
+        }
+    }
+
+    // the preview shows the old texture after it's uploaded again
+    pub(super) fn texture_uploaded(&mut self, pool: usize, index: usize) {
+        if self.texture_editor.target == Some((pool, index)) {
+            self.drop_texture_preview();
+        }
+    }
+
+    fn drop_texture_preview(&mut self) {
+        if let Some(id) = self.texture_editor.preview.take()
+            && let Some(imgui) = &mut self.imgui_renderer
+        {
+            imgui.renderer.textures.remove(id);
+        }
+    }
+}
+
+pub(super) fn texture_editor_window(
+    ui: &Ui,
+    editor: &mut TextureEditor,
+    pools: &[NvTexturePool],
+    theme: &Theme,
+) -> Option<TextureEditorRequest> {
+    if pools.iter().all(|pool| pool.textures.is_empty()) {
+        return None;
+    }
+
+    let mut request = None;
+    ui.window("texture editor")
+        .size([360.0, 560.0], imgui::Condition::FirstUseEver)
+        .position([1420.0, 100.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            let slot = editor
+                .target
+                .and_then(|(pool, index)| pools.get(pool)?.textures.get(index));
+            let preview = slot.map_or("none", |slot| slot.name.as_str());
+            if let Some(_combo) = ui.begin_combo("texture", preview) {
+                for (pool_id, pool) in pools.iter().enumerate() {
+                    for (index, slot) in pool.textures.iter().enumerate() {
+                        let selected = editor.target == Some((pool_id, index));
+                        let label = format!("{}##{}-{}", slot.name, pool_id, index);
+                        if ui.selectable_config(label).selected(selected).build() {
+                            request = Some(TextureEditorRequest::Inspect(pool_id, index));
+                        }
+                    }
+                }
+            }
+            let Some(slot) = slot else {
+                return;
+            };
+
+            match editor.preview {
+                Some(preview) => canvas(ui, editor, preview, theme),
+                None => ui.text_disabled("not resident, draw it to reload"),
+            }
+
+            ui.separator();
+            let current = editor.tool;
+            for (tool, label) in [
+                (EditorTool::Pivot, "move pivot"),
+                (EditorTool::Rect, "draw rect"),
+                (EditorTool::Polygon, "draw polygon"),
+            ] {
+                if ui.radio_button_bool(label, current == tool) {
+                    editor.tool = tool;
+                    editor.drag_start = None;
+                    editor.points.clear();
+                }
+                ui.same_line();
+            }
+            ui.new_line();
+            if editor.tool == EditorTool::Polygon && !editor.points.is_empty() {
+                ui.text(format!("{} points", editor.points.len()));
+                ui.same_line();
+                if editor.points.len() >= 3 && ui.small_button("close") {
+                    let points = std::mem::take(&mut editor.points);
+                    editor.draft.shapes.push(ColliderShape::Polygon(points));
+                }
+                ui.same_line();
+                if ui.small_button("cancel") {
+                    editor.points.clear();
+                }
+            }
+
+            let draft = &mut editor.draft;
+            ui.input_float2("pivot", &mut draft.pivot).build();
+            draft.pivot = draft.pivot.map(|value| value.clamp(0.0, 1.0));
+
+            let mut sliced = draft.slice.is_some();
+            if ui.checkbox("9-slice", &mut sliced) {
+                draft.slice = match sliced {
+                    true => {
+                        let [w, h] = editor.size.map(|side| (side / 4.0) as u32);
+                        Some([w, h, w, h])
+                    }
+                    false => None,
+                };
+            }
+            if let Some(slice) = &mut draft.slice {
+                let mut borders = slice.map(|border| border as i32);
+                if ui.input_int4("borders", &mut borders).build() {
+                    *slice = borders.map(|border| border.max(0) as u32);
+                }
+            }
+
+            ui.separator();
+            ui.text(format!("{} collision shapes", draft.shapes.len()));
+            let mut removed = None;
+            for (i, shape) in draft.shapes.iter().enumerate() {
+                if ui.small_button(format!("remove##shape{}", i)) {
+                    removed = Some(i);
+                }
+                ui.same_line();
+                match shape {
+                    ColliderShape::Rect([x, y, w, h]) => {
+                        ui.text(format!("rect {} {} {}x{}", x, y, w, h))
+                    }
+                    ColliderShape::Polygon(points) => {
+                        ui.text(format!("polygon, {} points", points.len()))
+                    }
+                }
+            }
+            if let Some(i) = removed {
+                draft.shapes.remove(i);
+            }
+
+            ui.separator();
+            if *draft != slot.meta {
+                if ui.button("save") {
+                    request = Some(TextureEditorRequest::Save);
+                }
+                ui.same_line();
+                if ui.button("revert") {
+                    request = Some(TextureEditorRequest::Revert);
+                }
+            } else {
+                ui.text_disabled(format!(
+                    "saved to {}",
+                    TextureMeta::sidecar_path(&slot.name)
+                ));
+            }
+        });
+    request
+}
+
+// the texture with its borders, pivot and shapes drawn over it
+fn canvas(ui: &Ui, editor: &mut TextureEditor, preview: TextureId, theme: &Theme) {
+    let [width, height] = editor.size;
+    let fit = PREVIEW_SIZE / width.max(height);
+    // whole zoom levels keep pixel art sharp
+    let scale = if fit >= 1.0 { fit.floor() } else { fit };
+    let origin = ui.cursor_screen_pos();
+    let to_screen = |[x, y]: [f32; 2]| [origin[0] + x * scale, origin[1] + y * scale];
+
+    ui.invisible_button("canvas", [width * scale, height * scale]);
+    let mouse = ui.io().mouse_pos;
+    let texel = [
+        ((mouse[0] - origin[0]) / scale).round().clamp(0.0, width),
+        ((mouse[1] - origin[1]) / scale).round().clamp(0.0, height),
+    ];
+
+    if ui.is_item_clicked() {
+        match editor.tool {
+            EditorTool::Pivot => editor.draft.pivot = [texel[0] / width, texel[1] / height],
+            EditorTool::Rect => editor.drag_start = Some(texel),
+            EditorTool::Polygon => editor.points.push(texel),
+        }
+    }
+    if let Some(start) = editor.drag_start
+        && ui.is_mouse_released(MouseButton::Left)
+    {
+        editor.drag_start = None;
+        let min = [start[0].min(texel[0]), start[1].min(texel[1])];
+        let size = [(start[0] - texel[0]).abs(), (start[1] - texel[1]).abs()];
+        if size[0] > 0.0 && size[1] > 0.0 {
+            editor
+                .draft
+                .shapes
+                .push(ColliderShape::Rect([min[0], min[1], size[0], size[1]]));
+        }
+    }
+
+    let draw_list = ui.get_window_draw_list();
+    let end = to_screen([width, height]);
+    draw_list.add_image(preview, origin, end).build();
+    draw_list.add_rect(origin, end, theme.border).build();
+
+    if let Some([left, top, right, bottom]) = editor.draft.slice {
+        let [left, top] = [left as f32, top as f32];
+        let [right, bottom] = [width - right as f32, height - bottom as f32];
+        for x in [left, right] {
+            draw_list
+                .add_line(to_screen([x, 0.0]), to_screen([x, height]), theme.accent)
+                .build();
+        }
+        for y in [top, bottom] {
+            draw_list
+                .add_line(to_screen([0.0, y]), to_screen([width, y]), theme.accent)
+                .build();
+        }
+    }
+
+    let shape_color = theme.debug_palette[1];
+    for shape in editor.draft.shapes.iter() {
+        match shape {
+            ColliderShape::Rect([x, y, w, h]) => {
+                draw_list
+                    .add_rect(to_screen([*x, *y]), to_screen([x + w, y + h]), shape_color)
+                    .build();
+            }
+            ColliderShape::Polygon(points) => {
+                // closed, back to the first point
+                let points: Vec<[f32; 2]> = points
+                    .iter()
+                    .chain(points.first())
+                    .map(|&point| to_screen(point))
+                    .collect();
+                draw_list
+                    .add_polyline(points, shape_color)
+                    .thickness(1.0)
+                    .build();
+            }
+        }
+    }
+    // what's being drawn right now
+    let pending = theme.debug_palette[2];
+    if let Some(start) = editor.drag_start {
+        draw_list
+            .add_rect(to_screen(start), to_screen(texel), pending)
+            .build();
+    }
+    if !editor.points.is_empty() {
+        let mut points: Vec<[f32; 2]> = editor
+            .points
+            .iter()
+            .map(|&point| to_screen(point))
+            .collect();
+        points.push(to_screen(texel));
+        draw_list.add_polyline(points, pending).build();
+    }
+
+    let [pivot_x, pivot_y] = to_screen([
+        editor.draft.pivot[0] * width,
+        editor.draft.pivot[1] * height,
+    ]);
+    let arm = 5.0;
+    for (start, end) in [
+        ([pivot_x - arm, pivot_y], [pivot_x + arm, pivot_y]),
+        ([pivot_x, pivot_y - arm], [pivot_x, pivot_y + arm]),
+    ] {
+        draw_list
+            .add_line(start, end, theme.error)
+            .thickness(2.0)
+            .build();
+    }
+}
@@ -0,0 +1,103 @@
+use imgui::StyleColor;
+
+use crate::renderer::Renderer;
+use crate::ui::theme::Theme;
+
+// the font atlas is built at this size, themes scale it
+pub(super) const IMGUI_FONT_SIZE: f32 = 13.0;
+
+impl<'a> Renderer<'a> {
+    // the theme widgets should draw with, the high contrast one while that is on
+    pub fn theme(&self) -> Theme {
+        match self.is_high_contrast() {
+            true => Theme::named("high_contrast"),
+            false => self.theme,
+        }
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.theme_revision += 1;
+    }
+
+    // bumped whenever theme() changes, widgets redraw when it moved
+    pub fn theme_revision(&self) -> u64 {
+        self.theme_revision
+    }
+
+    pub(super) fn apply_imgui_theme(&mut self) {
+        let theme = self.theme();
+        let scale_factor = self.scale_factor();
+        let Some(imgui) = &mut self.imgui_renderer else {
+            return;
+        };
+        if imgui.theme_revision == Some(self.theme_revision) {
+            return;
+        }
+        imgui.theme_revision = Some(self.theme_revision);
+
+        imgui.context.io_mut().font_global_scale = theme.font_size / IMGUI_FONT_SIZE / scale_factor;
+
+        let style = imgui.context.style_mut();
+        style.use_dark_colors();
+        style.window_border_size = theme.border_size;
+        style.frame_border_size = (theme.border_size - 1.0).clamp(0.0, 1.0);
+        style.item_spacing = [theme.spacing, theme.spacing * 0.5];
+        style.window_padding = [theme.spacing, theme.spacing];
+
+        let colors = [
+            (
+                theme.background,
+                &[
+                    StyleColor::WindowBg,
+                    StyleColor::PopupBg,
+                    StyleColor::TitleBg,
+                    StyleColor::MenuBarBg,
+                    StyleColor::ScrollbarBg,
+                ][..],
+            ),
+            (
+                theme.surface,
+                &[
+                    StyleColor::FrameBg,
+                    StyleColor::TitleBgActive,
+                    StyleColor::Button,
+                    StyleColor::Header,
+                ][..],
+            ),
+            (
+                theme.hovered,
+                &[
+                    StyleColor::FrameBgHovered,
+                    StyleColor::FrameBgActive,
+                    StyleColor::ButtonHovered,
+                    StyleColor::ButtonActive,
+                    StyleColor::HeaderHovered,
+                    StyleColor::HeaderActive,
+                ][..],
+            ),
+            (theme.text, &[StyleColor::Text][..]),
+            (theme.text_muted, &[StyleColor::TextDisabled][..]),
+            (
+                theme.border,
+                &[StyleColor::Border, StyleColor::Separator][..],
+            ),
+            (
+                theme.accent,
+                &[
+                    StyleColor::CheckMark,
+                    StyleColor::SliderGrab,
+                    StyleColor::SliderGrabActive,
+                    StyleColor::NavHighlight,
+                    StyleColor::ScrollbarGrabHovered,
+                ][..],
+            ),
+            (theme.scrollbar, &[StyleColor::ScrollbarGrab][..]),
+        ];
+        for (value, targets) in colors {
+            for target in targets {
+                style[*target] = value;
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use imgui::Ui;
+use log::info;
+
+use crate::renderer::Renderer;
+use crate::renderer::camera::Camera2D;
+use crate::renderer::layer::Sprite;
+
+// what the world looked like at the end of one frame's update
+struct Snapshot {
+    frame: u64,
+    layers: Vec<BTreeMap<usize, Sprite>>,
+    cameras: Vec<Camera2D>,
+}
+
+pub(super) enum TimelineRequest {
+    Show(usize), // index into the recorded frames
+    Resume,
+}
+
+// a ring of recent snapshots to scrub through while the game is paused.
+// off until given a capacity, every snapshot copies every sprite
+#[derive(Default)]
+pub(super) struct Timeline {
+    capacity: usize,
+    frames: VecDeque<Snapshot>,
+    shown: Option<usize>,
+    live: Option<Snapshot>, // the present, put back on resume
+}
+
+impl<'a> Renderer<'a> {
+    // frames of history to keep, 0 turns recording off
+    pub fn set_timeline_capacity(&mut self, frames: usize) {
+        if frames == 0 {
+            self.resume_timeline();
+        }
+        let timeline = &mut self.timeline;
+        timeline.capacity = frames;
+        while timeline.frames.len() > frames {
+            timeline.frames.pop_front();
+        }
+    }
+
+    // while true the engine stops updating game states and the renderer shows history
+    pub fn is_time_travelling(&self) -> bool {
+        self.timeline.shown.is_some()
+    }
+
+    pub fn timeline_len(&self) -> usize {
+        self.timeline.frames.len()
+    }
+
+    // moves through history by offset frames, negative is back in time
+    pub fn step_timeline(&mut self, offset: isize) {
+        let timeline = &self.timeline;
+        if timeline.frames.is_empty() {
+            return;
+        }
+
+        let last = timeline.frames.len() - 1;
+        let current = timeline.shown.unwrap_or(last + 1) as isize;
+        let index = (current + offset).clamp(0, last as isize) as usize;
+        self.show_snapshot(index);
+    }
+
+    pub fn resume_timeline(&mut self) {
+        let Some(live) = self.timeline.live.take() else {
+            return;
+        };
+        self.timeline.shown = None;
+        self.restore_snapshot(&live);
+        info!("resumed at frame {}", live.frame);
+    }
+
+    pub(super) fn record_snapshot(&mut self) {
+        if self.timeline.capacity == 0 || self.is_time_travelling() {
+            return;
+        }
+
+        let snapshot = self.capture_snapshot();
+        let timeline = &mut self.timeline;
+        if timeline.frames.len() == timeline.capacity {
+            timeline.frames.pop_front();
+        }
+        timeline.frames.push_back(snapshot);
+    }
+
+    pub(super) fn apply_timeline_request(&mut self, request: TimelineRequest) {
+        match request {
+            TimelineRequest::Show(index) => self.show_snapshot(index),
+            TimelineRequest::Resume => self.resume_timeline(),
+        }
+    }
+
+    fn show_snapshot(&mut self, index: usize) {
+        if self.timeline.live.is_none() {
+            self.timeline.live = Some(self.capture_snapshot());
+        }
+
+        let Some(snapshot) = self.timeline.frames.get(index) else {
+            return;
+        };
+        self.timeline.shown = Some(index);
+
+        // sprites and cameras created since keep their place, empty
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            layer.instances = snapshot.layers.get(index).cloned().unwrap_or_default();
+        }
+        for (camera, historical) in self.cameras.iter_mut().zip(&snapshot.cameras) {
+            *camera = *historical;
+        }
+    }
+
+    fn capture_snapshot(&self) -> Snapshot {
+        Snapshot {
+            frame: self.frame_index,
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| layer.instances.clone())
+                .collect(),
+            cameras: self.cameras.clone(),
+        }
+    }
+
+    fn restore_snapshot(&mut self, snapshot: &Snapshot) {
+        for (layer, instances) in self.layers.iter_mut().zip(&snapshot.layers) {
+            layer.instances = instances.clone();
+        }
+        for (camera, saved) in self.cameras.iter_mut().zip(&snapshot.cameras) {
+            *camera = *saved;
+        }
+    }
+}
+
+// the scrubber in the debug overlay, only shown while recording
+pub(super) fn timeline_window(ui: &Ui, timeline: &Timeline) -> Option<TimelineRequest> {
+    if timeline.capacity == 0 || timeline.frames.is_empty() {
+        return None;
+    }
+
+    let last = timeline.frames.len() - 1;
+    let mut request = None;
+    ui.window("timeline")
+        .size([400.0, 90.0], imgui::Condition::FirstUseEver)
+        .position([100.0, 450.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            let mut index = timeline.shown.unwrap_or(last);
+            if ui.button("<") {
+                request = Some(TimelineRequest::Show(index.saturating_sub(1)));
+            }
+            ui.same_line();
+            if ui.button(">") {
+                request = Some(TimelineRequest::Show((index + 1).min(last)));
+            }
+            ui.same_line();
+            if timeline.shown.is_some() && ui.button("live") {
+                request = Some(TimelineRequest::Resume);
+            }
+
+            if ui.slider("frame", 0, last, &mut index) {
+                request = Some(TimelineRequest::Show(index));
+            }
+            match timeline.shown {
+                Some(shown) => ui.text(format!(
+                    "showing frame {} of {} recorded",
+                    timeline.frames[shown].frame,
+                    timeline.frames.len()
+                )),
+                None => ui.text("live, step back to pause"),
+            }
+        });
+
+    request
+}
@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use log::warn;
+
+use crate::renderer::Renderer;
+use crate::renderer::shapes::ShapePoint;
+use crate::util::tween::Lerp;
+
+const MAX_POINTS: usize = 512; // per trail, the oldest go first
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Trail {
+    pub camera: usize,
+    pub width: f32,          // world pixels
+    pub color: [f32; 4],     // at the head
+    pub end_color: [f32; 4], // at the tail, blended along the length
+    pub lifetime: f32,       // seconds a recorded point stays
+    pub min_distance: f32,   // world pixels moved before another point is recorded
+    pub offset: [f32; 2],    // from the sprite's position
+    pub emitting: bool,      // false leaves what was recorded to fade out
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Trail {
+            camera: 0,
+            width: 8.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            end_color: [1.0, 1.0, 1.0, 1.0],
+            lifetime: 0.5,
+            min_distance: 4.0,
+            offset: [0.0, 0.0],
+            emitting: true,
+        }
+    }
+}
+
+// what a curve drives along a trail, 0 at the head and 1 at the tail
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TrailCurve {
+    Width, // times the trail's width, 1 without a curve
+    Alpha, // times the color's alpha, fading from 1 to 0 without a curve
+}
+
+struct TrailPoint {
+    position: [f32; 2],
+    age: f32,
+}
+
+pub(super) struct TrailState {
+    sprite: usize,
+    trail: Trail,
+    width_curve: Option<usize>, // from the curve library
+    alpha_curve: Option<usize>,
+    points: VecDeque<TrailPoint>, // newest first
+}
+
+// ribbons behind sprites, drawn with the shapes. the head follows the sprite
+// every frame, positions are only recorded every min_distance so a slow
+// sprite doesn't fill its trail with points on top of each other
+#[derive(Default)]
+pub(super) struct Trails {
+    trails: BTreeMap<usize, TrailState>,
+    next_id: usize,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn add_trail(&mut self, sprite: usize, trail: Trail) -> Option<usize> {
+        if self.sprite(sprite).is_none() {
+            warn!("no sprite {} to add a trail to", sprite);
+            return None;
+        }
+        let id = self.trails.next_id;
+        self.trails.next_id += 1;
+        self.trails.trails.insert(
+            id,
+            TrailState {
+                sprite,
+                trail,
+                width_curve: None,
+                alpha_curve: None,
+                points: VecDeque::new(),
+            },
+        );
+        Some(id)
+    }
+
+    pub fn trail(&mut self, id: usize) -> Option<&mut Trail> {
+        self.trails
+            .trails
+            .get_mut(&id)
+            .map(|state| &mut state.trail)
+    }
+
+    // a curve from add_curve along the trail's length, None for the default
+    pub fn set_trail_curve(&mut self, id: usize, target: TrailCurve, curve: Option<usize>) {
+        let Some(state) = self.trails.trails.get_mut(&id) else {
+            warn!("no trail {}", id);
+            return;
+        };
+        match target {
+            TrailCurve::Width => state.width_curve = curve,
+            TrailCurve::Alpha => state.alpha_curve = curve,
+        }
+    }
+
+    // forgets the recorded points, e.g. after teleporting the sprite
+    pub fn clear_trail(&mut self, id: usize) {
+        match self.trails.trails.get_mut(&id) {
+            Some(state) => state.points.clear(),
+            None => warn!("no trail {}", id),
+        }
+    }
+
+    pub fn remove_trail(&mut self, id: usize) {
+        if self.trails.trails.remove(&id).is_none() {
+            warn!("no trail {}", id);
+        }
+    }
+
+    // ages and records points, then queues every ribbon as shape triangles.
+    // trails of removed sprites fade out and are dropped once empty
+    pub(super) fn update_trails(&mut self, dt_seconds: f32) {
+        if self.trails.trails.is_empty() {
+            return;
+        }
+        let mut trails = std::mem::take(&mut self.trails.trails);
+        trails.retain(|_, state| {
+            let lifetime = state.trail.lifetime.max(f32::EPSILON);
+            for point in state.points.iter_mut() {
+                point.age += dt_seconds;
+            }
+            while state
+                .points
+                .back()
+                .is_some_and(|point| point.age >= lifetime)
+            {
+                state.points.pop_back();
+            }
+
+            let sprite = self.sprite(state.sprite).map(|sprite| sprite.position);
+            let head = sprite.filter(|_| state.trail.emitting).map(|position| {
+                [
+                    position[0] + state.trail.offset[0],
+                    position[1] + state.trail.offset[1],
+                ]
+            });
+            if let Some(head) = head {
+                let moved = state.points.front().is_none_or(|newest| {
+                    distance(newest.position, head) >= state.trail.min_distance
+                });
+                if moved {
+                    state.points.push_front(TrailPoint {
+                        position: head,
+                        age: 0.0,
+                    });
+                    state.points.truncate(MAX_POINTS);
+                }
+            }
+
+            let triangles = self.trail_triangles(state, head);
+            if !triangles.is_empty() {
+                self.queue_colored_triangles(state.trail.camera, &triangles);
+            }
+            sprite.is_some() || !state.points.is_empty()
+        });
+        self.trails.trails = trails;
+    }
+
+    // a strip of quads through the points, each as wide and colored as its age
+    // along the lifetime says
+    fn trail_triangles(&self, state: &TrailState, head: Option<[f32; 2]>) -> Vec<[ShapePoint; 3]> {
+        let lifetime = state.trail.lifetime.max(f32::EPSILON);
+        let mut points: Vec<([f32; 2], f32)> = Vec::with_capacity(state.points.len() + 1);
+        if let Some(head) = head
+            && state
+                .points
+                .front()
+                .is_some_and(|newest| distance(newest.position, head) > f32::EPSILON)
+        {
+            points.push((head, 0.0));
+        }
+        points.extend(
+            state
+                .points
+                .iter()
+                .map(|point| (point.position, (point.age / lifetime).clamp(0.0, 1.0))),
+        );
+        if points.len() < 2 {
+            return Vec::new();
+        }
+
+        let sample = |curve: Option<usize>, t: f32, default: f32| {
+            curve
+                .and_then(|id| self.curves.get(id))
+                .map_or(default, |curve| curve.sample(t))
+        };
+        let edges: Vec<[ShapePoint; 2]> = (0..points.len())
+            .map(|i| {
+                let (position, t) = points[i];
+                let before = points[i.saturating_sub(1)].0;
+                let after = points[(i + 1).min(points.len() - 1)].0;
+                let delta = [after[0] - before[0], after[1] - before[1]];
+                let length = (delta[0] * delta[0] + delta[1] * delta[1])
+                    .sqrt()
+                    .max(f32::EPSILON);
+                let half = state.trail.width * sample(state.width_curve, t, 1.0) * 0.5;
+                let normal = [-delta[1] / length * half, delta[0] / length * half];
+
+                let mut color = state.trail.color.lerp(&state.trail.end_color, t);
+                color[3] *= sample(state.alpha_curve, t, 1.0 - t);
+                [
+                    ([position[0] + normal[0], position[1] + normal[1]], color),
+                    ([position[0] - normal[0], position[1] - normal[1]], color),
+                ]
+            })
+            .collect();
+        edges
+            .windows(2)
+            .flat_map(|pair| {
+                let [[a, b], [c, d]] = [pair[0], pair[1]];
+                [[a, b, d], [a, d, c]]
+            })
+            .collect()
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
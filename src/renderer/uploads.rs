@@ -0,0 +1,307 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use log::{Level, debug, error, info};
+
+use crate::assets::atlas::AtlasRegions;
+use crate::assets::manager::LoadedPool;
+use crate::assets::remote;
+use crate::assets::{DecodedImage, NvTexture, NvTexturePool, NvTextureSlot};
+use crate::renderer::Renderer;
+use crate::tasks;
+
+// the upload_budget_ms cvar
+const DEFAULT_BUDGET: Duration = Duration::from_millis(2);
+
+struct QueuedUpload {
+    pool: usize,
+    index: usize,
+    image: DecodedImage,
+}
+
+struct Reload {
+    pool: usize,
+    index: usize,
+    name: String,
+    image: anyhow::Result<DecodedImage>,
+}
+
+// textures the async loader decoded, uploaded a few per frame so a large pool
+// arriving mid game doesn't stall a frame. at least one goes up every frame
+pub(super) struct UploadQueue {
+    budget: Option<Duration>, // None uploads everything right away
+    queued: VecDeque<QueuedUpload>,
+    // evicted or re-imported textures decoded by tasks, queued like the rest
+    // once they arrive
+    reloads: Option<(Sender<Reload>, Receiver<Reload>)>,
+    reloading: HashMap<(usize, usize), bool>, // true notifies when it arrives
+    placeholder: Option<NvTexture>,           // bound while a texture reloads
+}
+
+impl Default for UploadQueue {
+    fn default() -> Self {
+        UploadQueue {
+            budget: Some(DEFAULT_BUDGET),
+            queued: VecDeque::new(),
+            reloads: None,
+            reloading: HashMap::new(),
+            placeholder: None,
+        }
+    }
+}
+
+impl UploadQueue {
+    fn position(&self, pool: usize, index: usize) -> Option<usize> {
+        self.queued
+            .iter()
+            .position(|upload| upload.pool == pool && upload.index == index)
+    }
+
+    pub(super) fn placeholder(&self) -> Option<&NvTexture> {
+        self.placeholder.as_ref()
+    }
+}
+
+impl<'a> Renderer<'a> {
+    // time spent on texture uploads from the loader per frame, None for no limit
+    pub fn set_upload_budget(&mut self, budget: Option<Duration>) {
+        self.uploads.budget = budget;
+    }
+
+    pub fn upload_budget(&self) -> Option<Duration> {
+        self.uploads.budget
+    }
+
+    // textures decoded but not on the gpu yet
+    pub fn pending_uploads(&self) -> usize {
+        self.uploads.queued.len()
+    }
+
+    // like insert_loaded_pool, but the textures go up over the next frames.
+    // sprites using one that isn't up yet aren't drawn until it is. an asset
+    // pool queued again is reloaded into its texture pool
+    pub fn queue_loaded_pool(&mut self, pool: LoadedPool) -> usize {
+        if let Some(id) = self.texture_pool(pool.id) {
+            self.replace_loaded_pool(id, pool);
+            return id;
+        }
+        info!(
+            "queueing {} textures of asset pool {} for upload",
+            pool.images.len(),
+            pool.id
+        );
+        let layout = self
+            .bind_group_layouts
+            .first()
+            .expect("there is no bind group layout");
+        let id = self.loaded_pools.len();
+        self.loaded_pools.push(NvTexturePool {
+            textures: Vec::new(),
+            layout: layout.clone(),
+        });
+        self.asset_pools.insert(pool.id, id);
+        self.queue_pool_uploads(id, pool);
+        id
+    }
+
+    // slots keep their old texture until the new one is up, so a reloading
+    // pool doesn't blink out
+    pub(super) fn queue_pool_uploads(&mut self, id: usize, pool: LoadedPool) {
+        let frame = self.frame_index;
+        let textures = &mut self.loaded_pools[id].textures;
+        textures.truncate(pool.images.len());
+        for (index, image) in pool.images.into_iter().enumerate() {
+            let mut slot = NvTextureSlot {
+                name: image.name.clone(),
+                bytes: image.bytes(),
+                last_used: frame,
+                meta: image.meta.clone(),
+                regions: AtlasRegions::load(&image.name),
+                texture: None,
+            };
+            match textures.get_mut(index) {
+                Some(old) => {
+                    slot.texture = old.texture.take();
+                    *old = slot;
+                }
+                None => textures.push(slot),
+            }
+
+            // a reload replaces a queued upload of the same texture
+            if let Some(position) = self.uploads.position(id, index) {
+                self.uploads.queued.remove(position);
+            }
+            self.uploads.queued.push_back(QueuedUpload {
+                pool: id,
+                index,
+                image,
+            });
+        }
+    }
+
+    // whether the texture waits on an upload, which is moved to the front of
+    // the queue because it is being drawn
+    pub(super) fn prioritize_upload(&mut self, pool: usize, index: usize) -> bool {
+        let Some(position) = self.uploads.position(pool, index) else {
+            return false;
+        };
+        if position > 0
+            && let Some(upload) = self.uploads.queued.remove(position)
+        {
+            self.uploads.queued.push_front(upload);
+        }
+        true
+    }
+
+    // decodes the texture on the task pool, it is drawn with a transparent
+    // placeholder until the reload is uploaded. notify reports how it went
+    pub(super) fn queue_reload(&mut self, pool: usize, index: usize, notify: bool) {
+        if let Some(notifies) = self.uploads.reloading.get_mut(&(pool, index)) {
+            *notifies |= notify;
+            return;
+        }
+        let Some(slot) = self
+            .loaded_pools
+            .get(pool)
+            .and_then(|loaded_pool| loaded_pool.textures.get(index))
+        else {
+            return;
+        };
+        info!("reloading texture {}", slot.name);
+
+        if self.uploads.placeholder.is_none() {
+            let layout = &self.loaded_pools[pool].layout;
+            let image = DecodedImage::solid("reloading", [0, 0, 0, 0]);
+            self.uploads.placeholder = Some(NvTexture::from_image(
+                &self.device,
+                &self.queue,
+                layout,
+                &image,
+            ));
+        }
+
+        let name = slot.name.clone();
+        let sender = self
+            .uploads
+            .reloads
+            .get_or_insert_with(mpsc::channel)
+            .0
+            .clone();
+        self.uploads.reloading.insert((pool, index), notify);
+        tasks::pool().execute(remote::task_kind(&name), move || {
+            let image = DecodedImage::open(&name);
+            // the renderer may be gone by now
+            _ = sender.send(Reload {
+                pool,
+                index,
+                name,
+                image,
+            });
+        });
+    }
+
+    // whether the texture is being decoded again
+    pub(super) fn is_reloading(&self, pool: usize, index: usize) -> bool {
+        self.uploads.reloading.contains_key(&(pool, index))
+    }
+
+    // reloads that arrived go to the front of the queue, they are being drawn
+    fn receive_reloads(&mut self) {
+        let Some((_, receiver)) = &self.uploads.reloads else {
+            return;
+        };
+        // the queue keeps a sender itself, it never disconnects
+        let arrived: Vec<Reload> = receiver.try_iter().collect();
+
+        for reload in arrived {
+            let notify = self
+                .uploads
+                .reloading
+                .remove(&(reload.pool, reload.index))
+                .unwrap_or(false);
+            let image = match reload.image {
+                Ok(image) => {
+                    if notify {
+                        self.notify(format!("reloaded {}", reload.name), Level::Info, 2.0);
+                    }
+                    image
+                }
+                Err(e) => {
+                    error!("failed to load texture {}: {}", reload.name, e);
+                    if notify {
+                        self.notify(
+                            format!("failed to reload {}", reload.name),
+                            Level::Error,
+                            4.0,
+                        );
+                    }
+                    DecodedImage::placeholder(&reload.name)
+                }
+            };
+
+            // the pool may have been replaced since the request
+            let Some(slot) = self
+                .loaded_pools
+                .get_mut(reload.pool)
+                .and_then(|loaded_pool| loaded_pool.textures.get_mut(reload.index))
+                .filter(|slot| slot.name == reload.name)
+            else {
+                continue;
+            };
+            slot.meta = image.meta.clone();
+            slot.bytes = image.bytes(); // unknown for pending slots
+
+            if let Some(position) = self.uploads.position(reload.pool, reload.index) {
+                self.uploads.queued.remove(position);
+            }
+            self.uploads.queued.push_front(QueuedUpload {
+                pool: reload.pool,
+                index: reload.index,
+                image,
+            });
+        }
+    }
+
+    pub(super) fn upload_queued_textures(&mut self) {
+        self.receive_reloads();
+        let start = Instant::now();
+        let mut uploaded = 0;
+        while let Some(upload) = self.uploads.queued.pop_front() {
+            self.upload_texture(upload);
+            uploaded += 1;
+            if self
+                .uploads
+                .budget
+                .is_some_and(|budget| start.elapsed() >= budget)
+            {
+                break;
+            }
+        }
+
+        if uploaded > 0 {
+            debug!(
+                "uploaded {} textures in {:.2}ms, {} left",
+                uploaded,
+                start.elapsed().as_secs_f64() * 1000.0,
+                self.uploads.queued.len()
+            );
+        }
+    }
+
+    fn upload_texture(&mut self, upload: QueuedUpload) {
+        let Some(pool) = self.loaded_pools.get_mut(upload.pool) else {
+            return;
+        };
+        let Some(slot) = pool.textures.get_mut(upload.index) else {
+            return;
+        };
+        slot.texture = Some(NvTexture::from_image(
+            &self.device,
+            &self.queue,
+            &pool.layout,
+            &upload.image,
+        ));
+        self.texture_uploaded(upload.pool, upload.index);
+    }
+}
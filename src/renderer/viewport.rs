@@ -0,0 +1,265 @@
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::frame_dump::{DrawCall, DrawRecord, PassKind};
+use crate::renderer::pipeline::PipelineType;
+use crate::renderer::{FrameContext, Renderer};
+
+// the shape of the part of the window the game draws into
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectRatio {
+    #[default]
+    Fill, // the whole window, whatever its shape
+    // width over height, the rest of the window gets bars. wider windows are
+    // pillarboxed, taller ones letterboxed
+    Fixed(f32),
+}
+
+// a point of the safe area hud elements are placed against
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // 0..1 across the safe area, also how much of the element sits left and above it
+    fn factor(&self) -> [f32; 2] {
+        match self {
+            Anchor::TopLeft => [0.0, 0.0],
+            Anchor::Top => [0.5, 0.0],
+            Anchor::TopRight => [1.0, 0.0],
+            Anchor::Left => [0.0, 0.5],
+            Anchor::Center => [0.5, 0.5],
+            Anchor::Right => [1.0, 0.5],
+            Anchor::BottomLeft => [0.0, 1.0],
+            Anchor::Bottom => [0.5, 1.0],
+            Anchor::BottomRight => [1.0, 1.0],
+        }
+    }
+}
+
+pub(super) struct Viewport {
+    aspect: AspectRatio,
+    bar_color: wgpu::Color,
+    insets: [f32; 4], // left, top, right, bottom in physical pixels of the window
+    max_hud_aspect: Option<f32>, // keeps the hud off the far edges of ultra-wide screens
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport {
+            aspect: AspectRatio::Fill,
+            bar_color: wgpu::Color::BLACK,
+            insets: [0.0; 4],
+            max_hud_aspect: None,
+        }
+    }
+}
+
+// the widest rect of aspect centered in rect, all of it when it's narrower
+fn fit_aspect(rect: [f32; 4], aspect: f32) -> [f32; 4] {
+    let [x, y, w, h] = rect;
+    if w > h * aspect {
+        let width = h * aspect;
+        [x + (w - width) / 2.0, y, width, h]
+    } else {
+        [x, y, w, h]
+    }
+}
+
+impl<'a> Renderer<'a> {
+    pub fn aspect_ratio(&self) -> AspectRatio {
+        self.viewport.aspect
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect: AspectRatio) {
+        if let AspectRatio::Fixed(ratio) = aspect
+            && !(ratio.is_finite() && ratio > 0.0)
+        {
+            warn!("aspect ratio of {}, ignoring it", ratio);
+            return;
+        }
+        info!("drawing at {:?} aspect ratio", aspect);
+        self.viewport.aspect = aspect;
+    }
+
+    pub fn set_letterbox_color(&mut self, color: wgpu::Color) {
+        self.viewport.bar_color = color;
+    }
+
+    // x, y, width and height of the part of the window the game draws into, in
+    // physical pixels. the screen camera's 0, 0 is its top left corner
+    pub fn viewport(&self) -> [f32; 4] {
+        let window = [
+            0.0,
+            0.0,
+            self.surface_config.width as f32,
+            self.surface_config.height as f32,
+        ];
+        let AspectRatio::Fixed(aspect) = self.viewport.aspect else {
+            return window;
+        };
+
+        // whole pixels, so sprites snapped to the grid stay sharp
+        let [x, y, w, h] = match window[2] > window[3] * aspect {
+            true => fit_aspect(window, aspect),
+            false => {
+                let height = window[2] / aspect;
+                [0.0, (window[3] - height) / 2.0, window[2], height]
+            }
+        };
+        [x.floor(), y.floor(), w.round().max(1.0), h.round().max(1.0)]
+    }
+
+    pub fn viewport_size(&self) -> [f32; 2] {
+        let [_, _, width, height] = self.viewport();
+        [width, height]
+    }
+
+    // a point in physical pixels of the window, e.g. the mouse, relative to the
+    // viewport as screen cameras and hud elements use it
+    pub fn window_to_viewport(&self, point: [f32; 2]) -> [f32; 2] {
+        let [x, y, _, _] = self.viewport();
+        [point[0] - x, point[1] - y]
+    }
+
+    // left, top, right and bottom of the window the hud stays clear of, e.g.
+    // under a notch or the overscan of a tv. in physical pixels
+    pub fn set_safe_area_insets(&mut self, insets: [f32; 4]) {
+        self.viewport.insets = insets.map(|inset| inset.max(0.0));
+    }
+
+    // None lets the hud spread over the whole viewport however wide it gets
+    pub fn set_max_hud_aspect(&mut self, aspect: Option<f32>) {
+        if let Some(aspect) = aspect
+            && !(aspect.is_finite() && aspect > 0.0)
+        {
+            warn!("hud aspect ratio of {}, ignoring it", aspect);
+            return;
+        }
+        self.viewport.max_hud_aspect = aspect;
+    }
+
+    // the part of the viewport hud elements can go, x, y, width and height
+    // relative to the viewport like the screen camera
+    pub fn safe_area(&self) -> [f32; 4] {
+        let [x, y, w, h] = self.viewport();
+        let [left, top, right, bottom] = self.viewport.insets;
+        let window = [
+            self.surface_config.width as f32,
+            self.surface_config.height as f32,
+        ];
+
+        // insets only bite where they reach past the bars
+        let min = [left.max(x), top.max(y)];
+        let max = [
+            (window[0] - right).min(x + w),
+            (window[1] - bottom).min(y + h),
+        ];
+        let rect = [
+            min[0] - x,
+            min[1] - y,
+            (max[0] - min[0]).max(0.0),
+            (max[1] - min[1]).max(0.0),
+        ];
+        match self.viewport.max_hud_aspect {
+            Some(aspect) => fit_aspect(rect, aspect),
+            None => rect,
+        }
+    }
+
+    // the top left of an element of size placed at anchor of the safe area,
+    // margin pixels in from its edges
+    pub fn anchor(&self, anchor: Anchor, size: [f32; 2], margin: f32) -> [f32; 2] {
+        let [x, y, w, h] = self.safe_area();
+        let factor = anchor.factor();
+        // centered axes ignore the margin
+        let inset = factor.map(|f| margin * (1.0 - 2.0 * f));
+        [
+            x + (w - size[0]) * factor[0] + inset[0],
+            y + (h - size[1]) * factor[1] + inset[1],
+        ]
+    }
+
+    // world and hud passes draw into the viewport only, anything outside it is clipped
+    pub(super) fn apply_viewport(&self, pass: &mut wgpu::RenderPass) {
+        if self.viewport.aspect == AspectRatio::Fill {
+            return;
+        }
+        let [x, y, w, h] = self.viewport();
+        pass.set_viewport(x, y, w, h, 0.0, 1.0);
+    }
+
+    // paints the bars over whatever full screen passes left around the viewport
+    pub(super) fn render_letterbox(&mut self, context: &mut FrameContext) {
+        if self.viewport.aspect == AspectRatio::Fill {
+            return;
+        }
+        let [x, y, w, h] = self.viewport().map(|v| v as u32);
+        let (width, height) = (self.surface_config.width, self.surface_config.height);
+        // left and right or top and bottom, one pair is empty
+        let bars = [
+            [0, 0, x, height],
+            [x + w, 0, width.saturating_sub(x + w), height],
+            [0, 0, width, y],
+            [0, y + h, width, height.saturating_sub(y + h)],
+        ];
+
+        if !self.pipelines.contains_key(&PipelineType::Letterbox) {
+            match self.create_pipeline(&PipelineType::Letterbox, &[], &[]) {
+                Ok(pipeline) => {
+                    self.pipelines.insert(PipelineType::Letterbox, pipeline);
+                }
+                Err(e) => {
+                    error!("failed to create letterbox pipeline: {}", e);
+                    return;
+                }
+            }
+        }
+        let Some(pipeline) = self.pipelines.get(&PipelineType::Letterbox) else {
+            return;
+        };
+
+        let mut pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Letterbox Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+        pass.set_pipeline(pipeline);
+        pass.set_blend_constant(self.viewport.bar_color);
+
+        self.frame_dump
+            .pass("Letterbox Render Pass", PassKind::Render, "frame", false);
+        for [x, y, w, h] in bars.into_iter().filter(|bar| bar[2] > 0 && bar[3] > 0) {
+            pass.set_scissor_rect(x, y, w, h);
+            pass.draw(0..3, 0..1);
+            self.frame_dump.draw(|| {
+                DrawRecord::new(DrawCall::Draw, PipelineType::Letterbox.label())
+                    .vertices(0..3)
+                    .instances(0..1)
+            });
+        }
+    }
+}
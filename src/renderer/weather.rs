@@ -0,0 +1,213 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::Renderer;
+use crate::renderer::particles::{ParticleCurve, ParticleEmitter};
+use crate::util::curve::{Curve, CurveKey};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherKind {
+    Rain,
+    Snow,
+    Fog, // big soft puffs drifting with the wind
+}
+
+impl WeatherKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherKind::Rain => "rain",
+            WeatherKind::Snow => "snow",
+            WeatherKind::Fog => "fog",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [WeatherKind::Rain, WeatherKind::Snow, WeatherKind::Fog]
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    pub intensity: f32, // 0..1 of the preset's rate, 0 lets what's falling land
+    pub wind: f32,      // physical pixels per second, positive to the right
+    pub ground: Option<f32>, // world y rain and snow stop at, fog ignores it
+    pub camera: usize,  // the ground is seen through
+}
+
+impl Weather {
+    pub fn new(kind: WeatherKind) -> Self {
+        Weather {
+            kind,
+            intensity: 1.0,
+            wind: 0.0,
+            ground: None,
+            camera: 0,
+        }
+    }
+}
+
+// how a kind of weather looks at full intensity without wind
+struct Preset {
+    capacity: u32,
+    rate: f32,     // per second across one screen width
+    crossing: f32, // about how long a particle takes to fall through the screen
+    emitter: ParticleEmitter,
+    alpha: Curve,
+}
+
+fn preset(kind: WeatherKind) -> Preset {
+    let fade = |fade_in: f32, fade_out: f32| {
+        Curve::new(vec![
+            CurveKey::new(0.0, 0.0),
+            CurveKey::new(fade_in, 1.0),
+            CurveKey::new(1.0 - fade_out, 1.0),
+            CurveKey::new(1.0, 0.0),
+        ])
+    };
+    match kind {
+        WeatherKind::Rain => Preset {
+            capacity: 8192,
+            rate: 2400.0,
+            crossing: 0.7,
+            emitter: ParticleEmitter {
+                position: [0.0, 1.05],
+                gravity: [0.0, -1.0],
+                color: [0.7, 0.75, 0.9, 0.5],
+                lifetime: 1.5,
+                speed: 3.0,
+                direction: -std::f32::consts::FRAC_PI_2,
+                spread: 0.05,
+                size: 0.006,
+                stretch: 0.03,
+                softness: 0.5,
+                ..Default::default()
+            },
+            alpha: fade(0.02, 0.1),
+        },
+        WeatherKind::Snow => Preset {
+            capacity: 4096,
+            rate: 250.0,
+            crossing: 5.0,
+            emitter: ParticleEmitter {
+                position: [0.0, 1.05],
+                gravity: [0.0, 0.0],
+                color: [1.0, 1.0, 1.0, 0.9],
+                lifetime: 10.0,
+                speed: 0.35,
+                direction: -std::f32::consts::FRAC_PI_2,
+                spread: 0.6,
+                sway: 0.12,
+                size: 0.007,
+                softness: 1.0,
+                ..Default::default()
+            },
+            alpha: fade(0.05, 0.2),
+        },
+        WeatherKind::Fog => Preset {
+            capacity: 256,
+            rate: 10.0,
+            crossing: 0.0,
+            emitter: ParticleEmitter {
+                extent: [1.2, 1.0],
+                gravity: [0.0, 0.0],
+                color: [0.8, 0.85, 0.9, 0.12],
+                lifetime: 14.0,
+                speed: 0.03,
+                direction: 0.0,
+                spread: std::f32::consts::TAU,
+                size: 0.4,
+                softness: 1.0,
+                ..Default::default()
+            },
+            alpha: fade(0.2, 0.3),
+        },
+    }
+}
+
+pub(super) struct WeatherState {
+    weather: Weather,
+    system: usize, // its particle system
+    preset: Preset,
+}
+
+impl<'a> Renderer<'a> {
+    // a screen wide particle system set up for the kind, one per call
+    pub fn add_weather(&mut self, weather: Weather) -> Option<usize> {
+        let mut preset = preset(weather.kind);
+        let system = self.create_particle_system(preset.capacity, preset.emitter)?;
+        let alpha = std::mem::take(&mut preset.alpha);
+        let curve = self.add_curve(&format!("{} alpha", weather.kind.name()), alpha);
+        self.set_particle_curve(system, ParticleCurve::Alpha, Some(curve));
+
+        let id = self.weather.len();
+        self.weather.push(WeatherState {
+            weather,
+            system,
+            preset,
+        });
+        Some(id)
+    }
+
+    pub fn weather(&mut self, id: usize) -> Option<&mut Weather> {
+        self.weather.get_mut(id).map(|state| &mut state.weather)
+    }
+
+    // the oldest weather of the kind
+    pub fn find_weather(&self, kind: WeatherKind) -> Option<usize> {
+        self.weather
+            .iter()
+            .position(|state| state.weather.kind == kind)
+    }
+
+    // stops every kind, particles already out keep going until they land
+    pub fn clear_weather(&mut self) {
+        for state in self.weather.iter_mut() {
+            state.weather.intensity = 0.0;
+        }
+    }
+
+    // the weather's wind and ground into its emitter, in clip space
+    pub(super) fn update_weather(&mut self) {
+        let [width, height] = self.viewport_size();
+        for i in 0..self.weather.len() {
+            let WeatherState {
+                weather,
+                system,
+                preset,
+            } = &self.weather[i];
+            let wind = 2.0 * weather.wind / width;
+            let ground = match (weather.kind, weather.ground) {
+                (WeatherKind::Fog, _) | (_, None) => None,
+                (_, Some(ground)) => match self.cameras.get(weather.camera) {
+                    Some(camera) => {
+                        Some(1.0 - 2.0 * camera.world_to_screen([0.0, ground])[1] / height)
+                    }
+                    None => {
+                        warn!("no camera {} for the weather's ground", weather.camera);
+                        None
+                    }
+                },
+            };
+            // spawn further upwind the more it blows, so the wind doesn't
+            // leave one side of the screen dry
+            let upwind = wind * preset.crossing;
+            let extent = preset.emitter.extent[0].max(1.0) + upwind.abs() * 0.5;
+            let rate = preset.rate * weather.intensity.clamp(0.0, 1.0) * extent;
+            let system = *system;
+            let base = preset.emitter;
+
+            let Some(emitter) = self.particle_emitter(system) else {
+                continue;
+            };
+            emitter.position[0] = base.position[0] - upwind * 0.5;
+            emitter.extent[0] = extent;
+            emitter.wind = [wind, 0.0];
+            emitter.ground = ground;
+            emitter.rate = rate;
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use directories::ProjectDirs;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use winit::dpi::PhysicalSize;
+use winit::window::{Fullscreen, WindowAttributes};
+
+use crate::assets::discovery::DiscoveryMode;
+use crate::audio::AudioConfig;
+use crate::audio::mixer::{Bus, BusLevel};
+use crate::logging::LogConfig;
+use crate::platform::focus::FocusLossPolicy;
+use crate::renderer::hdr::Tonemapping;
+use crate::renderer::present::Vsync;
+use crate::tasks::TaskPoolConfig;
+use crate::ui::theme::Theme;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub resolution: [u32; 2],
+    pub fullscreen: bool,
+    pub volume: f32, // the master bus
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted_buses: Vec<Bus>,
+    pub music_crossfade_seconds: f32,
+    pub rumble: f32, // scales every controller rumble, 0 turns it off
+    pub keybinds: BTreeMap<String, String>, // action -> key name
+    pub texture_budget_mb: Option<u64>, // unset keeps every texture loaded
+    pub image_cache_mb: u64, // decoded textures kept compressed in ram, 0 turns it off
+    pub theme: String, // one of ui::theme::BUILTIN_THEMES
+    pub theme_overrides: toml::Table, // single theme fields, e.g. accent = [1, 0, 0, 1]
+    pub worker_threads: Option<usize>, // unset uses every core but one
+    pub hdr: bool,
+    pub hdr_output: bool, // needs a restart, ignored on displays without hdr
+    pub tonemapping: Tonemapping,
+    pub exposure: f32, // in stops
+    pub paper_white_nits: f32,
+    pub peak_nits: f32,
+    pub vsync: Vsync,
+    pub frame_latency: u32, // frames queued ahead, 1 is the most responsive
+    pub focus_loss: FocusLossPolicy,
+    pub asset_discovery: DiscoveryMode, // scan opts into the startup scan while developing
+    pub remote_assets: Option<String>,  // base url missing assets are fetched from
+    pub log: LogConfig,
+    pub cvars: toml::Table, // name -> value, see cvars.rs
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            resolution: [1280, 720],
+            fullscreen: false,
+            volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            muted_buses: Vec::new(),
+            music_crossfade_seconds: 2.0,
+            rumble: 1.0,
+            keybinds: BTreeMap::new(),
+            texture_budget_mb: None,
+            image_cache_mb: 64,
+            theme: "dark".to_string(),
+            theme_overrides: toml::Table::new(),
+            worker_threads: None,
+            hdr: false,
+            hdr_output: false,
+            tonemapping: Tonemapping::None,
+            exposure: 0.0,
+            paper_white_nits: 200.0,
+            peak_nits: 1000.0,
+            vsync: Vsync::default(),
+            frame_latency: 2,
+            focus_loss: FocusLossPolicy::default(),
+            asset_discovery: DiscoveryMode::default(),
+            remote_assets: None,
+            log: LogConfig::default(),
+            cvars: toml::Table::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn project_dirs() -> Option<ProjectDirs> {
+        ProjectDirs::from("", "", "nivalis")
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        Some(Self::project_dirs()?.config_dir().join(SETTINGS_FILE))
+    }
+
+    // falls back to defaults when the file is missing or broken
+    pub fn load() -> Settings {
+        let Some(path) = Self::path() else {
+            warn!("no config directory on this platform, using default settings");
+            return Settings::default();
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                info!("no settings at {}, using defaults", path.display());
+                return Settings::default();
+            }
+        };
+
+        match toml::from_str(&contents) {
+            Ok(settings) => {
+                info!("loaded settings from {}", path.display());
+                settings
+            }
+            Err(e) => {
+                warn!("failed to parse {}: {}", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().context("no config directory on this platform")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+
+        fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        info!("saved settings to {}", path.display());
+        Ok(())
+    }
+
+    pub fn texture_budget(&self) -> Option<u64> {
+        self.texture_budget_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    pub fn task_pool(&self) -> TaskPoolConfig {
+        TaskPoolConfig {
+            compute_threads: self.worker_threads,
+            ..Default::default()
+        }
+    }
+
+    pub fn audio(&self) -> AudioConfig {
+        let level = |bus, volume: f32| BusLevel {
+            volume: volume.max(0.0),
+            muted: self.muted_buses.contains(&bus),
+        };
+        AudioConfig {
+            master: level(Bus::Master, self.volume),
+            music: level(Bus::Music, self.music_volume),
+            sfx: level(Bus::Sfx, self.sfx_volume),
+            crossfade_seconds: self.music_crossfade_seconds.max(0.0),
+        }
+    }
+
+    // the named theme with the overrides on top, ignoring them when they don't fit
+    pub fn theme(&self) -> Theme {
+        let theme = Theme::named(&self.theme);
+        if self.theme_overrides.is_empty() {
+            return theme;
+        }
+
+        let mut fields = match toml::Table::try_from(theme) {
+            Ok(fields) => fields,
+            Err(e) => {
+                warn!("failed to serialize theme {}: {}", self.theme, e);
+                return theme;
+            }
+        };
+        fields.extend(self.theme_overrides.clone());
+
+        match fields.try_into() {
+            Ok(theme) => theme,
+            Err(e) => {
+                warn!("ignoring theme overrides: {}", e);
+                theme
+            }
+        }
+    }
+
+    pub fn window_attributes(&self) -> WindowAttributes {
+        let [width, height] = self.resolution;
+        let mut attributes =
+            WindowAttributes::default().with_inner_size(PhysicalSize::new(width, height));
+
+        if self.fullscreen {
+            attributes = attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+
+        attributes
+    }
+}
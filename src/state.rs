@@ -0,0 +1,184 @@
+use log::{info, warn};
+use winit::event::WindowEvent;
+
+use crate::{
+    assets::{discovery::AssetKind, manager::AssetManager},
+    audio::{Audio, SoundHandle},
+    cvars::Cvars,
+    input::Input,
+    integrations::Integrations,
+    net::clock::Clock,
+    platform::window::PlatformWindow,
+    renderer::Renderer,
+    settings::Settings,
+};
+
+// what a state wants the stack to do after a hook ran
+pub enum Transition {
+    None,
+    Push(Box<dyn GameState>),
+    Pop,
+    #[allow(dead_code)] // only the game's states swap themselves out
+    Replace(Box<dyn GameState>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EventResponse {
+    Consumed, // states below don't see the event
+    Pass,
+}
+
+pub struct StateContext<'r, 'a> {
+    pub renderer: &'r mut Renderer<'a>,
+    pub window: &'r mut PlatformWindow, // title, size limits, level and transparency
+    pub assets: &'r mut AssetManager,
+    pub audio: &'r mut Audio,
+    pub input: &'r mut Input,
+    pub settings: &'r mut Settings,
+    pub cvars: &'r mut Cvars,
+    pub integrations: &'r mut Integrations,
+    pub net_clock: Option<&'r mut Clock>, // when the game set one, see Engine::set_net_clock
+}
+
+impl StateContext<'_, '_> {
+    // texture pool and id of an asset by logical path, e.g. textures/cat,
+    // decoded the first time a sprite draws it
+    pub fn texture(&mut self, path: &str) -> Option<(usize, usize)> {
+        let path = self.assets.resolve(path, AssetKind::Texture)?;
+        Some(self.renderer.discovered_texture(path))
+    }
+
+    // texture pool and id of the index-th texture registered in an asset pool,
+    // what a sprite's pool and texture are set to. None until the pool loaded
+    pub fn pool_texture(&self, asset_pool: usize, index: usize) -> Option<(usize, usize)> {
+        Some((self.renderer.texture_pool(asset_pool)?, index))
+    }
+
+    // decoded on the first call for the path, later calls are a lookup
+    pub fn sound(&mut self, path: &str) -> Option<SoundHandle> {
+        let path = self.assets.resolve(path, AssetKind::Sound)?.to_string();
+        let pool = self.assets.discovered_pool();
+        Some(self.audio.find_or_load(pool, &path))
+    }
+}
+
+pub trait GameState {
+    fn name(&self) -> &str;
+
+    fn on_enter(&mut self, _ctx: &mut StateContext) {}
+    fn on_exit(&mut self, _ctx: &mut StateContext) {}
+
+    fn update(&mut self, _ctx: &mut StateContext, _dt_seconds: f32) -> Transition {
+        Transition::None
+    }
+
+    fn render(&mut self, _ctx: &mut StateContext) {}
+
+    fn handle_event(&mut self, _ctx: &mut StateContext, _event: &WindowEvent) -> EventResponse {
+        EventResponse::Pass
+    }
+
+    // false lets the states below keep updating (e.g. a hud)
+    fn blocks_update(&self) -> bool {
+        true
+    }
+
+    // true renders the states below first (e.g. a pause menu over gameplay)
+    fn is_transparent(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct StateStack {
+    states: Vec<Box<dyn GameState>>,
+    pending: Vec<Transition>,
+}
+
+impl StateStack {
+    pub fn new() -> Self {
+        StateStack::default()
+    }
+
+    pub fn top(&self) -> Option<&dyn GameState> {
+        self.states.last().map(|state| state.as_ref())
+    }
+
+    // transitions are applied at the start of the next update
+    pub fn push(&mut self, state: Box<dyn GameState>) {
+        self.pending.push(Transition::Push(state));
+    }
+
+    pub fn update(&mut self, ctx: &mut StateContext, dt_seconds: f32) {
+        self.apply_pending(ctx);
+
+        // top down until a state blocks the ones below
+        for state in self.states.iter_mut().rev() {
+            ctx.renderer.profile_begin(state.name());
+            let transition = state.update(ctx, dt_seconds);
+            ctx.renderer.profile_end();
+            if !matches!(transition, Transition::None) {
+                self.pending.push(transition);
+            }
+
+            if state.blocks_update() {
+                break;
+            }
+        }
+
+        self.apply_pending(ctx);
+    }
+
+    pub fn render(&mut self, ctx: &mut StateContext) {
+        // lowest state that is still visible through the ones above it
+        let first_visible = self
+            .states
+            .iter()
+            .rposition(|state| !state.is_transparent())
+            .unwrap_or(0);
+
+        for state in self.states.iter_mut().skip(first_visible) {
+            ctx.renderer.profile_begin(state.name());
+            state.render(ctx);
+            ctx.renderer.profile_end();
+        }
+    }
+
+    pub fn handle_event(&mut self, ctx: &mut StateContext, event: &WindowEvent) -> EventResponse {
+        for state in self.states.iter_mut().rev() {
+            if state.handle_event(ctx, event) == EventResponse::Consumed {
+                return EventResponse::Consumed;
+            }
+        }
+
+        EventResponse::Pass
+    }
+
+    fn apply_pending(&mut self, ctx: &mut StateContext) {
+        for transition in std::mem::take(&mut self.pending) {
+            match transition {
+                Transition::None => {}
+                Transition::Push(mut state) => {
+                    info!("pushing state {}", state.name());
+                    state.on_enter(ctx);
+                    self.states.push(state);
+                }
+                Transition::Pop => match self.states.pop() {
+                    Some(mut state) => {
+                        info!("popping state {}", state.name());
+                        state.on_exit(ctx);
+                    }
+                    None => warn!("tried to pop an empty state stack"),
+                },
+                Transition::Replace(mut state) => {
+                    if let Some(mut old) = self.states.pop() {
+                        info!("replacing state {} with {}", old.name(), state.name());
+                        old.on_exit(ctx);
+                    }
+                    state.on_enter(ctx);
+                    self.states.push(state);
+                }
+            }
+        }
+    }
+}
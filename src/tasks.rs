@@ -0,0 +1,226 @@
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+use log::{error, info, warn};
+
+static POOL: OnceLock<TaskPool> = OnceLock::new();
+
+const IO_THREADS: usize = 2;
+const CHUNKS_PER_THREAD: usize = 4; // parallel_for splits finer than the threads so they even out
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+thread_local! {
+    // set on the pool's threads. workers drop a panicking task and carry on,
+    // parallel_for raises a helper's panic again on its caller
+    static WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskKind {
+    Compute, // cpu bound, decoding and simulation
+    Io,      // blocking on files or the network, kept apart so they don't starve compute
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaskPoolConfig {
+    pub compute_threads: Option<usize>, // unset uses every core but the main thread's
+    pub io_threads: Option<usize>,
+}
+
+// the one place the engine starts threads. a compute and an io queue, each with
+// its own workers, and a parallel_for the calling thread helps with on up to as
+// many threads as compute has
+pub struct TaskPool {
+    compute: Sender<Job>,
+    io: Sender<Job>,
+    compute_threads: usize,
+    io_threads: usize,
+}
+
+// sets the thread counts, only before anything used the pool
+pub fn configure(config: TaskPoolConfig) {
+    if POOL.set(TaskPool::new(config)).is_err() {
+        warn!("task pool already running, ignoring the new thread counts");
+    }
+}
+
+pub fn pool() -> &'static TaskPool {
+    POOL.get_or_init(|| TaskPool::new(TaskPoolConfig::default()))
+}
+
+// whether a panic on this thread is caught by the pool
+pub fn is_worker_thread() -> bool {
+    WORKER.get()
+}
+
+impl TaskPool {
+    fn new(config: TaskPoolConfig) -> Self {
+        let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+        let compute_threads = config
+            .compute_threads
+            .unwrap_or(cores.saturating_sub(1))
+            .max(1);
+        let io_threads = config.io_threads.unwrap_or(IO_THREADS).max(1);
+        info!(
+            "starting task pool with {} compute and {} io threads",
+            compute_threads, io_threads
+        );
+
+        TaskPool {
+            compute: start_workers("compute", compute_threads),
+            io: start_workers("io", io_threads),
+            compute_threads,
+            io_threads,
+        }
+    }
+
+    pub fn compute_threads(&self) -> usize {
+        self.compute_threads
+    }
+
+    pub fn io_threads(&self) -> usize {
+        self.io_threads
+    }
+
+    // fire and forget, results go wherever the job sends them
+    pub fn execute(&self, kind: TaskKind, job: impl FnOnce() + Send + 'static) {
+        let queue = match kind {
+            TaskKind::Compute => &self.compute,
+            TaskKind::Io => &self.io,
+        };
+        if queue.send(Box::new(job)).is_err() {
+            error!("{:?} workers are gone, dropping a task", kind);
+        }
+    }
+
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        kind: TaskKind,
+        task: impl FnOnce() -> T + Send + 'static,
+    ) -> Task<T> {
+        let (sender, receiver) = mpsc::channel();
+        self.execute(kind, move || {
+            _ = sender.send(task());
+        });
+        Task { receiver }
+    }
+
+    // runs f on every item spread over as many threads as compute has, returning
+    // once all ran. the calling thread works through chunks too, so calling it
+    // from a task is fine
+    pub fn parallel_for<T: Send>(&self, items: &mut [T], f: impl Fn(&mut T) + Sync) {
+        let chunk_size = items
+            .len()
+            .div_ceil(self.compute_threads * CHUNKS_PER_THREAD)
+            .max(1);
+        if items.len() <= chunk_size {
+            items.iter_mut().for_each(f);
+            return;
+        }
+        // each chunk is claimed once through next, the locks are never contended
+        let chunks: Vec<Mutex<&mut [T]>> = items.chunks_mut(chunk_size).map(Mutex::new).collect();
+
+        let next = AtomicUsize::new(0);
+        let run = || {
+            loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(chunk) = chunks.get(index) else {
+                    return;
+                };
+                let mut chunk = chunk.lock().unwrap_or_else(|e| e.into_inner());
+                chunk.iter_mut().for_each(&f);
+            }
+        };
+
+        // scoped helpers can borrow the items, they're joined before the scope
+        // returns and a panic in one is raised again here
+        let helpers = self.compute_threads.min(chunks.len()) - 1;
+        thread::scope(|scope| {
+            for index in 0..helpers {
+                let spawned = thread::Builder::new()
+                    .name(format!("parallel-for-{}", index))
+                    .spawn_scoped(scope, || {
+                        WORKER.set(true);
+                        run();
+                    });
+                // the chunks it would have taken are left to the others
+                if let Err(e) = spawned {
+                    warn!("failed to spawn parallel_for thread: {}", e);
+                    break;
+                }
+            }
+            run();
+        });
+    }
+}
+
+fn start_workers(kind: &str, threads: usize) -> Sender<Job> {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for index in 0..threads {
+        let receiver = receiver.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("{}-worker-{}", kind, index))
+            .spawn(move || {
+                WORKER.set(true);
+                loop {
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(job) = job else {
+                        return; // the pool was dropped
+                    };
+                    // a panicking task is only logged by the crash hook, the worker lives on
+                    _ = panic::catch_unwind(AssertUnwindSafe(job));
+                }
+            });
+
+        if let Err(e) = spawned {
+            error!("failed to spawn {} worker thread: {}", kind, e);
+        }
+    }
+
+    sender
+}
+
+// the result of a spawned task
+pub struct Task<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Task<T> {
+    // None while it still runs
+    pub fn try_take(&mut self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_for_runs_every_item_once() {
+        let mut items: Vec<usize> = (0..10_000).collect();
+        pool().parallel_for(&mut items, |item| *item *= 2);
+
+        assert!(items.iter().enumerate().all(|(i, item)| *item == i * 2));
+    }
+
+    #[test]
+    fn parallel_for_raises_a_panic_on_the_caller() {
+        let mut items = vec![0; 10_000];
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool().parallel_for(&mut items, |_: &mut i32| panic!("item failed"))
+        }));
+
+        assert!(result.is_err());
+    }
+}
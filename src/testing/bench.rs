@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn};
+
+use crate::assets::manager::LoadedPool;
+use crate::assets::meta::TextureMeta;
+use crate::assets::{DecodedImage, MipLevels, PixelFormat};
+use crate::renderer::Renderer;
+use crate::renderer::layer::{BlendMode, SortMode, Sprite};
+
+const OUTPUT_PATH: &str = "target/bench/latest.toml";
+const REGRESSION_THRESHOLD: f64 = 0.10; // slower than this fraction gets flagged
+
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 720;
+const WARMUP_FRAMES: u32 = 10;
+const FRAMES: u32 = 200;
+const SPRITES: usize = 10_000;
+const SPRITE_COLUMNS: usize = 160;
+const BLEND_ROWS: usize = 16;
+const BLENDS: [BlendMode; 4] = [
+    BlendMode::Alpha,
+    BlendMode::Additive,
+    BlendMode::Multiply,
+    BlendMode::Screen,
+];
+const GLYPH_COUNTS: [usize; 4] = [64, 256, 1024, 4096];
+const UPLOAD_SIZE: u32 = 1024;
+const UPLOADS: u32 = 16;
+// asset pool ids, the uploads go into a pool of their own
+const SPRITE_POOL: usize = 0;
+const UPLOAD_POOL: usize = 1;
+
+// higher is better for every result, so comparing runs stays simple
+type Results = BTreeMap<String, f64>;
+
+// renders a few headless scenes and reports throughput, false on regressions
+pub fn run() -> bool {
+    let Some(mut renderer) = Renderer::new_headless(WIDTH, HEIGHT) else {
+        error!("failed to create headless renderer");
+        return false;
+    };
+
+    let mut results = Results::new();
+    bench_sprites(&mut renderer, &mut results);
+    bench_text(&mut renderer, &mut results);
+    bench_uploads(&mut renderer, &mut results);
+
+    for (name, value) in &results {
+        println!("{:<24} {:>14.1}", name, value);
+    }
+
+    // a regressed run would become the baseline the next one passes against
+    let passed = compare_previous(&results);
+    if passed {
+        save(&results);
+    }
+    passed
+}
+
+// renders frames and waits on the gpu so the time covers the whole frame
+fn time_frames(renderer: &mut Renderer, frames: u32) -> Duration {
+    for _ in 0..WARMUP_FRAMES {
+        renderer.handle_redraw();
+    }
+    wait_idle(renderer);
+
+    let start = Instant::now();
+    for _ in 0..frames {
+        renderer.handle_redraw();
+        wait_idle(renderer);
+    }
+    start.elapsed()
+}
+
+fn wait_idle(renderer: &Renderer) {
+    if let Err(e) = renderer.device().poll(wgpu::PollType::Wait) {
+        warn!("failed to wait for the gpu: {}", e);
+    }
+}
+
+fn bench_sprites(renderer: &mut Renderer, results: &mut Results) {
+    renderer.insert_loaded_pool(LoadedPool {
+        id: SPRITE_POOL,
+        images: vec![DecodedImage::open_or_placeholder("textures/cat.png")],
+        sounds: Vec::new(),
+    });
+
+    // a grid over the screen, the blend mode changes every few rows so the
+    // sprites split into several batches like a real scene's would
+    let layer = renderer.create_layer(0, SortMode::Insertion);
+    let ids: Vec<usize> = (0..SPRITES)
+        .filter_map(|i| {
+            let (column, row) = (i % SPRITE_COLUMNS, i / SPRITE_COLUMNS);
+            let sprite = Sprite {
+                position: [column as f32 * 8.0, row as f32 * 8.0],
+                size: [16.0, 16.0],
+                blend: BLENDS[row / BLEND_ROWS % BLENDS.len()],
+                ..Default::default()
+            };
+            renderer.add_sprite(layer, sprite)
+        })
+        .collect();
+
+    let elapsed = time_frames(renderer, FRAMES);
+    let sprites_per_sec = (ids.len() * FRAMES as usize) as f64 / elapsed.as_secs_f64();
+    info!(
+        "sprites: {} frames of {} sprites in {:?}",
+        FRAMES,
+        ids.len(),
+        elapsed
+    );
+
+    // the text bench measures against an empty frame
+    for id in ids {
+        renderer.remove_sprite(id);
+    }
+    results.insert("sprites_per_sec".to_string(), sprites_per_sec);
+}
+
+fn bench_text(renderer: &mut Renderer, results: &mut Results) {
+    let baseline = time_frames(renderer, FRAMES).as_secs_f64() / FRAMES as f64;
+
+    for glyphs in GLYPH_COUNTS {
+        let text: String = "nivalis ".chars().cycle().take(glyphs).collect();
+        let Some(id) = renderer.add_text(&text, 14.0, 1.2) else {
+            warn!("failed to add text for the text bench");
+            return;
+        };
+
+        let per_frame = time_frames(renderer, FRAMES).as_secs_f64() / FRAMES as f64;
+        renderer.remove_text(id);
+
+        // prepare cost is whatever the text adds on top of an empty frame
+        let text_cost = (per_frame - baseline).max(f64::EPSILON);
+        info!(
+            "text: {} glyphs add {:.3} ms per frame",
+            glyphs,
+            text_cost * 1000.0
+        );
+
+        results.insert(
+            format!("text_{}_glyphs_per_sec", glyphs),
+            glyphs as f64 / text_cost,
+        );
+    }
+}
+
+fn bench_uploads(renderer: &mut Renderer, results: &mut Results) {
+    let image = DecodedImage {
+        name: "bench upload".to_string(),
+        rgba: vec![127; (UPLOAD_SIZE * UPLOAD_SIZE * 4) as usize],
+        dimensions: (UPLOAD_SIZE, UPLOAD_SIZE),
+        format: PixelFormat::Rgba8,
+        meta: TextureMeta::default(),
+        mips: MipLevels::single((UPLOAD_SIZE, UPLOAD_SIZE)),
+    };
+    let bytes = image.rgba.len() as f64 * UPLOADS as f64;
+
+    let images = (0..UPLOADS)
+        .map(|_| DecodedImage {
+            name: image.name.clone(),
+            rgba: image.rgba.clone(),
+            dimensions: image.dimensions,
+            format: PixelFormat::Rgba8,
+            meta: image.meta.clone(),
+            mips: image.mips,
+        })
+        .collect();
+
+    let start = Instant::now();
+    renderer.insert_loaded_pool(LoadedPool {
+        id: UPLOAD_POOL,
+        images,
+        sounds: Vec::new(),
+    });
+    renderer.queue().submit([]);
+    wait_idle(renderer);
+    let elapsed = start.elapsed();
+
+    info!(
+        "uploads: {} textures of {}x{} in {:?}",
+        UPLOADS, UPLOAD_SIZE, UPLOAD_SIZE, elapsed
+    );
+    results.insert(
+        "upload_mb_per_sec".to_string(),
+        bytes / (1024.0 * 1024.0) / elapsed.as_secs_f64(),
+    );
+}
+
+// flags results that dropped too far below the previous run
+fn compare_previous(results: &Results) -> bool {
+    let previous: Results = match std::fs::read_to_string(OUTPUT_PATH) {
+        Ok(contents) => match toml::from_str(&contents) {
+            Ok(previous) => previous,
+            Err(e) => {
+                warn!("failed to parse {}: {}", OUTPUT_PATH, e);
+                return true;
+            }
+        },
+        Err(_) => {
+            info!("no previous bench results");
+            return true;
+        }
+    };
+
+    let mut passed = true;
+    for (name, value) in results {
+        let Some(old) = previous.get(name) else {
+            continue;
+        };
+
+        let change = value / old - 1.0;
+        if change < -REGRESSION_THRESHOLD {
+            error!("{} regressed by {:.1}%", name, -change * 100.0);
+            passed = false;
+        }
+    }
+
+    passed
+}
+
+fn save(results: &Results) {
+    let path = Path::new(OUTPUT_PATH);
+    if let Some(dir) = path.parent()
+        && let Err(e) = std::fs::create_dir_all(dir)
+    {
+        warn!("failed to create {}: {}", dir.display(), e);
+        return;
+    }
+
+    let contents = match toml::to_string_pretty(results) {
+        Ok(contents) => contents,
+        Err(e) => {
+            warn!("failed to serialize bench results: {}", e);
+            return;
+        }
+    };
+
+    match std::fs::write(path, contents) {
+        Ok(()) => info!("saved bench results to {}", path.display()),
+        Err(e) => warn!("failed to write {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,1057 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use image::RgbaImage;
+use log::{error, info, warn};
+
+use crate::assets::cubemap::DecodedCubemap;
+use crate::assets::manager::LoadedPool;
+use crate::assets::meta::TextureMeta;
+use crate::assets::{DecodedImage, MipLevels, PixelFormat};
+use crate::input::Input;
+use crate::renderer::Renderer;
+use crate::renderer::camera::{Camera2D, PixelSnap, SCREEN_CAMERA};
+use crate::renderer::debug_view::DebugView;
+use crate::renderer::distortion::Distortion;
+use crate::renderer::grid::GridOverlay;
+use crate::renderer::hdr::Tonemapping;
+use crate::renderer::layer::{BlendMode, LayerMask, Pivot, SortMode, Sprite, Visibility};
+use crate::renderer::lighting::LightingEnvironment;
+use crate::renderer::material::SpriteMaterial;
+use crate::renderer::outline::Outline;
+use crate::renderer::palette::SpritePalette;
+use crate::renderer::particles::ParticleEmitter;
+use crate::renderer::passes::{CustomPass, PassContext, RenderStage};
+use crate::renderer::polyline::{Dash, LineCap, LineJoin, LineStyle};
+use crate::renderer::sdf::ShapeStyle;
+use crate::renderer::skybox::SkyView;
+use crate::renderer::viewport::{Anchor, AspectRatio};
+use crate::ui::panel::Panel;
+use crate::ui::scroll_view::ScrollView;
+
+const GOLDEN_DIR: &str = "tests/golden";
+const OUTPUT_DIR: &str = "target/golden";
+const UPDATE_ENV: &str = "NIVALIS_UPDATE_GOLDEN";
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 180;
+const FIXED_DELTA: Duration = Duration::from_micros(16_667);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    pub channel: u8,       // per channel difference that still counts as equal
+    pub max_mismatch: f32, // fraction of pixels allowed to differ
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            channel: 8,
+            max_mismatch: 0.002,
+        }
+    }
+}
+
+pub struct GoldenCase {
+    pub name: &'static str,
+    pub frames: u32,
+    pub tolerance: Tolerance,
+    pub setup: fn(&mut Renderer),
+}
+
+#[derive(Debug)]
+pub enum GoldenResult {
+    Passed,
+    Written, // no golden yet, or updating them
+    Failed { mismatch: f32 },
+    Error(String),
+}
+
+pub fn cases() -> Vec<GoldenCase> {
+    vec![
+        GoldenCase {
+            name: "loading",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| renderer.set_loading_progress(Some(0.5)),
+        },
+        GoldenCase {
+            name: "sprite",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: insert_sprite,
+        },
+        GoldenCase {
+            name: "sprite_sorting",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // added back to front, so only sorting puts them in order
+                let ysort = renderer.create_layer(0, SortMode::YSort);
+                for (i, color) in TINTS.iter().enumerate() {
+                    let y = 110.0 - i as f32 * 20.0;
+                    renderer.add_sprite(ysort, tinted([90.0 + i as f32 * 20.0, y], *color));
+                }
+
+                let zindex = renderer.create_layer(1, SortMode::ZIndex);
+                for (i, color) in TINTS.iter().enumerate() {
+                    let sprite = Sprite {
+                        z_index: -(i as i32),
+                        ..tinted([210.0 + i as f32 * 20.0, 70.0 + i as f32 * 20.0], *color)
+                    };
+                    renderer.add_sprite(zindex, sprite);
+                }
+            },
+        },
+        GoldenCase {
+            name: "outlines",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // a rotated outline of its own, and one picked like a click would
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                renderer.add_sprite(
+                    layer,
+                    Sprite {
+                        position: [90.0, 90.0],
+                        size: [80.0, 60.0],
+                        rotation: 0.3,
+                        outline: Some(Outline {
+                            color: [1.0, 0.2, 0.2, 1.0],
+                            width: 3.0,
+                        }),
+                        ..Default::default()
+                    },
+                );
+                renderer.add_sprite(
+                    layer,
+                    Sprite {
+                        position: [230.0, 90.0],
+                        size: [80.0, 60.0],
+                        ..Default::default()
+                    },
+                );
+                let picked = renderer.pick_sprite([250.0, 100.0]);
+                renderer.select_sprite(picked);
+            },
+        },
+        GoldenCase {
+            name: "sprite_pivots",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // all rotated around their own pivot, which stays on the marker spot
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                let pivots = [
+                    Pivot::Center,
+                    Pivot::TopLeft,
+                    Pivot::BottomRight,
+                    Pivot::Custom([0.5, 1.0]),
+                ];
+                for (i, pivot) in pivots.into_iter().enumerate() {
+                    let position = [50.0 + i as f32 * 75.0, 90.0];
+                    renderer.add_sprite(
+                        layer,
+                        Sprite {
+                            pivot,
+                            rotation: std::f32::consts::FRAC_PI_4,
+                            ..tinted(position, TINTS[i % TINTS.len()])
+                        },
+                    );
+                    renderer.add_sprite(
+                        layer,
+                        Sprite {
+                            size: [4.0, 4.0],
+                            ..tinted(position, [1.0, 1.0, 1.0, 1.0])
+                        },
+                    );
+                }
+            },
+        },
+        GoldenCase {
+            name: "sprite_visibility",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // fading left to right, the last one hidden
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                for i in 0..4 {
+                    let visibility = match i {
+                        3 => Visibility::hidden(),
+                        _ => Visibility {
+                            opacity: 1.0 - i as f32 * 0.3,
+                            ..Default::default()
+                        },
+                    };
+                    renderer.add_sprite(
+                        layer,
+                        Sprite {
+                            visibility,
+                            ..tinted([80.0 + i as f32 * 55.0, 90.0], TINTS[i % TINTS.len()])
+                        },
+                    );
+                }
+            },
+        },
+        GoldenCase {
+            name: "blend_modes",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // alpha, additive, multiply and screen, each half over the backdrop
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                let modes = [
+                    BlendMode::Alpha,
+                    BlendMode::Additive,
+                    BlendMode::Multiply,
+                    BlendMode::Screen,
+                ];
+                for (i, blend) in modes.into_iter().enumerate() {
+                    renderer.add_sprite(
+                        layer,
+                        Sprite {
+                            blend,
+                            size: [70.0, 120.0],
+                            ..tinted([50.0 + i as f32 * 73.0, 90.0], TINTS[i % TINTS.len()])
+                        },
+                    );
+                }
+            },
+        },
+        GoldenCase {
+            name: "debug_overdraw",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // a staircase of overlaps, up to five deep in the middle
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                for i in 0..5 {
+                    let offset = i as f32 * 20.0;
+                    renderer.add_sprite(layer, tinted([110.0 + offset, 60.0 + offset], TINTS[0]));
+                    renderer.add_sprite(layer, tinted([270.0 - offset, 60.0 + offset], TINTS[1]));
+                }
+                renderer.set_debug_view(DebugView::Overdraw);
+            },
+        },
+        GoldenCase {
+            name: "debug_uv",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // stretched and rotated, the checker follows the texture
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                renderer.add_sprite(layer, tinted([90.0, 90.0], TINTS[0]));
+                renderer.add_sprite(
+                    layer,
+                    Sprite {
+                        size: [140.0, 50.0],
+                        rotation: 0.4,
+                        ..tinted([250.0, 110.0], TINTS[1])
+                    },
+                );
+                renderer.set_debug_view(DebugView::UvChecker);
+            },
+        },
+        GoldenCase {
+            name: "lighting",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // a dim blue night through the identity lut
+                renderer.set_lighting(LightingEnvironment {
+                    ambient: [0.45, 0.55, 1.0],
+                    exposure: -0.5,
+                    grading: 1.0,
+                });
+            },
+        },
+        GoldenCase {
+            name: "tonemapping",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // overexposed in a float scene, aces rolls the highlights off
+                renderer.set_hdr(true);
+                renderer.set_exposure(2.0);
+                renderer.set_tonemapping(Tonemapping::Aces);
+            },
+        },
+        GoldenCase {
+            name: "distortion",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // only the right half ripples, the cat stands in for an offset map
+                let (pool, texture) = renderer.white_texture();
+                let water = renderer.create_layer(0, SortMode::Insertion);
+                renderer.set_layer_mask(water, LayerMask::Distort);
+                renderer.add_sprite(
+                    water,
+                    Sprite {
+                        pool,
+                        texture,
+                        pivot: Pivot::TopLeft,
+                        position: [160.0, 0.0],
+                        size: [160.0, 180.0],
+                        ..Default::default()
+                    },
+                );
+                renderer.set_distortion(Some(Distortion {
+                    strength: 12.0,
+                    scale: 64.0,
+                    ..Default::default()
+                }));
+            },
+        },
+        GoldenCase {
+            name: "sprite_masking",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // left is cut by a scissor rect, right only shows inside a rotated mask
+                let clipped = renderer.create_layer(0, SortMode::Insertion);
+                renderer.set_layer_clip(clipped, Some([40, 50, 60, 80]));
+                renderer.add_sprite(
+                    clipped,
+                    Sprite {
+                        size: [100.0, 100.0],
+                        ..tinted([80.0, 90.0], TINTS[0])
+                    },
+                );
+
+                let mask = renderer.create_layer(1, SortMode::Insertion);
+                renderer.set_layer_mask(mask, LayerMask::Write(1));
+                renderer.add_sprite(
+                    mask,
+                    Sprite {
+                        size: [70.0, 70.0],
+                        rotation: std::f32::consts::FRAC_PI_4,
+                        ..tinted([230.0, 90.0], [1.0, 1.0, 1.0, 1.0])
+                    },
+                );
+
+                let masked = renderer.create_layer(2, SortMode::Insertion);
+                renderer.set_layer_mask(masked, LayerMask::Test(1));
+                renderer.add_sprite(
+                    masked,
+                    Sprite {
+                        size: [120.0, 120.0],
+                        ..tinted([230.0, 90.0], TINTS[1])
+                    },
+                );
+            },
+        },
+        GoldenCase {
+            name: "scroll_view",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                // a grid taller than the view, scrolled partway with both edges cut
+                let mut view =
+                    ScrollView::new(renderer, [60.0, 30.0, 200.0, 120.0], [200.0, 400.0], 0);
+                let (pool, texture) = renderer.white_texture();
+                for i in 0..20 {
+                    let position = [20.0 + (i % 4) as f32 * 50.0, 20.0 + (i / 4) as f32 * 80.0];
+                    renderer.add_sprite(
+                        view.layer(),
+                        Sprite {
+                            pool,
+                            texture,
+                            size: [40.0, 60.0],
+                            ..tinted(position, TINTS[i % TINTS.len()])
+                        },
+                    );
+                }
+
+                view.set_offset([0.0, 130.0]);
+                view.update(&mut Input::new(), renderer, 0.0);
+            },
+        },
+        GoldenCase {
+            name: "custom_pass",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // the sprite is drawn over the shade, the shade over the background
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                renderer.add_sprite(layer, tinted([160.0, 90.0], TINTS[1]));
+                renderer
+                    .add_render_pass(RenderStage::BeforeSprites, Box::new(ShadePass::default()));
+            },
+        },
+        GoldenCase {
+            name: "pixel_snap",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // same fractional positions, the bottom row lands on whole pixels
+                for (row, snap) in [PixelSnap::Smooth, PixelSnap::Snapped]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let camera = renderer.create_camera(Camera2D {
+                        position: [0.25, -0.4],
+                        zoom: 3.0,
+                        snap,
+                    });
+                    let layer = renderer.create_layer(0, SortMode::Insertion);
+                    renderer.set_layer_camera(layer, camera);
+
+                    for i in 0..4 {
+                        let position = [12.3 + i as f32 * 24.37, 15.6 + row as f32 * 24.0];
+                        renderer.add_sprite(
+                            layer,
+                            Sprite {
+                                size: [7.0, 7.0],
+                                ..tinted(position, TINTS[i % TINTS.len()])
+                            },
+                        );
+                    }
+                }
+            },
+        },
+        GoldenCase {
+            name: "particles",
+            frames: 30,
+            tolerance: Tolerance {
+                max_mismatch: 0.01,
+                ..Default::default()
+            },
+            setup: |renderer| {
+                insert_sprite(renderer);
+                renderer.create_particle_system(
+                    10_000,
+                    ParticleEmitter {
+                        position: [0.0, -0.6],
+                        color: [1.0, 0.6, 0.2, 1.0],
+                        rate: 2_000.0,
+                        ..Default::default()
+                    },
+                );
+            },
+        },
+        GoldenCase {
+            name: "debug_draw",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // the origin near the top left, zoomed so the minor lines are half faded in
+                let camera = renderer.create_camera(Camera2D {
+                    position: [-40.0, -60.0],
+                    zoom: 0.6,
+                    ..Default::default()
+                });
+                renderer.set_grid_overlay(GridOverlay {
+                    camera,
+                    ..Default::default()
+                });
+                renderer.show_grid(true);
+
+                renderer.draw_debug_rect(camera, [40.0, 40.0, 120.0, 80.0], TINTS[0], 2.0);
+                renderer.draw_debug_circle(camera, [260.0, 120.0], 50.0, TINTS[1], 3.0);
+                renderer.draw_debug_line(camera, [300.0, 0.0], [420.0, 200.0], TINTS[2], 1.0);
+            },
+        },
+        GoldenCase {
+            name: "sdf_shapes",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                Panel::new([10.0, 10.0, 300.0, 70.0]).draw(renderer);
+                let border = [1.0, 1.0, 1.0, 0.9];
+                renderer.draw_sdf_rect(
+                    SCREEN_CAMERA,
+                    [24.0, 24.0, 100.0, 42.0],
+                    12.0,
+                    ShapeStyle::bordered(TINTS[0], border, 3.0),
+                );
+                renderer.draw_sdf_circle(
+                    SCREEN_CAMERA,
+                    [80.0, 130.0],
+                    36.0,
+                    ShapeStyle::bordered(TINTS[1], border, 4.0),
+                );
+                renderer.draw_sdf_ring(
+                    SCREEN_CAMERA,
+                    [200.0, 130.0],
+                    36.0,
+                    10.0,
+                    ShapeStyle::bordered(TINTS[2], border, 2.0),
+                );
+                // small enough that the anti-aliased edge is most of it
+                renderer.draw_sdf_circle(
+                    SCREEN_CAMERA,
+                    [280.0, 130.0],
+                    4.5,
+                    ShapeStyle::filled([1.0, 1.0, 1.0, 1.0]),
+                );
+            },
+        },
+        GoldenCase {
+            name: "shapes",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                renderer.draw_rect(SCREEN_CAMERA, [20.0, 20.0, 80.0, 50.0], TINTS[0]);
+                renderer.draw_circle(SCREEN_CAMERA, [160.0, 60.0], 40.0, TINTS[1]);
+                renderer.draw_line(SCREEN_CAMERA, [20.0, 160.0], [300.0, 110.0], TINTS[2], 6.0);
+
+                // concave, so a fan would cover the notches
+                let star: Vec<[f32; 2]> = (0..10)
+                    .map(|i| {
+                        let angle = i as f32 / 10.0 * std::f32::consts::TAU;
+                        let radius = if i % 2 == 0 { 45.0 } else { 18.0 };
+                        [260.0 + radius * angle.sin(), 70.0 - radius * angle.cos()]
+                    })
+                    .collect();
+                renderer.draw_polygon(SCREEN_CAMERA, &star, [1.0, 1.0, 1.0, 0.8]);
+            },
+        },
+        GoldenCase {
+            name: "polylines",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // one zigzag per join, each with a different cap
+                let zigzag = |y: f32| [[20.0, y + 30.0], [70.0, y], [120.0, y + 30.0], [170.0, y]];
+                let styles = [
+                    (LineJoin::Miter, LineCap::Butt),
+                    (LineJoin::Round, LineCap::Round),
+                    (LineJoin::Bevel, LineCap::Square),
+                ];
+                for (i, (join, cap)) in styles.into_iter().enumerate() {
+                    let style = LineStyle {
+                        join,
+                        cap,
+                        ..LineStyle::new(TINTS[i], 10.0)
+                    };
+                    renderer.draw_polyline(SCREEN_CAMERA, &zigzag(20.0 + i as f32 * 55.0), &style);
+                }
+
+                let dashed = LineStyle {
+                    dash: Some(Dash {
+                        length: 12.0,
+                        gap: 8.0,
+                        offset: 4.0,
+                    }),
+                    cap: LineCap::Round,
+                    ..LineStyle::new([1.0, 1.0, 1.0, 1.0], 4.0)
+                };
+                let square = [[210.0, 30.0], [300.0, 30.0], [300.0, 150.0], [210.0, 150.0]];
+                renderer.draw_closed_polyline(SCREEN_CAMERA, &square, &dashed);
+            },
+        },
+        GoldenCase {
+            name: "sprite_materials",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+
+                // half flashed, half dissolved with its edge, and both at once
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                let materials = [
+                    SpriteMaterial {
+                        flash: 0.5,
+                        flash_color: [1.0, 0.1, 0.1],
+                        ..Default::default()
+                    },
+                    SpriteMaterial {
+                        dissolve: 0.5,
+                        ..Default::default()
+                    },
+                    SpriteMaterial {
+                        flash: 1.0,
+                        dissolve: 0.3,
+                        dissolve_color: [0.2, 0.6, 1.0],
+                        ..Default::default()
+                    },
+                ];
+                for (i, material) in materials.into_iter().enumerate() {
+                    renderer.add_sprite(
+                        layer,
+                        Sprite {
+                            position: [80.0 + i as f32 * 100.0, 90.0],
+                            size: [80.0, 80.0],
+                            material,
+                            ..Default::default()
+                        },
+                    );
+                }
+            },
+        },
+        GoldenCase {
+            name: "palette_swap",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+                let pool = renderer.insert_loaded_pool(LoadedPool {
+                    id: 1,
+                    images: vec![indexed_quadrants(), palette_rows()],
+                    sounds: Vec::new(),
+                });
+
+                // one sheet, a variant per row of the palette
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                for row in 0..3 {
+                    renderer.add_sprite(
+                        layer,
+                        Sprite {
+                            pool,
+                            texture: 0,
+                            position: [80.0 + row as f32 * 80.0, 90.0],
+                            size: [64.0, 64.0],
+                            palette: Some(SpritePalette::new(pool, 1).row(row)),
+                            ..Default::default()
+                        },
+                    );
+                }
+            },
+        },
+        GoldenCase {
+            name: "screen_fx",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                for (i, color) in TINTS.iter().enumerate() {
+                    renderer.add_sprite(layer, tinted([100.0 + i as f32 * 60.0, 90.0], *color));
+                }
+
+                // long enough that one frame barely eases them
+                renderer.flash_screen([1.0, 0.2, 0.2, 0.4], 1000.0);
+                renderer.pulse_chromatic_aberration(6.0, 1000.0);
+            },
+        },
+        GoldenCase {
+            name: "skybox",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+                renderer.set_hdr(true);
+                renderer.set_tonemapping(Tonemapping::Aces);
+
+                // looking up and to the right, across a seam between faces
+                match DecodedCubemap::from_equirect(&hdr_panorama()) {
+                    Ok(cubemap) => renderer.set_skybox(Some(cubemap)),
+                    Err(e) => error!("failed to convert the golden panorama: {}", e),
+                }
+                renderer.set_sky_view(SkyView {
+                    yaw: 40f32.to_radians(),
+                    pitch: 25f32.to_radians(),
+                    fov: 90f32.to_radians(),
+                });
+                renderer.set_skybox_exposure(-0.5);
+
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                for (i, color) in TINTS.iter().enumerate() {
+                    renderer.add_sprite(layer, tinted([100.0 + i as f32 * 60.0, 90.0], *color));
+                }
+            },
+        },
+        GoldenCase {
+            name: "letterbox",
+            frames: 1,
+            tolerance: Tolerance::default(),
+            setup: |renderer| {
+                insert_sprite(renderer);
+                // pillarboxed to 240x180 in the middle of the window
+                renderer.set_aspect_ratio(AspectRatio::Fixed(4.0 / 3.0));
+                renderer.set_letterbox_color(wgpu::Color {
+                    r: 0.1,
+                    g: 0.0,
+                    b: 0.1,
+                    a: 1.0,
+                });
+                renderer.set_safe_area_insets([0.0, 0.0, 0.0, 20.0]);
+
+                let layer = renderer.create_layer(0, SortMode::Insertion);
+                // runs off both sides, clipped at the bars
+                for (i, color) in TINTS.iter().enumerate() {
+                    renderer.add_sprite(layer, tinted([i as f32 * 100.0, 60.0], *color));
+                }
+                let hud = Sprite {
+                    size: [30.0, 30.0],
+                    pivot: Pivot::TopLeft,
+                    ..tinted([0.0, 0.0], [1.0, 1.0, 1.0, 1.0])
+                };
+                for anchor in [Anchor::TopLeft, Anchor::BottomRight] {
+                    let position = renderer.anchor(anchor, hud.size, 4.0);
+                    renderer.add_sprite(layer, Sprite { position, ..hud });
+                }
+            },
+        },
+        GoldenCase {
+            name: "text",
+            frames: 1,
+            // glyphs come from system fonts and vary between machines
+            tolerance: Tolerance {
+                channel: 16,
+                max_mismatch: 0.02,
+            },
+            setup: |renderer| {
+                insert_sprite(renderer);
+                renderer.add_text_at("nivalis golden test", 20.0, 1.2, [10.0, 10.0]);
+            },
+        },
+    ]
+}
+
+const TINTS: [[f32; 4]; 3] = [
+    [1.0, 0.3, 0.3, 1.0],
+    [0.3, 1.0, 0.3, 1.0],
+    [0.3, 0.3, 1.0, 1.0],
+];
+
+fn tinted(position: [f32; 2], color: [f32; 4]) -> Sprite {
+    Sprite {
+        position,
+        size: [60.0, 60.0],
+        color,
+        ..Default::default()
+    }
+}
+
+// indices 0 to 3 in the quadrants of an 8x8 texture, the last one transparent
+fn indexed_quadrants() -> DecodedImage {
+    let rgba = (0..64)
+        .flat_map(|i| {
+            let index = (i % 8 / 4 + i / 32 * 2) as u8;
+            [index, 0, 0, if index == 3 { 0 } else { 255 }]
+        })
+        .collect();
+    DecodedImage {
+        name: "golden/indexed".to_string(),
+        rgba,
+        dimensions: (8, 8),
+        format: PixelFormat::Rgba8,
+        meta: TextureMeta {
+            indexed: true,
+            ..Default::default()
+        },
+        mips: MipLevels::single((8, 8)),
+    }
+}
+
+// three variants of four colors
+fn palette_rows() -> DecodedImage {
+    let rows: [[[u8; 4]; 4]; 3] = [
+        [
+            [230, 60, 60, 255],
+            [250, 200, 80, 255],
+            [40, 40, 40, 255],
+            [0; 4],
+        ],
+        [
+            [60, 120, 230, 255],
+            [120, 230, 250, 255],
+            [40, 40, 40, 255],
+            [0; 4],
+        ],
+        [
+            [70, 200, 90, 255],
+            [240, 240, 240, 255],
+            [90, 40, 120, 255],
+            [0; 4],
+        ],
+    ];
+    DecodedImage {
+        name: "golden/palette".to_string(),
+        rgba: rows.as_flattened().as_flattened().to_vec(),
+        dimensions: (4, 3),
+        format: PixelFormat::Rgba8,
+        meta: TextureMeta::default(),
+        mips: MipLevels::single((4, 3)),
+    }
+}
+
+// hue around the horizon, a sun brighter than white and a dark floor
+fn hdr_panorama() -> DecodedImage {
+    let (width, height) = (64, 32);
+    let rgba = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            let u = x as f32 / width as f32;
+            let v = y as f32 / height as f32;
+            let sun = if (28..36).contains(&x) && (6..10).contains(&y) {
+                4.0
+            } else {
+                1.0
+            };
+            let light = (1.0 - v) * sun;
+            [u * light, (1.0 - u) * light, 0.5 * light, 1.0]
+        })
+        .flat_map(|value: f32| half::f16::from_f32(value).to_le_bytes())
+        .collect();
+    DecodedImage {
+        name: "golden/panorama".to_string(),
+        rgba,
+        dimensions: (width, height),
+        format: PixelFormat::Rgba16Float,
+        meta: TextureMeta::default(),
+        mips: MipLevels::single((width, height)),
+    }
+}
+
+// darkens the left half of the target with its own pipeline
+#[derive(Default)]
+struct ShadePass {
+    pipeline: Option<wgpu::RenderPipeline>,
+}
+
+const SHADE_SHADER: &str = "
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(vertex & 1u) - 1.0;
+    let y = f32(vertex >> 1u) * 2.0 - 1.0;
+    return vec4<f32>(x * 2.0 + 1.0, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 0.0, 0.0, 0.6);
+}
+";
+
+impl CustomPass for ShadePass {
+    fn name(&self) -> &str {
+        "shade"
+    }
+
+    fn render(&mut self, ctx: &mut PassContext) {
+        let pipeline = self.pipeline.get_or_insert_with(|| {
+            let module = ctx
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("Shade Shader"),
+                    source: wgpu::ShaderSource::Wgsl(SHADE_SHADER.into()),
+                });
+            ctx.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Shade Pipeline"),
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        module: &module,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &module,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: ctx.format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                })
+        });
+
+        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shade Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.draw(0..4, 0..1);
+    }
+}
+
+fn insert_sprite(renderer: &mut Renderer) {
+    renderer.insert_loaded_pool(LoadedPool {
+        id: 0,
+        images: vec![DecodedImage::open_or_placeholder("textures/cat.png")],
+        sounds: Vec::new(),
+    });
+}
+
+// renders every case and compares it against its golden, true when all pass
+pub fn run() -> bool {
+    let update = std::env::var(UPDATE_ENV).is_ok_and(|value| value == "1");
+    let mut failed = 0;
+
+    for case in cases() {
+        let result = run_case(&case, update);
+        match &result {
+            GoldenResult::Passed => info!("golden {} passed", case.name),
+            GoldenResult::Written => info!("golden {} written", case.name),
+            GoldenResult::Failed { mismatch } => {
+                error!(
+                    "golden {} failed, {:.3}% of pixels differ",
+                    case.name,
+                    mismatch * 100.0
+                );
+                failed += 1;
+            }
+            GoldenResult::Error(e) => {
+                error!("golden {} errored: {}", case.name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        error!("{} golden tests failed, see {}", failed, OUTPUT_DIR);
+    }
+    failed == 0
+}
+
+pub fn run_case(case: &GoldenCase, update: bool) -> GoldenResult {
+    let Some(mut renderer) = Renderer::new_headless(WIDTH, HEIGHT) else {
+        return GoldenResult::Error("failed to create headless renderer".to_string());
+    };
+    renderer.set_fixed_delta(Some(FIXED_DELTA));
+    (case.setup)(&mut renderer);
+
+    for _ in 0..case.frames {
+        if renderer.handle_redraw().is_none() {
+            return GoldenResult::Error("failed to render frame".to_string());
+        }
+    }
+
+    let Some(actual) = renderer.read_frame() else {
+        return GoldenResult::Error("failed to read frame back".to_string());
+    };
+
+    let golden_path = golden_path(case.name);
+    if update || !golden_path.exists() {
+        return match save(&actual, &golden_path) {
+            Ok(()) => GoldenResult::Written,
+            Err(e) => GoldenResult::Error(e),
+        };
+    }
+
+    let expected = match image::open(&golden_path) {
+        Ok(image) => image.to_rgba8(),
+        Err(e) => return GoldenResult::Error(format!("failed to open golden: {}", e)),
+    };
+
+    let Some((mismatch, diff)) = compare(&expected, &actual, case.tolerance.channel) else {
+        warn!(
+            "golden {} is {}x{}, frame is {}x{}",
+            case.name,
+            expected.width(),
+            expected.height(),
+            actual.width(),
+            actual.height()
+        );
+        return GoldenResult::Failed { mismatch: 1.0 };
+    };
+
+    if mismatch <= case.tolerance.max_mismatch {
+        return GoldenResult::Passed;
+    }
+
+    // keep what went wrong around for inspection
+    let output = Path::new(OUTPUT_DIR);
+    for (suffix, image) in [("actual", &actual), ("diff", &diff)] {
+        let path = output.join(format!("{}.{}.png", case.name, suffix));
+        if let Err(e) = save(image, &path) {
+            warn!("{}", e);
+        }
+    }
+
+    GoldenResult::Failed { mismatch }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(GOLDEN_DIR).join(format!("{}.png", name))
+}
+
+fn save(image: &RgbaImage, path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+
+    image
+        .save(path)
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+// fraction of differing pixels and an image marking them red
+pub fn compare(expected: &RgbaImage, actual: &RgbaImage, channel: u8) -> Option<(f32, RgbaImage)> {
+    if expected.dimensions() != actual.dimensions() {
+        return None;
+    }
+
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut mismatched = 0usize;
+
+    for ((a, b), out) in expected
+        .pixels()
+        .zip(actual.pixels())
+        .zip(diff.pixels_mut())
+    {
+        let differs =
+            a.0.iter()
+                .zip(b.0.iter())
+                .any(|(a, b)| a.abs_diff(*b) > channel);
+        if differs {
+            mismatched += 1;
+            *out = image::Rgba([255, 0, 0, 255]);
+        } else {
+            // faded copy of the frame for context
+            *out = image::Rgba([b[0] / 4, b[1] / 4, b[2] / 4, 255]);
+        }
+    }
+
+    let total = (actual.width() * actual.height()).max(1) as f32;
+    Some((mismatched as f32 / total, diff))
+}
@@ -0,0 +1,4 @@
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "golden-tests")]
+pub mod golden;
@@ -0,0 +1,425 @@
+use crate::input::events::{EngineEvent, MouseButton};
+use crate::input::touch::Gesture;
+use crate::input::{Input, KeyCode};
+use crate::renderer::Renderer;
+use crate::renderer::camera::{Camera2D, SCREEN_CAMERA};
+use crate::renderer::grid::{snap, snap_angle};
+use crate::renderer::readback::ReadbackId;
+
+// lengths in logical pixels, scaled to the display
+const ARM: f32 = 70.0; // pivot to the arrow tips and scale handles
+const HEAD: f32 = 10.0;
+const RING: f32 = 60.0;
+const RING_SEGMENTS: usize = 48;
+const HANDLE: f32 = 5.0; // half the size of the square handles
+const REACH: f32 = 6.0; // how far off a handle a press still grabs it
+const LINE_WIDTH: f32 = 2.0;
+
+const ROTATION_STEP: f32 = 15.0; // degrees
+const SCALE_STEP: f32 = 0.1;
+const MIN_SIZE: f32 = 1.0; // world pixels, so a drag can't collapse a sprite
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Handle {
+    Axis(usize), // move along world x or y
+    Free,
+    Ring,
+    Scale(usize), // along the sprite's own x or y
+    Uniform,
+}
+
+// the sprite's transform and the world point when the press started
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    handle: Handle,
+    sprite: usize,
+    mouse: [f32; 2],
+    position: [f32; 2],
+    rotation: f32,
+    size: [f32; 2],
+}
+
+// where a sprite's gizmo is on screen
+struct Placement {
+    camera: Camera2D,
+    origin: [f32; 2],    // the pivot, physical pixels
+    axes: [[f32; 2]; 2], // the sprite's x and y on screen, unit length
+    scale: f32,          // physical pixels per logical one
+}
+
+// move arrows, a rotate ring and scale handles on the renderer's selected sprite.
+// it takes every left click while updated, a press off the handles selects what's
+// under the cursor. w, e and r switch modes, holding control flips snapping to the
+// grid, to 15 degrees and to tenths of the size. dragging with the right button
+// pans the selected sprite's camera with the cursor locked, unless that is the
+// screen camera the ui is drawn with. touch drags and pinches move it as well
+#[derive(Default)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub snap: bool,
+    mouse_position: [f32; 2], // relative to the viewport, like the handles
+    hovered: Option<Handle>,
+    drag: Option<Drag>,
+    pick: Option<ReadbackId>, // answered by the pick buffer when that is on
+    panning: Option<usize>,   // the camera the right button drags
+}
+
+impl Gizmo {
+    // call every frame before game states see the input, returns whether the
+    // selected sprite was moved, turned or resized
+    pub fn update(&mut self, input: &mut Input, renderer: &mut Renderer) -> bool {
+        let control = input.key_down(KeyCode::ControlLeft) || input.key_down(KeyCode::ControlRight);
+        let snapping = self.snap != control;
+        self.take_pick(renderer);
+        if self
+            .drag
+            .is_some_and(|drag| renderer.selected_sprite() != Some(drag.sprite))
+        {
+            self.drag = None;
+        }
+
+        let mut changed = false;
+        let mut pan = None;
+        for queued in input.queue_mut().events_mut() {
+            // the cursor is tracked even when something else took the move
+            if queued.is_consumed() && !matches!(queued.event, EngineEvent::MouseMoved { .. }) {
+                continue;
+            }
+
+            match queued.event {
+                EngineEvent::MouseMoved { position } => {
+                    self.mouse_position = renderer.window_to_viewport(position);
+                    if self.drag.is_some() && !queued.is_consumed() {
+                        changed |= self.apply_drag(renderer, snapping);
+                        queued.consume("gizmo");
+                    }
+                }
+                EngineEvent::MousePressed {
+                    button: MouseButton::Left,
+                    position,
+                } => {
+                    self.mouse_position = renderer.window_to_viewport(position);
+                    match self.handle_at(renderer, self.mouse_position) {
+                        Some(handle) => self.start_drag(renderer, handle),
+                        None => self.select_at(renderer, position),
+                    }
+                    queued.consume("gizmo");
+                }
+                EngineEvent::MouseReleased {
+                    button: MouseButton::Left,
+                    ..
+                } if self.drag.is_some() => {
+                    self.drag = None;
+                    queued.consume("gizmo");
+                }
+                EngineEvent::MousePressed {
+                    button: MouseButton::Right,
+                    ..
+                } => {
+                    let camera = selected_camera(renderer);
+                    if camera.is_some() {
+                        pan = Some(camera);
+                        queued.consume("gizmo");
+                    }
+                }
+                EngineEvent::MouseReleased {
+                    button: MouseButton::Right,
+                    ..
+                } if self.panning.is_some() => {
+                    pan = Some(None);
+                    queued.consume("gizmo");
+                }
+                EngineEvent::Gesture(gesture) if !matches!(gesture, Gesture::Tap { .. }) => {
+                    let camera = selected_camera(renderer).and_then(|id| renderer.camera_mut(id));
+                    if let Some(camera) = camera {
+                        gesture.apply_to_camera(camera);
+                        queued.consume("gizmo");
+                    }
+                }
+                EngineEvent::KeyPressed { key, repeat: false } => {
+                    self.mode = match key {
+                        KeyCode::KeyW => GizmoMode::Translate,
+                        KeyCode::KeyE => GizmoMode::Rotate,
+                        KeyCode::KeyR => GizmoMode::Scale,
+                        _ => continue,
+                    };
+                    queued.consume("gizmo");
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(camera) = pan {
+            self.panning = camera;
+            input.set_relative_mouse(camera.is_some());
+        }
+        if let Some(camera) = self.panning.and_then(|id| renderer.camera_mut(id)) {
+            camera.pan(input.mouse_delta());
+        }
+
+        self.hovered = match self.drag {
+            Some(drag) => Some(drag.handle),
+            None => self.handle_at(renderer, self.mouse_position),
+        };
+        self.draw(renderer);
+        changed
+    }
+
+    // when the gizmo isn't updated anymore, a pan in progress lets go of the cursor
+    pub fn release(&mut self, input: &mut Input) {
+        if self.panning.take().is_some() {
+            input.set_relative_mouse(false);
+        }
+    }
+
+    fn select_at(&mut self, renderer: &mut Renderer, position: [f32; 2]) {
+        match renderer.has_pick_buffer() {
+            true => self.pick = renderer.request_pick(position),
+            false => {
+                let picked = renderer.pick_sprite(position);
+                renderer.select_sprite(picked);
+            }
+        }
+    }
+
+    fn take_pick(&mut self, renderer: &mut Renderer) {
+        if let Some(id) = self.pick
+            && let Some(picked) = renderer.take_pick(id)
+        {
+            self.pick = None;
+            renderer.select_sprite(picked);
+        }
+    }
+
+    fn start_drag(&mut self, renderer: &Renderer, handle: Handle) {
+        let Some(id) = renderer.selected_sprite() else {
+            return;
+        };
+        let (Some(sprite), Some(placement)) = (renderer.sprite(id), placement(renderer, id)) else {
+            return;
+        };
+        self.drag = Some(Drag {
+            handle,
+            sprite: id,
+            mouse: placement.camera.screen_to_world(self.mouse_position),
+            position: sprite.position,
+            rotation: sprite.rotation,
+            size: sprite.size,
+        });
+    }
+
+    fn apply_drag(&mut self, renderer: &mut Renderer, snapping: bool) -> bool {
+        let Some(drag) = self.drag else {
+            return false;
+        };
+        let Some(placement) = placement(renderer, drag.sprite) else {
+            self.drag = None;
+            return false;
+        };
+        let mouse = placement.camera.screen_to_world(self.mouse_position);
+        let delta = [mouse[0] - drag.mouse[0], mouse[1] - drag.mouse[1]];
+        let step = match snapping {
+            true => renderer.grid_step().unwrap_or(0.0),
+            false => 0.0,
+        };
+        let Some(sprite) = renderer.sprite_mut(drag.sprite) else {
+            self.drag = None;
+            return false;
+        };
+
+        match drag.handle {
+            Handle::Axis(axis) => {
+                sprite.position[axis] = snap(drag.position[axis] + delta[axis], step);
+            }
+            Handle::Free => {
+                sprite.position = [0, 1].map(|axis| snap(drag.position[axis] + delta[axis], step));
+            }
+            Handle::Ring => {
+                let angle = |point: [f32; 2]| {
+                    (point[1] - drag.position[1]).atan2(point[0] - drag.position[0])
+                };
+                let rotation = drag.rotation + angle(mouse) - angle(drag.mouse);
+                sprite.rotation = match snapping {
+                    true => snap_angle(rotation, ROTATION_STEP),
+                    false => rotation,
+                };
+            }
+            Handle::Scale(axis) => {
+                // the handle started an arm's length out along the sprite's axis
+                let reach = ARM * placement.scale / placement.camera.zoom;
+                let along = dot(delta, placement.axes[axis]);
+                let factor = scale_factor((reach + along) / reach, snapping);
+                sprite.size[axis] = resize(drag.size[axis], factor);
+            }
+            Handle::Uniform => {
+                // right and up grows, left and down shrinks
+                let reach = ARM * placement.scale / placement.camera.zoom;
+                let factor = scale_factor(1.0 + (delta[0] - delta[1]) / reach, snapping);
+                sprite.size = drag.size.map(|size| resize(size, factor));
+            }
+        }
+        true
+    }
+
+    fn handle_at(&self, renderer: &Renderer, point: [f32; 2]) -> Option<Handle> {
+        let placement = placement(renderer, renderer.selected_sprite()?)?;
+        let [arm, handle, reach] = [ARM, HANDLE, REACH].map(|length| length * placement.scale);
+        let offset = [
+            point[0] - placement.origin[0],
+            point[1] - placement.origin[1],
+        ];
+        let on_square = |center: [f32; 2]| {
+            (0..2).all(|axis| (offset[axis] - center[axis]).abs() <= handle + reach * 0.5)
+        };
+
+        match self.mode {
+            GizmoMode::Translate => {
+                if on_square([0.0, 0.0]) {
+                    return Some(Handle::Free);
+                }
+                (0..2)
+                    .find(|&axis| {
+                        let along = offset[axis].clamp(0.0, arm);
+                        let across = offset[1 - axis];
+                        (offset[axis] - along).hypot(across) <= reach
+                    })
+                    .map(Handle::Axis)
+            }
+            GizmoMode::Rotate => {
+                let radius = RING * placement.scale;
+                ((offset[0].hypot(offset[1]) - radius).abs() <= reach).then_some(Handle::Ring)
+            }
+            GizmoMode::Scale => {
+                let tip = (0..2)
+                    .find(|&axis| on_square(placement.axes[axis].map(|component| component * arm)));
+                match tip {
+                    Some(axis) => Some(Handle::Scale(axis)),
+                    None => on_square([0.0, 0.0]).then_some(Handle::Uniform),
+                }
+            }
+        }
+    }
+
+    fn draw(&self, renderer: &mut Renderer) {
+        let Some(placement) = renderer
+            .selected_sprite()
+            .and_then(|id| placement(renderer, id))
+        else {
+            return;
+        };
+        let theme = renderer.theme();
+        let axis_colors = [theme.debug_palette[0], theme.debug_palette[1]];
+        let color = |handle: Handle, base: [f32; 4]| match self.hovered == Some(handle) {
+            true => theme.accent,
+            false => base,
+        };
+        let [arm, head, handle, width] =
+            [ARM, HEAD, HANDLE, LINE_WIDTH].map(|length| length * placement.scale);
+        let origin = placement.origin;
+        let along = |direction: [f32; 2], length: f32| {
+            [
+                origin[0] + direction[0] * length,
+                origin[1] + direction[1] * length,
+            ]
+        };
+
+        match self.mode {
+            GizmoMode::Translate => {
+                for axis in 0..2 {
+                    let mut direction = [0.0, 0.0];
+                    direction[axis] = 1.0;
+                    let color = color(Handle::Axis(axis), axis_colors[axis]);
+                    let tip = along(direction, arm);
+                    renderer.draw_screen_line(origin, tip, color, width);
+
+                    let back = along(direction, arm - head);
+                    let normal = [-direction[1] * head * 0.5, direction[0] * head * 0.5];
+                    for side in [-1.0, 1.0] {
+                        let barb = [back[0] + normal[0] * side, back[1] + normal[1] * side];
+                        renderer.draw_screen_line(tip, barb, color, width);
+                    }
+                }
+                let free = color(Handle::Free, theme.text);
+                square(renderer, origin, handle, free);
+            }
+            GizmoMode::Rotate => {
+                let color = color(Handle::Ring, theme.debug_palette[2]);
+                let radius = RING * placement.scale;
+                let points: Vec<[f32; 2]> = (0..=RING_SEGMENTS)
+                    .map(|i| {
+                        let angle = i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                        along([angle.cos(), angle.sin()], radius)
+                    })
+                    .collect();
+                for pair in points.windows(2) {
+                    renderer.draw_screen_line(pair[0], pair[1], color, width);
+                }
+                // where the sprite's x points, so the angle is readable
+                renderer.draw_screen_line(origin, along(placement.axes[0], radius), color, width);
+            }
+            GizmoMode::Scale => {
+                for (axis, direction) in placement.axes.into_iter().enumerate() {
+                    let color = color(Handle::Scale(axis), axis_colors[axis]);
+                    let tip = along(direction, arm);
+                    renderer.draw_screen_line(origin, tip, color, width);
+                    square(renderer, tip, handle, color);
+                }
+                let uniform = color(Handle::Uniform, theme.text);
+                square(renderer, origin, handle, uniform);
+            }
+        }
+    }
+}
+
+fn placement(renderer: &Renderer, id: usize) -> Option<Placement> {
+    let camera = *renderer.camera(renderer.sprite_camera(id)?)?;
+    let sprite = renderer.sprite(id)?;
+    let (sin, cos) = sprite.rotation.sin_cos();
+    Some(Placement {
+        camera,
+        origin: camera.world_to_screen(sprite.position),
+        axes: [[cos, sin], [-sin, cos]],
+        scale: renderer.scale_factor(),
+    })
+}
+
+// a line with no length is a filled square as wide as the line
+fn square(renderer: &mut Renderer, center: [f32; 2], half_size: f32, color: [f32; 4]) {
+    renderer.draw_screen_line(center, center, color, half_size * 2.0);
+}
+
+// the camera the selected sprite is seen through, never the ui's
+fn selected_camera(renderer: &Renderer) -> Option<usize> {
+    renderer
+        .selected_sprite()
+        .and_then(|id| renderer.sprite_camera(id))
+        .filter(|&camera| camera != SCREEN_CAMERA)
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn scale_factor(factor: f32, snapping: bool) -> f32 {
+    match snapping {
+        true => snap(factor, SCALE_STEP).max(SCALE_STEP),
+        false => factor,
+    }
+}
+
+// keeps a flipped sprite flipped
+fn resize(size: f32, factor: f32) -> f32 {
+    let resized = (size.abs() * factor).max(MIN_SIZE);
+    match size < 0.0 {
+        true => -resized,
+        false => resized,
+    }
+}
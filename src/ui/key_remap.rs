@@ -0,0 +1,354 @@
+use log::{Level, warn};
+
+use crate::input::KeyCode;
+use crate::input::actions::key_name;
+use crate::input::events::{EngineEvent, MouseButton};
+use crate::renderer::Renderer;
+use crate::renderer::camera::SCREEN_CAMERA;
+use crate::renderer::sdf::ShapeStyle;
+use crate::renderer::viewport::Anchor;
+use crate::state::{GameState, StateContext, Transition};
+use crate::ui::panel::{Button, Panel, contains};
+use crate::ui::text_input::TextInput;
+use crate::ui::theme::Theme;
+
+// logical pixels
+const WIDTH: f32 = 420.0;
+const ROW_HEIGHT: f32 = 32.0;
+const GAP: f32 = 6.0;
+const PADDING: f32 = 16.0;
+const HEADER: f32 = 56.0; // the title and the hint above the rows
+const MARKER_RADIUS: f32 = 5.0;
+const FIELD_PADDING: f32 = 8.0; // between the filter's edge and its text
+
+// lists every registered action with its key. clicking one waits for the next
+// key press to bind it, escape cancels and backspace clears it. keys bound to
+// more than one action are marked. typing into the field above the rows only
+// lists the actions whose label or key matches. leaving saves the bindings to
+// the settings file
+pub struct KeyRemapScreen {
+    panel: Panel,
+    field: Panel, // behind the filter
+    filter: TextInput,
+    rows: Vec<Button>, // one per action, in registration order
+    reset: Button,
+    done: Button,
+    title: Option<usize>,
+    hint: Option<(usize, String)>, // reshaped only when the text changes
+    listening: Option<usize>,      // the row waiting for a key
+}
+
+impl Default for KeyRemapScreen {
+    fn default() -> Self {
+        KeyRemapScreen::new()
+    }
+}
+
+impl KeyRemapScreen {
+    pub fn new() -> Self {
+        let mut filter = TextInput::new([0.0; 2], 0.0);
+        filter.placeholder = "Filter actions".to_string();
+        KeyRemapScreen {
+            panel: Panel::new([0.0; 4]),
+            field: Panel::new([0.0; 4]),
+            filter,
+            rows: Vec::new(),
+            reset: Button::new([0.0; 4], "Reset to defaults"),
+            done: Button::new([0.0; 4], "Done"),
+            title: None,
+            hint: None,
+            listening: None,
+        }
+    }
+
+    // binds the listening row's action to the next key press, escape with
+    // nothing listening clears the filter while typing and otherwise leaves
+    // the screen
+    fn handle_keys(&mut self, ctx: &mut StateContext) -> bool {
+        let mut leave = false;
+        let mut clear_filter = false;
+        let mut binding = None;
+        for queued in ctx.input.queue_mut().unconsumed_mut() {
+            let EngineEvent::KeyPressed { key, repeat } = queued.event else {
+                continue;
+            };
+            queued.consume("key_remap");
+            if repeat {
+                continue;
+            }
+
+            match (self.listening.take(), key) {
+                (Some(_), KeyCode::Escape) => {}
+                (Some(row), KeyCode::Backspace | KeyCode::Delete) => binding = Some((row, None)),
+                (Some(row), key) => binding = Some((row, Some(key))),
+                (None, KeyCode::Escape) if self.filter.is_focused() => clear_filter = true,
+                (None, KeyCode::Escape) => leave = true,
+                (None, _) => {}
+            }
+        }
+
+        if clear_filter {
+            self.filter.set_value("");
+            self.filter.blur(ctx.input);
+        }
+
+        if let Some((row, key)) = binding
+            && let Some(name) = ctx.input.actions().get(row).map(|a| a.name.clone())
+        {
+            ctx.input.bind_action(&name, key);
+        }
+        leave
+    }
+
+    fn hint_text(&self, ctx: &StateContext) -> (String, [f32; 4]) {
+        let theme = ctx.renderer.theme();
+        let listening = self.listening.and_then(|row| ctx.input.actions().get(row));
+        let hovered = self
+            .rows
+            .iter()
+            .position(|button| button.is_hovered())
+            .and_then(|row| ctx.input.actions().get(row));
+        match listening {
+            Some(action) => (
+                format!(
+                    "press a key for {}, escape cancels, backspace clears",
+                    action.label
+                ),
+                theme.accent,
+            ),
+            None if let Some(action) = hovered => (
+                format!("click to rebind {}", action.label),
+                theme.text_muted,
+            ),
+            None if ctx.input.has_action_conflicts() => (
+                "some keys are bound to more than one action".to_string(),
+                theme.error,
+            ),
+            None => ("click an action to rebind it".to_string(), theme.text_muted),
+        }
+    }
+
+    // a click on the field starts typing, anywhere else stops it
+    fn handle_filter_clicks(&mut self, ctx: &mut StateContext) {
+        let mut clicked = None;
+        for queued in ctx.input.queue_mut().unconsumed_mut() {
+            let EngineEvent::MousePressed {
+                button: MouseButton::Left,
+                position,
+            } = queued.event
+            else {
+                continue;
+            };
+
+            let inside = contains(self.field.rect, ctx.renderer.window_to_viewport(position));
+            if inside {
+                queued.consume("key_remap");
+            }
+            clicked = Some(inside);
+        }
+
+        match clicked {
+            Some(true) => self.filter.focus(ctx.input),
+            Some(false) if self.filter.is_focused() => self.filter.blur(ctx.input),
+            _ => {}
+        }
+    }
+
+    fn matches_filter(&self, ctx: &StateContext, row: usize) -> bool {
+        let filter = self.filter.value().trim().to_lowercase();
+        let action = &ctx.input.actions()[row];
+        let key = action.key.map(key_name).unwrap_or_default();
+        action.label.to_lowercase().contains(&filter) || key.to_lowercase().contains(&filter)
+    }
+
+    fn row_label(&self, ctx: &StateContext, row: usize) -> String {
+        let action = &ctx.input.actions()[row];
+        let key = match (self.listening == Some(row), action.key) {
+            (true, _) => "...".to_string(),
+            (false, Some(key)) => key_name(key),
+            (false, None) => "unbound".to_string(),
+        };
+        let conflicts = ctx.input.action_conflicts(&action.name);
+        match conflicts.is_empty() {
+            true => format!("{}: {}", action.label, key),
+            false => {
+                let labels: Vec<&str> = conflicts
+                    .iter()
+                    .filter_map(|name| ctx.input.action(name))
+                    .map(|action| action.label.as_str())
+                    .collect();
+                format!("{}: {}, also {}", action.label, key, labels.join(", "))
+            }
+        }
+    }
+
+    // text is positioned in logical pixels
+    fn place_text(renderer: &mut Renderer, id: usize, position: [f32; 2]) {
+        let scale = renderer.scale_factor();
+        renderer.set_text_position(id, Some([position[0] / scale, position[1] / scale]));
+    }
+
+    fn remove(&mut self, renderer: &mut Renderer) {
+        for button in self.rows.iter_mut() {
+            button.remove(renderer);
+        }
+        self.rows.clear();
+        self.reset.remove(renderer);
+        self.done.remove(renderer);
+        self.filter.remove(renderer);
+        if let Some(id) = self.title.take() {
+            renderer.remove_text(id);
+        }
+        if let Some((id, _)) = self.hint.take() {
+            renderer.remove_text(id);
+        }
+    }
+}
+
+impl GameState for KeyRemapScreen {
+    fn name(&self) -> &str {
+        "key_remap"
+    }
+
+    fn on_enter(&mut self, ctx: &mut StateContext) {
+        let theme = ctx.renderer.theme();
+        if let Some(id) = ctx.renderer.add_text("", theme.font_size * 1.4, 1.0) {
+            let color = Theme::rgba8(theme.text);
+            ctx.renderer
+                .set_text_spans(id, &[("Controls", Some(color))]);
+            self.title = Some(id);
+        }
+        self.hint = ctx
+            .renderer
+            .add_text("", theme.font_size, 1.0)
+            .map(|id| (id, String::new()));
+        self.filter.font_size = theme.font_size;
+    }
+
+    fn on_exit(&mut self, ctx: &mut StateContext) {
+        if self.filter.is_focused() {
+            self.filter.blur(ctx.input);
+        }
+        self.remove(ctx.renderer);
+
+        ctx.settings.keybinds = ctx.input.keybinds();
+        if let Err(e) = ctx.settings.save() {
+            warn!("failed to save keybinds: {:#}", e);
+            ctx.renderer.notify(
+                format!("failed to save keybinds: {:#}", e),
+                Level::Error,
+                4.0,
+            );
+        }
+    }
+
+    fn update(&mut self, ctx: &mut StateContext, _dt_seconds: f32) -> Transition {
+        if self.handle_keys(ctx) {
+            return Transition::Pop;
+        }
+
+        // actions registered while the screen is open get a row too
+        let count = ctx.input.actions().len();
+        if self.rows.len() != count {
+            for button in self.rows.iter_mut() {
+                button.remove(ctx.renderer);
+            }
+            self.rows = (0..count).map(|_| Button::new([0.0; 4], "")).collect();
+            self.listening = None;
+        }
+        self.handle_filter_clicks(ctx);
+        let visible: Vec<usize> = (0..count)
+            .filter(|&row| self.matches_filter(ctx, row))
+            .collect();
+
+        let theme = ctx.renderer.theme();
+        let scale = ctx.renderer.scale_factor();
+        let [row_height, gap, padding] = [ROW_HEIGHT, GAP, PADDING].map(|v| v * scale);
+        // the filter, the visible rows and the buttons below them
+        let rows = visible.len() + 2;
+        let height = HEADER * scale + rows as f32 * (row_height + gap) + padding * 2.0;
+        let size = [WIDTH * scale, height];
+        let [x, y] = ctx.renderer.anchor(Anchor::Center, size, 0.0);
+        self.panel.rect = [x, y, size[0], size[1]];
+        self.panel.draw(ctx.renderer);
+
+        let left = x + padding;
+        let width = size[0] - padding * 2.0;
+        if let Some(id) = self.title {
+            Self::place_text(ctx.renderer, id, [left, y + padding]);
+        }
+        let (hint, color) = self.hint_text(ctx);
+        if let Some((id, shown)) = &mut self.hint {
+            let id = *id;
+            Self::place_text(ctx.renderer, id, [left, y + padding + HEADER * scale * 0.5]);
+            if *shown != hint {
+                ctx.renderer
+                    .set_text_spans(id, &[(&hint, Some(Theme::rgba8(color)))]);
+                *shown = hint;
+            }
+        }
+
+        let mut top = y + padding + HEADER * scale;
+        self.field.rect = [left, top, width, row_height];
+        self.field.draw(ctx.renderer);
+        self.filter.position = [
+            (left + FIELD_PADDING * scale) / scale,
+            (top + (row_height - theme.font_size * scale) * 0.5) / scale,
+        ];
+        if self.filter.update(ctx.input, ctx.renderer).is_some() {
+            self.filter.blur(ctx.input);
+        }
+        top += row_height + gap;
+
+        for row in 0..count {
+            // hidden rows keep their button, without its label
+            if !visible.contains(&row) {
+                self.rows[row].remove(ctx.renderer);
+                continue;
+            }
+
+            let label = self.row_label(ctx, row);
+            let button = &mut self.rows[row];
+            button.rect = [left, top, width, row_height];
+            button.set_label(&label);
+            if button.update(ctx.input, ctx.renderer) {
+                self.listening = Some(row);
+            }
+
+            let name = &ctx.input.actions()[row].name;
+            let center = [left + width - row_height * 0.5, top + row_height * 0.5];
+            if !ctx.input.action_conflicts(name).is_empty() {
+                let style = ShapeStyle::filled(theme.error);
+                ctx.renderer
+                    .draw_sdf_circle(SCREEN_CAMERA, center, MARKER_RADIUS * scale, style);
+            }
+            // waiting for a key
+            if self.listening == Some(row) {
+                let style = ShapeStyle::filled(theme.accent);
+                let radius = MARKER_RADIUS * 2.0 * scale;
+                ctx.renderer
+                    .draw_sdf_ring(SCREEN_CAMERA, center, radius, scale * 2.0, style);
+            }
+            top += row_height + gap;
+        }
+
+        let half = (width - gap) * 0.5;
+        self.reset.rect = [left, top, half, row_height];
+        self.done.rect = [left + half + gap, top, half, row_height];
+        // a reset would throw the key being waited for away
+        self.reset.set_enabled(self.listening.is_none());
+        if self.reset.update(ctx.input, ctx.renderer) {
+            ctx.input.reset_actions();
+            self.listening = None;
+        }
+        if self.done.update(ctx.input, ctx.renderer) {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    // drawn over the game it was opened from
+    fn is_transparent(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,6 @@
+pub mod gizmo;
+pub mod key_remap;
+pub mod panel;
+pub mod scroll_view;
+pub mod text_input;
+pub mod theme;
@@ -0,0 +1,184 @@
+use crate::input::Input;
+use crate::input::events::{EngineEvent, MouseButton};
+use crate::renderer::Renderer;
+use crate::renderer::camera::SCREEN_CAMERA;
+use crate::renderer::sdf::ShapeStyle;
+use crate::ui::theme::Theme;
+
+const CORNER_RADIUS: f32 = 6.0;
+const PADDING: f32 = 8.0; // physical pixels between a button's edge and its label
+
+pub(super) fn contains(rect: [f32; 4], point: [f32; 2]) -> bool {
+    let [x, y, w, h] = rect;
+    point[0] >= x && point[0] < x + w && point[1] >= y && point[1] < y + h
+}
+
+// a rounded background with a border, redrawn by draw every frame
+pub struct Panel {
+    pub rect: [f32; 4], // x, y, width, height in physical pixels, like sprites
+    pub radius: f32,
+    pub style: Option<ShapeStyle>, // the theme's background and border when None
+}
+
+impl Panel {
+    pub fn new(rect: [f32; 4]) -> Self {
+        Panel {
+            rect,
+            radius: CORNER_RADIUS,
+            style: None,
+        }
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        let theme = renderer.theme();
+        let style = self.style.unwrap_or(ShapeStyle::bordered(
+            theme.background,
+            theme.border,
+            theme.border_size,
+        ));
+        renderer.draw_sdf_rect(SCREEN_CAMERA, self.rect, self.radius, style);
+    }
+}
+
+// a clickable rounded box with a text label, hover and press shown in the
+// theme's colors
+pub struct Button {
+    pub rect: [f32; 4], // physical pixels
+    pub radius: f32,
+
+    label: String,
+    enabled: bool,
+    text_id: Option<usize>,
+    hovered: bool,
+    pressed: bool, // held down after starting on the button
+    mouse_position: [f32; 2],
+    dirty: bool,
+    theme_revision: Option<u64>,
+}
+
+impl Button {
+    pub fn new(rect: [f32; 4], label: &str) -> Self {
+        Button {
+            rect,
+            radius: CORNER_RADIUS,
+            enabled: true,
+            label: label.to_string(),
+            text_id: None,
+            hovered: false,
+            pressed: false,
+            mouse_position: [f32::MIN; 2],
+            dirty: true,
+            theme_revision: None,
+        }
+    }
+
+    pub fn set_label(&mut self, label: &str) {
+        if self.label != label {
+            self.label = label.to_string();
+            self.dirty = true;
+        }
+    }
+
+    // disabled buttons ignore clicks and gray out
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.enabled != enabled {
+            self.enabled = enabled;
+            self.pressed &= enabled;
+            self.dirty = true;
+        }
+    }
+
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+
+    // true on the frame a click started and ended on the button
+    pub fn update(&mut self, input: &mut Input, renderer: &mut Renderer) -> bool {
+        let mut clicked = false;
+        // rects are relative to the viewport, the mouse to the window
+        let to_viewport = |position| renderer.window_to_viewport(position);
+        for queued in input.queue_mut().events_mut() {
+            if queued.is_consumed() && !matches!(queued.event, EngineEvent::MouseMoved { .. }) {
+                continue;
+            }
+
+            match queued.event {
+                EngineEvent::MouseMoved { position } => self.mouse_position = to_viewport(position),
+                EngineEvent::MousePressed {
+                    button: MouseButton::Left,
+                    position,
+                } if self.enabled && contains(self.rect, to_viewport(position)) => {
+                    self.pressed = true;
+                    queued.consume("button");
+                }
+                EngineEvent::MouseReleased {
+                    button: MouseButton::Left,
+                    position,
+                } if self.pressed => {
+                    self.pressed = false;
+                    clicked = self.enabled && contains(self.rect, to_viewport(position));
+                    queued.consume("button");
+                }
+                _ => {}
+            }
+        }
+        self.hovered = self.enabled && contains(self.rect, self.mouse_position);
+
+        self.draw(renderer);
+        clicked
+    }
+
+    fn style(&self, theme: &Theme) -> ShapeStyle {
+        let fill = match (self.enabled, self.pressed, self.hovered) {
+            (false, _, _) => theme.background,
+            (true, true, _) => theme.accent,
+            (true, false, true) => theme.hovered,
+            (true, false, false) => theme.surface,
+        };
+        ShapeStyle::bordered(fill, theme.border, theme.border_size)
+    }
+
+    fn draw(&mut self, renderer: &mut Renderer) {
+        let theme = renderer.theme();
+        renderer.draw_sdf_rect(SCREEN_CAMERA, self.rect, self.radius, self.style(&theme));
+
+        // labels are positioned in logical pixels
+        let scale = renderer.scale_factor();
+        let [x, y, _, h] = self.rect;
+        let position = [
+            (x + PADDING) / scale,
+            (y + (h - theme.font_size * scale) * 0.5) / scale,
+        ];
+        let id = match self.text_id {
+            Some(id) => id,
+            None => {
+                let Some(id) = renderer.add_text_at("", theme.font_size, 1.0, position) else {
+                    return;
+                };
+                self.text_id = Some(id);
+                id
+            }
+        };
+        renderer.set_text_position(id, Some(position));
+        if self.theme_revision != Some(renderer.theme_revision()) {
+            self.theme_revision = Some(renderer.theme_revision());
+            self.dirty = true;
+        }
+        if std::mem::take(&mut self.dirty) {
+            let color = if self.enabled {
+                theme.text
+            } else {
+                theme.text_muted
+            };
+            renderer.set_text_spans(id, &[(&self.label, Some(Theme::rgba8(color)))]);
+        }
+    }
+
+    // drawn again afterwards, the label comes back
+    pub fn remove(&mut self, renderer: &mut Renderer) {
+        if let Some(id) = self.text_id.take() {
+            renderer.remove_text(id);
+            self.dirty = true;
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::input::Input;
+use crate::input::events::{EngineEvent, MouseButton};
+use crate::renderer::Renderer;
+use crate::renderer::camera::Camera2D;
+use crate::renderer::layer::{Pivot, SortMode, Sprite, Visibility};
+
+const LINE_HEIGHT: f32 = 40.0; // pixels scrolled per wheel line
+const FRICTION: f32 = 0.02; // fraction of the fling velocity left after a second
+const MIN_VELOCITY: f32 = 5.0;
+const BAR_WIDTH: f32 = 6.0;
+const MIN_THUMB: f32 = 16.0;
+
+// a clipped region whose layer scrolls by wheel or drag, flinging on release.
+// children go in `layer()` at content coordinates, 0,0 being the content top left.
+// for game uis, none of the engine's own screens are made of sprites
+#[allow(dead_code)]
+pub struct ScrollView {
+    pub rect: [f32; 4],         // x, y, width, height in physical pixels, like sprites
+    pub content_size: [f32; 2], // scrolling stops at its edges
+
+    offset: [f32; 2],
+    velocity: [f32; 2],
+    dragging: bool,
+    drag_motion: [f32; 2], // movement since the last update
+    mouse_position: [f32; 2],
+
+    layer: usize,
+    camera: usize,
+    bar_layer: usize,
+    bars: [usize; 2], // thumb sprites, horizontal and vertical
+}
+
+#[allow(dead_code)]
+impl ScrollView {
+    pub fn new(
+        renderer: &mut Renderer,
+        rect: [f32; 4],
+        content_size: [f32; 2],
+        zindex: i32,
+    ) -> Self {
+        let camera = renderer.create_camera(Camera2D::default());
+        let layer = renderer.create_layer(zindex, SortMode::Insertion);
+        renderer.set_layer_camera(layer, camera);
+
+        let (pool, texture) = renderer.white_texture();
+        let bar_layer = renderer.create_layer(zindex + 1, SortMode::Insertion);
+        let bar = Sprite {
+            pool,
+            texture,
+            pivot: Pivot::TopLeft,
+            color: renderer.theme().scrollbar,
+            visibility: Visibility::hidden(),
+            ..Default::default()
+        };
+        let bars = [
+            renderer.add_sprite(bar_layer, bar).unwrap_or_default(),
+            renderer.add_sprite(bar_layer, bar).unwrap_or_default(),
+        ];
+
+        let view = ScrollView {
+            rect,
+            content_size,
+            offset: [0.0, 0.0],
+            velocity: [0.0, 0.0],
+            dragging: false,
+            drag_motion: [0.0, 0.0],
+            mouse_position: [0.0, 0.0],
+            layer,
+            camera,
+            bar_layer,
+            bars,
+        };
+        view.sync(renderer);
+        view
+    }
+
+    // the sprite layer children are added to
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    pub fn offset(&self) -> [f32; 2] {
+        self.offset
+    }
+
+    pub fn set_offset(&mut self, offset: [f32; 2]) {
+        self.offset = offset;
+        self.velocity = [0.0, 0.0];
+        self.clamp();
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    // consumes the wheel and drag events over the view before game states see them
+    pub fn update(&mut self, input: &mut Input, renderer: &mut Renderer, dt: f32) {
+        // the view's rect is relative to the viewport, the mouse to the window
+        let to_viewport = |position| renderer.window_to_viewport(position);
+        for queued in input.queue_mut().events_mut() {
+            // the cursor is tracked even when something else took the move
+            if queued.is_consumed() && !matches!(queued.event, EngineEvent::MouseMoved { .. }) {
+                continue;
+            }
+
+            match queued.event {
+                EngineEvent::MouseMoved { position } => {
+                    let position = to_viewport(position);
+                    if self.dragging && !queued.is_consumed() {
+                        self.drag_motion[0] += position[0] - self.mouse_position[0];
+                        self.drag_motion[1] += position[1] - self.mouse_position[1];
+                        queued.consume("scroll_view");
+                    }
+                    self.mouse_position = position;
+                }
+                EngineEvent::Scrolled { delta } if self.contains(self.mouse_position) => {
+                    self.offset[0] -= delta[0] * LINE_HEIGHT;
+                    self.offset[1] -= delta[1] * LINE_HEIGHT;
+                    self.velocity = [0.0, 0.0];
+                    queued.consume("scroll_view");
+                }
+                EngineEvent::MousePressed {
+                    button: MouseButton::Left,
+                    position,
+                } if self.contains(to_viewport(position)) => {
+                    self.dragging = true;
+                    self.velocity = [0.0, 0.0];
+                    queued.consume("scroll_view");
+                }
+                EngineEvent::MouseReleased {
+                    button: MouseButton::Left,
+                    ..
+                } if self.dragging => {
+                    self.dragging = false;
+                    queued.consume("scroll_view");
+                }
+                _ => {}
+            }
+        }
+
+        if self.dragging {
+            // content follows the cursor, the velocity is kept for the fling
+            self.offset[0] -= self.drag_motion[0];
+            self.offset[1] -= self.drag_motion[1];
+            if dt > 0.0 {
+                for axis in 0..2 {
+                    let velocity = -self.drag_motion[axis] / dt;
+                    self.velocity[axis] = (self.velocity[axis] + velocity) * 0.5;
+                }
+            }
+        } else {
+            let decay = FRICTION.powf(dt);
+            for axis in 0..2 {
+                self.offset[axis] += self.velocity[axis] * dt;
+                self.velocity[axis] *= decay;
+                if self.velocity[axis].abs() < MIN_VELOCITY {
+                    self.velocity[axis] = 0.0;
+                }
+            }
+        }
+        self.drag_motion = [0.0, 0.0];
+
+        self.clamp();
+        self.sync(renderer);
+    }
+
+    pub fn remove(&mut self, renderer: &mut Renderer) {
+        for bar in self.bars {
+            renderer.remove_sprite(bar);
+        }
+    }
+
+    fn contains(&self, position: [f32; 2]) -> bool {
+        let [x, y, width, height] = self.rect;
+        position[0] >= x && position[0] < x + width && position[1] >= y && position[1] < y + height
+    }
+
+    fn max_offset(&self) -> [f32; 2] {
+        [
+            (self.content_size[0] - self.rect[2]).max(0.0),
+            (self.content_size[1] - self.rect[3]).max(0.0),
+        ]
+    }
+
+    // stops the fling at the edges
+    fn clamp(&mut self) {
+        for (axis, max) in self.max_offset().into_iter().enumerate() {
+            let clamped = self.offset[axis].clamp(0.0, max);
+            if clamped != self.offset[axis] {
+                self.velocity[axis] = 0.0;
+            }
+            self.offset[axis] = clamped;
+        }
+    }
+
+    fn sync(&self, renderer: &mut Renderer) {
+        let [x, y, width, height] = self.rect;
+        let clip = [
+            x.max(0.0) as u32,
+            y.max(0.0) as u32,
+            width.max(0.0) as u32,
+            height.max(0.0) as u32,
+        ];
+
+        renderer.set_layer_clip(self.layer, Some(clip));
+        renderer.set_layer_clip(self.bar_layer, Some(clip));
+        if let Some(camera) = renderer.camera_mut(self.camera) {
+            camera.position = [self.offset[0] - x, self.offset[1] - y];
+        }
+
+        // thumbs are sized by how much of the content is in view
+        let max = self.max_offset();
+        let color = renderer.theme().scrollbar;
+        for axis in 0..2 {
+            let Some(bar) = renderer.sprite_mut(self.bars[axis]) else {
+                continue;
+            };
+
+            let view = self.rect[2 + axis];
+            bar.visibility.visible = max[axis] > 0.0;
+            if !bar.visibility.visible {
+                continue;
+            }
+
+            let thumb = (view * view / self.content_size[axis])
+                .max(MIN_THUMB)
+                .min(view);
+            let along = self.rect[axis] + self.offset[axis] / max[axis] * (view - thumb);
+            let across = self.rect[1 - axis] + self.rect[3 - axis] - BAR_WIDTH;
+
+            let mut position = [0.0; 2];
+            position[axis] = along;
+            position[1 - axis] = across;
+            let mut size = [0.0; 2];
+            size[axis] = thumb;
+            size[1 - axis] = BAR_WIDTH;
+
+            bar.position = position;
+            bar.size = size;
+            bar.color = color;
+        }
+    }
+}
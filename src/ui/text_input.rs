@@ -0,0 +1,202 @@
+use crate::input::{Input, TextInputEvent};
+use crate::renderer::Renderer;
+use crate::renderer::accessibility::AccessibilityEvent;
+use crate::ui::theme::Theme;
+
+// single line text field drawn with the glyphon text renderer
+pub struct TextInput {
+    pub position: [f32; 2], // logical pixels
+    pub font_size: f32,
+    pub placeholder: String, // also what the field is announced as
+    pub max_length: Option<usize>,
+
+    value: String,
+    cursor: usize, // byte offset into value
+    composition: Option<String>,
+    focused: bool,
+    text_id: Option<usize>,
+    dirty: bool,
+    announced_focus: bool,
+    theme_revision: Option<u64>,
+}
+
+impl TextInput {
+    pub fn new(position: [f32; 2], font_size: f32) -> Self {
+        TextInput {
+            position,
+            font_size,
+            placeholder: String::new(),
+            max_length: None,
+            value: String::new(),
+            cursor: 0,
+            composition: None,
+            focused: false,
+            text_id: None,
+            dirty: true,
+            announced_focus: false,
+            theme_revision: None,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.to_string();
+        self.cursor = self.value.len();
+        self.dirty = true;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn focus(&mut self, input: &mut Input) {
+        self.focused = true;
+        self.dirty = true;
+        input.start_text_input();
+    }
+
+    pub fn blur(&mut self, input: &mut Input) {
+        self.focused = false;
+        self.composition = None;
+        self.dirty = true;
+        input.stop_text_input();
+    }
+
+    // returns the value when enter was pressed
+    pub fn update(&mut self, input: &mut Input, renderer: &mut Renderer) -> Option<String> {
+        let mut submitted = None;
+
+        if self.focused {
+            for event in input.text_events() {
+                if *event == TextInputEvent::Submit {
+                    submitted = Some(self.value.clone());
+                } else {
+                    self.apply(event);
+                }
+            }
+
+            // keep the ime candidate window next to the field
+            let scale = renderer.scale_factor();
+            input.set_ime_area(
+                [self.position[0] * scale, self.position[1] * scale],
+                [self.font_size * scale, self.font_size * 1.2 * scale],
+            );
+        }
+
+        if self.focused != self.announced_focus {
+            self.announced_focus = self.focused;
+            renderer.accessibility_event(match self.focused {
+                true => AccessibilityEvent::FocusChanged {
+                    label: self.placeholder.clone(),
+                    value: self.value.clone(),
+                },
+                false => AccessibilityEvent::FocusLost,
+            });
+        }
+
+        self.sync(renderer);
+        submitted
+    }
+
+    pub fn remove(&mut self, renderer: &mut Renderer) {
+        if let Some(id) = self.text_id.take() {
+            renderer.remove_text(id);
+        }
+    }
+
+    fn apply(&mut self, event: &TextInputEvent) {
+        match event {
+            TextInputEvent::Commit(text) => {
+                self.composition = None;
+                for c in text.chars() {
+                    if self
+                        .max_length
+                        .is_some_and(|max| self.value.chars().count() >= max)
+                    {
+                        break;
+                    }
+                    self.value.insert(self.cursor, c);
+                    self.cursor += c.len_utf8();
+                }
+            }
+            TextInputEvent::Composition { text, .. } => {
+                self.composition = (!text.is_empty()).then(|| text.clone());
+            }
+            TextInputEvent::Backspace => {
+                if let Some(c) = self.value[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                    self.value.remove(self.cursor);
+                }
+            }
+            TextInputEvent::Delete => {
+                if self.cursor < self.value.len() {
+                    self.value.remove(self.cursor);
+                }
+            }
+            TextInputEvent::Left => {
+                if let Some(c) = self.value[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                }
+            }
+            TextInputEvent::Right => {
+                if let Some(c) = self.value[self.cursor..].chars().next() {
+                    self.cursor += c.len_utf8();
+                }
+            }
+            TextInputEvent::Home => self.cursor = 0,
+            TextInputEvent::End => self.cursor = self.value.len(),
+            TextInputEvent::Submit => {}
+        }
+
+        self.dirty = true;
+    }
+
+    fn sync(&mut self, renderer: &mut Renderer) {
+        if self.theme_revision != Some(renderer.theme_revision()) {
+            self.theme_revision = Some(renderer.theme_revision());
+            self.dirty = true;
+        }
+        if !self.dirty {
+            return;
+        }
+        let theme = renderer.theme();
+        let composition_color = Theme::rgba8(theme.accent);
+        let placeholder_color = Theme::rgba8(theme.text_muted);
+
+        let id = match self.text_id {
+            Some(id) => id,
+            None => {
+                let Some(id) = renderer.add_text_at("", self.font_size, 1.2, self.position) else {
+                    return;
+                };
+                self.text_id = Some(id);
+                id
+            }
+        };
+
+        renderer.set_text_position(id, Some(self.position));
+
+        if self.value.is_empty() && self.composition.is_none() && !self.focused {
+            renderer.set_text_spans(id, &[(&self.placeholder, Some(placeholder_color))]);
+        } else {
+            let (before, after) = self.value.split_at(self.cursor);
+            let composition = self.composition.as_deref().unwrap_or("");
+            let caret = if self.focused { "|" } else { "" };
+
+            renderer.set_text_spans(
+                id,
+                &[
+                    (before, None),
+                    (composition, Some(composition_color)),
+                    (caret, None),
+                    (after, None),
+                ],
+            );
+        }
+
+        self.dirty = false;
+    }
+}
@@ -0,0 +1,145 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+// okabe and ito's palette, told apart with every common kind of colorblindness
+const ORANGE: [f32; 4] = [0.90, 0.62, 0.0, 1.0];
+const SKY_BLUE: [f32; 4] = [0.34, 0.71, 0.91, 1.0];
+const BLUISH_GREEN: [f32; 4] = [0.0, 0.62, 0.45, 1.0];
+const YELLOW: [f32; 4] = [0.94, 0.89, 0.26, 1.0];
+const BLUE: [f32; 4] = [0.0, 0.45, 0.70, 1.0];
+const VERMILLION: [f32; 4] = [0.84, 0.37, 0.0, 1.0];
+const REDDISH_PURPLE: [f32; 4] = [0.80, 0.47, 0.65, 1.0];
+
+pub const BUILTIN_THEMES: &[&str] = &[
+    "dark",
+    "light",
+    "high_contrast",
+    "deuteranopia",
+    "protanopia",
+    "tritanopia",
+];
+
+// colors and metrics shared by the debug overlay and the game ui widgets.
+// the settings file picks one by name and can override single fields of it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub text: [f32; 4],
+    pub text_muted: [f32; 4], // placeholders and disabled items
+    pub accent: [f32; 4],     // focus, selection and ime composition
+    pub background: [f32; 4], // windows
+    pub surface: [f32; 4],    // frames and buttons on the background
+    pub hovered: [f32; 4],
+    pub border: [f32; 4],
+    pub scrollbar: [f32; 4],
+    pub error: [f32; 4],
+    pub warning: [f32; 4],
+    pub success: [f32; 4],
+    pub debug_palette: [[f32; 4]; 6], // series colors for graphs and overlays
+    pub font_size: f32,               // logical pixels
+    pub spacing: f32,                 // between items, in logical pixels
+    pub border_size: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            text: [1.0, 1.0, 1.0, 1.0],
+            text_muted: [0.5, 0.5, 0.5, 1.0],
+            accent: [0.63, 0.63, 1.0, 1.0],
+            background: [0.06, 0.06, 0.06, 0.94],
+            surface: [0.16, 0.29, 0.48, 0.54],
+            hovered: [0.26, 0.59, 0.98, 0.40],
+            border: [0.43, 0.43, 0.50, 0.50],
+            scrollbar: [1.0, 1.0, 1.0, 0.5],
+            error: [1.0, 0.4, 0.4, 1.0],
+            warning: [1.0, 0.8, 0.2, 1.0],
+            success: [0.4, 0.9, 0.4, 1.0],
+            debug_palette: [
+                [1.0, 0.4, 0.4, 1.0],
+                [0.4, 0.9, 0.4, 1.0],
+                [0.4, 0.6, 1.0, 1.0],
+                [1.0, 0.8, 0.2, 1.0],
+                [0.8, 0.4, 1.0, 1.0],
+                [0.3, 0.9, 0.9, 1.0],
+            ],
+            font_size: 13.0,
+            spacing: 8.0,
+            border_size: 1.0,
+        }
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let dark = Theme::default();
+
+        Some(match name {
+            "dark" => dark,
+            "light" => Theme {
+                text: [0.0, 0.0, 0.0, 1.0],
+                text_muted: [0.45, 0.45, 0.45, 1.0],
+                accent: [0.16, 0.35, 0.80, 1.0],
+                background: [0.94, 0.94, 0.94, 1.0],
+                surface: [0.80, 0.84, 0.90, 1.0],
+                hovered: [0.26, 0.59, 0.98, 0.40],
+                border: [0.0, 0.0, 0.0, 0.3],
+                scrollbar: [0.0, 0.0, 0.0, 0.5],
+                error: [0.75, 0.1, 0.1, 1.0],
+                warning: [0.6, 0.4, 0.0, 1.0],
+                success: [0.1, 0.5, 0.1, 1.0],
+                ..dark
+            },
+            "high_contrast" => Theme {
+                text_muted: [0.8, 0.8, 0.8, 1.0],
+                accent: [1.0, 1.0, 0.0, 1.0],
+                background: [0.0, 0.0, 0.0, 1.0],
+                surface: [0.0, 0.0, 0.0, 1.0],
+                hovered: [0.0, 0.0, 0.5, 1.0],
+                border: [1.0, 1.0, 0.0, 1.0],
+                scrollbar: [1.0, 1.0, 0.0, 1.0],
+                border_size: 2.0,
+                ..dark
+            },
+            // red and green look alike, errors and successes differ in blue and orange
+            "deuteranopia" | "protanopia" => Theme {
+                accent: SKY_BLUE,
+                error: VERMILLION,
+                warning: YELLOW,
+                success: BLUE,
+                debug_palette: [ORANGE, SKY_BLUE, YELLOW, BLUE, VERMILLION, REDDISH_PURPLE],
+                ..dark
+            },
+            // blue and yellow look alike, keep to reds and teals
+            "tritanopia" => Theme {
+                accent: REDDISH_PURPLE,
+                error: VERMILLION,
+                warning: REDDISH_PURPLE,
+                success: BLUISH_GREEN,
+                debug_palette: [
+                    VERMILLION,
+                    BLUISH_GREEN,
+                    REDDISH_PURPLE,
+                    [0.0, 0.0, 0.0, 1.0],
+                    [0.6, 0.6, 0.6, 1.0],
+                    [1.0, 1.0, 1.0, 1.0],
+                ],
+                ..dark
+            },
+            _ => return None,
+        })
+    }
+
+    // unknown names fall back to the dark theme
+    pub fn named(name: &str) -> Theme {
+        Theme::builtin(name).unwrap_or_else(|| {
+            warn!("no theme {}, using dark", name);
+            Theme::default()
+        })
+    }
+
+    // for glyphon text spans
+    pub fn rgba8(color: [f32; 4]) -> [u8; 4] {
+        color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+}
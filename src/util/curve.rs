@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+const SOLVE_STEPS: usize = 24;
+
+// a point the curve passes through. handles are offsets from it to the bezier
+// control points on either side, zero handles make straight segments
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CurveKey {
+    pub time: f32,
+    pub value: f32,
+    #[serde(default)]
+    pub in_handle: [f32; 2],
+    #[serde(default)]
+    pub out_handle: [f32; 2],
+}
+
+impl CurveKey {
+    pub fn new(time: f32, value: f32) -> Self {
+        CurveKey {
+            time,
+            value,
+            in_handle: [0.0, 0.0],
+            out_handle: [0.0, 0.0],
+        }
+    }
+}
+
+// a value over time from cubic bezier segments between keys, for easing, over
+// lifetime parameters and the like. time usually runs 0..1, before the first
+// key and after the last the curve holds their values
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Curve {
+    keys: Vec<CurveKey>, // by time
+}
+
+impl Curve {
+    pub fn new(mut keys: Vec<CurveKey>) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Curve { keys }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Curve::new(vec![CurveKey::new(0.0, value)])
+    }
+
+    pub fn linear(from: f32, to: f32) -> Self {
+        Curve::new(vec![CurveKey::new(0.0, from), CurveKey::new(1.0, to)])
+    }
+
+    pub fn keys(&self) -> &[CurveKey] {
+        &self.keys
+    }
+
+    // returns where it ended up among the keys
+    pub fn insert(&mut self, key: CurveKey) -> usize {
+        let index = self.keys.partition_point(|other| other.time <= key.time);
+        self.keys.insert(index, key);
+        index
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<CurveKey> {
+        (index < self.keys.len()).then(|| self.keys.remove(index))
+    }
+
+    // moves a key, keeping it between its neighbours so the order holds
+    pub fn set_key(&mut self, index: usize, key: CurveKey) {
+        let Some(previous) = self.keys.get(index).copied() else {
+            return;
+        };
+        let min = index
+            .checked_sub(1)
+            .map_or(f32::NEG_INFINITY, |i| self.keys[i].time);
+        let max = self
+            .keys
+            .get(index + 1)
+            .map_or(f32::INFINITY, |next| next.time);
+        let time = match key.time.is_finite() {
+            true => key.time.clamp(min, max),
+            false => previous.time,
+        };
+        self.keys[index] = CurveKey { time, ..key };
+    }
+
+    pub fn sample(&self, time: f32) -> f32 {
+        let (Some(first), Some(last)) = (self.keys.first(), self.keys.last()) else {
+            return 0.0;
+        };
+        if time <= first.time {
+            return first.value;
+        }
+        if time >= last.time {
+            return last.value;
+        }
+
+        let next = self.keys.partition_point(|key| key.time <= time);
+        let (start, end) = (self.keys[next - 1], self.keys[next]);
+        let span = end.time - start.time;
+        if span <= 0.0 {
+            return end.value;
+        }
+
+        // handles can't reach past the neighbouring key, so time only ever
+        // grows along the segment and bisecting finds the one point at it
+        let x = [
+            start.time,
+            (start.time + start.out_handle[0]).clamp(start.time, end.time),
+            (end.time + end.in_handle[0]).clamp(start.time, end.time),
+            end.time,
+        ];
+        let y = [
+            start.value,
+            start.value + start.out_handle[1],
+            end.value + end.in_handle[1],
+            end.value,
+        ];
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..SOLVE_STEPS {
+            let middle = (low + high) * 0.5;
+            match bezier(x, middle) < time {
+                true => low = middle,
+                false => high = middle,
+            }
+        }
+        bezier(y, (low + high) * 0.5)
+    }
+
+    // evenly spaced samples over 0..1, e.g. for a uniform
+    pub fn bake<const N: usize>(&self) -> [f32; N] {
+        std::array::from_fn(|i| self.sample(i as f32 / (N.max(2) - 1) as f32))
+    }
+}
+
+fn bezier([p0, p1, p2, p3]: [f32; 4], t: f32) -> f32 {
+    let u = 1.0 - t;
+    u * u * u * p0 + 3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t * p3
+}
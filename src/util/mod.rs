@@ -1 +1,2 @@
-pub mod ext;
+pub mod curve;
+pub mod tween;
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use crate::util::curve::Curve;
+
+// how a tween moves between its ends over time
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum Easing {
+    Linear,
+    #[default]
+    SmoothStep,
+    EaseIn,            // starts slow
+    EaseOut,           // ends slow
+    Curve(Arc<Curve>), // 0 at the start and 1 at the end, overshooting is fine
+}
+
+impl Easing {
+    // t in 0..1
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::SmoothStep => t * t * (3.0 - 2.0 * t),
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::Curve(curve) => curve.sample(t),
+        }
+    }
+}
+
+pub trait Lerp: Copy {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<const N: usize> Lerp for [f32; N] {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&other[i], t))
+    }
+}
+
+// a value easing towards a target, advanced with update every frame
+#[derive(Clone, Debug)]
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    duration: f32, // seconds
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(value: T) -> Self {
+        Tween {
+            from: value,
+            to: value,
+            duration: 0.0,
+            elapsed: 0.0,
+            easing: Easing::default(),
+        }
+    }
+
+    // starts from wherever the tween is now, so retargeting mid way doesn't jump
+    pub fn start(&mut self, to: T, duration: f32, easing: Easing) {
+        self.from = self.value();
+        self.to = to;
+        self.duration = duration.max(0.0);
+        self.elapsed = 0.0;
+        self.easing = easing;
+    }
+
+    pub fn set(&mut self, value: T) {
+        self.start(value, 0.0, Easing::Linear);
+        self.from = value;
+    }
+
+    pub fn update(&mut self, dt_seconds: f32) -> T {
+        self.elapsed = (self.elapsed + dt_seconds).min(self.duration);
+        self.value()
+    }
+
+    pub fn value(&self) -> T {
+        if self.is_finished() {
+            return self.to;
+        }
+        let t = self.easing.apply(self.elapsed / self.duration);
+        self.from.lerp(&self.to, t)
+    }
+
+    pub fn target(&self) -> T {
+        self.to
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
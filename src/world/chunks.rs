@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use log::{debug, warn};
+
+use crate::renderer::Renderer;
+use crate::renderer::layer::Sprite;
+
+const MAX_LOADS_PER_UPDATE: usize = 4; // spreads a fast camera's loads over frames
+
+pub type ChunkCoord = [i32; 2];
+
+// a square of the world and everything placed in it
+#[allow(dead_code)] // data is only read by the game
+pub struct Chunk<T> {
+    pub data: T,             // user data, e.g. tile ids and spawn points
+    pub sprites: Vec<usize>, // removed from the renderer with the chunk
+}
+
+// fills chunks as they stream in and gets to save them before they go
+pub trait ChunkHooks<T> {
+    fn load(&mut self, coord: ChunkCoord, chunk: &mut Chunk<T>, world: &mut ChunkContext);
+
+    fn unload(&mut self, _coord: ChunkCoord, _chunk: &mut Chunk<T>, _world: &mut ChunkContext) {}
+}
+
+// what hooks get to place sprites with
+#[allow(dead_code)] // read by the game's hooks
+pub struct ChunkContext<'r, 'a> {
+    pub renderer: &'r mut Renderer<'a>,
+    pub layer: usize,
+    pub origin: [f32; 2], // world position of the chunk's top left
+    pub chunk_size: f32,
+}
+
+impl<T> Chunk<T> {
+    // sprites added here unload with the chunk
+    #[allow(dead_code)] // called by the game's hooks, see ChunkWorld
+    pub fn add_sprite(&mut self, world: &mut ChunkContext, sprite: Sprite) -> Option<usize> {
+        let id = world.renderer.add_sprite(world.layer, sprite)?;
+        self.sprites.push(id);
+        Some(id)
+    }
+}
+
+// splits a large 2d world into chunks that load around a camera and unload
+// once it has moved far enough away. the gap between the two radii keeps a chunk
+// on the boundary from reloading every time the camera wobbles across it.
+// for games, the engine has no world of its own to stream
+#[allow(dead_code)]
+pub struct ChunkWorld<T> {
+    pub chunk_size: f32,    // world pixels
+    pub load_radius: i32,   // chunks around the view that are loaded
+    pub unload_radius: i32, // chunks around the view kept once loaded, at least load_radius
+    layer: usize,
+    chunks: HashMap<ChunkCoord, Chunk<T>>,
+    hooks: Box<dyn ChunkHooks<T>>,
+}
+
+#[allow(dead_code)]
+impl<T: Default> ChunkWorld<T> {
+    pub fn new(layer: usize, chunk_size: f32, hooks: Box<dyn ChunkHooks<T>>) -> Self {
+        ChunkWorld {
+            chunk_size,
+            load_radius: 1,
+            unload_radius: 2,
+            layer,
+            chunks: HashMap::new(),
+            hooks,
+        }
+    }
+
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    pub fn chunk_at(&self, position: [f32; 2]) -> ChunkCoord {
+        [
+            (position[0] / self.chunk_size).floor() as i32,
+            (position[1] / self.chunk_size).floor() as i32,
+        ]
+    }
+
+    pub fn chunk_origin(&self, coord: ChunkCoord) -> [f32; 2] {
+        [
+            coord[0] as f32 * self.chunk_size,
+            coord[1] as f32 * self.chunk_size,
+        ]
+    }
+
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        self.chunks.contains_key(&coord)
+    }
+
+    pub fn chunk(&self, coord: ChunkCoord) -> Option<&Chunk<T>> {
+        self.chunks.get(&coord)
+    }
+
+    pub fn chunk_mut(&mut self, coord: ChunkCoord) -> Option<&mut Chunk<T>> {
+        self.chunks.get_mut(&coord)
+    }
+
+    pub fn loaded(&self) -> impl Iterator<Item = (ChunkCoord, &Chunk<T>)> {
+        self.chunks.iter().map(|(coord, chunk)| (*coord, chunk))
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    // places a sprite in the chunk under its position, None if that chunk isn't loaded
+    pub fn add_sprite(&mut self, renderer: &mut Renderer, sprite: Sprite) -> Option<usize> {
+        let coord = self.chunk_at(sprite.position);
+        let Some(chunk) = self.chunks.get_mut(&coord) else {
+            warn!("chunk {:?} is not loaded", coord);
+            return None;
+        };
+
+        let id = renderer.add_sprite(self.layer, sprite)?;
+        chunk.sprites.push(id);
+        Some(id)
+    }
+
+    // loads and unloads chunks around what the camera shows
+    pub fn update(&mut self, renderer: &mut Renderer, camera: usize) {
+        let Some(view) = renderer.camera_view(camera) else {
+            warn!("no camera {}", camera);
+            return;
+        };
+        let min = self.chunk_at([view[0], view[1]]);
+        let max = self.chunk_at([view[0] + view[2], view[1] + view[3]]);
+        let unload_radius = self.unload_radius.max(self.load_radius);
+
+        let outside = |coord: &ChunkCoord, radius: i32| {
+            coord[0] < min[0] - radius
+                || coord[0] > max[0] + radius
+                || coord[1] < min[1] - radius
+                || coord[1] > max[1] + radius
+        };
+
+        let leaving: Vec<ChunkCoord> = self
+            .chunks
+            .keys()
+            .filter(|coord| outside(coord, unload_radius))
+            .copied()
+            .collect();
+        for coord in leaving {
+            self.unload(renderer, coord);
+        }
+
+        // closest to the view's center first
+        let center = [(min[0] + max[0]) / 2, (min[1] + max[1]) / 2];
+        let radius = self.load_radius;
+        let mut missing = Vec::new();
+        for y in min[1] - radius..=max[1] + radius {
+            for x in min[0] - radius..=max[0] + radius {
+                if !self.chunks.contains_key(&[x, y]) {
+                    missing.push([x, y]);
+                }
+            }
+        }
+        missing.sort_by_key(|coord| (coord[0] - center[0]).abs() + (coord[1] - center[1]).abs());
+
+        for coord in missing.into_iter().take(MAX_LOADS_PER_UPDATE) {
+            self.load(renderer, coord);
+        }
+    }
+
+    pub fn unload_all(&mut self, renderer: &mut Renderer) {
+        let coords: Vec<ChunkCoord> = self.chunks.keys().copied().collect();
+        for coord in coords {
+            self.unload(renderer, coord);
+        }
+    }
+
+    fn context<'r, 'a>(
+        &self,
+        renderer: &'r mut Renderer<'a>,
+        coord: ChunkCoord,
+    ) -> ChunkContext<'r, 'a> {
+        ChunkContext {
+            renderer,
+            layer: self.layer,
+            origin: self.chunk_origin(coord),
+            chunk_size: self.chunk_size,
+        }
+    }
+
+    fn load(&mut self, renderer: &mut Renderer, coord: ChunkCoord) {
+        debug!("loading chunk {:?}", coord);
+
+        let mut chunk = Chunk {
+            data: T::default(),
+            sprites: Vec::new(),
+        };
+        let mut context = self.context(renderer, coord);
+        self.hooks.load(coord, &mut chunk, &mut context);
+        self.chunks.insert(coord, chunk);
+    }
+
+    fn unload(&mut self, renderer: &mut Renderer, coord: ChunkCoord) {
+        let Some(mut chunk) = self.chunks.remove(&coord) else {
+            return;
+        };
+        debug!("unloading chunk {:?}", coord);
+
+        let mut context = self.context(renderer, coord);
+        self.hooks.unload(coord, &mut chunk, &mut context);
+        for sprite in chunk.sprites {
+            renderer.remove_sprite(sprite);
+        }
+    }
+}
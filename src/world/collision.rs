@@ -0,0 +1,484 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::world::spatial::SpatialGrid;
+
+const SKIN: f32 = 0.01; // touching a wall isn't overlapping it, or bodies stick
+const BISECT_STEPS: usize = 12;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColliderShape {
+    Rect([f32; 4]),         // x, y, width, height
+    Polygon(Vec<[f32; 2]>), // world points, convex or not
+}
+
+impl ColliderShape {
+    pub fn bounds(&self) -> [f32; 4] {
+        match self {
+            ColliderShape::Rect(rect) => *rect,
+            ColliderShape::Polygon(points) => {
+                let mut min = [f32::MAX; 2];
+                let mut max = [f32::MIN; 2];
+                for point in points {
+                    for axis in 0..2 {
+                        min[axis] = min[axis].min(point[axis]);
+                        max[axis] = max[axis].max(point[axis]);
+                    }
+                }
+                [min[0], min[1], max[0] - min[0], max[1] - min[1]]
+            }
+        }
+    }
+
+    pub fn overlaps_rect(&self, rect: [f32; 4]) -> bool {
+        if !rects_overlap(self.bounds(), rect) {
+            return false;
+        }
+        let ColliderShape::Polygon(points) = self else {
+            return true;
+        };
+
+        let corners = rect_corners(rect);
+        points.iter().any(|&point| rect_contains(rect, point))
+            || corners
+                .iter()
+                .any(|&corner| polygon_contains(points, corner))
+            || edges(points)
+                .any(|(a, b)| edges(&corners).any(|(c, d)| segments_intersect(a, b, c, d)))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColliderKind {
+    Solid,
+    Trigger, // reports bodies entering and leaving, never blocks them
+}
+
+#[allow(dead_code)] // names and properties are only read by the game
+#[derive(Clone, Debug)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    pub kind: ColliderKind,
+    pub name: String,
+    pub class: String,
+    pub properties: HashMap<String, String>, // whatever the map author attached
+}
+
+impl Collider {
+    #[allow(dead_code)] // level geometry the game builds by hand, see CollisionWorld
+    pub fn new(shape: ColliderShape, kind: ColliderKind) -> Self {
+        Collider {
+            shape,
+            kind,
+            name: String::new(),
+            class: String::new(),
+            properties: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriggerEvent {
+    Enter { trigger: usize, body: usize },
+    Exit { trigger: usize, body: usize },
+}
+
+// static colliders for tile maps and level geometry. bodies are rects the game
+// moves itself, ids are whatever it indexes them by, e.g. sprite ids.
+// for games, the engine moves no bodies of its own
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct CollisionWorld {
+    colliders: Vec<Option<Collider>>,
+    grid: SpatialGrid,
+    inside: HashMap<usize, HashSet<usize>>, // body -> triggers it overlaps
+}
+
+#[allow(dead_code)]
+impl CollisionWorld {
+    pub fn add_collider(&mut self, collider: Collider) -> usize {
+        let id = self.colliders.len();
+        self.grid.insert(id, collider.shape.bounds());
+        self.colliders.push(Some(collider));
+        id
+    }
+
+    // bodies inside a removed trigger get their exit on the next update
+    pub fn remove_collider(&mut self, id: usize) -> Option<Collider> {
+        let collider = self.colliders.get_mut(id)?.take()?;
+        self.grid.remove(id);
+        Some(collider)
+    }
+
+    pub fn collider(&self, id: usize) -> Option<&Collider> {
+        self.colliders.get(id)?.as_ref()
+    }
+
+    pub fn colliders(&self) -> impl Iterator<Item = (usize, &Collider)> {
+        self.colliders
+            .iter()
+            .enumerate()
+            .filter_map(|(id, collider)| Some((id, collider.as_ref()?)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.grid.is_empty()
+    }
+
+    // forgets which triggers bodies were in too, without exits
+    pub fn clear(&mut self) {
+        self.colliders.clear();
+        self.grid.clear();
+        self.inside.clear();
+    }
+
+    // colliders of the kind overlapping a rect in id order, solids and triggers when None
+    pub fn query_rect(&self, rect: [f32; 4], kind: Option<ColliderKind>) -> Vec<usize> {
+        self.grid
+            .query_rect(rect)
+            .into_iter()
+            .filter(|&id| {
+                self.collider(id).is_some_and(|collider| {
+                    kind.is_none_or(|kind| collider.kind == kind)
+                        && collider.shape.overlaps_rect(rect)
+                })
+            })
+            .collect()
+    }
+
+    pub fn is_blocked(&self, rect: [f32; 4]) -> bool {
+        let inner = [
+            rect[0] + SKIN,
+            rect[1] + SKIN,
+            (rect[2] - SKIN * 2.0).max(0.0),
+            (rect[3] - SKIN * 2.0).max(0.0),
+        ];
+        !self.query_rect(inner, Some(ColliderKind::Solid)).is_empty()
+    }
+
+    // moves a rect by delta, one axis at a time so it slides along walls.
+    // returns how far it actually got. a rect that starts inside a solid moves freely
+    // so it can get out
+    pub fn move_rect(&self, rect: [f32; 4], delta: [f32; 2]) -> [f32; 2] {
+        if self.is_blocked(rect) {
+            return delta;
+        }
+
+        let mut position = [rect[0], rect[1]];
+        for axis in 0..2 {
+            // steps of at most half the rect, so thin walls can't be skipped
+            let reach = (rect[2 + axis] * 0.5).max(1.0);
+            let steps = (delta[axis].abs() / reach).ceil().max(1.0) as usize;
+            let step = delta[axis] / steps as f32;
+
+            for _ in 0..steps {
+                let moved = |t: f32| {
+                    let mut moved = [position[0], position[1], rect[2], rect[3]];
+                    moved[axis] += step * t;
+                    moved
+                };
+                if !self.is_blocked(moved(1.0)) {
+                    position[axis] += step;
+                    continue;
+                }
+
+                let (mut free, mut blocked) = (0.0, 1.0);
+                for _ in 0..BISECT_STEPS {
+                    let t = (free + blocked) * 0.5;
+                    match self.is_blocked(moved(t)) {
+                        true => blocked = t,
+                        false => free = t,
+                    }
+                }
+                position[axis] += step * free;
+                break;
+            }
+        }
+        [position[0] - rect[0], position[1] - rect[1]]
+    }
+
+    // call with a body's rect after moving it, reports triggers it entered or left
+    pub fn update_body(&mut self, body: usize, rect: [f32; 4]) -> Vec<TriggerEvent> {
+        let now: HashSet<usize> = self
+            .query_rect(rect, Some(ColliderKind::Trigger))
+            .into_iter()
+            .collect();
+        let before = self.inside.remove(&body).unwrap_or_default();
+
+        let mut events: Vec<TriggerEvent> = before
+            .difference(&now)
+            .map(|&trigger| TriggerEvent::Exit { trigger, body })
+            .chain(
+                now.difference(&before)
+                    .map(|&trigger| TriggerEvent::Enter { trigger, body }),
+            )
+            .collect();
+        events.sort_by_key(|event| match *event {
+            TriggerEvent::Exit { trigger, .. } => (0, trigger),
+            TriggerEvent::Enter { trigger, .. } => (1, trigger),
+        });
+
+        if !now.is_empty() {
+            self.inside.insert(body, now);
+        }
+        events
+    }
+
+    // exits for every trigger the body was in, for despawning
+    pub fn remove_body(&mut self, body: usize) -> Vec<TriggerEvent> {
+        let mut triggers: Vec<usize> = self
+            .inside
+            .remove(&body)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        triggers.sort_unstable();
+        triggers
+            .into_iter()
+            .map(|trigger| TriggerEvent::Exit { trigger, body })
+            .collect()
+    }
+}
+
+fn rects_overlap(a: [f32; 4], b: [f32; 4]) -> bool {
+    a[0] < b[0] + b[2] && b[0] < a[0] + a[2] && a[1] < b[1] + b[3] && b[1] < a[1] + a[3]
+}
+
+fn rect_contains(rect: [f32; 4], point: [f32; 2]) -> bool {
+    point[0] > rect[0]
+        && point[0] < rect[0] + rect[2]
+        && point[1] > rect[1]
+        && point[1] < rect[1] + rect[3]
+}
+
+fn rect_corners(rect: [f32; 4]) -> [[f32; 2]; 4] {
+    let [x, y, width, height] = rect;
+    [
+        [x, y],
+        [x + width, y],
+        [x + width, y + height],
+        [x, y + height],
+    ]
+}
+
+fn edges(points: &[[f32; 2]]) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+    (0..points.len()).map(move |i| (points[i], points[(i + 1) % points.len()]))
+}
+
+// even-odd, so self intersecting outlines behave like tiled draws them
+fn polygon_contains(points: &[[f32; 2]], point: [f32; 2]) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(points) {
+        if (a[1] > point[1]) != (b[1] > point[1])
+            && point[0] < a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn segments_intersect(a: [f32; 2], b: [f32; 2], c: [f32; 2], d: [f32; 2]) -> bool {
+    let cross = |o: [f32; 2], p: [f32; 2], q: [f32; 2]| {
+        (p[0] - o[0]) * (q[1] - o[1]) - (p[1] - o[1]) * (q[0] - o[0])
+    };
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(rect: [f32; 4]) -> Collider {
+        Collider::new(ColliderShape::Rect(rect), ColliderKind::Solid)
+    }
+
+    fn trigger(rect: [f32; 4]) -> Collider {
+        Collider::new(ColliderShape::Rect(rect), ColliderKind::Trigger)
+    }
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 0.05 && (a[1] - b[1]).abs() < 0.05
+    }
+
+    #[test]
+    fn move_rect_stops_at_a_wall() {
+        let mut world = CollisionWorld::default();
+        world.add_collider(solid([100.0, 0.0, 10.0, 100.0]));
+
+        let moved = world.move_rect([0.0, 10.0, 20.0, 20.0], [200.0, 0.0]);
+        assert!(close(moved, [80.0, 0.0]), "{:?}", moved);
+        // flush against the wall it can still move away
+        let moved = world.move_rect([80.0, 10.0, 20.0, 20.0], [-30.0, 0.0]);
+        assert!(close(moved, [-30.0, 0.0]), "{:?}", moved);
+    }
+
+    #[test]
+    fn move_rect_slides_along_a_wall() {
+        let mut world = CollisionWorld::default();
+        world.add_collider(solid([0.0, 100.0, 200.0, 10.0]));
+
+        let moved = world.move_rect([0.0, 70.0, 20.0, 20.0], [50.0, 50.0]);
+        assert!(close(moved, [50.0, 10.0]), "{:?}", moved);
+    }
+
+    #[test]
+    fn move_rect_doesnt_tunnel_through_thin_walls() {
+        let mut world = CollisionWorld::default();
+        world.add_collider(solid([100.0, 0.0, 1.0, 100.0]));
+
+        let moved = world.move_rect([0.0, 10.0, 20.0, 20.0], [1000.0, 0.0]);
+        assert!(close(moved, [80.0, 0.0]), "{:?}", moved);
+    }
+
+    #[test]
+    fn move_rect_ignores_triggers_and_frees_stuck_rects() {
+        let mut world = CollisionWorld::default();
+        world.add_collider(trigger([50.0, 0.0, 10.0, 100.0]));
+        assert_eq!(
+            world.move_rect([0.0, 10.0, 20.0, 20.0], [100.0, 0.0]),
+            [100.0, 0.0]
+        );
+
+        world.add_collider(solid([0.0, 0.0, 100.0, 100.0]));
+        assert_eq!(
+            world.move_rect([10.0, 10.0, 20.0, 20.0], [-50.0, 0.0]),
+            [-50.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn polygon_overlap_uses_the_outline() {
+        // a right triangle, the corner of its bounds opposite the hypotenuse is empty
+        let triangle = ColliderShape::Polygon(vec![[0.0, 0.0], [100.0, 0.0], [0.0, 100.0]]);
+        assert_eq!(triangle.bounds(), [0.0, 0.0, 100.0, 100.0]);
+        assert!(triangle.overlaps_rect([10.0, 10.0, 10.0, 10.0]));
+        assert!(!triangle.overlaps_rect([80.0, 80.0, 10.0, 10.0]));
+        // edges crossing without a corner inside either shape
+        assert!(triangle.overlaps_rect([-10.0, 40.0, 120.0, 5.0]));
+        // the whole triangle inside the rect
+        assert!(triangle.overlaps_rect([-10.0, -10.0, 200.0, 200.0]));
+        assert!(!triangle.overlaps_rect([200.0, 0.0, 10.0, 10.0]));
+    }
+
+    #[test]
+    fn concave_polygons_are_even_odd() {
+        // a u shape, the gap between its arms is outside
+        let u = ColliderShape::Polygon(vec![
+            [0.0, 0.0],
+            [30.0, 0.0],
+            [30.0, 100.0],
+            [70.0, 100.0],
+            [70.0, 0.0],
+            [100.0, 0.0],
+            [100.0, 130.0],
+            [0.0, 130.0],
+        ]);
+        assert!(!u.overlaps_rect([40.0, 10.0, 20.0, 20.0]));
+        assert!(u.overlaps_rect([5.0, 10.0, 20.0, 20.0]));
+        assert!(u.overlaps_rect([40.0, 110.0, 20.0, 10.0]));
+    }
+
+    #[test]
+    fn query_rect_filters_by_kind() {
+        let mut world = CollisionWorld::default();
+        let wall = world.add_collider(solid([0.0, 0.0, 10.0, 10.0]));
+        let zone = world.add_collider(trigger([5.0, 5.0, 10.0, 10.0]));
+        let area = [0.0, 0.0, 20.0, 20.0];
+
+        assert_eq!(world.query_rect(area, None), [wall, zone]);
+        assert_eq!(world.query_rect(area, Some(ColliderKind::Solid)), [wall]);
+        assert_eq!(world.query_rect(area, Some(ColliderKind::Trigger)), [zone]);
+        world.remove_collider(wall);
+        assert_eq!(world.query_rect(area, None), [zone]);
+        assert_eq!(world.len(), 1);
+    }
+
+    #[test]
+    fn triggers_report_enter_and_exit_once() {
+        let mut world = CollisionWorld::default();
+        let zone = world.add_collider(trigger([100.0, 0.0, 50.0, 50.0]));
+        let body = 7;
+
+        assert!(world.update_body(body, [0.0, 0.0, 10.0, 10.0]).is_empty());
+        assert_eq!(
+            world.update_body(body, [110.0, 10.0, 10.0, 10.0]),
+            [TriggerEvent::Enter {
+                trigger: zone,
+                body
+            }]
+        );
+        assert!(
+            world
+                .update_body(body, [120.0, 10.0, 10.0, 10.0])
+                .is_empty()
+        );
+        assert_eq!(
+            world.update_body(body, [200.0, 10.0, 10.0, 10.0]),
+            [TriggerEvent::Exit {
+                trigger: zone,
+                body
+            }]
+        );
+        assert!(
+            world
+                .update_body(body, [200.0, 10.0, 10.0, 10.0])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn exits_come_before_enters() {
+        let mut world = CollisionWorld::default();
+        let left = world.add_collider(trigger([0.0, 0.0, 50.0, 50.0]));
+        let right = world.add_collider(trigger([100.0, 0.0, 50.0, 50.0]));
+
+        world.update_body(1, [10.0, 10.0, 10.0, 10.0]);
+        assert_eq!(
+            world.update_body(1, [110.0, 10.0, 10.0, 10.0]),
+            [
+                TriggerEvent::Exit {
+                    trigger: left,
+                    body: 1
+                },
+                TriggerEvent::Enter {
+                    trigger: right,
+                    body: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_triggers_and_bodies_exit() {
+        let mut world = CollisionWorld::default();
+        let first = world.add_collider(trigger([0.0, 0.0, 50.0, 50.0]));
+        let second = world.add_collider(trigger([0.0, 0.0, 50.0, 50.0]));
+
+        world.update_body(1, [10.0, 10.0, 10.0, 10.0]);
+        world.remove_collider(first);
+        assert_eq!(
+            world.update_body(1, [10.0, 10.0, 10.0, 10.0]),
+            [TriggerEvent::Exit {
+                trigger: first,
+                body: 1
+            }]
+        );
+        assert_eq!(
+            world.remove_body(1),
+            [TriggerEvent::Exit {
+                trigger: second,
+                body: 1
+            }]
+        );
+        assert!(world.remove_body(1).is_empty());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod chunks;
+pub mod collision;
+pub mod registry;
+pub mod spatial;
+pub mod tiled;
@@ -0,0 +1,136 @@
+use std::collections::{BTreeSet, HashMap};
+
+use log::warn;
+
+// what set_name does when another entity already has the name
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NamePolicy {
+    #[default]
+    Unique, // the second entity is refused
+    Suffix,     // the second entity becomes "name 2", then "name 3"
+    Duplicates, // both keep it, find_by_name returns the oldest
+}
+
+// names and tags for sprite ids, so scripts and scene files can refer to
+// entities by what they are instead of by id. the renderer drops removed sprites
+#[derive(Default)]
+pub struct Registry {
+    pub policy: NamePolicy,
+    names: HashMap<String, BTreeSet<usize>>,
+    name_of: HashMap<usize, String>,
+    tags: HashMap<String, BTreeSet<usize>>,
+    tags_of: HashMap<usize, BTreeSet<String>>,
+}
+
+impl Registry {
+    // returns the name the entity ended up with, None when the policy refused it
+    pub fn set_name(&mut self, id: usize, name: &str) -> Option<String> {
+        if self.name_of.get(&id).is_some_and(|current| current == name) {
+            return Some(name.to_string());
+        }
+
+        let name = match self.policy {
+            NamePolicy::Duplicates => name.to_string(),
+            _ if !self.is_taken(name) => name.to_string(),
+            NamePolicy::Unique => {
+                warn!("entity name {} is taken, not naming {}", name, id);
+                return None;
+            }
+            NamePolicy::Suffix => (2..)
+                .map(|n| format!("{} {}", name, n))
+                .find(|candidate| !self.is_taken(candidate))?,
+        };
+
+        self.clear_name(id);
+        self.names.entry(name.clone()).or_default().insert(id);
+        self.name_of.insert(id, name.clone());
+        Some(name)
+    }
+
+    pub fn clear_name(&mut self, id: usize) {
+        let Some(name) = self.name_of.remove(&id) else {
+            return;
+        };
+        if let Some(ids) = self.names.get_mut(&name) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.names.remove(&name);
+            }
+        }
+    }
+
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.name_of.get(&id).map(String::as_str)
+    }
+
+    pub fn is_taken(&self, name: &str) -> bool {
+        self.names.contains_key(name)
+    }
+
+    // the oldest entity with the name
+    pub fn find_by_name(&self, name: &str) -> Option<usize> {
+        self.names.get(name)?.first().copied()
+    }
+
+    pub fn find_all_by_name(&self, name: &str) -> Vec<usize> {
+        self.names
+            .get(name)
+            .map_or_else(Vec::new, |ids| ids.iter().copied().collect())
+    }
+
+    pub fn add_tag(&mut self, id: usize, tag: &str) {
+        self.tags.entry(tag.to_string()).or_default().insert(id);
+        self.tags_of.entry(id).or_default().insert(tag.to_string());
+    }
+
+    pub fn remove_tag(&mut self, id: usize, tag: &str) {
+        if let Some(ids) = self.tags.get_mut(tag) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.tags.remove(tag);
+            }
+        }
+        if let Some(tags) = self.tags_of.get_mut(&id) {
+            tags.remove(tag);
+            if tags.is_empty() {
+                self.tags_of.remove(&id);
+            }
+        }
+    }
+
+    pub fn has_tag(&self, id: usize, tag: &str) -> bool {
+        self.tags.get(tag).is_some_and(|ids| ids.contains(&id))
+    }
+
+    // oldest first
+    pub fn with_tag(&self, tag: &str) -> impl Iterator<Item = usize> + '_ {
+        self.tags.get(tag).into_iter().flatten().copied()
+    }
+
+    // entities carrying every one of the tags
+    pub fn with_tags<'s>(&'s self, tags: &'s [&str]) -> impl Iterator<Item = usize> + 's {
+        let (first, rest) = tags
+            .split_first()
+            .map_or((None, &[][..]), |(first, rest)| (Some(*first), rest));
+        first
+            .into_iter()
+            .flat_map(|tag| self.with_tag(tag))
+            .filter(move |id| rest.iter().all(|tag| self.has_tag(*id, tag)))
+    }
+
+    pub fn tags(&self, id: usize) -> impl Iterator<Item = &str> {
+        self.tags_of
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    // forgets the entity's name and tags
+    pub fn remove(&mut self, id: usize) {
+        self.clear_name(id);
+        for tag in self.tags_of.get(&id).cloned().unwrap_or_default() {
+            self.remove_tag(id, &tag);
+        }
+    }
+}
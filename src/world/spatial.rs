@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+
+const DEFAULT_CELL_SIZE: f32 = 128.0;
+
+type CellRange = [i32; 4]; // min x, min y, max x, max y, inclusive
+
+struct Entry {
+    bounds: [f32; 4],
+    cells: CellRange,
+}
+
+// uniform grid over entity bounds for culling, picking and physics broad phase.
+// ids are whatever the caller indexes, e.g. sprite ids. updates are incremental,
+// an entry only moves between cells when the cells it covers change
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<[i32; 2], Vec<usize>>,
+    entries: HashMap<usize, Entry>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    // cells around the size of a typical entity keep queries cheap
+    pub fn new(cell_size: f32) -> Self {
+        SpatialGrid {
+            cell_size: cell_size.max(1.0),
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn bounds(&self, id: usize) -> Option<[f32; 4]> {
+        self.entries.get(&id).map(|entry| entry.bounds)
+    }
+
+    // inserts or moves an entry, bounds are x, y, width, height
+    pub fn insert(&mut self, id: usize, bounds: [f32; 4]) {
+        let cells = self.cell_range(bounds);
+
+        if let Some(entry) = self.entries.get_mut(&id) {
+            entry.bounds = bounds;
+            if entry.cells == cells {
+                return;
+            }
+            let old = std::mem::replace(&mut entry.cells, cells);
+            self.unlink(id, old);
+        } else {
+            self.entries.insert(id, Entry { bounds, cells });
+        }
+
+        for cell in cells_in(cells) {
+            self.cells.entry(cell).or_default().push(id);
+        }
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        if let Some(entry) = self.entries.remove(&id) {
+            self.unlink(id, entry.cells);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.entries.clear();
+    }
+
+    // mirrors a set of entries, e.g. a sprite layer's bounds. entries missing
+    // from it are dropped
+    pub fn sync(&mut self, bounds: Vec<(usize, [f32; 4])>) {
+        let present: HashSet<usize> = bounds.iter().map(|(id, _)| *id).collect();
+
+        let removed: Vec<usize> = self
+            .entries
+            .keys()
+            .filter(|id| !present.contains(id))
+            .copied()
+            .collect();
+        for id in removed {
+            self.remove(id);
+        }
+
+        for (id, bounds) in bounds {
+            self.insert(id, bounds);
+        }
+    }
+
+    // entries overlapping the rect, in id order
+    pub fn query_rect(&self, rect: [f32; 4]) -> Vec<usize> {
+        let mut found: Vec<usize> = cells_in(self.cell_range(rect))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(|id| overlaps(self.entries[id].bounds, rect))
+            .collect();
+
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    // entries containing the point, e.g. for mouse picking
+    pub fn query_point(&self, point: [f32; 2]) -> Vec<usize> {
+        let cell = self.cell_at(point);
+        let Some(ids) = self.cells.get(&cell) else {
+            return Vec::new();
+        };
+
+        ids.iter()
+            .copied()
+            .filter(|id| distance_to(self.entries[id].bounds, point) == 0.0)
+            .collect()
+    }
+
+    // the entry whose bounds are closest, 0 distance when the point is inside one
+    pub fn nearest(&self, point: [f32; 2], max_distance: f32) -> Option<(usize, f32)> {
+        let center = self.cell_at(point);
+        let last_ring = self
+            .cells
+            .keys()
+            .map(|cell| (cell[0] - center[0]).abs().max((cell[1] - center[1]).abs()))
+            .max()?;
+
+        let mut best: Option<(usize, f32)> = None;
+        for ring in 0..=last_ring {
+            // anything in a further ring is at least this far away
+            let reach = (ring - 1).max(0) as f32 * self.cell_size;
+            if reach > max_distance || best.is_some_and(|(_, distance)| distance <= reach) {
+                break;
+            }
+
+            for cell in ring_cells(center, ring) {
+                for id in self.cells.get(&cell).into_iter().flatten() {
+                    let distance = distance_to(self.entries[id].bounds, point);
+                    let closer = best.is_none_or(|(best_id, best_distance)| {
+                        distance < best_distance || (distance == best_distance && *id < best_id)
+                    });
+                    if distance <= max_distance && closer {
+                        best = Some((*id, distance));
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    // overlapping entry pairs with the lower id first, for the physics broad phase
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = HashSet::new();
+
+        for ids in self.cells.values() {
+            for (i, a) in ids.iter().enumerate() {
+                for b in &ids[i + 1..] {
+                    if overlaps(self.entries[a].bounds, self.entries[b].bounds) {
+                        pairs.insert((*a.min(b), *a.max(b)));
+                    }
+                }
+            }
+        }
+
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    fn cell_at(&self, point: [f32; 2]) -> [i32; 2] {
+        [
+            (point[0] / self.cell_size).floor() as i32,
+            (point[1] / self.cell_size).floor() as i32,
+        ]
+    }
+
+    fn cell_range(&self, bounds: [f32; 4]) -> CellRange {
+        let [x, y, width, height] = bounds;
+        let min = self.cell_at([x, y]);
+        let max = self.cell_at([x + width.max(0.0), y + height.max(0.0)]);
+        [min[0], min[1], max[0], max[1]]
+    }
+
+    fn unlink(&mut self, id: usize, cells: CellRange) {
+        for cell in cells_in(cells) {
+            if let Some(ids) = self.cells.get_mut(&cell) {
+                ids.retain(|other| *other != id);
+                if ids.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+}
+
+fn cells_in(range: CellRange) -> impl Iterator<Item = [i32; 2]> {
+    let [min_x, min_y, max_x, max_y] = range;
+    (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| [x, y]))
+}
+
+// the cells at exactly `ring` steps from the center
+fn ring_cells(center: [i32; 2], ring: i32) -> impl Iterator<Item = [i32; 2]> {
+    cells_in([
+        center[0] - ring,
+        center[1] - ring,
+        center[0] + ring,
+        center[1] + ring,
+    ])
+    .filter(move |cell| (cell[0] - center[0]).abs().max((cell[1] - center[1]).abs()) == ring)
+}
+
+fn overlaps(a: [f32; 4], b: [f32; 4]) -> bool {
+    a[0] <= b[0] + b[2] && b[0] <= a[0] + a[2] && a[1] <= b[1] + b[3] && b[1] <= a[1] + a[3]
+}
+
+fn distance_to(bounds: [f32; 4], point: [f32; 2]) -> f32 {
+    let [x, y, width, height] = bounds;
+    let dx = (x - point[0]).max(point[0] - (x + width)).max(0.0);
+    let dy = (y - point[1]).max(point[1] - (y + height)).max(0.0);
+    (dx * dx + dy * dy).sqrt()
+}
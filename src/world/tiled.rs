@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, bail};
+use log::{debug, warn};
+use roxmltree::Node;
+
+use crate::assets::{pak, remote};
+use crate::world::collision::{Collider, ColliderKind, ColliderShape, CollisionWorld};
+
+const FLIP_FLAGS: u32 = 0xf000_0000; // high bits of a gid flip or rotate the tile
+const ELLIPSE_SEGMENTS: usize = 16;
+
+// the playable part of a tiled map: solids from collision layers and trigger
+// volumes from objects. tiles themselves are drawn however the game likes.
+// for games, see CollisionWorld
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default)]
+pub struct TiledMap {
+    pub name: String,
+    pub size: [u32; 2],      // in tiles
+    pub tile_size: [u32; 2], // in pixels
+    pub colliders: Vec<Collider>,
+}
+
+// offsets and flags inherited from enclosing group layers
+#[derive(Clone, Copy)]
+struct LayerScope {
+    offset: [f32; 2],
+    collision: bool,
+}
+
+#[allow(dead_code)]
+impl TiledMap {
+    // a .tmx file saved with csv layer data, relative to the asset directory or in the pak.
+    // a layer is a collision layer when it is called collision or has a true
+    // `collision` property. objects of class trigger, or with a true `trigger`
+    // property, become triggers wherever they are. remote maps block while they
+    // download, open those with remote::spawn_open
+    pub fn open(map_name: &str) -> anyhow::Result<Self> {
+        let bytes = match pak::mounted().and_then(|pak| pak.read(map_name)) {
+            Some(bytes) => Some(bytes),
+            None => remote::read(map_name).transpose()?,
+        };
+        let xml = match bytes {
+            Some(bytes) => String::from_utf8(bytes).context("map isn't utf-8")?,
+            None => {
+                let file = format!("assets/{}", map_name);
+                debug!("loading map at {}", file);
+                std::fs::read_to_string(&file)
+                    .with_context(|| format!("failed to read {}", file))?
+            }
+        };
+        Self::parse(map_name, &xml).with_context(|| format!("failed to load map {}", map_name))
+    }
+
+    pub fn parse(name: &str, xml: &str) -> anyhow::Result<Self> {
+        let document = roxmltree::Document::parse(xml)?;
+        let root = document.root_element();
+        if !root.has_tag_name("map") {
+            bail!("not a tiled map");
+        }
+        if let Some(orientation) = root.attribute("orientation")
+            && orientation != "orthogonal"
+        {
+            bail!("{} maps aren't supported, only orthogonal", orientation);
+        }
+
+        let mut map = TiledMap {
+            name: name.to_string(),
+            size: [number(root, "width"), number(root, "height")].map(|n| n as u32),
+            tile_size: [number(root, "tilewidth"), number(root, "tileheight")].map(|n| n as u32),
+            colliders: Vec::new(),
+        };
+        if map.tile_size.contains(&0) {
+            bail!("map has no tile size");
+        }
+
+        let scope = LayerScope {
+            offset: [0.0, 0.0],
+            collision: false,
+        };
+        map.read_layers(root, scope)?;
+        debug!("map {} has {} colliders", name, map.colliders.len());
+        Ok(map)
+    }
+
+    pub fn pixel_size(&self) -> [f32; 2] {
+        [0, 1].map(|axis| (self.size[axis] * self.tile_size[axis]) as f32)
+    }
+
+    // adds every collider with the map's top left at origin, returns their ids
+    pub fn add_to(&self, world: &mut CollisionWorld, origin: [f32; 2]) -> Vec<usize> {
+        self.colliders
+            .iter()
+            .map(|collider| {
+                let shape = match &collider.shape {
+                    ColliderShape::Rect([x, y, width, height]) => {
+                        ColliderShape::Rect([x + origin[0], y + origin[1], *width, *height])
+                    }
+                    ColliderShape::Polygon(points) => ColliderShape::Polygon(
+                        points
+                            .iter()
+                            .map(|point| [point[0] + origin[0], point[1] + origin[1]])
+                            .collect(),
+                    ),
+                };
+                world.add_collider(Collider {
+                    shape,
+                    ..collider.clone()
+                })
+            })
+            .collect()
+    }
+
+    fn read_layers(&mut self, parent: Node, scope: LayerScope) -> anyhow::Result<()> {
+        for layer in parent.children().filter(Node::is_element) {
+            let properties = properties(layer);
+            let layer_name = layer.attribute("name").unwrap_or_default();
+            let scope = LayerScope {
+                offset: [
+                    scope.offset[0] + number(layer, "offsetx"),
+                    scope.offset[1] + number(layer, "offsety"),
+                ],
+                collision: scope.collision
+                    || layer_name.eq_ignore_ascii_case("collision")
+                    || is_true(&properties, "collision"),
+            };
+
+            match layer.tag_name().name() {
+                "group" => self.read_layers(layer, scope)?,
+                "layer" if scope.collision => self.read_tiles(layer, scope)?,
+                "objectgroup" => self.read_objects(layer, scope),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // solid tiles merged into as few rects as possible, runs across then down
+    fn read_tiles(&mut self, layer: Node, scope: LayerScope) -> anyhow::Result<()> {
+        let Some(data) = layer.children().find(|node| node.has_tag_name("data")) else {
+            return Ok(());
+        };
+        if data.attribute("encoding") != Some("csv") {
+            bail!(
+                "layer {} isn't csv, set the map's tile layer format to csv",
+                layer.attribute("name").unwrap_or_default()
+            );
+        }
+
+        // infinite maps split their layers into chunks
+        let chunks: Vec<Node> = data
+            .children()
+            .filter(|node| node.has_tag_name("chunk"))
+            .collect();
+        let chunks = match chunks.is_empty() {
+            true => vec![(data, [0, 0], number(layer, "width") as i32)],
+            false => chunks
+                .into_iter()
+                .map(|chunk| {
+                    let origin = [number(chunk, "x"), number(chunk, "y")].map(|n| n as i32);
+                    (chunk, origin, number(chunk, "width") as i32)
+                })
+                .collect(),
+        };
+
+        let mut solid = Vec::new();
+        for (node, origin, width) in chunks {
+            let gids = node
+                .text()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|gid| !gid.is_empty());
+            for (index, gid) in gids.enumerate() {
+                let gid: u32 = gid.parse().context("bad tile in layer data")?;
+                if gid & !FLIP_FLAGS != 0 && width > 0 {
+                    let index = index as i32;
+                    solid.push([origin[0] + index % width, origin[1] + index / width]);
+                }
+            }
+        }
+
+        let [tile_width, tile_height] = self.tile_size.map(|size| size as f32);
+        for [x, y, width, height] in merge_tiles(solid) {
+            let rect = [
+                scope.offset[0] + x as f32 * tile_width,
+                scope.offset[1] + y as f32 * tile_height,
+                width as f32 * tile_width,
+                height as f32 * tile_height,
+            ];
+            self.colliders.push(Collider::new(
+                ColliderShape::Rect(rect),
+                ColliderKind::Solid,
+            ));
+        }
+        Ok(())
+    }
+
+    fn read_objects(&mut self, layer: Node, scope: LayerScope) {
+        for object in layer.children().filter(|node| node.has_tag_name("object")) {
+            let properties = properties(object);
+            let class = object
+                .attribute("class")
+                .or_else(|| object.attribute("type"))
+                .unwrap_or_default();
+            let kind =
+                match class.eq_ignore_ascii_case("trigger") || is_true(&properties, "trigger") {
+                    true => ColliderKind::Trigger,
+                    false if scope.collision => ColliderKind::Solid,
+                    false => continue, // spawn points and the like, for the game to read
+                };
+
+            let name = object.attribute("name").unwrap_or_default();
+            let Some(shape) = object_shape(object, scope.offset) else {
+                warn!(
+                    "object {:?} in {} has no area, skipping it",
+                    name, self.name
+                );
+                continue;
+            };
+            self.colliders.push(Collider {
+                shape,
+                kind,
+                name: name.to_string(),
+                class: class.to_string(),
+                properties,
+            });
+        }
+    }
+}
+
+// None for points and polylines, which can't be walked into
+fn object_shape(object: Node, offset: [f32; 2]) -> Option<ColliderShape> {
+    let [mut x, mut y] = [number(object, "x"), number(object, "y")];
+    let [width, height] = [number(object, "width"), number(object, "height")];
+    // tile objects hang up from their bottom left corner
+    if object.attribute("gid").is_some() {
+        y -= height;
+    }
+    x += offset[0];
+    y += offset[1];
+    let rotation = number(object, "rotation").to_radians();
+
+    let child = |tag| object.children().find(|node| node.has_tag_name(tag));
+    let local: Vec<[f32; 2]> = if let Some(polygon) = child("polygon") {
+        polygon
+            .attribute("points")?
+            .split_whitespace()
+            .filter_map(|pair| {
+                let (px, py) = pair.split_once(',')?;
+                Some([px.parse().ok()?, py.parse().ok()?])
+            })
+            .collect()
+    } else if child("ellipse").is_some() {
+        (0..ELLIPSE_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * std::f32::consts::TAU;
+                [
+                    width * 0.5 * (1.0 + angle.cos()),
+                    height * 0.5 * (1.0 + angle.sin()),
+                ]
+            })
+            .collect()
+    } else if child("point").is_some() || child("polyline").is_some() {
+        return None;
+    } else if rotation == 0.0 {
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        return Some(ColliderShape::Rect([x, y, width, height]));
+    } else {
+        vec![[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]]
+    };
+    if local.len() < 3 {
+        return None;
+    }
+
+    // tiled rotates clockwise around the object's position
+    let (sin, cos) = rotation.sin_cos();
+    Some(ColliderShape::Polygon(
+        local
+            .into_iter()
+            .map(|[px, py]| [x + px * cos - py * sin, y + px * sin + py * cos])
+            .collect(),
+    ))
+}
+
+// tile coordinates to x, y, width, height rects in tiles
+fn merge_tiles(mut tiles: Vec<[i32; 2]>) -> Vec<[i32; 4]> {
+    tiles.sort_by_key(|&[x, y]| (y, x));
+    tiles.dedup();
+
+    let mut runs: Vec<[i32; 4]> = Vec::new();
+    for [x, y] in tiles {
+        match runs.last_mut() {
+            Some(run) if run[1] == y && run[0] + run[2] == x => run[2] += 1,
+            _ => runs.push([x, y, 1, 1]),
+        }
+    }
+
+    // a run continues the rect above it when it spans the same columns
+    let mut rects: Vec<[i32; 4]> = Vec::new();
+    let mut open: HashMap<[i32; 2], usize> = HashMap::new(); // x, width -> rect
+    for [x, y, width, _] in runs {
+        match open.get(&[x, width]) {
+            Some(&index) if rects[index][1] + rects[index][3] == y => rects[index][3] += 1,
+            _ => {
+                open.insert([x, width], rects.len());
+                rects.push([x, y, width, 1]);
+            }
+        }
+    }
+    rects
+}
+
+fn number(node: Node, attribute: &str) -> f32 {
+    node.attribute(attribute)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0.0)
+}
+
+fn properties(node: Node) -> HashMap<String, String> {
+    node.children()
+        .filter(|child| child.has_tag_name("properties"))
+        .flat_map(|properties| properties.children())
+        .filter(|property| property.has_tag_name("property"))
+        .filter_map(|property| {
+            let name = property.attribute("name")?;
+            // multiline strings are the element's text instead of a value
+            let value = property.attribute("value").or_else(|| property.text())?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn is_true(properties: &HashMap<String, String>, name: &str) -> bool {
+    properties.get(name).is_some_and(|value| value == "true")
+}